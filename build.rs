@@ -1,6 +1,31 @@
 use std::env;
+use std::path::Path;
+use std::process::Command;
 
 fn main() {
+    // `bench --history` records which build produced each run.  Outside a
+    // git checkout (e.g. a crates.io tarball) only the crate version is used.
+    if let Some(describe) = git(&["describe", "--tags", "--always", "--dirty"]) {
+        println!("cargo:rustc-env=GIT_DESCRIBE={describe}");
+    }
+    // Describe again when a commit, checkout, tag or `git add` moves what
+    // it reports; otherwise incremental builds keep the old value.
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=asm/chkstk_darwin_stub.s");
+    if let Some(git_dir) = git(&["rev-parse", "--git-dir"]) {
+        let git_dir = Path::new(&git_dir);
+        let mut watched = vec![git_dir.join("HEAD"), git_dir.join("index")];
+        watched.push(git_dir.join("refs/tags"));
+        if let Some(head_ref) = git(&["symbolic-ref", "-q", "HEAD"]) {
+            watched.push(git_dir.join(head_ref));
+        }
+        // Refs move here on `git gc` / `git pack-refs`.
+        watched.push(git_dir.join("packed-refs"));
+        for path in watched.iter().filter(|path| path.exists()) {
+            println!("cargo:rerun-if-changed={}", path.display());
+        }
+    }
+
     let target_os = env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
 
     if target_os == "ios" {
@@ -12,3 +37,14 @@ fn main() {
             .compile("chkstk_darwin_stub");
     }
 }
+
+/// Trimmed stdout of `git args`, or `None` if git fails or prints nothing.
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let text = String::from_utf8(output.stdout).ok()?;
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
@@ -0,0 +1,207 @@
+use anyhow::{Context, Result};
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// ── Constants ────────────────────────────────────────────────────────────────
+
+/// Default free-space floor for batch runs.  A single FLUX image is only a
+/// few MB, but model weights and the HF cache usually live on the same volume,
+/// so stop well before the disk is actually full.
+pub const DEFAULT_MIN_FREE_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+
+// ── Settings ─────────────────────────────────────────────────────────────────
+
+static MIN_FREE_BYTES: AtomicU64 = AtomicU64::new(DEFAULT_MIN_FREE_BYTES);
+
+/// Set the free-space floor for the rest of the process (`--min-free`).
+pub fn set_min_free_bytes(bytes: u64) {
+    MIN_FREE_BYTES.store(bytes, Ordering::Relaxed);
+}
+
+/// The free-space floor below which batch runs stop; 0 means no floor.
+pub fn min_free_bytes() -> u64 {
+    MIN_FREE_BYTES.load(Ordering::Relaxed)
+}
+
+/// `--min-free`: a non-negative number of GB (binary, as [`fmt_bytes`]
+/// prints them), e.g. `2` or `0.5`.
+pub fn parse_min_free(arg: &str) -> Result<u64, String> {
+    match arg.trim().parse::<f64>() {
+        Ok(gb) if gb.is_finite() && gb >= 0.0 => Ok((gb * 1024.0 * 1024.0 * 1024.0) as u64),
+        _ => Err(format!(
+            "expected a number of GB (e.g. 2 or 0.5), got {arg:?}"
+        )),
+    }
+}
+
+// ── Space probing ────────────────────────────────────────────────────────────
+
+/// Returns the number of bytes available to the current user on the volume
+/// containing the given path.
+///
+/// [`disk_space::preflight_with`](crate::disk_space::preflight_with) takes
+/// this as a plain function pointer so another probe can stand in.
+pub type SpaceProbe = fn(&Path) -> io::Result<u64>;
+
+/// The real [`SpaceProbe`], backed by `statvfs` / `GetDiskFreeSpaceExW`.
+///
+/// The output directory may not exist yet (it is created lazily on first
+/// write), so the probe walks up to the nearest existing ancestor.
+pub fn system_free_space(path: &Path) -> io::Result<u64> {
+    let mut probe_path = path;
+    while !probe_path.exists() {
+        match probe_path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => probe_path = parent,
+            _ => {
+                probe_path = Path::new(".");
+                break;
+            }
+        }
+    }
+    fs2::available_space(probe_path)
+}
+
+/// Outcome of a free-space check performed before writing the next artifact.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpaceCheck {
+    /// Enough room to keep going.
+    Ok { free_bytes: u64 },
+    /// Free space dropped below the configured floor — the batch should stop.
+    Low {
+        free_bytes: u64,
+        min_free_bytes: u64,
+    },
+}
+
+impl SpaceCheck {
+    pub fn is_low(self) -> bool {
+        matches!(self, Self::Low { .. })
+    }
+}
+
+/// `only 1.2 GB free, below the --min-free floor of 2.0 GB`.
+impl fmt::Display for SpaceCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Ok { free_bytes } => write!(f, "{} free", fmt_bytes(free_bytes)),
+            Self::Low {
+                free_bytes,
+                min_free_bytes,
+            } => write!(
+                f,
+                "only {} free, below the --min-free floor of {}",
+                fmt_bytes(free_bytes),
+                fmt_bytes(min_free_bytes)
+            ),
+        }
+    }
+}
+
+// ── ArtifactLedger ───────────────────────────────────────────────────────────
+
+/// Running account of everything a batch run has written to disk.
+///
+/// Each batch mode owns one ledger: call [`check_space`](Self::check_space)
+/// before producing the next item and [`record`](Self::record) after the file
+/// lands, then print [`summary`](Self::summary) at the end.
+#[derive(Debug)]
+pub struct ArtifactLedger {
+    output_dir: PathBuf,
+    min_free_bytes: u64,
+    count: usize,
+    total_bytes: u64,
+}
+
+impl ArtifactLedger {
+    /// Create a ledger for artifacts written under `output_dir`, stopping
+    /// at the [`--min-free`](min_free_bytes) floor.
+    pub fn new(output_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            output_dir: output_dir.into(),
+            min_free_bytes: min_free_bytes(),
+            count: 0,
+            total_bytes: 0,
+        }
+    }
+
+    /// Check the remaining space on the output volume against the floor.
+    ///
+    /// A floor of 0 turns the check off, without probing.
+    pub fn check_space(&self) -> Result<SpaceCheck> {
+        if self.min_free_bytes == 0 {
+            return Ok(SpaceCheck::Ok {
+                free_bytes: u64::MAX,
+            });
+        }
+        let free_bytes = system_free_space(&self.output_dir).with_context(|| {
+            format!(
+                "Failed to query free disk space for {}",
+                self.output_dir.display()
+            )
+        })?;
+
+        if free_bytes < self.min_free_bytes {
+            Ok(SpaceCheck::Low {
+                free_bytes,
+                min_free_bytes: self.min_free_bytes,
+            })
+        } else {
+            Ok(SpaceCheck::Ok { free_bytes })
+        }
+    }
+
+    /// Record a file that was just written and return its size in bytes.
+    pub fn record(&mut self, path: &Path) -> Result<u64> {
+        let bytes = std::fs::metadata(path)
+            .with_context(|| format!("Failed to stat artifact: {}", path.display()))?
+            .len();
+        self.count += 1;
+        self.total_bytes += bytes;
+        Ok(bytes)
+    }
+
+    /// Cumulative size of all recorded artifacts.
+    pub fn total_bytes(&self) -> u64 {
+        self.total_bytes
+    }
+
+    /// One-line summary, e.g. `Wrote 37 artifacts, 12.4 GB, 41.0 GB free remaining`.
+    ///
+    /// Free space is probed fresh; if the probe fails the free-space part is
+    /// omitted rather than failing the whole run at the very end.
+    pub fn summary(&self) -> String {
+        let noun = if self.count == 1 {
+            "artifact"
+        } else {
+            "artifacts"
+        };
+        let mut line = format!(
+            "Wrote {} {noun}, {}",
+            self.count,
+            fmt_bytes(self.total_bytes)
+        );
+        if let Ok(free) = system_free_space(&self.output_dir) {
+            line.push_str(&format!(", {} free remaining", fmt_bytes(free)));
+        }
+        line
+    }
+}
+
+// ── Helpers ──────────────────────────────────────────────────────────────────
+
+/// Format a byte count with a binary-scaled unit (e.g. "12.4 GB", "830.0 KB").
+pub fn fmt_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
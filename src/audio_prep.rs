@@ -0,0 +1,609 @@
+use anyhow::{Context, Result};
+use mistralrs::AudioInput;
+use std::f64::consts::PI;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+// ── Constants ────────────────────────────────────────────────────────────────
+
+/// Sample rate Gemma 3n's audio encoder expects.
+pub const TARGET_SAMPLE_RATE: u32 = 16_000;
+
+/// Default level below which leading / trailing audio counts as silence.
+/// Low enough to keep breaths and quiet onsets in separated vocal stems.
+pub const DEFAULT_SILENCE_THRESHOLD_DBFS: f32 = -50.0;
+
+/// Frame length used to measure loudness when trimming.
+const FRAME_MS: u32 = 10;
+
+/// Audio kept on each side of the detected non-silent region so soft
+/// onsets and decays aren't clipped.
+const TRIM_PADDING_MS: u32 = 100;
+
+/// Half-width of the resampling filter, in input-rate zero crossings.
+const RESAMPLE_HALF_TAPS: usize = 16;
+
+/// Default `--normalize` targets: speech level for RMS, just under full
+/// scale for peak.
+pub const DEFAULT_NORMALIZE_RMS_DBFS: f32 = -20.0;
+pub const DEFAULT_NORMALIZE_PEAK_DBFS: f32 = -1.0;
+
+/// Audio this close to the normalization target is left as it is.
+const NORMALIZE_TOLERANCE_DB: f32 = 1.0;
+
+/// Most gain normalization applies, so a stem that is nothing but faint
+/// bleed isn't blown up into noise.
+const MAX_NORMALIZE_GAIN_DB: f32 = 40.0;
+
+/// Level above which the limiter starts bending samples back towards full
+/// scale.
+const LIMITER_KNEE: f32 = 0.9;
+
+// ── Options ──────────────────────────────────────────────────────────────────
+
+/// Preprocessing applied before audio is sent to the model.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PrepOptions {
+    /// Trim leading / trailing audio quieter than this (dBFS).  `None`
+    /// disables trimming.
+    pub silence_threshold_dbfs: Option<f32>,
+    /// Bring the level to a target (`--normalize`); `None` leaves it.
+    pub normalize: Option<Normalization>,
+}
+
+impl Default for PrepOptions {
+    fn default() -> Self {
+        Self {
+            silence_threshold_dbfs: Some(DEFAULT_SILENCE_THRESHOLD_DBFS),
+            normalize: None,
+        }
+    }
+}
+
+/// What `--normalize` measures.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum NormalizeMode {
+    /// Average level of the audible frames, so a few loud transients don't
+    /// keep a quiet stem quiet.
+    #[default]
+    Rms,
+    /// Loudest sample.
+    Peak,
+}
+
+impl NormalizeMode {
+    pub fn default_target_dbfs(self) -> f32 {
+        match self {
+            Self::Rms => DEFAULT_NORMALIZE_RMS_DBFS,
+            Self::Peak => DEFAULT_NORMALIZE_PEAK_DBFS,
+        }
+    }
+}
+
+/// Loudness normalization: gain `samples` so their [`mode`](Self::mode)
+/// level lands on `target_dbfs`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Normalization {
+    pub mode: NormalizeMode,
+    pub target_dbfs: f32,
+}
+
+impl Normalization {
+    /// RMS normalization to `target_dbfs`.
+    pub fn rms(target_dbfs: f32) -> Self {
+        Self {
+            mode: NormalizeMode::Rms,
+            target_dbfs,
+        }
+    }
+}
+
+/// Mix down to mono, resample to [`TARGET_SAMPLE_RATE`], trim silence and
+/// normalize, returning the gain normalization applied in dB.
+///
+/// An all-silent input comes back with no samples.
+pub fn preprocess(audio: AudioInput, opts: &PrepOptions) -> (AudioInput, Option<f32>) {
+    let mono = downmix_to_mono(&audio.samples, audio.channels);
+    let mut samples = resample(&mono, audio.sample_rate, TARGET_SAMPLE_RATE);
+    if let Some(threshold) = opts.silence_threshold_dbfs {
+        let keep = non_silent_range(&samples, TARGET_SAMPLE_RATE, threshold);
+        samples.truncate(keep.end);
+        samples.drain(..keep.start);
+    }
+    let gain_db = opts
+        .normalize
+        .and_then(|norm| normalize(&mut samples, TARGET_SAMPLE_RATE, norm));
+    let audio = AudioInput {
+        samples,
+        sample_rate: TARGET_SAMPLE_RATE,
+        channels: 1,
+    };
+    (audio, gain_db)
+}
+
+/// Duration of interleaved `samples` in seconds.
+pub fn duration_secs(samples: &[f32], sample_rate: u32, channels: u16) -> f64 {
+    if sample_rate == 0 || channels == 0 {
+        return 0.0;
+    }
+    samples.len() as f64 / (sample_rate as f64 * channels as f64)
+}
+
+/// `secs` seconds of mono digital silence at `sample_rate`, e.g. for a
+/// warm-up request that shouldn't depend on real audio.
+pub fn silence(secs: f64, sample_rate: u32) -> AudioInput {
+    let frames = (secs.max(0.0) * sample_rate as f64).round() as usize;
+    AudioInput {
+        samples: vec![0.0; frames],
+        sample_rate,
+        channels: 1,
+    }
+}
+
+// ── Raw PCM ──────────────────────────────────────────────────────────────────
+
+/// Sample encoding of headerless PCM (`--raw-pcm --format`).  The names
+/// match ffmpeg's `-f` muxers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum PcmFormat {
+    /// 32-bit little-endian float.
+    #[default]
+    F32le,
+    /// 16-bit little-endian signed integer.
+    S16le,
+}
+
+impl PcmFormat {
+    fn name(self) -> &'static str {
+        match self {
+            Self::F32le => "f32le",
+            Self::S16le => "s16le",
+        }
+    }
+
+    fn sample_bytes(self) -> usize {
+        match self {
+            Self::F32le => 4,
+            Self::S16le => 2,
+        }
+    }
+}
+
+/// Layout of headerless interleaved PCM, which symphonia can't probe.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RawPcm {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub format: PcmFormat,
+}
+
+impl RawPcm {
+    /// Decode `bytes` into interleaved `f32` samples.
+    ///
+    /// Fails when the byte count doesn't hold a whole number of frames,
+    /// which usually means the wrong `--format` or `--channels`.
+    pub fn decode(&self, bytes: &[u8]) -> Result<AudioInput> {
+        if self.channels == 0 {
+            anyhow::bail!("--channels must be at least 1");
+        }
+        if self.sample_rate == 0 {
+            anyhow::bail!("--sample-rate must be greater than 0");
+        }
+        let sample_bytes = self.format.sample_bytes();
+        if !bytes.len().is_multiple_of(sample_bytes) {
+            anyhow::bail!(
+                "Raw PCM input is {} bytes, not a multiple of the {sample_bytes}-byte {} sample size; \
+                 check --format",
+                bytes.len(),
+                self.format.name(),
+            );
+        }
+        let frame_bytes = sample_bytes * self.channels as usize;
+        if !bytes.len().is_multiple_of(frame_bytes) {
+            anyhow::bail!(
+                "Raw PCM input is {} bytes, not a whole number of {}-channel frames ({frame_bytes} bytes each); \
+                 check --channels",
+                bytes.len(),
+                self.channels,
+            );
+        }
+        let samples = match self.format {
+            PcmFormat::F32le => bytes
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect(),
+            PcmFormat::S16le => bytes
+                .chunks_exact(2)
+                .map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32_768.0)
+                .collect(),
+        };
+        Ok(AudioInput {
+            samples,
+            sample_rate: self.sample_rate,
+            channels: self.channels,
+        })
+    }
+}
+
+// ── WAV dump ─────────────────────────────────────────────────────────────────
+
+/// Where `transcribe --dump-audio` writes the samples sent to the model.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AudioDump {
+    /// The file for a single request; chunks go next to it (see
+    /// [`chunk_path`](Self::chunk_path)).
+    pub path: PathBuf,
+    /// `f32le` writes a 32-bit float WAV with the samples exactly as sent;
+    /// `s16le` a 16-bit one that more tools can open.
+    pub format: PcmFormat,
+}
+
+impl AudioDump {
+    /// Path of chunk `index` (1-based) of a chunked request:
+    /// `dump.wav` → `dump_003.wav`.
+    pub fn chunk_path(&self, index: usize) -> PathBuf {
+        let stem = self
+            .path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "audio".to_string());
+        self.path.with_file_name(format!("{stem}_{index:03}.wav"))
+    }
+
+    /// Write `audio` to [`path`](Self::path) and return it.
+    pub fn write(&self, audio: &AudioInput) -> Result<PathBuf> {
+        write_wav(&self.path, audio, self.format)?;
+        Ok(self.path.clone())
+    }
+
+    /// Write `audio` to [`chunk_path`](Self::chunk_path) and return it.
+    pub fn write_chunk(&self, index: usize, audio: &AudioInput) -> Result<PathBuf> {
+        let path = self.chunk_path(index);
+        write_wav(&path, audio, self.format)?;
+        Ok(path)
+    }
+}
+
+/// Write `audio` to `path` as a WAV file in `format`, creating the parent
+/// directory if needed.
+///
+/// Float samples are written as-is; 16-bit ones are clamped to the `i16`
+/// range, the inverse of [`RawPcm::decode`].
+pub fn write_wav(path: &Path, audio: &AudioInput, format: PcmFormat) -> Result<()> {
+    if audio.channels == 0 || audio.sample_rate == 0 {
+        anyhow::bail!(
+            "Can't write {}: the audio has {} channels at {} Hz",
+            path.display(),
+            audio.channels,
+            audio.sample_rate
+        );
+    }
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+    }
+    let spec = hound::WavSpec {
+        channels: audio.channels,
+        sample_rate: audio.sample_rate,
+        bits_per_sample: (format.sample_bytes() * 8) as u16,
+        sample_format: match format {
+            PcmFormat::F32le => hound::SampleFormat::Float,
+            PcmFormat::S16le => hound::SampleFormat::Int,
+        },
+    };
+    let write = || -> Result<(), hound::Error> {
+        let mut writer = hound::WavWriter::create(path, spec)?;
+        for &sample in &audio.samples {
+            match format {
+                PcmFormat::F32le => writer.write_sample(sample)?,
+                PcmFormat::S16le => writer.write_sample(
+                    (sample * 32_768.0)
+                        .round()
+                        .clamp(i16::MIN as f32, i16::MAX as f32) as i16,
+                )?,
+            }
+        }
+        writer.finalize()
+    };
+    write().with_context(|| format!("Failed to write WAV file: {}", path.display()))
+}
+
+// ── DSP ──────────────────────────────────────────────────────────────────────
+
+/// Average interleaved multi-channel samples into a single channel.
+///
+/// Mono input is returned unchanged.
+pub fn downmix_to_mono(samples: &[f32], channels: u16) -> Vec<f32> {
+    let channels = usize::from(channels.max(1));
+    if channels == 1 {
+        return samples.to_vec();
+    }
+    samples
+        .chunks_exact(channels)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect()
+}
+
+/// Resample mono `samples` from `from_rate` to `to_rate` with a
+/// Hann-windowed sinc filter.
+///
+/// When downsampling, the filter cutoff is lowered to the new Nyquist
+/// frequency so content above it doesn't alias.
+pub fn resample(samples: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
+    if from_rate == to_rate || samples.is_empty() || from_rate == 0 || to_rate == 0 {
+        return samples.to_vec();
+    }
+
+    let ratio = from_rate as f64 / to_rate as f64;
+    // Cutoff relative to the input Nyquist frequency.
+    let cutoff = (1.0 / ratio).min(1.0);
+    let half_width = RESAMPLE_HALF_TAPS as f64 / cutoff;
+    let out_len = (samples.len() as f64 / ratio).round() as usize;
+
+    (0..out_len)
+        .map(|i| {
+            let center = i as f64 * ratio;
+            let first = (center - half_width).ceil().max(0.0) as usize;
+            let last = ((center + half_width).floor() as usize).min(samples.len() - 1);
+
+            let mut acc = 0.0;
+            let mut weight_sum = 0.0;
+            for (j, &sample) in samples.iter().enumerate().take(last + 1).skip(first) {
+                let x = j as f64 - center;
+                let window = 0.5 * (1.0 + (PI * x / half_width).cos());
+                let weight = cutoff * sinc(cutoff * x) * window;
+                acc += sample as f64 * weight;
+                weight_sum += weight;
+            }
+            // Normalizing keeps DC gain at 1, including near the edges where
+            // the filter is cut short.
+            if weight_sum.abs() > f64::EPSILON {
+                (acc / weight_sum) as f32
+            } else {
+                0.0
+            }
+        })
+        .collect()
+}
+
+/// Resample interleaved `samples` with `channels` channels, one channel at
+/// a time with [`resample`].
+pub fn resample_interleaved(
+    samples: &[f32],
+    channels: u16,
+    from_rate: u32,
+    to_rate: u32,
+) -> Vec<f32> {
+    let channels = usize::from(channels.max(1));
+    if channels == 1 {
+        return resample(samples, from_rate, to_rate);
+    }
+    let resampled: Vec<Vec<f32>> = (0..channels)
+        .map(|ch| {
+            let channel: Vec<f32> = samples.iter().skip(ch).step_by(channels).copied().collect();
+            resample(&channel, from_rate, to_rate)
+        })
+        .collect();
+    let frames = resampled.iter().map(Vec::len).min().unwrap_or(0);
+    (0..frames)
+        .flat_map(|frame| resampled.iter().map(move |channel| channel[frame]))
+        .collect()
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-9 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+/// Range of mono `samples` between the first and last frames louder than
+/// `threshold_dbfs`, padded by [`TRIM_PADDING_MS`] on each side.
+///
+/// Returns an empty range when every frame is below the threshold.
+pub fn non_silent_range(samples: &[f32], sample_rate: u32, threshold_dbfs: f32) -> Range<usize> {
+    let frame_len = ((sample_rate * FRAME_MS / 1000) as usize).max(1);
+    let loud = |frame: &[f32]| rms_dbfs(frame) >= threshold_dbfs;
+
+    let frames: Vec<&[f32]> = samples.chunks(frame_len).collect();
+    let Some(first) = frames.iter().position(|frame| loud(frame)) else {
+        return 0..0;
+    };
+    let last = frames
+        .iter()
+        .rposition(|frame| loud(frame))
+        .unwrap_or(first);
+
+    let padding = (sample_rate * TRIM_PADDING_MS / 1000) as usize;
+    let start = (first * frame_len).saturating_sub(padding);
+    let end = ((last + 1) * frame_len + padding).min(samples.len());
+    start..end
+}
+
+/// Root-mean-square level of `samples` in dBFS, where a full-scale square
+/// wave is 0 dBFS.  Digital silence is `-inf`.
+pub fn rms_dbfs(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return f32::NEG_INFINITY;
+    }
+    let mean_square = samples
+        .iter()
+        .map(|&s| (s as f64) * (s as f64))
+        .sum::<f64>()
+        / samples.len() as f64;
+    (10.0 * mean_square.log10()) as f32
+}
+
+// ── Normalization ────────────────────────────────────────────────────────────
+
+/// Level of mono `samples` as `mode` measures it, in dBFS.  RMS is taken
+/// over the frames louder than [`DEFAULT_SILENCE_THRESHOLD_DBFS`] only, so
+/// the pauses in a vocal stem don't drag it down.  Digital silence (or
+/// nothing above that floor) is `None`.
+pub fn level_dbfs(samples: &[f32], sample_rate: u32, mode: NormalizeMode) -> Option<f32> {
+    let level = match mode {
+        NormalizeMode::Peak => {
+            let peak = samples.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+            20.0 * peak.log10()
+        }
+        NormalizeMode::Rms => {
+            let frame_len = ((sample_rate * FRAME_MS / 1000) as usize).max(1);
+            let audible: Vec<f32> = samples
+                .chunks(frame_len)
+                .filter(|frame| rms_dbfs(frame) >= DEFAULT_SILENCE_THRESHOLD_DBFS)
+                .flatten()
+                .copied()
+                .collect();
+            rms_dbfs(&audible)
+        }
+    };
+    level.is_finite().then_some(level)
+}
+
+/// Gain in dB that brings a `level_dbfs` signal to `target_dbfs`, capped at
+/// [`MAX_NORMALIZE_GAIN_DB`].  `None` when it's already within
+/// [`NORMALIZE_TOLERANCE_DB`] of the target or silent.
+pub fn normalization_gain_db(level_dbfs: Option<f32>, target_dbfs: f32) -> Option<f32> {
+    let gain = target_dbfs - level_dbfs?;
+    (gain.abs() > NORMALIZE_TOLERANCE_DB).then_some(gain.min(MAX_NORMALIZE_GAIN_DB))
+}
+
+/// Soft limiter: samples up to [`LIMITER_KNEE`] pass unchanged, louder ones
+/// are bent towards full scale and never past it, so a gain that pushes
+/// transients over 0 dBFS doesn't clip them.
+pub fn limit(sample: f32) -> f32 {
+    let magnitude = sample.abs();
+    if magnitude <= LIMITER_KNEE {
+        return sample;
+    }
+    let headroom = 1.0 - LIMITER_KNEE;
+    let bent = LIMITER_KNEE + headroom * ((magnitude - LIMITER_KNEE) / headroom).tanh();
+    bent.copysign(sample)
+}
+
+/// Multiply `samples` by `gain_db`, through [`limit`].
+pub fn apply_gain(samples: &mut [f32], gain_db: f32) {
+    let factor = 10f32.powf(gain_db / 20.0);
+    for sample in samples {
+        *sample = limit(*sample * factor);
+    }
+}
+
+/// Normalize mono `samples` in place as `norm` says, returning the gain
+/// applied in dB; `None` when they were left alone.
+pub fn normalize(samples: &mut [f32], sample_rate: u32, norm: Normalization) -> Option<f32> {
+    let level = level_dbfs(samples, sample_rate, norm.mode);
+    let gain_db = normalization_gain_db(level, norm.target_dbfs)?;
+    apply_gain(samples, gain_db);
+    Some(gain_db)
+}
+
+// ── Voice activity detection ─────────────────────────────────────────────────
+
+/// Default level a frame must reach to start a speech segment.
+pub const DEFAULT_VAD_THRESHOLD_DBFS: f32 = -40.0;
+
+/// A segment ends only once frames drop this far below the start threshold,
+/// so a level hovering around the threshold doesn't flap on and off.
+const VAD_HYSTERESIS_DB: f32 = 6.0;
+
+/// Quiet stretches shorter than this stay inside the current segment
+/// (pauses between words, stop consonants).
+const VAD_MIN_SILENCE_MS: u32 = 300;
+
+/// Segments shorter than this are merged into their nearest neighbour.
+const VAD_MIN_SEGMENT_MS: u32 = 300;
+
+/// Split mono `samples` into speech segments (sample ranges) with an
+/// energy-based detector.
+///
+/// Frames are classified by RMS level with hysteresis: speech starts at
+/// `threshold_dbfs` and ends after [`VAD_MIN_SILENCE_MS`] below
+/// `threshold_dbfs - VAD_HYSTERESIS_DB`.  Segments shorter than
+/// [`VAD_MIN_SEGMENT_MS`] are merged into a neighbour, then each is padded by
+/// [`TRIM_PADDING_MS`] without overlapping the next, so boundaries fall in
+/// pauses rather than mid-word.  Returns an
+/// empty list when no speech is found.
+pub fn detect_speech(samples: &[f32], sample_rate: u32, threshold_dbfs: f32) -> Vec<Range<usize>> {
+    let frame_len = ((sample_rate * FRAME_MS / 1000) as usize).max(1);
+    let min_silence_frames = (VAD_MIN_SILENCE_MS / FRAME_MS) as usize;
+    let release_dbfs = threshold_dbfs - VAD_HYSTERESIS_DB;
+
+    // Frame-level segments: [first speech frame, last speech frame + 1).
+    let mut frames = Vec::new();
+    let mut start = None;
+    let mut last_loud = 0;
+    for (i, frame) in samples.chunks(frame_len).enumerate() {
+        let level = rms_dbfs(frame);
+        match start {
+            None if level >= threshold_dbfs => {
+                start = Some(i);
+                last_loud = i;
+            }
+            None => {}
+            Some(_) if level >= release_dbfs => last_loud = i,
+            Some(s) if i - last_loud >= min_silence_frames => {
+                frames.push(s..last_loud + 1);
+                start = None;
+            }
+            Some(_) => {}
+        }
+    }
+    if let Some(s) = start {
+        frames.push(s..last_loud + 1);
+    }
+
+    let speech = frames
+        .into_iter()
+        .map(|r| r.start * frame_len..(r.end * frame_len).min(samples.len()))
+        .collect();
+    let mut segments =
+        merge_short_segments(speech, (sample_rate * VAD_MIN_SEGMENT_MS / 1000) as usize);
+
+    // Pad each side, splitting overlapping padding at the middle of the gap.
+    let padding = (sample_rate * TRIM_PADDING_MS / 1000) as usize;
+    let gaps: Vec<usize> = segments
+        .windows(2)
+        .map(|pair| pair[1].start - pair[0].end)
+        .collect();
+    for (i, segment) in segments.iter_mut().enumerate() {
+        let before = if i == 0 {
+            padding
+        } else {
+            padding.min(gaps[i - 1] / 2)
+        };
+        let after = match gaps.get(i) {
+            Some(gap) => padding.min(gap - gap / 2),
+            None => padding,
+        };
+        segment.start = segment.start.saturating_sub(before);
+        segment.end = (segment.end + after).min(samples.len());
+    }
+    segments
+}
+
+/// Merge segments shorter than `min_len` samples into the neighbour across
+/// the smaller gap.  The merged segment spans both, including the gap.
+fn merge_short_segments(mut segments: Vec<Range<usize>>, min_len: usize) -> Vec<Range<usize>> {
+    while segments.len() > 1 {
+        let Some(i) = segments.iter().position(|s| s.len() < min_len) else {
+            break;
+        };
+        let gap_before = (i > 0).then(|| segments[i].start - segments[i - 1].end);
+        let gap_after = segments.get(i + 1).map(|next| next.start - segments[i].end);
+        let into_previous = match (gap_before, gap_after) {
+            (Some(before), Some(after)) => before <= after,
+            (Some(_), None) => true,
+            _ => false,
+        };
+        if into_previous {
+            let short = segments.remove(i);
+            segments[i - 1].end = short.end;
+        } else {
+            let short = segments.remove(i);
+            segments[i].start = short.start;
+        }
+    }
+    segments
+}
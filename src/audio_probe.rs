@@ -0,0 +1,286 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fmt;
+use std::fs::File;
+use std::io::{ErrorKind, Read, Seek};
+use std::path::Path;
+use symphonia::core::codecs::{self, CodecType, DecoderOptions};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+// ── Constants ────────────────────────────────────────────────────────────────
+
+/// The containers this build decodes, for error messages.
+const SUPPORTED: &str = "WAV, MP3, FLAC and Ogg Vorbis";
+
+/// Packets tried before a stream is called corrupt.  MP3 decoders can
+/// reject the first frame or two of a valid file (e.g. after a tag).
+const MAX_PROBE_PACKETS: usize = 8;
+
+// ── AudioProbe ───────────────────────────────────────────────────────────────
+
+/// What a quick look at an audio file found: enough to reject a file that
+/// can't be decoded before a model loads, and to describe one that can.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct AudioProbe {
+    /// Container, from the file's leading bytes (e.g. "WAV").
+    pub container: &'static str,
+    /// Codec of the audio track, as symphonia names it (e.g. "pcm_s16le").
+    pub codec: String,
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// From the header; `None` when the container doesn't say (some MP3s).
+    pub duration_secs: Option<f64>,
+    /// The file is shorter than its header says (a WAV cut off mid-copy).
+    /// The audio that is there still decodes.
+    pub truncated: bool,
+}
+
+impl AudioProbe {
+    /// Container and codec: `WAV, pcm_s16le`.
+    pub fn format(&self) -> String {
+        format!("{}, {}", self.container, self.codec)
+    }
+}
+
+/// `WAV, pcm_s16le, 44100 Hz, stereo, 3m 12.0s`; the duration is left out
+/// when unknown.
+impl fmt::Display for AudioProbe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.format())?;
+        write!(f, ", {} Hz", self.sample_rate)?;
+        match self.channels {
+            1 => write!(f, ", mono")?,
+            2 => write!(f, ", stereo")?,
+            n => write!(f, ", {n} ch")?,
+        }
+        if let Some(secs) = self.duration_secs {
+            write!(f, ", {}", fmt_secs(secs))?;
+        }
+        if self.truncated {
+            write!(f, ", truncated")?;
+        }
+        Ok(())
+    }
+}
+
+// ── Probing ──────────────────────────────────────────────────────────────────
+
+/// Check that `path` holds audio this build can decode, reading only its
+/// header and first few packets.
+///
+/// Fails in milliseconds, naming what was found, for a missing or empty
+/// file, a file that isn't audio (a PDF, an image), a container or codec
+/// that isn't supported (MP4 / AAC, Opus) and a stream whose header or
+/// first packets are corrupt.
+pub fn probe_audio(path: &Path) -> Result<AudioProbe> {
+    if !path.exists() {
+        anyhow::bail!("Audio file not found: {}", path.display());
+    }
+    let mut file = File::open(path)
+        .with_context(|| format!("Failed to open audio file: {}", path.display()))?;
+    let len = file
+        .metadata()
+        .with_context(|| format!("Failed to read audio file: {}", path.display()))?
+        .len();
+    if len == 0 {
+        anyhow::bail!("{} is empty (0 bytes)", path.display());
+    }
+    let mut header = Vec::with_capacity(16);
+    (&mut file)
+        .take(16)
+        .read_to_end(&mut header)
+        .and_then(|_| file.rewind())
+        .with_context(|| format!("Failed to read audio file: {}", path.display()))?;
+    let sniffed = sniff_container(&header);
+    let container = sniffed.unwrap_or("unknown container");
+
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+    let probed = symphonia::default::get_probe().format(
+        &hint,
+        stream,
+        &FormatOptions::default(),
+        &MetadataOptions::default(),
+    );
+    let mut format = match probed {
+        Ok(probed) => probed.format,
+        Err(err) => anyhow::bail!(
+            "{} can't be decoded: {}",
+            path.display(),
+            unreadable_reason(sniffed, &err)
+        ),
+    };
+
+    let track = format
+        .default_track()
+        .filter(|track| track.codec_params.codec != codecs::CODEC_TYPE_NULL)
+        .with_context(|| format!("{} ({container}) has no audio track", path.display()))?;
+    let track_id = track.id;
+    let params = track.codec_params.clone();
+    let Some(descriptor) = symphonia::default::get_codecs().get_codec(params.codec) else {
+        anyhow::bail!(
+            "{} ({container}) holds {} audio, which can't be decoded; convert it to WAV or FLAC",
+            path.display(),
+            codec_name(params.codec)
+        );
+    };
+    let codec = descriptor.short_name.to_string();
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&params, &DecoderOptions::default())
+        .with_context(|| format!("{} ({container}, {codec}) can't be decoded", path.display()))?;
+
+    // Decode a packet: a valid header over garbage fails here, not after
+    // the model has loaded.
+    let mut first_error = None;
+    let mut spec = None;
+    for _ in 0..MAX_PROBE_PACKETS {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(err)) if err.kind() == ErrorKind::UnexpectedEof => break,
+            Err(err) => {
+                first_error.get_or_insert(err);
+                break;
+            }
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        match decoder.decode(&packet) {
+            Ok(decoded) => {
+                spec = Some(*decoded.spec());
+                break;
+            }
+            Err(err @ SymphoniaError::DecodeError(_)) => {
+                first_error.get_or_insert(err);
+            }
+            Err(err) => {
+                first_error.get_or_insert(err);
+                break;
+            }
+        }
+    }
+    let Some(spec) = spec else {
+        match first_error {
+            Some(err) => anyhow::bail!(
+                "{} ({container}, {codec}) is corrupt: {err}",
+                path.display()
+            ),
+            None => anyhow::bail!(
+                "{} ({container}, {codec}) contains no audio",
+                path.display()
+            ),
+        }
+    };
+    let sample_rate = params.sample_rate.unwrap_or(spec.rate);
+    let channels = params.channels.unwrap_or(spec.channels).count() as u16;
+
+    let duration_secs = params
+        .n_frames
+        .filter(|_| sample_rate > 0)
+        .map(|frames| frames as f64 / sample_rate as f64);
+    // A WAV's data chunk size gives the bytes the samples need; a file
+    // smaller than that was cut off.
+    let truncated = sniffed == Some("WAV")
+        && params
+            .n_frames
+            .zip(params.bits_per_sample)
+            .is_some_and(|(frames, bits)| frames * channels as u64 * (bits as u64 / 8) > len);
+    Ok(AudioProbe {
+        container,
+        codec,
+        sample_rate,
+        channels,
+        duration_secs,
+        truncated,
+    })
+}
+
+/// Identify a file from its first bytes: audio containers, and the
+/// non-audio files most often passed by mistake.  `None` when nothing
+/// matches.
+pub fn sniff_container(header: &[u8]) -> Option<&'static str> {
+    let starts = |magic: &[u8]| header.starts_with(magic);
+    if starts(b"RIFF") && header.get(8..12) == Some(b"WAVE") {
+        Some("WAV")
+    } else if starts(b"RF64") {
+        Some("RF64")
+    } else if starts(b"fLaC") {
+        Some("FLAC")
+    } else if starts(b"OggS") {
+        Some("Ogg")
+    } else if starts(b"ID3") {
+        Some("MP3")
+    } else if starts(b"FORM") {
+        Some("AIFF")
+    } else if header.get(4..8) == Some(b"ftyp") {
+        Some("MP4 / M4A")
+    } else if starts(&[0x1A, 0x45, 0xDF, 0xA3]) {
+        Some("Matroska / WebM")
+    } else if starts(b"%PDF") {
+        Some("PDF")
+    } else if starts(b"\x89PNG") {
+        Some("PNG")
+    } else if starts(&[0xFF, 0xD8, 0xFF]) {
+        Some("JPEG")
+    } else if starts(b"GIF8") {
+        Some("GIF")
+    } else if starts(b"PK\x03\x04") {
+        Some("ZIP")
+    } else if header.len() >= 2 && header[0] == 0xFF && header[1] & 0xF6 == 0xF0 {
+        Some("AAC (ADTS)")
+    } else if header.len() >= 2 && header[0] == 0xFF && header[1] & 0xE0 == 0xE0 {
+        Some("MP3")
+    } else {
+        None
+    }
+}
+
+/// Why symphonia couldn't open a file whose leading bytes said `sniffed`.
+fn unreadable_reason(sniffed: Option<&str>, err: &SymphoniaError) -> String {
+    match sniffed {
+        Some(name @ ("WAV" | "FLAC" | "Ogg" | "MP3")) => {
+            format!("it looks like {name}, but the header is truncated or corrupt ({err})")
+        }
+        Some(name) => format!("its contents look like {name}; only {SUPPORTED} are supported"),
+        None => format!("the format wasn't recognised; only {SUPPORTED} are supported"),
+    }
+}
+
+/// A readable name for codecs symphonia knows of but this build can't
+/// decode.
+fn codec_name(codec: CodecType) -> String {
+    let name = match codec {
+        codecs::CODEC_TYPE_AAC => "AAC",
+        codecs::CODEC_TYPE_OPUS => "Opus",
+        codecs::CODEC_TYPE_ALAC => "ALAC",
+        codecs::CODEC_TYPE_SPEEX => "Speex",
+        codecs::CODEC_TYPE_WMA => "WMA",
+        codecs::CODEC_TYPE_EAC3 => "E-AC-3",
+        codecs::CODEC_TYPE_MP1 => "MPEG Layer I",
+        codecs::CODEC_TYPE_MP2 => "MPEG Layer II",
+        codecs::CODEC_TYPE_WAVPACK => "WavPack",
+        codecs::CODEC_TYPE_ADPCM_IMA_WAV | codecs::CODEC_TYPE_ADPCM_MS => "ADPCM",
+        other => return format!("an unknown codec ({other})"),
+    };
+    name.to_string()
+}
+
+// ── Helpers ──────────────────────────────────────────────────────────────────
+
+/// `3m 12.0s`, or `12.0s` under a minute.
+fn fmt_secs(secs: f64) -> String {
+    let mins = (secs / 60.0).floor() as u64;
+    let secs = secs - (mins as f64 * 60.0);
+    if mins > 0 {
+        format!("{mins}m {secs:.1}s")
+    } else {
+        format!("{secs:.1}s")
+    }
+}
@@ -1,12 +1,39 @@
 #![allow(dead_code)]
 
 use anyhow::{Context, Result};
-use mistralrs::{
-    AudioInput, IsqType, Model, ModelDType, RequestBuilder, TextMessageRole, VisionModelBuilder,
-};
+use mistralrs::{AudioInput, IsqType, Model, ModelDType, RequestBuilder, TextMessageRole};
+use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::io::Read;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+use crate::audio_prep::{self, AudioDump, Normalization, PrepOptions, RawPcm};
+use crate::audio_probe::{self, AudioProbe};
+use crate::cli_chat::estimate_tokens;
+use crate::disk_space;
+use crate::error::Error;
+use crate::inline_timestamps::{self, INLINE_TIMESTAMP_NOTE, TimestampMode};
+use crate::interrupt::{self, Interrupted};
+use crate::load_options::{GB, LoadOptions, LoadSettings};
+use crate::loader::{self, ModelKind};
+use crate::local_model::LocalModel;
+use crate::memory::{self, MemoryFootprint};
+use crate::memstats::{LoadMemory, MemorySnapshot};
+use crate::model_manager::{ModelKey, ModelManager};
+use crate::request_queue::RequestQueue;
+use crate::sampler::{DecodingArgs, SamplerConfig};
+use crate::song_tags::{self, SongTags};
+use crate::text_filters::Censor;
+use crate::timeout;
+use crate::transcript_format::LyricsFormatter;
+use crate::transcript_output::{self, LrcHeader, OutputFormat};
+use crate::transcription_session::{self, TranscriptionSession};
+use crate::ui::{self, OutputConfig, detail, fmt_duration, status};
+use crate::usage::Usage;
 
 // ── Model presets ────────────────────────────────────────────────────────────
 
@@ -52,29 +79,88 @@ impl TranscriptionModel {
         }
     }
 
-    /// Build the [`Model`] with the optimal dtype / ISQ settings for this
-    /// preset.
+    /// Effective parameter count in billions, for memory estimates when
+    /// `--isq` / `--dtype` override the preset.
+    fn approx_params_billions(self) -> f64 {
+        match self {
+            Self::GemmaE2b => 2.0,
+            Self::GemmaE4b => 4.0,
+        }
+    }
+
+    /// Memory footprint with `opts` applied: the preset string when the
+    /// settings are unchanged, otherwise an estimate.
+    pub fn memory_estimate(self, opts: &LoadOptions) -> String {
+        let settings = opts.apply(self.default_settings());
+        if settings == self.default_settings() {
+            self.approx_memory().to_string()
+        } else {
+            settings.approx_memory(self.approx_params_billions())
+        }
+    }
+
+    /// [`approx_memory`](Self::approx_memory) in bytes.
+    pub fn approx_bytes(self) -> u64 {
+        let bytes = match self {
+            Self::GemmaE2b => 1.5 * GB,
+            Self::GemmaE4b => 8.0 * GB,
+        };
+        bytes as u64
+    }
+
+    /// Size of the weights downloaded on first use, checked against free
+    /// disk space before the download starts.
+    pub fn download_size_bytes(self) -> u64 {
+        let bytes = match self {
+            Self::GemmaE2b => 10.9 * GB,
+            Self::GemmaE4b => 15.7 * GB,
+        };
+        bytes as u64
+    }
+
+    /// The preset's default dtype / ISQ.
+    pub fn default_settings(self) -> LoadSettings {
+        match self {
+            Self::GemmaE2b => LoadSettings::isq(IsqType::Q4K),
+            Self::GemmaE4b => LoadSettings::dtype(ModelDType::F16),
+        }
+    }
+
+    /// Cache key for [`ModelManager`]; mirrors the settings in
+    /// [`build_model`](Self::build_model).  Identical to the matching
+    /// `EnhancerModel` key, so a Gemma 3n loaded for enhancement is reused.
+    pub fn model_key(self, opts: &LoadOptions) -> ModelKey {
+        let settings = opts.apply(self.default_settings());
+        ModelKey::new(self.model_id(), settings.dtype, settings.isq)
+    }
+
+    /// Build the [`Model`] with this preset's dtype / ISQ settings, as
+    /// overridden by `opts`.
     ///
     /// Gemma 3n uses `Gemma3nForConditionalGeneration` (a multimodal
     /// architecture that includes a conformer audio encoder), so mistral.rs
-    /// classifies it as a **vision** model.  We load it via
-    /// [`VisionModelBuilder`].
-    async fn build_model(self) -> Result<Model> {
-        match self {
-            Self::GemmaE2b => {
-                VisionModelBuilder::new(self.model_id())
-                    .with_isq(IsqType::Q4K)
-                    .with_logging()
-                    .build()
-                    .await
-            }
-            Self::GemmaE4b => {
-                VisionModelBuilder::new(self.model_id())
-                    .with_dtype(ModelDType::F16)
-                    .with_logging()
-                    .build()
-                    .await
-            }
+    /// classifies it as a **vision** model and we load it as
+    /// [`ModelKind::Vision`].  `describe` loads it the same way for images.
+    pub async fn build_model(self, opts: &LoadOptions) -> Result<Model> {
+        let settings = opts.apply(self.default_settings());
+        disk_space::preflight(self.model_id(), self.download_size_bytes())?;
+        loader::build_model(self.model_id(), ModelKind::Vision, settings, &opts.uqff)
+            .await
+            .map_err(|source| opts.load_error(self.model_id(), source).into())
+    }
+}
+
+impl MemoryFootprint for TranscriptionModel {
+    const FALLBACK: Self = Self::GemmaE2b;
+
+    /// The preset figure when the settings are unchanged, otherwise an
+    /// estimate, like [`memory_estimate`](TranscriptionModel::memory_estimate).
+    fn required_bytes(self, opts: &LoadOptions) -> u64 {
+        let settings = opts.apply(self.default_settings());
+        if settings == self.default_settings() {
+            self.approx_bytes()
+        } else {
+            settings.approx_bytes(self.approx_params_billions())
         }
     }
 }
@@ -103,6 +189,367 @@ Follow these rules strictly:\n\
 /// a custom prompt.
 const DEFAULT_USER_PROMPT: &str = "Transcribe the vocals in this audio exactly, word for word.";
 
+/// User instruction when several tracks are sent in one request.
+const TRACKS_USER_PROMPT: &str = "These audio tracks are time-aligned parts of one recording, \
+for example lead and backing vocals. Transcribe the vocals across all of them exactly, word for \
+word, as a single transcript.";
+
+/// Appended to the system prompt in translation mode; overrides rule 1 above.
+const TRANSLATION_SYSTEM_NOTE: &str = "\
+Instead of a verbatim transcription, translate everything that is spoken or sung into English. \
+Keep one line per phrase and output ONLY the translation.";
+
+/// Appended to the system prompt, ahead of the lyrics, with
+/// [`AudioTranscriber::with_reference_text`].
+const REFERENCE_SYSTEM_NOTE: &str = "\
+Approximate lyrics for this audio follow. They may have wrong words, missing lines or lines that \
+are not sung. Use them to correct what you hear rather than copying them: transcribe what is \
+actually sung, and leave out any line that is not in the audio.";
+
+/// User instruction used in translation mode.
+const TRANSLATION_USER_PROMPT: &str = "Translate the lyrics in this audio into English.";
+
+/// User instruction in translation mode when several tracks are sent in
+/// one request.
+const TRACKS_TRANSLATION_USER_PROMPT: &str = "These audio tracks are time-aligned parts of one \
+recording. Translate the lyrics across all of them into English as a single translation.";
+
+/// System prompt for the per-segment `--detect-language` pass.
+const LANGUAGE_DETECTION_SYSTEM_PROMPT: &str = "\
+You identify the language of speech and singing in audio. \
+Answer with the name of the main language only, e.g. \"English\".";
+
+/// User instruction for the per-segment `--detect-language` pass.
+const LANGUAGE_DETECTION_USER_PROMPT: &str = "Which language is sung or spoken in this audio?";
+
+/// System prompt for the `--check-vocals` pre-check.
+const VOCAL_CHECK_SYSTEM_PROMPT: &str = "\
+You tell whether audio contains a human voice. \
+Answer yes if anyone sings or speaks in it, no if it is instrumental, noise or silence. \
+Answer with one word: yes or no.";
+
+/// User instruction for the `--check-vocals` pre-check.
+const VOCAL_CHECK_USER_PROMPT: &str = "Does this audio contain singing or speech? Answer yes/no.";
+
+/// Seconds of audio, from the first non-silent frame, the vocal check
+/// listens to.
+pub const VOCAL_CHECK_SECS: f64 = 20.0;
+
+/// Sampling for transcription requests: greedy, with the reply length
+/// left to the engine.  `--top-k`, the penalties and `--stop` apply on
+/// top via [`DecodingArgs`].
+pub const DEFAULT_SAMPLER: SamplerConfig = SamplerConfig::new(0.0, 1.0, 0);
+
+/// Length of the silent clip sent by [`AudioTranscriber::warm_up`]: enough
+/// to run the audio encoder end to end.
+const WARM_UP_SECS: f64 = 0.5;
+
+/// Stage name for [`Error::Generation`] and [`TimedOut`](timeout::TimedOut).
+const TRANSCRIBE_STAGE: &str = "Transcription";
+
+/// English names for common `--language` codes, so the hint reads naturally
+/// in the prompt.  Unknown codes are passed through as given.
+const LANGUAGE_NAMES: &[(&str, &str)] = &[
+    ("ar", "Arabic"),
+    ("de", "German"),
+    ("en", "English"),
+    ("es", "Spanish"),
+    ("fa", "Persian"),
+    ("fr", "French"),
+    ("hi", "Hindi"),
+    ("id", "Indonesian"),
+    ("it", "Italian"),
+    ("ja", "Japanese"),
+    ("ko", "Korean"),
+    ("nl", "Dutch"),
+    ("pl", "Polish"),
+    ("pt", "Portuguese"),
+    ("ru", "Russian"),
+    ("sv", "Swedish"),
+    ("th", "Thai"),
+    ("tr", "Turkish"),
+    ("uk", "Ukrainian"),
+    ("vi", "Vietnamese"),
+    ("zh", "Chinese"),
+];
+
+/// The English name for a language code such as `ja`, or the input itself
+/// if it isn't a known code (so `--language Japanese` works too).
+fn language_name(code: &str) -> String {
+    LANGUAGE_NAMES
+        .iter()
+        .find(|(c, _)| c.eq_ignore_ascii_case(code))
+        .map(|(_, name)| name.to_string())
+        .unwrap_or_else(|| code.to_string())
+}
+
+/// Other names the model answers with, mapped to codes in
+/// [`LANGUAGE_NAMES`].
+const LANGUAGE_ALIASES: &[(&str, &str)] = &[
+    ("bahasa", "id"),
+    ("brazilian", "pt"),
+    ("cantonese", "zh"),
+    ("castilian", "es"),
+    ("farsi", "fa"),
+    ("flemish", "nl"),
+    ("indonesia", "id"),
+    ("mandarin", "zh"),
+    ("persian", "fa"),
+];
+
+/// Map a language-detection reply to an ISO 639-1 code.
+///
+/// The model doesn't always answer with one word: "English", "en-US",
+/// "Bahasa Indonesia" and "This is mostly English with some Japanese" all
+/// work.  A bare code is taken as is; otherwise the first language named
+/// wins, skipping any directly after "not" / "no" ("not Japanese, it's
+/// Korean" → `ko`).  `None` when no known language is mentioned.
+pub fn normalize_language(answer: &str) -> Option<String> {
+    let answer = answer
+        .trim()
+        .trim_matches(|c: char| c.is_ascii_punctuation() || c.is_whitespace());
+    let code = answer.split(['-', '_']).next().unwrap_or_default();
+    if answer.len() <= 5 && code.len() == 2 {
+        let code = code.to_ascii_lowercase();
+        if LANGUAGE_NAMES.iter().any(|(c, _)| *c == code) {
+            return Some(code);
+        }
+    }
+
+    let words: Vec<String> = answer
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    words.iter().enumerate().find_map(|(i, word)| {
+        if i > 0 && matches!(words[i - 1].as_str(), "not" | "no" | "non") {
+            return None;
+        }
+        LANGUAGE_NAMES
+            .iter()
+            .find(|(_, name)| name.eq_ignore_ascii_case(word))
+            .map(|(code, _)| *code)
+            .or_else(|| {
+                LANGUAGE_ALIASES
+                    .iter()
+                    .find(|(alias, _)| alias == word)
+                    .map(|(_, code)| *code)
+            })
+            .map(str::to_string)
+    })
+}
+
+/// Words in a vocal-check reply that say a voice is there.
+const VOICE_WORDS: &[&str] = &[
+    "singing", "sung", "sings", "singer", "speech", "speaking", "spoken", "speaks", "talking",
+    "vocal", "vocals", "voice", "voices", "lyrics", "words",
+];
+
+/// Words in a vocal-check reply that say it isn't.
+const NO_VOICE_WORDS: &[&str] = &["instrumental", "silence", "silent", "noise", "music-only"];
+
+/// Read the reply to the vocal check: `Some(true)` for a voice, `Some(false)`
+/// for none, `None` when the reply says neither.
+///
+/// A leading "yes" / "no" decides it.  Otherwise the reply is read clause
+/// by clause: "singing", "speech", "vocals" and the like count for a voice,
+/// "instrumental", "silence" and "noise" against, and a "no" / "not" /
+/// "without" earlier in the clause flips them ("there is no singing, only
+/// guitar" → `Some(false)`).  Any sign of a voice wins over signs against,
+/// so a hedged reply still gets transcribed.
+pub fn parse_vocal_answer(answer: &str) -> Option<bool> {
+    let answer = answer.to_lowercase().replace('’', "'");
+    let words = |clause: &str| -> Vec<String> {
+        clause
+            .split(|c: char| !c.is_alphanumeric() && c != '\'' && c != '-')
+            .filter(|w| !w.is_empty())
+            .map(str::to_string)
+            .collect()
+    };
+    match words(&answer).first().map(String::as_str) {
+        Some("yes" | "yeah" | "yep" | "true") => return Some(true),
+        Some("no" | "nope" | "false") => return Some(false),
+        _ => {}
+    }
+
+    let (mut voice, mut no_voice) = (false, false);
+    for clause in answer.split(['.', ',', ';', ':', '!', '?', '\n']) {
+        let mut negated = false;
+        for word in words(clause) {
+            match word.as_str() {
+                "yes" | "yeah" => voice = true,
+                "no" | "none" => {
+                    no_voice = true;
+                    negated = true;
+                }
+                "not" | "without" | "never" | "nobody" | "nothing" => negated = true,
+                w if w.ends_with("n't") => negated = true,
+                "but" | "only" | "though" => negated = false,
+                w if VOICE_WORDS.contains(&w) => match negated {
+                    true => no_voice = true,
+                    false => voice = true,
+                },
+                w if NO_VOICE_WORDS.contains(&w) => match negated {
+                    true => voice = true,
+                    false => no_voice = true,
+                },
+                _ => {}
+            }
+        }
+    }
+    match (voice, no_voice) {
+        (true, _) => Some(true),
+        (false, true) => Some(false),
+        (false, false) => None,
+    }
+}
+
+/// What [`AudioTranscriber::with_vocal_check`] does before transcribing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VocalCheck {
+    /// Transcribe without asking.
+    #[default]
+    Off,
+    /// Ask whether there is a voice; on "no", return an empty transcript
+    /// instead of transcribing.
+    Skip,
+    /// Ask and record the answer, but transcribe regardless (`--force`).
+    Report,
+}
+
+/// The answer to the vocal pre-check, recorded on the
+/// [`TranscriptionResult`].
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct VocalVerdict {
+    /// [`parse_vocal_answer`] of `answer`; `None` (unclear) is treated as
+    /// a voice.
+    pub vocals: Option<bool>,
+    /// The model's reply as written.
+    pub answer: String,
+    /// Seconds of audio the check listened to.
+    pub excerpt_secs: f64,
+    /// The transcription was skipped because no voice was heard.
+    pub skipped: bool,
+}
+
+impl fmt::Display for VocalVerdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verdict = match self.vocals {
+            Some(true) => "vocals",
+            Some(false) => "no vocals",
+            None => "unclear, transcribed anyway",
+        };
+        write!(f, "{verdict} in the first {:.0}s", self.excerpt_secs)?;
+        if self.skipped {
+            write!(f, "; not transcribed")?;
+        }
+        Ok(())
+    }
+}
+
+/// What the model was asked to produce.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TranscriptionMode {
+    /// Verbatim transcription in the original language.
+    #[default]
+    Transcribe,
+    /// English translation.
+    Translate,
+}
+
+// ── Reference text ───────────────────────────────────────────────────────────
+
+/// Tokens of lyrics [`AudioTranscriber::with_reference_text`] keeps; the
+/// rest of a longer hint is dropped.  `--lyrics-hint-tokens` changes it.
+pub const DEFAULT_REFERENCE_TOKENS: usize = 1024;
+
+/// Approximate lyrics (e.g. scraped from a lyrics site) sent with the audio
+/// so the model corrects them instead of transcribing from scratch; see
+/// [`AudioTranscriber::with_reference`].
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ReferenceText {
+    /// The lyrics as sent, cut to the token budget.
+    #[serde(skip)]
+    pub text: String,
+    /// Estimated tokens in `text`.
+    pub tokens: usize,
+    /// Estimated tokens before cutting; the same as `tokens` when the
+    /// whole hint fit.
+    pub original_tokens: usize,
+}
+
+impl ReferenceText {
+    /// Trim `text` and keep as many whole lines as fit in `max_tokens`
+    /// (estimated at four characters per token).  A first line longer than
+    /// the budget is cut at the last space that fits.
+    pub fn fit(text: &str, max_tokens: usize) -> Self {
+        let text = text.trim();
+        let original_tokens = estimate_tokens(text);
+        let mut end = text.len();
+        if original_tokens > max_tokens {
+            end = 0;
+            for line_end in text.match_indices('\n').map(|(i, _)| i) {
+                if estimate_tokens(&text[..line_end]) > max_tokens {
+                    break;
+                }
+                end = line_end;
+            }
+            if end == 0 {
+                let limit = text
+                    .char_indices()
+                    .nth(max_tokens * 4)
+                    .map_or(text.len(), |(i, _)| i);
+                end = text[..limit].rfind(char::is_whitespace).unwrap_or(limit);
+            }
+        }
+        let text = text[..end].trim_end().to_string();
+        Self {
+            tokens: estimate_tokens(&text),
+            original_tokens,
+            text,
+        }
+    }
+
+    /// Read a `--lyrics-hint` file and [`fit`](Self::fit) it, warning when
+    /// it had to be cut.
+    pub fn from_file(path: &Path, max_tokens: usize) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read lyrics hint {}", path.display()))?;
+        if text.trim().is_empty() {
+            anyhow::bail!("Lyrics hint {} is empty", path.display());
+        }
+        let reference = Self::fit(&text, max_tokens);
+        reference.warn_if_truncated();
+        Ok(reference)
+    }
+
+    /// Whether the hint was longer than the budget.
+    pub fn truncated(&self) -> bool {
+        self.tokens < self.original_tokens
+    }
+
+    fn warn_if_truncated(&self) {
+        if self.truncated() {
+            tracing::warn!(
+                "lyrics hint is ~{} tokens; only the first ~{} are sent",
+                self.original_tokens,
+                self.tokens
+            );
+        }
+    }
+}
+
+impl fmt::Display for ReferenceText {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "~{} tokens", self.tokens)?;
+        if self.truncated() {
+            write!(f, " (cut from ~{})", self.original_tokens)?;
+        }
+        Ok(())
+    }
+}
+
 // ── AudioTranscriber ─────────────────────────────────────────────────────────
 
 /// A self-contained audio transcriber built on Gemma 3n's conformer audio
@@ -112,26 +559,145 @@ const DEFAULT_USER_PROMPT: &str = "Transcribe the vocals in this audio exactly,
 /// like demucs.  The 128-bin mel spectrogram + conformer encoder gives higher
 /// spectral resolution than typical 80-bin ASR front-ends, which helps with
 /// the artefacts present in separated vocals.
+///
+/// Safe to share between tasks: each request to the model waits its turn
+/// in a [`RequestQueue`] (one at a time unless
+/// [`set_max_concurrency`](Self::set_max_concurrency) says otherwise).
 pub struct AudioTranscriber {
-    model: Model,
+    model: Arc<Model>,
+    /// The model's HuggingFace ID or local path, recorded on the tracing
+    /// spans; see [`with_model_id`](Self::with_model_id).
+    model_id: Option<String>,
     system_prompt: String,
+    /// Language the audio is known to be in, as passed to `--language`.
+    language: Option<String>,
+    translate: bool,
+    /// Mono / 16 kHz / silence-trim preprocessing; `None` sends the decoded
+    /// audio as-is.
+    prep: Option<PrepOptions>,
+    /// Speech-start threshold (dBFS) when transcribing per VAD segment;
+    /// `None` sends the whole file in one request.
+    vad_threshold_dbfs: Option<f32>,
+    /// Ask for each VAD segment's language before transcribing it.
+    detect_language: bool,
+    /// Ask whether there is a voice at all first; see
+    /// [`with_vocal_check`](Self::with_vocal_check).
+    vocal_check: VocalCheck,
+    /// Ask for `[mm:ss]` markers and time segments by them; see
+    /// [`with_timestamps`](Self::with_timestamps).
+    timestamps: Option<TimestampMode>,
+    /// Resample tracks to a common rate; see [`with_track_resampling`](Self::with_track_resampling).
+    resample_tracks: bool,
+    /// Reshapes the text as lyrics; see [`with_lyrics_format`](Self::with_lyrics_format).
+    lyrics: Option<LyricsFormatter>,
+    /// Masks listed words in the result; see [`with_censor`](Self::with_censor).
+    censor: Option<Censor>,
+    /// Approximate lyrics to correct; see [`with_reference`](Self::with_reference).
+    reference: Option<ReferenceText>,
+    /// Writes the audio sent to the model; see [`with_audio_dump`](Self::with_audio_dump).
+    dump_audio: Option<AudioDump>,
+    /// Sampling for transcription requests; see [`with_sampler`](Self::with_sampler).
+    sampler: SamplerConfig,
+    /// Stops a transcription early; see [`with_cancellation`](Self::with_cancellation).
+    cancel: Option<CancellationToken>,
+    /// Limit per request; see [`with_timeout`](Self::with_timeout).
+    timeout: Option<Duration>,
+    /// Bounds the requests in flight; see [`set_max_concurrency`](Self::set_max_concurrency).
+    queue: RequestQueue,
 }
 
 impl AudioTranscriber {
     /// Build a new `AudioTranscriber` using the **default** preset
     /// ([`TranscriptionModel::GemmaE4b`]).
-    pub async fn new() -> Result<Self> {
+    pub async fn new() -> Result<Self, Error> {
         Self::from_preset(TranscriptionModel::default()).await
     }
 
     /// Build an `AudioTranscriber` from one of the built-in
     /// [`TranscriptionModel`] presets.
-    pub async fn from_preset(preset: TranscriptionModel) -> Result<Self> {
-        let model = preset.build_model().await?;
-        Ok(Self {
+    pub async fn from_preset(preset: TranscriptionModel) -> Result<Self, Error> {
+        Self::from_preset_with_options(preset, &LoadOptions::default()).await
+    }
+
+    /// Like [`from_preset`](Self::from_preset), with `--isq` / `--dtype`
+    /// overrides applied on top of the preset's defaults.
+    pub async fn from_preset_with_options(
+        preset: TranscriptionModel,
+        opts: &LoadOptions,
+    ) -> Result<Self, Error> {
+        let model = preset
+            .build_model(opts)
+            .await
+            .map_err(|source| Error::model_load(preset.model_id(), source))?;
+        Ok(Self::from_model(Arc::new(model)).with_model_id(preset.model_id()))
+    }
+
+    /// Like [`from_preset_with_options`](Self::from_preset_with_options), but
+    /// reuses a model already built by `manager` instead of loading it again.
+    pub async fn from_preset_with_manager(
+        preset: TranscriptionModel,
+        opts: &LoadOptions,
+        manager: &ModelManager,
+    ) -> Result<Self, Error> {
+        let model = manager
+            .get_or_build(preset.model_key(opts), || preset.build_model(opts))
+            .await
+            .map_err(|source| Error::model_load(preset.model_id(), source))?;
+        Ok(Self::from_model(model).with_model_id(preset.model_id()))
+    }
+
+    /// Load a Gemma 3n model from a local HuggingFace-layout directory
+    /// instead of a preset, for offline use.
+    ///
+    /// GGUF files and text-only architectures are rejected up front, since
+    /// they have no audio encoder.
+    pub async fn from_local_path(path: &Path, opts: &LoadOptions) -> Result<Self, Error> {
+        let model = async {
+            let local = LocalModel::from_path(path)?;
+            if !local.is_multimodal() {
+                anyhow::bail!(
+                    "Audio transcription needs a local Gemma 3n model directory; \
+                     {local} is a text-only model"
+                );
+            }
+            local.build(opts).await
+        }
+        .await
+        .map_err(|source| Error::model_load(path.display().to_string(), source))?;
+        Ok(Self::from_model(Arc::new(model)).with_model_id(path.display().to_string()))
+    }
+
+    /// Build an `AudioTranscriber` around an already-loaded Gemma 3n model.
+    pub fn from_model(model: Arc<Model>) -> Self {
+        Self {
             model,
+            model_id: None,
             system_prompt: TRANSCRIPTION_SYSTEM_PROMPT.to_string(),
-        })
+            language: None,
+            translate: false,
+            prep: Some(PrepOptions::default()),
+            vad_threshold_dbfs: None,
+            detect_language: false,
+            vocal_check: VocalCheck::Off,
+            timestamps: None,
+            resample_tracks: false,
+            lyrics: None,
+            censor: None,
+            reference: None,
+            dump_audio: None,
+            sampler: DEFAULT_SAMPLER,
+            cancel: None,
+            timeout: timeout::default_limit(),
+            queue: RequestQueue::default(),
+        }
+    }
+
+    /// Name the model on this transcriber's tracing spans.  The loading
+    /// constructors set it; one built with [`from_model`](Self::from_model)
+    /// has none until this is called.
+    pub fn with_model_id(mut self, model_id: impl Into<String>) -> Self {
+        self.model_id = Some(model_id.into());
+        self
     }
 
     /// Override the default system prompt.
@@ -140,6 +706,231 @@ impl AudioTranscriber {
         self
     }
 
+    /// Tell the model which language the audio is in, as an ISO 639-1 code
+    /// (`ja`) or a language name.
+    pub fn with_language(mut self, language: &str) -> Self {
+        self.language = Some(language.to_string());
+        self
+    }
+
+    /// Ask for an English translation instead of a verbatim transcript.
+    ///
+    /// Ignored for calls that pass a custom `user_prompt`: the custom
+    /// instruction wins.
+    pub fn with_translation(mut self, translate: bool) -> Self {
+        self.translate = translate;
+        self
+    }
+
+    /// Override the preprocessing applied before inference (enabled with
+    /// [`PrepOptions::default`] unless changed).  `None` disables it.
+    pub fn with_preprocessing(mut self, prep: Option<PrepOptions>) -> Self {
+        self.prep = prep;
+        self
+    }
+
+    /// RMS-normalize the audio to `target_dbfs` (e.g.
+    /// [`DEFAULT_NORMALIZE_RMS_DBFS`](audio_prep::DEFAULT_NORMALIZE_RMS_DBFS))
+    /// before inference, so quiet stems reach the model at a usable level.
+    /// The gain shows up as
+    /// [`normalization_gain_db`](TranscriptionResult::normalization_gain_db).
+    /// Part of preprocessing: no effect while that is disabled.
+    pub fn with_normalization(mut self, target_dbfs: f32) -> Self {
+        if let Some(prep) = &mut self.prep {
+            prep.normalize = Some(Normalization::rms(target_dbfs));
+        }
+        self
+    }
+
+    /// Split the audio on detected silences and transcribe each speech
+    /// segment separately, producing timed [`Segment`]s.  `None` (the
+    /// default) transcribes the whole file in one request.
+    pub fn with_vad(mut self, threshold_dbfs: Option<f32>) -> Self {
+        self.vad_threshold_dbfs = threshold_dbfs;
+        self
+    }
+
+    /// Before transcribing each VAD segment, ask the model which language
+    /// it is in, record that on the [`Segment`] and use it as the
+    /// segment's language hint (in place of [`with_language`](Self::with_language)).
+    ///
+    /// Costs one extra request per segment; ignored without VAD.
+    pub fn with_language_detection(mut self, detect: bool) -> Self {
+        self.detect_language = detect;
+        self
+    }
+
+    /// Before transcribing, send the first [`VOCAL_CHECK_SECS`] of audio
+    /// after any leading silence and ask whether anyone sings or speaks in
+    /// it, so an instrumental stem doesn't come back with hallucinated
+    /// lyrics.  The verdict is recorded as
+    /// [`TranscriptionResult::vocal_check`].
+    ///
+    /// Costs one extra short request per file; not done for
+    /// [`transcribe_tracks`](Self::transcribe_tracks).
+    pub fn with_vocal_check(mut self, check: VocalCheck) -> Self {
+        self.vocal_check = check;
+        self
+    }
+
+    /// Get segment timings from the model itself: with
+    /// [`TimestampMode::Inline`] the system prompt asks for a `[mm:ss]`
+    /// marker at the start of each line, and the markers are parsed out of
+    /// the reply into [`Segment`]s.  Leading silence is kept so the times
+    /// line up with the file.
+    ///
+    /// Ignored with VAD, which times segments itself.
+    pub fn with_timestamps(mut self, timestamps: Option<TimestampMode>) -> Self {
+        self.timestamps = timestamps;
+        self
+    }
+
+    /// Whether requests ask for inline `[mm:ss]` markers.
+    fn inline_timestamps(&self) -> bool {
+        self.timestamps == Some(TimestampMode::Inline) && self.vad_threshold_dbfs.is_none()
+    }
+
+    /// Without preprocessing, resample the tracks given to
+    /// [`transcribe_tracks`](Self::transcribe_tracks) to the first one's
+    /// sample rate instead of rejecting tracks whose rates differ.
+    /// Preprocessing brings every track to 16 kHz anyway.
+    pub fn with_track_resampling(mut self, resample: bool) -> Self {
+        self.resample_tracks = resample;
+        self
+    }
+
+    /// Write the exact audio each request sends to the model to a WAV file,
+    /// after preprocessing, to tell a decoding problem from a model one.
+    /// With VAD or several tracks, each chunk goes to its own numbered
+    /// file (see [`AudioDump::chunk_path`]).  `None` (the default) writes
+    /// nothing.
+    pub fn with_audio_dump(mut self, dump: Option<AudioDump>) -> Self {
+        self.dump_audio = dump;
+        self
+    }
+
+    /// Sample transcription requests with `sampler` instead of
+    /// [`DEFAULT_SAMPLER`].  The reply is cut at its first stop string.
+    /// Language detection and the warm-up keep their own fixed settings.
+    pub fn with_sampler(mut self, sampler: SamplerConfig) -> Self {
+        self.sampler = sampler;
+        self
+    }
+
+    /// Reformat the text and every segment of each result as lyrics,
+    /// keeping the model's reply in
+    /// [`raw_text`](TranscriptionResult::raw_text).  `None` (the default)
+    /// leaves the lines as the model wrote them.
+    pub fn with_lyrics_format(mut self, lyrics: Option<LyricsFormatter>) -> Self {
+        self.lyrics = lyrics;
+        self
+    }
+
+    /// Mask the words `censor` lists in the text and every segment of each
+    /// result.  `None` (the default) leaves the transcript as the model
+    /// wrote it.
+    pub fn with_censor(mut self, censor: Option<Censor>) -> Self {
+        self.censor = censor;
+        self
+    }
+
+    /// Send approximate lyrics with every transcription request, for the
+    /// model to correct against the audio rather than transcribe from
+    /// scratch.  Cut to [`DEFAULT_REFERENCE_TOKENS`], with a warning when
+    /// that drops some; use [`with_reference`](Self::with_reference) for
+    /// another budget.
+    pub fn with_reference_text(self, text: impl Into<String>) -> Self {
+        let reference = ReferenceText::fit(&text.into(), DEFAULT_REFERENCE_TOKENS);
+        reference.warn_if_truncated();
+        self.with_reference(Some(reference))
+    }
+
+    /// Send already [`fit`](ReferenceText::fit) lyrics with every
+    /// transcription request; `None` (the default) sends none.  They go at
+    /// the end of the system prompt, since Gemma's chat template allows
+    /// only one user turn before the reply.  Recorded as
+    /// [`TranscriptionResult::reference_text`].
+    pub fn with_reference(mut self, reference: Option<ReferenceText>) -> Self {
+        self.reference = reference;
+        self
+    }
+
+    /// Stop when `cancel` fires.  A single-request transcription is
+    /// abandoned with an [`Interrupted`] error; with VAD the segment in
+    /// progress is finished and the partial result is returned with
+    /// [`interrupted`](TranscriptionResult::interrupted) set.
+    pub fn with_cancellation(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Abandon a request that runs longer than `limit` with a
+    /// [`TimedOut`](timeout::TimedOut) error (default: `--timeout`, if given).  With VAD the limit applies
+    /// per segment, and a segment that times out is skipped with a warning
+    /// so the rest of the file is still transcribed.
+    pub fn with_timeout(mut self, limit: Duration) -> Self {
+        self.timeout = Some(limit);
+        self
+    }
+
+    /// Let up to `limit` requests (at least one) run at once; see
+    /// [`set_max_concurrency`](Self::set_max_concurrency).
+    pub fn with_max_concurrency(mut self, limit: usize) -> Self {
+        self.set_max_concurrency(limit);
+        self
+    }
+
+    /// Let up to `limit` requests (at least one, the default) run at once
+    /// across every task using this transcriber.  The limit is per request,
+    /// so with VAD each segment queues separately.
+    pub fn set_max_concurrency(&mut self, limit: usize) {
+        self.queue = RequestQueue::new(limit);
+    }
+
+    /// Most requests this transcriber sends at once.
+    pub fn max_concurrency(&self) -> usize {
+        self.queue.limit()
+    }
+
+    /// The mode used for a request with the given custom prompt.
+    fn mode(&self, user_prompt: Option<&str>) -> TranscriptionMode {
+        if self.translate && user_prompt.is_none() {
+            TranscriptionMode::Translate
+        } else {
+            TranscriptionMode::Transcribe
+        }
+    }
+
+    /// The system prompt a request with `user_prompt` sends, with the
+    /// language hint and translation note applied.
+    pub fn effective_system_prompt(&self, user_prompt: Option<&str>) -> String {
+        self.system_message(self.mode(user_prompt), self.language.as_deref())
+    }
+
+    /// System prompt with the language hint, translation note and lyrics
+    /// hint applied.
+    fn system_message(&self, mode: TranscriptionMode, language: Option<&str>) -> String {
+        let mut message = self.system_prompt.clone();
+        if let Some(language) = language {
+            message.push_str(&format!("\nThe audio is in {}.", language_name(language)));
+        }
+        if mode == TranscriptionMode::Translate {
+            message.push('\n');
+            message.push_str(TRANSLATION_SYSTEM_NOTE);
+        }
+        if self.inline_timestamps() {
+            message.push('\n');
+            message.push_str(INLINE_TIMESTAMP_NOTE);
+        }
+        if let Some(reference) = &self.reference {
+            message.push('\n');
+            message.push_str(REFERENCE_SYSTEM_NOTE);
+            message.push_str("\n\nApproximate lyrics:\n");
+            message.push_str(&reference.text);
+        }
+        message
+    }
+
     /// Transcribe audio from raw bytes (WAV, MP3, OGG, FLAC — anything
     /// symphonia can decode).
     ///
@@ -149,8 +940,9 @@ impl AudioTranscriber {
         &self,
         audio_bytes: &[u8],
         user_prompt: Option<&str>,
-    ) -> Result<TranscriptionResult> {
-        let audio = AudioInput::from_bytes(audio_bytes).context("Failed to decode audio bytes")?;
+    ) -> Result<TranscriptionResult, Error> {
+        let audio = AudioInput::from_bytes(audio_bytes)
+            .map_err(|source| Error::audio_decode(Path::new("<bytes>"), source))?;
         self.transcribe_audio(audio, user_prompt).await
     }
 
@@ -159,13 +951,13 @@ impl AudioTranscriber {
         &self,
         path: impl AsRef<Path>,
         user_prompt: Option<&str>,
-    ) -> Result<TranscriptionResult> {
+    ) -> Result<TranscriptionResult, Error> {
         let path = path.as_ref();
-        let audio = AudioInput::read_wav(
-            path.to_str()
-                .ok_or_else(|| anyhow::anyhow!("Path is not valid UTF-8: {}", path.display()))?,
-        )
-        .with_context(|| format!("Failed to read WAV file: {}", path.display()))?;
+        let audio = path
+            .to_str()
+            .ok_or_else(|| anyhow::anyhow!("Path is not valid UTF-8"))
+            .and_then(AudioInput::read_wav)
+            .map_err(|source| Error::audio_decode(path, source))?;
         self.transcribe_audio(audio, user_prompt).await
     }
 
@@ -175,89 +967,1026 @@ impl AudioTranscriber {
         &self,
         path: impl AsRef<Path>,
         user_prompt: Option<&str>,
-    ) -> Result<TranscriptionResult> {
+    ) -> Result<TranscriptionResult, Error> {
         let path = path.as_ref();
-        let bytes = std::fs::read(path)
-            .with_context(|| format!("Failed to read audio file: {}", path.display()))?;
-        self.transcribe_bytes(&bytes, user_prompt).await
+        let audio = std::fs::read(path)
+            .context("Failed to read the file")
+            .and_then(|bytes| AudioInput::from_bytes(&bytes))
+            .map_err(|source| Error::audio_decode(path, source))?;
+        self.transcribe_audio(audio, user_prompt).await
     }
 
-    /// Core transcription method that takes a decoded [`AudioInput`].
-    async fn transcribe_audio(
+    /// Transcribe several audio files, such as separate lead and backing
+    /// vocal stems, in a single request so the model hears them together.
+    ///
+    /// Each file must decode on its own; see
+    /// [`transcribe_tracks`](Self::transcribe_tracks) for the rest.
+    pub async fn transcribe_files(
         &self,
-        audio: AudioInput,
+        paths: &[PathBuf],
         user_prompt: Option<&str>,
-    ) -> Result<TranscriptionResult> {
-        let sample_rate = audio.sample_rate;
-        let channels = audio.channels;
-        let num_samples = audio.samples.len();
-        let duration_secs = num_samples as f64 / (sample_rate as f64 * channels as f64);
+    ) -> Result<TranscriptionResult, Error> {
+        let tracks = decode_tracks(paths, None)
+            .map_err(|source| Error::audio_decode(Path::new("-"), source))?;
+        self.transcribe_tracks(tracks, user_prompt).await
+    }
 
-        let user_text = user_prompt.unwrap_or(DEFAULT_USER_PROMPT);
+    /// Transcribe decoded `(source, audio)` tracks of one recording in a
+    /// single request.  Without a custom `user_prompt`, the instruction
+    /// tells the model the tracks belong together.
+    ///
+    /// The tracks are assumed to be time-aligned, so preprocessing skips
+    /// the silence trim, which would shift them against each other.
+    /// Without preprocessing, tracks must share a sample rate unless
+    /// [`with_track_resampling`](Self::with_track_resampling) is set.
+    /// Tracks can't be combined with VAD or inline timestamps, which time
+    /// a single track.
+    ///
+    /// [`audio_duration_secs`](TranscriptionResult::audio_duration_secs) is
+    /// the longest track; [`tracks`](TranscriptionResult::tracks) has each
+    /// one's format and length.
+    pub async fn transcribe_tracks(
+        &self,
+        tracks: Vec<(String, AudioInput)>,
+        user_prompt: Option<&str>,
+    ) -> Result<TranscriptionResult, Error> {
+        self.tracks_result(tracks, user_prompt)
+            .await
+            .map_err(|source| Error::generation(TRANSCRIBE_STAGE, source))
+    }
 
-        let request = RequestBuilder::new()
-            .set_sampler_temperature(0.0)
-            .add_message(TextMessageRole::System, &self.system_prompt)
-            .add_audio_message(TextMessageRole::User, user_text, vec![audio], &self.model)?;
+    async fn tracks_result(
+        &self,
+        tracks: Vec<(String, AudioInput)>,
+        user_prompt: Option<&str>,
+    ) -> Result<TranscriptionResult> {
+        if tracks.is_empty() {
+            anyhow::bail!("No audio tracks to transcribe");
+        }
+        if self.vad_threshold_dbfs.is_some() || self.inline_timestamps() {
+            anyhow::bail!("VAD and inline timestamps can't be used with several tracks");
+        }
+        if self.prep.is_none() && !self.resample_tracks {
+            check_track_rates(&tracks)?;
+        }
 
-        let start = Instant::now();
-        let response = self.model.send_chat_request(request).await?;
-        let inference_elapsed = start.elapsed();
+        let infos: Vec<TrackInfo> = tracks
+            .iter()
+            .map(|(source, audio)| TrackInfo::new(source, audio))
+            .collect();
+        let rate = tracks[0].1.sample_rate;
+        let audios: Vec<AudioInput> = tracks
+            .into_iter()
+            .map(|(_, audio)| match &self.prep {
+                Some(_) => PreparedAudio::new(audio, self.prep.as_ref(), true).audio,
+                None if audio.sample_rate != rate => AudioInput {
+                    samples: audio_prep::resample_interleaved(
+                        &audio.samples,
+                        audio.channels,
+                        audio.sample_rate,
+                        rate,
+                    ),
+                    sample_rate: rate,
+                    channels: audio.channels,
+                },
+                None => audio,
+            })
+            .collect();
 
-        let text = response.choices[0]
-            .message
-            .content
-            .as_ref()
-            .map(|c| c.trim().to_string())
-            .unwrap_or_default();
+        let mode = self.mode(user_prompt);
+        let mut result = TranscriptionResult {
+            text: String::new(),
+            audio_duration_secs: infos.iter().map(|t| t.duration_secs).fold(0.0, f64::max),
+            effective_duration_secs: audios
+                .iter()
+                .map(|a| audio_prep::duration_secs(&a.samples, a.sample_rate, a.channels))
+                .fold(0.0, f64::max),
+            inference_duration: Duration::ZERO,
+            sample_rate: infos[0].sample_rate,
+            channels: infos[0].channels,
+            mode,
+            language: self.language.clone(),
+            segments: None,
+            tracks: Some(infos),
+            interrupted: false,
+            usage: Usage::default(),
+            load_memory: None,
+            raw_text: None,
+            censored_words: None,
+            normalization_gain_db: None,
+            warm_up_secs: None,
+            dumped_audio: None,
+            vocal_check: None,
+            reference_text: self.reference.clone(),
+            tags: None,
+        };
+        if let Some(dump) = &self.dump_audio {
+            let paths = audios
+                .iter()
+                .enumerate()
+                .map(|(i, audio)| Ok(dump.write_chunk(i + 1, audio)?.display().to_string()))
+                .collect::<Result<Vec<_>>>()?;
+            result.dumped_audio = Some(paths);
+        }
+        if audios.iter().any(|audio| !audio.samples.is_empty()) {
+            let start = Instant::now();
+            let request = self.request_text(audios, user_prompt, mode, self.language.as_deref());
+            let (text, usage) = interrupt::unless_cancelled(self.cancel.as_ref(), request).await?;
+            result.text = text;
+            result.usage = usage;
+            result.inference_duration = start.elapsed();
+        }
+        self.finish_result(&mut result);
+        Ok(result)
+    }
 
-        Ok(TranscriptionResult {
-            text,
-            audio_duration_secs: duration_secs,
-            inference_duration: inference_elapsed,
-            sample_rate,
-            channels,
-        })
+    /// Core transcription method that takes a decoded [`AudioInput`].
+    pub async fn transcribe_audio(
+        &self,
+        audio: AudioInput,
+        user_prompt: Option<&str>,
+    ) -> Result<TranscriptionResult, Error> {
+        self.transcribe_prepared(self.prepare(audio), user_prompt)
+            .await
     }
 
-    /// Return a reference to the underlying `Model`.
-    pub fn model(&self) -> &Model {
-        &self.model
+    /// Preprocess `audio` the way this transcriber is configured to, so it
+    /// can be done off the inference path (see [`transcribe_prepared`](Self::transcribe_prepared)).
+    pub fn prepare(&self, audio: AudioInput) -> PreparedAudio {
+        let keep_timing = self.vad_threshold_dbfs.is_some() || self.inline_timestamps();
+        PreparedAudio::new(audio, self.prep.as_ref(), keep_timing)
     }
-}
 
-// ── TranscriptionResult ──────────────────────────────────────────────────────
+    /// Transcribe audio already run through [`prepare`](Self::prepare) or
+    /// [`PreparedAudio::new`] with the same settings.
+    pub async fn transcribe_prepared(
+        &self,
+        prepared: PreparedAudio,
+        user_prompt: Option<&str>,
+    ) -> Result<TranscriptionResult, Error> {
+        self.prepared_result(prepared, user_prompt)
+            .await
+            .map_err(|source| Error::generation(TRANSCRIBE_STAGE, source))
+    }
 
-/// The output of a transcription, including the text and timing metadata.
-pub struct TranscriptionResult {
-    /// The transcribed text.
-    pub text: String,
-    /// Duration of the input audio in seconds.
-    pub audio_duration_secs: f64,
-    /// Wall-clock time the model spent generating the transcription.
-    pub inference_duration: Duration,
-    /// Sample rate of the input audio (before any resampling by the model).
+    #[tracing::instrument(
+        name = "transcribe_audio",
+        skip_all,
+        fields(
+            model_id = self.model_id.as_deref(),
+            audio_secs = prepared.duration_secs
+        )
+    )]
+    async fn prepared_result(
+        &self,
+        prepared: PreparedAudio,
+        user_prompt: Option<&str>,
+    ) -> Result<TranscriptionResult> {
+        let PreparedAudio {
+            audio,
+            sample_rate,
+            channels,
+            duration_secs,
+            gain_db,
+        } = prepared;
+
+        let mode = self.mode(user_prompt);
+        let mut result = TranscriptionResult {
+            text: String::new(),
+            audio_duration_secs: duration_secs,
+            effective_duration_secs: 0.0,
+            inference_duration: Duration::ZERO,
+            sample_rate,
+            channels,
+            mode,
+            language: self.language.clone(),
+            segments: None,
+            tracks: None,
+            interrupted: false,
+            usage: Usage::default(),
+            load_memory: None,
+            raw_text: None,
+            censored_words: None,
+            normalization_gain_db: gain_db,
+            warm_up_secs: None,
+            dumped_audio: None,
+            vocal_check: None,
+            reference_text: self.reference.clone(),
+            tags: None,
+        };
+
+        if self.vocal_check != VocalCheck::Off && !audio.samples.is_empty() {
+            let verdict = self.check_vocals(&audio, &mut result.usage).await?;
+            let skip = verdict.as_ref().is_some_and(|v| v.skipped);
+            result.vocal_check = verdict;
+            if skip {
+                self.finish_result(&mut result);
+                return Ok(result);
+            }
+        }
+
+        let Some(threshold) = self.vad_threshold_dbfs else {
+            result.effective_duration_secs =
+                audio_prep::duration_secs(&audio.samples, audio.sample_rate, audio.channels);
+            if let Some(dump) = &self.dump_audio {
+                result.dumped_audio = Some(vec![dump.write(&audio)?.display().to_string()]);
+            }
+            // Empty when everything was trimmed as silence; nothing for the
+            // model to hear.
+            if !audio.samples.is_empty() {
+                let start = Instant::now();
+                let request =
+                    self.request_text(vec![audio], user_prompt, mode, self.language.as_deref());
+                let (text, usage) =
+                    interrupt::unless_cancelled(self.cancel.as_ref(), request).await?;
+                result.text = text;
+                result.usage = usage;
+                result.inference_duration = start.elapsed();
+            }
+            if self.inline_timestamps() && !result.text.is_empty() {
+                match inline_timestamps::parse_segments(&result.text, duration_secs) {
+                    Some(segments) => {
+                        result.text = joined_text(&segments);
+                        result.segments = Some(segments);
+                    }
+                    None => tracing::warn!(
+                        "the model wrote no usable [mm:ss] markers; \
+                         the transcript is timed as a single segment"
+                    ),
+                }
+            }
+            self.finish_result(&mut result);
+            return Ok(result);
+        };
+
+        let rate = audio.sample_rate;
+        let stride = usize::from(audio.channels.max(1));
+        let mut segments = Vec::new();
+        let mut dumped = Vec::new();
+        for (i, range) in speech_ranges(&audio, threshold).into_iter().enumerate() {
+            if self.cancel.as_ref().is_some_and(|c| c.is_cancelled()) {
+                result.interrupted = true;
+                break;
+            }
+            let slice = AudioInput {
+                samples: audio.samples[range.start * stride..range.end * stride].to_vec(),
+                sample_rate: rate,
+                channels: audio.channels,
+            };
+            if let Some(dump) = &self.dump_audio {
+                dumped.push(dump.write_chunk(i + 1, &slice)?.display().to_string());
+            }
+            let start = Instant::now();
+            let language = if self.detect_language {
+                match self.request_language(slice.clone()).await {
+                    Ok((language, usage)) => {
+                        result.usage += usage;
+                        language
+                    }
+                    Err(err) if timeout::is_timed_out(&err) => {
+                        tracing::warn!(
+                            "language detection for segment {:.1}s–{:.1}s skipped: {err}",
+                            range.start as f64 / rate as f64,
+                            range.end as f64 / rate as f64,
+                        );
+                        None
+                    }
+                    Err(err) => return Err(err),
+                }
+            } else {
+                None
+            };
+            let hint = language.as_deref().or(self.language.as_deref());
+            let text = match self
+                .request_text(vec![slice], user_prompt, mode, hint)
+                .await
+            {
+                Ok((text, usage)) => {
+                    result.usage += usage;
+                    text
+                }
+                Err(err) if timeout::is_timed_out(&err) => {
+                    result.inference_duration += start.elapsed();
+                    tracing::warn!(
+                        "segment {:.1}s–{:.1}s skipped: {err}",
+                        range.start as f64 / rate as f64,
+                        range.end as f64 / rate as f64,
+                    );
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+            result.inference_duration += start.elapsed();
+            result.effective_duration_secs += range.len() as f64 / rate as f64;
+            segments.push(Segment {
+                start_secs: range.start as f64 / rate as f64,
+                end_secs: range.end as f64 / rate as f64,
+                text,
+                language,
+            });
+        }
+
+        result.text = joined_text(&segments);
+        result.segments = Some(segments);
+        result.dumped_audio = self.dump_audio.is_some().then_some(dumped);
+        self.finish_result(&mut result);
+        Ok(result)
+    }
+
+    /// Apply `--text-format`, then `--censor`, so the raw text is censored
+    /// too.
+    fn finish_result(&self, result: &mut TranscriptionResult) {
+        if let Some(lyrics) = &self.lyrics {
+            result.format_lyrics(lyrics);
+        }
+        if let Some(censor) = &self.censor {
+            result.censor(censor);
+        }
+    }
+
+    /// Send one audio clip (or several tracks heard together) to the model
+    /// and return the trimmed reply with the request's token usage.
+    async fn request_text(
+        &self,
+        audio: Vec<AudioInput>,
+        user_prompt: Option<&str>,
+        mode: TranscriptionMode,
+        language: Option<&str>,
+    ) -> Result<(String, Usage)> {
+        let user_text = user_text(user_prompt, mode, audio.len() > 1);
+        let request = self
+            .sampler
+            .apply(RequestBuilder::new())
+            .add_message(TextMessageRole::System, self.system_message(mode, language))
+            .add_audio_message(TextMessageRole::User, user_text, audio, &self.model)?;
+
+        let _permit = self.queue.acquire().await;
+        let start = Instant::now();
+        let response = timeout::within(
+            self.timeout,
+            TRANSCRIBE_STAGE,
+            self.model.send_chat_request(request),
+        )
+        .await?;
+        let usage = Usage::from_response(&response.usage, start.elapsed());
+        let text = response.choices[0]
+            .message
+            .content
+            .as_ref()
+            .map(|c| self.sampler.trim_stop(c).trim().to_string())
+            .unwrap_or_default();
+        Ok((text, usage))
+    }
+
+    /// Send a throwaway one-token request with [`WARM_UP_SECS`] of silence,
+    /// so kernel compilation and cache setup for the audio encoder happen
+    /// now rather than in the first timed transcription.  Returns how long
+    /// it took.
+    pub async fn warm_up(&self) -> Result<Duration, Error> {
+        let clip = audio_prep::silence(WARM_UP_SECS, audio_prep::TARGET_SAMPLE_RATE);
+        let request = RequestBuilder::new()
+            .set_sampler_temperature(0.0)
+            .set_sampler_max_len(1)
+            .add_message(TextMessageRole::System, &self.system_prompt)
+            .add_audio_message(
+                TextMessageRole::User,
+                DEFAULT_USER_PROMPT,
+                vec![clip],
+                &self.model,
+            )
+            .map_err(|source| Error::generation("Warm-up", source))?;
+
+        let _permit = self.queue.acquire().await;
+        let start = Instant::now();
+        let request = timeout::within(
+            self.timeout,
+            "Warm-up",
+            self.model.send_chat_request(request),
+        );
+        interrupt::unless_cancelled(self.cancel.as_ref(), request)
+            .await
+            .map_err(|source| Error::generation("Warm-up", source))?;
+        Ok(start.elapsed())
+    }
+
+    /// Ask which language one audio clip is in, as an ISO 639-1 code
+    /// (`None` if the reply names no known language).
+    async fn request_language(&self, audio: AudioInput) -> Result<(Option<String>, Usage)> {
+        let request = RequestBuilder::new()
+            .set_sampler_temperature(0.0)
+            .set_sampler_max_len(24)
+            .add_message(TextMessageRole::System, LANGUAGE_DETECTION_SYSTEM_PROMPT)
+            .add_audio_message(
+                TextMessageRole::User,
+                LANGUAGE_DETECTION_USER_PROMPT,
+                vec![audio],
+                &self.model,
+            )?;
+
+        let _permit = self.queue.acquire().await;
+        let start = Instant::now();
+        let response = timeout::within(
+            self.timeout,
+            "Language detection",
+            self.model.send_chat_request(request),
+        )
+        .await?;
+        let usage = Usage::from_response(&response.usage, start.elapsed());
+        let answer = response.choices[0]
+            .message
+            .content
+            .as_deref()
+            .unwrap_or_default();
+        Ok((normalize_language(answer), usage))
+    }
+
+    /// Ask whether the start of `audio` has a voice in it, adding the
+    /// request's tokens to `usage`.  `None` when the request timed out,
+    /// which only warns: the transcription goes ahead.
+    async fn check_vocals(
+        &self,
+        audio: &AudioInput,
+        usage: &mut Usage,
+    ) -> Result<Option<VocalVerdict>> {
+        let excerpt = vocal_check_excerpt(audio);
+        let excerpt_secs =
+            audio_prep::duration_secs(&excerpt.samples, excerpt.sample_rate, excerpt.channels);
+        let request = RequestBuilder::new()
+            .set_sampler_temperature(0.0)
+            .set_sampler_max_len(32)
+            .add_message(TextMessageRole::System, VOCAL_CHECK_SYSTEM_PROMPT)
+            .add_audio_message(
+                TextMessageRole::User,
+                VOCAL_CHECK_USER_PROMPT,
+                vec![excerpt],
+                &self.model,
+            )?;
+
+        let _permit = self.queue.acquire().await;
+        let start = Instant::now();
+        let request = timeout::within(
+            self.timeout,
+            "Vocal check",
+            self.model.send_chat_request(request),
+        );
+        let response = match interrupt::unless_cancelled(self.cancel.as_ref(), request).await {
+            Ok(response) => response,
+            Err(err) if timeout::is_timed_out(&err) => {
+                tracing::warn!("vocal check skipped: {err}");
+                return Ok(None);
+            }
+            Err(err) => return Err(err),
+        };
+        *usage += Usage::from_response(&response.usage, start.elapsed());
+        let answer = response.choices[0]
+            .message
+            .content
+            .as_deref()
+            .unwrap_or_default()
+            .trim()
+            .to_string();
+        let vocals = parse_vocal_answer(&answer);
+        Ok(Some(VocalVerdict {
+            skipped: vocals == Some(false) && self.vocal_check == VocalCheck::Skip,
+            vocals,
+            answer,
+            excerpt_secs,
+        }))
+    }
+
+    /// Start a follow-up conversation about `audio`, which was just
+    /// transcribed as `transcript` with `user_prompt`.
+    ///
+    /// The session opens with the same system prompt and instruction the
+    /// transcription request sent, so the model sees the audio and its own
+    /// transcript before the first question.
+    pub fn session(
+        &self,
+        audio: Vec<AudioInput>,
+        user_prompt: Option<&str>,
+        transcript: &str,
+    ) -> Result<TranscriptionSession> {
+        let mode = self.mode(user_prompt);
+        let instruction = user_text(user_prompt, mode, audio.len() > 1);
+        let session = TranscriptionSession::new(
+            self.shared_model(),
+            &self.effective_system_prompt(user_prompt),
+            audio,
+            instruction,
+            transcript,
+        )?;
+        Ok(match self.timeout {
+            Some(limit) => session.with_timeout(limit),
+            None => session,
+        })
+    }
+
+    /// Return a reference to the underlying `Model`.
+    pub fn model(&self) -> &Model {
+        &self.model
+    }
+
+    /// Return a shared handle to the underlying `Model`.
+    pub fn shared_model(&self) -> Arc<Model> {
+        self.model.clone()
+    }
+}
+
+/// The instruction sent with the audio: `user_prompt`, or the default for
+/// the mode and the number of tracks.
+fn user_text(user_prompt: Option<&str>, mode: TranscriptionMode, tracks: bool) -> &str {
+    match (user_prompt, mode) {
+        (Some(prompt), _) => prompt,
+        (None, TranscriptionMode::Translate) if tracks => TRACKS_TRANSLATION_USER_PROMPT,
+        (None, TranscriptionMode::Translate) => TRANSLATION_USER_PROMPT,
+        (None, TranscriptionMode::Transcribe) if tracks => TRACKS_USER_PROMPT,
+        (None, TranscriptionMode::Transcribe) => DEFAULT_USER_PROMPT,
+    }
+}
+
+/// The transcript made of `segments`: their texts, one per line.
+pub(crate) fn joined_text(segments: &[Segment]) -> String {
+    segments
+        .iter()
+        .map(|seg| seg.text.as_str())
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Speech spans of `audio` found by VAD, as frame (per-channel sample)
+/// ranges.
+/// Up to [`VOCAL_CHECK_SECS`] of `audio`, starting at its first frame above
+/// the default silence threshold (the whole clip when it is all silence).
+fn vocal_check_excerpt(audio: &AudioInput) -> AudioInput {
+    let stride = usize::from(audio.channels.max(1));
+    let mono = audio_prep::downmix_to_mono(&audio.samples, audio.channels);
+    let start = audio_prep::non_silent_range(
+        &mono,
+        audio.sample_rate,
+        audio_prep::DEFAULT_SILENCE_THRESHOLD_DBFS,
+    )
+    .start;
+    let len = (VOCAL_CHECK_SECS * audio.sample_rate as f64) as usize;
+    let end = (start + len).min(mono.len());
+    AudioInput {
+        samples: audio.samples[start * stride..end * stride].to_vec(),
+        sample_rate: audio.sample_rate,
+        channels: audio.channels,
+    }
+}
+
+fn speech_ranges(audio: &AudioInput, threshold_dbfs: f32) -> Vec<Range<usize>> {
+    let mono = audio_prep::downmix_to_mono(&audio.samples, audio.channels);
+    audio_prep::detect_speech(&mono, audio.sample_rate, threshold_dbfs)
+}
+
+// ── PreparedAudio ───────────────────────────────────────────────────────────
+
+/// Decoded audio after preprocessing, with the format and length of the
+/// original that the result reports.
+pub struct PreparedAudio {
+    audio: AudioInput,
+    sample_rate: u32,
+    channels: u16,
+    duration_secs: f64,
+    /// Gain `--normalize` applied, in dB.
+    gain_db: Option<f32>,
+}
+
+impl PreparedAudio {
+    /// Run `prep` on `audio`; `None` keeps it as decoded.  With
+    /// `keep_timing` (VAD or inline timestamps) the silence trim is
+    /// skipped, since trimming would shift the timestamps.
+    pub fn new(audio: AudioInput, prep: Option<&PrepOptions>, keep_timing: bool) -> Self {
+        let sample_rate = audio.sample_rate;
+        let channels = audio.channels;
+        let duration_secs = audio_prep::duration_secs(&audio.samples, sample_rate, channels);
+        let (audio, gain_db) = match prep {
+            Some(prep) if keep_timing => audio_prep::preprocess(
+                audio,
+                &PrepOptions {
+                    silence_threshold_dbfs: None,
+                    ..*prep
+                },
+            ),
+            Some(prep) => audio_prep::preprocess(audio, prep),
+            None => (audio, None),
+        };
+        Self {
+            audio,
+            sample_rate,
+            channels,
+            duration_secs,
+            gain_db,
+        }
+    }
+}
+
+// ── TranscriptionResult ──────────────────────────────────────────────────────
+
+/// A timed span of the transcription.  Deserializes from the JSON output,
+/// so `retranscribe` can read it back.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Segment {
+    /// Start of the span, in seconds from the beginning of the audio.
+    pub start_secs: f64,
+    /// End of the span, in seconds from the beginning of the audio.
+    pub end_secs: f64,
+    /// Text transcribed for this span.
+    pub text: String,
+    /// ISO 639-1 code found by `--detect-language`, if it was enabled and
+    /// the model named a known language.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+}
+
+/// One input of a multi-track transcription.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TrackInfo {
+    /// The file, or "stdin".
+    pub source: String,
+    /// Sample rate as decoded, before any resampling.
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub duration_secs: f64,
+}
+
+impl TrackInfo {
+    fn new(source: &str, audio: &AudioInput) -> Self {
+        Self {
+            source: source.to_string(),
+            sample_rate: audio.sample_rate,
+            channels: audio.channels,
+            duration_secs: audio_prep::duration_secs(
+                &audio.samples,
+                audio.sample_rate,
+                audio.channels,
+            ),
+        }
+    }
+}
+
+/// Counts parsed from a transcript, for judging how much of it the model
+/// was unsure about.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct TranscriptStats {
+    /// `[inaudible]` markers.  The model also writes `[Inaudible]` or
+    /// `(inaudible)`, so case and bracket style don't matter.
+    pub inaudible: usize,
+    /// Words, not counting the markers.
+    pub words: usize,
+    /// Non-empty lines.
+    pub lines: usize,
+}
+
+impl TranscriptStats {
+    pub fn from_text(text: &str) -> Self {
+        let (inaudible, rest) = strip_inaudible(text);
+        Self {
+            inaudible,
+            words: rest
+                .split_whitespace()
+                .filter(|w| w.chars().any(char::is_alphanumeric))
+                .count(),
+            lines: text.lines().filter(|l| !l.trim().is_empty()).count(),
+        }
+    }
+
+    /// Share of the transcript (markers counted as one word each) that was
+    /// flagged inaudible.
+    pub fn inaudible_ratio(&self) -> f64 {
+        let total = self.words + self.inaudible;
+        if total == 0 {
+            0.0
+        } else {
+            self.inaudible as f64 / total as f64
+        }
+    }
+
+    /// A short verdict, e.g. "some passages unclear".
+    pub fn confidence_note(&self) -> &'static str {
+        match (self.inaudible, self.words) {
+            (0, 0) => "nothing transcribed",
+            (0, _) => "no sections flagged as inaudible",
+            (_, 0) => "only inaudible sections",
+            _ if self.inaudible_ratio() < 0.02 => "mostly clear",
+            _ if self.inaudible_ratio() < 0.1 => "some passages unclear",
+            _ => "low confidence: many inaudible sections",
+        }
+    }
+
+    /// One line, e.g. "3 inaudible sections / 412 words".
+    pub fn summary(&self) -> String {
+        let sections = if self.inaudible == 1 {
+            "section"
+        } else {
+            "sections"
+        };
+        let words = if self.words == 1 { "word" } else { "words" };
+        format!(
+            "{} inaudible {sections} / {} {words}",
+            self.inaudible, self.words
+        )
+    }
+}
+
+/// Count and remove `[inaudible]` / `(inaudible)` markers (any case, extra
+/// text after the word allowed, e.g. `[inaudible 0:12]`).  Other bracketed
+/// spans are left in place.
+fn strip_inaudible(text: &str) -> (usize, String) {
+    let mut count = 0;
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(open) = rest.find(['[', '(']) {
+        let close = if rest[open..].starts_with('[') {
+            ']'
+        } else {
+            ')'
+        };
+        out.push_str(&rest[..open]);
+        let inner = &rest[open + 1..];
+        match inner.find(close) {
+            Some(end) if is_inaudible_tag(&inner[..end]) => {
+                count += 1;
+                // Keep words on either side of the marker apart.
+                out.push(' ');
+                rest = &inner[end + 1..];
+            }
+            _ => {
+                out.push_str(&rest[open..open + 1]);
+                rest = inner;
+            }
+        }
+    }
+    out.push_str(rest);
+    (count, out)
+}
+
+fn is_inaudible_tag(tag: &str) -> bool {
+    tag.trim().to_lowercase().starts_with("inaudible")
+}
+
+/// The output of a transcription, including the text and timing metadata.
+#[derive(Serialize)]
+pub struct TranscriptionResult {
+    /// The transcribed text.
+    pub text: String,
+    /// Duration of the input audio in seconds.
+    pub audio_duration_secs: f64,
+    /// Duration actually sent to the model: after silence trimming, or the
+    /// total length of the speech segments with VAD.  Equal to
+    /// `audio_duration_secs` when preprocessing is disabled.
+    pub effective_duration_secs: f64,
+    /// Wall-clock time the model spent generating the transcription.
+    #[serde(rename = "inference_secs", serialize_with = "serialize_secs")]
+    pub inference_duration: Duration,
+    /// Sample rate of the input audio (before any resampling by the model).
     pub sample_rate: u32,
     /// Number of channels in the input audio.
     pub channels: u16,
+    /// Whether `text` is a transcript or an English translation.
+    pub mode: TranscriptionMode,
+    /// The `--language` hint given to the model, if any.
+    pub language: Option<String>,
+    /// Timed segments, populated when the audio was transcribed per VAD
+    /// segment (empty if no speech was detected) or split on the model's
+    /// inline `[mm:ss]` markers.  `None` means the whole file was
+    /// transcribed in a single request without usable markers.
+    pub segments: Option<Vec<Segment>>,
+    /// The inputs of a multi-track request, in order; `None` for a single
+    /// file.  `sample_rate` and `channels` above are the first track's.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tracks: Option<Vec<TrackInfo>>,
+    /// Cancelled part-way through: `text` and `segments` only cover the
+    /// segments finished before that.
+    pub interrupted: bool,
+    /// Tokens processed and generated, summed over every request (one per
+    /// VAD segment).
+    pub usage: Usage,
+    /// Memory in use once the model was loaded; set by the CLI, which
+    /// knows when the load happened.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub load_memory: Option<LoadMemory>,
+    /// The text as the model wrote it, before `--text-format` reshaped
+    /// `text`; `None` when no format was applied.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw_text: Option<String>,
+    /// Words masked by `--censor`; `None` when censoring was off.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub censored_words: Option<usize>,
+    /// Gain `--normalize` applied to the audio, in dB; `None` when it was
+    /// off, the audio was already within 1 dB of the target, or several
+    /// tracks were sent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub normalization_gain_db: Option<f32>,
+    /// Seconds the warm-up request took before this transcription, not
+    /// counted in `inference_secs`; `None` when there was no warm-up, so
+    /// the timings include first-request overhead.  Set by the CLI.
+    pub warm_up_secs: Option<f64>,
+    /// WAV files `--dump-audio` wrote, one per request or chunk; `None`
+    /// when it was off.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dumped_audio: Option<Vec<String>>,
+    /// The vocal pre-check's answer; `None` when it was off (or timed
+    /// out).  When it heard no voice, `text` is empty.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vocal_check: Option<VocalVerdict>,
+    /// The `--lyrics-hint` sent with the audio, without its text; `None`
+    /// when there was none.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reference_text: Option<ReferenceText>,
+    /// The input's title/artist tags, for the LRC header; `None` unless
+    /// the CLI read them with [`read_tags`](Self::read_tags).
+    #[serde(skip)]
+    pub tags: Option<SongTags>,
 }
 
 impl TranscriptionResult {
-    /// Real-time factor: `inference_time / audio_duration`.
+    /// Real-time factor: `inference_time / effective_duration`, i.e. measured
+    /// against the audio the model actually processed.
     ///
     /// Values below 1.0 mean the model transcribes faster than real-time.
     pub fn real_time_factor(&self) -> f64 {
-        if self.audio_duration_secs > 0.0 {
-            self.inference_duration.as_secs_f64() / self.audio_duration_secs
+        if self.effective_duration_secs > 0.0 {
+            self.inference_duration.as_secs_f64() / self.effective_duration_secs
         } else {
             f64::INFINITY
         }
     }
+
+    /// Whether the vocal pre-check heard no voice, so nothing was
+    /// transcribed.
+    pub fn skipped_no_vocals(&self) -> bool {
+        self.vocal_check.as_ref().is_some_and(|v| v.skipped)
+    }
+
+    /// The VAD segments, oldest first; empty when the file was transcribed
+    /// in a single request.
+    pub fn segments(&self) -> &[Segment] {
+        self.segments.as_deref().unwrap_or_default()
+    }
+
+    /// Languages found by `--detect-language`, in order of first
+    /// appearance, each with the seconds of speech it covers.  Empty when
+    /// detection was off.
+    pub fn detected_languages(&self) -> Vec<(&str, f64)> {
+        let mut languages: Vec<(&str, f64)> = Vec::new();
+        for seg in self.segments() {
+            let Some(language) = seg.language.as_deref() else {
+                continue;
+            };
+            let secs = seg.end_secs - seg.start_secs;
+            match languages.iter_mut().find(|(code, _)| *code == language) {
+                Some((_, total)) => *total += secs,
+                None => languages.push((language, secs)),
+            }
+        }
+        languages
+    }
+
+    /// Reformat `text` and each segment's text with `lyrics`, moving the
+    /// original text to [`raw_text`](Self::raw_text).
+    ///
+    /// Segments are formatted one by one, so lines are only merged within
+    /// a segment and SRT / VTT timings still hold.
+    pub fn format_lyrics(&mut self, lyrics: &LyricsFormatter) {
+        let formatted = lyrics.apply(&self.text);
+        let raw = std::mem::replace(&mut self.text, formatted);
+        self.raw_text.get_or_insert(raw);
+        for seg in self.segments.iter_mut().flatten() {
+            seg.text = lyrics.apply(&seg.text);
+        }
+    }
+
+    /// Mask the words `censor` lists in `text`, the raw text and each
+    /// segment, and record how many were masked.
+    ///
+    /// Masking keeps every word's length, so line breaks and segment
+    /// timings are untouched and SRT / VTT cues stay aligned.
+    pub fn censor(&mut self, censor: &Censor) {
+        let (text, count) = censor.apply(&self.text);
+        self.text = text;
+        if let Some(raw) = &mut self.raw_text {
+            *raw = censor.apply(raw).0;
+        }
+        for seg in self.segments.iter_mut().flatten() {
+            seg.text = censor.apply(&seg.text).0;
+        }
+        *self.censored_words.get_or_insert(0) += count;
+    }
+
+    /// Inaudible-marker, word and line counts for `text`.
+    pub fn stats(&self) -> TranscriptStats {
+        TranscriptStats::from_text(&self.text)
+    }
+
+    /// Timed segments for subtitle output.
+    ///
+    /// Falls back to a single segment spanning the whole audio when the file
+    /// was transcribed in one request.
+    pub fn subtitle_segments(&self) -> Vec<Segment> {
+        match &self.segments {
+            Some(segments) => segments.clone(),
+            None => vec![Segment {
+                start_secs: 0.0,
+                end_secs: self.audio_duration_secs,
+                text: self.text.clone(),
+                language: None,
+            }],
+        }
+    }
+
+    /// Render as SubRip subtitles.
+    pub fn to_srt(&self) -> String {
+        transcript_output::format_srt(&self.subtitle_segments())
+    }
+
+    /// Render as WebVTT subtitles.
+    pub fn to_vtt(&self) -> String {
+        transcript_output::format_vtt(&self.subtitle_segments())
+    }
+
+    /// Read `path`'s tags for the LRC header.  A file whose tags can't be
+    /// read only gets a warning: the lyrics are written without them.
+    pub fn read_tags(&mut self, path: &Path) {
+        if path.as_os_str() == "-" {
+            return;
+        }
+        match song_tags::read(path) {
+            Ok(tags) => self.tags = Some(tags),
+            Err(err) => tracing::warn!("LRC header left without tags: {err:#}"),
+        }
+    }
+
+    /// Render as LRC lyrics, with `[ti:]` / `[ar:]` from [`tags`](Self::tags)
+    /// and `[length:]` from the audio duration.
+    ///
+    /// A transcript without timed segments has its lines spread evenly over
+    /// the audio instead, with a warning, since the times are only a guess.
+    pub fn to_lrc(&self) -> String {
+        let segments = match &self.segments {
+            Some(segments) => segments.clone(),
+            None => {
+                tracing::warn!(
+                    "the transcript has no timestamps; LRC lines are spread evenly \
+                     over the audio (use --vad or --timestamps inline for real timing)"
+                );
+                transcript_output::spread_lines(&self.text, self.audio_duration_secs)
+            }
+        };
+        let tags = self.tags.clone().unwrap_or_default();
+        let header = LrcHeader {
+            title: tags.title,
+            artist: tags.artist,
+            length_secs: Some(self.audio_duration_secs),
+        };
+        transcript_output::format_lrc(&segments, &header)
+    }
+
+    /// Serialize the full result, including the real-time factor, as pretty
+    /// JSON.
+    pub fn to_json(&self) -> Result<String> {
+        #[derive(Serialize)]
+        struct JsonResult<'a> {
+            #[serde(flatten)]
+            result: &'a TranscriptionResult,
+            real_time_factor: f64,
+            stats: TranscriptStats,
+            confidence_note: &'static str,
+        }
+
+        let stats = self.stats();
+        Ok(serde_json::to_string_pretty(&JsonResult {
+            result: self,
+            real_time_factor: self.real_time_factor(),
+            stats,
+            confidence_note: stats.confidence_note(),
+        })?)
+    }
+
+    /// Render in the requested output format.
+    pub fn render(&self, format: OutputFormat) -> Result<String> {
+        Ok(match format {
+            OutputFormat::Text => self.text.clone(),
+            OutputFormat::Srt => self.to_srt(),
+            OutputFormat::Vtt => self.to_vtt(),
+            OutputFormat::Lrc => self.to_lrc(),
+            OutputFormat::Json => self.to_json()?,
+        })
+    }
+}
+
+fn serialize_secs<S: serde::Serializer>(d: &Duration, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_f64(d.as_secs_f64())
 }
 
 impl fmt::Display for TranscriptionResult {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        writeln!(f, "── Transcription ──")?;
+        match self.mode {
+            TranscriptionMode::Transcribe => writeln!(f, "── Transcription ──")?,
+            TranscriptionMode::Translate => writeln!(f, "── Translation ────")?,
+        }
         writeln!(f, "{}", self.text)?;
         writeln!(f, "───────────────────")?;
         writeln!(
@@ -265,63 +1994,726 @@ impl fmt::Display for TranscriptionResult {
             "Audio duration : {:.1}s ({} Hz, {} ch)",
             self.audio_duration_secs, self.sample_rate, self.channels,
         )?;
+        for track in self.tracks.iter().flatten() {
+            writeln!(
+                f,
+                "Track          : {} — {:.1}s ({} Hz, {} ch)",
+                track.source, track.duration_secs, track.sample_rate, track.channels
+            )?;
+        }
+        if self.effective_duration_secs < self.audio_duration_secs {
+            writeln!(f, "After trimming : {:.1}s", self.effective_duration_secs)?;
+        }
         writeln!(
             f,
             "Inference time : {}",
             fmt_duration(self.inference_duration),
         )?;
-        write!(f, "Real-time factor: {:.2}x", self.real_time_factor())
+        if let Some(language) = &self.language {
+            writeln!(f, "Language       : {}", language_name(language))?;
+        }
+        let detected = self.detected_languages();
+        if !detected.is_empty() {
+            let list = detected
+                .iter()
+                .map(|(code, secs)| format!("{} {secs:.1}s", language_name(code)))
+                .collect::<Vec<_>>()
+                .join(", ");
+            writeln!(f, "Languages      : {list}")?;
+        }
+        if let Some(secs) = self.warm_up_secs {
+            writeln!(f, "Warm-up        : {secs:.1}s (not in the timings)")?;
+        }
+        if let Some(verdict) = &self.vocal_check {
+            writeln!(f, "Vocal check    : {verdict}")?;
+        }
+        if let Some(reference) = &self.reference_text {
+            writeln!(f, "Lyrics hint    : {reference}")?;
+        }
+        writeln!(f, "Real-time factor: {:.2}x", self.real_time_factor())?;
+        writeln!(f, "Tokens         : {}", self.usage)?;
+        if let Some(count) = self.censored_words {
+            writeln!(f, "Censored       : {count} words")?;
+        }
+        match self.dumped_audio.as_deref() {
+            Some([path]) => writeln!(f, "Model input    : {path}")?,
+            Some(paths @ [first, .., last]) => writeln!(
+                f,
+                "Model input    : {} files, {first} … {last}",
+                paths.len()
+            )?,
+            _ => {}
+        }
+        let stats = self.stats();
+        write!(
+            f,
+            "Quality        : {} ({})",
+            stats.summary(),
+            stats.confidence_note()
+        )
     }
 }
 
-// ── Helpers ──────────────────────────────────────────────────────────────────
+// ── Standalone CLI entry-point ───────────────────────────────────────────────
+
+/// Transcription settings chosen on the command line.
+#[derive(Clone, Debug, Default)]
+pub struct TranscribeOptions {
+    /// Custom instruction sent alongside the audio.
+    pub user_prompt: Option<String>,
+    /// Custom system prompt (`None` = the built-in transcription prompt).
+    pub system_prompt: Option<String>,
+    /// Language hint (ISO 639-1 code or name).
+    pub language: Option<String>,
+    /// Translate into English instead of transcribing verbatim.
+    pub translate: bool,
+    /// Preprocessing before inference; `None` for `--no-preprocess`.
+    pub preprocess: Option<PrepOptions>,
+    /// VAD speech threshold (dBFS) for `--vad`; `None` transcribes in one
+    /// request.
+    pub vad_threshold: Option<f32>,
+    /// `--detect-language`: find each VAD segment's language first.
+    pub detect_language: bool,
+    /// `--no-check-vocals` / `--force`: whether to ask if there is a voice
+    /// first, and whether to stop when there isn't.
+    pub vocal_check: VocalCheck,
+    /// `--timestamps`: where segment timings come from without VAD.
+    pub timestamps: Option<TimestampMode>,
+    /// `--text-format lyrics`: reshape the transcript as lyrics.
+    pub lyrics: Option<LyricsFormatter>,
+    /// `--censor`: mask these words in the transcript.
+    pub censor: Option<Censor>,
+    /// `--lyrics-hint`: approximate lyrics, already cut to
+    /// `--lyrics-hint-tokens`.
+    pub reference: Option<ReferenceText>,
+    /// `--tracks`: send every input in one request as tracks of one
+    /// recording, rather than transcribing each file on its own.
+    pub tracks: bool,
+    /// `--resample-tracks`: resample tracks with mismatched rates instead
+    /// of rejecting them.
+    pub resample_tracks: bool,
+    /// `--interactive`: after the transcript, take questions about the
+    /// audio in a [`TranscriptionSession`].
+    pub interactive: bool,
+    /// How the result is rendered.
+    pub output_format: OutputFormat,
+    /// Write the rendered result to this file instead of stdout.
+    pub output: Option<PathBuf>,
+    /// Treat the input as headerless PCM with this layout instead of
+    /// letting symphonia probe it.
+    pub raw_pcm: Option<RawPcm>,
+    /// `--dump-audio`: write what the model hears to WAV.
+    pub dump_audio: Option<AudioDump>,
+    /// `--warmup`: send a throwaway request after loading so the timings
+    /// leave out first-request overhead.
+    pub warmup: bool,
+    /// `--top-k` / `--repeat-penalty` / `--presence-penalty` / `--stop`,
+    /// applied on top of [`DEFAULT_SAMPLER`].
+    pub decoding: DecodingArgs,
+}
+
+/// Read and decode the audio to transcribe: stdin for `-`, otherwise the
+/// file, as `raw_pcm` when set.
+/// Failures are [`Error::AudioDecode`].
+pub fn decode_input(audio_path: &Path, raw_pcm: Option<RawPcm>) -> Result<AudioInput> {
+    read_input(audio_path)
+        .and_then(|bytes| match raw_pcm {
+            Some(layout) => layout.decode(&bytes),
+            None => AudioInput::from_bytes(&bytes),
+        })
+        .map_err(|source| Error::audio_decode(audio_path, source).into())
+}
 
-/// Format a `Duration` as `Xm Ys` (e.g. "2m 30.5s") or just `Ys` when under
-/// a minute.
-fn fmt_duration(d: Duration) -> String {
-    let total_secs = d.as_secs_f64();
-    let mins = (total_secs / 60.0).floor() as u64;
-    let secs = total_secs - (mins as f64 * 60.0);
-    if mins > 0 {
-        format!("{}m {:.1}s", mins, secs)
+/// Probe each of `paths` with [`audio_probe::probe_audio`] so a file that
+/// can't be decoded fails before anything is read in full.  stdin and
+/// `--raw-pcm` input can't be probed and give `None`.  Warns about a
+/// truncated file, which still decodes.
+pub fn probe_inputs(paths: &[PathBuf], raw_pcm: Option<RawPcm>) -> Result<Vec<Option<AudioProbe>>> {
+    paths
+        .iter()
+        .map(|path| {
+            if raw_pcm.is_some() || path.as_os_str() == "-" {
+                return Ok(None);
+            }
+            let probe = audio_probe::probe_audio(path)?;
+            if probe.truncated {
+                tracing::warn!(
+                    "{} is shorter than its header says; only the audio that is there will be transcribed",
+                    path.display()
+                );
+            }
+            Ok(Some(probe))
+        })
+        .collect()
+}
+
+/// Decode each of `paths` as one track of a multi-track request, naming
+/// the file that fails.  stdin can only be a track on its own.
+pub fn decode_tracks(
+    paths: &[PathBuf],
+    raw_pcm: Option<RawPcm>,
+) -> Result<Vec<(String, AudioInput)>> {
+    if paths.len() > 1 && paths.iter().any(|path| path.as_os_str() == "-") {
+        anyhow::bail!("stdin (`-`) can only be transcribed on its own");
+    }
+    paths
+        .iter()
+        .map(|path| {
+            let audio = decode_input(path, raw_pcm)?;
+            Ok((source_name(path), audio))
+        })
+        .collect()
+}
+
+/// Fail unless every track has the same sample rate, listing them.
+pub fn check_track_rates(tracks: &[(String, AudioInput)]) -> Result<()> {
+    let Some((_, first)) = tracks.first() else {
+        return Ok(());
+    };
+    if tracks
+        .iter()
+        .all(|(_, audio)| audio.sample_rate == first.sample_rate)
+    {
+        return Ok(());
+    }
+    let rates = tracks
+        .iter()
+        .map(|(source, audio)| format!("{source} at {} Hz", audio.sample_rate))
+        .collect::<Vec<_>>()
+        .join(", ");
+    anyhow::bail!(
+        "The tracks have different sample rates ({rates}); pass --resample-tracks to \
+         resample them to the first track's rate, or drop --no-preprocess"
+    )
+}
+
+/// How `source` is shown in messages: "stdin" for `-`.
+fn source_name(audio_path: &Path) -> String {
+    if audio_path.as_os_str() == "-" {
+        "stdin".to_string()
     } else {
-        format!("{:.1}s", secs)
+        audio_path.display().to_string()
     }
 }
 
-// ── Standalone CLI entry-point ───────────────────────────────────────────────
+/// Read the audio to transcribe: stdin for `-`, otherwise the file.
+fn read_input(audio_path: &Path) -> Result<Vec<u8>> {
+    if audio_path.as_os_str() == "-" {
+        let mut bytes = Vec::new();
+        std::io::stdin()
+            .lock()
+            .read_to_end(&mut bytes)
+            .context("Failed to read audio from stdin")?;
+        if bytes.is_empty() {
+            anyhow::bail!("No audio on stdin");
+        }
+        return Ok(bytes);
+    }
+    if !audio_path.exists() {
+        anyhow::bail!("Audio file not found: {}", audio_path.display());
+    }
+    std::fs::read(audio_path)
+        .with_context(|| format!("Failed to read audio file: {}", audio_path.display()))
+}
+
+// ── Dry run ──────────────────────────────────────────────────────────────────
+
+/// How a file is split into model requests.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Chunking {
+    /// One request for the whole file.
+    Whole {
+        /// Seconds sent, after silence trimming.
+        secs: f64,
+        /// The model is asked for `[mm:ss]` markers (`--timestamps inline`).
+        inline_timestamps: bool,
+    },
+    /// One of several `--tracks` sent together in a single request.
+    Track {
+        /// Seconds of this track sent.
+        secs: f64,
+        /// Tracks in the request.
+        tracks: usize,
+    },
+    /// One request per speech segment found by VAD.
+    Vad {
+        threshold_dbfs: f32,
+        /// `(start, end)` of each segment, in seconds.
+        spans: Vec<(f64, f64)>,
+        /// Total length of the segments.
+        speech_secs: f64,
+        /// A language-detection request precedes each segment.
+        detect_language: bool,
+    },
+}
+
+/// What transcribing one file would do, worked out without loading the
+/// model: printed by `--dry-run`.
+#[derive(Debug, Serialize)]
+pub struct TranscriptionPlan {
+    /// The file, or "stdin".
+    pub source: String,
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub duration_secs: f64,
+    /// Container and codec found by [`audio_probe::probe_audio`]; `None`
+    /// for stdin and `--raw-pcm` input.
+    pub probe: Option<AudioProbe>,
+    /// The preset (or local path) that would be loaded.
+    pub model: String,
+    /// `approx_memory()` of the preset; `None` for a local model.
+    pub memory_estimate: Option<String>,
+    pub mode: TranscriptionMode,
+    /// The `--language` hint, if any.
+    pub language: Option<String>,
+    /// Gain `--normalize` would apply, in dB.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub normalization_gain_db: Option<f32>,
+    /// A vocal pre-check request precedes the transcription.
+    pub vocal_check: bool,
+    /// The `--lyrics-hint` that would be sent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reference_text: Option<ReferenceText>,
+    pub chunking: Chunking,
+}
+
+impl TranscriptionPlan {
+    /// Plan the transcription of already-decoded `audio`: preprocess it
+    /// and run VAD as the real run would, and pick the model.
+    ///
+    /// Checks the preset against available memory (`model` is the preset
+    /// that would actually load), but loads nothing.
+    pub fn new(
+        source: String,
+        audio: AudioInput,
+        probe: Option<AudioProbe>,
+        model: Option<TranscriptionModel>,
+        model_path: Option<&Path>,
+        load: &LoadOptions,
+        options: &TranscribeOptions,
+    ) -> Result<Self> {
+        let prepared = PreparedAudio::new(
+            audio,
+            options.preprocess.as_ref(),
+            options.vad_threshold.is_some() || options.timestamps.is_some() || options.tracks,
+        );
+        let audio = &prepared.audio;
+        let chunking = match options.vad_threshold {
+            None => Chunking::Whole {
+                secs: audio_prep::duration_secs(&audio.samples, audio.sample_rate, audio.channels),
+                inline_timestamps: options.timestamps == Some(TimestampMode::Inline),
+            },
+            Some(threshold_dbfs) => {
+                let rate = audio.sample_rate as f64;
+                let spans: Vec<(f64, f64)> = speech_ranges(audio, threshold_dbfs)
+                    .into_iter()
+                    .map(|range| (range.start as f64 / rate, range.end as f64 / rate))
+                    .collect();
+                Chunking::Vad {
+                    threshold_dbfs,
+                    speech_secs: spans
+                        .iter()
+                        .fold(0.0, |total, (start, end)| total + end - start),
+                    spans,
+                    detect_language: options.detect_language,
+                }
+            }
+        };
+
+        let (model, memory_estimate) = match model_path {
+            Some(path) => (path.display().to_string(), None),
+            None => {
+                let preset = model.unwrap_or_default();
+                let (preset, load) = match memory::would_load(preset, load) {
+                    Some(choice) => choice,
+                    // Fails with the same error the load would.
+                    None => memory::choose(preset, load)?,
+                };
+                (preset.to_string(), Some(preset.memory_estimate(&load)))
+            }
+        };
+
+        let translate = options.translate && options.user_prompt.is_none();
+        Ok(Self {
+            source,
+            sample_rate: prepared.sample_rate,
+            channels: prepared.channels,
+            duration_secs: prepared.duration_secs,
+            probe,
+            model,
+            memory_estimate,
+            mode: if translate {
+                TranscriptionMode::Translate
+            } else {
+                TranscriptionMode::Transcribe
+            },
+            language: options.language.clone(),
+            normalization_gain_db: prepared.gain_db,
+            vocal_check: options.vocal_check != VocalCheck::Off && !options.tracks,
+            reference_text: options.reference.clone(),
+            chunking,
+        })
+    }
+}
+
+impl fmt::Display for TranscriptionPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "── {} ──", self.source)?;
+        writeln!(
+            f,
+            "Audio          : {:.1}s ({} Hz, {} ch)",
+            self.duration_secs, self.sample_rate, self.channels
+        )?;
+        if let Some(probe) = &self.probe {
+            writeln!(f, "Format         : {}", probe.format())?;
+        }
+        writeln!(f, "Model          : {}", self.model)?;
+        if let Some(estimate) = &self.memory_estimate {
+            writeln!(f, "Memory estimate: {estimate}")?;
+        }
+        let mode = match self.mode {
+            TranscriptionMode::Transcribe => "transcribe",
+            TranscriptionMode::Translate => "translate into English",
+        };
+        writeln!(f, "Mode           : {mode}")?;
+        if let Some(language) = &self.language {
+            writeln!(f, "Language       : {}", language_name(language))?;
+        }
+        if let Some(gain) = self.normalization_gain_db {
+            writeln!(f, "Normalization  : {gain:+.1} dB")?;
+        }
+        if self.vocal_check {
+            writeln!(
+                f,
+                "Vocal check    : first {VOCAL_CHECK_SECS:.0}s, before transcribing"
+            )?;
+        }
+        if let Some(reference) = &self.reference_text {
+            writeln!(f, "Lyrics hint    : {reference}, in the system prompt")?;
+        }
+        match &self.chunking {
+            Chunking::Whole {
+                secs,
+                inline_timestamps,
+            } => {
+                write!(f, "Chunking       : one request for {secs:.1}s of audio")?;
+                if *inline_timestamps {
+                    write!(f, ", timed by the model's [mm:ss] markers")?;
+                }
+                Ok(())
+            }
+            Chunking::Track { secs, tracks } => write!(
+                f,
+                "Chunking       : one request with all {tracks} tracks, {secs:.1}s from this one"
+            ),
+            Chunking::Vad {
+                threshold_dbfs,
+                spans,
+                speech_secs,
+                detect_language,
+            } => {
+                write!(
+                    f,
+                    "Chunking       : VAD at {threshold_dbfs} dBFS, {} segments, {speech_secs:.1}s of speech",
+                    spans.len()
+                )?;
+                if *detect_language {
+                    write!(f, ", language detected per segment")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// `transcribe --dry-run`: decode each file, print its
+/// [`TranscriptionPlan`] and stop.  A JSON array with several files.
+pub fn dry_run(
+    audio_paths: &[PathBuf],
+    model: Option<TranscriptionModel>,
+    model_path: Option<PathBuf>,
+    load: LoadOptions,
+    options: TranscribeOptions,
+    out: OutputConfig,
+) -> Result<()> {
+    let probes = probe_inputs(audio_paths, options.raw_pcm)?;
+    let inputs = decode_tracks(audio_paths, options.raw_pcm)?;
+    let tracks = inputs.len();
+    if options.tracks && options.preprocess.is_none() && !options.resample_tracks {
+        check_track_rates(&inputs)?;
+    }
+    let mut plans = inputs
+        .into_iter()
+        .zip(probes)
+        .map(|((source, audio), probe)| {
+            let model_path = model_path.as_deref();
+            TranscriptionPlan::new(source, audio, probe, model, model_path, &load, &options)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if options.tracks {
+        for plan in &mut plans {
+            if let Chunking::Whole { secs, .. } = plan.chunking {
+                plan.chunking = Chunking::Track { secs, tracks };
+            }
+        }
+    }
+    if out.json {
+        return match plans.as_slice() {
+            [plan] => ui::print_json(plan),
+            _ => ui::print_json(&plans),
+        };
+    }
+    for (i, plan) in plans.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!("{plan}");
+    }
+    Ok(())
+}
 
 /// Run audio transcription as a standalone CLI example.
 ///
-/// Loads Gemma 3n, reads the audio file at the given path, and prints the
-/// transcription along with timing statistics.
+/// Loads Gemma 3n, reads the audio file at the given path (stdin for `-`,
+/// decoded as `raw_pcm` when set), and prints the transcription along with timing statistics.  With a non-text
+/// `output_format` the rendered document (SRT, VTT or JSON) is printed
+/// instead; with `output` it is written to that file.
+///
+/// With `tracks`, every path is a track of one recording and all of them
+/// go into a single request; otherwise `audio_paths` holds one path.
+///
+/// When `cancel` fires during a `--vad` transcription, the segments finished
+/// so far are printed / written as usual before returning [`Interrupted`].
 pub async fn run(
-    audio_path: PathBuf,
+    audio_paths: Vec<PathBuf>,
     model: Option<TranscriptionModel>,
-    user_prompt: Option<String>,
+    model_path: Option<PathBuf>,
+    load: LoadOptions,
+    options: TranscribeOptions,
+    cancel: CancellationToken,
+    out: OutputConfig,
 ) -> Result<()> {
+    let TranscribeOptions {
+        user_prompt,
+        system_prompt,
+        language,
+        translate,
+        preprocess,
+        vad_threshold,
+        detect_language,
+        vocal_check,
+        timestamps,
+        lyrics,
+        censor,
+        reference,
+        tracks,
+        resample_tracks,
+        interactive,
+        output_format,
+        output,
+        raw_pcm,
+        dump_audio,
+        warmup,
+        decoding,
+    } = options;
     let preset = model.unwrap_or_default();
+    if interactive && out.json {
+        anyhow::bail!("--json is not supported with --interactive");
+    }
+    if interactive && audio_paths.iter().any(|path| path.as_os_str() == "-") {
+        anyhow::bail!(
+            "--interactive reads questions from stdin, so the audio can't come from stdin"
+        );
+    }
 
-    // Validate input file exists
-    if !audio_path.exists() {
-        anyhow::bail!("Audio file not found: {}", audio_path.display());
+    // Probe, then read and decode the input before the model loads, so a
+    // bad file or a mismatched --raw-pcm layout fails fast.
+    let probes = probe_inputs(&audio_paths, raw_pcm)?;
+    let decode_start = Instant::now();
+    let mut inputs = decode_tracks(&audio_paths, raw_pcm)?;
+    if tracks && preprocess.is_none() && !resample_tracks {
+        check_track_rates(&inputs)?;
     }
+    detail!(
+        out,
+        "Input decoded in {}",
+        fmt_duration(decode_start.elapsed())
+    );
+    let source = inputs
+        .iter()
+        .map(|(source, _)| source.as_str())
+        .collect::<Vec<_>>()
+        .join(" + ");
+    let banner = inputs
+        .iter()
+        .zip(&probes)
+        .map(|((source, _), probe)| match probe {
+            Some(probe) => format!("{source} ({probe})"),
+            None => source.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join(" + ");
 
-    println!("Loading transcription model: {preset}");
-    println!("  Memory estimate: {}", preset.approx_memory());
+    if translate && user_prompt.is_some() {
+        tracing::warn!("--translate is ignored because --user-prompt replaces its instruction");
+    }
 
+    let before = MemorySnapshot::capture();
     let load_start = Instant::now();
-    let transcriber = AudioTranscriber::from_preset(preset).await?;
+    let loading = async {
+        match &model_path {
+            Some(path) => {
+                status!(out, "Loading transcription model: {}", path.display());
+                Ok::<_, anyhow::Error>(AudioTranscriber::from_local_path(path, &load).await?)
+            }
+            None => {
+                let (preset, load) = memory::choose(preset, &load)?;
+                status!(out, "Loading transcription model: {preset}");
+                status!(out, "  Memory estimate: {}", preset.memory_estimate(&load));
+                let settings = load.apply(preset.default_settings());
+                if let Some(weights) = load.uqff.describe(preset.model_id(), settings) {
+                    status!(out, "  {weights}");
+                }
+                Ok(AudioTranscriber::from_preset_with_options(preset, &load).await?)
+            }
+        }
+    };
+    let mut transcriber = interrupt::unless_cancelled(Some(&cancel), loading).await?;
     let load_elapsed = load_start.elapsed();
-    println!("Model loaded in {}\n", fmt_duration(load_elapsed));
+    let load_memory = MemorySnapshot::capture().since(before);
+    status!(out, "Model loaded in {}", fmt_duration(load_elapsed));
+    status!(out, "{}\n", load_memory.banner());
 
-    println!("Transcribing: {}", audio_path.display());
+    if let Some(system_prompt) = system_prompt {
+        transcriber = transcriber.with_system_prompt(system_prompt);
+    }
+    if let Some(language) = &language {
+        transcriber = transcriber.with_language(language);
+    }
+    transcriber = transcriber
+        .with_translation(translate)
+        .with_preprocessing(preprocess)
+        .with_vad(vad_threshold)
+        .with_language_detection(detect_language)
+        .with_vocal_check(vocal_check)
+        .with_timestamps(timestamps)
+        .with_track_resampling(resample_tracks)
+        .with_lyrics_format(lyrics)
+        .with_censor(censor)
+        .with_reference(reference)
+        .with_audio_dump(dump_audio)
+        .with_sampler(decoding.apply(DEFAULT_SAMPLER))
+        .with_cancellation(cancel.clone());
+    detail!(
+        out,
+        "System prompt:\n{}",
+        transcriber.effective_system_prompt(user_prompt.as_deref())
+    );
+    let warm_up = if warmup {
+        let elapsed = transcriber.warm_up().await?;
+        status!(out, "Warm-up: {}", fmt_duration(elapsed));
+        Some(elapsed.as_secs_f64())
+    } else {
+        None
+    };
 
-    let result = transcriber
-        .transcribe_file(&audio_path, user_prompt.as_deref())
-        .await?;
+    status!(out, "Transcribing: {banner}");
 
-    println!("\n{result}");
+    // The session asks about the same decoded audio, so keep a copy.
+    let session_audio = interactive.then(|| {
+        inputs
+            .iter()
+            .map(|(_, audio)| audio.clone())
+            .collect::<Vec<_>>()
+    });
 
+    let mut result = if tracks {
+        transcriber
+            .transcribe_tracks(inputs, user_prompt.as_deref())
+            .await?
+    } else {
+        let (_, audio) = inputs.pop().expect("one input without --tracks");
+        transcriber
+            .transcribe_audio(audio, user_prompt.as_deref())
+            .await?
+    };
+    result.load_memory = Some(load_memory);
+    result.warm_up_secs = warm_up;
+    if let Some(gain) = result.normalization_gain_db {
+        detail!(out, "Normalized by {gain:+.1} dB");
+    }
+    if output_format == OutputFormat::Lrc && !tracks && raw_pcm.is_none() {
+        result.read_tags(&audio_paths[0]);
+    }
+    if result.skipped_no_vocals() {
+        status!(
+            out,
+            "No vocals detected in {source}; nothing was transcribed (--force transcribes anyway)"
+        );
+    } else if result.effective_duration_secs == 0.0 {
+        tracing::warn!("no speech detected in {source}; nothing was transcribed");
+    }
+    if result.interrupted {
+        let covered = result
+            .segments
+            .as_ref()
+            .and_then(|segments| segments.last())
+            .map_or(0.0, |seg| seg.end_secs);
+        tracing::warn!(
+            "interrupted; the partial transcript covers the first {covered:.1}s of {:.1}s",
+            result.audio_duration_secs
+        );
+    }
+
+    if let Some(path) = &output {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).with_context(|| {
+                format!("Failed to create output directory: {}", parent.display())
+            })?;
+        }
+        std::fs::write(path, result.render(output_format)?)
+            .with_context(|| format!("Failed to write output file: {}", path.display()))?;
+    }
+
+    // In JSON mode stdout always carries the full result, whatever format
+    // was written to --output.
+    if out.json {
+        if let Some(path) = &output {
+            status!(out, "Transcription written to {}", path.display());
+        }
+        println!("{}", result.to_json()?);
+        return finish(&result);
+    }
+
+    match output {
+        Some(path) if out.quiet() => println!("{}", path.display()),
+        None if out.quiet() && output_format == OutputFormat::Text => println!("{}", result.text),
+        None if out.quiet() => println!("{}", result.render(output_format)?),
+        Some(path) => {
+            println!("\n{result}");
+            println!("Transcription written to {}", path.display());
+        }
+        None if output_format == OutputFormat::Text => println!("\n{result}"),
+        None => println!("\n{}", result.render(output_format)?),
+    }
+
+    if let Some(audio) = session_audio
+        && !result.interrupted
+    {
+        // The model's own words, before --text-format reshaped them.
+        let transcript = result.raw_text.as_deref().unwrap_or(&result.text);
+        let session = transcriber.session(audio, user_prompt.as_deref(), transcript)?;
+        return transcription_session::run_interactive(session, cancel).await;
+    }
+    finish(&result)
+}
+
+/// `Ok` for a complete transcription, [`Interrupted`] once a partial one
+/// has been flushed.
+fn finish(result: &TranscriptionResult) -> Result<()> {
+    if result.interrupted {
+        return Err(Interrupted.into());
+    }
     Ok(())
 }
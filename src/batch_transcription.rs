@@ -0,0 +1,712 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use mistralrs::AudioInput;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{Semaphore, mpsc};
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
+
+use crate::artifacts::ArtifactLedger;
+use crate::audio_prep::{PrepOptions, RawPcm};
+use crate::audio_transcription::{
+    self, AudioTranscriber, PreparedAudio, TranscribeOptions, TranscriptionModel,
+    TranscriptionResult,
+};
+use crate::interrupt::{self, Interrupted};
+use crate::load_options::LoadOptions;
+use crate::memory::{self, MemoryFootprint};
+use crate::memstats::MemorySnapshot;
+use crate::transcript_output::OutputFormat;
+use crate::ui::{self, OutputConfig, detail, fmt_duration, status};
+
+// ── Transcriber ──────────────────────────────────────────────────────────────
+
+/// What the scheduler needs from a loaded model, so [`transcribe_all`]'s
+/// ordering and error handling don't depend on mistral.rs.
+#[async_trait]
+pub trait TranscribePrepared: Send + Sync {
+    async fn transcribe_prepared(
+        &self,
+        audio: PreparedAudio,
+        user_prompt: Option<&str>,
+    ) -> Result<TranscriptionResult>;
+}
+
+#[async_trait]
+impl TranscribePrepared for AudioTranscriber {
+    async fn transcribe_prepared(
+        &self,
+        audio: PreparedAudio,
+        user_prompt: Option<&str>,
+    ) -> Result<TranscriptionResult> {
+        Ok(AudioTranscriber::transcribe_prepared(self, audio, user_prompt).await?)
+    }
+}
+
+// ── Worker pool ──────────────────────────────────────────────────────────────
+
+/// Loaded transcribers, each handling one request at a time.
+struct WorkerPool<T> {
+    idle: Mutex<Vec<Arc<T>>>,
+    available: Semaphore,
+}
+
+impl<T> WorkerPool<T> {
+    fn new(workers: Vec<T>) -> Self {
+        Self {
+            available: Semaphore::new(workers.len()),
+            idle: Mutex::new(workers.into_iter().map(Arc::new).collect()),
+        }
+    }
+
+    /// Wait for an idle worker and take it until the guard is dropped.
+    async fn acquire(self: &Arc<Self>) -> Result<Worker<T>> {
+        self.available
+            .acquire()
+            .await
+            .context("The worker pool was closed")?
+            .forget();
+        let worker = self
+            .idle
+            .lock()
+            .expect("worker pool lock poisoned")
+            .pop()
+            .expect("a permit means a worker is idle");
+        Ok(Worker {
+            pool: self.clone(),
+            worker: Some(worker),
+        })
+    }
+}
+
+/// A worker taken from a [`WorkerPool`]; dropping it puts it back.
+struct Worker<T> {
+    pool: Arc<WorkerPool<T>>,
+    worker: Option<Arc<T>>,
+}
+
+impl<T> Worker<T> {
+    fn get(&self) -> &T {
+        self.worker.as_deref().expect("worker taken before drop")
+    }
+}
+
+impl<T> Drop for Worker<T> {
+    fn drop(&mut self) {
+        if let Some(worker) = self.worker.take() {
+            self.pool
+                .idle
+                .lock()
+                .expect("worker pool lock poisoned")
+                .push(worker);
+            self.pool.available.add_permits(1);
+        }
+    }
+}
+
+// ── Scheduler ────────────────────────────────────────────────────────────────
+
+/// Decoded files waiting for a free model, on top of the ones still being
+/// decoded.  Enough that a worker never waits on the decoder between
+/// files, few enough that memory stays bounded by a couple of songs.
+pub const DECODE_AHEAD: usize = 2;
+
+/// The outcome for one input file.
+pub struct FileResult {
+    pub path: PathBuf,
+    pub result: Result<TranscriptionResult>,
+    /// Time spent reading, decoding and preprocessing the file.
+    pub decode_duration: Duration,
+}
+
+/// A file as it leaves the decode stage.
+struct Decoded {
+    index: usize,
+    audio: Result<PreparedAudio>,
+    duration: Duration,
+}
+
+/// Transcribe every file in `inputs` with the pool of `workers`, in two
+/// stages joined by a bounded channel.
+///
+/// The decode stage reads, decodes and preprocesses up to `jobs` files at
+/// once on the blocking thread pool and hands them over in input order;
+/// at most [`DECODE_AHEAD`] decoded files wait in the channel, so file
+/// N+1 is decoded while file N is transcribed without the whole batch
+/// piling up in memory.  The inference stage gives each file to the next
+/// idle worker.
+///
+/// `on_done` is called as each file finishes, in completion order; the
+/// returned results are in input order.  A file that fails to decode or
+/// transcribe (or panics) only fails its own entry.  When `cancel` fires,
+/// decoding stops and every file not yet finished fails with
+/// [`Interrupted`].
+pub async fn transcribe_all<T, D>(
+    workers: Vec<T>,
+    inputs: Vec<PathBuf>,
+    jobs: usize,
+    user_prompt: Option<String>,
+    decode: D,
+    mut on_done: impl FnMut(&Path, &Result<TranscriptionResult>),
+    cancel: CancellationToken,
+) -> Vec<FileResult>
+where
+    T: TranscribePrepared + 'static,
+    D: Fn(&Path) -> Result<PreparedAudio> + Send + Sync + 'static,
+{
+    let pool = Arc::new(WorkerPool::new(workers));
+    let user_prompt: Option<Arc<str>> = user_prompt.map(Into::into);
+    let (sender, mut decoded) = mpsc::channel(DECODE_AHEAD);
+    // Stops the decode stage however this function returns.
+    let decoder_cancel = cancel.child_token();
+    let _stop_decoding = decoder_cancel.clone().drop_guard();
+    tokio::spawn(decode_stage(
+        inputs.clone(),
+        jobs,
+        Arc::new(decode),
+        sender,
+        decoder_cancel,
+    ));
+
+    let mut results: Vec<Option<Result<TranscriptionResult>>> =
+        inputs.iter().map(|_| None).collect();
+    let mut decode_durations = vec![Duration::ZERO; inputs.len()];
+    let mut finish = |index: usize, result: Result<TranscriptionResult>| {
+        on_done(&inputs[index], &result);
+        results[index] = Some(result);
+    };
+
+    let mut tasks = JoinSet::new();
+    let mut index_of = HashMap::with_capacity(inputs.len());
+    let mut receiving = true;
+    while receiving || !tasks.is_empty() {
+        tokio::select! {
+            received = decoded.recv(), if receiving => {
+                let Some(Decoded { index, audio, duration }) = received else {
+                    receiving = false;
+                    continue;
+                };
+                decode_durations[index] = duration;
+                let audio = match audio {
+                    Ok(audio) => audio,
+                    Err(err) => {
+                        finish(index, Err(err));
+                        continue;
+                    }
+                };
+                let Ok(worker) =
+                    interrupt::unless_cancelled(Some(&cancel), pool.acquire()).await
+                else {
+                    finish(index, Err(Interrupted.into()));
+                    receiving = false;
+                    continue;
+                };
+                let user_prompt = user_prompt.clone();
+                let cancel = cancel.clone();
+                let task = tasks.spawn(async move {
+                    let work = worker.get().transcribe_prepared(audio, user_prompt.as_deref());
+                    interrupt::unless_cancelled(Some(&cancel), work).await
+                });
+                index_of.insert(task.id(), index);
+            }
+            Some(joined) = tasks.join_next_with_id() => {
+                let (id, result) = match joined {
+                    Ok((id, result)) => (id, result),
+                    Err(err) => (
+                        err.id(),
+                        Err(anyhow::anyhow!("The transcription task failed: {err}")),
+                    ),
+                };
+                finish(index_of[&id], result);
+            }
+        }
+    }
+
+    inputs
+        .into_iter()
+        .zip(results)
+        .zip(decode_durations)
+        .map(|((path, result), decode_duration)| FileResult {
+            path,
+            // Never handed to a worker: the run was cancelled first.
+            result: result.unwrap_or_else(|| Err(Interrupted.into())),
+            decode_duration,
+        })
+        .collect()
+}
+
+/// The decode stage of [`transcribe_all`]: decode `inputs`, up to `jobs`
+/// at once, and send them on in input order.  Returns when every file is
+/// sent, or early when the receiver is gone or `cancel` fires.
+async fn decode_stage<D>(
+    inputs: Vec<PathBuf>,
+    jobs: usize,
+    decode: Arc<D>,
+    sender: mpsc::Sender<Decoded>,
+    cancel: CancellationToken,
+) where
+    D: Fn(&Path) -> Result<PreparedAudio> + Send + Sync + 'static,
+{
+    let mut pending = VecDeque::with_capacity(jobs.max(1));
+    let mut inputs = inputs.into_iter().enumerate();
+    loop {
+        while pending.len() < jobs.max(1)
+            && let Some((index, path)) = inputs.next()
+        {
+            let decode = decode.clone();
+            pending.push_back((
+                index,
+                tokio::task::spawn_blocking(move || {
+                    let start = Instant::now();
+                    (decode(&path), start.elapsed())
+                }),
+            ));
+        }
+        let Some((index, handle)) = pending.pop_front() else {
+            return;
+        };
+        let Ok(joined) = interrupt::unless_cancelled(Some(&cancel), async {
+            Ok::<_, anyhow::Error>(handle.await)
+        })
+        .await
+        else {
+            return;
+        };
+        let (audio, duration) = match joined {
+            Ok((audio, duration)) => (audio, duration),
+            Err(err) => (
+                Err(anyhow::anyhow!("The decode task failed: {err}")),
+                Duration::ZERO,
+            ),
+        };
+        let decoded = Decoded {
+            index,
+            audio,
+            duration,
+        };
+        if sender.send(decoded).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// How much the pipeline saved: `wall` against decoding then transcribing
+/// each file in turn, e.g. `Pipeline: 41.2s vs ~55.0s one step at a time
+/// (1.3x)`.  `None` when nothing was transcribed.
+fn throughput_line(files: &[FileResult], wall: Duration) -> Option<String> {
+    let sequential: Duration = files
+        .iter()
+        .filter_map(|file| {
+            let result = file.result.as_ref().ok()?;
+            Some(file.decode_duration + result.inference_duration)
+        })
+        .sum();
+    if sequential.is_zero() || wall.is_zero() {
+        return None;
+    }
+    Some(format!(
+        "Pipeline: {} vs ~{} one step at a time ({:.1}x)",
+        fmt_duration(wall),
+        fmt_duration(sequential),
+        sequential.as_secs_f64() / wall.as_secs_f64()
+    ))
+}
+
+// ── Helpers ──────────────────────────────────────────────────────────────────
+
+/// Read, decode and preprocess one input file.
+fn decode_file(
+    path: &Path,
+    raw_pcm: Option<RawPcm>,
+    prep: Option<&PrepOptions>,
+    keep_timing: bool,
+) -> Result<PreparedAudio> {
+    if !path.exists() {
+        anyhow::bail!("Audio file not found: {}", path.display());
+    }
+    let bytes = std::fs::read(path)
+        .with_context(|| format!("Failed to read audio file: {}", path.display()))?;
+    let audio = match raw_pcm {
+        Some(layout) => layout.decode(&bytes)?,
+        None => AudioInput::from_bytes(&bytes).context("Failed to decode audio bytes")?,
+    };
+    Ok(PreparedAudio::new(audio, prep, keep_timing))
+}
+
+/// `<dir>/<stem>.<ext>` for each input, failing if two inputs would write
+/// the same file.
+fn output_paths(inputs: &[PathBuf], dir: &Path, extension: &str) -> Result<Vec<PathBuf>> {
+    let mut seen: HashMap<PathBuf, &Path> = HashMap::with_capacity(inputs.len());
+    let mut paths = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        let stem = input
+            .file_stem()
+            .with_context(|| format!("No file name in {}", input.display()))?;
+        let path = dir.join(stem).with_extension(extension);
+        if let Some(other) = seen.insert(path.clone(), input) {
+            anyhow::bail!(
+                "{} and {} would both be written to {}",
+                other.display(),
+                input.display(),
+                path.display()
+            );
+        }
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+/// How many copies of `preset` to load: `wanted`, or fewer if they don't
+/// all fit in the memory available now (at least one; whether that one
+/// fits is [`memory::choose`]'s call).
+fn models_that_fit(preset: TranscriptionModel, load: &LoadOptions, wanted: usize) -> usize {
+    let Some(available) = memory::available_bytes() else {
+        return wanted;
+    };
+    let each = preset.required_bytes(load).max(1);
+    let fit = usize::try_from(available / each).unwrap_or(usize::MAX);
+    if fit < wanted {
+        tracing::warn!(
+            "{wanted} copies of {preset} need ~{} but only {} is available; loading {}",
+            memory::fmt_gb(each.saturating_mul(wanted as u64)),
+            memory::fmt_gb(available),
+            fit.max(1)
+        );
+    }
+    fit.clamp(1, wanted)
+}
+
+// ── CLI entry-point ──────────────────────────────────────────────────────────
+
+/// `transcribe` settings for several input files.
+#[derive(Clone, Debug)]
+pub struct BatchOptions {
+    /// Settings applied to every file.  `output` names a directory.
+    pub transcribe: TranscribeOptions,
+    /// Files decoded and preprocessed at once.
+    pub jobs: usize,
+    /// Independent copies of the model to load, memory permitting.
+    pub jobs_models: usize,
+}
+
+/// One file in the `--json` array.
+#[derive(Serialize)]
+struct JsonFile<'a> {
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<&'a TranscriptionResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    /// Size of the file written to the output directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bytes: Option<u64>,
+    /// Bytes written for this file and every one before it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    total_bytes: Option<u64>,
+}
+
+/// Transcribe several files, overlapping decoding with inference.
+///
+/// Results are printed (or written to `<output>/<stem>.<ext>`) in input
+/// order once all files are done.  A failed file is reported and skipped;
+/// the command fails at the end if any did.  Written files are counted
+/// in an [`ArtifactLedger`]: when the output volume drops below the
+/// `--min-free` floor, the rest are not written (and fail), and the run
+/// still reports everything.
+pub async fn run(
+    audio_paths: Vec<PathBuf>,
+    model: Option<TranscriptionModel>,
+    model_path: Option<PathBuf>,
+    load: LoadOptions,
+    options: BatchOptions,
+    cancel: CancellationToken,
+    out: OutputConfig,
+) -> Result<()> {
+    let BatchOptions {
+        transcribe,
+        jobs,
+        jobs_models,
+    } = options;
+    let TranscribeOptions {
+        user_prompt,
+        system_prompt,
+        language,
+        translate,
+        preprocess,
+        vad_threshold,
+        detect_language,
+        vocal_check,
+        timestamps,
+        lyrics,
+        censor,
+        output_format,
+        output,
+        raw_pcm,
+        decoding,
+        ..
+    } = transcribe;
+
+    if jobs == 0 || jobs_models == 0 {
+        anyhow::bail!("--jobs and --jobs-models must be at least 1");
+    }
+    if audio_paths.iter().any(|path| path.as_os_str() == "-") {
+        anyhow::bail!("stdin (`-`) can only be transcribed on its own");
+    }
+    let output_files = output
+        .as_deref()
+        .map(|dir| output_paths(&audio_paths, dir, output_format.extension()))
+        .transpose()?;
+    if translate && user_prompt.is_some() {
+        tracing::warn!("--translate is ignored because --user-prompt replaces its instruction");
+    }
+
+    // Probe every file before the models load.  A file that can't be
+    // decoded is failed now and skipped, rather than after the load.
+    let total = audio_paths.len();
+    let mut rejected: Vec<Option<anyhow::Error>> = Vec::with_capacity(total);
+    let mut accepted = Vec::with_capacity(total);
+    for path in &audio_paths {
+        match audio_transcription::probe_inputs(std::slice::from_ref(path), raw_pcm) {
+            Ok(_) => {
+                accepted.push(path.clone());
+                rejected.push(None);
+            }
+            Err(err) => {
+                tracing::warn!("{err:#}; skipping it");
+                rejected.push(Some(err));
+            }
+        }
+    }
+    if accepted.is_empty() {
+        anyhow::bail!("None of the {total} files can be decoded");
+    }
+    // No point loading the models when nothing could be written.
+    let mut ledger = output.as_deref().map(ArtifactLedger::new);
+    if let (Some(ledger), Some(dir)) = (&ledger, &output) {
+        let space = ledger.check_space()?;
+        if space.is_low() {
+            anyhow::bail!("Not transcribing into {}: {space}", dir.display());
+        }
+    }
+
+    let before = MemorySnapshot::capture();
+    let load_start = Instant::now();
+    let loading = async {
+        let mut transcribers = Vec::with_capacity(jobs_models);
+        match &model_path {
+            Some(path) => {
+                status!(out, "Loading transcription model: {}", path.display());
+                for _ in 0..jobs_models {
+                    transcribers.push(AudioTranscriber::from_local_path(path, &load).await?);
+                }
+            }
+            None => {
+                let (preset, load) = memory::choose(model.unwrap_or_default(), &load)?;
+                let count = models_that_fit(preset, &load, jobs_models);
+                status!(out, "Loading transcription model: {preset} (x{count})");
+                status!(
+                    out,
+                    "  Memory estimate: {} each",
+                    preset.memory_estimate(&load)
+                );
+                let settings = load.apply(preset.default_settings());
+                if let Some(weights) = load.uqff.describe(preset.model_id(), settings) {
+                    status!(out, "  {weights}");
+                }
+                for _ in 0..count {
+                    transcribers
+                        .push(AudioTranscriber::from_preset_with_options(preset, &load).await?);
+                }
+            }
+        }
+        Ok::<_, anyhow::Error>(transcribers)
+    };
+    let transcribers = interrupt::unless_cancelled(Some(&cancel), loading)
+        .await?
+        .into_iter()
+        .map(|transcriber| {
+            let mut transcriber = transcriber;
+            if let Some(system_prompt) = &system_prompt {
+                transcriber = transcriber.with_system_prompt(system_prompt.clone());
+            }
+            if let Some(language) = &language {
+                transcriber = transcriber.with_language(language);
+            }
+            transcriber
+                .with_translation(translate)
+                .with_preprocessing(preprocess)
+                .with_vad(vad_threshold)
+                .with_language_detection(detect_language)
+                .with_vocal_check(vocal_check)
+                .with_timestamps(timestamps)
+                .with_lyrics_format(lyrics)
+                .with_censor(censor.clone())
+                .with_sampler(decoding.apply(audio_transcription::DEFAULT_SAMPLER))
+                .with_cancellation(cancel.clone())
+        })
+        .collect::<Vec<_>>();
+    let models = transcribers.len();
+    status!(
+        out,
+        "Model loaded in {}",
+        fmt_duration(load_start.elapsed())
+    );
+    status!(
+        out,
+        "{}\n",
+        MemorySnapshot::capture().since(before).banner()
+    );
+    if let Some(transcriber) = transcribers.first() {
+        detail!(
+            out,
+            "System prompt:\n{}",
+            transcriber.effective_system_prompt(user_prompt.as_deref())
+        );
+    }
+
+    let queued = accepted.len();
+    status!(
+        out,
+        "Transcribing {queued} files ({jobs} decoding at once, {models} model(s))"
+    );
+    let keep_timing = vad_threshold.is_some() || timestamps.is_some();
+    let decode = move |path: &Path| decode_file(path, raw_pcm, preprocess.as_ref(), keep_timing);
+    let mut done = 0;
+    let on_done = |path: &Path, result: &Result<TranscriptionResult>| {
+        done += 1;
+        match result {
+            Ok(result) if result.skipped_no_vocals() => status!(
+                out,
+                "  [{done}/{queued}] {}: no vocals detected, nothing transcribed",
+                path.display()
+            ),
+            Ok(result) => status!(
+                out,
+                "  [{done}/{queued}] {}: {:.1}s of audio in {}",
+                path.display(),
+                result.audio_duration_secs,
+                fmt_duration(result.inference_duration)
+            ),
+            Err(_) => status!(out, "  [{done}/{queued}] {}: failed", path.display()),
+        }
+    };
+    let started = Instant::now();
+    let mut transcribed = transcribe_all(
+        transcribers,
+        accepted,
+        jobs,
+        user_prompt,
+        decode,
+        on_done,
+        cancel,
+    )
+    .await
+    .into_iter();
+    let mut files: Vec<FileResult> = audio_paths
+        .into_iter()
+        .zip(rejected)
+        .map(|(path, rejected)| match rejected {
+            Some(err) => FileResult {
+                path,
+                result: Err(err),
+                decode_duration: Duration::ZERO,
+            },
+            None => transcribed.next().expect("one result per accepted file"),
+        })
+        .collect();
+
+    let mut failed = 0;
+    let mut interrupted = false;
+    // Once free space runs low, why the remaining files weren't written.
+    let mut low_space = None;
+    let mut sizes: Vec<Option<(u64, u64)>> = vec![None; files.len()];
+    for (index, file) in files.iter_mut().enumerate() {
+        if let (Some(reason), Ok(_)) = (&low_space, &file.result) {
+            file.result = Err(anyhow::anyhow!("not written: {reason}"));
+        }
+        match &mut file.result {
+            Ok(result) => {
+                interrupted |= result.interrupted;
+                if output_format == OutputFormat::Lrc && raw_pcm.is_none() {
+                    result.read_tags(&file.path);
+                }
+                if let (Some(paths), Some(ledger)) = (&output_files, &mut ledger) {
+                    let space = ledger.check_space()?;
+                    if space.is_low() {
+                        tracing::warn!("{space}; not writing the remaining transcriptions");
+                        let reason = space.to_string();
+                        file.result = Err(anyhow::anyhow!("not written: {reason}"));
+                        low_space = Some(reason);
+                        failed += 1;
+                        continue;
+                    }
+                    let path = &paths[index];
+                    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                        std::fs::create_dir_all(parent).with_context(|| {
+                            format!("Failed to create output directory: {}", parent.display())
+                        })?;
+                    }
+                    std::fs::write(path, result.render(output_format)?).with_context(|| {
+                        format!("Failed to write output file: {}", path.display())
+                    })?;
+                    let bytes = ledger.record(path)?;
+                    sizes[index] = Some((bytes, ledger.total_bytes()));
+                    if out.quiet() && !out.json {
+                        println!("{}", path.display());
+                    }
+                } else if !out.json {
+                    println!("\n== {} ==", file.path.display());
+                    println!("{}", result.render(output_format)?);
+                }
+            }
+            Err(err) => {
+                interrupted |= interrupt::is_interrupted(err);
+                failed += 1;
+                tracing::error!("{}: {err:#}", file.path.display());
+            }
+        }
+    }
+    let wall = started.elapsed();
+    status!(
+        out,
+        "\nTranscribed {} of {total} files in {}",
+        total - failed,
+        fmt_duration(wall)
+    );
+    if let Some(line) = throughput_line(&files, wall) {
+        status!(out, "{line}");
+    }
+    if let Some(dir) = &output {
+        status!(out, "Transcriptions written to {}", dir.display());
+    }
+    if let Some(ledger) = &ledger {
+        status!(out, "{}", ledger.summary());
+    }
+
+    if out.json {
+        let json: Vec<JsonFile> = files
+            .iter()
+            .zip(&sizes)
+            .map(|(file, size)| JsonFile {
+                path: file.path.display().to_string(),
+                result: file.result.as_ref().ok(),
+                error: file.result.as_ref().err().map(|err| format!("{err:#}")),
+                bytes: size.map(|(bytes, _)| bytes),
+                total_bytes: size.map(|(_, total)| total),
+            })
+            .collect();
+        ui::print_json(&json)?;
+    }
+
+    if interrupted {
+        return Err(Interrupted.into());
+    }
+    if failed > 0 {
+        anyhow::bail!("{failed} of {total} files failed");
+    }
+    Ok(())
+}
@@ -0,0 +1,980 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use indicatif::HumanBytes;
+use mistralrs::{Model, RequestBuilder, Response};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::audio_transcription::{AudioTranscriber, TranscriptionModel};
+use crate::cli_chat::{ChatModel, CliChat};
+use crate::device;
+use crate::load_options::LoadOptions;
+use crate::memory;
+use crate::memstats::{self, LoadMemory, MemorySnapshot};
+use crate::metrics::{self, NormalizeOptions, WordDiff};
+use crate::promp_enhancer::{EnhancerModel, PromptEnhancer};
+use crate::ui::{self, OutputConfig, status};
+
+// ── Tasks and presets ────────────────────────────────────────────────────────
+
+/// Workload measured by `bench`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum BenchTask {
+    /// Enhance a fixed seed prompt.
+    Prompt,
+    /// Transcribe the file given with `--audio`.
+    Transcribe,
+    /// Play a short canned chat exchange.
+    Chat,
+    /// Measure nothing; summarize a `--history` file instead.
+    Report,
+}
+
+impl fmt::Display for BenchTask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Prompt => "prompt",
+            Self::Transcribe => "transcribe",
+            Self::Chat => "chat",
+            Self::Report => "report",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Seed used by the `prompt` task.
+const BENCH_SEED: &str = "lonely astronaut on a neon-lit rooftop, watercolor";
+
+/// User turns of the `chat` task, sent in order on a fresh history.
+const CHAT_EXCHANGE: &[&str] = &[
+    "Suggest three names for a synthwave band.",
+    "Pick the best one and explain why in two sentences.",
+];
+
+/// A preset resolved for the task it is benchmarked on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Preset {
+    Enhancer(EnhancerModel),
+    Transcription(TranscriptionModel),
+}
+
+impl Preset {
+    /// Look up `name` (as accepted by `--model`) among the presets `task`
+    /// can use.
+    fn resolve(task: BenchTask, name: &str) -> Result<Self> {
+        let preset = match task {
+            BenchTask::Prompt | BenchTask::Chat => {
+                EnhancerModel::from_str(name, true).map(Self::Enhancer)
+            }
+            BenchTask::Transcribe => {
+                TranscriptionModel::from_str(name, true).map(Self::Transcription)
+            }
+            BenchTask::Report => unreachable!("bench report loads no presets"),
+        };
+        preset.map_err(|_| {
+            anyhow::anyhow!(
+                "Unknown {task} model `{name}` (expected one of: {})",
+                preset_names(task).join(", ")
+            )
+        })
+    }
+
+    /// The `--model` name, e.g. `gemma-e2b`.
+    fn name(self) -> String {
+        let value = match self {
+            Self::Enhancer(model) => model.to_possible_value(),
+            Self::Transcription(model) => model.to_possible_value(),
+        };
+        value.map(|v| v.get_name().to_string()).unwrap_or_default()
+    }
+
+    fn model_id(self) -> &'static str {
+        match self {
+            Self::Enhancer(model) => model.model_id(),
+            Self::Transcription(model) => model.model_id(),
+        }
+    }
+}
+
+fn preset_names(task: BenchTask) -> Vec<String> {
+    let values: Vec<_> = match task {
+        BenchTask::Prompt | BenchTask::Chat => EnhancerModel::value_variants()
+            .iter()
+            .filter_map(|m| m.to_possible_value())
+            .collect(),
+        BenchTask::Transcribe => TranscriptionModel::value_variants()
+            .iter()
+            .filter_map(|m| m.to_possible_value())
+            .collect(),
+        BenchTask::Report => Vec::new(),
+    };
+    values.iter().map(|v| v.get_name().to_string()).collect()
+}
+
+// ── Iteration harness ────────────────────────────────────────────────────────
+
+/// How many times each preset runs the task.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Plan {
+    /// Unmeasured runs first (kernel compilation, caches, allocator).
+    pub warmup: usize,
+    /// Runs that make it into the statistics.
+    pub iterations: usize,
+}
+
+/// One run of a [`Plan`], numbered from 1 within its phase.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Run {
+    Warmup(usize),
+    Measured(usize),
+}
+
+impl Plan {
+    /// All runs in order: warmups, then measured iterations.
+    pub fn runs(self) -> impl Iterator<Item = Run> {
+        (1..=self.warmup)
+            .map(Run::Warmup)
+            .chain((1..=self.iterations).map(Run::Measured))
+    }
+}
+
+impl Run {
+    pub fn is_measured(self) -> bool {
+        matches!(self, Self::Measured(_))
+    }
+}
+
+/// Measurements from one run of the task.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Sample {
+    /// Wall-clock time of the run (inference only for transcription).
+    pub secs: f64,
+    /// Generated tokens, when the task produces text token by token.
+    pub tokens: Option<usize>,
+    /// Time until the first streamed text chunk.
+    pub ttft_secs: Option<f64>,
+    /// Real-time factor, for transcription.
+    pub rtf: Option<f64>,
+    /// Word error rate against `--reference`, for transcription.
+    pub wer: Option<f64>,
+}
+
+impl Sample {
+    pub fn tokens_per_sec(&self) -> Option<f64> {
+        let tokens = self.tokens?;
+        (self.secs > 0.0).then(|| tokens as f64 / self.secs)
+    }
+}
+
+impl fmt::Display for Sample {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.2}s", self.secs)?;
+        if let Some(rate) = self.tokens_per_sec() {
+            write!(f, ", {rate:.1} tok/s")?;
+        }
+        if let Some(ttft) = self.ttft_secs {
+            write!(f, ", TTFT {ttft:.2}s")?;
+        }
+        if let Some(rtf) = self.rtf {
+            write!(f, ", RTF {rtf:.2}x")?;
+        }
+        if let Some(wer) = self.wer {
+            write!(f, ", WER {:.1}%", wer * 100.0)?;
+        }
+        Ok(())
+    }
+}
+
+// ── Statistics ───────────────────────────────────────────────────────────────
+
+/// Summary of one metric over the measured runs.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Stats {
+    pub mean: f64,
+    pub p50: f64,
+    pub p95: f64,
+}
+
+impl Stats {
+    /// Mean and nearest-rank percentiles of `values`; `None` when empty.
+    pub fn from_values(values: &[f64]) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        let mut sorted = values.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        Some(Self {
+            mean: sorted.iter().sum::<f64>() / sorted.len() as f64,
+            p50: percentile(&sorted, 50.0),
+            p95: percentile(&sorted, 95.0),
+        })
+    }
+
+    /// Stats of `metric` over the samples that have it.
+    fn of(samples: &[Sample], metric: impl Fn(&Sample) -> Option<f64>) -> Option<Self> {
+        let values: Vec<f64> = samples.iter().filter_map(metric).collect();
+        Self::from_values(&values)
+    }
+}
+
+/// Nearest-rank percentile of non-empty, ascending `sorted`.
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+// ── Running a preset ─────────────────────────────────────────────────────────
+
+/// A loaded model, ready to run its task.
+enum Workload {
+    Prompt(PromptEnhancer),
+    /// The transcriber, the audio and the `--reference` text.
+    Transcribe(Box<AudioTranscriber>, PathBuf, Option<String>),
+    Chat(CliChat),
+}
+
+impl Workload {
+    async fn load(
+        preset: Preset,
+        task: BenchTask,
+        options: &BenchOptions,
+        reference: Option<&str>,
+    ) -> Result<Self> {
+        let load = &options.load;
+        Ok(match (preset, task) {
+            (Preset::Enhancer(model), BenchTask::Prompt) => {
+                memory::choose(model, load)?;
+                Self::Prompt(PromptEnhancer::from_preset_with_options(model, load).await?)
+            }
+            (Preset::Enhancer(model), _) => {
+                let model = ChatModel::from(model);
+                memory::choose(model, load)?;
+                Self::Chat(CliChat::from_preset_with_options(model, None, load).await?)
+            }
+            (Preset::Transcription(model), _) => {
+                let audio = options
+                    .audio
+                    .clone()
+                    .context("The transcribe task needs --audio")?;
+                memory::choose(model, load)?;
+                Self::Transcribe(
+                    Box::new(AudioTranscriber::from_preset_with_options(model, load).await?),
+                    audio,
+                    reference.map(str::to_string),
+                )
+            }
+        })
+    }
+
+    async fn run_once(&mut self) -> Result<Sample> {
+        match self {
+            Self::Prompt(enhancer) => {
+                stream_timed(enhancer.model(), enhancer.enhance_request(BENCH_SEED)).await
+            }
+            Self::Transcribe(transcriber, audio, reference) => {
+                let result = transcriber.transcribe_file(&*audio, None).await?;
+                let wer = reference.as_deref().map(|reference| {
+                    WordDiff::new(reference, &result.text, &NormalizeOptions::WER)
+                        .stats
+                        .wer()
+                });
+                Ok(Sample {
+                    secs: result.inference_duration.as_secs_f64(),
+                    tokens: None,
+                    ttft_secs: None,
+                    rtf: Some(result.real_time_factor()),
+                    wer,
+                })
+            }
+            Self::Chat(chat) => {
+                chat.clear();
+                let mut sample = Sample::default();
+                for (i, message) in CHAT_EXCHANGE.iter().enumerate() {
+                    let reply = chat.send_streaming(message, |_| {}).await?;
+                    sample.secs += reply.total.as_secs_f64();
+                    *sample.tokens.get_or_insert(0) += reply.completion_tokens;
+                    // The first reply has no history to replay, so its TTFT
+                    // is comparable across presets.
+                    if i == 0 {
+                        sample.ttft_secs = reply.time_to_first_token.map(|d| d.as_secs_f64());
+                    }
+                }
+                Ok(sample)
+            }
+        }
+    }
+}
+
+/// Stream `request` and time it, counting tokens from the reported usage
+/// (or text chunks when none is reported).
+async fn stream_timed(model: &Model, request: RequestBuilder) -> Result<Sample> {
+    let start = Instant::now();
+    let mut ttft = None;
+    let mut chunks = 0;
+    let mut tokens = None;
+    let mut stream = model.stream_chat_request(request).await?;
+    while let Some(response) = stream.next().await {
+        match response {
+            Response::Chunk(chunk) => {
+                if let Some(usage) = &chunk.usage {
+                    tokens = Some(usage.completion_tokens);
+                }
+                let has_text = chunk
+                    .choices
+                    .first()
+                    .and_then(|choice| choice.delta.content.as_deref())
+                    .is_some_and(|content| !content.is_empty());
+                if has_text {
+                    ttft.get_or_insert_with(|| start.elapsed().as_secs_f64());
+                    chunks += 1;
+                }
+            }
+            Response::InternalError(e) | Response::ValidationError(e) => {
+                anyhow::bail!("Generation failed: {e}");
+            }
+            Response::ModelError(msg, _) => anyhow::bail!("Model error: {msg}"),
+            _ => {}
+        }
+    }
+    Ok(Sample {
+        secs: start.elapsed().as_secs_f64(),
+        tokens: Some(tokens.unwrap_or(chunks)),
+        ttft_secs: ttft,
+        rtf: None,
+        wer: None,
+    })
+}
+
+// ── Results ──────────────────────────────────────────────────────────────────
+
+/// Aggregated measurements for one preset.
+#[derive(Debug, Serialize)]
+pub struct PresetResult {
+    /// Preset name as passed to `--models`.
+    pub model: String,
+    pub model_id: String,
+    pub load_secs: f64,
+    /// Peak RSS while loading and running this preset.  Process-wide on
+    /// platforms where the counter can't be reset (everything but Linux).
+    pub peak_rss_bytes: Option<u64>,
+    /// Resident and device memory right after the load.
+    pub memory: LoadMemory,
+    pub latency_secs: Stats,
+    pub tokens_per_sec: Option<Stats>,
+    pub ttft_secs: Option<Stats>,
+    pub real_time_factor: Option<Stats>,
+    /// Word error rate against `--reference`, as a fraction.
+    pub wer: Option<Stats>,
+}
+
+impl PresetResult {
+    fn from_samples(
+        preset: Preset,
+        load_secs: f64,
+        peak_rss_bytes: Option<u64>,
+        memory: LoadMemory,
+        samples: &[Sample],
+    ) -> Result<Self> {
+        Ok(Self {
+            model: preset.name(),
+            model_id: preset.model_id().to_string(),
+            load_secs,
+            peak_rss_bytes,
+            memory,
+            latency_secs: Stats::of(samples, |s| Some(s.secs)).context("No measured iterations")?,
+            tokens_per_sec: Stats::of(samples, Sample::tokens_per_sec),
+            ttft_secs: Stats::of(samples, |s| s.ttft_secs),
+            real_time_factor: Stats::of(samples, |s| s.rtf),
+            wer: Stats::of(samples, |s| s.wer),
+        })
+    }
+}
+
+/// Everything `bench` measured, printed in `--json` mode and written by
+/// `--report *.json`.
+#[derive(Debug, Serialize)]
+pub struct BenchReport {
+    pub task: BenchTask,
+    pub warmup: usize,
+    pub iterations: usize,
+    pub results: Vec<PresetResult>,
+}
+
+/// File format of `--report`, chosen by extension.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReportFormat {
+    Csv,
+    Json,
+}
+
+impl ReportFormat {
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let ext = path.extension().map(|e| e.to_string_lossy().to_lowercase());
+        match ext.as_deref() {
+            Some("csv") => Ok(Self::Csv),
+            Some("json") => Ok(Self::Json),
+            _ => anyhow::bail!("--report must end in .csv or .json, got {}", path.display()),
+        }
+    }
+}
+
+impl BenchReport {
+    /// One row per preset; missing metrics are empty cells.
+    pub fn to_csv(&self) -> String {
+        let metrics = ["latency_secs", "tokens_per_sec", "ttft_secs", "rtf", "wer"];
+        let mut header = vec![
+            "task".to_string(),
+            "model".to_string(),
+            "model_id".to_string(),
+            "load_secs".to_string(),
+            "peak_rss_bytes".to_string(),
+            "resident_bytes".to_string(),
+            "device_bytes".to_string(),
+        ];
+        for metric in metrics {
+            for stat in ["mean", "p50", "p95"] {
+                header.push(format!("{metric}_{stat}"));
+            }
+        }
+
+        let mut out = header.join(",");
+        out.push('\n');
+        for result in &self.results {
+            let mut row = vec![
+                self.task.to_string(),
+                result.model.clone(),
+                result.model_id.clone(),
+                format!("{:.3}", result.load_secs),
+                result
+                    .peak_rss_bytes
+                    .map(|b| b.to_string())
+                    .unwrap_or_default(),
+                result
+                    .memory
+                    .after
+                    .resident_bytes
+                    .map(|b| b.to_string())
+                    .unwrap_or_default(),
+                result
+                    .memory
+                    .after
+                    .device_bytes
+                    .map(|b| b.to_string())
+                    .unwrap_or_default(),
+            ];
+            for stats in [
+                Some(result.latency_secs),
+                result.tokens_per_sec,
+                result.ttft_secs,
+                result.real_time_factor,
+                result.wer,
+            ] {
+                match stats {
+                    Some(s) => row.extend([s.mean, s.p50, s.p95].map(|v| format!("{v:.3}"))),
+                    None => row.extend(std::iter::repeat_n(String::new(), 3)),
+                }
+            }
+            out.push_str(&row.join(","));
+            out.push('\n');
+        }
+        out
+    }
+
+    fn write(&self, path: &Path) -> Result<()> {
+        let contents = match ReportFormat::from_path(path)? {
+            ReportFormat::Csv => self.to_csv(),
+            ReportFormat::Json => serde_json::to_string_pretty(self)?,
+        };
+        std::fs::write(path, contents)
+            .with_context(|| format!("Failed to write report: {}", path.display()))
+    }
+
+    fn print_table(&self) {
+        let dash = || "-".to_string();
+        println!(
+            "{:<14} {:>8} {:>10} {:>24} {:>8} {:>9} {:>8} {:>7}",
+            "Model",
+            "Load",
+            "Peak RSS",
+            "Latency mean/p50/p95",
+            "tok/s",
+            "TTFT p50",
+            "RTF p50",
+            "WER"
+        );
+        for r in &self.results {
+            let latency = format!(
+                "{:.2}/{:.2}/{:.2}s",
+                r.latency_secs.mean, r.latency_secs.p50, r.latency_secs.p95
+            );
+            println!(
+                "{:<14} {:>8} {:>10} {:>24} {:>8} {:>9} {:>8} {:>7}",
+                r.model,
+                format!("{:.1}s", r.load_secs),
+                r.peak_rss_bytes
+                    .map(|b| HumanBytes(b).to_string())
+                    .unwrap_or_else(dash),
+                latency,
+                r.tokens_per_sec
+                    .map(|s| format!("{:.1}", s.mean))
+                    .unwrap_or_else(dash),
+                r.ttft_secs
+                    .map(|s| format!("{:.2}s", s.p50))
+                    .unwrap_or_else(dash),
+                r.real_time_factor
+                    .map(|s| format!("{:.2}x", s.p50))
+                    .unwrap_or_else(dash),
+                r.wer
+                    .map(|s| format!("{:.1}%", s.mean * 100.0))
+                    .unwrap_or_else(dash),
+            );
+        }
+    }
+}
+
+// ── History ──────────────────────────────────────────────────────────────────
+
+/// One measured run, as appended to `--history` (one JSON object per line).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Seconds since the Unix epoch when the run finished.
+    pub timestamp: u64,
+    /// Crate version of the binary.
+    pub version: String,
+    /// `git describe` of the checkout it was built from, when known.
+    pub git: Option<String>,
+    pub task: BenchTask,
+    /// Preset name as passed to `--models`.
+    pub model: String,
+    pub model_id: String,
+    /// `--device`, e.g. `auto` or `cuda:1`.
+    pub device: String,
+    /// `--isq` override; `None` keeps the preset's default.
+    pub isq: Option<String>,
+    /// `--dtype` override; `None` keeps the preset's default.
+    pub dtype: Option<String>,
+    /// Measured run number within this bench, from 1.
+    pub run: usize,
+    /// Load time of the preset, repeated on each of its runs.
+    pub load_secs: f64,
+    /// Generation time, or inference time for transcription.
+    pub secs: f64,
+    pub tokens_per_sec: Option<f64>,
+    pub ttft_secs: Option<f64>,
+    pub rtf: Option<f64>,
+    pub wer: Option<f64>,
+    /// Peak RSS of the preset so far (see [`PresetResult::peak_rss_bytes`]).
+    pub peak_rss_bytes: Option<u64>,
+    /// Device memory right after the load.
+    pub device_bytes: Option<u64>,
+}
+
+impl HistoryEntry {
+    /// The `--isq` / `--dtype` overrides, e.g. "q8_0/f16", or "default".
+    pub fn settings(&self) -> String {
+        match (&self.isq, &self.dtype) {
+            (Some(isq), Some(dtype)) => format!("{isq}/{dtype}"),
+            (Some(value), None) | (None, Some(value)) => value.clone(),
+            (None, None) => "default".to_string(),
+        }
+    }
+}
+
+/// The `--history` file, opened for appending.
+struct HistoryLog {
+    file: File,
+    path: PathBuf,
+}
+
+impl HistoryLog {
+    /// Open (or create) `path` up front, so a bad path fails before any
+    /// model loads.
+    fn open(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open history file: {}", path.display()))?;
+        Ok(Self {
+            file,
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// Append `entry` as one line and flush it to disk, so a crash later in
+    /// the bench keeps every run recorded so far.
+    fn append(&mut self, entry: &HistoryEntry) -> Result<()> {
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+        self.file
+            .write_all(line.as_bytes())
+            .and_then(|()| self.file.sync_data())
+            .with_context(|| format!("Failed to append to {}", self.path.display()))
+    }
+}
+
+/// Name of a clap value, e.g. `q8_0` for [`IsqChoice::Q80`].
+fn value_name(value: Option<impl ValueEnum>) -> Option<String> {
+    value
+        .and_then(|v| v.to_possible_value())
+        .map(|v| v.get_name().to_string())
+}
+
+fn unix_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Read a `--history` file.  Lines that don't parse (e.g. one cut short by
+/// a crash) are skipped with a warning.
+pub fn read_history(path: &Path) -> Result<Vec<HistoryEntry>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read history file: {}", path.display()))?;
+    let mut entries = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(line) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => tracing::warn!("skipping line {} of {}: {e}", i + 1, path.display()),
+        }
+    }
+    Ok(entries)
+}
+
+/// Best, median and latest value of one metric across a preset's runs.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Trend {
+    pub best: f64,
+    pub median: f64,
+    pub latest: f64,
+}
+
+impl Trend {
+    /// `values` in file order; `None` when empty.  "Best" is the lowest
+    /// value, or the highest with `higher_is_better`.
+    pub fn from_values(values: &[f64], higher_is_better: bool) -> Option<Self> {
+        let latest = *values.last()?;
+        let mut sorted = values.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        Some(Self {
+            best: if higher_is_better {
+                sorted[sorted.len() - 1]
+            } else {
+                sorted[0]
+            },
+            median: percentile(&sorted, 50.0),
+            latest,
+        })
+    }
+
+    fn of(
+        entries: &[&HistoryEntry],
+        higher_is_better: bool,
+        metric: impl Fn(&HistoryEntry) -> Option<f64>,
+    ) -> Option<Self> {
+        let values: Vec<f64> = entries.iter().filter_map(|e| metric(e)).collect();
+        Self::from_values(&values, higher_is_better)
+    }
+
+    fn cell(trend: Option<Self>, fmt: impl Fn(f64) -> String) -> String {
+        match trend {
+            Some(t) => format!("{}/{}/{}", fmt(t.best), fmt(t.median), fmt(t.latest)),
+            None => "-".to_string(),
+        }
+    }
+}
+
+/// The runs of one preset on one task, device and set of overrides.
+#[derive(Debug, Serialize)]
+pub struct PresetTrend {
+    pub task: BenchTask,
+    pub model: String,
+    pub device: String,
+    /// See [`HistoryEntry::settings`].
+    pub settings: String,
+    pub runs: usize,
+    /// Version (or `git describe`) of the first and the latest run.
+    pub first_version: String,
+    pub latest_version: String,
+    pub load_secs: Trend,
+    pub latency_secs: Trend,
+    pub tokens_per_sec: Option<Trend>,
+    pub rtf: Option<Trend>,
+    pub wer: Option<Trend>,
+    pub peak_rss_bytes: Option<Trend>,
+}
+
+impl PresetTrend {
+    /// Group `entries` by task, preset, device and overrides, in order of
+    /// first appearance.
+    pub fn aggregate(entries: &[HistoryEntry]) -> Vec<Self> {
+        let mut groups: Vec<Vec<&HistoryEntry>> = Vec::new();
+        for entry in entries {
+            let key = |e: &HistoryEntry| (e.task, e.model.clone(), e.device.clone(), e.settings());
+            match groups.iter_mut().find(|g| key(g[0]) == key(entry)) {
+                Some(group) => group.push(entry),
+                None => groups.push(vec![entry]),
+            }
+        }
+        groups.iter().map(|group| Self::from_group(group)).collect()
+    }
+
+    fn from_group(group: &[&HistoryEntry]) -> Self {
+        let version = |e: &HistoryEntry| e.git.clone().unwrap_or_else(|| e.version.clone());
+        let (first, latest) = (group[0], group[group.len() - 1]);
+        Self {
+            task: first.task,
+            model: first.model.clone(),
+            device: first.device.clone(),
+            settings: first.settings(),
+            runs: group.len(),
+            first_version: version(first),
+            latest_version: version(latest),
+            load_secs: Trend::of(group, false, |e| Some(e.load_secs))
+                .expect("a group has at least one run"),
+            latency_secs: Trend::of(group, false, |e| Some(e.secs))
+                .expect("a group has at least one run"),
+            tokens_per_sec: Trend::of(group, true, |e| e.tokens_per_sec),
+            rtf: Trend::of(group, false, |e| e.rtf),
+            wer: Trend::of(group, false, |e| e.wer),
+            peak_rss_bytes: Trend::of(group, false, |e| e.peak_rss_bytes.map(|b| b as f64)),
+        }
+    }
+}
+
+/// Print one trend table per task.
+fn print_trends(trends: &[PresetTrend]) {
+    let mut tasks: Vec<BenchTask> = Vec::new();
+    for trend in trends {
+        if !tasks.contains(&trend.task) {
+            tasks.push(trend.task);
+        }
+    }
+    for task in tasks {
+        println!("\n== {task} ==  (best/median/latest)");
+        println!(
+            "{:<14} {:<10} {:<8} {:>5} {:>20} {:>20} {:>20} {:>10}  Versions",
+            "Model", "Settings", "Device", "Runs", "Latency", "tok/s", "RTF", "Peak RSS",
+        );
+        for t in trends.iter().filter(|t| t.task == task) {
+            let versions = if t.first_version == t.latest_version {
+                t.latest_version.clone()
+            } else {
+                format!("{} → {}", t.first_version, t.latest_version)
+            };
+            println!(
+                "{:<14} {:<10} {:<8} {:>5} {:>20} {:>20} {:>20} {:>10}  {}",
+                t.model,
+                t.settings,
+                t.device,
+                t.runs,
+                Trend::cell(Some(t.latency_secs), |v| format!("{v:.2}s")),
+                Trend::cell(t.tokens_per_sec, |v| format!("{v:.1}")),
+                Trend::cell(t.rtf, |v| format!("{v:.2}x")),
+                t.peak_rss_bytes
+                    .map(|p| HumanBytes(p.latest as u64).to_string())
+                    .unwrap_or_else(|| "-".to_string()),
+                versions,
+            );
+        }
+    }
+}
+
+/// `bench report`: summarize the runs recorded in a `--history` file.
+pub fn report_history(path: &Path, out: OutputConfig) -> Result<()> {
+    let entries = read_history(path)?;
+    if entries.is_empty() {
+        anyhow::bail!("No runs recorded in {}", path.display());
+    }
+    let trends = PresetTrend::aggregate(&entries);
+    if out.json {
+        return ui::print_json(&trends);
+    }
+    status!(
+        out,
+        "{} run(s) of {} preset(s) in {}",
+        entries.len(),
+        trends.len(),
+        path.display()
+    );
+    print_trends(&trends);
+    Ok(())
+}
+
+// ── CLI entry-point ──────────────────────────────────────────────────────────
+
+/// Settings for [`run`].
+#[derive(Clone, Debug)]
+pub struct BenchOptions {
+    /// Preset names to compare, in order.
+    pub models: Vec<String>,
+    /// Input for the `transcribe` task.
+    pub audio: Option<PathBuf>,
+    /// Ground truth for `audio`; each transcription is scored against it.
+    pub reference: Option<PathBuf>,
+    /// `--isq` / `--dtype` applied to every preset.
+    pub load: LoadOptions,
+    pub warmup: usize,
+    pub iterations: usize,
+    /// Also write the results to this `.csv` / `.json` file.
+    pub report: Option<PathBuf>,
+    /// Append each measured run to this JSON Lines file.
+    pub history: Option<PathBuf>,
+}
+
+/// Benchmark `task` on each preset in turn: load it, run the warmup and
+/// measured iterations, then drop it before loading the next.
+pub async fn run(task: BenchTask, options: BenchOptions, out: OutputConfig) -> Result<()> {
+    if task == BenchTask::Report {
+        anyhow::bail!("`bench report` reads a history file; see `report_history`");
+    }
+    if options.iterations == 0 {
+        anyhow::bail!("--iterations must be at least 1");
+    }
+    if options.load.uqff.has_explicit_path() {
+        anyhow::bail!(
+            "--save-uqff / --from-uqff name a single model's weights; use --cache-quantized with bench"
+        );
+    }
+    if options.load.auto_downgrade {
+        anyhow::bail!(
+            "--auto-downgrade is not supported with bench; it would measure a different preset"
+        );
+    }
+    if let Some(path) = &options.report {
+        ReportFormat::from_path(path)?;
+    }
+    if let Some(audio) = &options.audio
+        && !audio.exists()
+    {
+        anyhow::bail!("Audio file not found: {}", audio.display());
+    }
+    if options.reference.is_some() && task != BenchTask::Transcribe {
+        anyhow::bail!("--reference only applies to the transcribe task");
+    }
+    let reference = options
+        .reference
+        .as_deref()
+        .map(metrics::read_transcript)
+        .transpose()?;
+    let presets = options
+        .models
+        .iter()
+        .map(|name| Preset::resolve(task, name))
+        .collect::<Result<Vec<_>>>()?;
+    if presets.is_empty() {
+        anyhow::bail!("--models must name at least one preset");
+    }
+    let mut history = options
+        .history
+        .as_deref()
+        .map(HistoryLog::open)
+        .transpose()?;
+
+    let plan = Plan {
+        warmup: options.warmup,
+        iterations: options.iterations,
+    };
+    let mut results = Vec::with_capacity(presets.len());
+    let mut peak_is_per_preset = true;
+    for preset in presets {
+        status!(
+            out,
+            "\n== {task}: {} ({}) ==",
+            preset.name(),
+            preset.model_id()
+        );
+        peak_is_per_preset &= memstats::reset_peak_rss();
+
+        let before = MemorySnapshot::capture();
+        let started = Instant::now();
+        let mut workload = Workload::load(preset, task, &options, reference.as_deref()).await?;
+        let load_secs = started.elapsed().as_secs_f64();
+        let memory = MemorySnapshot::capture().since(before);
+        status!(out, "  loaded in {load_secs:.1}s ({})", memory.after);
+
+        let mut samples = Vec::with_capacity(plan.iterations);
+        for run in plan.runs() {
+            let sample = workload
+                .run_once()
+                .await
+                .with_context(|| format!("Benchmark run failed for {}", preset.name()))?;
+            match run {
+                Run::Warmup(i) => status!(out, "  warmup {i}/{}: {sample}", plan.warmup),
+                Run::Measured(i) => status!(out, "  run {i}/{}: {sample}", plan.iterations),
+            }
+            if let (Run::Measured(i), Some(history)) = (run, history.as_mut()) {
+                history.append(&HistoryEntry {
+                    timestamp: unix_secs(),
+                    version: env!("CARGO_PKG_VERSION").to_string(),
+                    git: option_env!("GIT_DESCRIBE").map(str::to_string),
+                    task,
+                    model: preset.name(),
+                    model_id: preset.model_id().to_string(),
+                    device: device::selected().to_string(),
+                    isq: value_name(options.load.isq),
+                    dtype: value_name(options.load.dtype),
+                    run: i,
+                    load_secs,
+                    secs: sample.secs,
+                    tokens_per_sec: sample.tokens_per_sec(),
+                    ttft_secs: sample.ttft_secs,
+                    rtf: sample.rtf,
+                    wer: sample.wer,
+                    peak_rss_bytes: memstats::peak_rss_bytes(),
+                    device_bytes: memory.after.device_bytes,
+                })?;
+            }
+            if run.is_measured() {
+                samples.push(sample);
+            }
+        }
+        let peak = memstats::peak_rss_bytes();
+        drop(workload);
+
+        results.push(PresetResult::from_samples(
+            preset, load_secs, peak, memory, &samples,
+        )?);
+    }
+
+    let report = BenchReport {
+        task,
+        warmup: plan.warmup,
+        iterations: plan.iterations,
+        results,
+    };
+    if let Some(path) = &options.report {
+        report.write(path)?;
+        status!(out, "\nReport written to {}", path.display());
+    }
+    if let Some(history) = &history {
+        status!(out, "Runs appended to {}", history.path.display());
+    }
+
+    if out.json {
+        return ui::print_json(&report);
+    }
+
+    println!();
+    report.print_table();
+    match report.warmup {
+        0 => println!("\nNo warm-up: the first run includes kernel compilation and cache setup."),
+        n => println!("\nWarm-up: {n} unmeasured run(s) per preset before timing."),
+    }
+    if !peak_is_per_preset && report.results.len() > 1 {
+        println!(
+            "\nNote: peak RSS can't be reset on this platform, so it includes earlier presets.  \
+             Bench one preset per run for exact numbers."
+        );
+    }
+    Ok(())
+}
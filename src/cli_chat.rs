@@ -1,17 +1,33 @@
-use anyhow::Result;
-use mistralrs::{
-    IsqType, Model, ModelDType, RequestBuilder, TextMessageRole, TextModelBuilder,
-    VisionModelBuilder,
-};
-use std::fmt;
+use anyhow::{Context, Result};
+use mistralrs::{IsqType, Model, ModelDType, RequestBuilder, Response, TextMessageRole};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Write as _};
 use std::io::{self, Write};
-use std::time::{Duration, Instant};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+
+use crate::clipboard;
+use crate::disk_space;
+use crate::error::Error;
+use crate::interrupt::{self, Interrupt};
+use crate::load_options::{GB, LoadOptions, LoadSettings};
+use crate::loader::{self, ModelKind};
+use crate::local_model::LocalModel;
+use crate::memory::{self, MemoryFootprint};
+use crate::memstats::MemorySnapshot;
 
 /// Available chat model presets.
 ///
 /// These match the presets used by `promp_enhancer.rs` so both modules use
 /// identical model IDs and loading strategies.
 use crate::promp_enhancer::EnhancerModel;
+use crate::sampler::{self, SamplerArgs, SamplerConfig};
+use crate::sessions::Session;
+use crate::timeout;
+use crate::ui::{OutputConfig, detail, fmt_duration};
+use crate::usage::Usage;
 
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub enum ChatModel {
@@ -24,6 +40,16 @@ pub enum ChatModel {
 
     /// Phi-3.5-mini — strongest quality, larger memory footprint (~2.8 GB with Q4K).
     Phi35Mini,
+
+    /// Qwen2.5-1.5B-Instruct — very small and fast (~1.8 GB with Q8_0).
+    Qwen25_1_5b,
+
+    /// Llama 3.2 3B Instruct — gated, needs Meta's license accepted (~2.3 GB with Q4K).
+    Llama32_3b,
+
+    /// SmolLM2-135M-Instruct — tiny, for CI smoke runs (~0.1 GB with Q4K).
+    #[cfg(feature = "ci-models")]
+    Micro,
 }
 
 impl From<EnhancerModel> for ChatModel {
@@ -32,6 +58,10 @@ impl From<EnhancerModel> for ChatModel {
             EnhancerModel::GemmaE2b => Self::GemmaE2b,
             EnhancerModel::GemmaE4b => Self::GemmaE4b,
             EnhancerModel::Phi35Mini => Self::Phi35Mini,
+            EnhancerModel::Qwen25_1_5b => Self::Qwen25_1_5b,
+            EnhancerModel::Llama32_3b => Self::Llama32_3b,
+            #[cfg(feature = "ci-models")]
+            EnhancerModel::Micro => Self::Micro,
         }
     }
 }
@@ -43,6 +73,10 @@ impl ChatModel {
             Self::GemmaE2b => "google/gemma-3n-E2B-it",
             Self::GemmaE4b => "google/gemma-3n-E4B-it",
             Self::Phi35Mini => "microsoft/Phi-3.5-mini-instruct",
+            Self::Qwen25_1_5b => "Qwen/Qwen2.5-1.5B-Instruct",
+            Self::Llama32_3b => "meta-llama/Llama-3.2-3B-Instruct",
+            #[cfg(feature = "ci-models")]
+            Self::Micro => EnhancerModel::Micro.model_id(),
         }
     }
 
@@ -52,6 +86,10 @@ impl ChatModel {
             Self::GemmaE2b => "Gemma 3n E2B",
             Self::GemmaE4b => "Gemma 3n E4B",
             Self::Phi35Mini => "Phi-3.5-mini",
+            Self::Qwen25_1_5b => "Qwen2.5-1.5B",
+            Self::Llama32_3b => "Llama 3.2 3B",
+            #[cfg(feature = "ci-models")]
+            Self::Micro => "SmolLM2-135M",
         }
     }
 
@@ -61,36 +99,112 @@ impl ChatModel {
             Self::GemmaE2b => "~1.5 GB (Q4K)",
             Self::GemmaE4b => "~8 GB (F16)",
             Self::Phi35Mini => "~2.8 GB (Q4K)",
+            Self::Qwen25_1_5b => "~1.8 GB (Q8_0)",
+            Self::Llama32_3b => "~2.3 GB (Q4K)",
+            #[cfg(feature = "ci-models")]
+            Self::Micro => "~0.1 GB (Q4K)",
+        }
+    }
+
+    /// Effective parameter count in billions, for memory estimates when
+    /// `--isq` / `--dtype` override the preset.
+    fn approx_params_billions(self) -> f64 {
+        match self {
+            Self::GemmaE2b => 2.0,
+            Self::GemmaE4b => 4.0,
+            Self::Phi35Mini => 3.8,
+            Self::Qwen25_1_5b => 1.5,
+            Self::Llama32_3b => 3.2,
+            #[cfg(feature = "ci-models")]
+            Self::Micro => 0.135,
+        }
+    }
+
+    /// Memory footprint with `opts` applied: the preset string when the
+    /// settings are unchanged, otherwise an estimate.
+    pub fn memory_estimate(self, opts: &LoadOptions) -> String {
+        let settings = opts.apply(self.default_settings());
+        if settings == self.default_settings() {
+            self.approx_memory().to_string()
+        } else {
+            settings.approx_memory(self.approx_params_billions())
         }
     }
 
-    /// Build the [`Model`] with preset-specific dtype / ISQ settings.
+    /// [`approx_memory`](Self::approx_memory) in bytes.
+    pub fn approx_bytes(self) -> u64 {
+        let bytes = match self {
+            Self::GemmaE2b => 1.5 * GB,
+            Self::GemmaE4b => 8.0 * GB,
+            Self::Phi35Mini => 2.8 * GB,
+            Self::Qwen25_1_5b => 1.8 * GB,
+            Self::Llama32_3b => 2.3 * GB,
+            #[cfg(feature = "ci-models")]
+            Self::Micro => 0.1 * GB,
+        };
+        bytes as u64
+    }
+
+    /// Size of the weights downloaded on first use, checked against free
+    /// disk space before the download starts.  ISQ quantizes after the
+    /// download, so this is the repo's 16-bit safetensors.
+    pub fn download_size_bytes(self) -> u64 {
+        let bytes = match self {
+            Self::GemmaE2b => 10.9 * GB,
+            Self::GemmaE4b => 15.7 * GB,
+            Self::Phi35Mini => 7.7 * GB,
+            Self::Qwen25_1_5b => 3.1 * GB,
+            Self::Llama32_3b => 6.5 * GB,
+            #[cfg(feature = "ci-models")]
+            Self::Micro => crate::promp_enhancer::MICRO_DOWNLOAD_BYTES,
+        };
+        bytes as u64
+    }
+
+    /// The preset's default dtype / ISQ.
+    pub fn default_settings(self) -> LoadSettings {
+        match self {
+            Self::GemmaE2b => LoadSettings::isq(IsqType::Q4K),
+            Self::GemmaE4b => LoadSettings::dtype(ModelDType::F16),
+            Self::Phi35Mini => LoadSettings::isq(IsqType::Q4K),
+            Self::Qwen25_1_5b => LoadSettings::isq(IsqType::Q8_0),
+            Self::Llama32_3b => LoadSettings::isq(IsqType::Q4K),
+            #[cfg(feature = "ci-models")]
+            Self::Micro => LoadSettings::isq(IsqType::Q4K),
+        }
+    }
+
+    /// Build the [`Model`] with preset-specific dtype / ISQ settings, as
+    /// overridden by `opts`.
     ///
     /// Gemma 3n variants use a multimodal architecture and are loaded through
-    /// [`VisionModelBuilder`] even for text chat.
-    async fn build_model(self) -> Result<Model> {
-        match self {
-            Self::GemmaE2b => {
-                VisionModelBuilder::new(self.model_id())
-                    .with_isq(IsqType::Q4K)
-                    .with_logging()
-                    .build()
-                    .await
-            }
-            Self::GemmaE4b => {
-                VisionModelBuilder::new(self.model_id())
-                    .with_dtype(ModelDType::F16)
-                    .with_logging()
-                    .build()
-                    .await
-            }
-            Self::Phi35Mini => {
-                TextModelBuilder::new(self.model_id())
-                    .with_isq(IsqType::Q4K)
-                    .with_logging()
-                    .build()
-                    .await
-            }
+    /// [`VisionModelBuilder`](mistralrs::VisionModelBuilder) even for text chat.
+    async fn build_model(self, opts: &LoadOptions) -> Result<Model> {
+        let settings = opts.apply(self.default_settings());
+        let kind = match self {
+            Self::GemmaE2b | Self::GemmaE4b => ModelKind::Vision,
+            Self::Phi35Mini | Self::Qwen25_1_5b | Self::Llama32_3b => ModelKind::Text,
+            #[cfg(feature = "ci-models")]
+            Self::Micro => ModelKind::Text,
+        };
+        disk_space::preflight(self.model_id(), self.download_size_bytes())?;
+        loader::build_model(self.model_id(), kind, settings, &opts.uqff)
+            .await
+            .map_err(|source| opts.load_error(self.model_id(), source).into())
+    }
+}
+
+impl MemoryFootprint for ChatModel {
+    const FALLBACK: Self = Self::GemmaE2b;
+
+    /// The preset figure when the settings are unchanged, otherwise an
+    /// estimate, like [`memory_estimate`](ChatModel::memory_estimate).
+    fn required_bytes(self, opts: &LoadOptions) -> u64 {
+        let settings = opts.apply(self.default_settings());
+        if settings == self.default_settings() {
+            self.approx_bytes()
+        } else {
+            settings.approx_bytes(self.approx_params_billions())
         }
     }
 }
@@ -101,57 +215,371 @@ impl fmt::Display for ChatModel {
     }
 }
 
-/// Format a `Duration` as `Xm Ys` (e.g. "2m 30.5s") or `Ys` under a minute.
-fn fmt_duration(d: Duration) -> String {
-    let total_secs = d.as_secs_f64();
-    let mins = (total_secs / 60.0).floor() as u64;
-    let secs = total_secs - (mins as f64 * 60.0);
-    if mins > 0 {
-        format!("{}m {:.1}s", mins, secs)
-    } else {
-        format!("{:.1}s", secs)
-    }
+/// Format seconds since the Unix epoch as `YYYY-MM-DD HH:MM:SS UTC`.
+pub fn fmt_utc(unix_secs: u64) -> String {
+    let (days, secs) = (unix_secs / 86_400, unix_secs % 86_400);
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm).
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02} UTC",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// Seconds since the Unix epoch for `time` (0 if the clock is before it).
+pub fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Default token budget for the system prompt plus replayed history, kept
+/// well inside the smallest preset context window (Gemma 3n, 32k) so
+/// long sessions stay fast.
+const DEFAULT_MAX_HISTORY_TOKENS: usize = 4096;
+
+/// Default sampling for chat replies: temperature 0.7, top-p 0.95, up to 512
+/// tokens per reply.
+pub const DEFAULT_SAMPLER: SamplerConfig = SamplerConfig::new(0.7, 0.95, 512);
+
+/// Stage name for [`Error::Generation`] and [`TimedOut`](timeout::TimedOut).
+const REPLY_STAGE: &str = "Chat reply";
+
+/// Rough token count for `text`: about four characters per token for
+/// English with the presets' tokenizers.  Only used for budgeting, so
+/// being slightly off is fine.
+pub(crate) fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
 }
 
 /// A single conversation message.
-#[derive(Clone, Debug)]
-struct ChatTurn {
-    role: TextMessageRole,
-    content: String,
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ChatTurn {
+    #[serde(with = "role_name")]
+    pub role: TextMessageRole,
+    pub content: String,
+    /// Seconds since the Unix epoch when the message was sent (user) or
+    /// finished (assistant).  Missing in chats saved before it was recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+    /// Seconds the assistant took to reply.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_secs: Option<f64>,
+}
+
+impl ChatTurn {
+    /// A message sent now by `role`, without a latency.
+    pub fn now(role: TextMessageRole, content: impl Into<String>) -> Self {
+        Self {
+            role,
+            content: content.into(),
+            timestamp: Some(unix_secs(SystemTime::now())),
+            latency_secs: None,
+        }
+    }
+}
+
+/// Serializes [`TextMessageRole`] (which mistral.rs doesn't derive serde for)
+/// as its lowercase name, e.g. `"user"`.  Unknown names round-trip through
+/// `TextMessageRole::Custom`.
+mod role_name {
+    use mistralrs::TextMessageRole;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(role: &TextMessageRole, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(match role {
+            TextMessageRole::User => "user",
+            TextMessageRole::Assistant => "assistant",
+            TextMessageRole::System => "system",
+            TextMessageRole::Tool => "tool",
+            TextMessageRole::Custom(name) => name,
+        })
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<TextMessageRole, D::Error> {
+        let name = String::deserialize(d)?;
+        Ok(match name.as_str() {
+            "user" => TextMessageRole::User,
+            "assistant" => TextMessageRole::Assistant,
+            "system" => TextMessageRole::System,
+            "tool" => TextMessageRole::Tool,
+            _ => TextMessageRole::Custom(name),
+        })
+    }
+}
+
+/// What a [`SavedChat`] holds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SavedKind {
+    /// A `chat` conversation.
+    #[default]
+    Chat,
+    /// A `prompt --interactive` refinement: the seed, then each version of
+    /// the prompt as an assistant turn after the instruction that made it.
+    Prompt,
+}
+
+impl fmt::Display for SavedKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Chat => "chat",
+            Self::Prompt => "prompt",
+        })
+    }
+}
+
+/// On-disk format of `/save` / `/load` and of `--session` files: the
+/// conversation plus everything needed to continue it with the same
+/// behaviour.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedChat {
+    /// Format version, bumped on incompatible changes.
+    pub version: u32,
+    /// Missing in files written before prompt sessions existed, which are
+    /// all chats.
+    #[serde(default)]
+    pub kind: SavedKind,
+    pub system_prompt: String,
+    pub temperature: f64,
+    pub top_p: f64,
+    pub max_len: usize,
+    pub history: Vec<ChatTurn>,
+}
+
+impl SavedChat {
+    pub const VERSION: u32 = 1;
+
+    /// Read a file written by [`write`](Self::write), checking its version.
+    pub fn read(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let saved: Self = serde_json::from_str(&json)
+            .with_context(|| format!("Not a saved chat: {}", path.display()))?;
+        if saved.version != Self::VERSION {
+            anyhow::bail!(
+                "Unsupported chat file version {} in {} (expected {})",
+                saved.version,
+                path.display(),
+                Self::VERSION
+            );
+        }
+        Ok(saved)
+    }
+
+    /// Write to `path` as JSON.  The file is written beside `path` and
+    /// renamed over it, so a crash mid-write leaves the previous version.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+        std::fs::write(&tmp, json)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        std::fs::rename(&tmp, path).with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// A streamed assistant reply with its timing breakdown.
+pub struct StreamedReply {
+    /// Time from sending the request until the first non-empty chunk.
+    /// `None` if the model produced no text at all.
+    pub time_to_first_token: Option<Duration>,
+    /// Time from sending the request until the stream finished.
+    pub total: Duration,
+    /// Oldest history messages dropped to fit `max_history_tokens` before
+    /// this request was sent.
+    pub trimmed: usize,
+    /// Generated tokens, as reported by the model's usage stats, or the
+    /// number of text chunks when none were reported.
+    pub completion_tokens: usize,
+    /// Prompt and completion tokens over `total`; `None` when the model
+    /// reported no usage stats.
+    pub usage: Option<Usage>,
 }
 
 /// Interactive chat session state.
 pub struct CliChat {
-    model: Model,
+    model: Arc<Model>,
+    /// The model's HuggingFace ID or local path, recorded on the tracing
+    /// spans; see [`with_model_id`](Self::with_model_id).
+    model_id: Option<String>,
     system_prompt: String,
     history: Vec<ChatTurn>,
-    temperature: f64,
-    top_p: f64,
-    max_len: usize,
+    sampler: SamplerConfig,
+    max_history_tokens: usize,
+    /// Limit per reply: `--timeout`, if given.
+    timeout: Option<Duration>,
 }
 
 impl CliChat {
-    /// Build a chat session from model preset.
-    pub async fn from_preset(model: ChatModel, system_prompt: Option<String>) -> Result<Self> {
-        let loaded = model.build_model().await?;
-        Ok(Self {
-            model: loaded,
+    /// Build a chat session from a model preset, with `--isq` / `--dtype`
+    /// overrides applied on top of the preset's defaults.
+    pub async fn from_preset_with_options(
+        model: ChatModel,
+        system_prompt: Option<String>,
+        opts: &LoadOptions,
+    ) -> Result<Self, Error> {
+        let loaded = model
+            .build_model(opts)
+            .await
+            .map_err(|source| Error::model_load(model.model_id(), source))?;
+        Ok(Self::from_model(Arc::new(loaded), system_prompt).with_model_id(model.model_id()))
+    }
+
+    /// Load a model from a local GGUF file or HuggingFace-layout directory
+    /// instead of a preset, for offline use.
+    pub async fn from_local_path(
+        path: &Path,
+        system_prompt: Option<String>,
+        opts: &LoadOptions,
+    ) -> Result<Self, Error> {
+        let loaded = async { LocalModel::from_path(path)?.build(opts).await }
+            .await
+            .map_err(|source| Error::model_load(path.display().to_string(), source))?;
+        Ok(Self::from_model(Arc::new(loaded), system_prompt)
+            .with_model_id(path.display().to_string()))
+    }
+
+    /// Build a chat session around an already-loaded model.
+    pub fn from_model(model: Arc<Model>, system_prompt: Option<String>) -> Self {
+        Self {
+            model,
+            model_id: None,
             system_prompt: system_prompt.unwrap_or_else(|| {
                 "You are a helpful, concise assistant. Answer clearly and accurately.".to_string()
             }),
             history: Vec::new(),
-            temperature: 0.7,
-            top_p: 0.95,
-            max_len: 512,
+            sampler: DEFAULT_SAMPLER,
+            max_history_tokens: DEFAULT_MAX_HISTORY_TOKENS,
+            timeout: timeout::default_limit(),
+        }
+    }
+
+    /// Send a throwaway one-token request so kernel compilation and cache
+    /// setup don't land in the first reply.  The history is untouched.
+    /// Returns how long it took.
+    pub async fn warm_up(&self) -> Result<Duration, Error> {
+        let request = RequestBuilder::new()
+            .set_sampler_max_len(1)
+            .add_message(TextMessageRole::System, &self.system_prompt)
+            .add_message(TextMessageRole::User, "Hi");
+
+        let start = Instant::now();
+        timeout::within(
+            self.timeout,
+            "Warm-up",
+            self.model.send_chat_request(request),
+        )
+        .await
+        .map_err(|source| Error::generation("Warm-up", source))?;
+        Ok(start.elapsed())
+    }
+
+    /// Send one user message and stream the assistant response.
+    ///
+    /// `on_token` is called with each text chunk as soon as it arrives.  The
+    /// assembled reply is recorded in history once the stream finishes.  If
+    /// the returned future is dropped mid-stream (e.g. on Ctrl-C) or the
+    /// reply runs past the timeout, the turn is discarded and generation
+    /// stops.
+    #[tracing::instrument(
+        name = "chat",
+        skip_all,
+        fields(
+            model_id = self.model_id.as_deref(),
+            prompt_words = user_message.split_whitespace().count()
+        )
+    )]
+    pub async fn send_streaming<F>(
+        &mut self,
+        user_message: &str,
+        mut on_token: F,
+    ) -> Result<StreamedReply, Error>
+    where
+        F: FnMut(&str),
+    {
+        let dropped = self.trim_history(user_message);
+        let request = self.build_request(user_message);
+
+        let sent_at = SystemTime::now();
+        let start = Instant::now();
+        let mut time_to_first_token = None;
+        let mut text = String::new();
+        let mut chunks = 0;
+        let mut usage = None;
+
+        let streaming = async {
+            let mut stream = self.model.stream_chat_request(request).await?;
+            while let Some(response) = stream.next().await {
+                match response {
+                    Response::Chunk(chunk) => {
+                        if let Some(reported) = &chunk.usage {
+                            usage = Some(reported.clone());
+                        }
+                        let Some(content) = chunk
+                            .choices
+                            .first()
+                            .and_then(|choice| choice.delta.content.as_deref())
+                        else {
+                            continue;
+                        };
+                        if content.is_empty() {
+                            continue;
+                        }
+                        time_to_first_token.get_or_insert_with(|| start.elapsed());
+                        chunks += 1;
+                        on_token(content);
+                        text.push_str(content);
+                    }
+                    Response::InternalError(e) | Response::ValidationError(e) => {
+                        anyhow::bail!("Generation failed: {e}");
+                    }
+                    Response::ModelError(msg, _) => anyhow::bail!("Model error: {msg}"),
+                    _ => {}
+                }
+            }
+            Ok::<_, anyhow::Error>(())
+        };
+        // The stream's borrow of the model ends here, before history is updated.
+        timeout::within(self.timeout, REPLY_STAGE, streaming)
+            .await
+            .map_err(|source| Error::generation(REPLY_STAGE, source))?;
+
+        let trimmed = self.sampler.trim_stop(&text).trim();
+        let assistant = if trimmed.is_empty() {
+            String::from("(empty response)")
+        } else {
+            trimmed.to_string()
+        };
+        let total = start.elapsed();
+        self.push_turn(user_message, sent_at, &assistant, total);
+
+        Ok(StreamedReply {
+            time_to_first_token,
+            total,
+            trimmed: dropped,
+            completion_tokens: usage.as_ref().map_or(chunks, |u| u.completion_tokens),
+            usage: usage.map(|u| Usage::from_response(&u, total)),
         })
     }
 
-    /// Send one user message and return assistant response.
-    pub async fn send(&mut self, user_message: &str) -> Result<String> {
-        let mut request = RequestBuilder::new()
-            .set_sampler_temperature(self.temperature)
-            .set_sampler_topp(self.top_p)
-            .set_sampler_max_len(self.max_len)
+    /// Build a request containing the system prompt, prior history, and the
+    /// new user message.
+    fn build_request(&self, user_message: &str) -> RequestBuilder {
+        let mut request = self
+            .sampler
+            .apply(RequestBuilder::new())
             .add_message(TextMessageRole::System, &self.system_prompt);
 
         // Replay prior conversation for context.
@@ -160,105 +588,775 @@ impl CliChat {
         }
 
         // Add current user turn.
-        request = request.add_message(TextMessageRole::User, user_message);
+        request.add_message(TextMessageRole::User, user_message)
+    }
 
-        let response = self.model.send_chat_request(request).await?;
-        let assistant = response.choices[0]
-            .message
-            .content
-            .as_ref()
-            .map(|c| c.trim().to_string())
-            .unwrap_or_else(|| String::from("(empty response)"));
+    /// Estimated tokens of the system prompt plus the stored history.
+    pub fn estimated_tokens(&self) -> usize {
+        estimate_tokens(&self.system_prompt)
+            + self
+                .history
+                .iter()
+                .map(|turn| estimate_tokens(&turn.content))
+                .sum::<usize>()
+    }
 
-        // Persist turn history.
+    /// Drop the oldest exchanges until the system prompt, history and
+    /// `user_message` fit in `max_history_tokens`.  Turns are removed in
+    /// user/assistant pairs; the system prompt is never dropped.  Returns the
+    /// number of messages removed.
+    fn trim_history(&mut self, user_message: &str) -> usize {
+        let mut total = self.estimated_tokens() + estimate_tokens(user_message);
+        let mut removed = 0;
+        while total > self.max_history_tokens && removed < self.history.len() {
+            let pair = (self.history.len() - removed).min(2);
+            total -= self.history[removed..removed + pair]
+                .iter()
+                .map(|turn| estimate_tokens(&turn.content))
+                .sum::<usize>();
+            removed += pair;
+        }
+        self.history.drain(..removed);
+        removed
+    }
+
+    /// Persist a completed user/assistant exchange: the user message sent
+    /// at `sent_at` and the reply that took `latency`.
+    fn push_turn(
+        &mut self,
+        user_message: &str,
+        sent_at: SystemTime,
+        assistant: &str,
+        latency: Duration,
+    ) {
         self.history.push(ChatTurn {
             role: TextMessageRole::User,
             content: user_message.to_string(),
+            timestamp: Some(unix_secs(sent_at)),
+            latency_secs: None,
         });
         self.history.push(ChatTurn {
             role: TextMessageRole::Assistant,
-            content: assistant.clone(),
+            content: assistant.to_string(),
+            timestamp: Some(unix_secs(sent_at + latency)),
+            latency_secs: Some(latency.as_secs_f64()),
         });
+    }
+
+    /// Name the model on this session's tracing spans.  The loading
+    /// constructors set it; one built with [`from_model`](Self::from_model)
+    /// has none until this is called.
+    pub fn with_model_id(mut self, model_id: impl Into<String>) -> Self {
+        self.model_id = Some(model_id.into());
+        self
+    }
+
+    /// Override the sampling settings (default [`DEFAULT_SAMPLER`]).
+    pub fn with_sampler(mut self, sampler: SamplerConfig) -> Self {
+        self.sampler = sampler;
+        self
+    }
+
+    /// The sampling settings used for every reply.
+    pub fn sampler(&self) -> &SamplerConfig {
+        &self.sampler
+    }
+
+    /// The system prompt sent at the start of every request.
+    pub fn system_prompt(&self) -> &str {
+        &self.system_prompt
+    }
+
+    /// Replace the system prompt mid-session.  The history is cleared
+    /// unless `keep_history`, since earlier replies followed the old prompt.
+    pub fn set_system_prompt(&mut self, prompt: &str, keep_history: bool) -> Result<()> {
+        let prompt = prompt.trim();
+        if prompt.is_empty() {
+            anyhow::bail!("the system prompt can't be empty");
+        }
+        self.system_prompt = prompt.to_string();
+        if !keep_history {
+            self.history.clear();
+        }
+        Ok(())
+    }
+
+    /// Change the sampling temperature (>= 0) for the following replies.
+    pub fn set_temperature(&mut self, temperature: f64) -> Result<()> {
+        sampler::check_temperature(temperature).map_err(|e| anyhow::anyhow!("temperature {e}"))?;
+        self.sampler = self.sampler.clone().with_temperature(temperature);
+        Ok(())
+    }
+
+    /// Change top-p, in (0, 1], for the following replies.
+    pub fn set_top_p(&mut self, top_p: f64) -> Result<()> {
+        sampler::check_top_p(top_p).map_err(|e| anyhow::anyhow!("top-p {e}"))?;
+        self.sampler = self.sampler.clone().with_top_p(top_p);
+        Ok(())
+    }
+
+    /// Change the maximum reply length in tokens (at least 1).
+    pub fn set_max_len(&mut self, max_len: usize) -> Result<()> {
+        if max_len == 0 {
+            anyhow::bail!("max length must be at least 1 token");
+        }
+        self.sampler = self.sampler.clone().with_max_len(max_len);
+        Ok(())
+    }
+
+    /// Override the token budget for the system prompt plus replayed history
+    /// (default 4096).  Older exchanges are dropped to stay within it.
+    pub fn with_max_history_tokens(mut self, max_history_tokens: usize) -> Self {
+        self.max_history_tokens = max_history_tokens;
+        self
+    }
 
-        Ok(assistant)
+    /// The token budget for the system prompt plus replayed history.
+    pub fn max_history_tokens(&self) -> usize {
+        self.max_history_tokens
     }
 
     /// Clear conversation history but keep loaded model and system prompt.
     pub fn clear(&mut self) {
         self.history.clear();
     }
+
+    /// Number of messages in the history (user and assistant turns).
+    pub fn history_len(&self) -> usize {
+        self.history.len()
+    }
+
+    /// Text of the most recent assistant turn, if any.
+    pub fn last_reply(&self) -> Option<&str> {
+        self.history
+            .iter()
+            .rev()
+            .find(|turn| matches!(turn.role, TextMessageRole::Assistant))
+            .map(|turn| turn.content.as_str())
+    }
+
+    /// Write the history, system prompt and sampler settings to `path` as
+    /// JSON.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        self.to_saved().write(path)
+    }
+
+    /// The history, system prompt and sampler settings in the `/save`
+    /// format.
+    pub fn to_saved(&self) -> SavedChat {
+        SavedChat {
+            version: SavedChat::VERSION,
+            kind: SavedKind::Chat,
+            system_prompt: self.system_prompt.clone(),
+            temperature: self.sampler.temperature,
+            top_p: self.sampler.top_p,
+            max_len: self.sampler.max_len,
+            history: self.history.clone(),
+        }
+    }
+
+    /// Render the conversation as Markdown: the system prompt, then one
+    /// section per message headed by its role, time and (for replies)
+    /// latency.  Message text is copied verbatim, so code blocks survive;
+    /// a reply cut off inside a fence gets the fence closed so the next
+    /// heading still renders.
+    pub fn export_markdown(&self) -> String {
+        let capacity = self.system_prompt.len()
+            + self
+                .history
+                .iter()
+                .map(|turn| turn.content.len() + 64)
+                .sum::<usize>()
+            + 128;
+        let mut md = String::with_capacity(capacity);
+        // Writing to a String can't fail.
+        let _ = writeln!(md, "# Chat transcript\n");
+        let _ = writeln!(md, "_Exported {}_\n", fmt_utc(unix_secs(SystemTime::now())));
+        let _ = writeln!(md, "## System\n\n{}\n", self.system_prompt.trim_end());
+
+        for turn in &self.history {
+            let role = match &turn.role {
+                TextMessageRole::User => "User",
+                TextMessageRole::Assistant => "Assistant",
+                TextMessageRole::System => "System",
+                TextMessageRole::Tool => "Tool",
+                TextMessageRole::Custom(name) => name,
+            };
+            md.push_str("## ");
+            md.push_str(role);
+            if let Some(timestamp) = turn.timestamp {
+                let _ = write!(md, " · {}", fmt_utc(timestamp));
+            }
+            if let Some(latency) = turn.latency_secs {
+                let _ = write!(md, " · {}", fmt_duration(Duration::from_secs_f64(latency)));
+            }
+            md.push_str("\n\n");
+            md.push_str(turn.content.trim_end());
+            md.push('\n');
+            let fences = turn
+                .content
+                .lines()
+                .filter(|line| line.trim_start().starts_with("```"))
+                .count();
+            if !fences.is_multiple_of(2) {
+                md.push_str("```\n");
+            }
+            md.push('\n');
+        }
+        md
+    }
+
+    /// Write [`export_markdown`](Self::export_markdown) to `path`.
+    pub fn export(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, self.export_markdown())
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Replace the history, system prompt and sampler settings with those
+    /// saved in `path` by [`save`](Self::save).  The loaded model is kept.
+    pub fn load(&mut self, path: &Path) -> Result<()> {
+        self.restore(SavedChat::read(path)?)
+    }
+
+    /// Replace the history, system prompt and sampler settings with
+    /// `saved`'s.  Fails for a prompt-refinement session.
+    pub fn restore(&mut self, saved: SavedChat) -> Result<()> {
+        if saved.kind != SavedKind::Chat {
+            anyhow::bail!("This is a saved {} session, not a chat", saved.kind);
+        }
+        self.system_prompt = saved.system_prompt;
+        self.sampler = self
+            .sampler
+            .clone()
+            .with_temperature(saved.temperature)
+            .with_top_p(saved.top_p)
+            .with_max_len(saved.max_len);
+        self.history = saved.history;
+        Ok(())
+    }
+}
+
+/// Spawn a dedicated thread that reads stdin line by line.
+///
+/// A blocking `read_line` can't be raced against Ctrl-C, so input is handed
+/// over through a channel instead.  The channel closes on EOF.
+pub fn spawn_stdin_reader() -> mpsc::UnboundedReceiver<io::Result<String>> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    std::thread::spawn(move || {
+        let stdin = io::stdin();
+        loop {
+            let mut line = String::new();
+            match stdin.read_line(&mut line) {
+                Ok(0) => break,
+                Ok(_) => {
+                    if tx.send(Ok(line)).is_err() {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    let _ = tx.send(Err(e));
+                    break;
+                }
+            }
+        }
+    });
+    rx
+}
+
+// ── Input ────────────────────────────────────────────────────────────────────
+
+/// Printed by `/help`.
+const HELP: &str = "Commands:
+  /help   Show this help
+  /clear  Clear chat history
+  /stats  Show history size and estimated token usage
+  /show   Show the system prompt, sampling settings and turns so far
+  /copy   Copy the last reply to the clipboard
+  /system [--keep-history] <prompt>  Replace the system prompt (clears history)
+  /temp <t>     Set the temperature (>= 0)
+  /topp <p>     Set top-p, in (0, 1]
+  /maxlen <n>   Set the maximum reply length in tokens
+  /save <path>  Save history and settings as JSON
+  /load [--replace] <path>  Restore a saved chat
+  /export <path.md>  Write the conversation as Markdown
+  /exit   Quit
+  /quit   Quit";
+
+/// One line typed at the `you>` prompt, parsed without touching the chat.
+///
+/// Path arguments are empty when the command was given without one, so the
+/// loop can print its usage.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChatInput<'a> {
+    /// Blank line: prompt again.
+    Empty,
+    /// Text to send to the model.
+    Message(&'a str),
+    Help,
+    Clear,
+    Stats,
+    Show,
+    /// `/copy`: put the last reply on the clipboard.
+    Copy,
+    Exit,
+    System {
+        prompt: &'a str,
+        /// `--keep-history`: don't clear the history.
+        keep_history: bool,
+    },
+    /// `/temp`, with the unparsed value.
+    Temperature(&'a str),
+    /// `/topp`, with the unparsed value.
+    TopP(&'a str),
+    /// `/maxlen`, with the unparsed value.
+    MaxLen(&'a str),
+    Save(&'a str),
+    Export(&'a str),
+    Load {
+        path: &'a str,
+        /// `--replace`: don't ask before replacing the history.
+        replace: bool,
+    },
+    /// A `/word` that isn't a command, e.g. a typo of one.
+    Unknown(&'a str),
+}
+
+impl<'a> ChatInput<'a> {
+    /// Parse a line as read from stdin.  Surrounding whitespace, including
+    /// the `\r` of a Windows line ending, is ignored.
+    pub fn parse(line: &'a str) -> Self {
+        let input = line.trim();
+        match input {
+            "" => return Self::Empty,
+            "/help" => return Self::Help,
+            "/clear" => return Self::Clear,
+            "/stats" => return Self::Stats,
+            "/show" => return Self::Show,
+            "/copy" => return Self::Copy,
+            "/exit" | "/quit" => return Self::Exit,
+            _ => {}
+        }
+        if let Some(arg) = command_arg(input, "/system") {
+            return match command_arg(arg, "--keep-history") {
+                Some(prompt) => Self::System {
+                    prompt,
+                    keep_history: true,
+                },
+                None => Self::System {
+                    prompt: arg,
+                    keep_history: false,
+                },
+            };
+        }
+        if let Some(value) = command_arg(input, "/temp") {
+            return Self::Temperature(value);
+        }
+        if let Some(value) = command_arg(input, "/topp") {
+            return Self::TopP(value);
+        }
+        if let Some(value) = command_arg(input, "/maxlen") {
+            return Self::MaxLen(value);
+        }
+        if let Some(path) = command_arg(input, "/save") {
+            return Self::Save(path);
+        }
+        if let Some(path) = command_arg(input, "/export") {
+            return Self::Export(path);
+        }
+        if let Some(arg) = command_arg(input, "/load") {
+            return match command_arg(arg, "--replace") {
+                Some(path) => Self::Load {
+                    path,
+                    replace: true,
+                },
+                None => Self::Load {
+                    path: arg,
+                    replace: false,
+                },
+            };
+        }
+        // `/` followed by a word is a mistyped command; a path such as
+        // `/etc/hosts is …` or a lone `/` is a message.
+        let word = input.split_whitespace().next().unwrap_or_default();
+        if word.len() > 1
+            && word.starts_with('/')
+            && word[1..].chars().all(|c| c.is_ascii_alphabetic())
+        {
+            return Self::Unknown(word);
+        }
+        Self::Message(input)
+    }
+}
+
+/// If `input` is `command` or starts with `command` followed by whitespace,
+/// return the (trimmed) rest of the line.
+pub fn command_arg<'a>(input: &'a str, command: &str) -> Option<&'a str> {
+    let rest = input.strip_prefix(command)?;
+    if rest.is_empty() || rest.starts_with(char::is_whitespace) {
+        Some(rest.trim())
+    } else {
+        None
+    }
+}
+
+/// `chat` settings chosen on the command line.
+#[derive(Clone, Debug, Default)]
+pub struct ChatOptions {
+    /// Custom system prompt (`None` = a general-purpose assistant prompt).
+    pub system_prompt: Option<String>,
+    /// `--temperature` / `--top-p` / `--max-tokens` overrides.
+    pub sampler: SamplerArgs,
+    /// Token budget for the system prompt plus history (`None` = 4096).
+    pub max_history_tokens: Option<usize>,
+    /// `--warmup`: send a throwaway request after loading so the first
+    /// reply's timing leaves out first-request overhead.
+    pub warmup: bool,
+    /// `--session`: keep the conversation under this name, resume it on
+    /// start and save it after every turn.
+    pub session: Option<String>,
 }
 
 /// Run an interactive CLI chat session.
 ///
+/// Replies are streamed token by token.  Ctrl-C (delivered through
+/// `interrupt`) while a reply is streaming aborts that reply and returns to
+/// the prompt; Ctrl-C at the prompt exits.
+///
 /// Commands:
 /// - `/help`  : show command help
 /// - `/clear` : clear chat history
+/// - `/stats` : show history size and estimated token usage
+/// - `/show`  : show the system prompt, sampling settings and turn count
+/// - `/copy`  : copy the last reply to the clipboard
+/// - `/system [--keep-history] <prompt>` : replace the system prompt
+/// - `/temp <t>`, `/topp <p>`, `/maxlen <n>` : change sampling
+/// - `/save <path>` : save history and settings as JSON
+/// - `/load [--replace] <path>` : restore a saved chat
+/// - `/export <path.md>` : write the conversation as Markdown
 /// - `/exit`  : quit
 /// - `/quit`  : quit
-pub async fn run(model: Option<EnhancerModel>) -> Result<()> {
+pub async fn run(
+    model: Option<EnhancerModel>,
+    model_path: Option<PathBuf>,
+    load: LoadOptions,
+    options: ChatOptions,
+    interrupt: Interrupt,
+    out: OutputConfig,
+) -> Result<()> {
+    let ChatOptions {
+        system_prompt,
+        sampler,
+        max_history_tokens,
+        warmup,
+        session,
+    } = options;
     let preset = model.unwrap_or_default();
     let preset: ChatModel = preset.into();
 
-    println!("Loading chat model: {preset}");
-    println!("  Memory estimate: {}", preset.approx_memory());
+    if max_history_tokens == Some(0) {
+        anyhow::bail!("--max-history-tokens must be at least 1");
+    }
+
+    // Before the load, so a session open elsewhere fails fast.
+    let session = session.as_deref().map(Session::open).transpose()?;
 
+    let before = MemorySnapshot::capture();
     let load_start = Instant::now();
-    let mut chat = CliChat::from_preset(preset, None).await?;
+    let mut cancel = interrupt.token();
+    let loading = async {
+        match &model_path {
+            Some(path) => {
+                println!("Loading chat model: {}", path.display());
+                Ok::<_, anyhow::Error>(CliChat::from_local_path(path, system_prompt, &load).await?)
+            }
+            None => {
+                let (preset, load) = memory::choose(preset, &load)?;
+                println!("Loading chat model: {preset}");
+                println!("  Memory estimate: {}", preset.memory_estimate(&load));
+                let settings = load.apply(preset.default_settings());
+                if let Some(weights) = load.uqff.describe(preset.model_id(), settings) {
+                    println!("  {weights}");
+                }
+                Ok(CliChat::from_preset_with_options(preset, system_prompt, &load).await?)
+            }
+        }
+    };
+    let mut chat = interrupt::unless_cancelled(Some(&cancel), loading).await?;
+    chat = chat.with_sampler(sampler.apply(DEFAULT_SAMPLER));
+    if let Some(n) = max_history_tokens {
+        chat = chat.with_max_history_tokens(n);
+    }
     println!("Model loaded in {}", fmt_duration(load_start.elapsed()));
+    println!("{}", MemorySnapshot::capture().since(before).banner());
+    if warmup {
+        let warm_up = interrupt::unless_cancelled(Some(&cancel), chat.warm_up()).await?;
+        println!("Warm-up: {}", fmt_duration(warm_up));
+    }
+    if let Some(session) = &session {
+        match session.load(SavedKind::Chat)? {
+            Some(saved) => {
+                chat.restore(saved)?;
+                println!(
+                    "Resumed session `{}`: {} messages",
+                    session.name(),
+                    chat.history_len()
+                );
+            }
+            None => println!(
+                "Started session `{}` ({})",
+                session.name(),
+                session.path().display()
+            ),
+        }
+    }
+    detail!(out, "System prompt:\n{}", chat.system_prompt());
 
     println!();
     println!("Interactive chat is ready.");
     println!("Type your message and press Enter.");
-    println!("Commands: /help, /clear, /exit, /quit");
+    println!(
+        "Commands: /help, /show, /copy, /system, /temp, /topp, /maxlen, /clear, /stats, /save, \
+         /load, /export, /exit, /quit \
+         (Ctrl-C interrupts a reply)"
+    );
     println!();
 
-    let stdin = io::stdin();
+    let mut lines = spawn_stdin_reader();
 
     loop {
+        // Whatever the last command or reply changed is on disk before
+        // waiting for the next one.
+        if let Some(session) = &session
+            && let Err(err) = session.save(&chat.to_saved())
+        {
+            tracing::warn!("{err:#}");
+        }
         print!("you> ");
         io::stdout().flush()?;
 
-        let mut input = String::new();
-        let n = stdin.read_line(&mut input)?;
-        if n == 0 {
+        let line = tokio::select! {
+            line = lines.recv() => line,
+            _ = cancel.cancelled() => {
+                println!("\nExiting.");
+                break;
+            }
+        };
+        let Some(line) = line else {
             // EOF (Ctrl-D / piped input end).
             println!("\nExiting.");
             break;
-        }
+        };
+        let line = line?;
 
-        let input = input.trim();
-        if input.is_empty() {
-            continue;
-        }
-
-        match input {
-            "/exit" | "/quit" => {
+        let input = match ChatInput::parse(&line) {
+            ChatInput::Empty => continue,
+            ChatInput::Message(input) => input,
+            ChatInput::Exit => {
                 println!("Exiting.");
                 break;
             }
-            "/help" => {
-                println!("Commands:");
-                println!("  /help   Show this help");
-                println!("  /clear  Clear chat history");
-                println!("  /exit   Quit");
-                println!("  /quit   Quit");
+            ChatInput::Help => {
+                println!("{HELP}");
                 continue;
             }
-            "/clear" => {
+            ChatInput::Clear => {
                 chat.clear();
                 println!("History cleared.");
                 continue;
             }
-            _ => {}
-        }
+            ChatInput::Stats => {
+                let messages = chat.history_len();
+                println!("Turns: {} ({messages} messages)", messages.div_ceil(2));
+                println!(
+                    "Estimated tokens: ~{} of {} (system prompt + history)",
+                    chat.estimated_tokens(),
+                    chat.max_history_tokens()
+                );
+                println!();
+                continue;
+            }
+            ChatInput::Show => {
+                let sampler = chat.sampler();
+                println!("System prompt  : {}", chat.system_prompt());
+                println!("Temperature    : {}", sampler.temperature);
+                println!("Top-p          : {}", sampler.top_p);
+                println!("Max tokens     : {}", sampler.max_len);
+                println!("Turns          : {}", chat.history_len().div_ceil(2));
+                println!();
+                continue;
+            }
+            ChatInput::Copy => {
+                match chat.last_reply() {
+                    Some(reply) => {
+                        if clipboard::copy(reply) {
+                            println!("Copied the last reply ({} chars).", reply.chars().count());
+                        }
+                    }
+                    None => println!("No reply to copy yet."),
+                }
+                println!();
+                continue;
+            }
+            ChatInput::System {
+                prompt,
+                keep_history,
+            } => {
+                let turns = chat.history_len().div_ceil(2);
+                match chat.set_system_prompt(prompt, keep_history) {
+                    Ok(()) if keep_history || turns == 0 => println!("System prompt updated."),
+                    Ok(()) => println!("System prompt updated; cleared {turns} turns."),
+                    Err(_) => println!("Usage: /system [--keep-history] <prompt>"),
+                }
+                println!();
+                continue;
+            }
+            ChatInput::Temperature(value) => {
+                match value
+                    .parse()
+                    .map_err(anyhow::Error::from)
+                    .and_then(|t| chat.set_temperature(t))
+                {
+                    Ok(()) => println!("Temperature set to {}.", chat.sampler().temperature),
+                    Err(e) => {
+                        println!("error: {e:#}\nUsage: /temp <t>, with t >= 0 (e.g. /temp 0.7)")
+                    }
+                }
+                println!();
+                continue;
+            }
+            ChatInput::TopP(value) => {
+                match value
+                    .parse()
+                    .map_err(anyhow::Error::from)
+                    .and_then(|p| chat.set_top_p(p))
+                {
+                    Ok(()) => println!("Top-p set to {}.", chat.sampler().top_p),
+                    Err(e) => {
+                        println!("error: {e:#}\nUsage: /topp <p>, with 0 < p <= 1 (e.g. /topp 0.9)")
+                    }
+                }
+                println!();
+                continue;
+            }
+            ChatInput::MaxLen(value) => {
+                match value
+                    .parse()
+                    .map_err(anyhow::Error::from)
+                    .and_then(|n| chat.set_max_len(n))
+                {
+                    Ok(()) => println!("Max tokens set to {}.", chat.sampler().max_len),
+                    Err(e) => {
+                        println!("error: {e:#}\nUsage: /maxlen <n>, with n >= 1 (e.g. /maxlen 256)")
+                    }
+                }
+                println!();
+                continue;
+            }
+            ChatInput::Unknown(command) => {
+                println!("Unknown command: {command} (type /help for the list)");
+                println!();
+                continue;
+            }
+            ChatInput::Save(path) => {
+                if path.is_empty() {
+                    println!("Usage: /save <path>");
+                } else {
+                    match chat.save(Path::new(path)) {
+                        Ok(()) => println!("Saved {} messages to {path}", chat.history_len()),
+                        Err(e) => println!("error: {e:#}"),
+                    }
+                }
+                println!();
+                continue;
+            }
+            ChatInput::Export(path) => {
+                if path.is_empty() {
+                    println!("Usage: /export <path.md>");
+                } else {
+                    match chat.export(Path::new(path)) {
+                        Ok(()) => println!("Exported {} messages to {path}", chat.history_len()),
+                        Err(e) => println!("error: {e:#}"),
+                    }
+                }
+                println!();
+                continue;
+            }
+            ChatInput::Load { path, replace } => {
+                if path.is_empty() {
+                    println!("Usage: /load [--replace] <path>");
+                    println!();
+                    continue;
+                }
+                if !replace && chat.history_len() > 0 {
+                    print!(
+                        "Replace the current {} messages? [y/N] ",
+                        chat.history_len()
+                    );
+                    io::stdout().flush()?;
+                    let answer = tokio::select! {
+                        answer = lines.recv() => answer.transpose()?.unwrap_or_default(),
+                        _ = cancel.cancelled() => {
+                            println!("\nExiting.");
+                            break;
+                        }
+                    };
+                    if !matches!(answer.trim(), "y" | "Y" | "yes") {
+                        println!("Load cancelled.");
+                        println!();
+                        continue;
+                    }
+                }
+                match chat.load(Path::new(path)) {
+                    Ok(()) => println!("Loaded {} messages from {path}", chat.history_len()),
+                    Err(e) => println!("error: {e:#}"),
+                }
+                println!();
+                continue;
+            }
+        };
 
-        let turn_start = Instant::now();
-        let reply = chat.send(input).await?;
-        let elapsed = turn_start.elapsed();
+        print!("assistant> ");
+        io::stdout().flush()?;
+
+        let reply = tokio::select! {
+            reply = chat.send_streaming(input, |token| {
+                print!("{token}");
+                let _ = io::stdout().flush();
+            }) => Some(reply),
+            _ = cancel.cancelled() => None,
+        };
 
-        println!("assistant> {}", reply);
-        println!("(latency: {})", fmt_duration(elapsed));
+        match reply {
+            Some(Err(err)) if err.is_timed_out() => {
+                println!("\n({} — reply discarded)", err.inner());
+            }
+            Some(Err(err)) => return Err(err.into()),
+            Some(Ok(reply)) => {
+                println!();
+                if reply.trimmed > 0 {
+                    println!(
+                        "(dropped {} oldest messages to fit the context budget)",
+                        reply.trimmed
+                    );
+                }
+                let tokens = reply
+                    .usage
+                    .map(|usage| format!(", {usage}"))
+                    .unwrap_or_default();
+                match reply.time_to_first_token {
+                    Some(ttft) => println!(
+                        "(first token: {}, total: {}{tokens})",
+                        fmt_duration(ttft),
+                        fmt_duration(reply.total)
+                    ),
+                    None => println!("(total: {}{tokens})", fmt_duration(reply.total)),
+                }
+            }
+            None => {
+                println!("\n(interrupted — reply discarded)");
+                cancel = interrupt.reset();
+            }
+        }
         println!();
     }
 
@@ -0,0 +1,88 @@
+use anyhow::Result;
+
+// ── Backends ─────────────────────────────────────────────────────────────────
+
+/// Somewhere `prompt --copy` and chat `/copy` can put text.
+///
+/// [`SystemClipboard`] is the real one; anything else (e.g. a fake that
+/// records what it was given) can stand in through [`copy_with`].
+pub trait Clipboard {
+    /// Replace the clipboard's contents with `text`.
+    fn set_text(&mut self, text: &str) -> Result<()>;
+}
+
+/// The system clipboard, through arboard.
+///
+/// On X11 the contents belong to this process: they stay available after
+/// it exits only if a clipboard manager picks them up.
+#[cfg(feature = "clipboard")]
+pub struct SystemClipboard(arboard::Clipboard);
+
+#[cfg(feature = "clipboard")]
+impl SystemClipboard {
+    /// Connect to the clipboard.  Fails on headless systems (no display
+    /// server) and where the platform has none.
+    pub fn open() -> Result<Self> {
+        use anyhow::Context;
+
+        arboard::Clipboard::new()
+            .map(Self)
+            .context("Failed to open the system clipboard")
+    }
+}
+
+#[cfg(feature = "clipboard")]
+impl Clipboard for SystemClipboard {
+    fn set_text(&mut self, text: &str) -> Result<()> {
+        use anyhow::Context;
+
+        self.0
+            .set_text(text)
+            .context("Failed to copy to the system clipboard")
+    }
+}
+
+/// Stand-in for builds without the `clipboard` feature (e.g. iOS and CI):
+/// it can't be opened, so every copy ends in a warning.
+#[cfg(not(feature = "clipboard"))]
+pub struct SystemClipboard;
+
+#[cfg(not(feature = "clipboard"))]
+impl SystemClipboard {
+    pub fn open() -> Result<Self> {
+        anyhow::bail!("this build has no clipboard support; rebuild with `--features clipboard`")
+    }
+}
+
+#[cfg(not(feature = "clipboard"))]
+impl Clipboard for SystemClipboard {
+    fn set_text(&mut self, _text: &str) -> Result<()> {
+        Ok(())
+    }
+}
+
+// ── Copying ──────────────────────────────────────────────────────────────────
+
+/// Copy `text` to `clipboard`.  A failure is printed as a warning rather
+/// than returned, since the text has been printed anyway.  Returns whether
+/// it was copied.
+pub fn copy_with(clipboard: &mut dyn Clipboard, text: &str) -> bool {
+    match clipboard.set_text(text) {
+        Ok(()) => true,
+        Err(err) => {
+            tracing::warn!("{err:#}");
+            false
+        }
+    }
+}
+
+/// Copy `text` to the system clipboard, warning if it can't be reached.
+pub fn copy(text: &str) -> bool {
+    match SystemClipboard::open() {
+        Ok(mut clipboard) => copy_with(&mut clipboard, text),
+        Err(err) => {
+            tracing::warn!("{err:#}");
+            false
+        }
+    }
+}
@@ -0,0 +1,486 @@
+use anyhow::{Context, Result};
+use clap::parser::ValueSource;
+use clap::{ArgMatches, Command, Parser};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use crate::ui::{self, OutputConfig};
+
+// ── Locations ────────────────────────────────────────────────────────────────
+
+/// Name of the config file, in the working directory or the user config
+/// directory.
+pub const FILE_NAME: &str = "mistralrs-example.toml";
+
+/// `~/.config/mistralrs-example/mistralrs-example.toml`, or under
+/// `XDG_CONFIG_HOME` when set.
+pub fn user_config_path() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => {
+            let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+            PathBuf::from(home).join(".config")
+        }
+    };
+    Some(base.join("mistralrs-example").join(FILE_NAME))
+}
+
+/// Where config files are looked for, lowest precedence first: the user
+/// config, then the working directory.
+pub fn search_paths() -> Vec<PathBuf> {
+    user_config_path()
+        .into_iter()
+        .chain(std::iter::once(PathBuf::from(FILE_NAME)))
+        .collect()
+}
+
+// ── Config ───────────────────────────────────────────────────────────────────
+
+/// One `key = value` from a config file.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Setting {
+    /// Subcommand table the key is in (`[prompt]`); `None` for top-level
+    /// keys, which set global flags.
+    pub section: Option<String>,
+    /// Flag name without the dashes, as written (`max-tokens` or
+    /// `max_tokens`).
+    pub key: String,
+    pub value: toml::Value,
+    /// File the value came from.
+    pub file: PathBuf,
+}
+
+impl Setting {
+    fn location(&self) -> String {
+        match &self.section {
+            Some(section) => format!("`{}` in [{section}] of {}", self.key, self.file.display()),
+            None => format!("`{}` in {}", self.key, self.file.display()),
+        }
+    }
+}
+
+/// Defaults from the config files, merged.
+///
+/// ```toml
+/// device = "cpu"           # top level: global flags
+///
+/// [prompt]                 # one table per subcommand
+/// model = "gemma-e2b"
+/// isq = "q4k"
+/// temperature = 0.9
+///
+/// [image]
+/// out = "renders/"
+/// ```
+///
+/// Keys are the subcommand's long flags.  A key in a later file replaces
+/// the same key from an earlier one; flags given on the command line
+/// always win over both.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Config {
+    /// Files read, lowest precedence first.
+    pub files: Vec<PathBuf>,
+    /// Every setting after merging, one per section and key.
+    pub settings: Vec<Setting>,
+}
+
+impl Config {
+    /// Read whichever of [`search_paths`] exist.
+    pub fn load() -> Result<Self> {
+        let paths: Vec<PathBuf> = search_paths()
+            .into_iter()
+            .filter(|path| path.is_file())
+            .collect();
+        Self::from_files(&paths)
+    }
+
+    /// Read and merge `paths`, lowest precedence first.  The same file
+    /// reached by two paths is read once.
+    pub fn from_files(paths: &[PathBuf]) -> Result<Self> {
+        let mut config = Self::default();
+        let mut seen = Vec::new();
+        for path in paths {
+            let canonical = path.canonicalize().unwrap_or_else(|_| path.clone());
+            if seen.contains(&canonical) {
+                continue;
+            }
+            seen.push(canonical);
+            let text = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            config.merge(parse_file(&text, path)?);
+            config.files.push(path.clone());
+        }
+        Ok(config)
+    }
+
+    /// Add `settings`, replacing any with the same section and key.
+    pub fn merge(&mut self, settings: Vec<Setting>) {
+        for setting in settings {
+            let key = normalize(&setting.key);
+            self.settings
+                .retain(|s| !(s.section == setting.section && normalize(&s.key) == key));
+            self.settings.push(setting);
+        }
+        self.settings
+            .sort_by(|a, b| (&a.section, normalize(&a.key)).cmp(&(&b.section, normalize(&b.key))));
+    }
+
+    /// Drop (with a warning) settings that don't name a flag of `cli`.
+    pub fn retain_known(&mut self, cli: &Command) {
+        self.settings.retain(|setting| match resolve(cli, setting) {
+            Ok(_) => true,
+            Err(reason) => {
+                eprintln!("warning: ignoring {}: {reason}", setting.location());
+                false
+            }
+        });
+    }
+
+    /// Parse the command line with these settings as defaults.
+    ///
+    /// Exits like `P::parse()` on `--help` or a command-line error.  A
+    /// setting is skipped when its flag, or one it conflicts with (say
+    /// `model` against `--model-path`), was given on the command line, and
+    /// when its subcommand isn't the one running.  A setting whose value
+    /// clap rejects is dropped with a warning naming the key and file.
+    pub fn parse<P: Parser>(&self) -> P {
+        self.parse_from(std::env::args_os().collect())
+    }
+
+    /// [`parse`](Self::parse) with explicit arguments.
+    pub fn parse_from<P: Parser>(&self, args: Vec<OsString>) -> P {
+        let cli = P::command();
+        let matches = cli
+            .clone()
+            .try_get_matches_from(&args)
+            .unwrap_or_else(|err| err.exit());
+
+        let mut accepted = args;
+        let mut final_matches = matches.clone();
+        for setting in &self.settings {
+            let tokens = match defaults_for(&cli, &matches, setting) {
+                Ok(Some(tokens)) => tokens,
+                Ok(None) => continue,
+                Err(reason) => {
+                    eprintln!("warning: ignoring {}: {reason}", setting.location());
+                    continue;
+                }
+            };
+            let candidate = with_flags(&accepted, tokens);
+            match cli.clone().try_get_matches_from(&candidate) {
+                Ok(matches) => {
+                    accepted = candidate;
+                    final_matches = matches;
+                }
+                Err(err) => eprintln!(
+                    "warning: ignoring {}: {}",
+                    setting.location(),
+                    first_line(&err.to_string())
+                ),
+            }
+        }
+        P::from_arg_matches(&final_matches).unwrap_or_else(|err| err.exit())
+    }
+}
+
+// ── Parsing ──────────────────────────────────────────────────────────────────
+
+/// The settings in one config file: top-level keys, plus one table per
+/// subcommand.
+pub fn parse_file(text: &str, path: &Path) -> Result<Vec<Setting>> {
+    let table: toml::Table =
+        toml::from_str(text).with_context(|| format!("Invalid config file: {}", path.display()))?;
+    let mut settings = Vec::new();
+    for (key, value) in table {
+        match value {
+            toml::Value::Table(table) => {
+                for (name, value) in table {
+                    settings.push(Setting {
+                        section: Some(key.clone()),
+                        key: name,
+                        value,
+                        file: path.to_path_buf(),
+                    });
+                }
+            }
+            value => settings.push(Setting {
+                section: None,
+                key,
+                value,
+                file: path.to_path_buf(),
+            }),
+        }
+    }
+    Ok(settings)
+}
+
+/// `max_tokens` and `max-tokens` name the same flag.
+fn normalize(key: &str) -> String {
+    key.replace('-', "_")
+}
+
+fn first_line(message: &str) -> &str {
+    let line = message.lines().next().unwrap_or_default();
+    line.strip_prefix("error: ").unwrap_or(line)
+}
+
+/// The subcommand and argument `setting` refers to, or why it doesn't
+/// refer to one.
+fn resolve<'a>(
+    cli: &'a Command,
+    setting: &Setting,
+) -> Result<(&'a Command, &'a clap::Arg), String> {
+    let command = match &setting.section {
+        Some(section) => cli
+            .find_subcommand(section)
+            .ok_or_else(|| format!("there is no `{section}` subcommand"))?,
+        None => cli,
+    };
+    let id = normalize(&setting.key);
+    let is_flag = |arg: &&clap::Arg| arg.get_id() == id.as_str() && arg.get_long().is_some();
+    let arg = command
+        .get_arguments()
+        .find(is_flag)
+        // Global flags can be set per subcommand too.
+        .or_else(|| {
+            cli.get_arguments()
+                .filter(|arg| arg.is_global_set())
+                .find(is_flag)
+        })
+        .ok_or_else(|| match &setting.section {
+            Some(section) => format!(
+                "`{section}` has no --{} flag",
+                setting.key.replace('_', "-")
+            ),
+            None => format!(
+                "there is no global --{} flag (subcommand flags go in a [subcommand] table)",
+                setting.key.replace('_', "-")
+            ),
+        })?;
+    Ok((command, arg))
+}
+
+/// The flags to add for `setting` given what is on the command line:
+/// `None` when the command line already decides it.
+fn defaults_for(
+    cli: &Command,
+    matches: &ArgMatches,
+    setting: &Setting,
+) -> Result<Option<Vec<OsString>>, String> {
+    let (command, arg) = resolve(cli, setting)?;
+
+    // Only the subcommand that is running, and not when it runs a nested
+    // one (`transcribe diff`).
+    let sub_matches = match (&setting.section, matches.subcommand()) {
+        (None, _) => None,
+        (Some(_), Some((name, sub))) if name == command.get_name() => {
+            if sub.subcommand().is_some() {
+                return Ok(None);
+            }
+            Some(sub)
+        }
+        (Some(_), _) => return Ok(None),
+    };
+    // Ask each level only about its own arguments; clap panics on ids it
+    // doesn't know.
+    let defines = |command: &Command, id: &str| command.get_arguments().any(|a| a.get_id() == id);
+    let given = |id: &str| {
+        let on_command_line = |m: &ArgMatches| m.value_source(id) == Some(ValueSource::CommandLine);
+        (defines(cli, id) && on_command_line(matches))
+            || sub_matches.is_some_and(|sub| defines(command, id) && on_command_line(sub))
+    };
+
+    if given(arg.get_id().as_str()) {
+        return Ok(None);
+    }
+    let scope = if command.get_name() == cli.get_name() {
+        cli
+    } else {
+        command
+    };
+    let conflicting = scope
+        .get_arguments()
+        .filter(|other| given(other.get_id().as_str()))
+        .any(|other| {
+            scope.get_arg_conflicts_with(arg).contains(&other)
+                || scope.get_arg_conflicts_with(other).contains(&arg)
+        });
+    if conflicting {
+        return Ok(None);
+    }
+
+    let long = format!(
+        "--{}",
+        arg.get_long().expect("resolve only returns long flags")
+    );
+    let takes_value = arg.get_action().takes_values();
+    let values = match &setting.value {
+        toml::Value::Boolean(set) if !takes_value => {
+            return Ok(set.then(|| vec![OsString::from(long)]));
+        }
+        _ if !takes_value => return Err("expected true or false".to_string()),
+        toml::Value::Array(items) => items.iter().map(scalar).collect::<Result<Vec<_>, _>>()?,
+        value => vec![scalar(value)?],
+    };
+    Ok(Some(
+        values
+            .into_iter()
+            .map(|value| OsString::from(format!("{long}={value}")))
+            .collect(),
+    ))
+}
+
+/// A TOML scalar as it would be typed on the command line.
+fn scalar(value: &toml::Value) -> Result<String, String> {
+    match value {
+        toml::Value::String(s) => Ok(s.clone()),
+        toml::Value::Integer(n) => Ok(n.to_string()),
+        toml::Value::Float(x) => Ok(x.to_string()),
+        toml::Value::Boolean(b) => Ok(b.to_string()),
+        _ => Err(format!("unsupported value {value}")),
+    }
+}
+
+/// `args` with `flags` appended, before a `--` separator if there is one.
+fn with_flags(args: &[OsString], flags: Vec<OsString>) -> Vec<OsString> {
+    let at = args
+        .iter()
+        .skip(1)
+        .position(|arg| arg == "--")
+        .map_or(args.len(), |i| i + 1);
+    let mut out = args[..at].to_vec();
+    out.extend(flags);
+    out.extend_from_slice(&args[at..]);
+    out
+}
+
+// ── Writing ──────────────────────────────────────────────────────────────────
+
+/// Set each `(section, key, value)` in the config file at `path`, creating
+/// it (and its directory) if needed.  A key already there under either
+/// spelling (`enhancer_model` / `enhancer-model`) is replaced.
+///
+/// Other settings are kept, but the file is rewritten, so comments and
+/// formatting are lost.
+pub fn write_settings(path: &Path, values: &[(&str, &str, toml::Value)]) -> Result<()> {
+    let mut table: toml::Table = if path.is_file() {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        toml::from_str(&text).with_context(|| format!("Invalid config file: {}", path.display()))?
+    } else {
+        toml::Table::new()
+    };
+    for (section, key, value) in values {
+        let entry = table
+            .entry(section.to_string())
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+        let toml::Value::Table(section_table) = entry else {
+            anyhow::bail!(
+                "`{section}` in {} is a value, not a [{section}] table",
+                path.display()
+            );
+        };
+        section_table.retain(|name, _| normalize(name) != normalize(key));
+        section_table.insert(key.to_string(), value.clone());
+    }
+
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    let text = toml::to_string_pretty(&table).context("Failed to serialize the config")?;
+    std::fs::write(path, text).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// `config` as one TOML file: the merged settings, top-level keys first,
+/// under a comment naming the files they came from.
+pub fn effective_toml(config: &Config) -> Result<String> {
+    let mut table = toml::Table::new();
+    for setting in &config.settings {
+        let section = match &setting.section {
+            None => Some(&mut table),
+            Some(name) => match table
+                .entry(name.clone())
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+            {
+                toml::Value::Table(section) => Some(section),
+                // A top-level key with the subcommand's name; config show
+                // lists both.
+                _ => None,
+            },
+        };
+        if let Some(section) = section {
+            section.insert(setting.key.clone(), setting.value.clone());
+        }
+    }
+
+    let mut text = String::new();
+    if config.files.is_empty() {
+        text.push_str("# No config file found.\n");
+    } else {
+        text.push_str("# Merged from, later ones taking precedence:\n");
+        for path in &config.files {
+            text.push_str(&format!("#   {}\n", path.display()));
+        }
+    }
+    text.push('\n');
+    text.push_str(&toml::to_string_pretty(&table).context("Failed to serialize the config")?);
+    Ok(text)
+}
+
+// ── CLI entry-point ──────────────────────────────────────────────────────────
+
+/// `config show`: the files read and every setting they contribute, as
+/// TOML annotated with where each value came from (or as JSON).
+pub fn show(config: &Config, out: OutputConfig) -> Result<()> {
+    if out.json {
+        #[derive(Serialize)]
+        struct JsonConfig<'a> {
+            searched: Vec<PathBuf>,
+            files: &'a [PathBuf],
+            settings: &'a [Setting],
+        }
+        return ui::print_json(&JsonConfig {
+            searched: search_paths(),
+            files: &config.files,
+            settings: &config.settings,
+        });
+    }
+
+    if config.files.is_empty() {
+        println!("# No config file found. Looked for:");
+        for path in search_paths() {
+            println!("#   {}", path.display());
+        }
+        return Ok(());
+    }
+    println!("# Config files, later ones taking precedence:");
+    for path in &config.files {
+        println!("#   {}", path.display());
+    }
+
+    let mut sections: BTreeMap<Option<&str>, Vec<&Setting>> = BTreeMap::new();
+    for setting in &config.settings {
+        sections
+            .entry(setting.section.as_deref())
+            .or_default()
+            .push(setting);
+    }
+    for (section, settings) in sections {
+        println!();
+        if let Some(section) = section {
+            println!("[{section}]");
+        }
+        for setting in settings {
+            println!(
+                "{} = {}  # {}",
+                setting.key,
+                setting.value,
+                setting.file.display()
+            );
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,132 @@
+use anyhow::{Context, Result};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+// ── Device ───────────────────────────────────────────────────────────────────
+
+/// Compute backend chosen with `--device`.
+///
+/// mistral.rs picks the best backend compiled into the binary on its own
+/// (CUDA, then Metal, then CPU); `Auto` keeps that.  The others pin it, so
+/// a misbehaving GPU path can be bypassed or a GPU confirmed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Device {
+    #[default]
+    Auto,
+    Cpu,
+    Metal,
+    /// CUDA device with this ordinal (`cuda` = `cuda:0`).
+    Cuda(usize),
+}
+
+impl FromStr for Device {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "auto" => Ok(Self::Auto),
+            "cpu" => Ok(Self::Cpu),
+            "metal" => Ok(Self::Metal),
+            "cuda" => Ok(Self::Cuda(0)),
+            other => match other.strip_prefix("cuda:") {
+                Some(ordinal) => ordinal
+                    .parse()
+                    .map(Self::Cuda)
+                    .with_context(|| format!("invalid CUDA ordinal {ordinal:?}")),
+                None => anyhow::bail!("expected auto, cpu, metal, cuda or cuda:N, got {s:?}"),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Device {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Auto => write!(f, "auto"),
+            Self::Cpu => write!(f, "cpu"),
+            Self::Metal => write!(f, "metal"),
+            Self::Cuda(ordinal) => write!(f, "cuda:{ordinal}"),
+        }
+    }
+}
+
+impl Device {
+    /// Fail unless this binary was built with the backend.  Only compile-time
+    /// support can be checked up front; a missing GPU is reported by
+    /// mistral.rs when the model loads.
+    pub fn check_available(self) -> Result<()> {
+        match self {
+            Self::Auto | Self::Cpu => Ok(()),
+            Self::Metal if cfg!(any(target_os = "macos", target_os = "ios")) => Ok(()),
+            Self::Metal => {
+                anyhow::bail!("--device metal is only available in macOS and iOS builds")
+            }
+            Self::Cuda(_) if cfg!(feature = "cuda") => Ok(()),
+            Self::Cuda(_) => anyhow::bail!(
+                "--device {self} needs a build with CUDA support; rebuild with \
+                 `cargo build --release --features cuda`"
+            ),
+        }
+    }
+
+    /// Whether builders should call `.with_force_cpu()`.
+    pub fn force_cpu(self) -> bool {
+        self == Self::Cpu
+    }
+
+    /// What the load banner shows, e.g. "CUDA device 1" or "Metal (auto)".
+    pub fn describe(self) -> String {
+        match self {
+            Self::Auto if cfg!(feature = "cuda") => {
+                "CUDA device 0 if present, else CPU (auto)".into()
+            }
+            Self::Auto if cfg!(any(target_os = "macos", target_os = "ios")) => {
+                "Metal (auto)".into()
+            }
+            Self::Auto => "CPU (auto)".into(),
+            Self::Cpu => "CPU".into(),
+            Self::Metal => "Metal".into(),
+            Self::Cuda(ordinal) => format!("CUDA device {ordinal}"),
+        }
+    }
+}
+
+// ── Selection ────────────────────────────────────────────────────────────────
+
+static SELECTED: OnceLock<Device> = OnceLock::new();
+
+/// Check `device` and use it for every model built afterwards.
+///
+/// mistral.rs always loads onto CUDA ordinal 0, so `cuda:N` narrows
+/// `CUDA_VISIBLE_DEVICES` to that one GPU (the Nth of an existing list).
+/// Must be called before any other thread starts.
+pub fn select(device: Device) -> Result<()> {
+    device.check_available()?;
+    if let Device::Cuda(ordinal) = device {
+        let physical = match std::env::var("CUDA_VISIBLE_DEVICES") {
+            Ok(visible) => visible
+                .split(',')
+                .map(str::trim)
+                .filter(|id| !id.is_empty())
+                .nth(ordinal)
+                .with_context(|| {
+                    format!(
+                        "--device {device}: CUDA_VISIBLE_DEVICES={visible} has no device {ordinal}"
+                    )
+                })?
+                .to_string(),
+            Err(_) => ordinal.to_string(),
+        };
+        // SAFETY: `main` calls this before building the tokio runtime, while
+        // the process is still single-threaded.
+        unsafe { std::env::set_var("CUDA_VISIBLE_DEVICES", physical) };
+    }
+    let _ = SELECTED.set(device);
+    Ok(())
+}
+
+/// The device chosen with [`select`], or [`Device::Auto`].
+pub fn selected() -> Device {
+    SELECTED.get().copied().unwrap_or_default()
+}
@@ -0,0 +1,101 @@
+use anyhow::Result;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::artifacts::{SpaceProbe, fmt_bytes, system_free_space};
+use crate::loader;
+use crate::model_cache::{self, CacheState};
+
+// ── Settings ─────────────────────────────────────────────────────────────────
+
+static CHECK_ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Turn the check off for the rest of the process (`--skip-space-check`).
+pub fn set_check_enabled(enabled: bool) {
+    CHECK_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn check_enabled() -> bool {
+    CHECK_ENABLED.load(Ordering::Relaxed)
+}
+
+// ── Preflight ────────────────────────────────────────────────────────────────
+
+/// Bytes still to download when `cached` of `required` are on disk, if
+/// that's more than `available`.
+pub fn shortfall(required: u64, cached: u64, available: u64) -> Option<u64> {
+    let missing = required.saturating_sub(cached);
+    (missing > available).then_some(missing)
+}
+
+/// The error for a download of `model_id` that won't fit in `cache`.
+pub fn shortfall_message(
+    model_id: &str,
+    required: u64,
+    cached: u64,
+    available: u64,
+    cache: &Path,
+) -> String {
+    let missing = required.saturating_sub(cached);
+    let already = if cached > 0 {
+        format!(", {} already downloaded", fmt_bytes(cached))
+    } else {
+        String::new()
+    };
+    format!(
+        "Not enough disk space to download {model_id}: it needs ~{} more (~{} in total{already}), \
+         but only {} is free on the volume of the HuggingFace cache ({}).\n\
+         Free up space, point --cache-dir at a bigger volume, or pass --skip-space-check to try \
+         anyway.",
+        fmt_bytes(missing),
+        fmt_bytes(required),
+        fmt_bytes(available),
+        cache.display()
+    )
+}
+
+/// Refuse to start downloading `model_id` (about `download_bytes` of
+/// weights) when the HuggingFace cache's volume can't hold it.
+///
+/// A repo that's already complete, a local path, or a cache that can't be
+/// located or probed passes.  A repo an earlier run left half-downloaded is
+/// reported with the directory to delete, and only the rest of it has to
+/// fit.
+pub fn preflight(model_id: &str, download_bytes: u64) -> Result<()> {
+    preflight_with(model_id, download_bytes, system_free_space)
+}
+
+/// [`preflight`] with another [`SpaceProbe`].
+pub fn preflight_with(model_id: &str, download_bytes: u64, probe: SpaceProbe) -> Result<()> {
+    if !check_enabled() {
+        return Ok(());
+    }
+    let (Some(dir), Some(cache)) = (loader::repo_cache_dir(model_id), loader::hf_hub_cache())
+    else {
+        return Ok(());
+    };
+    let scan = model_cache::scan_repo(&dir);
+    match scan.state {
+        CacheState::Complete => return Ok(()),
+        CacheState::Partial => tracing::warn!(
+            "an earlier download of {model_id} didn't finish ({} on disk). \
+             If loading fails, delete {} and run again",
+            fmt_bytes(scan.size_bytes),
+            dir.display()
+        ),
+        CacheState::Missing => {}
+    }
+    let Ok(available) = probe(&cache) else {
+        return Ok(());
+    };
+    if shortfall(download_bytes, scan.size_bytes, available).is_some() {
+        anyhow::bail!(shortfall_message(
+            model_id,
+            download_bytes,
+            scan.size_bytes,
+            available,
+            &cache
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,463 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use mistralrs::{EmbeddingModelBuilder, EmbeddingRequest, Model};
+use serde::Serialize;
+use std::fmt;
+use std::io::{BufWriter, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::device;
+use crate::disk_space;
+use crate::loader;
+use crate::memstats::{LoadMemory, MemorySnapshot};
+use crate::promp_enhancer::EnhancerModel;
+use crate::timeout;
+use crate::ui::{self, OutputConfig, detail, fmt_duration, status};
+
+// ── Constants ────────────────────────────────────────────────────────────────
+
+/// Texts sent to the model per request by [`Embedder::embed_batch_with_progress`].
+pub const DEFAULT_BATCH_SIZE: usize = 32;
+
+// ── Models ───────────────────────────────────────────────────────────────────
+
+/// Dedicated embedding models mistral.rs can load.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum EmbeddingModel {
+    /// Qwen3-Embedding-0.6B: 1024 dimensions, multilingual, not gated.
+    #[default]
+    #[value(name = "qwen3-embedding")]
+    Qwen3Embedding,
+    /// EmbeddingGemma 300M: 768 dimensions, smallest; gated, so accept
+    /// Google's license on HuggingFace first.
+    EmbeddingGemma,
+}
+
+impl EmbeddingModel {
+    pub fn model_id(self) -> &'static str {
+        match self {
+            Self::Qwen3Embedding => "Qwen/Qwen3-Embedding-0.6B",
+            Self::EmbeddingGemma => "google/embeddinggemma-300m",
+        }
+    }
+
+    /// Size of the weights downloaded on first use.
+    pub fn download_size_bytes(self) -> u64 {
+        match self {
+            Self::Qwen3Embedding => 1_200_000_000,
+            Self::EmbeddingGemma => 1_250_000_000,
+        }
+    }
+}
+
+impl fmt::Display for EmbeddingModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Qwen3Embedding => "Qwen3-Embedding 0.6B",
+            Self::EmbeddingGemma => "EmbeddingGemma 300M",
+        };
+        write!(f, "{name} ({})", self.model_id())
+    }
+}
+
+/// What `embed --model` names: an embedding model, or one of the text
+/// presets, which are accepted only to say why they can't be used.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EmbedSource {
+    Embedding(EmbeddingModel),
+    Enhancer(EnhancerModel),
+}
+
+impl Default for EmbedSource {
+    fn default() -> Self {
+        Self::Embedding(EmbeddingModel::default())
+    }
+}
+
+/// clap value parser for `embed --model`: an [`EmbeddingModel`] or an
+/// [`EnhancerModel`] name.
+pub fn parse_source(value: &str) -> Result<EmbedSource, String> {
+    if let Ok(model) = EmbeddingModel::from_str(value, true) {
+        return Ok(EmbedSource::Embedding(model));
+    }
+    if let Ok(preset) = EnhancerModel::from_str(value, true) {
+        return Ok(EmbedSource::Enhancer(preset));
+    }
+    Err(format!(
+        "unknown model `{value}`; expected one of: {}",
+        model_names().join(", ")
+    ))
+}
+
+/// The `--model` names of the embedding models.
+fn model_names() -> Vec<String> {
+    EmbeddingModel::value_variants()
+        .iter()
+        .filter_map(|model| model.to_possible_value())
+        .map(|value| value.get_name().to_string())
+        .collect()
+}
+
+/// The `--model` name of `preset`, e.g. `gemma-e2b`.
+fn preset_name(preset: EnhancerModel) -> String {
+    preset
+        .to_possible_value()
+        .map(|v| v.get_name().to_string())
+        .unwrap_or_default()
+}
+
+/// Why a text preset can't embed: mistral.rs returns the text a chat model
+/// generates, never its hidden states, so there's nothing to mean-pool.
+pub fn capability_error(preset: EnhancerModel) -> anyhow::Error {
+    anyhow::anyhow!(
+        "{} ({preset}) is a chat model and can't produce embeddings: mistral.rs returns only \
+         the text it generates, not its hidden states.\n\
+         Use an embedding model instead: --model {}, or --model-id with a HuggingFace ID",
+        preset_name(preset),
+        model_names().join(" / --model ")
+    )
+}
+
+// ── Embedder ─────────────────────────────────────────────────────────────────
+
+/// Turns text into vectors with an embedding model.
+pub struct Embedder {
+    model: Model,
+    model_id: String,
+    batch_size: usize,
+    /// Limit per request: `--timeout`, if given.
+    timeout: Option<Duration>,
+}
+
+impl Embedder {
+    /// Load an embedding preset.
+    pub async fn from_preset(preset: EmbeddingModel) -> Result<Self> {
+        disk_space::preflight(preset.model_id(), preset.download_size_bytes())?;
+        Self::from_model_id(preset.model_id()).await
+    }
+
+    /// Load what `--model` names; a text preset fails with
+    /// [`capability_error`].
+    pub async fn from_source(source: EmbedSource) -> Result<Self> {
+        match source {
+            EmbedSource::Embedding(model) => Self::from_preset(model).await,
+            EmbedSource::Enhancer(preset) => Err(capability_error(preset)),
+        }
+    }
+
+    /// Load any embedding model mistral.rs supports, by HuggingFace ID.
+    #[tracing::instrument(name = "build_model", skip_all, fields(model_id = model_id, kind = "Embedding"))]
+    pub async fn from_model_id(model_id: &str) -> Result<Self> {
+        let model = loader::with_retries(model_id, || {
+            let mut builder = EmbeddingModelBuilder::new(model_id);
+            if device::selected().force_cpu() {
+                builder = builder.with_force_cpu();
+            }
+            loader::with_progress(model_id, "Loading weights", builder.build())
+        })
+        .await?;
+        Ok(Self {
+            model,
+            model_id: model_id.to_string(),
+            batch_size: DEFAULT_BATCH_SIZE,
+            timeout: timeout::default_limit(),
+        })
+    }
+
+    /// Texts per request (default [`DEFAULT_BATCH_SIZE`]).
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    pub fn model_id(&self) -> &str {
+        &self.model_id
+    }
+
+    /// One vector per text, in order, sent in batches of
+    /// [`with_batch_size`](Self::with_batch_size), calling `on_batch(done,
+    /// total)` with the texts embedded so far after each batch.
+    pub async fn embed_batch_with_progress<F: FnMut(usize, usize)>(
+        &self,
+        texts: &[&str],
+        mut on_batch: F,
+    ) -> Result<Vec<Vec<f32>>> {
+        let mut embeddings: Vec<Vec<f32>> = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(self.batch_size) {
+            let request = EmbeddingRequest::builder().add_prompts(batch.iter().copied());
+            let vectors = timeout::within(
+                self.timeout,
+                "Embedding",
+                self.model.generate_embeddings(request),
+            )
+            .await?;
+            if vectors.len() != batch.len() {
+                anyhow::bail!(
+                    "{} returned {} embeddings for {} texts",
+                    self.model_id,
+                    vectors.len(),
+                    batch.len()
+                );
+            }
+            if let Some(first) = embeddings.first().or(vectors.first())
+                && let Some(odd) = vectors.iter().find(|v| v.len() != first.len())
+            {
+                anyhow::bail!(
+                    "{} returned embeddings of {} and {} dimensions",
+                    self.model_id,
+                    first.len(),
+                    odd.len()
+                );
+            }
+            embeddings.extend(vectors);
+            on_batch(embeddings.len(), texts.len());
+        }
+        Ok(embeddings)
+    }
+}
+
+// ── Output ───────────────────────────────────────────────────────────────────
+
+/// Values accepted by `embed --format`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum EmbedFormat {
+    /// One `{"text": …, "embedding": […]}` object per line.
+    #[default]
+    Jsonl,
+    /// A NumPy `.npy` file: one float32 row per text, in input order.
+    Npy,
+}
+
+#[derive(Serialize)]
+struct EmbeddingLine<'a> {
+    text: &'a str,
+    embedding: &'a [f32],
+}
+
+/// Write one JSON object per text and its vector.
+pub fn write_jsonl(writer: &mut impl Write, texts: &[&str], embeddings: &[Vec<f32>]) -> Result<()> {
+    for (text, embedding) in texts.iter().zip(embeddings) {
+        serde_json::to_writer(&mut *writer, &EmbeddingLine { text, embedding })?;
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+/// Write `embeddings` as a version 1.0 `.npy` array of shape
+/// `(rows, dimensions)`, little-endian float32, readable with
+/// `numpy.load`.
+pub fn write_npy(writer: &mut impl Write, embeddings: &[Vec<f32>]) -> Result<()> {
+    let dimensions = embeddings.first().map_or(0, Vec::len);
+    if embeddings.iter().any(|row| row.len() != dimensions) {
+        anyhow::bail!("Embeddings of different lengths can't be written as one array");
+    }
+    let mut header = format!(
+        "{{'descr': '<f4', 'fortran_order': False, 'shape': ({}, {dimensions}), }}",
+        embeddings.len()
+    );
+    // Magic (6) + version (2) + length (2) + header, padded with spaces to
+    // a multiple of 64 and ended by a newline.
+    let unpadded = 10 + header.len() + 1;
+    header.push_str(&" ".repeat(unpadded.next_multiple_of(64) - unpadded));
+    header.push('\n');
+
+    writer.write_all(b"\x93NUMPY\x01\x00")?;
+    writer.write_all(&(header.len() as u16).to_le_bytes())?;
+    writer.write_all(header.as_bytes())?;
+    for value in embeddings.iter().flatten() {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+// ── CLI entry-point ──────────────────────────────────────────────────────────
+
+/// `embed` settings chosen on the command line.
+#[derive(Clone, Debug, Default)]
+pub struct EmbedOptions {
+    /// File of texts, one per line; `-` for stdin.
+    pub input: Option<PathBuf>,
+    pub model: EmbedSource,
+    /// `--model-id`: any embedding model, instead of `model`.
+    pub model_id: Option<String>,
+    pub format: EmbedFormat,
+    /// Write here instead of stdout.
+    pub out: Option<PathBuf>,
+    pub batch_size: usize,
+}
+
+/// Machine-readable summary of `embed --out`, printed in `--json` mode.
+#[derive(Serialize)]
+struct EmbedReport {
+    model: String,
+    /// The file or "stdin".
+    source: String,
+    out: String,
+    format: EmbedFormat,
+    count: usize,
+    dimensions: usize,
+    load_secs: f64,
+    embed_secs: f64,
+    memory: LoadMemory,
+}
+
+/// The non-empty lines of `input`, trimmed, and where they came from:
+/// the file, or stdin (for `-`, or when nothing is given and stdin is
+/// piped).
+fn read_lines(input: Option<&Path>) -> Result<(Vec<String>, String)> {
+    let (text, source) = match input {
+        Some(path) if path.as_os_str() != "-" => {
+            let text = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            (text, path.display().to_string())
+        }
+        None if std::io::stdin().is_terminal() => {
+            anyhow::bail!("Give a FILE of texts to embed (one per line), or `-` for stdin")
+        }
+        _ => {
+            let mut text = String::new();
+            std::io::stdin()
+                .lock()
+                .read_to_string(&mut text)
+                .context("Failed to read the texts from stdin")?;
+            (text, "stdin".to_string())
+        }
+    };
+    let lines = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect();
+    Ok((lines, source))
+}
+
+/// Embed each line of a file or stdin and write the vectors as JSONL or
+/// `.npy`, to `--out` or stdout.
+pub async fn run(options: EmbedOptions, out: OutputConfig) -> Result<()> {
+    let EmbedOptions {
+        input,
+        model,
+        model_id,
+        format,
+        out: out_path,
+        batch_size,
+    } = options;
+    if batch_size == 0 {
+        anyhow::bail!("--batch-size must be at least 1");
+    }
+    if out.json && out_path.is_none() {
+        anyhow::bail!("--json needs --out: without it the embeddings themselves go to stdout");
+    }
+    if format == EmbedFormat::Npy && out_path.is_none() && std::io::stdout().is_terminal() {
+        anyhow::bail!("--format npy is binary: give --out, or redirect stdout to a file");
+    }
+    if model_id.is_none()
+        && let EmbedSource::Enhancer(preset) = model
+    {
+        return Err(capability_error(preset));
+    }
+    // Read the texts before the model loads, so a missing file fails fast.
+    let (lines, source) = read_lines(input.as_deref())?;
+    if lines.is_empty() {
+        anyhow::bail!("Nothing to embed: {source} has no non-empty lines");
+    }
+
+    let before = MemorySnapshot::capture();
+    let load_start = Instant::now();
+    let embedder = match &model_id {
+        Some(id) => {
+            status!(out, "Loading embedding model: {id}");
+            Embedder::from_model_id(id).await?
+        }
+        None => {
+            status!(out, "Loading embedding model: {}", describe_source(model));
+            Embedder::from_source(model).await?
+        }
+    }
+    .with_batch_size(batch_size);
+    let load_elapsed = load_start.elapsed();
+    let memory = MemorySnapshot::capture().since(before);
+    status!(out, "Model loaded in {}", fmt_duration(load_elapsed));
+    status!(out, "{}\n", memory.banner());
+
+    status!(out, "Embedding {} line(s) from {source}", lines.len());
+    let embed_start = Instant::now();
+    let texts: Vec<&str> = lines.iter().map(String::as_str).collect();
+    let embeddings = embedder
+        .embed_batch_with_progress(&texts, |done, total| {
+            if total > batch_size {
+                detail!(out, "  {done}/{total}");
+            }
+        })
+        .await?;
+    let embed_elapsed = embed_start.elapsed();
+    let dimensions = embeddings.first().map_or(0, Vec::len);
+
+    match &out_path {
+        Some(path) => {
+            if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                std::fs::create_dir_all(parent)
+                    .with_context(|| format!("Failed to create {}", parent.display()))?;
+            }
+            let file = std::fs::File::create(path)
+                .with_context(|| format!("Failed to create {}", path.display()))?;
+            let mut writer = BufWriter::new(file);
+            write_embeddings(&mut writer, format, &texts, &embeddings)
+                .and_then(|()| Ok(writer.flush()?))
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+        None => {
+            let mut writer = BufWriter::new(std::io::stdout().lock());
+            write_embeddings(&mut writer, format, &texts, &embeddings)?;
+            writer.flush()?;
+        }
+    }
+
+    if out.json {
+        return ui::print_json(&EmbedReport {
+            model: embedder.model_id().to_string(),
+            source,
+            out: out_path
+                .map(|p| p.display().to_string())
+                .unwrap_or_default(),
+            format,
+            count: embeddings.len(),
+            dimensions,
+            load_secs: load_elapsed.as_secs_f64(),
+            embed_secs: embed_elapsed.as_secs_f64(),
+            memory,
+        });
+    }
+    status!(
+        out,
+        "{} embedding(s) of {dimensions} dimensions in {}",
+        embeddings.len(),
+        fmt_duration(embed_elapsed)
+    );
+    if let Some(path) = &out_path {
+        status!(out, "Saved to {}", path.display());
+    }
+    Ok(())
+}
+
+fn write_embeddings(
+    writer: &mut impl Write,
+    format: EmbedFormat,
+    texts: &[&str],
+    embeddings: &[Vec<f32>],
+) -> Result<()> {
+    match format {
+        EmbedFormat::Jsonl => write_jsonl(writer, texts, embeddings),
+        EmbedFormat::Npy => write_npy(writer, embeddings),
+    }
+}
+
+fn describe_source(source: EmbedSource) -> String {
+    match source {
+        EmbedSource::Embedding(model) => model.to_string(),
+        EmbedSource::Enhancer(preset) => preset.to_string(),
+    }
+}
@@ -0,0 +1,193 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::load_options::LoadOptions;
+use crate::memory;
+use crate::promp_enhancer::{EnhancerModel, PromptEnhancer};
+use crate::ui::{OutputConfig, status};
+
+/// The `--model` name of `preset`, e.g. `gemma-e2b`.
+fn preset_name(preset: EnhancerModel) -> String {
+    preset
+        .to_possible_value()
+        .map(|v| v.get_name().to_string())
+        .unwrap_or_default()
+}
+
+// ── Chain ────────────────────────────────────────────────────────────────────
+
+/// What a bare `--fallback` tries: the default preset, then the smallest
+/// one, then the one that isn't gated and loads with a text builder.
+pub const DEFAULT_CHAIN: [EnhancerModel; 3] = [
+    EnhancerModel::GemmaE4b,
+    EnhancerModel::GemmaE2b,
+    EnhancerModel::Phi35Mini,
+];
+
+/// Builds one preset of a fallback chain.
+///
+/// [`load_chain`] only decides which preset to try next; the building is
+/// behind this trait so the chain doesn't care whether it's a real load
+/// with status output ([`CliBuilder`]) or a plain one ([`OptionsBuilder`]).
+#[async_trait]
+pub trait PresetBuilder {
+    type Output: Send;
+
+    async fn build(&self, preset: EnhancerModel) -> Result<Self::Output>;
+}
+
+/// One preset of a chain that failed to load, and why.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct FailedLoad {
+    /// The `--model` name, e.g. `gemma-e4b`.
+    pub preset: String,
+    pub error: String,
+}
+
+/// What [`load_chain`] loaded: the first preset that built, and every
+/// preset before it that didn't.
+pub struct ChainLoad<T> {
+    pub loaded: T,
+    pub preset: EnhancerModel,
+    pub failures: Vec<FailedLoad>,
+}
+
+/// Build the presets of `chain` in order until one loads.
+///
+/// Each failure is reported on stderr before the next preset is tried.
+/// Fails only when every preset does, with an error listing each one and
+/// its cause.
+pub async fn load_chain<B: PresetBuilder + Sync>(
+    chain: &[EnhancerModel],
+    builder: &B,
+) -> Result<ChainLoad<B::Output>> {
+    if chain.is_empty() {
+        anyhow::bail!("The fallback chain has no presets");
+    }
+    let mut failures = Vec::new();
+    for (i, &preset) in chain.iter().enumerate() {
+        match builder.build(preset).await {
+            Ok(loaded) => {
+                return Ok(ChainLoad {
+                    loaded,
+                    preset,
+                    failures,
+                });
+            }
+            Err(err) => {
+                let error = format!("{err:#}");
+                if let Some(&next) = chain.get(i + 1) {
+                    tracing::warn!(
+                        "{} failed to load, falling back to {}: {error}",
+                        preset_name(preset),
+                        preset_name(next)
+                    );
+                }
+                failures.push(FailedLoad {
+                    preset: preset_name(preset),
+                    error,
+                });
+            }
+        }
+    }
+
+    let mut message = format!(
+        "None of the {} presets in the fallback chain loaded:",
+        failures.len()
+    );
+    for failure in &failures {
+        message.push_str(&format!(
+            "\n  {}: {}",
+            failure.preset,
+            failure.error.replace('\n', "\n    ")
+        ));
+    }
+    Err(anyhow::anyhow!(message))
+}
+
+/// The presets `--fallback` tries: `--model` first when given, then the
+/// list passed to `--fallback`, or [`DEFAULT_CHAIN`] when it was passed
+/// bare.  Repeats are dropped.
+pub fn resolve(model: Option<EnhancerModel>, fallback: &[EnhancerModel]) -> Vec<EnhancerModel> {
+    let rest = if fallback.is_empty() {
+        &DEFAULT_CHAIN[..]
+    } else {
+        fallback
+    };
+    let mut chain = Vec::with_capacity(rest.len() + 1);
+    for &preset in model.iter().chain(rest) {
+        if !chain.contains(&preset) {
+            chain.push(preset);
+        }
+    }
+    chain
+}
+
+/// The first preset of `chain` that fits in memory now, for plans that
+/// load nothing; the head of the chain when none does (or it's empty, the
+/// default preset).
+pub fn would_load(chain: &[EnhancerModel], opts: &LoadOptions) -> EnhancerModel {
+    let opts = &LoadOptions {
+        auto_downgrade: false,
+        ..opts.clone()
+    };
+    chain
+        .iter()
+        .copied()
+        .find(|&preset| memory::would_load(preset, opts).is_some())
+        .or_else(|| chain.first().copied())
+        .unwrap_or_default()
+}
+
+// ── Builders ─────────────────────────────────────────────────────────────────
+
+/// Check that `preset` fits in memory as is.  The chain is the fallback,
+/// so `--auto-downgrade` doesn't swap in another preset behind its back.
+fn check_fits(preset: EnhancerModel, opts: &LoadOptions) -> Result<LoadOptions> {
+    let opts = LoadOptions {
+        auto_downgrade: false,
+        ..opts.clone()
+    };
+    memory::choose(preset, &opts)?;
+    Ok(opts)
+}
+
+/// Loads a preset with `--isq` / `--dtype` applied, after checking that
+/// it fits in memory; used by [`PromptEnhancer::from_preset_chain`].
+pub struct OptionsBuilder<'a>(pub &'a LoadOptions);
+
+#[async_trait]
+impl PresetBuilder for OptionsBuilder<'_> {
+    type Output = PromptEnhancer;
+
+    async fn build(&self, preset: EnhancerModel) -> Result<PromptEnhancer> {
+        let load = check_fits(preset, self.0)?;
+        Ok(PromptEnhancer::from_preset_with_options(preset, &load).await?)
+    }
+}
+
+/// Like [`OptionsBuilder`], printing the same status lines as a
+/// single-preset load in `prompt` and `image`.
+pub struct CliBuilder<'a> {
+    pub load: &'a LoadOptions,
+    pub out: OutputConfig,
+}
+
+#[async_trait]
+impl PresetBuilder for CliBuilder<'_> {
+    type Output = PromptEnhancer;
+
+    async fn build(&self, preset: EnhancerModel) -> Result<PromptEnhancer> {
+        let out = self.out;
+        let load = check_fits(preset, self.load)?;
+        status!(out, "Loading prompt enhancer model: {preset}");
+        status!(out, "  Memory estimate: {}", preset.memory_estimate(&load));
+        let settings = load.apply(preset.default_settings());
+        if let Some(weights) = load.uqff.describe(preset.model_id(), settings) {
+            status!(out, "  {weights}");
+        }
+        Ok(PromptEnhancer::from_preset_with_options(preset, &load).await?)
+    }
+}
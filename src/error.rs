@@ -0,0 +1,174 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::loader::AccessDenied;
+use crate::timeout;
+
+// ── Error ────────────────────────────────────────────────────────────────────
+
+/// What went wrong in one of the model types' public methods
+/// ([`PromptEnhancer`](crate::promp_enhancer::PromptEnhancer),
+/// [`AudioTranscriber`](crate::audio_transcription::AudioTranscriber),
+/// [`CliChat`](crate::cli_chat::CliChat) and
+/// [`ImageGenerator`](crate::image_generation::ImageGenerator)), so callers
+/// can tell a failed download from a failed request without matching on
+/// message text.
+///
+/// Each variant keeps the underlying error as its
+/// [`source`](std::error::Error::source), so `{:#}` prints the whole chain
+/// and checks like [`timeout::is_timed_out`] see through it.  The
+/// CLI converts it to `anyhow` at the top and prints [`hint`](Self::hint)
+/// under the message.
+#[derive(Debug)]
+pub enum Error {
+    /// Downloading or building the weights of `model_id` (a repo ID or a
+    /// local path) failed.
+    ModelLoad {
+        model_id: String,
+        source: anyhow::Error,
+    },
+    /// The audio at `path` couldn't be read or decoded.  Audio passed as
+    /// bytes has the path `<bytes>`.
+    AudioDecode {
+        path: PathBuf,
+        source: anyhow::Error,
+    },
+    /// A request to a loaded model failed or timed out during `stage`,
+    /// e.g. "Prompt enhancement".
+    Generation {
+        stage: String,
+        source: anyhow::Error,
+    },
+    /// The diffusion model's text encoder rejected the prompt, and every
+    /// shortened retry down to the last one tried as well.  Both lengths
+    /// are [`describe_length`](crate::prompt_limits::describe_length)
+    /// descriptions, since a word count alone hides emoji and CJK text.
+    PromptTooLong {
+        length: String,
+        last_tried: String,
+        source: anyhow::Error,
+    },
+}
+
+impl Error {
+    /// [`Error::ModelLoad`], unless `source` already is an [`Error`], which
+    /// is returned as it is.
+    pub fn model_load(model_id: impl Into<String>, source: anyhow::Error) -> Self {
+        source
+            .downcast::<Self>()
+            .unwrap_or_else(|source| Self::ModelLoad {
+                model_id: model_id.into(),
+                source,
+            })
+    }
+
+    /// [`Error::AudioDecode`], unless `source` already is an [`Error`].
+    pub fn audio_decode(path: &Path, source: anyhow::Error) -> Self {
+        source
+            .downcast::<Self>()
+            .unwrap_or_else(|source| Self::AudioDecode {
+                path: path.to_path_buf(),
+                source,
+            })
+    }
+
+    /// [`Error::Generation`], unless `source` already is an [`Error`].
+    pub fn generation(stage: impl Into<String>, source: anyhow::Error) -> Self {
+        source
+            .downcast::<Self>()
+            .unwrap_or_else(|source| Self::Generation {
+                stage: stage.into(),
+                source,
+            })
+    }
+
+    /// The underlying error.
+    pub fn inner(&self) -> &anyhow::Error {
+        match self {
+            Self::ModelLoad { source, .. }
+            | Self::AudioDecode { source, .. }
+            | Self::Generation { source, .. }
+            | Self::PromptTooLong { source, .. } => source,
+        }
+    }
+
+    /// Whether the request was abandoned by `--timeout`.
+    pub fn is_timed_out(&self) -> bool {
+        timeout::is_timed_out(self.inner())
+    }
+
+    /// What the user can do about it, for the CLI to print under the
+    /// error.
+    pub fn hint(&self) -> Option<String> {
+        match self {
+            Self::ModelLoad { source, .. } => Some(
+                access_denied(source)
+                    .map(AccessDenied::guidance)
+                    .unwrap_or_else(|| {
+                        "Run again with --verbose to see mistral.rs' log of the load.".to_string()
+                    }),
+            ),
+            Self::AudioDecode { .. } => Some(
+                "WAV, FLAC, MP3 and Ogg Vorbis can be decoded.  Convert other formats with \
+                 ffmpeg first, or pass headerless PCM with --raw-pcm --sample-rate HZ."
+                    .to_string(),
+            ),
+            Self::Generation { source, .. } if timeout::is_timed_out(source) => Some(
+                "Raise --timeout, or leave it out to wait for the model however long it takes."
+                    .to_string(),
+            ),
+            Self::Generation { .. } => None,
+            Self::PromptTooLong { .. } => Some(
+                "Shorten the prompt.  Emoji and non-Latin scripts take far more of the text \
+                 encoder's window than their word count suggests."
+                    .to_string(),
+            ),
+        }
+    }
+}
+
+/// `{:#}` appends the source chain, like it does for `anyhow::Error`.
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ModelLoad { model_id, .. } => write!(f, "Failed to load {model_id}"),
+            Self::AudioDecode { path, .. } => {
+                write!(f, "Failed to decode audio from {}", path.display())
+            }
+            Self::Generation { stage, .. } => write!(f, "{stage} failed"),
+            Self::PromptTooLong {
+                length, last_tried, ..
+            } => write!(
+                f,
+                "The diffusion model rejected the prompt as too long ({length}; \
+                 still rejected at {last_tried})"
+            ),
+        }?;
+        if f.alternate() {
+            write!(f, ": {:#}", self.inner())?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.inner().as_ref())
+    }
+}
+
+// ── Hints ────────────────────────────────────────────────────────────────────
+
+fn access_denied(err: &anyhow::Error) -> Option<&AccessDenied> {
+    err.chain()
+        .find_map(|cause| cause.downcast_ref::<AccessDenied>())
+}
+
+/// The hint for the first [`Error`] in `err`'s chain.  A gated repo that
+/// was loaded outside one (e.g. by `summarize`) still gets its guidance.
+pub fn hint(err: &anyhow::Error) -> Option<String> {
+    match err.chain().find_map(|cause| cause.downcast_ref::<Error>()) {
+        Some(error) => error.hint(),
+        None => access_denied(err).map(AccessDenied::guidance),
+    }
+}
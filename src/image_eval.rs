@@ -0,0 +1,611 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+use crate::audio_transcription::TranscriptionModel;
+use crate::bench::ReportFormat;
+use crate::image_generation::{DIFFUSION_APPROX_BYTES, ImageGenerator, ImageOptions};
+use crate::image_metadata::MetadataOutput;
+use crate::image_understanding::{ImageDescriber, InputImage};
+use crate::interrupt;
+use crate::load_options::LoadOptions;
+use crate::memory::{self, MemoryFootprint};
+use crate::promp_enhancer::{EnhancerModel, PromptEnhancer};
+use crate::sampler::SamplerConfig;
+use crate::ui::{self, OutputConfig, detail, fmt_duration, status};
+
+// ── Judge ────────────────────────────────────────────────────────────────────
+
+/// Greedy, and long enough for the score line plus a short critique.
+const JUDGE_SAMPLER: SamplerConfig = SamplerConfig::new(0.0, 1.0, 256);
+
+/// Instruction sent with each generated image.  Asking for a fixed layout
+/// keeps [`parse_score`] on its first rule for well-behaved replies.
+fn judge_instruction(seed: &str) -> String {
+    format!(
+        "This image was generated from the description: \"{seed}\"\n\
+         Rate how faithfully the image matches the description on a scale of 1 to 10, \
+         where 10 means every element is present and correct.\n\
+         Reply in exactly this format:\n\
+         Score: <1-10>\n\
+         Critique: <one or two sentences on what matches and what is missing>"
+    )
+}
+
+/// A number found in a judge reply, with its byte range.
+#[derive(Clone, Copy, Debug)]
+struct Number {
+    value: f64,
+    start: usize,
+    end: usize,
+}
+
+/// Every decimal number in `text` ("7", "7.5"), in order.
+fn numbers(text: &str) -> Vec<Number> {
+    let bytes = text.as_bytes();
+    let mut found = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if !bytes[i].is_ascii_digit() {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < bytes.len() && bytes[i].is_ascii_digit() {
+            i += 1;
+        }
+        if i + 1 < bytes.len() && bytes[i] == b'.' && bytes[i + 1].is_ascii_digit() {
+            i += 1;
+            while i < bytes.len() && bytes[i].is_ascii_digit() {
+                i += 1;
+            }
+        }
+        if let Ok(value) = text[start..i].parse() {
+            found.push(Number {
+                value,
+                start,
+                end: i,
+            });
+        }
+    }
+    found
+}
+
+/// `rest` with leading whitespace and Markdown emphasis removed.
+fn skip_filler(rest: &str) -> &str {
+    rest.trim_start_matches(|c: char| c.is_whitespace() || c == '*' || c == '_')
+}
+
+/// Whether `after` (the text following a number) continues a "1-10" or
+/// "1 to 10" range.
+fn starts_range(after: &str) -> bool {
+    let after = skip_filler(after);
+    ["-", "–", "to "]
+        .iter()
+        .find_map(|sep| after.strip_prefix(sep))
+        .is_some_and(|rest| skip_filler(rest).starts_with("10"))
+}
+
+/// Whether `after` (the text following a number) marks it as a score out of
+/// ten: "/10", "/ 10" or "out of 10".
+fn out_of_ten(after: &str) -> bool {
+    let after = skip_filler(after);
+    let rest = after
+        .strip_prefix('/')
+        .or_else(|| after.strip_prefix("out of"));
+    rest.is_some_and(|rest| skip_filler(rest).starts_with("10"))
+}
+
+/// Pull the 1–10 score out of a judge reply.
+///
+/// Numbers that only describe the scale are ignored: both ends of "1-10" or
+/// "1 to 10" and the 10 in "/10" or "out of 10".  The score is then, in
+/// order of preference, the first number after a "score" or "rating" label
+/// on the same line, the first one written as "N/10" or "N out of 10", or
+/// a number the reply starts with.  A value outside 1–10 where the score
+/// should be gives `None` rather than a guess from elsewhere in the text.
+pub fn parse_score(reply: &str) -> Option<f64> {
+    let lower = reply.to_ascii_lowercase();
+    let all = numbers(&lower);
+
+    let mut noise = vec![false; all.len()];
+    for (i, number) in all.iter().enumerate() {
+        let before = lower[..number.start].trim_end_matches(|c: char| c.is_whitespace());
+        if before.ends_with('/') || before.ends_with("out of") {
+            noise[i] = true;
+        }
+        if starts_range(&lower[number.end..]) {
+            noise[i] = true;
+            if let Some(end) = noise.get_mut(i + 1) {
+                *end = true;
+            }
+        }
+    }
+    let candidates: Vec<Number> = all
+        .iter()
+        .zip(&noise)
+        .filter(|(_, noise)| !**noise)
+        .map(|(number, _)| *number)
+        .collect();
+    let in_range = |number: &Number| (1.0..=10.0).contains(&number.value);
+
+    let labelled = ["score", "rating"].iter().find_map(|label| {
+        lower.match_indices(label).find_map(|(at, _)| {
+            let line_end = lower[at..].find('\n').map_or(lower.len(), |n| at + n);
+            candidates
+                .iter()
+                .find(|number| number.start > at && number.end <= line_end)
+        })
+    });
+    if let Some(number) = labelled {
+        return in_range(number).then_some(number.value);
+    }
+
+    if let Some(number) = candidates
+        .iter()
+        .find(|number| out_of_ten(&lower[number.end..]))
+    {
+        return in_range(number).then_some(number.value);
+    }
+
+    let leading = candidates.first().filter(|number| {
+        lower[..number.start]
+            .trim_start_matches(['*', '#', ' '])
+            .is_empty()
+    });
+    leading.filter(|number| in_range(number)).map(|n| n.value)
+}
+
+/// The critique part of a judge reply: whatever follows a "Critique:"
+/// label, or the whole reply without its score line when there is none.
+pub fn parse_critique(reply: &str) -> String {
+    let lower = reply.to_ascii_lowercase();
+    if let Some(at) = lower.find("critique") {
+        let rest = &reply[at + "critique".len()..];
+        return skip_filler(rest.trim_start_matches(['*', ':']))
+            .trim()
+            .to_string();
+    }
+    reply
+        .lines()
+        .filter(|line| {
+            let line = line.to_ascii_lowercase();
+            !(line.contains("score") || line.contains("rating"))
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim()
+        .to_string()
+}
+
+// ── Seeds ────────────────────────────────────────────────────────────────────
+
+/// Read seed prompts from `path`, one per line.  Blank lines and lines
+/// starting with `#` are skipped.
+pub fn read_seeds(path: &Path) -> Result<Vec<String>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read seeds file: {}", path.display()))?;
+    let seeds: Vec<String> = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect();
+    if seeds.is_empty() {
+        anyhow::bail!("No seed prompts in {}", path.display());
+    }
+    Ok(seeds)
+}
+
+/// The `--model` name of `preset`, e.g. `gemma-e2b`.
+fn preset_name(preset: EnhancerModel) -> String {
+    preset
+        .to_possible_value()
+        .map(|v| v.get_name().to_string())
+        .unwrap_or_default()
+}
+
+// ── Report ───────────────────────────────────────────────────────────────────
+
+/// One seed run through one enhancer preset.
+#[derive(Clone, Debug, Serialize)]
+pub struct EvalRow {
+    pub preset: String,
+    pub seed: String,
+    /// The enhanced prompt the image was generated from.
+    pub prompt: String,
+    pub image_path: String,
+    /// The judge's 1–10 score, or `None` if its reply had none.
+    pub score: Option<f64>,
+    pub critique: String,
+    /// The judge's reply as given.
+    pub reply: String,
+}
+
+/// Scores of one enhancer preset across all seeds.
+#[derive(Clone, Debug, Serialize)]
+pub struct PresetSummary {
+    pub preset: String,
+    /// Mean over the seeds that got a score.
+    pub mean_score: Option<f64>,
+    pub scored: usize,
+    pub seeds: usize,
+}
+
+/// Everything `eval` produced, printed in `--json` mode and written by
+/// `--report`.
+#[derive(Debug, Serialize)]
+pub struct EvalReport {
+    pub judge: String,
+    pub rows: Vec<EvalRow>,
+    pub presets: Vec<PresetSummary>,
+}
+
+/// Quote a CSV field if it contains a delimiter, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+impl EvalReport {
+    fn new(judge: String, rows: Vec<EvalRow>, presets: &[EnhancerModel]) -> Self {
+        let presets = presets
+            .iter()
+            .map(|&preset| {
+                let name = preset_name(preset);
+                let rows: Vec<&EvalRow> = rows.iter().filter(|row| row.preset == name).collect();
+                let scores: Vec<f64> = rows.iter().filter_map(|row| row.score).collect();
+                PresetSummary {
+                    preset: name,
+                    mean_score: (!scores.is_empty())
+                        .then(|| scores.iter().sum::<f64>() / scores.len() as f64),
+                    scored: scores.len(),
+                    seeds: rows.len(),
+                }
+            })
+            .collect();
+        Self {
+            judge,
+            rows,
+            presets,
+        }
+    }
+
+    /// One `seed` row per seed and preset, then one `mean` row per preset;
+    /// missing scores are empty cells.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("kind,preset,seed,prompt,image_path,score,critique\n");
+        for row in &self.rows {
+            let fields = [
+                "seed".to_string(),
+                csv_field(&row.preset),
+                csv_field(&row.seed),
+                csv_field(&row.prompt),
+                csv_field(&row.image_path),
+                row.score.map(|s| format!("{s:.1}")).unwrap_or_default(),
+                csv_field(&row.critique),
+            ];
+            out.push_str(&fields.join(","));
+            out.push('\n');
+        }
+        for summary in &self.presets {
+            let fields = [
+                "mean".to_string(),
+                csv_field(&summary.preset),
+                String::new(),
+                String::new(),
+                String::new(),
+                summary
+                    .mean_score
+                    .map(|s| format!("{s:.2}"))
+                    .unwrap_or_default(),
+                format!("{}/{} scored", summary.scored, summary.seeds),
+            ];
+            out.push_str(&fields.join(","));
+            out.push('\n');
+        }
+        out
+    }
+
+    fn write(&self, path: &Path) -> Result<()> {
+        let contents = match ReportFormat::from_path(path)? {
+            ReportFormat::Csv => self.to_csv(),
+            ReportFormat::Json => serde_json::to_string_pretty(self)?,
+        };
+        std::fs::write(path, contents)
+            .with_context(|| format!("Failed to write report: {}", path.display()))
+    }
+
+    fn print_table(&self) {
+        println!("{:<14} {:>10} {:>8}", "Preset", "Mean", "Scored");
+        for summary in &self.presets {
+            println!(
+                "{:<14} {:>10} {:>8}",
+                summary.preset,
+                summary
+                    .mean_score
+                    .map(|s| format!("{s:.2}/10"))
+                    .unwrap_or_else(|| "-".to_string()),
+                format!("{}/{}", summary.scored, summary.seeds),
+            );
+        }
+    }
+}
+
+// ── Helpers ──────────────────────────────────────────────────────────────────
+
+/// Load the judge, returning it with its load time.
+async fn load_judge(
+    judge: TranscriptionModel,
+    load: LoadOptions,
+    cancel: CancellationToken,
+) -> Result<(ImageDescriber, Duration)> {
+    let started = Instant::now();
+    let describer = interrupt::unless_cancelled(
+        Some(&cancel),
+        ImageDescriber::from_preset_with_options(judge, &load),
+    )
+    .await?
+    .with_sampler(JUDGE_SAMPLER);
+    Ok((describer, started.elapsed()))
+}
+
+/// Why the judge can't load while FLUX renders, or `None` if both fit in
+/// the memory available now.  Unlike [`memory::choose`], an unknown amount
+/// of memory keeps the phases apart.
+fn overlap_blocker(judge: TranscriptionModel, load: &LoadOptions) -> Option<String> {
+    let required = judge.required_bytes(load) + DIFFUSION_APPROX_BYTES;
+    match memory::available_bytes() {
+        Some(available) if required <= available => None,
+        Some(available) => Some(format!(
+            "the judge and FLUX need ~{} together but only {} is available",
+            memory::fmt_gb(required),
+            memory::fmt_gb(available)
+        )),
+        None => Some("available memory can't be measured".to_string()),
+    }
+}
+
+// ── CLI entry-point ──────────────────────────────────────────────────────────
+
+/// Settings for [`run`].
+#[derive(Clone, Debug)]
+pub struct EvalOptions {
+    /// Enhancer presets to compare, in order.
+    pub models: Vec<EnhancerModel>,
+    /// Gemma 3n variant that scores the images.
+    pub judge: TranscriptionModel,
+    /// `--isq` / `--dtype` for the enhancers and the judge.
+    pub load: LoadOptions,
+    /// Images go to `<out_dir>/<preset>/seed-N.png`.
+    pub out_dir: PathBuf,
+    pub width: Option<usize>,
+    pub height: Option<usize>,
+    /// Overwrite existing images.
+    pub force: bool,
+    /// `.csv` or `.json` report (default `<out_dir>/report.csv`).
+    pub report: Option<PathBuf>,
+}
+
+/// Score enhancer presets by round trip: enhance each seed, render it with
+/// FLUX, then ask Gemma 3n how faithfully the image matches the seed.
+///
+/// The work runs in three phases so each model loads once: every enhancer
+/// in turn (dropped before the next), then FLUX for all images, then the
+/// judge.  The judge loads while FLUX renders only when both fit in memory.
+pub async fn run(
+    seeds_path: PathBuf,
+    options: EvalOptions,
+    cancel: CancellationToken,
+    out: OutputConfig,
+) -> Result<()> {
+    if options.load.uqff.has_explicit_path() {
+        anyhow::bail!(
+            "--save-uqff / --from-uqff name a single model's weights; use --cache-quantized with eval"
+        );
+    }
+    if options.load.auto_downgrade {
+        anyhow::bail!(
+            "--auto-downgrade is not supported with eval; it would score a different preset"
+        );
+    }
+    if options.models.is_empty() {
+        anyhow::bail!("--models must name at least one preset");
+    }
+    let report_path = options
+        .report
+        .clone()
+        .unwrap_or_else(|| options.out_dir.join("report.csv"));
+    ReportFormat::from_path(&report_path)?;
+    let seeds = read_seeds(&seeds_path)?;
+
+    // Plan every image up front so bad dimensions or an existing file fail
+    // before any model loads.
+    let width = seeds.len().to_string().len();
+    let image_options = |preset: EnhancerModel, index: usize| ImageOptions {
+        width: options.width,
+        height: options.height,
+        num_images: 1,
+        out: Some(
+            options
+                .out_dir
+                .join(preset_name(preset))
+                .join(format!("seed-{:0width$}.png", index + 1)),
+        ),
+        force: options.force,
+        negative_prompt: None,
+        guidance: None,
+        metadata: MetadataOutput::default(),
+        grid: None,
+        sizes: Vec::new(),
+    };
+    for &preset in &options.models {
+        for index in 0..seeds.len() {
+            let planned = image_options(preset, index);
+            planned.generation_params()?;
+            planned.check_overwrite()?;
+        }
+    }
+    for &preset in &options.models {
+        let dir = options.out_dir.join(preset_name(preset));
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create output directory: {}", dir.display()))?;
+    }
+
+    // ── Phase 1: enhance ──
+    status!(
+        out,
+        "\n[1/3] Enhancing {} seed(s) with {} preset(s)",
+        seeds.len(),
+        options.models.len()
+    );
+    let mut prompts: Vec<(EnhancerModel, Vec<String>)> = Vec::with_capacity(options.models.len());
+    for &preset in &options.models {
+        let (preset, load) = memory::choose(preset, &options.load)?;
+        status!(out, "  Loading {preset}");
+        let load_start = Instant::now();
+        let enhancer = interrupt::unless_cancelled(
+            Some(&cancel),
+            PromptEnhancer::from_preset_with_options(preset, &load),
+        )
+        .await?;
+        detail!(
+            out,
+            "  model loaded in {}",
+            fmt_duration(load_start.elapsed())
+        );
+        let mut enhanced = Vec::with_capacity(seeds.len());
+        for (index, seed) in seeds.iter().enumerate() {
+            let prompt: String = interrupt::unless_cancelled(Some(&cancel), enhancer.enhance(seed))
+                .await
+                .with_context(|| format!("{preset} failed to enhance seed {}", index + 1))?
+                .into();
+            detail!(out, "  seed {}/{}: {prompt}", index + 1, seeds.len());
+            enhanced.push(prompt);
+        }
+        drop(enhancer);
+        prompts.push((preset, enhanced));
+    }
+
+    // ── Phase 2: render ──
+    status!(
+        out,
+        "\n[2/3] Rendering {} image(s) with FLUX.1-schnell",
+        seeds.len() * options.models.len()
+    );
+    let judge_cancel = cancel.child_token();
+    let _stop_judge = judge_cancel.clone().drop_guard();
+    let early_judge = match overlap_blocker(options.judge, &options.load) {
+        None => {
+            status!(out, "  Loading the judge ({}) alongside", options.judge);
+            Some(tokio::spawn(load_judge(
+                options.judge,
+                options.load.clone(),
+                judge_cancel,
+            )))
+        }
+        Some(reason) => {
+            detail!(out, "  Judge loads afterwards: {reason}");
+            None
+        }
+    };
+    let load_start = Instant::now();
+    let generator = interrupt::unless_cancelled(Some(&cancel), ImageGenerator::new()).await?;
+    detail!(
+        out,
+        "  model loaded in {}",
+        fmt_duration(load_start.elapsed())
+    );
+    let mut rendered: Vec<(EnhancerModel, usize, String)> = Vec::new();
+    for (preset, enhanced) in &prompts {
+        for (index, prompt) in enhanced.iter().enumerate() {
+            let request = image_options(*preset, index)
+                .request_with_budget(prompt, generator.prompt_budget())?;
+            let result = interrupt::unless_cancelled(Some(&cancel), generator.generate(request))
+                .await
+                .with_context(|| format!("Failed to render seed {} for {preset}", index + 1))?;
+            let path = result
+                .images
+                .into_iter()
+                .next()
+                .map(|image| image.path)
+                .context("The diffusion model returned no image")?;
+            status!(out, "  {path}");
+            rendered.push((*preset, index, path));
+        }
+    }
+    drop(generator);
+
+    // ── Phase 3: judge ──
+    status!(
+        out,
+        "\n[3/3] Scoring {} image(s) with {}",
+        rendered.len(),
+        options.judge
+    );
+    let (judge, judge_load) = match early_judge {
+        Some(handle) => handle.await.context("The judge load task failed")??,
+        None => {
+            let (preset, load) = memory::choose(options.judge, &options.load)?;
+            load_judge(preset, load, cancel.clone()).await?
+        }
+    };
+    detail!(out, "  model loaded in {}", fmt_duration(judge_load));
+
+    let mut rows = Vec::with_capacity(rendered.len());
+    for (preset, index, image_path) in rendered {
+        let seed = &seeds[index];
+        let image = InputImage::open(Path::new(&image_path))?;
+        let reply = interrupt::unless_cancelled(
+            Some(&cancel),
+            judge.describe(vec![image.image], &judge_instruction(seed)),
+        )
+        .await
+        .with_context(|| format!("Failed to score {image_path}"))?;
+        let score = parse_score(&reply);
+        match score {
+            Some(score) => status!(
+                out,
+                "  {} seed {}/{}: {score:.1}/10",
+                preset_name(preset),
+                index + 1,
+                seeds.len()
+            ),
+            None => {
+                tracing::warn!("no 1-10 score in the judge's reply for {image_path}: {reply:?}")
+            }
+        }
+        let prompt = prompts
+            .iter()
+            .find(|(p, _)| *p == preset)
+            .map(|(_, enhanced)| enhanced[index].clone())
+            .unwrap_or_default();
+        rows.push(EvalRow {
+            preset: preset_name(preset),
+            seed: seed.clone(),
+            prompt,
+            image_path,
+            score,
+            critique: parse_critique(&reply),
+            reply,
+        });
+    }
+    drop(judge);
+
+    let report = EvalReport::new(options.judge.to_string(), rows, &options.models);
+    report.write(&report_path)?;
+    status!(out, "\nReport written to {}", report_path.display());
+
+    if out.json {
+        return ui::print_json(&report);
+    }
+    println!();
+    report.print_table();
+    Ok(())
+}
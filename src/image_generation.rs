@@ -1,122 +1,2362 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use base64::Engine;
 use mistralrs::{
-    DiffusionGenerationParams, DiffusionLoaderType, DiffusionModelBuilder,
-    ImageGenerationResponseFormat, ModelDType,
+    DiffusionGenerationParams, DiffusionLoaderType, DiffusionModelBuilder, ImageChoice,
+    ImageGenerationResponse, ImageGenerationResponseFormat, Model, ModelDType,
 };
-use std::time::{Duration, Instant};
-
-use crate::promp_enhancer::{EnhancerModel, PromptEnhancer};
-
-/// Maximum number of whitespace-separated words to send to the diffusion model.
-/// CLIP (used by FLUX.1-schnell) has a hard 77-token limit; keeping prompts
-/// under 50 words provides safe headroom for BOS/EOS and sub-word splits.
-const MAX_PROMPT_WORDS: usize = 50;
-
-/// Format a `Duration` as `Xm Ys` (e.g. "2m 30.5s") or just `Ys` when under a minute.
-fn fmt_duration(d: Duration) -> String {
-    let total_secs = d.as_secs_f64();
-    let mins = (total_secs / 60.0).floor() as u64;
-    let secs = total_secs - (mins as f64 * 60.0);
-    if mins > 0 {
-        format!("{}m {:.1}s", mins, secs)
+use serde::Serialize;
+use std::fmt;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio_util::sync::CancellationToken;
+
+use crate::artifacts::ArtifactLedger;
+use crate::cli_chat::fmt_utc;
+use crate::device;
+use crate::disk_space;
+use crate::enhancer_chain::{self, CliBuilder};
+use crate::error::Error;
+use crate::image_metadata::{ImageMetadata, MetadataOutput, PromptOrigin};
+use crate::image_output::{self, GridCaption, GridOptions};
+use crate::image_session;
+use crate::interrupt::{self, Interrupted};
+use crate::load_options::{DtypeChoice, LoadOptions};
+use crate::loader;
+use crate::memory::{self, MemoryFootprint};
+use crate::memstats::{LoadMemory, MemorySnapshot};
+use crate::promp_enhancer::{self, EnhancerBackend, EnhancerModel, PromptEnhance, PromptEnhancer};
+use crate::prompt_cache::{CacheKey, CacheMode, PromptCache};
+use crate::prompt_limits::{
+    MAX_OVERFLOW_RETRIES, PromptBudget, describe_length, is_prompt_overflow, shorten_prompt,
+};
+use crate::remote_enhancer::{self, RemoteArgs};
+use crate::sampler::{self, SamplerArgs};
+use crate::styles;
+use crate::template_enhancer::TemplateEnhancer;
+use crate::timeout;
+use crate::ui::{self, OutputConfig, detail, fmt_duration, status};
+
+pub const DEFAULT_MODEL: &str = "black-forest-labs/FLUX.1-schnell";
+const DEFAULT_LOADER: DiffusionLoaderType = DiffusionLoaderType::FluxOffloaded;
+const DEFAULT_DTYPE: ModelDType = ModelDType::BF16;
+
+/// Names the diffusion model when `--diffusion-model` isn't given.
+pub const DIFFUSION_MODEL_ENV: &str = "MISTRALRS_EXAMPLE_DIFFUSION_MODEL";
+
+/// Prompt used when `image` is given neither `--prompt` nor `--seed-prompt`.
+const DEFAULT_PROMPT: &str = "A majestic castle on a cliff overlooking the sea at sunset, \
+                              highly detailed, digital painting, trending on artstation, \
+                              in the style of Raden Saleh";
+
+/// Rough peak footprint of [`DEFAULT_MODEL`] loaded with [`DEFAULT_LOADER`]
+/// in BF16, used to decide whether the prompt enhancer fits alongside it.
+pub const DIFFUSION_APPROX_BYTES: u64 = 12_000_000_000;
+
+/// What [`DEFAULT_MODEL`] downloads on first use: the transformer, the T5
+/// and CLIP text encoders and the VAE.
+pub const DIFFUSION_DOWNLOAD_BYTES: u64 = 34_000_000_000;
+
+/// FLUX's VAE downsamples by 8 and the transformer packs 2×2 latent patches,
+/// so both image dimensions must be multiples of 16.
+const DIMENSION_MULTIPLE: usize = 16;
+
+/// Stage name for [`Error::Generation`] and [`TimedOut`](timeout::TimedOut).
+const GENERATE_STAGE: &str = "Image generation";
+
+/// Diffusion settings chosen on the command line.
+#[derive(Clone, Debug)]
+pub struct ImageOptions {
+    /// Output width in pixels (`None` = mistral.rs default).
+    pub width: Option<usize>,
+    /// Output height in pixels (`None` = mistral.rs default).
+    pub height: Option<usize>,
+    /// Number of images to generate from the same prompt.
+    pub num_images: usize,
+    /// Write PNGs to this path instead of mistral.rs' temporary location.
+    /// With several images, `_1`, `_2`, … are appended before the extension.
+    pub out: Option<PathBuf>,
+    /// Overwrite existing files at `out`.
+    pub force: bool,
+    /// Things the image should not contain.
+    pub negative_prompt: Option<String>,
+    /// Classifier-free guidance scale.
+    pub guidance: Option<f64>,
+    /// Metadata written with each saved image.
+    pub metadata: MetadataOutput,
+    /// Also compose the images into one contact sheet (`--grid`).
+    pub grid: Option<GridOptions>,
+    /// Render the same prompt at each of these sizes (`--sizes`) instead of
+    /// `width` × `height`; see [`for_size`](Self::for_size).
+    pub sizes: Vec<ImageSize>,
+}
+
+impl ImageOptions {
+    /// Validate the options and build the [`DiffusionGenerationParams`].
+    ///
+    /// Called before any model is loaded so bad dimensions fail immediately.
+    pub fn generation_params(&self) -> Result<DiffusionGenerationParams> {
+        let defaults = DiffusionGenerationParams::default();
+        let width = self.width.unwrap_or(defaults.width);
+        let height = self.height.unwrap_or(defaults.height);
+
+        for (name, value) in [("width", width), ("height", height)] {
+            if let Some(problem) = dimension_problem(value) {
+                anyhow::bail!("--{name} {problem}");
+            }
+        }
+        for (i, size) in self.sizes.iter().enumerate() {
+            for (name, value) in [("width", size.width), ("height", size.height)] {
+                if let Some(problem) = dimension_problem(value) {
+                    anyhow::bail!("--sizes {size}: the {name} {problem}");
+                }
+            }
+            if self.sizes[..i].contains(size) {
+                anyhow::bail!("--sizes lists {size} twice");
+            }
+        }
+        if self.num_images == 0 {
+            anyhow::bail!("--num-images must be at least 1");
+        }
+        if let Some(g) = self.guidance
+            && !(g.is_finite() && g > 0.0)
+        {
+            anyhow::bail!("--guidance must be a positive number, got {g}");
+        }
+        if let Some(grid) = &self.grid {
+            if self.num_images < 2 {
+                anyhow::bail!("--grid needs --num-images of at least 2");
+            }
+            if grid.columns == Some(0) {
+                anyhow::bail!("--grid-columns must be at least 1");
+            }
+        }
+
+        match self.sizes.first() {
+            Some(size) => Ok(DiffusionGenerationParams {
+                height: size.height,
+                width: size.width,
+            }),
+            None => Ok(DiffusionGenerationParams { height, width }),
+        }
+    }
+
+    /// Refuse to clobber existing files unless `--force` was passed.
+    pub fn check_overwrite(&self) -> Result<()> {
+        if !self.sizes.is_empty() {
+            return self
+                .sizes
+                .iter()
+                .try_for_each(|&size| self.for_size(size).check_overwrite());
+        }
+        let Some(out) = &self.out else {
+            return Ok(());
+        };
+        if self.force {
+            return Ok(());
+        }
+        let grid = self.grid.map(|_| image_output::grid_path(out));
+        let images = (0..self.num_images).map(|index| numbered_path(out, self.num_images, index));
+        for path in images.chain(grid) {
+            if path.exists() {
+                anyhow::bail!(
+                    "Output file already exists: {} (pass --force to overwrite)",
+                    path.display()
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// An [`ArtifactLedger`] for the output directory, when these options
+    /// (for each of `seeds` seed prompts) write more than one image.
+    pub fn artifact_ledger(&self, seeds: usize) -> Option<ArtifactLedger> {
+        let out = match (&self.out, self.sizes.is_empty()) {
+            (Some(out), _) => out.clone(),
+            (None, false) => PathBuf::from(image_session::DEFAULT_OUT),
+            (None, true) => return None,
+        };
+        let images = self.num_images * self.sizes.len().max(1) * seeds;
+        let dir = out.parent().map(Path::to_path_buf).unwrap_or_default();
+        (images > 1).then(|| ArtifactLedger::new(dir))
+    }
+
+    /// These options for the `index`-th of `count` seeds rendered in one
+    /// run: `out` numbered like [`numbered_path`] when there are several.
+    pub fn for_seed(&self, index: usize, count: usize) -> Self {
+        Self {
+            out: self
+                .out
+                .as_deref()
+                .map(|out| numbered_path(out, count, index)),
+            ..self.clone()
+        }
+    }
+
+    /// These options for one of the `--sizes`: `size` as the width and
+    /// height, and `out` (or [`DEFAULT_OUT`](image_session::DEFAULT_OUT))
+    /// suffixed with it, e.g. `image_1024x1024.png`.
+    pub fn for_size(&self, size: ImageSize) -> Self {
+        let out = self
+            .out
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(image_session::DEFAULT_OUT));
+        Self {
+            width: Some(size.width),
+            height: Some(size.height),
+            out: Some(sized_path(&out, size)),
+            sizes: Vec::new(),
+            ..self.clone()
+        }
+    }
+
+    /// Build the [`ImageRequest`] for `prompt` with these options, for
+    /// CLIP's window.
+    pub fn request(&self, prompt: &str) -> Result<ImageRequest> {
+        self.request_with_budget(prompt, PromptBudget::CLIP)
+    }
+
+    /// Like [`request`](Self::request), with the prompts cut to `budget`
+    /// (see [`ImageGenerator::prompt_budget`]).
+    pub fn request_with_budget(&self, prompt: &str, budget: PromptBudget) -> Result<ImageRequest> {
+        let mut request = ImageRequest::with_budget(prompt, budget)
+            .with_params(self.generation_params()?)
+            .with_num_images(self.num_images)
+            .with_metadata(self.metadata);
+        if let Some(negative) = &self.negative_prompt {
+            request = request.with_negative_prompt(negative);
+        }
+        if let Some(guidance) = self.guidance {
+            request = request.with_guidance(guidance);
+        }
+        if let Some(out) = &self.out {
+            request = request.with_out(out);
+        }
+        Ok(request)
+    }
+}
+
+/// Why `value` can't be an image dimension, if it can't: the rest of a
+/// sentence that starts with the option's name.
+fn dimension_problem(value: usize) -> Option<String> {
+    if value != 0 && value.is_multiple_of(DIMENSION_MULTIPLE) {
+        return None;
+    }
+    let lower = (value / DIMENSION_MULTIPLE * DIMENSION_MULTIPLE).max(DIMENSION_MULTIPLE);
+    Some(format!(
+        "must be a positive multiple of {DIMENSION_MULTIPLE} for FLUX, got {value} \
+         (try {lower} or {})",
+        lower + DIMENSION_MULTIPLE
+    ))
+}
+
+/// `out` with `_<width>x<height>` before the extension, for `--sizes`.
+fn sized_path(out: &Path, size: ImageSize) -> PathBuf {
+    let stem = out.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match out.extension() {
+        Some(ext) => format!("{stem}_{size}.{}", ext.to_string_lossy()),
+        None => format!("{stem}_{size}"),
+    };
+    out.with_file_name(name)
+}
+
+/// Path of the `index`-th (0-based) of `count` images written to `out`:
+/// `out` itself for a single image, otherwise `_1`, `_2`, … before the
+/// extension.
+fn numbered_path(out: &Path, count: usize, index: usize) -> PathBuf {
+    if count == 1 {
+        return out.to_path_buf();
+    }
+    let stem = out.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match out.extension() {
+        Some(ext) => format!("{stem}_{}.{}", index + 1, ext.to_string_lossy()),
+        None => format!("{stem}_{}", index + 1),
+    };
+    out.with_file_name(name)
+}
+
+/// Warnings for request settings `loader` will ignore; see
+/// [`ImageGenerator::ignored_settings`].
+fn ignored_settings(loader: DiffusionLoaderType, request: &ImageRequest) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if request.negative_prompt.is_some() {
+        warnings.push(format!(
+            "negative prompt is ignored: the {loader:?} loader does not support negative prompts"
+        ));
+    }
+    if request.guidance.is_some() {
+        warnings.push(format!(
+            "guidance is ignored: the {loader:?} loader does not expose a guidance scale"
+        ));
+    }
+    warnings
+}
+
+// ── Sizes ────────────────────────────────────────────────────────────────────
+
+/// One output size of `--sizes`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct ImageSize {
+    pub width: usize,
+    pub height: usize,
+}
+
+/// `1920x1080`.
+impl fmt::Display for ImageSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)
+    }
+}
+
+/// Parse one `--sizes` entry: `WIDTHxHEIGHT` (`x` or `X`), or a single
+/// number for a square.  Whether the model takes the size is checked later,
+/// by [`ImageOptions::generation_params`].
+pub fn parse_size(s: &str) -> Result<ImageSize, String> {
+    let s = s.trim();
+    let number = |part: &str, name: &str| {
+        part.trim()
+            .parse::<usize>()
+            .map_err(|_| format!("invalid {name} `{}` in size `{s}`", part.trim()))
+    };
+    match s.split_once(['x', 'X']) {
+        Some((width, height)) => Ok(ImageSize {
+            width: number(width, "width")?,
+            height: number(height, "height")?,
+        }),
+        None if s.is_empty() => Err("empty size; expected WIDTHxHEIGHT, e.g. 1024x768".into()),
+        None => {
+            let side = s.parse::<usize>().map_err(|_| {
+                format!("invalid size `{s}`; expected WIDTHxHEIGHT or one number for a square")
+            })?;
+            Ok(ImageSize {
+                width: side,
+                height: side,
+            })
+        }
+    }
+}
+
+// ── ImageRequest ─────────────────────────────────────────────────────────────
+
+/// One prompt's worth of work for [`ImageGenerator::generate`].
+///
+/// The prompt and negative prompt are truncated to the text encoder's
+/// [`PromptBudget`] on construction, so whatever is stored here is exactly
+/// what the diffusion model will see.
+#[derive(Clone, Debug)]
+pub struct ImageRequest {
+    pub prompt: String,
+    pub negative_prompt: Option<String>,
+    pub guidance: Option<f64>,
+    pub params: DiffusionGenerationParams,
+    pub num_images: usize,
+    /// Write PNGs here (numbered when `num_images > 1`) instead of
+    /// mistral.rs' temporary location.
+    pub out: Option<PathBuf>,
+    /// Where the prompt came from, recorded in each image's metadata.
+    pub origin: PromptOrigin,
+    /// Which metadata copies to write for each image.
+    pub metadata: MetadataOutput,
+    /// The window the prompts were cut to.
+    pub budget: PromptBudget,
+}
+
+impl ImageRequest {
+    /// A request for one image of `prompt` at the default size, cut to
+    /// `budget`'s window.
+    pub fn with_budget(prompt: &str, budget: PromptBudget) -> Self {
+        Self {
+            prompt: budget.truncate(prompt),
+            negative_prompt: None,
+            guidance: None,
+            params: DiffusionGenerationParams::default(),
+            num_images: 1,
+            out: None,
+            origin: PromptOrigin::default(),
+            metadata: MetadataOutput::default(),
+            budget,
+        }
+    }
+
+    /// Things the image should not contain.  Subject to the same
+    /// truncation as the prompt.
+    pub fn with_negative_prompt(mut self, negative_prompt: &str) -> Self {
+        self.negative_prompt = Some(self.budget.truncate(negative_prompt));
+        self
+    }
+
+    /// Classifier-free guidance scale.
+    pub fn with_guidance(mut self, guidance: f64) -> Self {
+        self.guidance = Some(guidance);
+        self
+    }
+
+    /// Image size.
+    pub fn with_params(mut self, params: DiffusionGenerationParams) -> Self {
+        self.params = params;
+        self
+    }
+
+    /// Number of images to generate from the prompt.
+    pub fn with_num_images(mut self, num_images: usize) -> Self {
+        self.num_images = num_images;
+        self
+    }
+
+    /// Save PNGs to `out`.
+    pub fn with_out(mut self, out: impl Into<PathBuf>) -> Self {
+        self.out = Some(out.into());
+        self
+    }
+
+    /// Record the seed, enhancer and enhanced prompt behind `prompt` in the
+    /// image metadata.
+    pub fn with_origin(mut self, origin: PromptOrigin) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    /// Choose the metadata written with each image (default: a JSON
+    /// sidecar only).
+    pub fn with_metadata(mut self, metadata: MetadataOutput) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Path the `index`-th (0-based) image is written to, if `out` is set.
+    fn output_path(&self, index: usize) -> Option<PathBuf> {
+        let out = self.out.as_ref()?;
+        Some(numbered_path(out, self.num_images, index))
+    }
+}
+
+// ── ImageResult ──────────────────────────────────────────────────────────────
+
+/// The images produced for one [`ImageRequest`].
+#[derive(Debug, Serialize)]
+pub struct ImageResult {
+    /// The prompt sent to the diffusion model (after enhancement and
+    /// truncation).
+    pub prompt: String,
+    pub width: usize,
+    pub height: usize,
+    pub images: Vec<GeneratedImage>,
+    /// The `--grid` contact sheet, if one was written.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grid: Option<String>,
+    /// Cancelled before every requested image was generated; `images` holds
+    /// the ones finished before that.
+    pub interrupted: bool,
+    /// Stopped before every requested image was generated because the
+    /// output volume fell below the `--min-free` floor; `images` holds the
+    /// ones saved before that.
+    pub low_space: bool,
+    /// Bytes the run has written so far, images of earlier requests
+    /// included, when it keeps an [`ArtifactLedger`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_bytes: Option<u64>,
+}
+
+/// One saved image within an [`ImageResult`].
+#[derive(Debug, Serialize)]
+pub struct GeneratedImage {
+    /// Where the PNG was written.
+    pub path: String,
+    /// Seconds spent generating this image.
+    pub generation_secs: f64,
+    /// The metadata sidecar written next to it, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<String>,
+    /// Size of the PNG and its sidecar, when the run keeps an
+    /// [`ArtifactLedger`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bytes: Option<u64>,
+}
+
+/// The images rendered at one of the `--sizes`, or why none were.
+#[derive(Debug, Serialize)]
+pub struct SizeResult {
+    #[serde(flatten)]
+    pub size: ImageSize,
+    pub images: Vec<GeneratedImage>,
+    /// The `--grid` contact sheet for this size, if one was written.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grid: Option<String>,
+    /// Seconds spent on this size, failed or not.
+    pub generation_secs: f64,
+    /// Why this size failed; the other sizes were still rendered.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Everything an `image --sizes` run rendered, in the order of `--sizes`.
+#[derive(Debug, Serialize)]
+pub struct SizesResult {
+    /// The prompt sent to the diffusion model, as for [`ImageResult`].
+    pub prompt: String,
+    pub sizes: Vec<SizeResult>,
+    /// Cancelled before every size was rendered; `sizes` holds the ones
+    /// started before that.
+    pub interrupted: bool,
+    /// Stopped at the `--min-free` floor, as for [`ImageResult`].
+    pub low_space: bool,
+}
+
+impl SizesResult {
+    /// Sizes that ended in an error.
+    pub fn failed(&self) -> usize {
+        self.sizes
+            .iter()
+            .filter(|size| size.error.is_some())
+            .count()
+    }
+}
+
+// ── Diffusion model ──────────────────────────────────────────────────────────
+
+/// Values accepted by `--loader`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum LoaderChoice {
+    /// Everything on the device: fastest, needs the most memory.
+    Flux,
+    /// Offload parts of the model to the CPU between steps.
+    #[default]
+    FluxOffloaded,
+}
+
+impl LoaderChoice {
+    /// The mistral.rs loader this choice selects.
+    pub fn loader_type(self) -> DiffusionLoaderType {
+        match self {
+            Self::Flux => DiffusionLoaderType::Flux,
+            Self::FluxOffloaded => DiffusionLoaderType::FluxOffloaded,
+        }
+    }
+}
+
+/// Which diffusion model `image` loads, and how.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiffusionConfig {
+    /// HuggingFace model ID.
+    pub model_id: String,
+    pub loader: DiffusionLoaderType,
+    pub dtype: ModelDType,
+    /// `--max-prompt-tokens`: the text encoder's window (`None` = the
+    /// model's own; see [`PromptBudget::for_model`]).
+    pub max_prompt_tokens: Option<usize>,
+}
+
+impl Default for DiffusionConfig {
+    /// FLUX.1-schnell, offloaded, in BF16.
+    fn default() -> Self {
+        Self {
+            model_id: DEFAULT_MODEL.to_string(),
+            loader: DEFAULT_LOADER,
+            dtype: DEFAULT_DTYPE,
+            max_prompt_tokens: None,
+        }
+    }
+}
+
+impl DiffusionConfig {
+    /// The model from `--diffusion-model`, else [`DIFFUSION_MODEL_ENV`],
+    /// else [`DEFAULT_MODEL`]; `dtype` defaults to BF16.
+    pub fn resolve(
+        model: Option<String>,
+        loader: LoaderChoice,
+        dtype: Option<DtypeChoice>,
+    ) -> Self {
+        let model_id = model
+            .or_else(|| std::env::var(DIFFUSION_MODEL_ENV).ok())
+            .map(|id| id.trim().to_string())
+            .filter(|id| !id.is_empty())
+            .unwrap_or_else(|| DEFAULT_MODEL.to_string());
+        Self {
+            model_id,
+            loader: loader.loader_type(),
+            dtype: dtype.map_or(DEFAULT_DTYPE, DtypeChoice::model_dtype),
+            max_prompt_tokens: None,
+        }
+    }
+
+    /// Override the text encoder's window (`None` keeps the model's own).
+    pub fn with_max_prompt_tokens(mut self, max_tokens: Option<usize>) -> Self {
+        self.max_prompt_tokens = max_tokens;
+        self
+    }
+
+    /// How long a prompt the model takes: `--max-prompt-tokens`, else the
+    /// model's entry in [`PromptBudget::for_model`]'s table.
+    pub fn prompt_budget(&self) -> PromptBudget {
+        PromptBudget::resolve(&self.model_id, self.max_prompt_tokens)
+    }
+
+    /// Whether [`DIFFUSION_APPROX_BYTES`] applies: it was measured for the
+    /// default model and loader, in either 16-bit dtype.
+    pub fn footprint_known(&self) -> bool {
+        self.model_id == DEFAULT_MODEL && self.loader == DEFAULT_LOADER
+    }
+}
+
+/// `black-forest-labs/FLUX.1-schnell (FluxOffloaded, BF16)`.
+impl fmt::Display for DiffusionConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({:?}, {:?})", self.model_id, self.loader, self.dtype)
+    }
+}
+
+// ── ImageGenerator ───────────────────────────────────────────────────────────
+
+/// A loaded diffusion model that can be reused for several prompts.
+pub struct ImageGenerator {
+    model: Model,
+    model_id: String,
+    loader: DiffusionLoaderType,
+    dtype: ModelDType,
+    budget: PromptBudget,
+    /// Stops generation early; see [`with_cancellation`](Self::with_cancellation).
+    cancel: Option<CancellationToken>,
+    /// Limit per image: `--timeout`, if given.
+    timeout: Option<Duration>,
+    /// Files written so far; see [`with_artifacts`](Self::with_artifacts).
+    artifacts: Option<Mutex<ArtifactLedger>>,
+}
+
+impl ImageGenerator {
+    /// Load the default model (FLUX.1-schnell, offloaded).
+    pub async fn new() -> Result<Self, Error> {
+        Self::from_config(&DiffusionConfig::default()).await
+    }
+
+    /// Load the model `config` names, with its loader and dtype.
+    #[tracing::instrument(
+        name = "build_model",
+        skip_all,
+        fields(model_id = %config.model_id, kind = "Diffusion")
+    )]
+    pub async fn from_config(config: &DiffusionConfig) -> Result<Self, Error> {
+        let (model_id, loader, dtype) = (config.model_id.as_str(), config.loader, config.dtype);
+        if model_id == DEFAULT_MODEL {
+            disk_space::preflight(model_id, DIFFUSION_DOWNLOAD_BYTES)
+                .map_err(|source| Error::model_load(model_id, source))?;
+        }
+        let model = loader::with_retries(model_id, || {
+            let mut builder = DiffusionModelBuilder::new(model_id, loader).with_dtype(dtype);
+            if device::selected().force_cpu() {
+                builder = builder.with_force_cpu();
+            }
+            loader::with_progress(model_id, "Loading weights", builder.build())
+        })
+        .await
+        .map_err(|source| Error::model_load(model_id, source))?;
+        Ok(Self {
+            model,
+            model_id: model_id.to_string(),
+            loader,
+            dtype,
+            budget: config.prompt_budget(),
+            cancel: None,
+            timeout: timeout::default_limit(),
+            artifacts: None,
+        })
+    }
+
+    /// The text encoder's window, for building requests with
+    /// [`ImageRequest::with_budget`].
+    pub fn prompt_budget(&self) -> PromptBudget {
+        self.budget
+    }
+
+    /// Stop when `cancel` fires: the image in progress is abandoned and the
+    /// images already saved are returned with
+    /// [`interrupted`](ImageResult::interrupted) set.
+    pub fn with_cancellation(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Count every file written in `ledger`, and check its free-space
+    /// floor before each image: once the output volume is below it, the
+    /// request stops with [`low_space`](ImageResult::low_space) set.  For
+    /// runs that write several images to `--out`.
+    pub fn with_artifacts(mut self, ledger: ArtifactLedger) -> Self {
+        self.artifacts = Some(Mutex::new(ledger));
+        self
+    }
+
+    /// The ledger's `Wrote N artifacts, …` line, if there is one.
+    pub fn artifact_summary(&self) -> Option<String> {
+        let ledger = self.artifacts.as_ref()?;
+        Some(
+            ledger
+                .lock()
+                .expect("artifact ledger lock poisoned")
+                .summary(),
+        )
+    }
+
+    /// Count `path` in the ledger; its size, or `None` without a ledger.
+    pub(crate) fn record_artifact(&self, path: &Path) -> Result<Option<u64>> {
+        let Some(ledger) = &self.artifacts else {
+            return Ok(None);
+        };
+        let mut ledger = ledger.lock().expect("artifact ledger lock poisoned");
+        ledger.record(path).map(Some)
+    }
+
+    /// Whether the output volume is below the ledger's floor, warning when
+    /// it is.  Always `false` without a ledger.
+    fn low_on_space(&self) -> Result<bool> {
+        let Some(ledger) = &self.artifacts else {
+            return Ok(false);
+        };
+        let space = ledger
+            .lock()
+            .expect("artifact ledger lock poisoned")
+            .check_space()?;
+        if space.is_low() {
+            tracing::warn!("{space}; not generating more images");
+        }
+        Ok(space.is_low())
+    }
+
+    fn artifact_total(&self) -> Option<u64> {
+        let ledger = self.artifacts.as_ref()?;
+        Some(
+            ledger
+                .lock()
+                .expect("artifact ledger lock poisoned")
+                .total_bytes(),
+        )
+    }
+
+    /// Warnings for request settings the loaded model will ignore.
+    ///
+    /// mistral.rs' diffusion pipelines only take a size: the FLUX loaders
+    /// have no negative-prompt input, and guidance is fixed by the model
+    /// (schnell is guidance-distilled).
+    pub fn ignored_settings(&self, request: &ImageRequest) -> Vec<String> {
+        ignored_settings(self.loader, request)
+    }
+
+    /// Generate every image in `request`.
+    pub async fn generate(&self, request: ImageRequest) -> Result<ImageResult, Error> {
+        self.generate_with_progress(request, |_, _| {}).await
+    }
+
+    /// Like [`generate`](Self::generate), calling `on_image(index, total)`
+    /// before each image starts.
+    ///
+    /// When the model rejects the prompt as too long for its text encoder,
+    /// it's retried shortened by [`shorten_prompt`], up to
+    /// [`MAX_OVERFLOW_RETRIES`] times.  The prompt that worked is the one
+    /// in the result and the metadata, and is kept for the later images.
+    /// When every retry is rejected too, the error is
+    /// [`Error::PromptTooLong`].
+    pub async fn generate_with_progress<F: FnMut(usize, usize)>(
+        &self,
+        request: ImageRequest,
+        on_image: F,
+    ) -> Result<ImageResult, Error> {
+        self.images_result(request, on_image)
+            .await
+            .map_err(|source| Error::generation(GENERATE_STAGE, source))
+    }
+
+    async fn images_result<F: FnMut(usize, usize)>(
+        &self,
+        mut request: ImageRequest,
+        mut on_image: F,
+    ) -> Result<ImageResult> {
+        let response_format = if request.out.is_some() {
+            ImageGenerationResponseFormat::B64Json
+        } else {
+            ImageGenerationResponseFormat::Url
+        };
+
+        let mut images = Vec::with_capacity(request.num_images);
+        let mut interrupted = false;
+        let mut low_space = false;
+        for i in 0..request.num_images {
+            if self.low_on_space()? {
+                low_space = true;
+                break;
+            }
+            on_image(i, request.num_images);
+            let start = Instant::now();
+            let response = match self.generate_one(&mut request, response_format).await {
+                Err(e) if interrupt::is_interrupted(&e) => {
+                    interrupted = true;
+                    break;
+                }
+                response => response?,
+            };
+            let elapsed = start.elapsed();
+
+            for image in &response.data {
+                let path = match request.output_path(images.len()) {
+                    Some(path) => {
+                        write_png(image, &path)?;
+                        path.display().to_string()
+                    }
+                    None => image
+                        .url
+                        .clone()
+                        .context("expected image URL in response")?,
+                };
+                let file = Path::new(&path);
+                let (metadata, bytes) = if file.is_file() {
+                    let sidecar = self
+                        .metadata(&request, elapsed)
+                        .save(file, request.metadata)?;
+                    // After the metadata, which may be embedded in the PNG.
+                    let mut bytes = self.record_artifact(file)?;
+                    if let Some(sidecar) = &sidecar
+                        && let Some(size) = self.record_artifact(sidecar)?
+                    {
+                        bytes = bytes.map(|png| png + size);
+                    }
+                    (sidecar.map(|sidecar| sidecar.display().to_string()), bytes)
+                } else {
+                    (None, None)
+                };
+                images.push(GeneratedImage {
+                    path,
+                    generation_secs: elapsed.as_secs_f64(),
+                    metadata,
+                    bytes,
+                });
+            }
+        }
+
+        Ok(ImageResult {
+            prompt: request.prompt,
+            width: request.params.width,
+            height: request.params.height,
+            images,
+            grid: None,
+            interrupted,
+            low_space,
+            total_bytes: self.artifact_total(),
+        })
+    }
+
+    /// One image of `request`, shortening its prompt after each overflow
+    /// error (see [`generate_with_progress`](Self::generate_with_progress)).
+    #[tracing::instrument(
+        name = "generate_image",
+        skip_all,
+        fields(
+            model_id = %self.model_id,
+            width = request.params.width,
+            height = request.params.height,
+            prompt_words = request.prompt.split_whitespace().count()
+        )
+    )]
+    async fn generate_one(
+        &self,
+        request: &mut ImageRequest,
+        response_format: ImageGenerationResponseFormat,
+    ) -> Result<ImageGenerationResponse> {
+        let original = request.prompt.clone();
+        let mut retries = 0;
+        loop {
+            let generation = timeout::within(
+                self.timeout,
+                GENERATE_STAGE,
+                self.model.generate_image(
+                    &request.prompt,
+                    response_format,
+                    request.params.clone(),
+                    None,
+                ),
+            );
+            let err = match interrupt::unless_cancelled(self.cancel.as_ref(), generation).await {
+                Ok(response) => return Ok(response),
+                Err(err) if interrupt::is_interrupted(&err) || !is_prompt_overflow(&err) => {
+                    return Err(err);
+                }
+                Err(err) => err,
+            };
+            let shorter = shorten_prompt(&request.prompt);
+            if retries == MAX_OVERFLOW_RETRIES || shorter.is_empty() {
+                return Err(Error::PromptTooLong {
+                    length: describe_length(&original),
+                    last_tried: describe_length(&request.prompt),
+                    source: err,
+                }
+                .into());
+            }
+            retries += 1;
+            tracing::warn!(
+                "the prompt ({}) is too long for the text encoder; \
+                 retrying with {} (retry {retries} of {MAX_OVERFLOW_RETRIES})",
+                describe_length(&request.prompt),
+                describe_length(&shorter)
+            );
+            request.prompt = shorter;
+        }
+    }
+
+    /// Metadata for an image of `request` that took `elapsed`, created now.
+    fn metadata(&self, request: &ImageRequest, elapsed: Duration) -> ImageMetadata {
+        ImageMetadata {
+            origin: request.origin.clone(),
+            prompt: request.prompt.clone(),
+            negative_prompt: request.negative_prompt.clone(),
+            model: self.model_id.clone(),
+            loader: format!("{:?}", self.loader),
+            dtype: Some(format!("{:?}", self.dtype)),
+            width: request.params.width,
+            height: request.params.height,
+            steps: diffusion_steps(&self.model_id),
+            generation_secs: elapsed.as_secs_f64(),
+            created_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+        }
+    }
+}
+
+/// Denoising steps mistral.rs runs for `model_id`, where the model fixes
+/// them: FLUX.1-schnell is distilled for 4.  `None` otherwise.
+fn diffusion_steps(model_id: &str) -> Option<usize> {
+    model_id.to_lowercase().contains("schnell").then_some(4)
+}
+
+/// Decode a base64 image payload and write it to `path` as a PNG, creating
+/// parent directories as needed.
+fn write_png(image: &ImageChoice, path: &Path) -> Result<()> {
+    let b64 = image
+        .b64_json
+        .as_deref()
+        .context("expected base64 image data in response")?;
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(b64)
+        .context("Failed to decode base64 image data")?;
+
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create output directory: {}", parent.display()))?;
+    }
+
+    const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+    if bytes.starts_with(PNG_SIGNATURE) {
+        std::fs::write(path, &bytes)
+            .with_context(|| format!("Failed to write image: {}", path.display()))
     } else {
-        format!("{:.1}s", secs)
+        // Not already PNG-encoded: decode and re-encode so the file on disk
+        // always matches its extension.
+        image::load_from_memory(&bytes)
+            .context("Failed to decode generated image")?
+            .save_with_format(path, image::ImageFormat::Png)
+            .with_context(|| format!("Failed to write image: {}", path.display()))
     }
 }
 
-const DEFAULT_MODEL: &str = "black-forest-labs/FLUX.1-schnell";
-const DEFAULT_LOADER: DiffusionLoaderType = DiffusionLoaderType::FluxOffloaded;
+// ── Prompt enhancement ───────────────────────────────────────────────────────
+
+/// Prompt-enhancer settings for `image --seed-prompt`.
+#[derive(Clone, Debug, Default)]
+pub struct EnhancerOptions {
+    /// Model or template backend (`--enhancer`).
+    pub backend: EnhancerBackend,
+    /// Preset to load (`None` = default preset).
+    pub model: Option<EnhancerModel>,
+    /// Load a local model instead of a preset.
+    pub model_path: Option<PathBuf>,
+    /// `--isq` / `--dtype` overrides.
+    pub load: LoadOptions,
+    /// `--temperature` / `--top-p` / `--max-tokens` overrides.
+    pub sampler: SamplerArgs,
+    /// `--sampler-seed` for a model enhancer; [`run`] picks (and prints) a
+    /// random one when unset.
+    pub sampler_seed: Option<u64>,
+    /// Number of candidate prompts to generate (`None` = 1).
+    pub candidates: Option<usize>,
+    /// 1-based candidate to use; asked interactively when unset.
+    pub pick: Option<usize>,
+    /// `--style` preset name, composed with the seed before enhancement.
+    pub style: Option<String>,
+    /// Load the diffusion model only after enhancement (`--sequential`)
+    /// instead of overlapping the two.
+    pub sequential: bool,
+    /// `--fallback`: presets to try after `model` if it fails to load;
+    /// empty for [`enhancer_chain::DEFAULT_CHAIN`].
+    pub fallback: Option<Vec<EnhancerModel>>,
+    /// `--no-cache` / `--refresh`; see [`CacheLookup`].
+    pub cache: CacheMode,
+    /// `--remote-url` / `--remote-model` for `--enhancer remote`.
+    pub remote: RemoteArgs,
+}
+
+impl EnhancerOptions {
+    /// Validate `--candidates` / `--pick` before any model is loaded.
+    fn validate(&self) -> Result<()> {
+        let n = self.candidates.unwrap_or(1);
+        if n == 0 {
+            anyhow::bail!("--candidates must be at least 1");
+        }
+        if let Some(pick) = self.pick
+            && !(1..=n).contains(&pick)
+        {
+            anyhow::bail!("--pick must be between 1 and {n}, got {pick}");
+        }
+        if self.backend == EnhancerBackend::Remote {
+            if self.model.is_some() || self.model_path.is_some() || self.fallback.is_some() {
+                anyhow::bail!(
+                    "--model / --model-path / --fallback can't be used with --enhancer remote; \
+                     name the model with --remote-model"
+                );
+            }
+        } else if self.remote.is_set() {
+            anyhow::bail!(
+                "--remote-url / --remote-model / --remote-timeout need --enhancer remote"
+            );
+        }
+        Ok(())
+    }
+
+    /// The preset that would load: `--model` (or the default), or with
+    /// `--fallback` the first preset of the chain that fits now.
+    fn planned_preset(&self) -> EnhancerModel {
+        match &self.fallback {
+            Some(fallback) => enhancer_chain::would_load(
+                &enhancer_chain::resolve(self.model, fallback),
+                &self.load,
+            ),
+            None => self.model.unwrap_or_default(),
+        }
+    }
+
+    /// The models a cached prompt may come from, in the order a load would
+    /// try them: the model ID (or local path) that would load, or every
+    /// preset of a `--fallback` chain.
+    fn cache_models(&self) -> Vec<String> {
+        if let Some(path) = &self.model_path {
+            return vec![path.display().to_string()];
+        }
+        if let Some(fallback) = &self.fallback {
+            return enhancer_chain::resolve(self.model, fallback)
+                .into_iter()
+                .map(|preset| preset.model_id().to_string())
+                .collect();
+        }
+        let preset = self.model.unwrap_or_default();
+        let preset = memory::would_load(preset, &self.load).map_or(preset, |(preset, _)| preset);
+        vec![preset.model_id().to_string()]
+    }
+
+    /// Why enhancement can't overlap with loading the diffusion model, or
+    /// `None` if it can.
+    ///
+    /// Overlapping holds both models at once, so it's only done when their
+    /// combined estimate fits in the memory available now (or memory can't
+    /// be probed, as in [`memory::choose`]).
+    fn overlap_blocker(&self, diffusion: &DiffusionConfig) -> Option<String> {
+        if self.sequential {
+            return Some("--sequential".to_string());
+        }
+        if !diffusion.footprint_known() {
+            return Some(format!("the footprint of {diffusion} isn't known"));
+        }
+        if self.candidates.unwrap_or(1) > 1 && self.pick.is_none() {
+            return Some("the candidate prompt is picked interactively".to_string());
+        }
+        let enhancer_bytes = match self.backend {
+            EnhancerBackend::Template | EnhancerBackend::Remote => 0,
+            _ if self.model_path.is_some() => {
+                return Some("the local model's footprint isn't known".to_string());
+            }
+            _ => self.planned_preset().required_bytes(&self.load),
+        };
+        let required = enhancer_bytes + DIFFUSION_APPROX_BYTES;
+        match memory::available_bytes() {
+            Some(available) if required > available => Some(format!(
+                "both models need ~{} but only {} is available",
+                memory::fmt_gb(required),
+                memory::fmt_gb(available)
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// Load the backend chosen with `--enhancer`, returning it with its name
+/// for the image metadata: "template", the model ID, the local path, or
+/// the remote model and URL.  With `--fallback` the model ID is that of
+/// the preset that loaded.  Prompts are written for `budget`.
+pub async fn load_enhancer(
+    options: &EnhancerOptions,
+    budget: PromptBudget,
+    cancel: &CancellationToken,
+    out: OutputConfig,
+) -> Result<(Box<dyn PromptEnhance>, String)> {
+    if options.backend == EnhancerBackend::Template {
+        if options.model.is_some() || options.model_path.is_some() {
+            anyhow::bail!("--model / --model-path can't be used with --enhancer template");
+        }
+        status!(out, "Using the template prompt enhancer (no model)");
+        let enhancer = TemplateEnhancer::new().with_prompt_budget(budget);
+        return Ok((Box::new(enhancer), "template".to_string()));
+    }
+    if options.backend == EnhancerBackend::Remote {
+        let config = options.remote.resolve()?;
+        let label = config.label();
+        status!(out, "Using the remote prompt enhancer: {label}");
+        let sampler = options
+            .sampler
+            .apply(promp_enhancer::default_sampler(budget));
+        return Ok((remote_enhancer::connect(config, sampler, budget)?, label));
+    }
+
+    let enhancer_start = Instant::now();
+    let load = &options.load;
+    let loading = async {
+        match (&options.model_path, &options.fallback) {
+            (Some(path), _) => {
+                status!(out, "Loading prompt enhancer model: {}", path.display());
+                let enhancer = PromptEnhancer::from_local_path(path, load).await?;
+                Ok::<_, anyhow::Error>((enhancer, path.display().to_string()))
+            }
+            (None, Some(fallback)) => {
+                let chain = enhancer_chain::resolve(options.model, fallback);
+                let chosen = enhancer_chain::load_chain(&chain, &CliBuilder { load, out }).await?;
+                if !chosen.failures.is_empty() {
+                    status!(
+                        out,
+                        "Fell back to {} after {} failed preset(s)",
+                        chosen.preset,
+                        chosen.failures.len()
+                    );
+                }
+                Ok((chosen.loaded, chosen.preset.model_id().to_string()))
+            }
+            (None, None) => {
+                let preset = options.model.unwrap_or_default();
+                let (preset, load) = memory::choose(preset, load)?;
+                status!(out, "Loading prompt enhancer model: {preset}");
+                status!(out, "  Memory estimate: {}", preset.memory_estimate(&load));
+                let settings = load.apply(preset.default_settings());
+                if let Some(weights) = load.uqff.describe(preset.model_id(), settings) {
+                    status!(out, "  {weights}");
+                }
+                let enhancer = PromptEnhancer::from_preset_with_options(preset, &load).await?;
+                Ok((enhancer, preset.model_id().to_string()))
+            }
+        }
+    };
+    let (enhancer, label) = interrupt::unless_cancelled(Some(cancel), loading).await?;
+    let mut enhancer = enhancer.with_prompt_budget(budget).with_sampler(
+        options
+            .sampler
+            .apply(promp_enhancer::default_sampler(budget)),
+    );
+    if let Some(seed) = options.sampler_seed {
+        enhancer = enhancer.with_seed(seed);
+    }
+    status!(
+        out,
+        "Prompt enhancer loaded in {}",
+        fmt_duration(enhancer_start.elapsed())
+    );
+    Ok((Box::new(enhancer), label))
+}
+
+/// Turn `seed` into the prompt sent to the diffusion model: one enhanced
+/// prompt, or `--candidates` of them with one picked via [`choose_candidate`].
+pub async fn enhance_seed(
+    enhancer: &dyn PromptEnhance,
+    seed: &str,
+    options: &EnhancerOptions,
+    cancel: &CancellationToken,
+    out: OutputConfig,
+) -> Result<String> {
+    status!(out, "\nSeed prompt:\n  \"{seed}\"\n");
+
+    let n = options.candidates.unwrap_or(1);
+    if promp_enhancer::streams_live(n, out) {
+        let prompt =
+            interrupt::unless_cancelled(Some(cancel), promp_enhancer::enhance_live(enhancer, seed))
+                .await?;
+        println!();
+        return Ok(prompt);
+    }
+    let enhance_start = Instant::now();
+    let candidates = interrupt::unless_cancelled(Some(cancel), enhancer.enhance_n(seed, n)).await?;
+    let enhance_elapsed = enhance_start.elapsed();
+
+    if let [enhanced] = candidates.as_slice() {
+        status!(
+            out,
+            "Enhanced prompt ({}):\n  \"{enhanced}\"\n",
+            fmt_duration(enhance_elapsed)
+        );
+        Ok(enhanced.clone())
+    } else {
+        status!(
+            out,
+            "Enhanced prompts ({} distinct of {n}, {}):",
+            candidates.len(),
+            fmt_duration(enhance_elapsed)
+        );
+        for (i, candidate) in candidates.iter().enumerate() {
+            status!(out, "  [{}] \"{candidate}\"", i + 1);
+        }
+        status!(out);
+        choose_candidate(candidates, options.pick, out.quiet())
+    }
+}
+
+/// Choose one of several enhanced prompts: `pick` if given, otherwise ask on
+/// the terminal.  With `list` the candidates are printed first, since
+/// `--quiet` didn't show them.
+fn choose_candidate(candidates: Vec<String>, pick: Option<usize>, list: bool) -> Result<String> {
+    if let Some(pick) = pick {
+        let count = candidates.len();
+        return candidates.into_iter().nth(pick - 1).with_context(|| {
+            format!("--pick {pick}: only {count} distinct candidates were generated")
+        });
+    }
+    if !std::io::stdin().is_terminal() {
+        anyhow::bail!(
+            "{} candidate prompts were generated; pass --pick <N> to choose one",
+            candidates.len()
+        );
+    }
+
+    if list {
+        for (i, candidate) in candidates.iter().enumerate() {
+            eprintln!("  [{}] \"{candidate}\"", i + 1);
+        }
+    }
+    let stdin = std::io::stdin();
+    loop {
+        eprint!("Pick a prompt [1-{}]: ", candidates.len());
+        std::io::stderr().flush()?;
+        let mut line = String::new();
+        if stdin.read_line(&mut line)? == 0 {
+            anyhow::bail!("No prompt picked");
+        }
+        match line.trim().parse::<usize>() {
+            Ok(i) if (1..=candidates.len()).contains(&i) => {
+                return Ok(candidates[i - 1].clone());
+            }
+            _ => eprintln!("Enter a number between 1 and {}.", candidates.len()),
+        }
+    }
+}
+
+// ── Prompt cache ─────────────────────────────────────────────────────────────
+
+/// The [`PromptCache`] as it applies to one `image --seed-prompt` run.
+///
+/// Only a model enhancer given `--sampler-seed` and producing one
+/// candidate is cached: without a fixed seed every run samples a different
+/// prompt, and with `--candidates` the pick is the user's.
+struct CacheLookup {
+    cache: PromptCache,
+    /// The seed as the enhancer sees it, style included.
+    styled: String,
+    sampler_seed: u64,
+    /// The enhancer's sampler settings, for the key's settings hash.
+    sampler: sampler::SamplerConfig,
+    /// The enhancer's system prompt, which states the word limit.
+    system_prompt: String,
+    mode: CacheMode,
+}
+
+impl CacheLookup {
+    /// `None` when the cache is off or doesn't apply to this run.
+    /// `seed_given` says whether `--sampler-seed` was passed rather than
+    /// picked at random.
+    fn new(
+        styled: String,
+        options: &EnhancerOptions,
+        budget: PromptBudget,
+        seed_given: bool,
+        out: OutputConfig,
+    ) -> Option<Self> {
+        if options.cache == CacheMode::Off || options.backend != EnhancerBackend::Model {
+            return None;
+        }
+        let Some(sampler_seed) = options.sampler_seed.filter(|_| seed_given) else {
+            detail!(out, "Prompt cache not used: it needs --sampler-seed");
+            return None;
+        };
+        if options.candidates.unwrap_or(1) > 1 {
+            detail!(out, "Prompt cache not used with --candidates");
+            return None;
+        }
+        Some(Self {
+            cache: PromptCache::open()?,
+            styled,
+            sampler_seed,
+            sampler: options
+                .sampler
+                .apply(promp_enhancer::default_sampler(budget)),
+            system_prompt: promp_enhancer::system_prompt(budget),
+            mode: options.cache,
+        })
+    }
+
+    fn key(&self, model: &str) -> CacheKey {
+        CacheKey::new(
+            &self.styled,
+            model,
+            self.sampler_seed,
+            &self.system_prompt,
+            &self.sampler,
+        )
+    }
+
+    /// The prompt cached for the first of `models` that has one, with its
+    /// origin for the metadata; always `None` with `--refresh`.
+    fn hit(
+        &self,
+        seed: &str,
+        style: Option<&styles::StylePreset>,
+        models: &[String],
+        out: OutputConfig,
+    ) -> Option<(String, PromptOrigin)> {
+        if self.mode == CacheMode::Refresh {
+            return None;
+        }
+        let (model, entry) = models
+            .iter()
+            .find_map(|model| self.cache.get(&self.key(model)).map(|entry| (model, entry)))?;
+        if let Some(style) = style {
+            status!(out, "\nStyle: {style}");
+        }
+        status!(out, "\nSeed prompt:\n  \"{}\"\n", self.styled);
+        status!(
+            out,
+            "Enhanced prompt (cached {}, enhancer not loaded):\n  \"{}\"\n",
+            fmt_utc(entry.created),
+            entry.prompt
+        );
+        let origin = PromptOrigin {
+            seed_prompt: Some(seed.to_string()),
+            style: style.map(|style| style.name.clone()),
+            enhancer: Some(model.clone()),
+            enhanced_prompt: Some(entry.prompt.clone()),
+            sampler_seed: Some(self.sampler_seed),
+        };
+        Some((entry.prompt.clone(), origin))
+    }
+
+    /// Remember the prompt `origin` records.  A failed write only warns:
+    /// the images don't depend on it.
+    fn store(mut self, origin: &PromptOrigin, out: OutputConfig) {
+        let (Some(model), Some(prompt)) = (&origin.enhancer, &origin.enhanced_prompt) else {
+            return;
+        };
+        self.cache.insert(self.key(model), prompt.clone());
+        match self.cache.save() {
+            Ok(()) => detail!(
+                out,
+                "Enhanced prompt cached in {}",
+                self.cache.path().display()
+            ),
+            Err(err) => tracing::warn!("the enhanced prompt was not cached: {err:#}"),
+        }
+    }
+}
+
+/// Checks done before anything loads, by both [`run`] and
+/// [`ImagePlan::new`]; returns the generation parameters.
+pub(crate) fn check_inputs(
+    enhancer_options: &EnhancerOptions,
+    options: &ImageOptions,
+) -> Result<DiffusionGenerationParams> {
+    let params = options.generation_params()?;
+    options.check_overwrite()?;
+    enhancer_options.validate()?;
+    Ok(params)
+}
+
+// ── Dry run ──────────────────────────────────────────────────────────────────
+
+/// Where the prompt sent to the diffusion model comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PromptSource {
+    /// `--prompt`, used as given.
+    Direct,
+    /// `--seed-prompt`, styled and enhanced.
+    Seed,
+    /// Neither: the built-in default prompt.
+    Default,
+}
+
+/// What an `image` run would do, worked out without loading a model:
+/// printed by `--dry-run`.
+///
+/// A model enhancer isn't run, so with one `prompt` is the styled seed it
+/// would be given; the template enhancer is run, since it needs no model.
+#[derive(Debug, Serialize)]
+pub struct ImagePlan {
+    pub source: PromptSource,
+    /// The seed with any `--style` applied.
+    pub seed_prompt: Option<String>,
+    /// Name of the `--style` preset, if any.
+    pub style: Option<String>,
+    /// The enhancer the seed goes through: "template", or the model that
+    /// would be loaded.
+    pub enhancer: Option<String>,
+    /// `--sampler-seed` given for a model enhancer (`None` = random).
+    pub sampler_seed: Option<u64>,
+    /// `false` when a model enhancer would still rewrite `prompt`.
+    pub prompt_is_final: bool,
+    /// Every template candidate, when `--candidates` asked for several.
+    pub candidates: Vec<String>,
+    /// The prompt sent to the diffusion model, after truncation to
+    /// `budget`.
+    pub prompt: String,
+    /// Whether truncation shortened it.
+    pub truncated: bool,
+    /// The text encoder's window.
+    pub budget: PromptBudget,
+    /// Diffusion model ID, loader and dtype.
+    pub model: String,
+    pub loader: String,
+    pub dtype: String,
+    pub width: usize,
+    pub height: usize,
+    pub num_images: usize,
+    /// Files that would be written (`--out`), numbered for several images,
+    /// then the `--grid` sheet.
+    pub out: Vec<PathBuf>,
+    /// Settings the model would ignore, and other notes.
+    pub warnings: Vec<String>,
+}
+
+impl ImagePlan {
+    /// Validate the options and resolve the prompt as [`run`] would,
+    /// without loading a model or touching the network.
+    pub fn new(
+        prompt: Option<String>,
+        seed_prompt: Option<&str>,
+        enhancer_options: &EnhancerOptions,
+        options: &ImageOptions,
+        diffusion: &DiffusionConfig,
+    ) -> Result<Self> {
+        let params = check_inputs(enhancer_options, options)?;
+        let style = enhancer_options
+            .style
+            .as_deref()
+            .map(styles::find)
+            .transpose()?;
+        let mut warnings = Vec::new();
+        let budget = diffusion.prompt_budget();
+
+        let mut plan_seed = None;
+        let mut enhancer = None;
+        let mut candidates = Vec::new();
+        let (source, resolved, prompt_is_final) = match (prompt, seed_prompt) {
+            (Some(prompt), _) => (PromptSource::Direct, prompt, true),
+            (None, None) => (PromptSource::Default, DEFAULT_PROMPT.to_string(), true),
+            (None, Some(seed)) => {
+                let seed = match &style {
+                    Some(style) => style.compose(seed),
+                    None => seed.to_string(),
+                };
+                plan_seed = Some(seed.clone());
+                if enhancer_options.backend == EnhancerBackend::Template {
+                    if enhancer_options.model.is_some() || enhancer_options.model_path.is_some() {
+                        anyhow::bail!(
+                            "--model / --model-path can't be used with --enhancer template"
+                        );
+                    }
+                    enhancer = Some("template".to_string());
+                    let n = enhancer_options.candidates.unwrap_or(1);
+                    candidates = TemplateEnhancer::new()
+                        .with_prompt_budget(budget)
+                        .candidates(&seed, n);
+                    let index = match enhancer_options.pick {
+                        Some(pick) if pick > candidates.len() => anyhow::bail!(
+                            "--pick {pick}: only {} distinct candidates were generated",
+                            candidates.len()
+                        ),
+                        Some(pick) => pick - 1,
+                        None => {
+                            if candidates.len() > 1 {
+                                warnings.push(
+                                    "the prompt would be picked interactively; showing candidate 1"
+                                        .to_string(),
+                                );
+                            }
+                            0
+                        }
+                    };
+                    let chosen = candidates[index].clone();
+                    if candidates.len() == 1 {
+                        candidates.clear();
+                    }
+                    (PromptSource::Seed, chosen, true)
+                } else if enhancer_options.backend == EnhancerBackend::Remote {
+                    enhancer = Some(enhancer_options.remote.resolve()?.label());
+                    (PromptSource::Seed, seed, false)
+                } else {
+                    enhancer = Some(match &enhancer_options.model_path {
+                        Some(path) => path.display().to_string(),
+                        None if enhancer_options.fallback.is_some() => {
+                            enhancer_options.planned_preset().to_string()
+                        }
+                        None => {
+                            let preset = enhancer_options.model.unwrap_or_default();
+                            match memory::would_load(preset, &enhancer_options.load) {
+                                Some((preset, _)) => preset.to_string(),
+                                None => {
+                                    memory::choose(preset, &enhancer_options.load)?;
+                                    preset.to_string()
+                                }
+                            }
+                        }
+                    });
+                    (PromptSource::Seed, seed, false)
+                }
+            }
+        };
+
+        let request = options.request_with_budget(&resolved, budget)?;
+        warnings.extend(ignored_settings(diffusion.loader, &request));
+        let out_paths = options
+            .out
+            .as_deref()
+            .map(|path| {
+                (0..options.num_images)
+                    .map(|index| numbered_path(path, options.num_images, index))
+                    .chain(options.grid.map(|_| image_output::grid_path(path)))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(Self {
+            source,
+            seed_prompt: plan_seed,
+            style: style.map(|style| style.name),
+            sampler_seed: enhancer_options.sampler_seed.filter(|_| !prompt_is_final),
+            enhancer,
+            prompt_is_final,
+            candidates,
+            truncated: request.prompt != resolved,
+            prompt: request.prompt,
+            budget,
+            model: diffusion.model_id.clone(),
+            loader: format!("{:?}", diffusion.loader),
+            dtype: format!("{:?}", diffusion.dtype),
+            width: params.width,
+            height: params.height,
+            num_images: options.num_images,
+            out: out_paths,
+            warnings,
+        })
+    }
+}
+
+impl fmt::Display for ImagePlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let source = match self.source {
+            PromptSource::Direct => "--prompt",
+            PromptSource::Seed => "--seed-prompt",
+            PromptSource::Default => "built-in default",
+        };
+        writeln!(f, "Prompt source  : {source}")?;
+        if let Some(seed) = &self.seed_prompt {
+            writeln!(f, "Seed           : {seed}")?;
+        }
+        if let Some(style) = &self.style {
+            writeln!(f, "Style          : {style}")?;
+        }
+        if let Some(enhancer) = &self.enhancer {
+            writeln!(f, "Enhancer       : {enhancer}")?;
+        }
+        if let Some(seed) = self.sampler_seed {
+            writeln!(f, "Sampler seed   : {seed}")?;
+        }
+        for (i, candidate) in self.candidates.iter().enumerate() {
+            writeln!(f, "  [{}] \"{candidate}\"", i + 1)?;
+        }
+        writeln!(
+            f,
+            "Model          : {} ({}, {})",
+            self.model, self.loader, self.dtype
+        )?;
+        writeln!(
+            f,
+            "Images         : {} at {}x{}",
+            self.num_images, self.width, self.height
+        )?;
+        for path in &self.out {
+            writeln!(f, "  {}", path.display())?;
+        }
+        writeln!(f, "Prompt budget  : {}", self.budget)?;
+        for warning in &self.warnings {
+            writeln!(f, "Note           : {warning}")?;
+        }
+        let label = match (self.prompt_is_final, self.truncated) {
+            (false, _) => "Prompt (before enhancement, which a dry run skips)",
+            (true, true) => "Prompt (truncated to the prompt budget)",
+            (true, false) => "Prompt",
+        };
+        write!(f, "\n{label}:\n  \"{}\"", self.prompt)
+    }
+}
+
+/// `image --dry-run`: print the [`ImagePlan`] and stop.  With several
+/// seeds (`--keep-enhancer`), one plan per seed, and with `--sizes` one per
+/// size; a JSON array with `--json`.
+pub fn dry_run(
+    prompt: Option<String>,
+    seed_prompts: Vec<String>,
+    enhancer_options: EnhancerOptions,
+    options: ImageOptions,
+    diffusion: DiffusionConfig,
+    out: OutputConfig,
+) -> Result<()> {
+    if !options.sizes.is_empty() {
+        options.generation_params()?;
+        let plans = options
+            .sizes
+            .iter()
+            .map(|&size| {
+                ImagePlan::new(
+                    prompt.clone(),
+                    seed_prompts.first().map(String::as_str),
+                    &enhancer_options,
+                    &options.for_size(size),
+                    &diffusion,
+                )
+            })
+            .collect::<Result<Vec<_>>>()?;
+        return print_plans(&plans, out);
+    }
+    if seed_prompts.len() <= 1 {
+        let plan = ImagePlan::new(
+            prompt,
+            seed_prompts.first().map(String::as_str),
+            &enhancer_options,
+            &options,
+            &diffusion,
+        )?;
+        if out.json {
+            return ui::print_json(&plan);
+        }
+        println!("{plan}");
+        return Ok(());
+    }
+
+    let plans = seed_prompts
+        .iter()
+        .enumerate()
+        .map(|(index, seed)| {
+            let options = options.for_seed(index, seed_prompts.len());
+            ImagePlan::new(None, Some(seed), &enhancer_options, &options, &diffusion)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    print_plans(&plans, out)
+}
+
+/// Print `plans` one after another, or as a JSON array.
+fn print_plans(plans: &[ImagePlan], out: OutputConfig) -> Result<()> {
+    if out.json {
+        return ui::print_json(&plans);
+    }
+    for (i, plan) in plans.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!("{plan}");
+    }
+    Ok(())
+}
 
 /// Run image generation, optionally enhancing a seed prompt first.
 ///
 /// - If `prompt` is provided it is used directly (no enhancement).
-/// - If `seed` is provided the prompt enhancer expands it before generation.
+/// - If `seed_prompt` is provided the prompt enhancer expands it before
+///   generation.
 /// - If neither is provided a built-in default prompt is used.
+///
+/// When `cancel` fires, loading or generation stops; images already saved
+/// are reported before returning [`Interrupted`].
 pub async fn run(
     prompt: Option<String>,
-    seed: Option<String>,
-    enhancer_model: Option<EnhancerModel>,
+    seed_prompt: Option<String>,
+    enhancer_options: EnhancerOptions,
+    options: ImageOptions,
+    diffusion: DiffusionConfig,
+    cancel: CancellationToken,
+    out: OutputConfig,
 ) -> Result<()> {
-    // ── Resolve the final prompt ────────────────────────────────────────
-    let prompt = if let Some(p) = prompt {
-        // Direct prompt — use as-is.
-        p
-    } else if let Some(seed_text) = seed {
-        // Seed provided — enhance it first.
-        let preset = enhancer_model.unwrap_or_default();
-        println!("Loading prompt enhancer model: {preset}");
-        println!("  Memory estimate: {}", preset.approx_memory());
-        let enhancer_start = Instant::now();
-        let enhancer = PromptEnhancer::from_preset(preset).await?;
-        let enhancer_load = enhancer_start.elapsed();
-        println!("Prompt enhancer loaded in {}", fmt_duration(enhancer_load));
-
-        println!("\nSeed prompt:\n  \"{seed_text}\"\n");
-
-        let enhance_start = Instant::now();
-        let enhanced = enhancer.enhance(&seed_text).await?;
-        let enhance_elapsed = enhance_start.elapsed();
+    let mut enhancer_options = enhancer_options;
+    check_inputs(&enhancer_options, &options)?;
+    let style = enhancer_options
+        .style
+        .as_deref()
+        .map(styles::find)
+        .transpose()?;
+    let seed_given = enhancer_options.sampler_seed.is_some();
+    let budget = diffusion.prompt_budget();
+    // Fix the seed up front so it can be printed before enhancement and
+    // recorded in the JSON.
+    let sampler_seed = (prompt.is_none()
+        && seed_prompt.is_some()
+        && enhancer_options.backend == EnhancerBackend::Model)
+        .then(|| {
+            *enhancer_options
+                .sampler_seed
+                .get_or_insert_with(sampler::random_seed)
+        });
+    if let Some(seed) = sampler_seed {
+        status!(out, "Sampler seed: {seed}");
+    }
+    let prompt_start = Instant::now();
 
-        println!(
-            "Enhanced prompt ({}):\n  \"{enhanced}\"\n",
-            fmt_duration(enhance_elapsed)
+    // A cached prompt means the enhancer never loads, so this comes first.
+    let cache = match (&prompt, &seed_prompt) {
+        (None, Some(seed)) => {
+            let styled = match &style {
+                Some(style) => style.compose(seed),
+                None => seed.clone(),
+            };
+            CacheLookup::new(styled, &enhancer_options, budget, seed_given, out)
+        }
+        _ => None,
+    };
+    let cached = match (&cache, &seed_prompt) {
+        (Some(cache), Some(seed)) => {
+            cache.hit(seed, style.as_ref(), &enhancer_options.cache_models(), out)
+        }
+        _ => None,
+    };
+    let from_cache = cached.is_some();
+
+    // ── Resolve the prompt and load the diffusion model ─────────────────
+    //
+    // The diffusion weights don't depend on the prompt, so with a seed the
+    // model loads on its own task while the enhancer runs.
+    let overlap = match &seed_prompt {
+        Some(_) if prompt.is_none() && !from_cache => {
+            match enhancer_options.overlap_blocker(&diffusion) {
+                Some(reason) => {
+                    detail!(
+                        out,
+                        "Enhancing before loading the diffusion model: {reason}"
+                    );
+                    false
+                }
+                None => true,
+            }
+        }
+        _ => false,
+    };
+    let ((prompt, origin), prompt_elapsed, generator, load_elapsed, load_memory) = if overlap {
+        // Cancelled when this scope exits, so an enhancement error (or
+        // Ctrl-C) stops the load instead of leaving it running.
+        let load_cancel = cancel.child_token();
+        let loading = tokio::spawn(load_diffusion(diffusion.clone(), load_cancel.clone(), out));
+        let _stop_loading = load_cancel.drop_guard();
+        let resolving = async {
+            let prompt = resolve_prompt(
+                prompt,
+                seed_prompt.as_deref(),
+                &enhancer_options,
+                style.as_ref(),
+                budget,
+                &cancel,
+                out,
+            )
+            .await?;
+            Ok::<_, anyhow::Error>((prompt, prompt_start.elapsed()))
+        };
+        // `try_join!` returns on the first error and drops the other side.
+        let ((prompt, prompt_elapsed), (generator, load_elapsed, load_memory)) =
+            tokio::try_join!(resolving, async {
+                loading
+                    .await
+                    .context("The diffusion model load task failed")?
+            })?;
+        let wall = prompt_start.elapsed();
+        status!(
+            out,
+            "Prompt and model ready in {} ({} saved by loading the model during enhancement)",
+            fmt_duration(wall),
+            fmt_duration((prompt_elapsed + load_elapsed).saturating_sub(wall))
         );
-        enhanced
+        (prompt, prompt_elapsed, generator, load_elapsed, load_memory)
     } else {
-        // Fallback default.
-        "A majestic castle on a cliff overlooking the sea at sunset, \
-         highly detailed, digital painting, trending on artstation, in the style of Raden Saleh"
-            .to_string()
+        let prompt = match cached {
+            Some(cached) => cached,
+            None => {
+                resolve_prompt(
+                    prompt,
+                    seed_prompt.as_deref(),
+                    &enhancer_options,
+                    style.as_ref(),
+                    budget,
+                    &cancel,
+                    out,
+                )
+                .await?
+            }
+        };
+        let prompt_elapsed = prompt_start.elapsed();
+        let (generator, load_elapsed, load_memory) =
+            load_diffusion(diffusion.clone(), cancel.clone(), out).await?;
+        (prompt, prompt_elapsed, generator, load_elapsed, load_memory)
     };
+    let mut generator = generator.with_cancellation(cancel);
+    if let Some(ledger) = options.artifact_ledger(1) {
+        generator = generator.with_artifacts(ledger);
+    }
+    if let Some(cache) = cache.filter(|_| !from_cache) {
+        cache.store(&origin, out);
+    }
 
-    // ── Load diffusion model ────────────────────────────────────────────
-    println!("Loading diffusion model ({DEFAULT_MODEL})...");
-    let load_start = Instant::now();
-    let model = DiffusionModelBuilder::new(DEFAULT_MODEL, DEFAULT_LOADER)
-        .with_dtype(ModelDType::BF16)
-        .with_logging()
-        .build()
-        .await?;
-    let load_elapsed = load_start.elapsed();
-    println!("Model loaded in {}", fmt_duration(load_elapsed));
+    if !options.sizes.is_empty() {
+        let (result, generation_elapsed) =
+            render_sizes(&generator, &options, &prompt, origin, out).await;
+        if out.json {
+            ui::print_json(&ImageReport {
+                result: &result,
+                seed_prompt,
+                style: style.map(|style| style.name),
+                sampler_seed,
+                load_secs: load_elapsed.as_secs_f64(),
+                overlapped: overlap,
+                prompt_cached: from_cache,
+                memory: load_memory,
+            })?;
+        } else {
+            detail!(
+                out,
+                "Timings: prompt {}, model load {}, generation {}",
+                fmt_duration(prompt_elapsed),
+                fmt_duration(load_elapsed),
+                fmt_duration(generation_elapsed)
+            );
+            print_sizes(&result, generation_elapsed, out);
+            if let Some(line) = generator.artifact_summary() {
+                status!(out, "{line}");
+            }
+        }
+        return finish_sizes(&result);
+    }
 
-    // ── Truncate to fit CLIP's 77-token window ──────────────────────────
-    let prompt = truncate_to_words(&prompt, MAX_PROMPT_WORDS);
+    let (result, generation_elapsed) = render(&generator, &options, &prompt, origin, out).await?;
 
-    // ── Generate image ──────────────────────────────────────────────────
-    println!("\nGenerating image for prompt:\n  \"{prompt}\"");
+    if out.json {
+        ui::print_json(&ImageReport {
+            result: &result,
+            seed_prompt,
+            style: style.map(|style| style.name),
+            sampler_seed,
+            load_secs: load_elapsed.as_secs_f64(),
+            overlapped: overlap,
+            prompt_cached: from_cache,
+            memory: load_memory,
+        })?;
+        return finish(&result);
+    }
 
-    let start = Instant::now();
-    let response = model
-        .generate_image(
-            &prompt,
-            ImageGenerationResponseFormat::Url,
-            DiffusionGenerationParams::default(),
-            None,
-        )
+    detail!(
+        out,
+        "Timings: prompt {}, model load {}, generation {}",
+        fmt_duration(prompt_elapsed),
+        fmt_duration(load_elapsed),
+        fmt_duration(generation_elapsed)
+    );
+    print_images(&result, generation_elapsed, out);
+    if let Some(line) = generator.artifact_summary() {
+        status!(out, "{line}");
+    }
+    finish(&result)
+}
+
+/// `image --keep-enhancer`: load the prompt enhancer and the diffusion
+/// model once, then enhance and render each of `seeds` in turn.
+///
+/// Both models stay loaded for the whole run, so this needs the memory for
+/// both; [`run`] releases the enhancer before the diffusion model loads.
+/// With `--out`, the images of the `i`-th seed are numbered `_i` (see
+/// [`ImageOptions::for_seed`]).
+pub async fn run_seeds(
+    seeds: Vec<String>,
+    enhancer_options: EnhancerOptions,
+    options: ImageOptions,
+    diffusion: DiffusionConfig,
+    cancel: CancellationToken,
+    out: OutputConfig,
+) -> Result<()> {
+    if seeds.is_empty() {
+        anyhow::bail!("--keep-enhancer needs at least one --seed-prompt");
+    }
+    let mut enhancer_options = enhancer_options;
+    let per_seed: Vec<ImageOptions> = (0..seeds.len())
+        .map(|index| options.for_seed(index, seeds.len()))
+        .collect();
+    for options in &per_seed {
+        check_inputs(&enhancer_options, options)?;
+    }
+    let style = enhancer_options
+        .style
+        .as_deref()
+        .map(styles::find)
+        .transpose()?;
+    let sampler_seed = (enhancer_options.backend == EnhancerBackend::Model).then(|| {
+        *enhancer_options
+            .sampler_seed
+            .get_or_insert_with(sampler::random_seed)
+    });
+    if let Some(seed) = sampler_seed {
+        status!(out, "Sampler seed: {seed}");
+    }
+
+    let (enhancer, label) =
+        load_enhancer(&enhancer_options, diffusion.prompt_budget(), &cancel, out).await?;
+    let (generator, load_elapsed, load_memory) =
+        load_diffusion(diffusion.clone(), cancel.clone(), out).await?;
+    let mut generator = generator.with_cancellation(cancel.clone());
+    if let Some(ledger) = options.artifact_ledger(seeds.len()) {
+        generator = generator.with_artifacts(ledger);
+    }
+
+    let mut reports = Vec::with_capacity(seeds.len());
+    for (index, (seed, options)) in seeds.iter().zip(&per_seed).enumerate() {
+        if seeds.len() > 1 {
+            status!(out, "\n── Seed {}/{} ──", index + 1, seeds.len());
+        }
+        let styled = match &style {
+            Some(style) => {
+                status!(out, "\nStyle: {style}");
+                style.compose(seed)
+            }
+            None => seed.clone(),
+        };
+        let enhanced =
+            enhance_seed(enhancer.as_ref(), &styled, &enhancer_options, &cancel, out).await?;
+        let origin = PromptOrigin {
+            seed_prompt: Some(seed.clone()),
+            style: style.as_ref().map(|style| style.name.clone()),
+            enhancer: Some(label.clone()),
+            enhanced_prompt: Some(enhanced.clone()),
+            sampler_seed,
+        };
+        let (result, generation_elapsed) =
+            render(&generator, options, &enhanced, origin, out).await?;
+        if !out.json {
+            print_images(&result, generation_elapsed, out);
+        }
+        let stopped = result.interrupted || result.low_space;
+        reports.push(result);
+        if stopped {
+            break;
+        }
+    }
+    if !out.json
+        && let Some(line) = generator.artifact_summary()
+    {
+        status!(out, "\n{line}");
+    }
+
+    if out.json {
+        let json: Vec<ImageReport> = reports
+            .iter()
+            .zip(&seeds)
+            .map(|(result, seed)| ImageReport {
+                result,
+                seed_prompt: Some(seed.clone()),
+                style: style.as_ref().map(|style| style.name.clone()),
+                sampler_seed,
+                load_secs: load_elapsed.as_secs_f64(),
+                overlapped: false,
+                prompt_cached: false,
+                memory: load_memory,
+            })
+            .collect();
+        ui::print_json(&json)?;
+    }
+    match reports.last() {
+        Some(result) => finish(result),
+        None => Ok(()),
+    }
+}
+
+/// Machine-readable summary of an `image` run, printed in `--json` mode
+/// (an array of them, one per seed, with `--keep-enhancer`).  `R` is the
+/// [`ImageResult`], or the [`SizesResult`] with `--sizes`.
+#[derive(Serialize)]
+struct ImageReport<'a, R = ImageResult> {
+    #[serde(flatten)]
+    result: &'a R,
+    /// The seed prompt, when the prompt was produced by the enhancer.
+    seed_prompt: Option<String>,
+    /// Name of the `--style` preset applied to the seed, if any.
+    style: Option<String>,
+    /// Sampler seed of the enhancement, for `--sampler-seed`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sampler_seed: Option<u64>,
+    /// Seconds spent loading the diffusion model.
+    load_secs: f64,
+    /// Whether the model loaded while the seed was being enhanced.
+    overlapped: bool,
+    /// Whether the enhanced prompt came from the prompt cache, so the
+    /// enhancer wasn't loaded.
+    prompt_cached: bool,
+    /// Memory in use once the diffusion model was loaded.
+    memory: LoadMemory,
+}
+
+/// Generate the images `options` asks for from `prompt` (truncated to the
+/// generator's [`PromptBudget`]), reporting progress.  Returns them with the
+/// time generation took; fails with [`Interrupted`] when cancelled before
+/// any image was saved.
+pub(crate) async fn render(
+    generator: &ImageGenerator,
+    options: &ImageOptions,
+    prompt: &str,
+    origin: PromptOrigin,
+    out: OutputConfig,
+) -> Result<(ImageResult, Duration)> {
+    let sampler_seed = origin.sampler_seed;
+    let request = options
+        .request_with_budget(prompt, generator.prompt_budget())?
+        .with_origin(origin);
+    for warning in generator.ignored_settings(&request) {
+        tracing::warn!("{warning}");
+    }
+
+    status!(
+        out,
+        "\nGenerating {} image(s) at {}x{} for prompt:\n  \"{}\"",
+        request.num_images,
+        request.params.width,
+        request.params.height,
+        request.prompt
+    );
+
+    let total_start = Instant::now();
+    let sent = request.prompt.clone();
+    let mut result = generator
+        .generate_with_progress(request, |i, total| {
+            if total > 1 {
+                status!(out, "Generating image {}/{total}...", i + 1);
+            }
+        })
         .await?;
-    let elapsed = start.elapsed();
+    if result.prompt != sent {
+        status!(
+            out,
+            "Generated with the prompt shortened to:\n  \"{}\"",
+            result.prompt
+        );
+    }
+    if result.interrupted {
+        if result.images.is_empty() {
+            eprintln!("Image generation interrupted; no image was saved.");
+            return Err(Interrupted.into());
+        }
+        tracing::warn!(
+            "interrupted after {} of {} images",
+            result.images.len(),
+            options.num_images
+        );
+    }
+    if result.low_space && !result.images.is_empty() {
+        tracing::warn!(
+            "stopped after {} of {} images",
+            result.images.len(),
+            options.num_images
+        );
+    }
+    if let Some(grid) = options.grid
+        && result.images.len() > 1
+    {
+        let path = save_grid(&result, options, grid, sampler_seed)?;
+        generator.record_artifact(&path)?;
+        result.total_bytes = generator.artifact_total();
+        result.grid = Some(path.display().to_string());
+    }
+    Ok((result, total_start.elapsed()))
+}
 
-    let path = response.data[0]
-        .url
-        .as_ref()
-        .expect("expected image URL in response");
+/// `image --sizes`: [`render`] `prompt` at each size with the loaded
+/// `generator`.  A size that fails is recorded and the next one still
+/// runs; cancelling stops at the size in progress.  Returns what was
+/// rendered and the time it all took.
+async fn render_sizes(
+    generator: &ImageGenerator,
+    options: &ImageOptions,
+    prompt: &str,
+    origin: PromptOrigin,
+    out: OutputConfig,
+) -> (SizesResult, Duration) {
+    let total_start = Instant::now();
+    let mut result = SizesResult {
+        prompt: prompt.to_string(),
+        sizes: Vec::with_capacity(options.sizes.len()),
+        interrupted: false,
+        low_space: false,
+    };
+    for (i, &size) in options.sizes.iter().enumerate() {
+        status!(out, "\nSize {}/{}: {size}", i + 1, options.sizes.len());
+        let start = Instant::now();
+        // A prompt shortened for the text encoder at an earlier size is
+        // sent as-is, instead of being rejected again.
+        let rendered = render(
+            generator,
+            &options.for_size(size),
+            &result.prompt,
+            origin.clone(),
+            out,
+        )
+        .await;
+        let mut outcome = SizeResult {
+            size,
+            images: Vec::new(),
+            grid: None,
+            generation_secs: start.elapsed().as_secs_f64(),
+            error: None,
+        };
+        match rendered {
+            Ok((rendered, _)) => {
+                result.prompt = rendered.prompt;
+                result.interrupted = rendered.interrupted;
+                result.low_space = rendered.low_space;
+                outcome.images = rendered.images;
+                outcome.grid = rendered.grid;
+            }
+            Err(err) if interrupt::is_interrupted(&err) => {
+                result.interrupted = true;
+            }
+            Err(err) => {
+                tracing::warn!("{size} failed: {err:#}");
+                outcome.error = Some(format!("{err:#}"));
+            }
+        }
+        result.sizes.push(outcome);
+        if result.interrupted || result.low_space {
+            break;
+        }
+    }
+    (result, total_start.elapsed())
+}
+
+/// Compose the images of `result` into the `--grid` sheet: next to `--out`,
+/// or next to the first image without it.  Captions show the prompt, each
+/// image's number and the enhancer's sampler seed; mistral.rs takes no
+/// seed for the diffusion noise, so there is none per image.
+fn save_grid(
+    result: &ImageResult,
+    options: &ImageOptions,
+    grid: GridOptions,
+    sampler_seed: Option<u64>,
+) -> Result<PathBuf> {
+    let paths: Vec<&Path> = result
+        .images
+        .iter()
+        .map(|image| Path::new(&image.path))
+        .collect();
+    let path = image_output::grid_path(options.out.as_deref().unwrap_or(paths[0]));
+    let caption = GridCaption {
+        prompt: result.prompt.clone(),
+        note: sampler_seed.map(|seed| format!("Sampler seed: {seed}")),
+        labels: (1..=paths.len()).map(|i| format!("#{i}")).collect(),
+    };
+    image_output::save_grid(&paths, &path, grid, Some(&caption))?;
+    Ok(path)
+}
 
+/// Print where the images went: just the paths with `--quiet`.
+pub(crate) fn print_images(result: &ImageResult, elapsed: Duration, out: OutputConfig) {
+    // Stopped at the --min-free floor before the first image.
+    if result.images.is_empty() {
+        return;
+    }
+    if out.quiet() {
+        for image in &result.images {
+            println!("{}", image.path);
+        }
+    } else if let [image] = result.images.as_slice() {
+        println!(
+            "Done! Image generation took {}.\nImage saved at: {}",
+            fmt_duration(Duration::from_secs_f64(image.generation_secs)),
+            image.path
+        );
+    } else {
+        println!(
+            "Done! Generated {} images in {}:",
+            result.images.len(),
+            fmt_duration(elapsed)
+        );
+        for (i, image) in result.images.iter().enumerate() {
+            println!(
+                "  [{}] {} ({})",
+                i + 1,
+                image.path,
+                fmt_duration(Duration::from_secs_f64(image.generation_secs))
+            );
+        }
+    }
+    if let Some(grid) = &result.grid {
+        if out.quiet() {
+            println!("{grid}");
+        } else {
+            println!("Grid saved at: {grid}");
+        }
+    }
+}
+
+/// Print each `--sizes` size with its images, or its error: just the
+/// paths with `--quiet`.
+fn print_sizes(result: &SizesResult, elapsed: Duration, out: OutputConfig) {
+    if out.quiet() {
+        for size in &result.sizes {
+            for image in &size.images {
+                println!("{}", image.path);
+            }
+            if let Some(grid) = &size.grid {
+                println!("{grid}");
+            }
+        }
+        return;
+    }
     println!(
-        "Done! Image generation took {}.\nImage saved at: {path}",
+        "Done! Rendered {} size(s) in {}:",
+        result.sizes.len() - result.failed(),
         fmt_duration(elapsed)
     );
+    for size in &result.sizes {
+        let took = fmt_duration(Duration::from_secs_f64(size.generation_secs));
+        if let Some(error) = &size.error {
+            println!(
+                "  {:<10} failed after {took}: {error}",
+                size.size.to_string()
+            );
+            continue;
+        }
+        if size.images.is_empty() {
+            println!("  {:<10} interrupted", size.size.to_string());
+            continue;
+        }
+        for image in &size.images {
+            println!(
+                "  {:<10} {} ({})",
+                size.size.to_string(),
+                image.path,
+                fmt_duration(Duration::from_secs_f64(image.generation_secs))
+            );
+        }
+        if let Some(grid) = &size.grid {
+            println!("  {:<10} grid: {grid}", size.size.to_string());
+        }
+    }
+}
 
+/// The prompt sent to the diffusion model: `prompt` as-is, `seed` styled
+/// and enhanced, or the built-in default when neither is given.  Returned
+/// with its [`PromptOrigin`] for the image metadata.
+pub(crate) async fn resolve_prompt(
+    prompt: Option<String>,
+    seed: Option<&str>,
+    options: &EnhancerOptions,
+    style: Option<&styles::StylePreset>,
+    budget: PromptBudget,
+    cancel: &CancellationToken,
+    out: OutputConfig,
+) -> Result<(String, PromptOrigin)> {
+    if let Some(prompt) = prompt {
+        return Ok((prompt, PromptOrigin::default()));
+    }
+    let Some(seed) = seed else {
+        return Ok((DEFAULT_PROMPT.to_string(), PromptOrigin::default()));
+    };
+
+    let (enhancer, label) = load_enhancer(options, budget, cancel, out).await?;
+    let styled = match style {
+        Some(style) => {
+            status!(out, "\nStyle: {style}");
+            style.compose(seed)
+        }
+        None => seed.to_string(),
+    };
+    let enhanced = enhance_seed(enhancer.as_ref(), &styled, options, cancel, out).await?;
+    if options.backend == EnhancerBackend::Model {
+        release_enhancer(enhancer, out);
+    }
+    let origin = PromptOrigin {
+        seed_prompt: Some(seed.to_string()),
+        style: style.map(|style| style.name.clone()),
+        enhancer: Some(label),
+        enhanced_prompt: Some(enhanced.clone()),
+        sampler_seed: options
+            .sampler_seed
+            .filter(|_| options.backend == EnhancerBackend::Model),
+    };
+    Ok((enhanced, origin))
+}
+
+/// Drop the enhancer so its model is freed before the diffusion model
+/// loads (or, when the two overlap, while it finishes), and report the
+/// memory in use before and after.
+fn release_enhancer(enhancer: Box<dyn PromptEnhance>, out: OutputConfig) {
+    let before = MemorySnapshot::capture();
+    drop(enhancer);
+    let after = MemorySnapshot::capture();
+    status!(out, "Releasing enhancer model: {before} → {after}");
+}
+
+/// Load the diffusion model `config` names, returning it with the time the
+/// load took and the memory in use afterwards.
+pub(crate) async fn load_diffusion(
+    config: DiffusionConfig,
+    cancel: CancellationToken,
+    out: OutputConfig,
+) -> Result<(ImageGenerator, Duration, LoadMemory)> {
+    status!(out, "Loading diffusion model: {config}...");
+    let before = MemorySnapshot::capture();
+    let load_start = Instant::now();
+    let generator =
+        interrupt::unless_cancelled(Some(&cancel), ImageGenerator::from_config(&config)).await?;
+    let load_elapsed = load_start.elapsed();
+    let memory = MemorySnapshot::capture().since(before);
+    status!(out, "Model loaded in {}", fmt_duration(load_elapsed));
+    status!(out, "{}", memory.banner());
+    Ok((generator, load_elapsed, memory))
+}
+
+/// `Ok` when every image was generated, [`Interrupted`] once the partial
+/// result has been reported.
+fn finish(result: &ImageResult) -> Result<()> {
+    if result.interrupted {
+        return Err(Interrupted.into());
+    }
+    if result.low_space {
+        anyhow::bail!(
+            "Stopped after {} image(s): the output volume is below the --min-free floor",
+            result.images.len()
+        );
+    }
     Ok(())
 }
 
-/// Truncate `text` to at most `max_words` whitespace-separated words.
-///
-/// Acts as a final safety net so prompts never exceed CLIP's 77-token limit.
-fn truncate_to_words(text: &str, max_words: usize) -> String {
-    let words: Vec<&str> = text.split_whitespace().collect();
-    if words.len() <= max_words {
-        return text.to_string();
+/// Like [`finish`], failing too when any of the `--sizes` did, once every
+/// size has been reported.
+fn finish_sizes(result: &SizesResult) -> Result<()> {
+    if result.interrupted {
+        return Err(Interrupted.into());
+    }
+    if let Some(last) = result.sizes.last().filter(|_| result.low_space) {
+        anyhow::bail!(
+            "Stopped at {}: the output volume is below the --min-free floor",
+            last.size
+        );
+    }
+    match result.failed() {
+        0 => Ok(()),
+        failed => anyhow::bail!("{failed} of {} sizes failed", result.sizes.len()),
     }
-    words[..max_words].join(" ")
 }
@@ -0,0 +1,215 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+// ── Metadata ─────────────────────────────────────────────────────────────────
+
+/// Where a diffusion prompt came from.  Known before generation, so it
+/// travels on the [`ImageRequest`](crate::image_generation::ImageRequest).
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PromptOrigin {
+    /// The seed prompt given to the enhancer, before any style.
+    pub seed_prompt: Option<String>,
+    /// Name of the `--style` preset composed with the seed.
+    pub style: Option<String>,
+    /// The enhancer: "template", a model ID, or a local model path.
+    pub enhancer: Option<String>,
+    /// The enhancer's output, before truncation to the prompt budget.
+    pub enhanced_prompt: Option<String>,
+    /// Sampler seed of the enhancement (`--sampler-seed` replays it).
+    /// mistral.rs doesn't take a seed for the diffusion noise itself.
+    pub sampler_seed: Option<u64>,
+}
+
+/// Everything that went into one generated image, written next to it as
+/// `<image>.json` and, with `--embed-metadata`, into the PNG itself.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ImageMetadata {
+    #[serde(flatten)]
+    pub origin: PromptOrigin,
+    /// The prompt the diffusion model saw, after truncation.
+    pub prompt: String,
+    #[serde(default)]
+    pub negative_prompt: Option<String>,
+    /// Diffusion model ID.
+    pub model: String,
+    /// mistral.rs loader type, e.g. `FluxOffloaded`.
+    pub loader: String,
+    /// Weight dtype the model was loaded in, e.g. `BF16`; `None` in
+    /// metadata written before it was recorded.
+    #[serde(default)]
+    pub dtype: Option<String>,
+    pub width: usize,
+    pub height: usize,
+    /// Denoising steps; `None` where the loader picks them and the count
+    /// isn't known.
+    #[serde(default)]
+    pub steps: Option<usize>,
+    /// Seconds spent generating the image.
+    pub generation_secs: f64,
+    /// When the image was written, as Unix seconds.
+    pub created_at: u64,
+}
+
+/// Which copies of the metadata to write for each image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MetadataOutput {
+    /// `<image>.json` next to the image (on unless `--no-metadata`).
+    pub sidecar: bool,
+    /// A tEXt chunk in the PNG (`--embed-metadata`).
+    pub embed: bool,
+}
+
+impl Default for MetadataOutput {
+    fn default() -> Self {
+        Self {
+            sidecar: true,
+            embed: false,
+        }
+    }
+}
+
+impl MetadataOutput {
+    /// Write nothing, e.g. for scratch files that are deleted right away.
+    pub const NONE: Self = Self {
+        sidecar: false,
+        embed: false,
+    };
+}
+
+/// Path of the sidecar for `image`: `art.png` → `art.png.json`.
+pub fn sidecar_path(image: &Path) -> PathBuf {
+    let mut name = image.as_os_str().to_owned();
+    name.push(".json");
+    PathBuf::from(name)
+}
+
+impl ImageMetadata {
+    /// Write the copies `output` asks for.  Returns the sidecar's path, if
+    /// one was written.
+    pub fn save(&self, image: &Path, output: MetadataOutput) -> Result<Option<PathBuf>> {
+        if output.embed {
+            self.embed_png(image)?;
+        }
+        if !output.sidecar {
+            return Ok(None);
+        }
+        self.write_sidecar(image).map(Some)
+    }
+
+    /// Write `<image>.json`, returning its path.
+    pub fn write_sidecar(&self, image: &Path) -> Result<PathBuf> {
+        let path = sidecar_path(image);
+        let json = serde_json::to_string_pretty(self).context("Failed to serialize metadata")?;
+        std::fs::write(&path, json + "\n")
+            .with_context(|| format!("Failed to write metadata: {}", path.display()))?;
+        Ok(path)
+    }
+
+    /// Add the metadata to the PNG at `path` as a tEXt chunk, replacing
+    /// one written earlier.
+    pub fn embed_png(&self, path: &Path) -> Result<()> {
+        let png =
+            std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let json = serde_json::to_string(self).context("Failed to serialize metadata")?;
+        let png = png_with_text(&png, PNG_KEYWORD, &ascii_json(&json))
+            .with_context(|| format!("Can't embed metadata: {} is not a PNG", path.display()))?;
+        std::fs::write(path, png)
+            .with_context(|| format!("Failed to write image: {}", path.display()))
+    }
+}
+
+// ── PNG chunks ───────────────────────────────────────────────────────────────
+
+/// tEXt keyword the metadata is stored under.
+const PNG_KEYWORD: &str = "mistralrs-example";
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// `(type, data)` of every chunk in `png`; `None` if it isn't a
+/// well-formed PNG.
+fn png_chunks(png: &[u8]) -> Option<Vec<([u8; 4], &[u8])>> {
+    let mut rest = png.strip_prefix(PNG_SIGNATURE)?;
+    let mut chunks = Vec::new();
+    while !rest.is_empty() {
+        let len = u32::from_be_bytes(rest.get(..4)?.try_into().ok()?) as usize;
+        let kind: [u8; 4] = rest.get(4..8)?.try_into().ok()?;
+        let data = rest.get(8..8 + len)?;
+        rest = rest.get(12 + len..)?;
+        chunks.push((kind, data));
+    }
+    Some(chunks)
+}
+
+/// `png` with a tEXt chunk holding `text` under `keyword`, inserted before
+/// IEND.  A tEXt chunk already under `keyword` is dropped.
+fn png_with_text(png: &[u8], keyword: &str, text: &str) -> Option<Vec<u8>> {
+    let chunks = png_chunks(png)?;
+    let mut data = Vec::with_capacity(keyword.len() + 1 + text.len());
+    data.extend_from_slice(keyword.as_bytes());
+    data.push(0);
+    data.extend_from_slice(text.as_bytes());
+
+    let mut out = PNG_SIGNATURE.to_vec();
+    for (kind, chunk) in chunks {
+        let ours = &kind == b"tEXt" && text_chunk(chunk).is_some_and(|(k, _)| k == keyword);
+        if ours {
+            continue;
+        }
+        if &kind == b"IEND" {
+            push_chunk(&mut out, *b"tEXt", &data);
+        }
+        push_chunk(&mut out, kind, chunk);
+    }
+    Some(out)
+}
+
+fn push_chunk(out: &mut Vec<u8>, kind: [u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    out.extend_from_slice(&kind);
+    out.extend_from_slice(data);
+    out.extend_from_slice(&crc32(&[&kind, data]).to_be_bytes());
+}
+
+/// Split a tEXt chunk into keyword and text.  Both are Latin-1; only the
+/// ASCII ones this module writes are read back.
+fn text_chunk(data: &[u8]) -> Option<(&str, &str)> {
+    let nul = data.iter().position(|&b| b == 0)?;
+    let keyword = std::str::from_utf8(&data[..nul]).ok()?;
+    let text = std::str::from_utf8(&data[nul + 1..]).ok()?;
+    Some((keyword, text))
+}
+
+/// `json` with every non-ASCII character written as a `\u` escape, so it
+/// is valid Latin-1 for a tEXt chunk and still the same JSON.  Non-ASCII
+/// only occurs inside strings, where escapes are allowed.
+fn ascii_json(json: &str) -> String {
+    let mut out = String::with_capacity(json.len());
+    for c in json.chars() {
+        if c.is_ascii() {
+            out.push(c);
+        } else {
+            for unit in c.encode_utf16(&mut [0; 2]) {
+                out.push_str(&format!("\\u{unit:04x}"));
+            }
+        }
+    }
+    out
+}
+
+/// CRC-32 (ISO-HDLC, as PNG uses) over the concatenation of `parts`.
+fn crc32(parts: &[&[u8]]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in parts.iter().flat_map(|part| part.iter()) {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
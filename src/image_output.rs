@@ -0,0 +1,413 @@
+use anyhow::{Context, Result};
+use image::imageops::{self, FilterType};
+use image::{Rgb, RgbImage};
+use std::path::{Path, PathBuf};
+
+// ── Constants ────────────────────────────────────────────────────────────────
+
+/// Space around and between tiles, in pixels.
+pub const GRID_MARGIN: u32 = 8;
+
+/// Background of the sheet and its caption strips.
+const BACKGROUND: Rgb<u8> = Rgb([24, 24, 24]);
+
+/// Caption text colour.
+const TEXT: Rgb<u8> = Rgb([230, 230, 230]);
+
+/// Glyph cell of the built-in font, before scaling.
+const GLYPH_WIDTH: u32 = 5;
+const GLYPH_HEIGHT: u32 = 7;
+
+/// Lines of prompt shown in the caption strip; longer prompts end in `...`.
+const MAX_PROMPT_LINES: usize = 2;
+
+// ── GridOptions ──────────────────────────────────────────────────────────────
+
+/// How `image --grid` lays out its contact sheet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GridOptions {
+    /// Tiles per row (`None` = about square; see [`default_columns`]).
+    pub columns: Option<usize>,
+    /// Label each tile and add a strip with the prompt (off with
+    /// `--no-grid-caption`).
+    pub caption: bool,
+}
+
+impl Default for GridOptions {
+    fn default() -> Self {
+        Self {
+            columns: None,
+            caption: true,
+        }
+    }
+}
+
+/// Text drawn on the sheet when [`GridOptions::caption`] is on.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GridCaption {
+    /// Shown in the strip along the bottom, wrapped to the sheet width.
+    pub prompt: String,
+    /// Extra line under the prompt, e.g. the sampler seed.
+    pub note: Option<String>,
+    /// One label per tile, drawn under it (e.g. `#2`).
+    pub labels: Vec<String>,
+}
+
+/// Where the sheet for images written to `out` goes: `art.png` →
+/// `art_grid.png`.
+pub fn grid_path(out: &Path) -> PathBuf {
+    let stem = out.file_stem().unwrap_or_default().to_string_lossy();
+    out.with_file_name(format!("{stem}_grid.png"))
+}
+
+// ── Layout ───────────────────────────────────────────────────────────────────
+
+/// Tiles per row when `--grid-columns` isn't given: the smallest count
+/// that makes the grid at least as wide as it is tall (4 → 2, 5 → 3).
+pub fn default_columns(count: usize) -> usize {
+    let mut columns = 1;
+    while columns * columns < count {
+        columns += 1;
+    }
+    columns
+}
+
+/// The size every tile is scaled to: the smallest width and height among
+/// `sizes`, so no image is enlarged.  `None` when `sizes` is empty.
+pub fn common_size(sizes: &[(u32, u32)]) -> Option<(u32, u32)> {
+    let width = sizes.iter().map(|&(w, _)| w).min()?;
+    let height = sizes.iter().map(|&(_, h)| h).min()?;
+    Some((width, height))
+}
+
+/// Pixel geometry of a contact sheet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GridLayout {
+    pub columns: u32,
+    pub rows: u32,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub margin: u32,
+    /// Height of the label strip under each tile (0 without captions).
+    pub label_height: u32,
+    /// Height of the caption strip along the bottom (0 without captions).
+    pub caption_height: u32,
+    /// Size of the whole sheet.
+    pub width: u32,
+    pub height: u32,
+}
+
+impl GridLayout {
+    /// Lay out `count` tiles of `tile` size in `columns` columns (capped at
+    /// `count`), with `margin` pixels around and between them.
+    pub fn new(
+        count: usize,
+        columns: usize,
+        tile: (u32, u32),
+        margin: u32,
+        label_height: u32,
+        caption_height: u32,
+    ) -> Self {
+        let columns = columns.clamp(1, count.max(1)) as u32;
+        let rows = (count.max(1) as u32).div_ceil(columns);
+        let (tile_width, tile_height) = tile;
+        let width = columns * tile_width + (columns + 1) * margin;
+        let height = rows * (tile_height + label_height)
+            + (rows + 1) * margin
+            + if caption_height > 0 {
+                caption_height + margin
+            } else {
+                0
+            };
+        Self {
+            columns,
+            rows,
+            tile_width,
+            tile_height,
+            margin,
+            label_height,
+            caption_height,
+            width,
+            height,
+        }
+    }
+
+    /// Top-left corner of the `index`-th tile, filled row by row.
+    pub fn tile_origin(&self, index: usize) -> (u32, u32) {
+        let column = index as u32 % self.columns;
+        let row = index as u32 / self.columns;
+        (
+            self.margin + column * (self.tile_width + self.margin),
+            self.margin + row * (self.tile_height + self.label_height + self.margin),
+        )
+    }
+
+    /// Top of the caption strip along the bottom.
+    pub fn caption_top(&self) -> u32 {
+        self.height - self.margin - self.caption_height
+    }
+}
+
+// ── Caption text ─────────────────────────────────────────────────────────────
+
+/// Font scale for a sheet `width` pixels wide: large enough to read on a
+/// big sheet, never below 2× the 5×7 font.
+pub fn caption_scale(width: u32) -> u32 {
+    (width / 640).clamp(2, 6)
+}
+
+/// Height of one line of text at `scale`, with room between lines.
+pub fn line_height(scale: u32) -> u32 {
+    (GLYPH_HEIGHT + 3) * scale
+}
+
+/// How many characters fit in `width` pixels at `scale`.
+pub fn chars_that_fit(width: u32, scale: u32) -> usize {
+    ((width / scale + 1) / (GLYPH_WIDTH + 1)) as usize
+}
+
+/// Word-wrap `text` into at most `max_lines` lines of `max_chars`; the last
+/// line ends in `...` when some of the text didn't fit.  Words longer than
+/// a line are split.
+pub fn wrap_caption(text: &str, max_chars: usize, max_lines: usize) -> Vec<String> {
+    if max_chars == 0 || max_lines == 0 {
+        return Vec::new();
+    }
+    let mut lines: Vec<String> = Vec::new();
+    let mut current = String::new();
+    let mut words = text.split_whitespace().peekable();
+    let mut overflow = false;
+    while let Some(word) = words.next() {
+        let mut word: Vec<char> = word.chars().collect();
+        while !word.is_empty() {
+            let used = current.chars().count();
+            let space = usize::from(used > 0);
+            if used + space + word.len() <= max_chars {
+                if space == 1 {
+                    current.push(' ');
+                }
+                current.extend(word.drain(..));
+            } else if used == 0 {
+                current.extend(word.drain(..max_chars));
+            } else {
+                lines.push(std::mem::take(&mut current));
+            }
+            if lines.len() == max_lines {
+                overflow = true;
+                break;
+            }
+        }
+        if overflow {
+            break;
+        }
+        if words.peek().is_none() && !current.is_empty() {
+            lines.push(std::mem::take(&mut current));
+        }
+    }
+    if overflow && let Some(last) = lines.last_mut() {
+        *last = ellipsize(last, max_chars);
+    }
+    lines
+}
+
+/// `text` cut to `max_chars` ending in `...`, or as-is if it already
+/// leaves room.
+fn ellipsize(text: &str, max_chars: usize) -> String {
+    let keep = max_chars.saturating_sub(3);
+    let mut out: String = text.chars().take(keep).collect();
+    out.truncate(out.trim_end().len());
+    out.push_str("...");
+    out.chars().take(max_chars).collect()
+}
+
+/// Draw `text` with its top-left corner at `(x, y)`, `scale` pixels per
+/// font dot, clipped to the canvas.
+pub fn draw_text(canvas: &mut RgbImage, x: u32, y: u32, text: &str, scale: u32, color: Rgb<u8>) {
+    for (i, c) in text.chars().enumerate() {
+        let left = x + i as u32 * (GLYPH_WIDTH + 1) * scale;
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for col in 0..GLYPH_WIDTH {
+                if bits & (1 << (GLYPH_WIDTH - 1 - col)) == 0 {
+                    continue;
+                }
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        let px = left + col * scale + dx;
+                        let py = y + row as u32 * scale + dy;
+                        if px < canvas.width() && py < canvas.height() {
+                            canvas.put_pixel(px, py, color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The 5×7 bitmap of `c`, one byte per row with the leftmost dot in bit 4.
+/// Lowercase letters use the capitals; anything else not in the table is
+/// drawn as `?`.
+fn glyph(c: char) -> [u8; 7] {
+    match c.to_ascii_uppercase() {
+        ' ' => [0; 7],
+        'A' => [0x0E, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'B' => [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E],
+        'C' => [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E],
+        'D' => [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C],
+        'E' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F],
+        'F' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10],
+        'G' => [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F],
+        'H' => [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'I' => [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F],
+        'M' => [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'P' => [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10],
+        'Q' => [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D],
+        'R' => [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11],
+        'S' => [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E],
+        'T' => [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A],
+        'X' => [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04],
+        'Z' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F],
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C],
+        ',' => [0x00, 0x00, 0x00, 0x00, 0x0C, 0x04, 0x08],
+        ':' => [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00],
+        ';' => [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x04, 0x08],
+        '-' => [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+        '\'' => [0x0C, 0x04, 0x08, 0x00, 0x00, 0x00, 0x00],
+        '"' => [0x0A, 0x0A, 0x00, 0x00, 0x00, 0x00, 0x00],
+        '!' => [0x04, 0x04, 0x04, 0x04, 0x04, 0x00, 0x04],
+        '#' => [0x0A, 0x0A, 0x1F, 0x0A, 0x1F, 0x0A, 0x0A],
+        '/' => [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00],
+        '(' => [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02],
+        ')' => [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08],
+        '&' => [0x0C, 0x12, 0x14, 0x08, 0x15, 0x12, 0x0D],
+        _ => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04],
+    }
+}
+
+// ── Compositing ──────────────────────────────────────────────────────────────
+
+/// Compose `images` into one sheet, `options.columns` per row.
+///
+/// Images of different sizes are scaled to the smallest width and height
+/// among them.  With a `caption`, each tile gets its label underneath and
+/// the prompt (and note) run along the bottom.  `None` when `images` is
+/// empty.
+pub fn compose_grid(
+    images: &[RgbImage],
+    options: GridOptions,
+    caption: Option<&GridCaption>,
+) -> Option<RgbImage> {
+    let sizes: Vec<(u32, u32)> = images.iter().map(|image| image.dimensions()).collect();
+    let tile = common_size(&sizes)?;
+    let columns = options
+        .columns
+        .unwrap_or_else(|| default_columns(images.len()));
+    let caption = caption.filter(|_| options.caption);
+
+    // The font scale depends only on the width, which captions don't change.
+    let bare = GridLayout::new(images.len(), columns, tile, GRID_MARGIN, 0, 0);
+    let scale = caption_scale(bare.width);
+    let (label_height, prompt_lines) = match caption {
+        Some(caption) => {
+            let max_chars = chars_that_fit(bare.width - 2 * GRID_MARGIN, scale);
+            let mut lines = wrap_caption(&caption.prompt, max_chars, MAX_PROMPT_LINES);
+            if let Some(note) = &caption.note {
+                lines.extend(wrap_caption(note, max_chars, 1));
+            }
+            (line_height(scale), lines)
+        }
+        None => (0, Vec::new()),
+    };
+    let caption_height = prompt_lines.len() as u32 * line_height(scale);
+    let layout = GridLayout::new(
+        images.len(),
+        columns,
+        tile,
+        GRID_MARGIN,
+        label_height,
+        caption_height,
+    );
+
+    let mut sheet = RgbImage::from_pixel(layout.width, layout.height, BACKGROUND);
+    for (index, image) in images.iter().enumerate() {
+        let (x, y) = layout.tile_origin(index);
+        if image.dimensions() == tile {
+            imageops::replace(&mut sheet, image, x.into(), y.into());
+        } else {
+            let scaled = imageops::resize(image, tile.0, tile.1, FilterType::Triangle);
+            imageops::replace(&mut sheet, &scaled, x.into(), y.into());
+        }
+        if let Some(label) = caption.and_then(|caption| caption.labels.get(index)) {
+            let max_chars = chars_that_fit(layout.tile_width, scale);
+            let label = match wrap_caption(label, max_chars, 1).pop() {
+                Some(label) => label,
+                None => continue,
+            };
+            draw_text(
+                &mut sheet,
+                x,
+                y + layout.tile_height + scale * 2,
+                &label,
+                scale,
+                TEXT,
+            );
+        }
+    }
+    let top = layout.caption_top();
+    for (i, line) in prompt_lines.iter().enumerate() {
+        draw_text(
+            &mut sheet,
+            GRID_MARGIN,
+            top + i as u32 * line_height(scale) + scale,
+            line,
+            scale,
+            TEXT,
+        );
+    }
+    Some(sheet)
+}
+
+/// Read the PNGs at `paths`, compose them with [`compose_grid`] and write
+/// the sheet to `out` (creating its directory).
+pub fn save_grid(
+    paths: &[&Path],
+    out: &Path,
+    options: GridOptions,
+    caption: Option<&GridCaption>,
+) -> Result<()> {
+    let images = paths
+        .iter()
+        .map(|path| {
+            image::open(path)
+                .map(|image| image.to_rgb8())
+                .with_context(|| format!("Failed to read image for the grid: {}", path.display()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let sheet = compose_grid(&images, options, caption).context("No images to put in the grid")?;
+    if let Some(parent) = out.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create output directory: {}", parent.display()))?;
+    }
+    sheet
+        .save_with_format(out, image::ImageFormat::Png)
+        .with_context(|| format!("Failed to write grid: {}", out.display()))
+}
@@ -0,0 +1,421 @@
+use anyhow::{Context, Result};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use tokio_util::sync::CancellationToken;
+
+use crate::cli_chat::{command_arg, spawn_stdin_reader};
+use crate::image_generation::{
+    self, DiffusionConfig, EnhancerOptions, ImageGenerator, ImageOptions, ImageResult,
+};
+use crate::image_metadata::{PromptOrigin, sidecar_path};
+use crate::interrupt::{self, Interrupt};
+use crate::promp_enhancer::{EnhancerBackend, PromptEnhance};
+use crate::sampler;
+use crate::styles::{self, StylePreset};
+use crate::ui::{OutputConfig, status};
+
+// ── Constants ────────────────────────────────────────────────────────────────
+
+/// Where session images go without `--out`: `image_1.png`, `image_2.png`, …
+/// in the current directory.
+pub const DEFAULT_OUT: &str = "image.png";
+
+/// Printed by `h`.
+const HELP: &str = "Commands:
+  r         Generate the current prompt again (new diffusion noise)
+  e <text>  Replace the prompt and generate; with --keep-enhancer, <text>
+            is a new seed that is enhanced first
+  o <path>  Save a copy of the last image and its metadata
+  p         Show the current prompt
+  h         Show this help
+  q         Quit";
+
+// ── Input ────────────────────────────────────────────────────────────────────
+
+/// One line typed at the `image>` prompt, parsed without touching the
+/// session.  Arguments are empty when a command was given without one, so
+/// the loop can print its usage.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReviewInput<'a> {
+    /// Blank line: prompt again.
+    Empty,
+    Regenerate,
+    /// `e`, with the new prompt (or seed).
+    Edit(&'a str),
+    /// `o`, with the destination path.
+    SaveCopy(&'a str),
+    Show,
+    Help,
+    Quit,
+    /// A line that isn't a command.
+    Unknown(&'a str),
+}
+
+impl<'a> ReviewInput<'a> {
+    /// Parse a line as read from stdin.  Surrounding whitespace is ignored.
+    pub fn parse(line: &'a str) -> Self {
+        let input = line.trim();
+        match input {
+            "" => return Self::Empty,
+            "r" => return Self::Regenerate,
+            "p" => return Self::Show,
+            "h" | "?" | "help" => return Self::Help,
+            "q" | "quit" | "exit" => return Self::Quit,
+            _ => {}
+        }
+        if let Some(text) = command_arg(input, "e") {
+            return Self::Edit(text);
+        }
+        if let Some(path) = command_arg(input, "o") {
+            return Self::SaveCopy(path);
+        }
+        Self::Unknown(input)
+    }
+}
+
+// ── ImageSession ─────────────────────────────────────────────────────────────
+
+/// A diffusion model kept loaded between images, for `image --interactive`.
+///
+/// Each [`generate`](Self::generate) writes the next numbered file after
+/// `--out` (`fox_1.png`, `fox_2.png`, …) with its metadata, so nothing an
+/// earlier round produced is overwritten.
+pub struct ImageSession {
+    generator: ImageGenerator,
+    /// Size, negative prompt and metadata settings of every image.
+    options: ImageOptions,
+    /// `--out`, or [`DEFAULT_OUT`]; the images are numbered after it.
+    base: PathBuf,
+    prompt: String,
+    origin: PromptOrigin,
+    /// Number of the last image written.
+    count: usize,
+    last: Option<PathBuf>,
+}
+
+impl ImageSession {
+    /// Start a session rendering `prompt`, whose origin goes into each
+    /// image's metadata.
+    pub fn new(
+        generator: ImageGenerator,
+        options: ImageOptions,
+        prompt: String,
+        origin: PromptOrigin,
+    ) -> Self {
+        let base = options
+            .out
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_OUT));
+        Self {
+            generator,
+            options,
+            base,
+            prompt,
+            origin,
+            count: 0,
+            last: None,
+        }
+    }
+
+    /// The prompt the next image is generated from.
+    pub fn prompt(&self) -> &str {
+        &self.prompt
+    }
+
+    /// Generate from `prompt` from now on.
+    pub fn set_prompt(&mut self, prompt: String, origin: PromptOrigin) {
+        self.prompt = prompt;
+        self.origin = origin;
+    }
+
+    /// The image written last, if any.
+    pub fn last_image(&self) -> Option<&Path> {
+        self.last.as_deref()
+    }
+
+    /// Generate one image of the current prompt into the next numbered
+    /// file, reporting progress like a one-shot `image` run.
+    pub async fn generate(&mut self, out: OutputConfig) -> Result<ImageResult> {
+        let path = self.next_path();
+        let options = ImageOptions {
+            out: Some(path.clone()),
+            num_images: 1,
+            force: true,
+            grid: None,
+            ..self.options.clone()
+        };
+        let (result, elapsed) = image_generation::render(
+            &self.generator,
+            &options,
+            &self.prompt,
+            self.origin.clone(),
+            out,
+        )
+        .await?;
+        // A prompt the model only took shortened is kept shortened.
+        self.prompt.clone_from(&result.prompt);
+        if !result.images.is_empty() {
+            self.last = Some(path);
+        }
+        image_generation::print_images(&result, elapsed, out);
+        Ok(result)
+    }
+
+    /// Copy the last image, and its metadata sidecar if it has one, to
+    /// `dest`.  Refuses to overwrite an existing file unless `--force` was
+    /// given.
+    pub fn save_copy(&self, dest: &Path) -> Result<()> {
+        let Some(last) = &self.last else {
+            anyhow::bail!("No image has been generated yet");
+        };
+        if dest.exists() && !self.options.force {
+            anyhow::bail!(
+                "{} already exists (pass --force to overwrite)",
+                dest.display()
+            );
+        }
+        if let Some(parent) = dest.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        std::fs::copy(last, dest)
+            .with_context(|| format!("Failed to copy {} to {}", last.display(), dest.display()))?;
+        let sidecar = sidecar_path(last);
+        if sidecar.is_file() {
+            let copy = sidecar_path(dest);
+            std::fs::copy(&sidecar, &copy)
+                .with_context(|| format!("Failed to write metadata: {}", copy.display()))?;
+        }
+        Ok(())
+    }
+
+    /// `<base>_<n>.<ext>` for the next `n`, skipping files that already
+    /// exist unless `--force` was given.
+    fn next_path(&mut self) -> PathBuf {
+        loop {
+            self.count += 1;
+            let path = numbered(&self.base, self.count);
+            if self.options.force || !path.exists() {
+                return path;
+            }
+        }
+    }
+}
+
+/// `fox.png` → `fox_3.png` for `n` = 3.
+fn numbered(base: &Path, n: usize) -> PathBuf {
+    let stem = base.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match base.extension() {
+        Some(ext) => format!("{stem}_{n}.{}", ext.to_string_lossy()),
+        None => format!("{stem}_{n}"),
+    };
+    base.with_file_name(name)
+}
+
+// ── Interactive loop ─────────────────────────────────────────────────────────
+
+/// The enhancer kept loaded with `--keep-enhancer`, for `e`.
+struct Resident {
+    enhancer: Box<dyn PromptEnhance>,
+    label: String,
+    style: Option<StylePreset>,
+    sampler_seed: Option<u64>,
+}
+
+impl Resident {
+    /// Enhance `seed` (with the style, if any) into the next prompt and its
+    /// origin.
+    async fn enhance(
+        &self,
+        seed: &str,
+        cancel: &CancellationToken,
+    ) -> Result<(String, PromptOrigin)> {
+        let styled = match &self.style {
+            Some(style) => style.compose(seed),
+            None => seed.to_string(),
+        };
+        let enhanced =
+            interrupt::unless_cancelled(Some(cancel), self.enhancer.enhance(&styled)).await?;
+        let origin = PromptOrigin {
+            seed_prompt: Some(seed.to_string()),
+            style: self.style.as_ref().map(|style| style.name.clone()),
+            enhancer: Some(self.label.clone()),
+            enhanced_prompt: Some(enhanced.clone()),
+            sampler_seed: self.sampler_seed,
+        };
+        Ok((enhanced, origin))
+    }
+}
+
+/// `image --interactive`: load the diffusion model once, generate an image
+/// of the prompt, then offer to generate again, edit the prompt or save a
+/// copy after each one, until `q` or EOF.
+///
+/// The prompt is resolved as for a one-shot run.  With `keep_enhancer`
+/// the enhancer stays loaded, and `e` enhances a new seed instead of
+/// taking the text as the prompt.
+pub async fn run_interactive(
+    prompt: Option<String>,
+    seed_prompt: Option<String>,
+    enhancer_options: EnhancerOptions,
+    options: ImageOptions,
+    diffusion: DiffusionConfig,
+    keep_enhancer: bool,
+    out: OutputConfig,
+) -> Result<()> {
+    if out.json {
+        anyhow::bail!("--interactive can't be combined with --json");
+    }
+    if options.num_images > 1 || options.grid.is_some() {
+        anyhow::bail!("--interactive generates one image per round; drop --num-images / --grid");
+    }
+    let mut enhancer_options = enhancer_options;
+    // Session images are numbered, so `--out` itself is never written.
+    image_generation::check_inputs(
+        &enhancer_options,
+        &ImageOptions {
+            out: None,
+            ..options.clone()
+        },
+    )?;
+    let style = enhancer_options
+        .style
+        .as_deref()
+        .map(styles::find)
+        .transpose()?;
+    let sampler_seed =
+        (seed_prompt.is_some() && enhancer_options.backend == EnhancerBackend::Model).then(|| {
+            *enhancer_options
+                .sampler_seed
+                .get_or_insert_with(sampler::random_seed)
+        });
+    if let Some(seed) = sampler_seed {
+        status!(out, "Sampler seed: {seed}");
+    }
+    let budget = diffusion.prompt_budget();
+    let cancel = Interrupt::install(true).token();
+
+    let (resident, (prompt, origin)) = match seed_prompt {
+        Some(seed) if keep_enhancer => {
+            let (enhancer, label) =
+                image_generation::load_enhancer(&enhancer_options, budget, &cancel, out).await?;
+            if let Some(style) = &style {
+                status!(out, "\nStyle: {style}");
+            }
+            let styled = match &style {
+                Some(style) => style.compose(&seed),
+                None => seed.clone(),
+            };
+            let enhanced = image_generation::enhance_seed(
+                enhancer.as_ref(),
+                &styled,
+                &enhancer_options,
+                &cancel,
+                out,
+            )
+            .await?;
+            let resident = Resident {
+                enhancer,
+                label,
+                style,
+                sampler_seed,
+            };
+            let origin = PromptOrigin {
+                seed_prompt: Some(seed),
+                style: resident.style.as_ref().map(|style| style.name.clone()),
+                enhancer: Some(resident.label.clone()),
+                enhanced_prompt: Some(enhanced.clone()),
+                sampler_seed,
+            };
+            (Some(resident), (enhanced, origin))
+        }
+        seed => {
+            let resolved = image_generation::resolve_prompt(
+                prompt,
+                seed.as_deref(),
+                &enhancer_options,
+                style.as_ref(),
+                budget,
+                &cancel,
+                out,
+            )
+            .await?;
+            (None, resolved)
+        }
+    };
+
+    let (generator, _, _) =
+        image_generation::load_diffusion(diffusion, cancel.clone(), out).await?;
+    let generator = generator.with_cancellation(cancel.clone());
+    let mut session = ImageSession::new(generator, options, prompt, origin);
+
+    let mut generate_next = true;
+    let mut lines = spawn_stdin_reader();
+    loop {
+        if generate_next {
+            match session.generate(out).await {
+                Ok(_) => {}
+                Err(err) if interrupt::is_interrupted(&err) => {
+                    println!("\n(interrupted)");
+                    break;
+                }
+                Err(err) => println!("error: {err:#}"),
+            }
+            generate_next = false;
+            println!("\nr: again, e <text>: edit, o <path>: save a copy, q: quit");
+        }
+        print!("\nimage> ");
+        io::stdout().flush()?;
+        let line = tokio::select! {
+            line = lines.recv() => line,
+            _ = cancel.cancelled() => None,
+        };
+        let Some(line) = line else {
+            println!();
+            break;
+        };
+        let line = line?;
+
+        match ReviewInput::parse(&line) {
+            ReviewInput::Empty => {}
+            ReviewInput::Quit => break,
+            ReviewInput::Help => println!("{HELP}"),
+            ReviewInput::Show => println!("Prompt:\n  \"{}\"", session.prompt()),
+            ReviewInput::Regenerate => generate_next = true,
+            ReviewInput::Edit("") => {
+                println!("Usage: e <text>");
+                println!("Prompt:\n  \"{}\"", session.prompt());
+            }
+            ReviewInput::Edit(text) => match &resident {
+                Some(resident) => match resident.enhance(text, &cancel).await {
+                    Ok((enhanced, origin)) => {
+                        println!("\nEnhanced prompt:\n  \"{enhanced}\"");
+                        session.set_prompt(enhanced, origin);
+                        generate_next = true;
+                    }
+                    Err(err) if interrupt::is_interrupted(&err) => {
+                        println!("\n(interrupted)");
+                        break;
+                    }
+                    Err(err) => println!("error: {err:#}"),
+                },
+                None => {
+                    session.set_prompt(text.to_string(), PromptOrigin::default());
+                    generate_next = true;
+                }
+            },
+            ReviewInput::SaveCopy("") => println!("Usage: o <path>"),
+            ReviewInput::SaveCopy(path) => match session.save_copy(Path::new(path)) {
+                Ok(()) => println!("Copy saved at: {path}"),
+                Err(err) => println!("error: {err:#}"),
+            },
+            ReviewInput::Unknown(input) => {
+                println!("Unknown command {input}; type h for the list.")
+            }
+        }
+    }
+    if let Some(last) = session.last_image() {
+        status!(out, "Last image: {}", last.display());
+    }
+    Ok(())
+}
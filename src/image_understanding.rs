@@ -0,0 +1,295 @@
+use anyhow::{Context, Result};
+use image::{DynamicImage, ImageReader};
+use mistralrs::{Model, RequestBuilder, TextMessageRole};
+use serde::Serialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+use crate::audio_transcription::TranscriptionModel;
+use crate::interrupt;
+use crate::load_options::LoadOptions;
+use crate::local_model::LocalModel;
+use crate::memory;
+use crate::sampler::{SamplerArgs, SamplerConfig};
+use crate::timeout;
+use crate::ui::{self, OutputConfig, detail, fmt_duration, status};
+
+// ── Constants ────────────────────────────────────────────────────────────────
+
+/// Instruction sent with the images when `--user-prompt` isn't given.
+pub const DEFAULT_USER_PROMPT: &str = "Describe this image in detail.";
+
+/// Keeps the model on what it can see rather than guessing at context.
+const SYSTEM_PROMPT: &str = "You are an image analysis assistant. Describe only what is visible \
+in the images: subjects, composition, colors, lighting, style and any text. When several images \
+are given, refer to them by their order (first, second, …).";
+
+/// Default sampling for descriptions: near-greedy so repeated runs agree,
+/// up to 512 tokens.
+pub const DEFAULT_SAMPLER: SamplerConfig = SamplerConfig::new(0.2, 0.95, 512);
+
+// ── ImageDescriber ───────────────────────────────────────────────────────────
+
+/// Describes images with Gemma 3n's vision encoder.
+///
+/// Gemma 3n is loaded through `VisionModelBuilder` for transcription too,
+/// so the [`TranscriptionModel`] presets are reused and a model already
+/// loaded for audio can be shared via [`from_model`](Self::from_model).
+pub struct ImageDescriber {
+    model: Arc<Model>,
+    sampler: SamplerConfig,
+    /// Limit per request: `--timeout`, if given.
+    timeout: Option<Duration>,
+}
+
+impl ImageDescriber {
+    /// Load a Gemma 3n preset with `--isq` / `--dtype` overrides applied.
+    pub async fn from_preset_with_options(
+        preset: TranscriptionModel,
+        opts: &LoadOptions,
+    ) -> Result<Self> {
+        Ok(Self::from_model(Arc::new(preset.build_model(opts).await?)))
+    }
+
+    /// Load Gemma 3n from a local HuggingFace-layout directory.
+    pub async fn from_local_path(path: &Path, opts: &LoadOptions) -> Result<Self> {
+        let model = LocalModel::from_path(path)?.build(opts).await?;
+        Ok(Self::from_model(Arc::new(model)))
+    }
+
+    /// Wrap an already-loaded vision model.
+    pub fn from_model(model: Arc<Model>) -> Self {
+        Self {
+            model,
+            sampler: DEFAULT_SAMPLER,
+            timeout: timeout::default_limit(),
+        }
+    }
+
+    /// Override the sampling settings (default [`DEFAULT_SAMPLER`]).
+    pub fn with_sampler(mut self, sampler: SamplerConfig) -> Self {
+        self.sampler = sampler;
+        self
+    }
+
+    /// Send `images` with `instruction` in a single request and return the
+    /// trimmed reply.
+    pub async fn describe(&self, images: Vec<DynamicImage>, instruction: &str) -> Result<String> {
+        if images.is_empty() {
+            anyhow::bail!("No images to describe");
+        }
+        let request = self
+            .sampler
+            .apply(RequestBuilder::new())
+            .add_message(TextMessageRole::System, SYSTEM_PROMPT)
+            .add_image_message(TextMessageRole::User, instruction, images, &self.model)?;
+
+        let response = timeout::within(
+            self.timeout,
+            "Image description",
+            self.model.send_chat_request(request),
+        )
+        .await?;
+        Ok(response.choices[0]
+            .message
+            .content
+            .as_ref()
+            .map(|c| self.sampler.trim_stop(c).trim().to_string())
+            .unwrap_or_default())
+    }
+}
+
+// ── Input images ─────────────────────────────────────────────────────────────
+
+/// An image read from disk, with what the result reports about it.
+pub struct InputImage {
+    pub path: PathBuf,
+    pub image: DynamicImage,
+}
+
+impl InputImage {
+    /// Read and decode a PNG or JPEG, sniffing the format from the content.
+    pub fn open(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            anyhow::bail!("Image file not found: {}", path.display());
+        }
+        let image = ImageReader::open(path)
+            .and_then(|reader| reader.with_guessed_format())
+            .with_context(|| format!("Failed to read image file: {}", path.display()))?
+            .decode()
+            .with_context(|| {
+                format!(
+                    "Failed to decode {} (PNG and JPEG are supported)",
+                    path.display()
+                )
+            })?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            image,
+        })
+    }
+
+    fn info(&self) -> ImageInfo {
+        ImageInfo {
+            path: self.path.display().to_string(),
+            width: self.image.width(),
+            height: self.image.height(),
+        }
+    }
+}
+
+// ── DescriptionResult ────────────────────────────────────────────────────────
+
+/// One input image as reported in a [`DescriptionResult`].
+#[derive(Clone, Debug, Serialize)]
+pub struct ImageInfo {
+    pub path: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl fmt::Display for ImageInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}x{})", self.path, self.width, self.height)
+    }
+}
+
+/// The output of a description, including the text and timing metadata.
+#[derive(Serialize)]
+pub struct DescriptionResult {
+    /// The model's description.
+    pub text: String,
+    /// The images sent, in order.
+    pub images: Vec<ImageInfo>,
+    /// The instruction sent alongside the images.
+    pub user_prompt: String,
+    /// Wall-clock time the model spent generating the description.
+    #[serde(rename = "inference_secs", serialize_with = "serialize_secs")]
+    pub inference_duration: Duration,
+}
+
+fn serialize_secs<S: serde::Serializer>(d: &Duration, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_f64(d.as_secs_f64())
+}
+
+impl fmt::Display for DescriptionResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "── Description ──")?;
+        writeln!(f, "{}", self.text)?;
+        writeln!(f, "─────────────────")?;
+        for (i, image) in self.images.iter().enumerate() {
+            let label = if i == 0 { "Images" } else { "" };
+            writeln!(f, "{label:<15}: {image}")?;
+        }
+        write!(
+            f,
+            "Inference time : {}",
+            fmt_duration(self.inference_duration)
+        )
+    }
+}
+
+// ── Standalone CLI entry-point ───────────────────────────────────────────────
+
+/// Description settings chosen on the command line.
+#[derive(Clone, Debug, Default)]
+pub struct DescribeOptions {
+    /// Instruction sent alongside the images (`None` = [`DEFAULT_USER_PROMPT`]).
+    pub user_prompt: Option<String>,
+    /// `--temperature` / `--top-p` / `--max-tokens` overrides.
+    pub sampler: SamplerArgs,
+}
+
+/// Describe one or more images as a standalone CLI example.
+///
+/// The images are decoded before Gemma 3n loads, so a missing or
+/// unsupported file fails fast.  All images go into one request with the
+/// `--user-prompt` instruction.
+pub async fn run(
+    image_paths: Vec<PathBuf>,
+    model: Option<TranscriptionModel>,
+    model_path: Option<PathBuf>,
+    load: LoadOptions,
+    options: DescribeOptions,
+    cancel: CancellationToken,
+    out: OutputConfig,
+) -> Result<()> {
+    let DescribeOptions {
+        user_prompt,
+        sampler,
+    } = options;
+    let decode_start = Instant::now();
+    let inputs = image_paths
+        .iter()
+        .map(|path| InputImage::open(path))
+        .collect::<Result<Vec<_>>>()?;
+    detail!(
+        out,
+        "{} image(s) decoded in {}",
+        inputs.len(),
+        fmt_duration(decode_start.elapsed())
+    );
+
+    let load_start = Instant::now();
+    let loading = async {
+        match &model_path {
+            Some(path) => {
+                status!(out, "Loading vision model: {}", path.display());
+                ImageDescriber::from_local_path(path, &load).await
+            }
+            None => {
+                let (preset, load) = memory::choose(model.unwrap_or_default(), &load)?;
+                status!(out, "Loading vision model: {preset}");
+                status!(out, "  Memory estimate: {}", preset.memory_estimate(&load));
+                let settings = load.apply(preset.default_settings());
+                if let Some(weights) = load.uqff.describe(preset.model_id(), settings) {
+                    status!(out, "  {weights}");
+                }
+                ImageDescriber::from_preset_with_options(preset, &load).await
+            }
+        }
+    };
+    let describer = interrupt::unless_cancelled(Some(&cancel), loading)
+        .await?
+        .with_sampler(sampler.apply(DEFAULT_SAMPLER));
+    status!(
+        out,
+        "Model loaded in {}\n",
+        fmt_duration(load_start.elapsed())
+    );
+
+    let user_prompt = user_prompt.unwrap_or_else(|| DEFAULT_USER_PROMPT.to_string());
+    let images: Vec<ImageInfo> = inputs.iter().map(InputImage::info).collect();
+    for image in &images {
+        status!(out, "Describing: {image}");
+    }
+
+    let inference_start = Instant::now();
+    let text = interrupt::unless_cancelled(
+        Some(&cancel),
+        describer.describe(
+            inputs.into_iter().map(|input| input.image).collect(),
+            &user_prompt,
+        ),
+    )
+    .await?;
+    let result = DescriptionResult {
+        text,
+        images,
+        user_prompt,
+        inference_duration: inference_start.elapsed(),
+    };
+
+    if out.json {
+        return ui::print_json(&result);
+    }
+    if out.quiet() {
+        println!("{}", result.text);
+    } else {
+        println!("\n{result}");
+    }
+    Ok(())
+}
@@ -0,0 +1,213 @@
+use crate::audio_transcription::Segment;
+
+// ── Timestamp modes ──────────────────────────────────────────────────────────
+
+/// Where `transcribe --timestamps` gets segment timings from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum TimestampMode {
+    /// Ask the model to start each line with a `[mm:ss]` marker and parse
+    /// the markers out of its reply.  The times are the model's estimate.
+    Inline,
+}
+
+/// Appended to the system prompt in [`TimestampMode::Inline`].
+pub const INLINE_TIMESTAMP_NOTE: &str = "Start each line of the transcript with the time \
+     it begins in the audio, written as [mm:ss] (for example [00:00] or [01:07]). Start a new \
+     line at each pause or new sentence.";
+
+/// How far past the end of the audio a marker may point before it is
+/// treated as garbled: the model rounds to whole seconds.
+const END_TOLERANCE_SECS: f64 = 1.0;
+
+// ── Parsing ──────────────────────────────────────────────────────────────────
+
+/// A bracketed span in the model's reply that looks like a timestamp.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Marker {
+    /// `[1:02]`, `[01:02]`, `[00:01:02]`, `[01:02.5]`, or a range such as
+    /// `[00:01 - 00:04]` (only the start is kept).
+    Time(f64),
+    /// Digits and colons that don't form a valid time, e.g. `[1:75]` or
+    /// `[:12]`.  Dropped from the text, with no timing taken from it.
+    Garbled,
+}
+
+/// Split the model's reply into timed [`Segment`]s, one per `[mm:ss]`
+/// marker, each ending where the next begins and the last at
+/// `duration_secs`.  Markers may appear anywhere, not only at line
+/// starts.
+///
+/// The reply is taken as the model wrote it, within limits:
+///
+/// - Text before the first marker starts at 0.
+/// - A garbled marker, one that doesn't move forward in time, or one past
+///   the end of the audio is dropped; its text continues the segment
+///   before it.
+/// - A marker with no text after it still ends the segment before it, so
+///   pauses the model marked stay out of the cues.
+/// - Markdown emphasis around a marker (`**[00:03]**`) is dropped with it.
+/// - Other bracketed spans, such as `[inaudible]`, stay in the text.
+///
+/// `None` when the reply has no usable marker at all, so the caller can
+/// keep its single whole-file timing.
+pub fn parse_segments(text: &str, duration_secs: f64) -> Option<Vec<Segment>> {
+    let limit = if duration_secs > 0.0 {
+        duration_secs + END_TOLERANCE_SECS
+    } else {
+        f64::INFINITY
+    };
+    let mut segments: Vec<Segment> = Vec::new();
+    let mut timed = false;
+    for (marker, piece) in split_markers(text) {
+        let start = match marker {
+            Some(Marker::Time(secs)) => Some(secs),
+            Some(Marker::Garbled) | None => None,
+        }
+        .filter(|&secs| secs <= limit)
+        .map(|secs| {
+            if duration_secs > 0.0 {
+                secs.min(duration_secs)
+            } else {
+                secs
+            }
+        })
+        .filter(|&secs| segments.last().is_none_or(|last| secs > last.start_secs));
+        match (start, segments.last_mut()) {
+            (Some(secs), _) => {
+                timed = true;
+                segments.push(Segment {
+                    start_secs: secs,
+                    end_secs: 0.0,
+                    text: piece.to_string(),
+                    language: None,
+                });
+            }
+            (None, Some(last)) => {
+                // Join across the dropped marker with a single space or
+                // the line break that was already there.
+                if last.text.ends_with(char::is_whitespace) {
+                    last.text.push_str(piece.trim_start());
+                } else {
+                    if !piece.starts_with(char::is_whitespace) {
+                        last.text.push(' ');
+                    }
+                    last.text.push_str(piece);
+                }
+            }
+            (None, None) => segments.push(Segment {
+                start_secs: 0.0,
+                end_secs: 0.0,
+                text: piece.to_string(),
+                language: None,
+            }),
+        }
+    }
+    if !timed {
+        return None;
+    }
+
+    let starts: Vec<f64> = segments.iter().map(|seg| seg.start_secs).collect();
+    for (i, seg) in segments.iter_mut().enumerate() {
+        seg.end_secs = starts
+            .get(i + 1)
+            .copied()
+            .unwrap_or(duration_secs)
+            .max(seg.start_secs);
+        seg.text = seg.text.trim().to_string();
+    }
+    segments.retain(|seg| !seg.text.is_empty());
+    Some(segments)
+}
+
+/// Cut `text` at each marker: the text before the first marker (with
+/// `None`), then each marker with the text up to the next one.
+fn split_markers(text: &str) -> Vec<(Option<Marker>, &str)> {
+    let mut pieces = Vec::new();
+    let mut marker = None;
+    let mut start = 0;
+    let mut search = 0;
+    while let Some(open) = text[search..].find('[').map(|i| search + i) {
+        let inner = &text[open + 1..];
+        // A marker never spans lines.
+        let found = inner
+            .find([']', '\n', '['])
+            .filter(|&end| inner[end..].starts_with(']'))
+            .and_then(|end| Some((end, parse_marker(&inner[..end])?)));
+        match found {
+            Some((end, next)) => {
+                // Markdown emphasis around the marker (`**[00:03]**`) goes
+                // with it.
+                let close = open + 1 + end + 1;
+                let before = &text[start..open];
+                let emphasis = before.len() - before.trim_end_matches(['*', '`']).len();
+                let emphasis = (0..=emphasis)
+                    .rev()
+                    .find(|&n| text[close..].starts_with(&before[before.len() - n..]))
+                    .unwrap_or(0);
+                pieces.push((marker, &text[start..open - emphasis]));
+                marker = Some(next);
+                start = close + emphasis;
+                search = start;
+            }
+            None => search = open + 1,
+        }
+    }
+    pieces.push((marker, &text[start..]));
+    pieces
+}
+
+/// Read the inside of a `[...]` span as a [`Marker`]; `None` when it isn't
+/// timestamp-like at all.
+fn parse_marker(inner: &str) -> Option<Marker> {
+    let inner = inner.trim();
+    let looks_like_time = inner.contains(':')
+        && inner
+            .chars()
+            .all(|c| c.is_ascii_digit() || matches!(c, ':' | '.' | ',' | '-' | '–' | '>' | ' '));
+    if !looks_like_time {
+        return None;
+    }
+    // `[00:01 - 00:04]` or `[00:01 --> 00:04]`: the start is enough, the
+    // next marker gives the end.
+    let start = inner.split(['-', '–']).next().unwrap_or_default();
+    Some(parse_clock(start).map_or(Marker::Garbled, Marker::Time))
+}
+
+/// `m:ss`, `mm:ss` or `h:mm:ss` / `hh:mm:ss`, with optional fractional
+/// seconds after `.` or `,`, as seconds.
+fn parse_clock(clock: &str) -> Option<f64> {
+    let clock = clock.trim();
+    let (whole, fraction) = match clock.split_once(['.', ',']) {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (clock, None),
+    };
+    let parts: Vec<&str> = whole.split(':').collect();
+    let digits = |part: &&str| {
+        !part.is_empty() && part.len() <= 2 && part.bytes().all(|b| b.is_ascii_digit())
+    };
+    if !parts.iter().all(digits) || parts.last()?.len() != 2 {
+        return None;
+    }
+    let numbers: Vec<u32> = parts
+        .iter()
+        .map(|part| part.parse().ok())
+        .collect::<Option<_>>()?;
+    let (hours, minutes, seconds) = match numbers[..] {
+        [minutes, seconds] => (0, minutes, seconds),
+        [hours, minutes, seconds] if parts[1].len() == 2 && minutes < 60 => {
+            (hours, minutes, seconds)
+        }
+        _ => return None,
+    };
+    if seconds >= 60 {
+        return None;
+    }
+    let fraction = match fraction {
+        None => 0.0,
+        Some(digits) if !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) => {
+            format!("0.{digits}").parse().ok()?
+        }
+        Some(_) => return None,
+    };
+    Some(f64::from(hours * 3600 + minutes * 60 + seconds) + fraction)
+}
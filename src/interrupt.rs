@@ -0,0 +1,107 @@
+use anyhow::Result;
+use std::fmt;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio_util::sync::CancellationToken;
+
+// ── Interrupted ──────────────────────────────────────────────────────────────
+
+/// Exit status of a process stopped by Ctrl-C (128 + SIGINT).
+pub const EXIT_CODE: i32 = 130;
+
+/// Error returned when work stops because its [`CancellationToken`] was
+/// cancelled.  `main` maps it to [`EXIT_CODE`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Interrupted;
+
+impl fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Interrupted")
+    }
+}
+
+impl std::error::Error for Interrupted {}
+
+/// Whether `err` (or anything in its chain) is [`Interrupted`].
+pub fn is_interrupted(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| cause.is::<Interrupted>())
+}
+
+/// Await `fut` unless `cancel` fires first, in which case fail with
+/// [`Interrupted`] and drop `fut`.  Without a token this is just `fut.await`.
+/// `fut` may fail with any error `anyhow` takes, such as
+/// [`Error`](crate::error::Error).
+pub async fn unless_cancelled<T, E: Into<anyhow::Error>>(
+    cancel: Option<&CancellationToken>,
+    fut: impl Future<Output = Result<T, E>>,
+) -> Result<T> {
+    let Some(cancel) = cancel else {
+        return fut.await.map_err(Into::into);
+    };
+    tokio::select! {
+        result = fut => result.map_err(Into::into),
+        _ = cancel.cancelled() => Err(Interrupted.into()),
+    }
+}
+
+// ── Ctrl-C handler ───────────────────────────────────────────────────────────
+
+/// Turns Ctrl-C into cancellation of the work in progress.
+///
+/// The first Ctrl-C cancels the current token so the command can stop at a
+/// safe point and keep what it already has.  A second Ctrl-C before the
+/// token is [`reset`](Self::reset) exits the process straight away, so a
+/// step that can't be cancelled never traps the user.
+#[derive(Clone, Debug)]
+pub struct Interrupt {
+    current: Arc<Mutex<CancellationToken>>,
+}
+
+impl Interrupt {
+    /// Install the Ctrl-C handler for the rest of the process.  With
+    /// `notice`, the first Ctrl-C also says what happens next.
+    ///
+    /// Must be called from within the tokio runtime.  Commands that never
+    /// call this keep the default Ctrl-C behaviour (exit immediately).
+    pub fn install(notice: bool) -> Self {
+        let interrupt = Self {
+            current: Arc::new(Mutex::new(CancellationToken::new())),
+        };
+        let current = interrupt.current.clone();
+        tokio::spawn(async move {
+            while tokio::signal::ctrl_c().await.is_ok() {
+                let token = current.lock().unwrap_or_else(|e| e.into_inner()).clone();
+                if token.is_cancelled() {
+                    eprintln!("\nInterrupted again; exiting.");
+                    std::process::exit(EXIT_CODE);
+                }
+                if notice {
+                    eprintln!(
+                        "\nInterrupted; stopping after the current step \
+                         (press Ctrl-C again to exit immediately)."
+                    );
+                }
+                token.cancel();
+            }
+        });
+        interrupt
+    }
+
+    /// The token the next Ctrl-C will cancel.
+    pub fn token(&self) -> CancellationToken {
+        self.current
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Swap in a fresh token once an interruption has been handled, so the
+    /// next Ctrl-C is a soft one again.  Returns the new token.
+    pub fn reset(&self) -> CancellationToken {
+        let mut current = self.current.lock().unwrap_or_else(|e| e.into_inner());
+        if current.is_cancelled() {
+            *current = CancellationToken::new();
+        }
+        current.clone()
+    }
+}
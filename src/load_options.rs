@@ -0,0 +1,187 @@
+use mistralrs::{IsqType, ModelDType};
+use std::fmt;
+
+use crate::error::Error;
+use crate::uqff::UqffArgs;
+
+// ── CLI choices ──────────────────────────────────────────────────────────────
+
+/// Values accepted by `--isq`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum IsqChoice {
+    /// 4-bit k-quant (~0.56 bytes per weight).
+    #[value(name = "q4k")]
+    Q4k,
+    /// 8-bit (~1.06 bytes per weight).
+    #[value(name = "q8_0")]
+    Q80,
+    /// No quantization; weights stay in `--dtype`.
+    None,
+}
+
+/// Values accepted by `--dtype`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum DtypeChoice {
+    /// Let mistral.rs pick based on the device.
+    Auto,
+    F16,
+    Bf16,
+}
+
+impl DtypeChoice {
+    /// The mistral.rs dtype this choice loads weights in.
+    pub fn model_dtype(self) -> ModelDType {
+        match self {
+            Self::Auto => ModelDType::Auto,
+            Self::F16 => ModelDType::F16,
+            Self::Bf16 => ModelDType::BF16,
+        }
+    }
+}
+
+// ── Resolved settings ────────────────────────────────────────────────────────
+
+/// The dtype / ISQ a model is actually loaded with.  `None` leaves the
+/// mistral.rs default in place.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LoadSettings {
+    pub dtype: Option<ModelDType>,
+    pub isq: Option<IsqType>,
+}
+
+impl LoadSettings {
+    /// Settings that only quantize.
+    pub fn isq(isq: IsqType) -> Self {
+        Self {
+            dtype: None,
+            isq: Some(isq),
+        }
+    }
+
+    /// Settings that only pick a dtype.
+    pub fn dtype(dtype: ModelDType) -> Self {
+        Self {
+            dtype: Some(dtype),
+            isq: None,
+        }
+    }
+
+    /// Bytes per weight and a short label, e.g. `(1.0625, "Q8_0")`.
+    fn weight_format(&self) -> (f64, String) {
+        match (self.isq, self.dtype) {
+            (Some(IsqType::Q4K), _) => (0.5625, "Q4K".to_string()),
+            (Some(IsqType::Q8_0), _) => (1.0625, "Q8_0".to_string()),
+            (Some(isq), _) => (1.0, format!("{isq:?}")),
+            (None, Some(ModelDType::F32)) => (4.0, "F32".to_string()),
+            (None, Some(dtype)) => (2.0, format!("{dtype:?}")),
+            (None, None) => (2.0, "Auto".to_string()),
+        }
+    }
+
+    /// Rough memory footprint for a model with `params_billions` weights,
+    /// e.g. "~4.0 GB (Q8_0)".
+    pub fn approx_memory(&self, params_billions: f64) -> String {
+        let (bytes_per_weight, label) = self.weight_format();
+        format!("~{:.1} GB ({label})", params_billions * bytes_per_weight)
+    }
+
+    /// [`approx_memory`](Self::approx_memory) in bytes.
+    pub fn approx_bytes(&self, params_billions: f64) -> u64 {
+        let (bytes_per_weight, _) = self.weight_format();
+        (params_billions * bytes_per_weight * GB) as u64
+    }
+}
+
+/// Bytes in the "GB" of memory estimates.
+pub const GB: f64 = 1e9;
+
+// ── LoadOptions ──────────────────────────────────────────────────────────────
+
+/// `--isq` / `--dtype` overrides shared by every subcommand that loads a
+/// text model.  Unset fields keep the preset's defaults.
+#[derive(Clone, Debug, Default, PartialEq, Eq, clap::Args)]
+pub struct LoadOptions {
+    /// Override the preset's in-situ quantization.
+    /// With `image`, applies to the prompt enhancer only.
+    ///
+    /// Possible values:
+    ///   q4k  — 4-bit, smallest
+    ///   q8_0 — 8-bit, near-lossless
+    ///   none — no quantization (use --dtype)
+    #[arg(long, value_enum)]
+    pub isq: Option<IsqChoice>,
+
+    /// Override the preset's weight dtype.
+    /// With `image`, applies to the prompt enhancer only.
+    ///
+    /// Possible values:
+    ///   auto — let mistral.rs pick for the device
+    ///   f16  — half precision
+    ///   bf16 — bfloat16
+    #[arg(long, value_enum)]
+    pub dtype: Option<DtypeChoice>,
+
+    /// If the model doesn't fit in available memory, load Gemma 3n E2B
+    /// (Q4K) instead of failing.
+    #[arg(long)]
+    pub auto_downgrade: bool,
+
+    #[command(flatten)]
+    pub uqff: UqffArgs,
+}
+
+impl LoadOptions {
+    /// Whether neither `--isq` nor `--dtype` was given.
+    pub fn is_default(&self) -> bool {
+        self.isq.is_none() && self.dtype.is_none()
+    }
+
+    /// Apply the overrides on top of a preset's defaults.
+    pub fn apply(&self, defaults: LoadSettings) -> LoadSettings {
+        let isq = match self.isq {
+            Some(IsqChoice::Q4k) => Some(IsqType::Q4K),
+            Some(IsqChoice::Q80) => Some(IsqType::Q8_0),
+            Some(IsqChoice::None) => None,
+            None => defaults.isq,
+        };
+        let dtype = match self.dtype {
+            Some(choice) => Some(choice.model_dtype()),
+            None => defaults.dtype,
+        };
+        LoadSettings { dtype, isq }
+    }
+
+    /// [`Error::ModelLoad`] for `model_id`, naming the override flags so a
+    /// builder error (e.g. an unsupported ISQ type) points at the flag that
+    /// caused it.
+    pub fn load_error(&self, model_id: &str, source: anyhow::Error) -> Error {
+        let source = if self.is_default() && !self.uqff.has_explicit_path() {
+            source
+        } else {
+            source.context(format!("Loaded with {self}"))
+        };
+        Error::model_load(model_id, source)
+    }
+}
+
+/// Renders the overrides as they were passed, e.g. "--isq q8_0 --dtype f16".
+impl fmt::Display for LoadOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use clap::ValueEnum;
+
+        let mut flags = Vec::new();
+        if let Some(isq) = self.isq.and_then(|v| v.to_possible_value()) {
+            flags.push(format!("--isq {}", isq.get_name()));
+        }
+        if let Some(dtype) = self.dtype.and_then(|v| v.to_possible_value()) {
+            flags.push(format!("--dtype {}", dtype.get_name()));
+        }
+        if let Some(path) = &self.uqff.save_uqff {
+            flags.push(format!("--save-uqff {}", path.display()));
+        }
+        if let Some(path) = &self.uqff.from_uqff {
+            flags.push(format!("--from-uqff {}", path.display()));
+        }
+        write!(f, "{}", flags.join(" "))
+    }
+}
@@ -0,0 +1,432 @@
+use anyhow::Result;
+use indicatif::{HumanBytes, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use mistralrs::{Model, TextModelBuilder, VisionModelBuilder};
+use std::fmt;
+use std::future::Future;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::device;
+use crate::load_options::LoadSettings;
+use crate::uqff::{Uqff, UqffArgs};
+
+// ── Model building ───────────────────────────────────────────────────────────
+
+/// Which mistral.rs builder a model needs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ModelKind {
+    /// Plain text model ([`TextModelBuilder`]).
+    Text,
+    /// Multimodal architecture such as Gemma 3n ([`VisionModelBuilder`]),
+    /// also used for text-only chat and audio.
+    Vision,
+}
+
+/// Build a model from a HuggingFace id or local directory with `settings`
+/// applied, reporting progress on stderr.
+///
+/// Every preset goes through here so that download / load feedback is the
+/// same for the enhancer, transcriber and chat.  `uqff` decides whether the
+/// quantized weights are read from / written to a UQFF file.
+#[tracing::instrument(skip_all, fields(model_id = model_id, kind = ?kind))]
+pub async fn build_model(
+    model_id: &str,
+    kind: ModelKind,
+    settings: LoadSettings,
+    uqff: &UqffArgs,
+) -> Result<Model> {
+    let uqff = uqff.resolve(model_id, settings);
+    let loading = match (&uqff, settings.isq) {
+        (Some(Uqff::Load { .. }), _) => "Loading quantized weights (UQFF)".to_string(),
+        (_, Some(isq)) => format!("Loading weights and quantizing ({isq:?})"),
+        (_, None) => "Loading weights".to_string(),
+    };
+    if let Some(uqff) = &uqff {
+        uqff.prepare()?;
+    }
+    let model = with_retries(model_id, || {
+        with_progress(
+            model_id,
+            &loading,
+            build_once(model_id, kind, settings, uqff.as_ref()),
+        )
+    })
+    .await?;
+    if let Some(uqff) = &uqff {
+        uqff.finish()?;
+    }
+    Ok(model)
+}
+
+async fn build_once(
+    model_id: &str,
+    kind: ModelKind,
+    settings: LoadSettings,
+    uqff: Option<&Uqff>,
+) -> Result<Model> {
+    // A UQFF file is already quantized, so ISQ is skipped when loading one.
+    let (isq, from_uqff, write_uqff) = match uqff {
+        Some(Uqff::Load { path, .. }) => (None, Some(path.clone()), None),
+        Some(Uqff::Save { path, .. }) => (settings.isq, None, Some(path.clone())),
+        None => (settings.isq, None, None),
+    };
+    match kind {
+        ModelKind::Vision => {
+            let mut builder = VisionModelBuilder::new(model_id);
+            if device::selected().force_cpu() {
+                builder = builder.with_force_cpu();
+            }
+            if let Some(isq) = isq {
+                builder = builder.with_isq(isq);
+            }
+            if let Some(dtype) = settings.dtype {
+                builder = builder.with_dtype(dtype);
+            }
+            if let Some(path) = from_uqff {
+                builder = builder.from_uqff(vec![path]);
+            }
+            if let Some(path) = write_uqff {
+                builder = builder.write_uqff(path);
+            }
+            builder.build().await
+        }
+        ModelKind::Text => {
+            let mut builder = TextModelBuilder::new(model_id);
+            if device::selected().force_cpu() {
+                builder = builder.with_force_cpu();
+            }
+            if let Some(isq) = isq {
+                builder = builder.with_isq(isq);
+            }
+            if let Some(dtype) = settings.dtype {
+                builder = builder.with_dtype(dtype);
+            }
+            if let Some(path) = from_uqff {
+                builder = builder.from_uqff(vec![path]);
+            }
+            if let Some(path) = write_uqff {
+                builder = builder.write_uqff(path);
+            }
+            builder.build().await
+        }
+    }
+}
+
+// ── Retries ──────────────────────────────────────────────────────────────────
+
+/// Retries after a failed download unless `--download-retries` says
+/// otherwise.
+pub const DEFAULT_DOWNLOAD_RETRIES: u32 = 3;
+
+static DOWNLOAD_RETRIES: AtomicU32 = AtomicU32::new(DEFAULT_DOWNLOAD_RETRIES);
+
+/// Wait before the first retry; doubled for each further attempt.
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(2);
+
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// Error text that points at a transient network / IO problem.
+const RETRYABLE_HINTS: &[&str] = &[
+    "timed out",
+    "timeout",
+    "connection",
+    "network",
+    "dns",
+    "broken pipe",
+    "reset by peer",
+    "unexpected eof",
+    "temporarily unavailable",
+    "status code 429",
+    "status code 500",
+    "status code 502",
+    "status code 503",
+    "status code 504",
+];
+
+/// Error text for a HuggingFace auth failure (gated or private repo).
+const ACCESS_DENIED_HINTS: &[&str] = &[
+    "status code 401",
+    "status code 403",
+    "401 unauthorized",
+    "403 forbidden",
+    "unauthorized",
+    "forbidden",
+    "gated",
+];
+
+/// Set how many times a failed load is retried (`--download-retries`).
+pub fn set_download_retries(retries: u32) {
+    DOWNLOAD_RETRIES.store(retries, Ordering::Relaxed);
+}
+
+/// How a failed load should be handled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Failure {
+    /// Network / IO hiccup: worth another attempt.
+    Retryable,
+    /// 401 / 403 from the Hub: retrying won't help, the user must act.
+    AccessDenied,
+    /// Anything else (bad config, unsupported ISQ, out of memory …).
+    Fatal,
+}
+
+fn classify(err: &anyhow::Error) -> Failure {
+    let text = format!("{err:#}").to_lowercase();
+    if ACCESS_DENIED_HINTS.iter().any(|hint| text.contains(hint)) {
+        Failure::AccessDenied
+    } else if err.chain().any(|cause| cause.is::<std::io::Error>())
+        || RETRYABLE_HINTS.iter().any(|hint| text.contains(hint))
+    {
+        Failure::Retryable
+    } else {
+        Failure::Fatal
+    }
+}
+
+/// Run `attempt` until it succeeds, retrying network / IO failures with
+/// exponential backoff.
+///
+/// Files that finished downloading stay in the HuggingFace cache, so a retry
+/// only fetches what is still missing.  A 401 / 403 fails straight away
+/// with [`AccessDenied`].
+pub async fn with_retries<T, F, Fut>(model_id: &str, mut attempt: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    // Local paths never download, so there is nothing to retry.
+    if repo_cache_dir(model_id).is_none() {
+        return attempt().await;
+    }
+
+    let retries = DOWNLOAD_RETRIES.load(Ordering::Relaxed);
+    let mut delay = RETRY_BASE_DELAY;
+    let mut retry = 0;
+    loop {
+        let err = match attempt().await {
+            Ok(value) => return Ok(value),
+            Err(err) => err,
+        };
+        match classify(&err) {
+            Failure::AccessDenied => {
+                return Err(AccessDenied {
+                    model_id: model_id.to_string(),
+                }
+                .into());
+            }
+            Failure::Retryable if retry < retries => {
+                retry += 1;
+                tracing::warn!(
+                    "loading {model_id} failed ({err}); retrying in {}s (retry {}/{retries})",
+                    delay.as_secs(),
+                    retry
+                );
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(RETRY_MAX_DELAY);
+            }
+            _ => return Err(err),
+        }
+    }
+}
+
+/// Extra steps for gated repos whose access isn't granted on the spot,
+/// keyed by organisation prefix.
+const LICENSE_NOTES: &[(&str, &str)] = &[(
+    "meta-llama/",
+    "Llama models need Meta's community license: fill in the access request form \
+     on the model page and wait for the approval email (usually minutes, sometimes \
+     hours) before retrying.",
+)];
+
+/// Error for a model repo the Hub refused to serve (401 / 403).  What to do
+/// about it is in [`guidance`](Self::guidance), which the CLI prints as a
+/// hint under the error.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AccessDenied {
+    pub model_id: String,
+}
+
+impl AccessDenied {
+    /// How to get access: accept the license, then set a token.  Some
+    /// organisations also need an approval first; see [`LICENSE_NOTES`].
+    pub fn guidance(&self) -> String {
+        let model_id = &self.model_id;
+        let note = LICENSE_NOTES
+            .iter()
+            .find(|(prefix, _)| model_id.starts_with(prefix))
+            .map(|(_, note)| format!("\n{note}"))
+            .unwrap_or_default();
+        format!(
+            "This repo is gated: accept its license at https://huggingface.co/{model_id} \
+             while logged in, then set HF_TOKEN to a token with read access \
+             (or run `huggingface-cli login`) and try again.{note}"
+        )
+    }
+}
+
+impl fmt::Display for AccessDenied {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "HuggingFace denied access to {} (401/403)",
+            self.model_id
+        )
+    }
+}
+
+impl std::error::Error for AccessDenied {}
+
+// ── Progress ─────────────────────────────────────────────────────────────────
+
+static PROGRESS_ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// How often the HuggingFace cache is measured while a model loads.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Once the cache stops growing for this long, the download is assumed to be
+/// done and the bar switches to the loading message.
+const DOWNLOAD_IDLE: Duration = Duration::from_secs(3);
+
+/// Enable or disable progress bars for the rest of the process.  `main`
+/// turns them off for `--json` and when stdout isn't a terminal.
+pub fn set_progress_enabled(enabled: bool) {
+    PROGRESS_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn progress_enabled() -> bool {
+    PROGRESS_ENABLED.load(Ordering::Relaxed) && std::io::stderr().is_terminal()
+}
+
+/// Await `build` while showing a spinner on stderr.
+///
+/// mistral.rs exposes no download callbacks, so progress is measured by
+/// polling the size of the model's HuggingFace cache directory: while it
+/// grows the bar shows bytes downloaded, afterwards it shows `loading`
+/// (weight loading / ISQ).
+pub async fn with_progress<T>(
+    model_id: &str,
+    loading: &str,
+    build: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    if !progress_enabled() {
+        return build.await;
+    }
+
+    let bar = ProgressBar::with_draw_target(None, ProgressDrawTarget::stderr());
+    bar.set_style(
+        ProgressStyle::with_template("{spinner:.green} {msg} [{elapsed}]")
+            .unwrap_or_else(|_| ProgressStyle::default_spinner()),
+    );
+    bar.enable_steady_tick(Duration::from_millis(100));
+
+    let cache = repo_cache_dir(model_id);
+    let initial = cache.as_deref().map(dir_size).unwrap_or(0);
+    let mut size = initial;
+    let mut last_growth: Option<Instant> = None;
+    bar.set_message(format!("{loading}: {model_id}"));
+
+    let mut build = std::pin::pin!(build);
+    let mut poll = tokio::time::interval(POLL_INTERVAL);
+    loop {
+        tokio::select! {
+            result = &mut build => {
+                bar.finish_and_clear();
+                return result;
+            }
+            _ = poll.tick() => {
+                let Some(cache) = cache.as_deref() else { continue };
+                let current = dir_size(cache);
+                if current > size {
+                    size = current;
+                    last_growth = Some(Instant::now());
+                }
+                let downloading = last_growth.is_some_and(|t| t.elapsed() < DOWNLOAD_IDLE);
+                if downloading {
+                    bar.set_message(format!(
+                        "Downloading {model_id}: {}",
+                        HumanBytes(size.saturating_sub(initial))
+                    ));
+                } else {
+                    bar.set_message(format!("{loading}: {model_id}"));
+                }
+            }
+        }
+    }
+}
+
+// ── HuggingFace cache ────────────────────────────────────────────────────────
+
+/// Point the HuggingFace hub cache at `dir` for the rest of the process
+/// (`--cache-dir`).
+///
+/// Sets `HF_HUB_CACHE`, which [`hf_hub_cache`] and mistral.rs's builders
+/// both read, so every download and cache lookup agrees on the location.
+/// Must be called before any other thread starts.
+pub fn set_cache_dir(dir: &Path) {
+    // SAFETY: `main` calls this before building the tokio runtime, while
+    // the process is still single-threaded.
+    unsafe { std::env::set_var("HF_HUB_CACHE", dir) };
+}
+
+/// The HuggingFace hub cache directory, honouring `HF_HUB_CACHE` and
+/// `HF_HOME` like mistral.rs does.
+pub fn hf_hub_cache() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os("HF_HUB_CACHE") {
+        return Some(PathBuf::from(dir));
+    }
+    if let Some(dir) = std::env::var_os("HF_HOME") {
+        return Some(PathBuf::from(dir).join("hub"));
+    }
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+    Some(PathBuf::from(home).join(".cache/huggingface/hub"))
+}
+
+/// Cache directory for a hub repo (`models--org--name`).  `None` for local
+/// paths, which aren't downloaded.
+pub fn repo_cache_dir(model_id: &str) -> Option<PathBuf> {
+    if Path::new(model_id).exists() {
+        return None;
+    }
+    Some(hf_hub_cache()?.join(format!("models--{}", model_id.replace('/', "--"))))
+}
+
+/// Commit hash the hub cache's `main` ref points at, i.e. the revision a
+/// load of `repo` would use.  `None` before the first download.
+pub fn repo_revision(repo: &str) -> Option<String> {
+    let refs = repo_cache_dir(repo)?.join("refs/main");
+    let revision = std::fs::read_to_string(refs).ok()?;
+    let revision = revision.trim();
+    (!revision.is_empty()).then(|| revision.to_string())
+}
+
+/// Snapshot directories of `repo` in the hub cache (usually just one).
+pub fn snapshots(repo: &str) -> Vec<PathBuf> {
+    let Some(dir) = repo_cache_dir(repo) else {
+        return Vec::new();
+    };
+    let Ok(entries) = std::fs::read_dir(dir.join("snapshots")) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_dir())
+        .collect()
+}
+
+/// Total size of the regular files under `dir` (symlinks are not followed,
+/// so hub snapshots aren't counted twice).  Missing directories are 0.
+pub fn dir_size(dir: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| match entry.file_type() {
+            Ok(kind) if kind.is_dir() => dir_size(&entry.path()),
+            Ok(kind) if kind.is_file() => entry.metadata().map(|m| m.len()).unwrap_or(0),
+            _ => 0,
+        })
+        .sum()
+}
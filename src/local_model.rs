@@ -0,0 +1,143 @@
+use anyhow::{Context, Result};
+use mistralrs::{GgufModelBuilder, Model};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::device;
+use crate::load_options::{LoadOptions, LoadSettings};
+use crate::loader::{self, ModelKind};
+
+// ── Local models ─────────────────────────────────────────────────────────────
+
+/// A model loaded from disk instead of the HuggingFace Hub.
+///
+/// Used by `--model-path` so the CLI works fully offline.  Two layouts are
+/// accepted:
+///
+/// - a single `.gguf` file (already quantized, text-only in mistral.rs);
+/// - a directory in HuggingFace layout (`config.json`, tokenizer files and
+///   safetensors weights), e.g. a `huggingface-cli download` target.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LocalModel {
+    Gguf {
+        /// Directory containing the GGUF file.
+        dir: PathBuf,
+        /// File name within `dir`.
+        file: String,
+    },
+    Directory {
+        dir: PathBuf,
+        /// First entry of `architectures` in `config.json`, if present.
+        architecture: Option<String>,
+    },
+}
+
+impl LocalModel {
+    /// Validate `path` and work out how to load it.
+    ///
+    /// Fails with a descriptive error when the path doesn't exist, is a file
+    /// other than `.gguf`, or is a directory without `config.json`.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let meta = std::fs::metadata(path)
+            .with_context(|| format!("Model path not found: {}", path.display()))?;
+
+        if meta.is_file() {
+            let is_gguf = path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("gguf"));
+            if !is_gguf {
+                anyhow::bail!(
+                    "Unsupported model file: {} (expected a .gguf file or a model directory)",
+                    path.display()
+                );
+            }
+            let dir = match path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                Some(parent) => parent.to_path_buf(),
+                None => PathBuf::from("."),
+            };
+            let file = path
+                .file_name()
+                .context("GGUF path has no file name")?
+                .to_string_lossy()
+                .into_owned();
+            return Ok(Self::Gguf { dir, file });
+        }
+
+        let config_path = path.join("config.json");
+        if !config_path.is_file() {
+            anyhow::bail!(
+                "Not a model directory: {} (no config.json; pass a .gguf file or a \
+                 HuggingFace-layout directory)",
+                path.display()
+            );
+        }
+        let config = std::fs::read_to_string(&config_path)
+            .with_context(|| format!("Failed to read {}", config_path.display()))?;
+        let config: serde_json::Value = serde_json::from_str(&config)
+            .with_context(|| format!("Invalid JSON in {}", config_path.display()))?;
+        let architecture = config["architectures"][0].as_str().map(str::to_string);
+
+        Ok(Self::Directory {
+            dir: path.to_path_buf(),
+            architecture,
+        })
+    }
+
+    /// The path the user passed, for load banners.
+    pub fn path(&self) -> PathBuf {
+        match self {
+            Self::Gguf { dir, file } => dir.join(file),
+            Self::Directory { dir, .. } => dir.clone(),
+        }
+    }
+
+    /// Whether the model is a multimodal architecture that mistral.rs loads
+    /// as a vision model (e.g. `Gemma3nForConditionalGeneration`, which is
+    /// also the only local model that can transcribe audio).
+    pub fn is_multimodal(&self) -> bool {
+        match self {
+            Self::Gguf { .. } => false,
+            Self::Directory { architecture, .. } => architecture
+                .as_deref()
+                .is_some_and(|arch| arch.ends_with("ForConditionalGeneration")),
+        }
+    }
+
+    /// Build the [`Model`] with the matching mistral.rs builder.
+    ///
+    /// Without overrides, directories use the dtype from their `config.json`.
+    /// GGUF files are already quantized, so `--isq` / `--dtype` are rejected
+    /// for them.
+    pub async fn build(&self, opts: &LoadOptions) -> Result<Model> {
+        let settings = opts.apply(LoadSettings::default());
+        match self {
+            Self::Gguf { dir, file } => {
+                if !opts.is_default() || opts.uqff.has_explicit_path() {
+                    anyhow::bail!(
+                        "{opts} cannot be applied to {self}: GGUF files are already quantized"
+                    );
+                }
+                let mut builder = GgufModelBuilder::new(dir.to_string_lossy(), vec![file.clone()]);
+                if device::selected().force_cpu() {
+                    builder = builder.with_force_cpu();
+                }
+                loader::with_progress(file, "Loading weights", builder.build()).await
+            }
+            Self::Directory { dir, .. } => {
+                let kind = if self.is_multimodal() {
+                    ModelKind::Vision
+                } else {
+                    ModelKind::Text
+                };
+                loader::build_model(&dir.to_string_lossy(), kind, settings, &opts.uqff).await
+            }
+        }
+        .map_err(|source| opts.load_error(&self.to_string(), source).into())
+    }
+}
+
+impl fmt::Display for LocalModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path().display())
+    }
+}
@@ -0,0 +1,154 @@
+use std::fmt::{self, Write as _};
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::fmt::format::{FmtSpan, Writer};
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields, FormattedFields};
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::ui::{OutputConfig, Verbosity};
+
+// ── Format ───────────────────────────────────────────────────────────────────
+
+/// How `--log-format` writes progress lines, warnings and span timings.
+/// Either way they go to stderr, so stdout carries only the result.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// Plain lines, as the commands have always printed them.  With
+    /// `--verbose`, each line is prefixed by the spans it happened in, and
+    /// every span reports its time when it closes.
+    #[default]
+    Pretty,
+    /// One JSON object per line, with the current span and its parents.
+    /// Every span reports `time.busy` / `time.idle` when it closes.
+    Json,
+}
+
+/// The filter used when `RUST_LOG` isn't set: this crate's progress lines
+/// at `info`, the `--verbose` details at `debug`, and other crates
+/// (mistral.rs, candle, hf-hub) only with `--verbose`.
+fn default_filter(verbosity: Verbosity) -> &'static str {
+    match verbosity {
+        Verbosity::Quiet => "warn",
+        Verbosity::Normal => concat!("warn,", env!("CARGO_CRATE_NAME"), "=info"),
+        Verbosity::Verbose => concat!("info,", env!("CARGO_CRATE_NAME"), "=debug"),
+    }
+}
+
+/// Install the global subscriber for `format`.  `RUST_LOG`, when set,
+/// replaces the filter `out` implies.  Called once, before anything logs;
+/// mistral.rs' and other crates' `log` records go through it too.
+pub fn init(format: LogFormat, out: OutputConfig) -> Result<()> {
+    let filter = match std::env::var("RUST_LOG") {
+        Ok(directives) if !directives.trim().is_empty() => EnvFilter::try_new(&directives)
+            .with_context(|| format!("Invalid RUST_LOG: {directives}"))?,
+        _ => EnvFilter::new(default_filter(out.verbosity)),
+    };
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .with_ansi(false);
+    let installed = match format {
+        LogFormat::Pretty => builder
+            .with_span_events(if out.verbose() {
+                FmtSpan::CLOSE
+            } else {
+                FmtSpan::NONE
+            })
+            .event_format(PlainFormat {
+                spans: out.verbose(),
+            })
+            .try_init(),
+        LogFormat::Json => builder
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .with_span_events(FmtSpan::CLOSE)
+            .try_init(),
+    };
+    installed.map_err(|err| anyhow::anyhow!("Failed to install the log subscriber: {err}"))
+}
+
+// ── Pretty ───────────────────────────────────────────────────────────────────
+
+/// [`LogFormat::Pretty`]: the message as-is, `warning: ` / `error: ` in
+/// front of those levels and the target in front of other crates' events.
+/// Newlines leading the message (the blank lines between sections) stay
+/// in front of everything.
+struct PlainFormat {
+    /// Prefix the spans the event happened in, e.g.
+    /// `enhance{model_id=… prompt_words=3}: `.
+    spans: bool,
+}
+
+impl<S, N> FormatEvent<S, N> for PlainFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        let mut text = EventText::default();
+        event.record(&mut text);
+        let message = text.message.trim_start_matches('\n');
+        for _ in message.len()..text.message.len() {
+            writeln!(writer)?;
+        }
+
+        let metadata = event.metadata();
+        match *metadata.level() {
+            Level::ERROR => write!(writer, "error: ")?,
+            Level::WARN => write!(writer, "warning: ")?,
+            _ => {}
+        }
+        if self.spans
+            && let Some(scope) = ctx.event_scope()
+        {
+            for span in scope.from_root() {
+                write!(writer, "{}", span.name())?;
+                if let Some(fields) = span.extensions().get::<FormattedFields<N>>()
+                    && !fields.is_empty()
+                {
+                    write!(writer, "{{{fields}}}")?;
+                }
+                write!(writer, ": ")?;
+            }
+        }
+        if !metadata.target().starts_with(env!("CARGO_CRATE_NAME")) {
+            write!(writer, "{}: ", metadata.target())?;
+        }
+        writeln!(writer, "{message}{}", text.fields)
+    }
+}
+
+/// An event's `message`, and its other fields as ` name=value`.
+#[derive(Default)]
+struct EventText {
+    message: String,
+    fields: String,
+}
+
+impl Visit for EventText {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            let _ = write!(self.fields, " {}={value}", field.name());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{value:?}");
+        } else {
+            let _ = write!(self.fields, " {}={value:?}", field.name());
+        }
+    }
+}
@@ -1,14 +1,101 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand};
-use std::path::PathBuf;
+use clap::{CommandFactory, Parser, Subcommand};
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
+mod artifacts;
+mod audio_prep;
+mod audio_probe;
 mod audio_transcription;
+mod batch_transcription;
+mod bench;
 mod cli_chat;
+mod clipboard;
+mod config;
+mod device;
+mod disk_space;
+mod embeddings;
+mod enhancer_chain;
+mod error;
+mod image_eval;
 mod image_generation;
+mod image_metadata;
+mod image_output;
+mod image_session;
+mod image_understanding;
+mod inline_timestamps;
+mod interrupt;
+mod load_options;
+mod loader;
+mod local_model;
+mod logging;
+mod memory;
+mod memstats;
+mod metrics;
+mod model_cache;
+mod model_manager;
+mod pipeline;
 mod promp_enhancer;
+mod prompt_cache;
+mod prompt_compare;
+mod prompt_limits;
+mod prompt_session;
+mod remote_enhancer;
+mod request_queue;
+mod retranscribe;
+mod runs;
+mod sampler;
+mod server;
+mod sessions;
+mod setup;
+mod song_tags;
+mod styles;
+mod summarizer;
+mod system_prompt;
+mod template_enhancer;
+mod text_filters;
+mod timeout;
+mod transcript_format;
+mod transcript_output;
+mod transcription_session;
+mod ui;
+mod uqff;
+mod usage;
+mod watch_folder;
 
-use audio_transcription::TranscriptionModel;
-use promp_enhancer::EnhancerModel;
+use audio_prep::{AudioDump, Normalization, NormalizeMode, PcmFormat, PrepOptions, RawPcm};
+use audio_transcription::{ReferenceText, TranscribeOptions, TranscriptionModel, VocalCheck};
+use batch_transcription::BatchOptions;
+use bench::{BenchOptions, BenchTask};
+use cli_chat::ChatOptions;
+use device::Device;
+use embeddings::{EmbedFormat, EmbedOptions, EmbedSource};
+use image_eval::EvalOptions;
+use image_generation::{DiffusionConfig, EnhancerOptions, ImageOptions, ImageSize, LoaderChoice};
+use image_metadata::MetadataOutput;
+use image_output::GridOptions;
+use image_understanding::DescribeOptions;
+use inline_timestamps::TimestampMode;
+use interrupt::Interrupt;
+use load_options::{DtypeChoice, LoadOptions};
+use logging::LogFormat;
+use metrics::{Hypothesis, NormalizeOptions, WerOptions};
+use model_cache::CachedModel;
+use promp_enhancer::{EnhancerBackend, EnhancerModel, FewShot, PromptOptions};
+use prompt_cache::CacheMode;
+use remote_enhancer::RemoteArgs;
+use retranscribe::{RetranscribeOptions, SegmentList, TimeRange};
+use runs::RunDir;
+use sampler::{DecodingArgs, SamplerArgs};
+use server::{Capability, ServeOptions};
+use setup::SetupOptions;
+use summarizer::{SummarizeOptions, SummaryStyle};
+use system_prompt::SystemPromptArgs;
+use text_filters::Censor;
+use transcript_format::{LyricsFormatter, TextFormat};
+use transcript_output::OutputFormat;
+use ui::OutputConfig;
 
 #[derive(Parser)]
 #[command(name = "mistralrs-example")]
@@ -16,6 +103,100 @@ use promp_enhancer::EnhancerModel;
     about = "mistral.rs examples — image generation, prompt enhancement & audio transcription"
 )]
 struct Cli {
+    /// Print a single JSON object on stdout instead of human-readable
+    /// output.  Progress messages go to stderr.  Not supported by `chat` or
+    /// `serve`.
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// Print only the result (enhanced prompt, transcript, image path),
+    /// warnings and errors.  With --json, stdout carries just the JSON
+    /// object and stderr stays quiet: the mode for scripts.
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Also print mistral.rs's own logging, extra per-stage timings and,
+    /// in front of each line, the span (model load, enhancement, …) it
+    /// belongs to.
+    #[arg(short, long, global = true)]
+    verbose: bool,
+
+    /// How progress lines, warnings and timings are written to stderr.
+    /// The result (prompt, transcript, image paths) stays plain text on
+    /// stdout either way.  RUST_LOG, when set, picks what is logged.
+    ///
+    /// Possible values:
+    ///   pretty — plain lines; with --verbose, span names and timings [default]
+    ///   json   — one JSON object per line, with spans and their timings
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        value_name = "FORMAT",
+        default_value_t = LogFormat::default(),
+        hide_possible_values = true
+    )]
+    log_format: LogFormat,
+
+    /// How many times to retry a model download that fails with a network
+    /// error, with exponential backoff (2s, 4s, 8s, …).  0 disables retries.
+    #[arg(long, global = true, value_name = "N", default_value_t = loader::DEFAULT_DOWNLOAD_RETRIES)]
+    download_retries: u32,
+
+    /// Download a model even when the HuggingFace cache's volume looks too
+    /// small for it.  By default a download that won't fit is refused
+    /// before it starts.
+    #[arg(long, global = true)]
+    skip_space_check: bool,
+
+    /// Stop a batch (several images, or transcriptions written to a
+    /// directory) before the output volume has less than this many GB
+    /// free.  What was written so far is kept and reported.  0 turns the
+    /// check off.
+    #[arg(long, global = true, value_name = "GB", default_value = "2", value_parser = artifacts::parse_min_free)]
+    min_free: u64,
+
+    /// HuggingFace hub cache to download models into and load them from.
+    /// Overrides HF_HUB_CACHE / HF_HOME (default ~/.cache/huggingface/hub).
+    #[arg(long, global = true, value_name = "DIR")]
+    cache_dir: Option<PathBuf>,
+
+    /// Compute backend: auto, cpu, metal, cuda or cuda:N.  `auto` lets
+    /// mistral.rs pick the best one this build supports; `cpu` is the
+    /// escape hatch when a GPU path misbehaves.
+    #[arg(long, global = true, value_name = "DEVICE", default_value = "auto")]
+    device: Device,
+
+    /// Abandon any single model request (an enhanced prompt, a chat reply,
+    /// one transcription segment or image) that runs longer than SECS
+    /// seconds, and report which stage timed out.
+    #[arg(long, global = true, value_name = "SECS")]
+    timeout: Option<u64>,
+
+    /// Check the inputs and print what would be done — the audio's format
+    /// and chunking, the final image prompt, the enhancer's messages —
+    /// without loading a model or downloading anything.  For `image`,
+    /// `prompt` and `transcribe`.
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+    /// Put this invocation's files in a new directory under BASE (`runs`
+    /// when given as plain --run-dir), named after the UTC start time and
+    /// a short ID: `runs/2024-06-05_14-32-10_3fa9c1/`.  Relative --out /
+    /// --output paths land inside it, and transcripts are written there
+    /// instead of printed.  `run.json` records the command line, timings
+    /// and files; `config.toml` the config-file settings in effect.  See
+    /// `runs list`.
+    #[arg(
+        long,
+        global = true,
+        value_name = "BASE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = runs::DEFAULT_BASE
+    )]
+    run_dir: Option<PathBuf>,
+
     #[command(subcommand)]
     command: Command,
 }
@@ -25,35 +206,233 @@ enum Command {
     /// Generate an image using a diffusion model (FLUX.1-schnell).
     ///
     /// You can provide a fully-formed prompt with `--prompt`, or a short seed
-    /// with `--seed` which will be auto-enhanced by the prompt enhancer before
-    /// image generation.
+    /// prompt with `--seed-prompt` which will be auto-enhanced by the prompt
+    /// enhancer before image generation.
     ///
     /// Examples:
     ///   cargo run -- image
     ///   cargo run -- image --prompt "A cat riding a bicycle on the moon"
-    ///   cargo run -- image --seed "lonely astronaut, watercolor"
-    ///   cargo run -- image --seed "lonely astronaut" --model gemma-e2b
+    ///   cargo run -- image --seed-prompt "lonely astronaut, watercolor"
+    ///   cargo run -- image --seed-prompt "lonely astronaut" --model gemma-e2b
+    ///   cargo run -- image --seed-prompt "lonely astronaut" --fallback gemma-e2b,qwen2.5-1.5b
+    ///   cargo run -- image --seed-prompt "lonely astronaut" --candidates 4 --pick 2
+    ///   cargo run -- image --seed-prompt "lonely astronaut" --style watercolor
+    ///   cargo run -- image --width 1024 --height 1024 --num-images 4
+    ///   cargo run -- image --prompt "a fox" --out out/fox.png
+    ///   cargo run -- image --prompt "a fox" -n 6 --grid --out out/fox.png
+    ///   cargo run -- image --diffusion-model black-forest-labs/FLUX.1-dev --loader flux
     Image {
         /// A fully-formed prompt to use directly for image generation.
-        /// Mutually exclusive with --seed.
-        #[arg(short, long, conflicts_with = "seed")]
+        /// Mutually exclusive with --seed-prompt.
+        #[arg(short, long, conflicts_with = "seed_prompt")]
         prompt: Option<String>,
 
         /// A short seed prompt that will be enhanced by the prompt enhancer
         /// before being sent to the diffusion model.
-        /// Mutually exclusive with --prompt.
-        #[arg(short, long, conflicts_with = "prompt")]
-        seed: Option<String>,
+        /// Mutually exclusive with --prompt.  Repeat it with
+        /// --keep-enhancer to render several seeds in one run.
+        ///
+        /// (Formerly `--seed`, which still works.)
+        #[arg(short, long, alias = "seed", conflicts_with = "prompt")]
+        seed_prompt: Vec<String>,
+
+        /// How --seed-prompt is enhanced.
+        ///
+        /// Possible values:
+        ///   model    — a text model (see --model / --model-path) [default]
+        ///   template — fixed templates, no model or download
+        ///   remote   — an OpenAI-compatible API (see --remote-url /
+        ///              --remote-model); needs the remote-enhancer feature
+        #[arg(long, value_enum, default_value_t = EnhancerBackend::default(), hide_possible_values = true)]
+        enhancer: EnhancerBackend,
+
+        #[command(flatten)]
+        remote: RemoteArgs,
 
         /// Which text model to use for prompt enhancement.
-        /// Only used when --seed is provided.
+        /// Only used when --seed-prompt is provided.
         ///
         /// Possible values:
         ///   gemma-e2b    — Gemma 3n E2B, smallest (~1.5 GB Q4K), best for iPhone
         ///   gemma-e4b    — Gemma 3n E4B, balanced (~8 GB F16) [default]
         ///   phi-3.5-mini — Phi-3.5-mini, strongest quality (~2.8 GB Q4K)
+        ///   qwen2.5-1.5b — Qwen2.5-1.5B, very small and fast (~1.8 GB Q8_0)
+        ///   llama-3.2-3b — Llama 3.2 3B, gated: accept Meta's license (~2.3 GB Q4K)
         #[arg(short, long, value_enum)]
         model: Option<EnhancerModel>,
+
+        /// Load the text model from a local `.gguf` file or HuggingFace-layout
+        /// directory instead of a --model preset (works offline).
+        #[arg(long, value_name = "PATH", conflicts_with = "model")]
+        model_path: Option<PathBuf>,
+
+        #[command(flatten)]
+        load: LoadOptions,
+
+        #[command(flatten)]
+        sampler: SamplerArgs,
+
+        /// Seed the prompt enhancer's sampler to replay an earlier run.
+        /// Without it a random seed is used and printed.  The diffusion
+        /// noise isn't seeded (mistral.rs takes no seed for it), so the
+        /// image itself still differs between runs.
+        #[arg(long, value_name = "U64", requires = "seed_prompt")]
+        sampler_seed: Option<u64>,
+
+        /// Generate this many enhanced prompts from --seed-prompt and pick
+        /// one (see --pick).
+        #[arg(long, value_name = "N", requires = "seed_prompt")]
+        candidates: Option<usize>,
+
+        /// Which candidate (1-based) to send to the diffusion model.  If
+        /// omitted, you are asked interactively.
+        #[arg(long, value_name = "I", requires = "candidates")]
+        pick: Option<usize>,
+
+        /// Compose --seed-prompt with a named style before enhancement.
+        /// Built-in styles plus any in ~/.config/mistralrs-example/styles.toml;
+        /// an unknown name lists them.
+        #[arg(long, value_name = "NAME", requires = "seed_prompt")]
+        style: Option<String>,
+
+        /// Load the diffusion model only after the seed prompt is enhanced.
+        /// By default the two overlap when both models fit in memory.
+        #[arg(long, requires = "seed_prompt")]
+        sequential: bool,
+
+        /// Keep the prompt enhancer loaded next to the diffusion model and
+        /// render every --seed-prompt with both.  Needs the memory for both
+        /// models; by default the enhancer is released before FLUX loads.
+        #[arg(long, requires = "seed_prompt", conflicts_with = "sequential")]
+        keep_enhancer: bool,
+
+        /// Keep the diffusion model loaded and review each image: `r`
+        /// generates again, `e <text>` edits the prompt, `o <path>` saves a
+        /// copy, `q` quits (`h` lists the rest).  Images are numbered after
+        /// --out (default image.png): image_1.png, image_2.png, …  With
+        /// --keep-enhancer the enhancer stays loaded too and `e` takes a new
+        /// seed.
+        #[arg(long, conflicts_with = "grid")]
+        interactive: bool,
+
+        /// If the preset fails to load (a gated repo, too little memory, a
+        /// download error), try the next of a comma-separated list (names
+        /// as for --model); bare, gemma-e4b → gemma-e2b → phi-3.5-mini.
+        /// --model, when given, is tried first.  Each failure is printed
+        /// and the preset that loaded is reported.
+        #[arg(
+            long,
+            requires = "seed_prompt",
+            value_enum,
+            value_name = "PRESETS",
+            num_args = 0..=1,
+            value_delimiter = ',',
+            conflicts_with_all = ["model_path", "auto_downgrade"]
+        )]
+        fallback: Option<Vec<EnhancerModel>>,
+
+        /// Don't look up or store the enhanced prompt in
+        /// ~/.cache/mistralrs-example/prompts.json.  By default a run with
+        /// --sampler-seed reuses the prompt an earlier run enhanced from the
+        /// same seed, model and settings, without loading the enhancer.
+        #[arg(long, requires = "seed_prompt", conflicts_with = "refresh")]
+        no_cache: bool,
+
+        /// Enhance the seed again and replace its cached prompt.
+        #[arg(long, requires = "seed_prompt")]
+        refresh: bool,
+
+        /// HuggingFace ID of the diffusion model.  Defaults to
+        /// $MISTRALRS_EXAMPLE_DIFFUSION_MODEL, then
+        /// black-forest-labs/FLUX.1-schnell.
+        #[arg(long, value_name = "HF_ID")]
+        diffusion_model: Option<String>,
+
+        /// How mistral.rs loads the diffusion model.
+        #[arg(long, value_enum, default_value_t = LoaderChoice::FluxOffloaded)]
+        loader: LoaderChoice,
+
+        /// Weight dtype of the diffusion model (--dtype sets the enhancer's).
+        /// Defaults to bf16; some checkpoints want f16.
+        #[arg(long, value_enum, value_name = "DTYPE")]
+        diffusion_dtype: Option<DtypeChoice>,
+
+        /// Longest prompt, in tokens, the diffusion model's text encoder
+        /// takes.  Defaults to the model's own (77 for FLUX's CLIP encoder,
+        /// and for models it doesn't know); raise it for a model whose
+        /// encoder reads longer prompts.  The enhancer's word limit and
+        /// reply length follow it.
+        #[arg(long, value_name = "N", value_parser = prompt_limits::parse_max_tokens_arg)]
+        max_prompt_tokens: Option<usize>,
+
+        /// Image width in pixels (multiple of 16). Defaults to 1280.
+        #[arg(long)]
+        width: Option<usize>,
+
+        /// Image height in pixels (multiple of 16). Defaults to 720.
+        #[arg(long)]
+        height: Option<usize>,
+
+        /// Number of images to generate from the same prompt.
+        #[arg(short = 'n', long, default_value_t = 1)]
+        num_images: usize,
+
+        /// Save the generated PNG to this path (parent directories are
+        /// created). With --num-images > 1, `_1`, `_2`, … are appended
+        /// before the extension.
+        #[arg(short, long, value_name = "PATH")]
+        out: Option<PathBuf>,
+
+        /// Overwrite existing files at --out.
+        #[arg(long, requires = "out")]
+        force: bool,
+
+        /// Also compose the images into one contact sheet, `<out>_grid.png`
+        /// (next to the first image without --out).  Needs --num-images > 1.
+        #[arg(long)]
+        grid: bool,
+
+        /// Images per row in the --grid sheet.  Defaults to about square.
+        #[arg(long, value_name = "N", requires = "grid")]
+        grid_columns: Option<usize>,
+
+        /// Leave the prompt, image numbers and seed off the --grid sheet.
+        #[arg(long, requires = "grid")]
+        no_grid_caption: bool,
+
+        /// Render the prompt at several sizes in one run, loading the
+        /// diffusion model once: comma-separated WIDTHxHEIGHT, or one number
+        /// for a square (e.g. `1024,1920x1080,512x512`).  Each size is
+        /// saved as --out (default image.png) with `_WIDTHxHEIGHT` before
+        /// the extension; a size that fails doesn't stop the rest.
+        #[arg(
+            long,
+            value_name = "WxH,...",
+            value_delimiter = ',',
+            value_parser = image_generation::parse_size,
+            conflicts_with_all = ["width", "height", "interactive", "keep_enhancer"]
+        )]
+        sizes: Vec<ImageSize>,
+
+        /// Things the image should not contain.  Ignored (with a warning)
+        /// by the FLUX loaders.
+        #[arg(long)]
+        negative_prompt: Option<String>,
+
+        /// Classifier-free guidance scale.  Ignored (with a warning) by the
+        /// FLUX loaders.
+        #[arg(long)]
+        guidance: Option<f64>,
+
+        /// Don't write `<image>.json` next to each image.  By default it
+        /// records the seed, enhancer, final prompt, model and size.
+        #[arg(long)]
+        no_metadata: bool,
+
+        /// Also embed the metadata in each PNG as a tEXt chunk
+        /// (keyword `mistralrs-example`).
+        #[arg(long)]
+        embed_metadata: bool,
     },
 
     /// Enhance a short prompt into a detailed image-generation prompt
@@ -61,14 +440,26 @@ enum Command {
     ///
     /// Examples:
     ///   cargo run -- prompt
-    ///   cargo run -- prompt --seed "A lonely astronaut, watercolor"
+    ///   cargo run -- prompt --seed-prompt "A lonely astronaut, watercolor"
     ///   cargo run -- prompt --model gemma-e2b
-    ///   cargo run -- prompt --model phi-3.5-mini --seed "cyberpunk city"
+    ///   cargo run -- prompt --model phi-3.5-mini --seed-prompt "cyberpunk city"
+    ///   cargo run -- prompt --model-path ~/models/gemma-3n-e2b-q4_k_m.gguf --seed-prompt "foo"
+    ///   cargo run -- prompt --model gemma-e4b --isq q4k
+    ///   cargo run -- prompt --seed-prompt "castle at dusk" --compare gemma-e2b,phi-3.5-mini
+    ///   cargo run -- prompt --seed-prompt "castle at dusk" --fallback
+    ///   cargo run -- prompt --seed-prompt "cyberpunk city" --candidates 5
+    ///   cargo run -- prompt --seed-prompt "cyberpunk city" --temperature 0.6 --top-p 0.9
+    ///   cargo run -- prompt --seed-prompt "castle at dusk" --interactive
+    ///   cargo run -- prompt --seed-prompt "castle at dusk" --style noir
+    ///   cargo run -- prompt --from-audio song.mp3 --style noir
+    ///   cargo run -- prompt --seed-prompt "castle at dusk" --copy
     Prompt {
         /// The seed prompt to enhance.
         /// If omitted a default seed is used.
-        #[arg(short, long)]
-        seed: Option<String>,
+        ///
+        /// (Formerly `--seed`, which still works.)
+        #[arg(short, long, alias = "seed")]
+        seed_prompt: Option<String>,
 
         /// Which text model to use for prompt enhancement.
         ///
@@ -76,8 +467,220 @@ enum Command {
         ///   gemma-e2b    — Gemma 3n E2B, smallest (~1.5 GB Q4K), best for iPhone
         ///   gemma-e4b    — Gemma 3n E4B, balanced (~8 GB F16) [default]
         ///   phi-3.5-mini — Phi-3.5-mini, strongest quality (~2.8 GB Q4K)
+        ///   qwen2.5-1.5b — Qwen2.5-1.5B, very small and fast (~1.8 GB Q8_0)
+        ///   llama-3.2-3b — Llama 3.2 3B, gated: accept Meta's license (~2.3 GB Q4K)
+        #[arg(short, long, value_enum)]
+        model: Option<EnhancerModel>,
+
+        /// Load the text model from a local `.gguf` file or HuggingFace-layout
+        /// directory instead of a --model preset (works offline).
+        #[arg(long, value_name = "PATH", conflicts_with = "model")]
+        model_path: Option<PathBuf>,
+
+        #[command(flatten)]
+        load: LoadOptions,
+
+        #[command(flatten)]
+        sampler: SamplerArgs,
+
+        #[command(flatten)]
+        system: SystemPromptArgs,
+
+        /// Send three built-in examples (seed → enhanced prompt) before the
+        /// seed, so the output's structure varies less between runs.  Adds
+        /// about 250 tokens to every request.
+        #[arg(long)]
+        few_shot: bool,
+
+        /// Send your own examples instead: a `.toml` file with one
+        /// `[[examples]]` table per example, or `.json` with an `examples`
+        /// array, each with `seed` and `enhanced` strings.  Examples past
+        /// about 600 tokens in total are dropped with a warning.
+        #[arg(long, value_name = "PATH", conflicts_with = "few_shot")]
+        examples_file: Option<PathBuf>,
+
+        /// Seed the sampler to replay an earlier run: the same seed prompt,
+        /// model and settings give the same result.  Without it a random
+        /// seed is used and printed (and recorded in --json output).
+        #[arg(long, value_name = "U64")]
+        sampler_seed: Option<u64>,
+
+        /// Generate this many distinct enhanced prompts and print them all.
+        #[arg(long, value_name = "N")]
+        candidates: Option<usize>,
+
+        /// Keep the model loaded and refine the enhanced prompt with
+        /// follow-up instructions (`/use`, `/image`, `/reset`, `/help`).
+        #[arg(short, long, conflicts_with = "candidates")]
+        interactive: bool,
+
+        /// Compose the seed with a named style before enhancement.
+        /// Built-in styles plus any in ~/.config/mistralrs-example/styles.toml;
+        /// an unknown name lists them.
+        #[arg(long, value_name = "NAME")]
+        style: Option<String>,
+
+        /// Seed from an audio file's tags instead: "<title> by <artist>,
+        /// <genre>" from its ID3 / Vorbis comment / RIFF INFO tags, or the
+        /// file name when it has no title tag.
+        #[arg(long, value_name = "PATH", conflicts_with = "seed_prompt")]
+        from_audio: Option<PathBuf>,
+
+        /// Enhance the seed with several presets, one loaded at a time, and
+        /// compare load time, enhance time, length and the prompts.  Takes
+        /// a comma-separated list (names as for --model); bare, every preset.
+        /// All use the same sampler settings and sampler seed.
+        #[arg(
+            long,
+            value_enum,
+            value_name = "PRESETS",
+            num_args = 0..=1,
+            value_delimiter = ',',
+            conflicts_with_all = ["model", "model_path", "interactive", "candidates"]
+        )]
+        compare: Option<Vec<EnhancerModel>>,
+
+        /// If the preset fails to load (a gated repo, too little memory, a
+        /// download error), try the next of a comma-separated list (names
+        /// as for --model); bare, gemma-e4b → gemma-e2b → phi-3.5-mini.
+        /// --model, when given, is tried first.  Each failure is printed
+        /// and the preset that loaded is reported.
+        #[arg(
+            long,
+            value_enum,
+            value_name = "PRESETS",
+            num_args = 0..=1,
+            value_delimiter = ',',
+            conflicts_with_all = ["model_path", "compare", "auto_downgrade"]
+        )]
+        fallback: Option<Vec<EnhancerModel>>,
+
+        /// After loading, send a throwaway one-token request so kernel
+        /// compilation and cache setup stay out of the enhance time.  Its
+        /// own duration is printed as "Warm-up".
+        #[arg(long)]
+        warmup: bool,
+
+        /// Keep the --interactive refinement as a named session in
+        /// ~/.local/state/mistralrs-example/sessions/, saved after every
+        /// revision.  An existing session is resumed where it left off,
+        /// and its seed is used instead of this run's.
+        #[arg(long, value_name = "NAME", requires = "interactive")]
+        session: Option<String>,
+
+        /// Also put the enhanced prompt (the first, with --candidates) on
+        /// the system clipboard.  Needs a build with `--features
+        /// clipboard`; if the clipboard can't be reached, a warning is
+        /// printed instead.
+        #[arg(long, conflicts_with_all = ["interactive", "compare"])]
+        copy: bool,
+    },
+
+    /// Summarize text (a transcript, lyrics, notes) with the same text
+    /// model presets as the prompt enhancer.
+    ///
+    /// Long texts are summarized in chunks first, then the chunk summaries
+    /// are combined, so input length is not bounded by the model's context.
+    ///
+    /// Examples:
+    ///   cargo run -- summarize lyrics.txt
+    ///   cargo run -- summarize notes.md --style bullet --max-words 60
+    ///   cargo run -- summarize --text "..." --model qwen2.5-1.5b
+    ///   cargo run -- transcribe vocals.wav --quiet | cargo run -- summarize --style theme
+    Summarize {
+        /// Text file to summarize, or `-` for stdin.  Without it, piped
+        /// stdin is read.
+        #[arg(value_name = "FILE", conflicts_with = "text")]
+        input: Option<PathBuf>,
+
+        /// Summarize this text instead of a file.
+        #[arg(long)]
+        text: Option<String>,
+
+        /// Shape of the summary.
+        ///
+        /// Possible values:
+        ///   bullet    — short bullet points, one per line
+        ///   paragraph — one paragraph of prose [default]
+        ///   theme     — the central theme, mood and imagery (e.g. of lyrics)
+        #[arg(long, value_enum, default_value_t = SummaryStyle::default())]
+        style: SummaryStyle,
+
+        /// Longest summary, in words.
+        #[arg(long, value_name = "N", default_value_t = summarizer::DEFAULT_MAX_WORDS)]
+        max_words: usize,
+
+        /// Summarize texts longer than this many words in chunks of this
+        /// size, then combine the chunk summaries.
+        #[arg(long, value_name = "N", default_value_t = summarizer::DEFAULT_CHUNK_WORDS)]
+        chunk_words: usize,
+
+        /// Which text model to use for summarization.
+        ///
+        /// Possible values:
+        ///   gemma-e2b    — Gemma 3n E2B, smallest (~1.5 GB Q4K), best for iPhone
+        ///   gemma-e4b    — Gemma 3n E4B, balanced (~8 GB F16) [default]
+        ///   phi-3.5-mini — Phi-3.5-mini, strongest quality (~2.8 GB Q4K)
+        ///   qwen2.5-1.5b — Qwen2.5-1.5B, very small and fast (~1.8 GB Q8_0)
+        ///   llama-3.2-3b — Llama 3.2 3B, gated: accept Meta's license (~2.3 GB Q4K)
         #[arg(short, long, value_enum)]
         model: Option<EnhancerModel>,
+
+        /// Load the text model from a local `.gguf` file or HuggingFace-layout
+        /// directory instead of a --model preset (works offline).
+        #[arg(long, value_name = "PATH", conflicts_with = "model")]
+        model_path: Option<PathBuf>,
+
+        #[command(flatten)]
+        load: LoadOptions,
+
+        #[command(flatten)]
+        sampler: SamplerArgs,
+    },
+
+    /// Turn text into embedding vectors, one per input line, e.g. to
+    /// cluster enhanced prompts or transcripts by similarity.
+    ///
+    /// Uses a dedicated embedding model: the chat presets of `prompt` and
+    /// `summarize` only generate text, so they can't embed.
+    ///
+    /// Examples:
+    ///   cargo run -- embed prompts.txt > prompts.jsonl
+    ///   cargo run -- embed prompts.txt --format npy --out prompts.npy
+    ///   cargo run -- transcribe vocals.wav --quiet | cargo run -- embed --model embedding-gemma
+    Embed {
+        /// Text file with one text per line (blank lines are skipped), or
+        /// `-` for stdin.  Without it, piped stdin is read.
+        #[arg(value_name = "FILE")]
+        input: Option<PathBuf>,
+
+        /// Which embedding model to use.
+        ///
+        /// Possible values:
+        ///   qwen3-embedding — Qwen3-Embedding 0.6B, 1024 dimensions, multilingual [default]
+        ///   embedding-gemma — EmbeddingGemma 300M, 768 dimensions; gated: accept Google's license
+        #[arg(short, long, value_name = "MODEL", value_parser = embeddings::parse_source, default_value = "qwen3-embedding")]
+        model: EmbedSource,
+
+        /// HuggingFace ID of another embedding model mistral.rs supports.
+        #[arg(long, value_name = "ID", conflicts_with = "model")]
+        model_id: Option<String>,
+
+        /// Output format.
+        ///
+        /// Possible values:
+        ///   jsonl — one {"text": …, "embedding": […]} object per line [default]
+        ///   npy   — a NumPy array of float32 rows, in input order
+        #[arg(long, value_enum, default_value_t = EmbedFormat::default())]
+        format: EmbedFormat,
+
+        /// Write to this file instead of stdout.
+        #[arg(short, long, value_name = "PATH")]
+        out: Option<PathBuf>,
+
+        /// Texts sent to the model per request.
+        #[arg(long, value_name = "N", default_value_t = embeddings::DEFAULT_BATCH_SIZE)]
+        batch_size: usize,
     },
 
     /// Transcribe audio using Gemma 3n's conformer audio encoder.
@@ -92,10 +695,38 @@ enum Command {
     ///   cargo run -- transcribe vocals.wav
     ///   cargo run -- transcribe separated/vocals.wav --model gemma-e2b
     ///   cargo run -- transcribe song.mp3 --user-prompt "Transcribe the singing lyrics"
+    ///   cargo run -- transcribe vocals.wav --output-format srt --output vocals.srt
+    ///   cargo run -- transcribe vocals.wav --language ja --translate
+    ///   cargo run -- transcribe vocals.wav --vad --output-format srt
+    ///   cargo run -- transcribe quiet-vocals.wav --normalize
+    ///   cargo run -- transcribe lead.wav backing.wav --tracks
+    ///   cargo run -- transcribe vocals.wav --interactive
+    ///   cargo run -- transcribe separated/ --watch --output-format srt
+    ///   cargo run -- transcribe diff before.txt after.json
+    ///
+    /// Pass `-` to read the audio from stdin, e.g. straight from ffmpeg or
+    /// demucs.  Headerless PCM needs --raw-pcm and its layout:
+    ///   ffmpeg -i song.mp3 -f f32le -ac 1 -ar 16000 - | \
+    ///     cargo run -- transcribe - --raw-pcm --sample-rate 16000 --channels 1 --format f32le
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
     Transcribe {
-        /// Path to the audio file to transcribe.
-        #[arg(value_name = "AUDIO_FILE")]
-        audio_path: PathBuf,
+        #[command(subcommand)]
+        action: Option<TranscribeAction>,
+
+        /// Audio file(s) to transcribe, or `-` for stdin.  Several files
+        /// are decoded in parallel (see --jobs) and reported in the order
+        /// given, or sent together with --tracks.  With --watch, the
+        /// directory to watch.
+        #[arg(value_name = "AUDIO_FILE", required = true)]
+        audio_paths: Vec<PathBuf>,
+
+        /// Keep running and transcribe each WAV / MP3 / OGG / FLAC file
+        /// that lands in the AUDIO_FILE directory, once it has finished
+        /// being written.  Transcriptions go next to the audio as
+        /// `<stem>.<txt|srt|vtt|json>`; files listed in the directory's
+        /// `.transcribed.json` are skipped.  Stop with Ctrl-C.
+        #[arg(long, conflicts_with_all = ["output", "jobs", "jobs_models", "raw_pcm"])]
+        watch: bool,
 
         /// Which Gemma 3n variant to use.
         ///
@@ -105,10 +736,476 @@ enum Command {
         #[arg(short, long, value_enum)]
         model: Option<TranscriptionModel>,
 
+        /// Load Gemma 3n from a local HuggingFace-layout directory instead of
+        /// a --model preset (works offline).
+        #[arg(long, value_name = "PATH", conflicts_with = "model")]
+        model_path: Option<PathBuf>,
+
+        #[command(flatten)]
+        load: LoadOptions,
+
         /// Custom instruction to send alongside the audio.
         /// If omitted, a default transcription prompt is used.
         #[arg(short, long)]
         user_prompt: Option<String>,
+
+        #[command(flatten)]
+        system: SystemPromptArgs,
+
+        /// Language of the audio, as an ISO 639-1 code (e.g. `ja`) or a
+        /// name.  Steers the model when the language is known up front.
+        #[arg(long, value_name = "CODE")]
+        language: Option<String>,
+
+        /// Produce an English translation instead of a verbatim transcript.
+        /// A custom --user-prompt takes precedence.
+        #[arg(long)]
+        translate: bool,
+
+        /// Send the decoded audio as-is, without mixing down to mono,
+        /// resampling to 16 kHz or trimming leading / trailing silence.
+        #[arg(long)]
+        no_preprocess: bool,
+
+        /// Level (dBFS) below which leading / trailing audio is trimmed as
+        /// silence (default -50).
+        #[arg(
+            long,
+            value_name = "DBFS",
+            allow_negative_numbers = true,
+            conflicts_with = "no_preprocess"
+        )]
+        silence_threshold: Option<f32>,
+
+        /// Bring quiet audio, such as a demucs vocal stem, up to a target
+        /// level before inference.  A soft limiter keeps the gain from
+        /// clipping; audio already within 1 dB of the target is left alone.
+        #[arg(long, conflicts_with = "no_preprocess")]
+        normalize: bool,
+
+        /// What --normalize measures:
+        ///   rms  — the average level of the audible frames [default]
+        ///   peak — the loudest sample
+        #[arg(long, value_enum, value_name = "MODE", requires = "normalize")]
+        normalize_mode: Option<NormalizeMode>,
+
+        /// Level --normalize aims for, in dBFS (default -20 for rms, -1 for
+        /// peak).
+        #[arg(
+            long,
+            value_name = "DBFS",
+            allow_negative_numbers = true,
+            requires = "normalize"
+        )]
+        normalize_target: Option<f32>,
+
+        /// Split the audio on detected silences and transcribe each speech
+        /// segment separately.  Gives per-segment timestamps in SRT / VTT
+        /// output.
+        #[arg(long)]
+        vad: bool,
+
+        /// Level (dBFS) at which --vad detects the start of speech
+        /// (default -40).  Raise it for noisy stems.
+        #[arg(
+            long,
+            value_name = "DBFS",
+            allow_negative_numbers = true,
+            requires = "vad"
+        )]
+        vad_threshold: Option<f32>,
+
+        /// Ask the model which language each --vad segment is in before
+        /// transcribing it, for stems that switch languages.  The code is
+        /// shown per cue in SRT / VTT and per segment in JSON.  One extra
+        /// request per segment.
+        #[arg(long, requires = "vad", conflicts_with = "language")]
+        detect_language: bool,
+
+        /// Before transcribing, ask the model whether the first 20 seconds
+        /// (after leading silence) contain singing or speech.  On "no",
+        /// report "No vocals detected" and write an empty transcript
+        /// instead of lyrics the model would make up.  On by default; one
+        /// extra short request per file (not with --tracks).
+        #[arg(long, overrides_with = "no_check_vocals")]
+        check_vocals: bool,
+
+        /// Skip the vocal check and always transcribe.
+        #[arg(long)]
+        no_check_vocals: bool,
+
+        /// Transcribe even when the vocal check hears no voice.  The
+        /// check's answer is still reported.
+        #[arg(long, conflicts_with = "no_check_vocals")]
+        force: bool,
+
+        /// Get SRT / VTT timings without --vad by asking the model for them.
+        ///
+        /// Possible values:
+        ///   inline — the model starts each line with a `[mm:ss]` marker,
+        ///            parsed into one cue per line.  Approximate; a reply
+        ///            without usable markers falls back to one cue.
+        #[arg(long, value_enum, value_name = "MODE", conflicts_with = "vad")]
+        timestamps: Option<TimestampMode>,
+
+        /// Reshape the transcript after the model writes it.  The model's
+        /// own text stays in the JSON output as `raw_text`.
+        ///
+        /// Possible values:
+        ///   lyrics — merge short lines, mark repeated lines with (x2),
+        ///            straighten quotes and dashes, and drop stray
+        ///            punctuation and trailing periods
+        #[arg(long, value_enum, value_name = "FORMAT")]
+        text_format: Option<TextFormat>,
+
+        /// With --text-format lyrics, merge lines shorter than this many
+        /// characters with their neighbour (0 keeps every line).
+        #[arg(
+            long,
+            value_name = "N",
+            default_value_t = transcript_format::DEFAULT_MIN_LINE_CHARS,
+            requires = "text_format"
+        )]
+        min_line_chars: usize,
+
+        /// Send every AUDIO_FILE in one request as time-aligned tracks of
+        /// one recording, e.g. lead and backing vocal stems, and get a
+        /// single transcript.
+        #[arg(long, conflicts_with_all = ["watch", "vad", "timestamps"])]
+        tracks: bool,
+
+        /// With --tracks and --no-preprocess, resample tracks to the first
+        /// one's sample rate instead of rejecting mismatched rates.
+        #[arg(long, requires = "tracks")]
+        resample_tracks: bool,
+
+        /// After printing the transcript, take follow-up questions about
+        /// the audio ("what language is the second verse?") in the same
+        /// conversation.  `/retranscribe <instruction>` asks again with a
+        /// new instruction; `/done` exits.
+        #[arg(short, long, conflicts_with = "watch")]
+        interactive: bool,
+
+        /// Mask profanity in the transcript (`Shit` → `S***`).  Whole words
+        /// only, case-insensitive; SRT / VTT timings are unchanged.
+        #[arg(long)]
+        censor: bool,
+
+        /// Extra words to mask with --censor, one per line (`#` starts a
+        /// comment).  Added to the built-in list.
+        #[arg(long, value_name = "PATH", requires = "censor")]
+        censor_list: Option<PathBuf>,
+
+        /// Approximate lyrics for the song (e.g. from a lyrics site), sent
+        /// with the audio for the model to correct rather than transcribe
+        /// from scratch.  Lines that aren't sung should be left out.  Not
+        /// with several files unless --tracks.
+        #[arg(long, value_name = "PATH", conflicts_with = "watch")]
+        lyrics_hint: Option<PathBuf>,
+
+        /// Keep at most this many tokens of --lyrics-hint (about four
+        /// characters each); a longer hint is cut at a line end, with a
+        /// warning.
+        #[arg(
+            long,
+            value_name = "N",
+            default_value_t = audio_transcription::DEFAULT_REFERENCE_TOKENS,
+            requires = "lyrics_hint"
+        )]
+        lyrics_hint_tokens: usize,
+
+        /// Output format for the transcription.
+        ///
+        /// Possible values:
+        ///   text — plain transcription text [default]
+        ///   srt  — SubRip subtitles
+        ///   vtt  — WebVTT subtitles
+        ///   lrc  — LRC lyrics for music players, with title/artist tags
+        ///   json — full result including timings, RTF and sample rate
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        output_format: OutputFormat,
+
+        /// Write the rendered transcription to this file instead of stdout.
+        /// With several input files, a directory: each transcription goes
+        /// to `<PATH>/<stem>.<txt|srt|vtt|lrc|json>`.
+        #[arg(short, long, value_name = "PATH")]
+        output: Option<PathBuf>,
+
+        /// With several input files, how many to read, decode and
+        /// preprocess at once while the model transcribes.
+        #[arg(long, value_name = "N", default_value_t = 1)]
+        jobs: usize,
+
+        /// With several input files, load this many independent copies of
+        /// the model and transcribe that many files at once.  Fewer are
+        /// loaded if they don't all fit in memory.
+        #[arg(long, value_name = "N", default_value_t = 1)]
+        jobs_models: usize,
+
+        /// The input is headerless interleaved PCM (e.g. ffmpeg's `-f f32le`)
+        /// rather than a container symphonia can probe.  Needs
+        /// --sample-rate.
+        #[arg(long, requires = "sample_rate")]
+        raw_pcm: bool,
+
+        /// Sample rate of the --raw-pcm input, in Hz.
+        #[arg(long, value_name = "HZ", requires = "raw_pcm")]
+        sample_rate: Option<u32>,
+
+        /// Number of interleaved channels in the --raw-pcm input.
+        #[arg(long, value_name = "N", default_value_t = 1, requires = "raw_pcm")]
+        channels: u16,
+
+        /// Sample encoding of the --raw-pcm input.
+        #[arg(long, value_enum, default_value_t = PcmFormat::F32le, requires = "raw_pcm")]
+        format: PcmFormat,
+
+        /// Also write the audio exactly as the model receives it (after
+        /// downmix, resampling and trimming) to this WAV file, to tell
+        /// whether decoding or the model garbled a transcript.  With --vad
+        /// or --tracks, one file per chunk: `<stem>_001.wav`, …
+        #[arg(long, value_name = "PATH.wav", conflicts_with = "watch")]
+        dump_audio: Option<PathBuf>,
+
+        /// Sample encoding of the --dump-audio file: f32le keeps the exact
+        /// samples, s16le is a 16-bit WAV more tools can open.
+        #[arg(long, value_enum, value_name = "FORMAT", default_value_t = PcmFormat::F32le, requires = "dump_audio")]
+        dump_format: PcmFormat,
+
+        /// After loading, transcribe half a second of silence so kernel
+        /// compilation and cache setup stay out of the inference time and
+        /// real-time factor.  Its own duration is printed as "Warm-up".
+        #[arg(long, conflicts_with = "watch")]
+        warmup: bool,
+
+        #[command(flatten)]
+        decoding: DecodingArgs,
+    },
+
+    /// Transcribe a few segments of an existing SRT / JSON transcript again,
+    /// leaving the rest as it is.
+    ///
+    /// Only the audio of the chosen segments is decoded out and sent to the
+    /// model, so fixing two bad lines doesn't mean redoing the whole song.
+    /// Pick segments by number (the SRT cue numbers) and/or by time range;
+    /// a range replaces every segment it overlaps, or adds a segment when
+    /// it falls in a gap.  The updated transcript keeps the input's format.
+    ///
+    /// Examples:
+    ///   cargo run -- retranscribe vocals.wav vocals.srt --segments 4,9-10 -o vocals.fixed.srt
+    ///   cargo run -- retranscribe vocals.wav vocals.json --range 1:02-1:15 --user-prompt "Japanese lyrics"
+    Retranscribe {
+        /// The audio the transcript was made from.
+        #[arg(value_name = "AUDIO_FILE")]
+        audio_path: PathBuf,
+
+        /// SRT or JSON transcript written by `transcribe` with --vad or
+        /// --timestamps inline.
+        #[arg(value_name = "TRANSCRIPT")]
+        transcript: PathBuf,
+
+        /// Segments to transcribe again, 1-based: `4`, `4,9` or `9-12`.
+        #[arg(long, value_name = "LIST", value_parser = retranscribe::parse_indices_arg, required_unless_present = "range")]
+        segments: Option<SegmentList>,
+
+        /// Stretch of time to transcribe again, e.g. `1:02-1:15` or
+        /// `62-75.5`.  Repeat for several.
+        #[arg(long, value_name = "START-END", value_parser = retranscribe::parse_range_arg)]
+        range: Vec<TimeRange>,
+
+        /// Which Gemma 3n variant to use.  gemma-e4b is the higher-effort
+        /// choice for segments gemma-e2b got wrong.
+        #[arg(short, long, value_enum)]
+        model: Option<TranscriptionModel>,
+
+        /// Load Gemma 3n from a local HuggingFace-layout directory instead of
+        /// a --model preset (works offline).
+        #[arg(long, value_name = "PATH", conflicts_with = "model")]
+        model_path: Option<PathBuf>,
+
+        #[command(flatten)]
+        load: LoadOptions,
+
+        /// Instruction sent with each segment instead of the default, e.g.
+        /// to name the language or words the first pass got wrong.
+        #[arg(short, long)]
+        user_prompt: Option<String>,
+
+        #[command(flatten)]
+        system: SystemPromptArgs,
+
+        /// Language of the audio, as an ISO 639-1 code (e.g. `ja`) or a
+        /// name.
+        #[arg(long, value_name = "CODE")]
+        language: Option<String>,
+
+        /// Send each segment's audio as-is, without mixing down to mono,
+        /// resampling to 16 kHz or trimming silence.
+        #[arg(long)]
+        no_preprocess: bool,
+
+        /// Write the updated transcript to this file instead of stdout.
+        #[arg(short, long, value_name = "PATH")]
+        output: Option<PathBuf>,
+
+        #[command(flatten)]
+        decoding: DecodingArgs,
+    },
+
+    /// Score a transcript against a reference text by word error rate.
+    ///
+    /// Both sides are normalized (lowercased, punctuation stripped, tags
+    /// like `[inaudible]` removed) and aligned word by word.  Prints the WER,
+    /// the substitution / deletion / insertion counts and a word-level diff
+    /// with deletions in red and insertions in green.  The transcript can be
+    /// plain text, a `transcribe` JSON result or SRT; with --audio the audio
+    /// is transcribed first.
+    ///
+    /// Examples:
+    ///   cargo run -- wer vocals.json --reference lyrics.txt
+    ///   cargo run -- wer vocals.srt --reference lyrics.txt --keep-case --no-color
+    ///   cargo run --release -- wer --audio vocals.wav --model gemma-e2b --reference lyrics.txt
+    Wer {
+        /// Transcript to score.
+        #[arg(
+            value_name = "TRANSCRIPT",
+            required_unless_present = "audio",
+            conflicts_with = "audio"
+        )]
+        hypothesis: Option<PathBuf>,
+
+        /// Ground-truth text, e.g. the published lyrics.  Plain text, JSON
+        /// or SRT.
+        #[arg(short, long, value_name = "FILE")]
+        reference: PathBuf,
+
+        /// Transcribe this audio and score the result instead of a saved
+        /// transcript.
+        #[arg(long, value_name = "AUDIO_FILE")]
+        audio: Option<PathBuf>,
+
+        /// Which Gemma 3n variant transcribes --audio.
+        #[arg(short, long, value_enum, conflicts_with = "hypothesis")]
+        model: Option<TranscriptionModel>,
+
+        #[command(flatten)]
+        load: LoadOptions,
+
+        /// Count differences in letter case as errors.
+        #[arg(long)]
+        keep_case: bool,
+
+        /// Count differences in punctuation as errors.
+        #[arg(long)]
+        keep_punctuation: bool,
+
+        /// Keep bracketed tags like `[inaudible]` or `(chorus)` as words.
+        #[arg(long)]
+        keep_tags: bool,
+
+        /// Mark changes with `[-old-]` / `{+new+}` instead of ANSI colors.
+        #[arg(long)]
+        no_color: bool,
+    },
+
+    /// Describe one or more images with Gemma 3n's vision encoder.
+    ///
+    /// All images go into a single request, so the instruction can compare
+    /// them.  Handy for checking what `image` produced.
+    ///
+    /// Examples:
+    ///   cargo run -- describe --image photo.jpg
+    ///   cargo run -- describe --image out/fox.png --user-prompt "Does this match: a fox in snow?"
+    ///   cargo run -- describe --image a.png --image b.png --user-prompt "Which is sharper?"
+    #[command(alias = "describe-image")]
+    Describe {
+        /// PNG or JPEG to describe.  Repeat for several images.
+        #[arg(long = "image", value_name = "PATH", required = true)]
+        images: Vec<PathBuf>,
+
+        /// Which Gemma 3n variant to use.
+        ///
+        /// Possible values:
+        ///   gemma-e2b — Gemma 3n E2B, smallest (~1.5 GB Q4K), fastest
+        ///   gemma-e4b — Gemma 3n E4B, balanced (~8 GB F16) [default]
+        #[arg(short, long, value_enum)]
+        model: Option<TranscriptionModel>,
+
+        /// Load Gemma 3n from a local HuggingFace-layout directory instead of
+        /// a --model preset (works offline).
+        #[arg(long, value_name = "PATH", conflicts_with = "model")]
+        model_path: Option<PathBuf>,
+
+        #[command(flatten)]
+        load: LoadOptions,
+
+        /// Instruction sent with the images.
+        /// Defaults to "Describe this image in detail."
+        #[arg(short, long)]
+        user_prompt: Option<String>,
+
+        #[command(flatten)]
+        sampler: SamplerArgs,
+    },
+
+    /// Turn a vocal stem into cover art: transcribe the lyrics, distill them
+    /// into an image prompt, then render it with FLUX.
+    ///
+    /// Models are loaded one stage at a time to bound peak memory.
+    /// `lyrics.txt`, `prompt.txt` and `artwork.png` are written to the
+    /// output directory as each stage finishes.  Lyrics too long to prompt
+    /// from whole are first summarized with the enhancer model into
+    /// `summary.txt`.
+    ///
+    /// Examples:
+    ///   cargo run -- lyrics-art vocals.wav
+    ///   cargo run -- lyrics-art vocals.wav --style "ukiyo-e woodblock print" --out-dir art/
+    #[command(alias = "pipeline")]
+    LyricsArt {
+        /// Vocal stem (or full song) to take the lyrics from.
+        #[arg(value_name = "AUDIO_FILE")]
+        audio_path: PathBuf,
+
+        /// Visual style for the artwork, e.g. "watercolor" or "synthwave".
+        #[arg(long)]
+        style: Option<String>,
+
+        /// Gemma 3n variant used to transcribe the lyrics.
+        #[arg(long, value_enum, default_value_t = TranscriptionModel::default())]
+        transcription_model: TranscriptionModel,
+
+        /// Text model preset used to write the image prompt.
+        #[arg(long, value_enum, default_value_t = EnhancerModel::default())]
+        enhancer_model: EnhancerModel,
+
+        /// `template` writes the image prompt from fixed templates instead
+        /// of --enhancer-model; `remote` asks an OpenAI-compatible API (see
+        /// --remote-url / --remote-model).
+        #[arg(long, value_enum, default_value_t = EnhancerBackend::default())]
+        enhancer: EnhancerBackend,
+
+        #[command(flatten)]
+        remote: RemoteArgs,
+
+        #[command(flatten)]
+        load: LoadOptions,
+
+        /// Directory for the lyrics, prompt and artwork
+        /// (default: `<AUDIO_FILE stem>-art`).
+        #[arg(long, value_name = "DIR")]
+        out_dir: Option<PathBuf>,
+
+        /// Artwork width in pixels (multiple of 16).
+        #[arg(long)]
+        width: Option<usize>,
+
+        /// Artwork height in pixels (multiple of 16).
+        #[arg(long)]
+        height: Option<usize>,
+
+        /// Overwrite an existing artwork.png.
+        #[arg(long)]
+        force: bool,
     },
 
     /// Start an interactive CLI chat with the same model presets used by
@@ -118,6 +1215,7 @@ enum Command {
     ///   cargo run -- chat
     ///   cargo run -- chat --model gemma-e2b
     ///   cargo run -- chat --model phi-3.5-mini
+    ///   cargo run -- chat --system-prompt "You are a terse Rust reviewer." --temperature 0.2
     Chat {
         /// Which chat model preset to use.
         ///
@@ -125,27 +1223,1235 @@ enum Command {
         ///   gemma-e2b    — Gemma 3n E2B, smallest (~1.5 GB Q4K), best for iPhone
         ///   gemma-e4b    — Gemma 3n E4B, balanced (~8 GB F16) [default]
         ///   phi-3.5-mini — Phi-3.5-mini, strongest quality (~2.8 GB Q4K)
+        ///   qwen2.5-1.5b — Qwen2.5-1.5B, very small and fast (~1.8 GB Q8_0)
+        ///   llama-3.2-3b — Llama 3.2 3B, gated: accept Meta's license (~2.3 GB Q4K)
         #[arg(short, long, value_enum)]
         model: Option<EnhancerModel>,
+
+        /// Load the text model from a local `.gguf` file or HuggingFace-layout
+        /// directory instead of a --model preset (works offline).
+        #[arg(long, value_name = "PATH", conflicts_with = "model")]
+        model_path: Option<PathBuf>,
+
+        #[command(flatten)]
+        load: LoadOptions,
+
+        #[command(flatten)]
+        system: SystemPromptArgs,
+
+        #[command(flatten)]
+        sampler: SamplerArgs,
+
+        /// Token budget for the system prompt plus replayed history
+        /// (default 4096, estimated at ~4 characters per token).  The oldest
+        /// exchanges are dropped once it's exceeded.
+        #[arg(long)]
+        max_history_tokens: Option<usize>,
+
+        /// After loading, send a throwaway one-token request so the first
+        /// reply doesn't pay for kernel compilation and cache setup.
+        #[arg(long)]
+        warmup: bool,
+
+        /// Keep the conversation as a named session in
+        /// ~/.local/state/mistralrs-example/sessions/, saved after every
+        /// turn.  An existing session is resumed with its history, system
+        /// prompt and sampling settings.  A session can only be open in one
+        /// process at a time.
+        #[arg(long, value_name = "NAME")]
+        session: Option<String>,
+    },
+
+    /// Compare model presets on one task: load time, latency, tokens/sec,
+    /// time-to-first-token, peak RSS and (for transcription) real-time
+    /// factor.
+    ///
+    /// Each preset is loaded in turn, runs the warmup iterations, then the
+    /// measured ones, and is dropped before the next one loads.
+    ///
+    /// Examples:
+    ///   cargo run --release -- bench prompt
+    ///   cargo run --release -- bench chat --models gemma-e2b,phi-3.5-mini --iterations 5
+    ///   cargo run --release -- bench transcribe --audio vocals.wav --report bench.csv
+    ///   cargo run --release -- bench transcribe --audio vocals.wav --reference lyrics.txt
+    ///   cargo run --release -- bench prompt --history bench.jsonl
+    ///   cargo run --release -- bench report bench.jsonl
+    Bench {
+        /// Workload to measure
+        #[arg(value_enum)]
+        task: BenchTask,
+
+        /// History file to summarize with `bench report`.
+        #[arg(value_name = "HISTORY_FILE", required_if_eq("task", "report"))]
+        history_file: Option<PathBuf>,
+
+        /// Presets to compare, comma-separated (names as for --model).
+        #[arg(long, value_delimiter = ',', default_value = "gemma-e2b,gemma-e4b")]
+        models: Vec<String>,
+
+        /// Audio file for the transcribe task.
+        #[arg(long, value_name = "AUDIO_FILE", required_if_eq("task", "transcribe"))]
+        audio: Option<PathBuf>,
+
+        /// Ground-truth text for --audio (plain text, JSON or SRT).  Each
+        /// transcription is scored against it and the WER reported, as
+        /// `wer` computes it.
+        #[arg(long, value_name = "FILE", requires = "audio")]
+        reference: Option<PathBuf>,
+
+        #[command(flatten)]
+        load: LoadOptions,
+
+        /// Unmeasured runs per preset before timing starts.
+        #[arg(long, value_name = "N", default_value_t = 1)]
+        warmup: usize,
+
+        /// Measured runs per preset.
+        #[arg(long, value_name = "N", default_value_t = 3)]
+        iterations: usize,
+
+        /// Also write the results to a `.csv` or `.json` file.
+        #[arg(long, value_name = "PATH")]
+        report: Option<PathBuf>,
+
+        /// Append one JSON line per measured run (timestamp, version,
+        /// device, overrides, timings, peak memory) to this file, so runs
+        /// can be compared over time with `bench report`.
+        #[arg(long, value_name = "PATH.jsonl")]
+        history: Option<PathBuf>,
     },
+
+    /// Score prompt enhancer presets by round trip: enhance each seed,
+    /// render it with FLUX, then have Gemma 3n rate how faithfully the image
+    /// matches the seed (1–10) and say why.
+    ///
+    /// Runs in three phases so each model loads once: the enhancers in
+    /// turn, then FLUX, then the judge.  The judge loads while FLUX renders
+    /// only if both fit in memory.
+    ///
+    /// Examples:
+    ///   cargo run --release -- eval seeds.txt
+    ///   cargo run --release -- eval seeds.txt --models gemma-e2b,qwen2.5-1.5b --judge gemma-e4b
+    ///   cargo run --release -- eval seeds.txt --out-dir eval-run --report scores.json
+    Eval {
+        /// Text file with one seed prompt per line (blank lines and `#`
+        /// comments are skipped).
+        #[arg(value_name = "SEEDS_FILE")]
+        seeds: PathBuf,
+
+        /// Enhancer presets to compare, comma-separated (names as for
+        /// `prompt --model`).
+        #[arg(
+            long,
+            value_enum,
+            value_delimiter = ',',
+            default_value = "gemma-e2b,gemma-e4b"
+        )]
+        models: Vec<EnhancerModel>,
+
+        /// Gemma 3n variant that scores the images.
+        #[arg(long, value_enum, default_value_t = TranscriptionModel::default())]
+        judge: TranscriptionModel,
+
+        #[command(flatten)]
+        load: LoadOptions,
+
+        /// Directory for the images (`<DIR>/<preset>/seed-N.png`) and the
+        /// default report.
+        #[arg(long, value_name = "DIR", default_value = "eval")]
+        out_dir: PathBuf,
+
+        /// Image width in pixels (multiple of 16).
+        #[arg(long)]
+        width: Option<usize>,
+
+        /// Image height in pixels (multiple of 16).
+        #[arg(long)]
+        height: Option<usize>,
+
+        /// Overwrite images left by an earlier run.
+        #[arg(long)]
+        force: bool,
+
+        /// Write the scores to this `.csv` or `.json` file
+        /// (default: `<DIR>/report.csv`).
+        #[arg(long, value_name = "PATH")]
+        report: Option<PathBuf>,
+    },
+
+    /// Keep models loaded and serve them over HTTP.
+    ///
+    /// Endpoints (JSON in, JSON out; errors are `{"error": {...}}`):
+    ///   GET  /health         — enabled capabilities
+    ///   POST /v1/enhance     — {"seed": "..."} → {"prompt": "..."}
+    ///   POST /v1/transcribe  — multipart `audio` upload or {"audio_base64": "..."}
+    ///   POST /v1/images      — {"prompt": "...", "response_format": "png" | "b64_json"}
+    ///
+    /// Requests to the same model are queued and handled one at a time.
+    ///
+    /// Examples:
+    ///   cargo run --release -- serve
+    ///   cargo run --release -- serve --enable enhance,transcribe --port 9000
+    Serve {
+        /// Models to load, comma-separated; endpoints for the others
+        /// return 404.
+        #[arg(
+            long,
+            value_enum,
+            value_delimiter = ',',
+            default_value = "enhance,transcribe,images"
+        )]
+        enable: Vec<Capability>,
+
+        /// Address to bind.
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+
+        /// Port to listen on.
+        #[arg(long, default_value_t = 8080)]
+        port: u16,
+
+        /// Text model preset for /v1/enhance.
+        #[arg(long, value_enum, default_value_t = EnhancerModel::default())]
+        enhancer_model: EnhancerModel,
+
+        /// Gemma 3n variant for /v1/transcribe.
+        #[arg(long, value_enum, default_value_t = TranscriptionModel::default())]
+        transcription_model: TranscriptionModel,
+
+        /// Requests /v1/enhance and /v1/transcribe each send to their model
+        /// at once; later ones wait their turn in arrival order.
+        #[arg(long, value_name = "N", default_value_t = request_queue::DEFAULT_MAX_CONCURRENCY)]
+        max_concurrency: usize,
+
+        #[command(flatten)]
+        load: LoadOptions,
+
+        #[command(flatten)]
+        sampler: SamplerArgs,
+    },
+
+    /// Check this machine is ready to run the examples, recommend presets
+    /// and make them the defaults.
+    ///
+    /// Looks for a HuggingFace token (needed for the gated Gemma models),
+    /// measures free memory, reports the GPU backend and whether the
+    /// recommended presets are downloaded, then offers to download them
+    /// and write them to the user config file.  Each failed check says
+    /// what to do next; the command fails if any did.  With --dry-run it
+    /// only checks and recommends.
+    ///
+    /// Examples:
+    ///   cargo run -- setup
+    ///   cargo run -- setup --yes --prefetch
+    ///   cargo run -- --json --dry-run setup
+    Setup {
+        /// Don't ask: write the config file, and download only with
+        /// --prefetch.
+        #[arg(short, long)]
+        yes: bool,
+
+        /// Download the recommended presets without asking.
+        #[arg(long)]
+        prefetch: bool,
+    },
+
+    /// Inspect and manage the models this tool downloads into the
+    /// HuggingFace cache (HF_HUB_CACHE / HF_HOME, or --cache-dir).
+    ///
+    /// Examples:
+    ///   cargo run -- models list
+    ///   cargo run -- models prefetch gemma-e2b
+    ///   cargo run -- models rm flux-schnell
+    ///   cargo run -- --cache-dir /mnt/models models list
+    Models {
+        #[command(subcommand)]
+        action: ModelsAction,
+    },
+
+    /// Inspect the config files that supply default flags.
+    ///
+    /// `mistralrs-example.toml` is read from ~/.config/mistralrs-example/
+    /// (or $XDG_CONFIG_HOME) and then from the working directory, which
+    /// takes precedence.  Top-level keys set global flags; a `[prompt]`,
+    /// `[transcribe]`, … table sets that subcommand's flags.  Flags given
+    /// on the command line always win.
+    ///
+    /// Examples:
+    ///   cargo run -- config show
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+
+    /// List and delete the sessions kept by `chat --session` and
+    /// `prompt --interactive --session`, in
+    /// ~/.local/state/mistralrs-example/sessions/ (or $XDG_STATE_HOME).
+    ///
+    /// Examples:
+    ///   cargo run -- sessions list
+    ///   cargo run -- sessions rm scratch
+    Sessions {
+        #[command(subcommand)]
+        action: SessionsAction,
+    },
+
+    /// List the run directories made by `--run-dir`, in ./runs or the
+    /// BASE given with --run-dir=BASE.
+    ///
+    /// Examples:
+    ///   cargo run -- runs list
+    ///   cargo run -- --run-dir=experiments runs list --json
+    Runs {
+        #[command(subcommand)]
+        action: RunsAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Print the merged settings and the file each one comes from.
+    Show,
+}
+
+#[derive(Subcommand)]
+enum SessionsAction {
+    /// Show each saved session: kind, messages, last write and whether
+    /// another process has it open.
+    List,
+
+    /// Delete a saved session.  Refused while it is open.
+    Rm {
+        name: String,
+
+        /// Don't ask for confirmation.
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum RunsAction {
+    /// Show each finished run: outcome, duration, the files it wrote and
+    /// its command line, oldest first.
+    List,
+}
+
+#[derive(Subcommand)]
+enum ModelsAction {
+    /// Show each preset's cached repo: size on disk and whether the
+    /// download is complete.
+    List,
+
+    /// Download a preset's weights without loading them, so the next run
+    /// starts straight away.
+    Prefetch {
+        #[arg(value_enum)]
+        preset: CachedModel,
+    },
+
+    /// Delete a preset's cached repo.
+    Rm {
+        #[arg(value_enum)]
+        preset: CachedModel,
+
+        /// Don't ask for confirmation.
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum TranscribeAction {
+    /// Compare two saved transcripts word by word (no model is loaded).
+    ///
+    /// Accepts plain-text transcripts or JSON result files (the `text` field
+    /// is extracted).  Both sides are normalized and diffed as a word stream,
+    /// so differing line breaks don't show up as changes.  FILE_A is treated
+    /// as the reference when computing WER.
+    ///
+    /// Examples:
+    ///   cargo run -- transcribe diff run1.txt run2.txt
+    ///   cargo run -- transcribe diff run1.json run2.json --no-color
+    Diff {
+        /// Reference transcript.
+        #[arg(value_name = "FILE_A")]
+        file_a: PathBuf,
+
+        /// Transcript to compare against the reference.
+        #[arg(value_name = "FILE_B")]
+        file_b: PathBuf,
+
+        /// Mark changes with `[-old-]` / `{+new+}` instead of ANSI colors.
+        #[arg(long)]
+        no_color: bool,
+    },
+}
+
+impl Command {
+    /// Whether `--dry-run` can plan the command.
+    fn supports_dry_run(&self) -> bool {
+        matches!(
+            self,
+            Self::Image { .. }
+                | Self::Prompt { .. }
+                | Self::Transcribe {
+                    action: None,
+                    watch: false,
+                    ..
+                }
+                | Self::Setup { .. }
+        )
+    }
+
+    /// Whether the command loads a model, and so gets the device banner.
+    fn loads_models(&self) -> bool {
+        !matches!(
+            self,
+            Self::Setup { .. }
+                | Self::Models { .. }
+                | Self::Config { .. }
+                | Self::Sessions { .. }
+                | Self::Runs { .. }
+                | Self::Transcribe {
+                    action: Some(_),
+                    ..
+                }
+                | Self::Wer { audio: None, .. }
+        )
+    }
+
+    /// Whether `--run-dir` makes a run directory for the command: not for
+    /// the ones that only look after models, config, sessions and runs.
+    fn records_run(&self) -> bool {
+        !matches!(
+            self,
+            Self::Setup { .. }
+                | Self::Models { .. }
+                | Self::Config { .. }
+                | Self::Sessions { .. }
+                | Self::Runs { .. }
+        )
+    }
+
+    /// Point the command's output paths into `run`.  Relative ones move
+    /// under it, and files that would otherwise land in the working
+    /// directory (the default image, `lyrics-art`'s directory) or only on
+    /// stdout (transcripts) are written there too.
+    fn route_into(&mut self, run: &RunDir) {
+        let place = |path: &mut PathBuf| *path = run.place(path);
+        match self {
+            Self::Image { out, .. } => {
+                let path = out
+                    .take()
+                    .unwrap_or_else(|| PathBuf::from(image_session::DEFAULT_OUT));
+                *out = Some(run.place(&path));
+            }
+            Self::Embed { out, .. } => out.iter_mut().for_each(place),
+            Self::Transcribe {
+                action: None,
+                watch: false,
+                audio_paths,
+                tracks,
+                output_format,
+                output,
+                dump_audio,
+                ..
+            } => {
+                *output = Some(match output.take() {
+                    Some(path) => run.place(&path),
+                    // A batch writes `<stem>.<ext>` per file into it.
+                    None if audio_paths.len() > 1 && !*tracks => run.path().to_path_buf(),
+                    None => {
+                        let stem = match audio_paths.as_slice() {
+                            [path] if path.as_os_str() != "-" => path.file_stem(),
+                            _ => None,
+                        };
+                        let stem = stem.map_or("transcript".into(), |s| s.to_string_lossy());
+                        run.path()
+                            .join(format!("{stem}.{}", output_format.extension()))
+                    }
+                });
+                dump_audio.iter_mut().for_each(place);
+            }
+            Self::Retranscribe {
+                transcript, output, ..
+            } => {
+                let path = output.take().unwrap_or_else(|| {
+                    Path::new(transcript.file_name().unwrap_or_default()).to_path_buf()
+                });
+                *output = Some(run.place(&path));
+            }
+            Self::LyricsArt {
+                audio_path,
+                out_dir,
+                ..
+            } => {
+                let dir = out_dir
+                    .take()
+                    .unwrap_or_else(|| pipeline::default_out_dir(audio_path));
+                *out_dir = Some(run.place(&dir));
+            }
+            Self::Bench { report, .. } => report.iter_mut().for_each(place),
+            Self::Eval {
+                out_dir, report, ..
+            } => {
+                place(out_dir);
+                report.iter_mut().for_each(place);
+            }
+            _ => {}
+        }
+    }
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let cli = Cli::parse();
+fn main() -> Result<()> {
+    let mut config = config::Config::load()?;
+    config.retain_known(&Cli::command());
+    let mut cli: Cli = config.parse();
+    let output_config = OutputConfig::new(cli.json, cli.quiet, cli.verbose);
+    logging::init(cli.log_format, output_config)?;
+    let run_dir = match &cli.run_dir {
+        Some(base) if cli.command.records_run() && !cli.dry_run => {
+            let args = std::env::args_os()
+                .skip(1)
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect();
+            let run = RunDir::create(base, args)?;
+            run.write_config(&config)?;
+            cli.command.route_into(&run);
+            ui::status!(output_config, "Run directory: {}", run.path().display());
+            Some(run)
+        }
+        _ => None,
+    };
+    // Before the runtime starts: this sets an environment variable.
+    if let Some(dir) = &cli.cache_dir {
+        loader::set_cache_dir(dir);
+    }
+    device::select(cli.device)?;
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    let result = runtime.block_on(run(cli, config));
+    if let Some(run) = run_dir
+        && let Err(err) = run.finish(&result)
+    {
+        tracing::warn!("{err:#}");
+    }
+    // Ctrl-C already explained what was kept; exit like an interrupted
+    // process instead of printing an error.
+    if let Err(err) = &result
+        && interrupt::is_interrupted(err)
+    {
+        eprintln!("Interrupted.");
+        std::process::exit(interrupt::EXIT_CODE);
+    }
+    // Print the error the way returning it would, with what to do about
+    // it underneath.
+    if let Err(err) = &result
+        && let Some(hint) = error::hint(err)
+    {
+        eprintln!("Error: {err:?}\n\nhint: {hint}");
+        std::process::exit(1);
+    }
+    result
+}
+
+async fn run(cli: Cli, config: config::Config) -> Result<()> {
+    let output_config = OutputConfig::new(cli.json, cli.quiet, cli.verbose);
+    // Progress bars go to stderr but would still clutter piped, --json or
+    // --quiet runs.
+    loader::set_progress_enabled(!cli.json && !cli.quiet && std::io::stdout().is_terminal());
+    loader::set_download_retries(cli.download_retries);
+    disk_space::set_check_enabled(!cli.skip_space_check);
+    artifacts::set_min_free_bytes(cli.min_free);
+    if cli.timeout == Some(0) {
+        anyhow::bail!("--timeout must be at least 1 second");
+    }
+    timeout::set_default(cli.timeout.map(Duration::from_secs));
+    let dry_run = cli.dry_run;
+    if dry_run && !cli.command.supports_dry_run() {
+        anyhow::bail!(
+            "--dry-run is only supported by image, prompt, transcribe (without --watch) and setup"
+        );
+    }
+    if cli.command.loads_models() && !dry_run {
+        ui::status!(output_config, "Device: {}", device::selected().describe());
+    }
 
     match cli.command {
         Command::Image {
             prompt,
-            seed,
+            seed_prompt,
+            enhancer,
+            remote,
             model,
-        } => image_generation::run(prompt, seed, model).await,
-        Command::Prompt { seed, model } => promp_enhancer::run(seed, model).await,
+            model_path,
+            load,
+            sampler,
+            sampler_seed,
+            candidates,
+            pick,
+            style,
+            sequential,
+            keep_enhancer,
+            interactive,
+            fallback,
+            no_cache,
+            refresh,
+            diffusion_model,
+            loader,
+            diffusion_dtype,
+            max_prompt_tokens,
+            width,
+            height,
+            num_images,
+            out,
+            force,
+            grid,
+            grid_columns,
+            no_grid_caption,
+            sizes,
+            negative_prompt,
+            guidance,
+            no_metadata,
+            embed_metadata,
+        } => {
+            let options = ImageOptions {
+                width,
+                height,
+                num_images,
+                out,
+                force,
+                negative_prompt,
+                guidance,
+                metadata: MetadataOutput {
+                    sidecar: !no_metadata,
+                    embed: embed_metadata,
+                },
+                grid: grid.then_some(GridOptions {
+                    columns: grid_columns,
+                    caption: !no_grid_caption,
+                }),
+                sizes,
+            };
+            let enhancer = EnhancerOptions {
+                backend: enhancer,
+                model,
+                model_path,
+                load,
+                sampler,
+                sampler_seed,
+                candidates,
+                pick,
+                style,
+                sequential,
+                fallback,
+                cache: match (no_cache, refresh) {
+                    (true, _) => CacheMode::Off,
+                    (_, true) => CacheMode::Refresh,
+                    _ => CacheMode::Use,
+                },
+                remote,
+            };
+            let diffusion = DiffusionConfig::resolve(diffusion_model, loader, diffusion_dtype)
+                .with_max_prompt_tokens(max_prompt_tokens);
+            if seed_prompt.len() > 1 && !keep_enhancer {
+                anyhow::bail!(
+                    "Several --seed-prompt values need --keep-enhancer, which keeps the prompt \
+                     enhancer loaded next to the diffusion model"
+                );
+            }
+            if dry_run {
+                return image_generation::dry_run(
+                    prompt,
+                    seed_prompt,
+                    enhancer,
+                    options,
+                    diffusion,
+                    output_config,
+                );
+            }
+            if interactive {
+                if seed_prompt.len() > 1 {
+                    anyhow::bail!("--interactive takes one --seed-prompt");
+                }
+                return image_session::run_interactive(
+                    prompt,
+                    seed_prompt.into_iter().next(),
+                    enhancer,
+                    options,
+                    diffusion,
+                    keep_enhancer,
+                    output_config,
+                )
+                .await;
+            }
+            let cancel = Interrupt::install(true).token();
+            if keep_enhancer {
+                return image_generation::run_seeds(
+                    seed_prompt,
+                    enhancer,
+                    options,
+                    diffusion,
+                    cancel,
+                    output_config,
+                )
+                .await;
+            }
+            image_generation::run(
+                prompt,
+                seed_prompt.into_iter().next(),
+                enhancer,
+                options,
+                diffusion,
+                cancel,
+                output_config,
+            )
+            .await
+        }
+        Command::Prompt {
+            seed_prompt,
+            model,
+            model_path,
+            load,
+            sampler,
+            system,
+            few_shot,
+            examples_file,
+            sampler_seed,
+            candidates,
+            interactive,
+            style,
+            from_audio,
+            compare,
+            fallback,
+            warmup,
+            session,
+            copy,
+        } => {
+            let options = PromptOptions {
+                sampler,
+                sampler_seed,
+                candidates,
+                interactive,
+                style,
+                system_prompt: system.resolve()?,
+                few_shot: match (few_shot, examples_file) {
+                    (_, Some(path)) => Some(FewShot::from_file(&path)?),
+                    (true, None) => Some(FewShot::builtin()),
+                    (false, None) => None,
+                },
+                from_audio,
+                warmup,
+                session,
+                fallback,
+                copy,
+            };
+            if let Some(presets) = compare {
+                if dry_run {
+                    return prompt_compare::dry_run(
+                        seed_prompt,
+                        presets,
+                        load,
+                        options,
+                        output_config,
+                    );
+                }
+                return prompt_compare::run(seed_prompt, presets, load, options, output_config)
+                    .await;
+            }
+            if dry_run {
+                return promp_enhancer::dry_run(
+                    seed_prompt,
+                    model,
+                    model_path,
+                    load,
+                    options,
+                    output_config,
+                );
+            }
+            promp_enhancer::run(seed_prompt, model, model_path, load, options, output_config).await
+        }
+        Command::Summarize {
+            input,
+            text,
+            style,
+            max_words,
+            chunk_words,
+            model,
+            model_path,
+            load,
+            sampler,
+        } => {
+            let options = SummarizeOptions {
+                input,
+                text,
+                style,
+                max_words,
+                chunk_words,
+                sampler,
+            };
+            summarizer::run(model, model_path, load, options, output_config).await
+        }
+        Command::Embed {
+            input,
+            model,
+            model_id,
+            format,
+            out,
+            batch_size,
+        } => {
+            let options = EmbedOptions {
+                input,
+                model,
+                model_id,
+                format,
+                out,
+                batch_size,
+            };
+            embeddings::run(options, output_config).await
+        }
         Command::Transcribe {
+            action:
+                Some(TranscribeAction::Diff {
+                    file_a,
+                    file_b,
+                    no_color,
+                }),
+            ..
+        } => metrics::run_diff(&file_a, &file_b, no_color, cli.json),
+        Command::Retranscribe {
             audio_path,
+            transcript,
+            segments,
+            range,
             model,
+            model_path,
+            load,
             user_prompt,
-        } => audio_transcription::run(audio_path, model, user_prompt).await,
-        Command::Chat { model } => cli_chat::run(model).await,
+            system,
+            language,
+            no_preprocess,
+            output,
+            decoding,
+        } => {
+            let options = RetranscribeOptions {
+                audio: audio_path,
+                transcript,
+                indices: segments.unwrap_or_default().0,
+                ranges: range,
+                model,
+                model_path,
+                load,
+                user_prompt,
+                system_prompt: system.resolve()?,
+                language,
+                preprocess: (!no_preprocess).then(PrepOptions::default),
+                decoding,
+                output,
+            };
+            let cancel = Interrupt::install(true).token();
+            retranscribe::run(options, cancel, output_config).await
+        }
+        Command::Wer {
+            hypothesis,
+            reference,
+            audio,
+            model,
+            load,
+            keep_case,
+            keep_punctuation,
+            keep_tags,
+            no_color,
+        } => {
+            // clap requires one of TRANSCRIPT and --audio.
+            let hypothesis = match audio {
+                Some(path) => Hypothesis::Audio { path, model, load },
+                None => Hypothesis::File(hypothesis.unwrap_or_default()),
+            };
+            let options = WerOptions {
+                reference,
+                hypothesis,
+                normalize: NormalizeOptions {
+                    lowercase: !keep_case,
+                    strip_punctuation: !keep_punctuation,
+                    drop_tags: !keep_tags,
+                },
+                no_color,
+            };
+            let cancel = Interrupt::install(true).token();
+            metrics::run_wer(options, cancel, output_config).await
+        }
+        Command::Transcribe {
+            action: None,
+            audio_paths,
+            watch,
+            model,
+            model_path,
+            load,
+            user_prompt,
+            system,
+            language,
+            translate,
+            no_preprocess,
+            silence_threshold,
+            normalize,
+            normalize_mode,
+            normalize_target,
+            vad,
+            vad_threshold,
+            detect_language,
+            check_vocals: _,
+            no_check_vocals,
+            force,
+            timestamps,
+            text_format,
+            min_line_chars,
+            tracks,
+            resample_tracks,
+            interactive,
+            censor,
+            censor_list,
+            lyrics_hint,
+            lyrics_hint_tokens,
+            output_format,
+            output,
+            jobs,
+            jobs_models,
+            raw_pcm,
+            sample_rate,
+            channels,
+            format,
+            dump_audio,
+            dump_format,
+            warmup,
+            decoding,
+        } => {
+            let options = TranscribeOptions {
+                user_prompt,
+                system_prompt: system.resolve()?,
+                language,
+                translate,
+                preprocess: (!no_preprocess).then(|| PrepOptions {
+                    silence_threshold_dbfs: silence_threshold
+                        .or(PrepOptions::default().silence_threshold_dbfs),
+                    normalize: normalize.then(|| {
+                        let mode = normalize_mode.unwrap_or_default();
+                        Normalization {
+                            mode,
+                            target_dbfs: normalize_target
+                                .unwrap_or_else(|| mode.default_target_dbfs()),
+                        }
+                    }),
+                }),
+                vad_threshold: vad
+                    .then(|| vad_threshold.unwrap_or(audio_prep::DEFAULT_VAD_THRESHOLD_DBFS)),
+                detect_language,
+                vocal_check: match (no_check_vocals, force) {
+                    (true, _) => VocalCheck::Off,
+                    (false, true) => VocalCheck::Report,
+                    (false, false) => VocalCheck::Skip,
+                },
+                timestamps,
+                lyrics: text_format.map(|TextFormat::Lyrics| {
+                    LyricsFormatter::default().with_min_line_chars(min_line_chars)
+                }),
+                censor: match (censor, censor_list) {
+                    (false, _) => None,
+                    (true, None) => Some(Censor::default()),
+                    (true, Some(path)) => Some(Censor::default().with_list(&path)?),
+                },
+                reference: match lyrics_hint {
+                    Some(_) if lyrics_hint_tokens == 0 => {
+                        anyhow::bail!("--lyrics-hint-tokens must be at least 1")
+                    }
+                    Some(path) => Some(ReferenceText::from_file(&path, lyrics_hint_tokens)?),
+                    None => None,
+                },
+                tracks,
+                resample_tracks,
+                interactive,
+                output_format,
+                output,
+                raw_pcm: raw_pcm.then(|| RawPcm {
+                    sample_rate: sample_rate.expect("clap requires --sample-rate with --raw-pcm"),
+                    channels,
+                    format,
+                }),
+                dump_audio: dump_audio.map(|path| AudioDump {
+                    path,
+                    format: dump_format,
+                }),
+                warmup,
+                decoding,
+            };
+            if dry_run {
+                if options.dump_audio.is_some() {
+                    anyhow::bail!("--dump-audio writes what a real run sends; drop --dry-run");
+                }
+                return audio_transcription::dry_run(
+                    &audio_paths,
+                    model,
+                    model_path,
+                    load,
+                    options,
+                    output_config,
+                );
+            }
+            let cancel = Interrupt::install(true).token();
+            if watch {
+                if cli.json {
+                    anyhow::bail!("--json is not supported with --watch");
+                }
+                let [dir] = <[PathBuf; 1]>::try_from(audio_paths)
+                    .map_err(|_| anyhow::anyhow!("--watch takes a single directory"))?;
+                return watch_folder::run(
+                    dir,
+                    model,
+                    model_path,
+                    load,
+                    options,
+                    cancel,
+                    output_config,
+                )
+                .await;
+            }
+            if audio_paths.len() > 1 && !tracks {
+                if interactive {
+                    anyhow::bail!(
+                        "--interactive takes a single AUDIO_FILE, or several with --tracks"
+                    );
+                }
+                if options.dump_audio.is_some() {
+                    anyhow::bail!(
+                        "--dump-audio takes a single AUDIO_FILE, or several with --tracks"
+                    );
+                }
+                if options.warmup {
+                    anyhow::bail!("--warmup takes a single AUDIO_FILE, or several with --tracks");
+                }
+                if options.reference.is_some() {
+                    anyhow::bail!(
+                        "--lyrics-hint takes a single AUDIO_FILE, or several with --tracks"
+                    );
+                }
+                let options = BatchOptions {
+                    transcribe: options,
+                    jobs,
+                    jobs_models,
+                };
+                return batch_transcription::run(
+                    audio_paths,
+                    model,
+                    model_path,
+                    load,
+                    options,
+                    cancel,
+                    output_config,
+                )
+                .await;
+            }
+            audio_transcription::run(
+                audio_paths,
+                model,
+                model_path,
+                load,
+                options,
+                cancel,
+                output_config,
+            )
+            .await
+        }
+        Command::Describe {
+            images,
+            model,
+            model_path,
+            load,
+            user_prompt,
+            sampler,
+        } => {
+            let options = DescribeOptions {
+                user_prompt,
+                sampler,
+            };
+            let cancel = Interrupt::install(true).token();
+            image_understanding::run(
+                images,
+                model,
+                model_path,
+                load,
+                options,
+                cancel,
+                output_config,
+            )
+            .await
+        }
+        Command::LyricsArt {
+            audio_path,
+            style,
+            transcription_model,
+            enhancer_model,
+            enhancer,
+            remote,
+            load,
+            out_dir,
+            width,
+            height,
+            force,
+        } => {
+            let options = pipeline::PipelineOptions {
+                style,
+                transcription_model,
+                enhancer_model,
+                enhancer,
+                remote,
+                load,
+                out_dir,
+                width,
+                height,
+                force,
+            };
+            pipeline::run(audio_path, options, output_config).await
+        }
+        Command::Chat {
+            model,
+            model_path,
+            load,
+            system,
+            sampler,
+            max_history_tokens,
+            warmup,
+            session,
+        } => {
+            if cli.json {
+                anyhow::bail!("--json is not supported by the interactive chat");
+            }
+            let options = ChatOptions {
+                system_prompt: system.resolve()?,
+                sampler,
+                max_history_tokens,
+                warmup,
+                session,
+            };
+            cli_chat::run(
+                model,
+                model_path,
+                load,
+                options,
+                Interrupt::install(false),
+                output_config,
+            )
+            .await
+        }
+        Command::Bench {
+            task,
+            history_file,
+            models,
+            audio,
+            reference,
+            load,
+            warmup,
+            iterations,
+            report,
+            history,
+        } => {
+            match (task, history_file) {
+                (BenchTask::Report, Some(path)) => {
+                    return bench::report_history(&path, output_config);
+                }
+                (BenchTask::Report, None) => unreachable!("clap requires the history file"),
+                (_, Some(path)) => anyhow::bail!(
+                    "Only `bench report` takes a history file ({}); record runs with --history",
+                    path.display()
+                ),
+                (_, None) => {}
+            }
+            let options = BenchOptions {
+                models,
+                audio,
+                reference,
+                load,
+                warmup,
+                iterations,
+                report,
+                history,
+            };
+            bench::run(task, options, output_config).await
+        }
+        Command::Eval {
+            seeds,
+            models,
+            judge,
+            load,
+            out_dir,
+            width,
+            height,
+            force,
+            report,
+        } => {
+            let options = EvalOptions {
+                models,
+                judge,
+                load,
+                out_dir,
+                width,
+                height,
+                force,
+                report,
+            };
+            let cancel = Interrupt::install(true).token();
+            image_eval::run(seeds, options, cancel, output_config).await
+        }
+        Command::Serve {
+            enable,
+            host,
+            port,
+            enhancer_model,
+            transcription_model,
+            max_concurrency,
+            load,
+            sampler,
+        } => {
+            if cli.json {
+                anyhow::bail!(
+                    "--json is not supported by serve; its endpoints already return JSON"
+                );
+            }
+            let options = ServeOptions {
+                host,
+                port,
+                enable,
+                enhancer_model,
+                transcription_model,
+                max_concurrency,
+                load,
+                sampler,
+            };
+            server::run(options).await
+        }
+        Command::Setup { yes, prefetch } => {
+            setup::run(SetupOptions { yes, prefetch }, dry_run, output_config).await
+        }
+        Command::Models { action } => match action {
+            ModelsAction::List => model_cache::list(output_config),
+            ModelsAction::Prefetch { preset } => model_cache::prefetch(preset, output_config).await,
+            ModelsAction::Rm { preset, yes } => model_cache::remove(preset, yes, output_config),
+        },
+        Command::Config { action } => match action {
+            ConfigAction::Show => config::show(&config, output_config),
+        },
+        Command::Sessions { action } => match action {
+            SessionsAction::List => sessions::list(output_config),
+            SessionsAction::Rm { name, yes } => sessions::remove(&name, yes, output_config),
+        },
+        Command::Runs { action } => match action {
+            RunsAction::List => runs::list(
+                cli.run_dir
+                    .as_deref()
+                    .unwrap_or(Path::new(runs::DEFAULT_BASE)),
+                output_config,
+            ),
+        },
     }
 }
@@ -0,0 +1,129 @@
+use anyhow::Result;
+use std::fmt;
+use sysinfo::System;
+
+use crate::load_options::{GB, LoadOptions};
+use crate::uqff::UqffArgs;
+
+// ── Presets ──────────────────────────────────────────────────────────────────
+
+/// A model preset whose memory needs are known up front, so a load that
+/// can't fit is refused before the builder runs out of memory halfway.
+pub trait MemoryFootprint: Copy + fmt::Display {
+    /// The smallest preset, loaded instead with `--auto-downgrade`.
+    const FALLBACK: Self;
+
+    /// Approximate bytes needed to load this preset with `opts` applied.
+    fn required_bytes(self, opts: &LoadOptions) -> u64;
+}
+
+/// `--auto-downgrade` loads the fallback with its own defaults, whatever
+/// `--isq` / `--dtype` said.  Only `--cache-quantized` carries over: a
+/// `--from-uqff` file belongs to the model that was asked for.
+fn fallback_options(opts: &LoadOptions) -> LoadOptions {
+    LoadOptions {
+        auto_downgrade: opts.auto_downgrade,
+        uqff: UqffArgs {
+            cache_quantized: opts.uqff.cache_quantized,
+            ..UqffArgs::default()
+        },
+        ..LoadOptions::default()
+    }
+}
+
+/// Format a byte count the way the memory estimates do, e.g. "7.9 GB".
+pub fn fmt_gb(bytes: u64) -> String {
+    format!("{:.1} GB", bytes as f64 / GB)
+}
+
+// ── Decision ─────────────────────────────────────────────────────────────────
+
+/// What to do with a preset given the memory that's available.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Decision {
+    /// It fits: load it as asked.
+    Load,
+    /// It doesn't fit, but [`MemoryFootprint::FALLBACK`] does and
+    /// `--auto-downgrade` was given.
+    Downgrade,
+    /// Nothing we're allowed to load fits.
+    TooLarge,
+}
+
+/// Decide whether `preset` with `opts` fits in `available` bytes.
+pub fn decide<P: MemoryFootprint>(preset: P, opts: &LoadOptions, available: u64) -> Decision {
+    if preset.required_bytes(opts) <= available {
+        Decision::Load
+    } else if opts.auto_downgrade
+        && P::FALLBACK.required_bytes(&fallback_options(opts)) <= available
+    {
+        Decision::Downgrade
+    } else {
+        Decision::TooLarge
+    }
+}
+
+// ── Probe ────────────────────────────────────────────────────────────────────
+
+/// Memory available for a new model right now: free RAM on Linux, unified
+/// memory on Apple silicon.  `None` if the platform doesn't report it.
+pub fn available_bytes() -> Option<u64> {
+    let mut system = System::new();
+    system.refresh_memory();
+    Some(system.available_memory()).filter(|&bytes| bytes > 0)
+}
+
+/// What [`choose`] would load, without its warning: `None` when it
+/// would fail.  For planning a run without loading anything.
+pub fn would_load<P: MemoryFootprint>(preset: P, opts: &LoadOptions) -> Option<(P, LoadOptions)> {
+    let Some(available) = available_bytes() else {
+        return Some((preset, opts.clone()));
+    };
+    match decide(preset, opts, available) {
+        Decision::Load => Some((preset, opts.clone())),
+        Decision::Downgrade => Some((P::FALLBACK, fallback_options(opts))),
+        Decision::TooLarge => None,
+    }
+}
+
+/// Check that `preset` fits before it's built and return what to load.
+///
+/// Fails with a suggestion when it doesn't, or, with `--auto-downgrade`,
+/// switches to [`MemoryFootprint::FALLBACK`] and says so on stderr.  When
+/// memory can't be probed the preset is loaded as asked.
+pub fn choose<P: MemoryFootprint>(preset: P, opts: &LoadOptions) -> Result<(P, LoadOptions)> {
+    let Some(available) = available_bytes() else {
+        return Ok((preset, opts.clone()));
+    };
+    let required = preset.required_bytes(opts);
+    match decide(preset, opts, available) {
+        Decision::Load => Ok((preset, opts.clone())),
+        Decision::Downgrade => {
+            let fallback = fallback_options(opts);
+            tracing::warn!(
+                "{preset} needs ~{} but only {} is available; \
+                 loading {} with Q4K instead (--auto-downgrade)",
+                fmt_gb(required),
+                fmt_gb(available),
+                P::FALLBACK,
+            );
+            Ok((P::FALLBACK, fallback))
+        }
+        Decision::TooLarge if opts.auto_downgrade => anyhow::bail!(
+            "{preset} needs ~{} but only {} is available, and even {} (~{}) doesn't fit.\n\
+             Close other applications to free memory and try again.",
+            fmt_gb(required),
+            fmt_gb(available),
+            P::FALLBACK,
+            fmt_gb(P::FALLBACK.required_bytes(&fallback_options(opts))),
+        ),
+        Decision::TooLarge => anyhow::bail!(
+            "{preset} needs ~{} but only {} is available.\n\
+             Try a smaller preset (e.g. gemma-e2b) or --isq q4k, or pass \
+             --auto-downgrade to fall back to {} automatically.",
+            fmt_gb(required),
+            fmt_gb(available),
+            P::FALLBACK,
+        ),
+    }
+}
@@ -0,0 +1,201 @@
+use serde::Serialize;
+use std::fmt;
+use sysinfo::{ProcessRefreshKind, ProcessesToUpdate, System};
+
+use crate::device::{self, Device};
+use crate::memory::fmt_gb;
+
+// ── Snapshot ─────────────────────────────────────────────────────────────────
+
+/// Memory this process is actually using, as opposed to the
+/// `approx_memory()` estimates.
+///
+/// Either field is `None` where the platform doesn't report it; capturing
+/// never fails.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct MemorySnapshot {
+    /// Resident set size of the process.
+    pub resident_bytes: Option<u64>,
+    /// Memory in use on the GPU: this process's allocations on the CUDA
+    /// device, or its physical footprint on Apple silicon, where Metal
+    /// buffers live in the same unified memory.  `None` on CPU.
+    pub device_bytes: Option<u64>,
+}
+
+impl MemorySnapshot {
+    /// Read the current numbers.
+    pub fn capture() -> Self {
+        Self {
+            resident_bytes: resident_bytes(),
+            device_bytes: device_bytes(),
+        }
+    }
+
+    /// What changed between `before` and this snapshot, e.g. across a
+    /// model load.
+    pub fn since(self, before: Self) -> LoadMemory {
+        let added = |after: Option<u64>, before: Option<u64>| {
+            Some(after?.saturating_sub(before.unwrap_or(0)))
+        };
+        LoadMemory {
+            resident_added_bytes: added(self.resident_bytes, before.resident_bytes),
+            device_added_bytes: added(self.device_bytes, before.device_bytes),
+            after: self,
+        }
+    }
+}
+
+/// `7.4 GB resident, 6.9 GB on device`; missing numbers are left out.
+impl fmt::Display for MemorySnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.resident_bytes, self.device_bytes) {
+            (Some(resident), Some(device)) => write!(
+                f,
+                "{} resident, {} on device",
+                fmt_gb(resident),
+                fmt_gb(device)
+            ),
+            (Some(resident), None) => write!(f, "{} resident", fmt_gb(resident)),
+            (None, Some(device)) => write!(f, "{} on device", fmt_gb(device)),
+            (None, None) => write!(f, "not reported on this platform"),
+        }
+    }
+}
+
+// ── Load ─────────────────────────────────────────────────────────────────────
+
+/// Memory in use after a model load, and how much of it the load added.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct LoadMemory {
+    #[serde(flatten)]
+    pub after: MemorySnapshot,
+    /// Growth in resident memory since the load started.
+    pub resident_added_bytes: Option<u64>,
+    /// Growth in device memory since the load started.
+    pub device_added_bytes: Option<u64>,
+}
+
+impl LoadMemory {
+    /// The banner line printed after "Model loaded in …".
+    pub fn banner(&self) -> String {
+        format!("  Actual memory: {}", self.after)
+    }
+}
+
+// ── Probes ───────────────────────────────────────────────────────────────────
+
+/// Resident set size of this process, via sysinfo.
+fn resident_bytes() -> Option<u64> {
+    let pid = sysinfo::get_current_pid().ok()?;
+    let mut system = System::new();
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&[pid]),
+        false,
+        ProcessRefreshKind::nothing().with_memory(),
+    );
+    Some(system.process(pid)?.memory()).filter(|&bytes| bytes > 0)
+}
+
+/// Device memory for the backend in use; `None` when running on the CPU.
+fn device_bytes() -> Option<u64> {
+    if device::selected() == Device::Cpu {
+        return None;
+    }
+    gpu_bytes()
+}
+
+/// This process's memory on the CUDA device, from `nvidia-smi`.
+///
+/// mistral.rs doesn't expose candle's allocator statistics, and the
+/// driver's per-process accounting is what `nvidia-smi` shows anyway.
+#[cfg(feature = "cuda")]
+fn gpu_bytes() -> Option<u64> {
+    let output = std::process::Command::new("nvidia-smi")
+        .args([
+            "--query-compute-apps=pid,used_memory",
+            "--format=csv,noheader,nounits",
+        ])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    parse_nvidia_smi(&String::from_utf8_lossy(&output.stdout), std::process::id())
+}
+
+/// Physical footprint of this process on Apple silicon, which includes
+/// Metal buffers (unlike the resident set size).
+#[cfg(all(not(feature = "cuda"), target_os = "macos"))]
+fn gpu_bytes() -> Option<u64> {
+    // SAFETY: `proc_pid_rusage` only writes a `rusage_info_v2` into the
+    // zero-initialised buffer.
+    let mut info: libc::rusage_info_v2 = unsafe { std::mem::zeroed() };
+    let status = unsafe {
+        libc::proc_pid_rusage(
+            std::process::id() as libc::c_int,
+            libc::RUSAGE_INFO_V2,
+            (&mut info as *mut libc::rusage_info_v2).cast(),
+        )
+    };
+    (status == 0).then_some(info.ri_phys_footprint)
+}
+
+#[cfg(all(not(feature = "cuda"), not(target_os = "macos")))]
+fn gpu_bytes() -> Option<u64> {
+    None
+}
+
+/// Sum the `used_memory` (MiB) of `pid`'s lines in
+/// `nvidia-smi --query-compute-apps=pid,used_memory --format=csv,noheader,nounits`
+/// output.  `None` when the process has nothing on any GPU.
+#[cfg(feature = "cuda")]
+pub fn parse_nvidia_smi(output: &str, pid: u32) -> Option<u64> {
+    let mib: u64 = output
+        .lines()
+        .filter_map(|line| {
+            let (line_pid, used) = line.split_once(',')?;
+            if line_pid.trim().parse::<u32>().ok()? != pid {
+                return None;
+            }
+            used.trim().parse::<u64>().ok()
+        })
+        .sum();
+    (mib > 0).then_some(mib * 1024 * 1024)
+}
+
+// ── Peak ─────────────────────────────────────────────────────────────────────
+
+/// Reset the peak-RSS counter so the next reading only covers what follows.
+/// Returns `false` where the OS can't do that and the peak is process-wide.
+#[cfg(target_os = "linux")]
+pub fn reset_peak_rss() -> bool {
+    std::fs::write("/proc/self/clear_refs", "5").is_ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn reset_peak_rss() -> bool {
+    false
+}
+
+/// Peak resident set size of this process in bytes, if the OS reports it.
+#[cfg(target_os = "linux")]
+pub fn peak_rss_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|l| l.starts_with("VmHWM:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
+
+#[cfg(target_os = "macos")]
+pub fn peak_rss_bytes() -> Option<u64> {
+    // SAFETY: `getrusage` only writes into the zero-initialised struct.
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    if unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) } != 0 {
+        return None;
+    }
+    // macOS reports bytes (Linux would report KiB).
+    Some(usage.ru_maxrss as u64)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn peak_rss_bytes() -> Option<u64> {
+    None
+}
@@ -0,0 +1,529 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fmt;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use tokio_util::sync::CancellationToken;
+
+use crate::audio_transcription::{self, AudioTranscriber, TranscriptionModel};
+use crate::interrupt;
+use crate::load_options::LoadOptions;
+use crate::memory;
+use crate::retranscribe;
+use crate::ui::{self, OutputConfig, status};
+
+// ── Normalization ────────────────────────────────────────────────────────────
+
+/// Controls how transcripts are reduced to a comparable word stream.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NormalizeOptions {
+    /// Compare words case-insensitively.
+    pub lowercase: bool,
+    /// Drop punctuation (hyphens and dashes split words; in-word apostrophes
+    /// are kept so "don't" stays one word).
+    pub strip_punctuation: bool,
+    /// Remove bracketed tags such as `[inaudible]` or `(instrumental)`.
+    pub drop_tags: bool,
+}
+
+impl NormalizeOptions {
+    /// What `wer` and `bench --reference` compare by default: differences
+    /// in case, punctuation and tags like `[inaudible]` aren't errors.
+    pub const WER: Self = Self {
+        lowercase: true,
+        strip_punctuation: true,
+        drop_tags: true,
+    };
+}
+
+impl Default for NormalizeOptions {
+    fn default() -> Self {
+        Self {
+            lowercase: true,
+            strip_punctuation: true,
+            drop_tags: false,
+        }
+    }
+}
+
+/// Normalize `text` into a flat list of words.
+///
+/// Line structure is deliberately discarded: two runs that break the same
+/// lyrics into different lines still produce the same word stream.
+pub fn normalize_words(text: &str, opts: &NormalizeOptions) -> Vec<String> {
+    let mut text = if opts.drop_tags {
+        strip_tags(text)
+    } else {
+        text.to_string()
+    };
+
+    if opts.lowercase {
+        text = text.to_lowercase();
+    }
+
+    if opts.strip_punctuation {
+        text = text.replace(['-', '–', '—'], " ");
+    }
+
+    text.split_whitespace()
+        .map(|raw| {
+            if opts.strip_punctuation {
+                strip_punctuation(raw)
+            } else {
+                raw.to_string()
+            }
+        })
+        .filter(|w| !w.is_empty())
+        .collect()
+}
+
+/// Remove `[...]` and `(...)` spans, e.g. `[inaudible]` or `(x2)`.
+fn strip_tags(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut depth = 0usize;
+    for c in text.chars() {
+        match c {
+            '[' | '(' => depth += 1,
+            ']' | ')' if depth > 0 => {
+                depth -= 1;
+                // Keep words on either side of the tag apart.
+                out.push(' ');
+            }
+            _ if depth == 0 => out.push(c),
+            _ => {}
+        }
+    }
+    out
+}
+
+/// Keep alphanumerics plus apostrophes that sit between two alphanumerics.
+fn strip_punctuation(word: &str) -> String {
+    let chars: Vec<char> = word
+        .chars()
+        .map(|c| if c == '’' { '\'' } else { c })
+        .collect();
+    let mut out = String::with_capacity(word.len());
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_alphanumeric() {
+            out.push(c);
+        } else if c == '\'' {
+            let prev = i > 0 && chars[i - 1].is_alphanumeric();
+            let next = chars.get(i + 1).is_some_and(|n| n.is_alphanumeric());
+            if prev && next {
+                out.push(c);
+            }
+        }
+    }
+    out
+}
+
+// ── Alignment ────────────────────────────────────────────────────────────────
+
+/// One step of a word-level alignment between a reference (A) and a
+/// hypothesis (B).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EditOp {
+    Equal(String),
+    Substitute {
+        reference: String,
+        hypothesis: String,
+    },
+    Delete(String),
+    Insert(String),
+}
+
+/// Compute a minimum-edit-distance alignment of two word streams.
+///
+/// Standard Levenshtein dynamic programming with unit costs; ties prefer
+/// matches/substitutions over deletions over insertions so the diff reads
+/// left-to-right.
+pub fn align(reference: &[String], hypothesis: &[String]) -> Vec<EditOp> {
+    let n = reference.len();
+    let m = hypothesis.len();
+    let width = m + 1;
+
+    // cost[i * width + j] = edit distance between reference[..i] and hypothesis[..j].
+    let mut cost = vec![0u32; (n + 1) * width];
+    for i in 0..=n {
+        cost[i * width] = i as u32;
+    }
+    for (j, c) in cost.iter_mut().enumerate().take(width) {
+        *c = j as u32;
+    }
+    for i in 1..=n {
+        for j in 1..=m {
+            let diag =
+                cost[(i - 1) * width + j - 1] + u32::from(reference[i - 1] != hypothesis[j - 1]);
+            let del = cost[(i - 1) * width + j] + 1;
+            let ins = cost[i * width + j - 1] + 1;
+            cost[i * width + j] = diag.min(del).min(ins);
+        }
+    }
+
+    let mut ops = Vec::with_capacity(n.max(m));
+    let (mut i, mut j) = (n, m);
+    while i > 0 || j > 0 {
+        let here = cost[i * width + j];
+        if i > 0 && j > 0 {
+            let same = reference[i - 1] == hypothesis[j - 1];
+            if here == cost[(i - 1) * width + j - 1] + u32::from(!same) {
+                ops.push(if same {
+                    EditOp::Equal(reference[i - 1].clone())
+                } else {
+                    EditOp::Substitute {
+                        reference: reference[i - 1].clone(),
+                        hypothesis: hypothesis[j - 1].clone(),
+                    }
+                });
+                i -= 1;
+                j -= 1;
+                continue;
+            }
+        }
+        if i > 0 && here == cost[(i - 1) * width + j] + 1 {
+            ops.push(EditOp::Delete(reference[i - 1].clone()));
+            i -= 1;
+        } else {
+            ops.push(EditOp::Insert(hypothesis[j - 1].clone()));
+            j -= 1;
+        }
+    }
+    ops.reverse();
+    ops
+}
+
+/// Aggregate counts over an alignment.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct AlignmentStats {
+    pub equal: usize,
+    pub substitutions: usize,
+    pub deletions: usize,
+    pub insertions: usize,
+}
+
+impl AlignmentStats {
+    pub fn from_ops(ops: &[EditOp]) -> Self {
+        let mut stats = Self::default();
+        for op in ops {
+            match op {
+                EditOp::Equal(_) => stats.equal += 1,
+                EditOp::Substitute { .. } => stats.substitutions += 1,
+                EditOp::Delete(_) => stats.deletions += 1,
+                EditOp::Insert(_) => stats.insertions += 1,
+            }
+        }
+        stats
+    }
+
+    /// Number of words in the reference side of the alignment.
+    pub fn reference_words(&self) -> usize {
+        self.equal + self.substitutions + self.deletions
+    }
+
+    /// Number of words in the hypothesis side of the alignment.
+    pub fn hypothesis_words(&self) -> usize {
+        self.equal + self.substitutions + self.insertions
+    }
+
+    /// Word error rate: `(S + D + I) / N_reference`.
+    ///
+    /// An empty reference yields 0.0 when the hypothesis is also empty and
+    /// 1.0 otherwise, rather than dividing by zero.
+    pub fn wer(&self) -> f64 {
+        let errors = self.substitutions + self.deletions + self.insertions;
+        match self.reference_words() {
+            0 if errors == 0 => 0.0,
+            0 => 1.0,
+            n => errors as f64 / n as f64,
+        }
+    }
+}
+
+/// A hypothesis aligned against a reference, word by word.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WordDiff {
+    pub ops: Vec<EditOp>,
+    pub stats: AlignmentStats,
+}
+
+impl WordDiff {
+    /// Normalize both texts with `opts` and align them.
+    pub fn new(reference: &str, hypothesis: &str, opts: &NormalizeOptions) -> Self {
+        let ops = align(
+            &normalize_words(reference, opts),
+            &normalize_words(hypothesis, opts),
+        );
+        let stats = AlignmentStats::from_ops(&ops);
+        Self { ops, stats }
+    }
+}
+
+impl fmt::Display for AlignmentStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} equal, {} substituted, {} deleted, {} inserted — WER {:.1}%",
+            self.equal,
+            self.substitutions,
+            self.deletions,
+            self.insertions,
+            self.wer() * 100.0,
+        )
+    }
+}
+
+// ── Rendering ────────────────────────────────────────────────────────────────
+
+const RED: &str = "\x1b[31;9m";
+const GREEN: &str = "\x1b[32m";
+const RESET: &str = "\x1b[0m";
+
+/// Render an alignment as a single word-diff line.
+///
+/// With `color`, deletions are red/struck-through and insertions green.
+/// Without it, git's word-diff markers are used: `[-old-]` and `{+new+}`.
+pub fn render_diff(ops: &[EditOp], color: bool) -> String {
+    let del = |w: &str| {
+        if color {
+            format!("{RED}{w}{RESET}")
+        } else {
+            format!("[-{w}-]")
+        }
+    };
+    let ins = |w: &str| {
+        if color {
+            format!("{GREEN}{w}{RESET}")
+        } else {
+            format!("{{+{w}+}}")
+        }
+    };
+
+    let words: Vec<String> = ops
+        .iter()
+        .map(|op| match op {
+            EditOp::Equal(w) => w.clone(),
+            EditOp::Substitute {
+                reference,
+                hypothesis,
+            } => format!("{}{}", del(reference), ins(hypothesis)),
+            EditOp::Delete(w) => del(w),
+            EditOp::Insert(w) => ins(w),
+        })
+        .collect();
+    words.join(" ")
+}
+
+// ── Transcript files ─────────────────────────────────────────────────────────
+
+/// Read transcript text from a plain-text file, a JSON result file or an
+/// SRT file.
+///
+/// JSON files are detected by content (a leading `{`) and the `text` field is
+/// extracted, so results saved by `transcribe` can be diffed directly.  SRT
+/// files (by extension) contribute their cue text without the numbers and
+/// timings.
+pub fn read_transcript(path: &Path) -> Result<String> {
+    let raw = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read transcript: {}", path.display()))?;
+
+    if path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("srt"))
+    {
+        let segments = retranscribe::parse_srt(&raw)
+            .with_context(|| format!("Failed to parse SRT transcript: {}", path.display()))?;
+        return Ok(audio_transcription::joined_text(&segments));
+    }
+
+    if !raw.trim_start().starts_with('{') {
+        return Ok(raw);
+    }
+
+    let value: serde_json::Value = serde_json::from_str(&raw)
+        .with_context(|| format!("Failed to parse JSON transcript: {}", path.display()))?;
+    value
+        .get("text")
+        .and_then(|t| t.as_str())
+        .map(str::to_string)
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "JSON transcript has no string \"text\" field: {}",
+                path.display()
+            )
+        })
+}
+
+// ── Standalone CLI entry-point ───────────────────────────────────────────────
+
+/// Machine-readable summary of a `transcribe diff` run, printed in `--json`
+/// mode.
+#[derive(Debug, Serialize)]
+pub struct DiffResult {
+    pub file_a: String,
+    pub file_b: String,
+    pub words_a: usize,
+    pub words_b: usize,
+    #[serde(flatten)]
+    pub stats: AlignmentStats,
+    /// Word error rate of B against A.
+    pub wer: f64,
+}
+
+/// Diff two saved transcripts word by word and print summary statistics.
+///
+/// `file_a` is treated as the reference, so the reported WER is "B measured
+/// against A".  No model is loaded.
+pub fn run_diff(file_a: &Path, file_b: &Path, no_color: bool, json: bool) -> Result<()> {
+    let diff = WordDiff::new(
+        &read_transcript(file_a)?,
+        &read_transcript(file_b)?,
+        &NormalizeOptions::default(),
+    );
+    let stats = diff.stats;
+
+    if json {
+        return ui::print_json(&DiffResult {
+            file_a: file_a.display().to_string(),
+            file_b: file_b.display().to_string(),
+            words_a: stats.reference_words(),
+            words_b: stats.hypothesis_words(),
+            stats,
+            wer: stats.wer(),
+        });
+    }
+
+    let color = !no_color && std::io::stdout().is_terminal();
+
+    println!(
+        "A: {} ({} words)",
+        file_a.display(),
+        stats.reference_words()
+    );
+    println!(
+        "B: {} ({} words)\n",
+        file_b.display(),
+        stats.hypothesis_words()
+    );
+    println!("{}\n", render_diff(&diff.ops, color));
+    println!("{stats}");
+
+    Ok(())
+}
+
+/// Where `wer` gets the transcript it scores.
+#[derive(Clone, Debug)]
+pub enum Hypothesis {
+    /// A saved transcript: plain text, JSON or SRT.
+    File(PathBuf),
+    /// Audio to transcribe first.
+    Audio {
+        path: PathBuf,
+        model: Option<TranscriptionModel>,
+        load: LoadOptions,
+    },
+}
+
+/// Settings for [`run_wer`], from the `wer` command line.
+#[derive(Clone, Debug)]
+pub struct WerOptions {
+    /// Ground truth, e.g. the published lyrics.
+    pub reference: PathBuf,
+    pub hypothesis: Hypothesis,
+    pub normalize: NormalizeOptions,
+    /// `--no-color`: git's word-diff markers instead of ANSI colors.
+    pub no_color: bool,
+}
+
+/// Machine-readable result of `wer`, printed in `--json` mode.
+#[derive(Debug, Serialize)]
+pub struct WerReport {
+    pub reference: String,
+    pub hypothesis: String,
+    pub reference_words: usize,
+    pub hypothesis_words: usize,
+    #[serde(flatten)]
+    pub stats: AlignmentStats,
+    pub wer: f64,
+    /// The transcript scored, when it was made by this run (`--audio`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transcript: Option<String>,
+}
+
+/// Score a transcript against a reference text: word error rate, the
+/// substitution / deletion / insertion counts and a word-level diff.
+///
+/// With [`Hypothesis::Audio`] the audio is transcribed first, so presets
+/// can be scored without saving their output.
+pub async fn run_wer(
+    options: WerOptions,
+    cancel: CancellationToken,
+    out: OutputConfig,
+) -> Result<()> {
+    let reference = read_transcript(&options.reference)?;
+    let (label, text, transcript) = match &options.hypothesis {
+        Hypothesis::File(path) => (path.display().to_string(), read_transcript(path)?, None),
+        Hypothesis::Audio { path, model, load } => {
+            let text = transcribe(path, *model, load, &cancel, out).await?;
+            (path.display().to_string(), text.clone(), Some(text))
+        }
+    };
+
+    let diff = WordDiff::new(&reference, &text, &options.normalize);
+    let stats = diff.stats;
+    if stats.reference_words() == 0 {
+        tracing::warn!(
+            "{} has no words left after normalization, so WER is only 0% or 100%",
+            options.reference.display()
+        );
+    }
+
+    if out.json {
+        return ui::print_json(&WerReport {
+            reference: options.reference.display().to_string(),
+            hypothesis: label,
+            reference_words: stats.reference_words(),
+            hypothesis_words: stats.hypothesis_words(),
+            stats,
+            wer: stats.wer(),
+            transcript,
+        });
+    }
+    if out.quiet() {
+        println!("{:.1}%", stats.wer() * 100.0);
+        return Ok(());
+    }
+
+    let color = !options.no_color && std::io::stdout().is_terminal();
+
+    println!(
+        "Reference : {} ({} words)",
+        options.reference.display(),
+        stats.reference_words()
+    );
+    println!("Hypothesis: {label} ({} words)\n", stats.hypothesis_words());
+    println!("{}\n", render_diff(&diff.ops, color));
+    println!("{stats}");
+
+    Ok(())
+}
+
+/// Load a transcription preset and transcribe `path` with its defaults.
+async fn transcribe(
+    path: &Path,
+    model: Option<TranscriptionModel>,
+    load: &LoadOptions,
+    cancel: &CancellationToken,
+    out: OutputConfig,
+) -> Result<String> {
+    if !path.exists() {
+        anyhow::bail!("Audio file not found: {}", path.display());
+    }
+    let (preset, load) = memory::choose(model.unwrap_or_default(), load)?;
+    status!(out, "Loading transcription model: {preset}");
+    let loading = AudioTranscriber::from_preset_with_options(preset, &load);
+    let transcriber = interrupt::unless_cancelled(Some(cancel), loading)
+        .await?
+        .with_cancellation(cancel.clone());
+    status!(out, "Transcribing {}", path.display());
+    Ok(transcriber.transcribe_file(path, None).await?.text)
+}
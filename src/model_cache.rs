@@ -0,0 +1,412 @@
+use anyhow::{Context, Result};
+use indicatif::HumanBytes;
+use serde::Serialize;
+use std::fmt;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+use crate::audio_transcription::TranscriptionModel;
+use crate::disk_space;
+use crate::image_generation;
+use crate::loader;
+use crate::promp_enhancer::EnhancerModel;
+use crate::ui::{self, OutputConfig, status};
+
+// ── Presets ──────────────────────────────────────────────────────────────────
+
+/// Every model repo a preset can download, as named on the command line.
+///
+/// The text presets are shared by `prompt`, `transcribe` and `chat`, so
+/// each repo appears once.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, clap::ValueEnum)]
+pub enum CachedModel {
+    #[value(name = "gemma-e2b")]
+    #[serde(rename = "gemma-e2b")]
+    GemmaE2b,
+    #[value(name = "gemma-e4b")]
+    #[serde(rename = "gemma-e4b")]
+    GemmaE4b,
+    #[value(name = "phi-3.5-mini")]
+    #[serde(rename = "phi-3.5-mini")]
+    Phi35Mini,
+    #[value(name = "qwen2.5-1.5b")]
+    #[serde(rename = "qwen2.5-1.5b")]
+    Qwen25_1_5b,
+    #[value(name = "llama-3.2-3b")]
+    #[serde(rename = "llama-3.2-3b")]
+    Llama32_3b,
+    #[value(name = "flux-schnell")]
+    #[serde(rename = "flux-schnell")]
+    FluxSchnell,
+    #[cfg(feature = "ci-models")]
+    #[value(name = "micro")]
+    #[serde(rename = "micro")]
+    Micro,
+}
+
+impl CachedModel {
+    pub const ALL: &[Self] = &[
+        Self::GemmaE2b,
+        Self::GemmaE4b,
+        Self::Phi35Mini,
+        Self::Qwen25_1_5b,
+        Self::Llama32_3b,
+        Self::FluxSchnell,
+        #[cfg(feature = "ci-models")]
+        Self::Micro,
+    ];
+
+    pub fn model_id(self) -> &'static str {
+        match self {
+            Self::GemmaE2b => EnhancerModel::GemmaE2b.model_id(),
+            Self::GemmaE4b => EnhancerModel::GemmaE4b.model_id(),
+            Self::Phi35Mini => EnhancerModel::Phi35Mini.model_id(),
+            Self::Qwen25_1_5b => EnhancerModel::Qwen25_1_5b.model_id(),
+            Self::Llama32_3b => EnhancerModel::Llama32_3b.model_id(),
+            Self::FluxSchnell => image_generation::DEFAULT_MODEL,
+            #[cfg(feature = "ci-models")]
+            Self::Micro => EnhancerModel::Micro.model_id(),
+        }
+    }
+
+    /// Size of the repo's weights; see
+    /// [`EnhancerModel::download_size_bytes`].
+    pub fn download_size_bytes(self) -> u64 {
+        match self {
+            Self::GemmaE2b => EnhancerModel::GemmaE2b.download_size_bytes(),
+            Self::GemmaE4b => EnhancerModel::GemmaE4b.download_size_bytes(),
+            Self::Phi35Mini => EnhancerModel::Phi35Mini.download_size_bytes(),
+            Self::Qwen25_1_5b => EnhancerModel::Qwen25_1_5b.download_size_bytes(),
+            Self::Llama32_3b => EnhancerModel::Llama32_3b.download_size_bytes(),
+            Self::FluxSchnell => image_generation::DIFFUSION_DOWNLOAD_BYTES,
+            #[cfg(feature = "ci-models")]
+            Self::Micro => EnhancerModel::Micro.download_size_bytes(),
+        }
+    }
+
+    /// The name `models` takes on the command line, e.g. "gemma-e2b".
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::GemmaE2b => "gemma-e2b",
+            Self::GemmaE4b => "gemma-e4b",
+            Self::Phi35Mini => "phi-3.5-mini",
+            Self::Qwen25_1_5b => "qwen2.5-1.5b",
+            Self::Llama32_3b => "llama-3.2-3b",
+            Self::FluxSchnell => "flux-schnell",
+            #[cfg(feature = "ci-models")]
+            Self::Micro => "micro",
+        }
+    }
+}
+
+impl From<EnhancerModel> for CachedModel {
+    fn from(preset: EnhancerModel) -> Self {
+        match preset {
+            EnhancerModel::GemmaE2b => Self::GemmaE2b,
+            EnhancerModel::GemmaE4b => Self::GemmaE4b,
+            EnhancerModel::Phi35Mini => Self::Phi35Mini,
+            EnhancerModel::Qwen25_1_5b => Self::Qwen25_1_5b,
+            EnhancerModel::Llama32_3b => Self::Llama32_3b,
+            #[cfg(feature = "ci-models")]
+            EnhancerModel::Micro => Self::Micro,
+        }
+    }
+}
+
+impl From<TranscriptionModel> for CachedModel {
+    fn from(preset: TranscriptionModel) -> Self {
+        match preset {
+            TranscriptionModel::GemmaE2b => Self::GemmaE2b,
+            TranscriptionModel::GemmaE4b => Self::GemmaE4b,
+        }
+    }
+}
+
+impl fmt::Display for CachedModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.name(), self.model_id())
+    }
+}
+
+// ── Cache layout ─────────────────────────────────────────────────────────────
+
+/// Suffixes of blobs still being written: hf-hub's async and sync APIs and
+/// the Python `huggingface_hub` client respectively.
+const PARTIAL_SUFFIXES: &[&str] = &[".sync.part", ".part", ".incomplete"];
+
+/// Weight files at least one of which a usable snapshot must contain.
+const WEIGHT_EXTENSIONS: &[&str] = &["safetensors", "gguf", "bin"];
+
+/// Files `prefetch` skips: alternative weight formats mistral.rs never
+/// reads, which would otherwise double the download.
+const SKIPPED_EXTENSIONS: &[&str] = &[
+    "bin", "pth", "pt", "ckpt", "gguf", "onnx", "msgpack", "h5", "ot", "tflite",
+];
+
+/// Whether a cached repo can be loaded without touching the network.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheState {
+    /// Nothing downloaded.
+    Missing,
+    /// Some files are there, but a download was interrupted or the
+    /// snapshot has no weights yet.
+    Partial,
+    /// The `main` snapshot resolves and contains weights.
+    Complete,
+}
+
+impl fmt::Display for CacheState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Missing => "not downloaded",
+            Self::Partial => "partial",
+            Self::Complete => "complete",
+        })
+    }
+}
+
+/// What's on disk for `preset`'s repo.
+pub fn scan_preset(preset: CachedModel) -> RepoScan {
+    loader::repo_cache_dir(preset.model_id()).map_or(RepoScan::MISSING, |dir| scan_repo(&dir))
+}
+
+/// What's on disk for one hub repo (`models--org--name`).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct RepoScan {
+    pub state: CacheState,
+    /// Bytes on disk (blobs only; snapshot symlinks aren't counted twice).
+    pub size_bytes: u64,
+    /// Commit `refs/main` points at.
+    pub revision: Option<String>,
+}
+
+impl RepoScan {
+    const MISSING: Self = Self {
+        state: CacheState::Missing,
+        size_bytes: 0,
+        revision: None,
+    };
+}
+
+/// Inspect a repo directory laid out like the HuggingFace hub cache:
+/// `blobs/`, `refs/main` and `snapshots/<revision>/` with links into
+/// `blobs/`.
+pub fn scan_repo(dir: &Path) -> RepoScan {
+    if !dir.is_dir() {
+        return RepoScan::MISSING;
+    }
+    let size_bytes = loader::dir_size(dir);
+    let revision = std::fs::read_to_string(dir.join("refs/main"))
+        .ok()
+        .map(|rev| rev.trim().to_string())
+        .filter(|rev| !rev.is_empty());
+
+    let interrupted = std::fs::read_dir(dir.join("blobs"))
+        .map(|entries| {
+            entries.filter_map(|entry| entry.ok()).any(|entry| {
+                let name = entry.file_name();
+                let name = name.to_string_lossy();
+                PARTIAL_SUFFIXES.iter().any(|suffix| name.ends_with(suffix))
+            })
+        })
+        .unwrap_or(false);
+    let has_weights = revision
+        .as_ref()
+        .map(|rev| snapshot_has_weights(&dir.join("snapshots").join(rev)))
+        .unwrap_or(false);
+
+    let state = if !interrupted && has_weights {
+        CacheState::Complete
+    } else {
+        CacheState::Partial
+    };
+    RepoScan {
+        state,
+        size_bytes,
+        revision,
+    }
+}
+
+/// Every link in the snapshot resolves, and at least one is a weight file.
+fn snapshot_has_weights(snapshot: &Path) -> bool {
+    let mut files = Vec::new();
+    if collect_files(snapshot, &mut files).is_err() {
+        return false;
+    }
+    // `exists` follows the link, so a dangling one (blob never finished)
+    // reads as missing.
+    files.iter().all(|file| file.exists())
+        && files.iter().any(|file| {
+            file.extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| WEIGHT_EXTENSIONS.contains(&ext))
+        })
+}
+
+/// Paths of the non-directory entries under `dir`, without following links.
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            collect_files(&entry.path(), files)?;
+        } else {
+            files.push(entry.path());
+        }
+    }
+    Ok(())
+}
+
+/// Whether `prefetch` downloads `filename` (a path within the repo).
+fn should_prefetch(filename: &str) -> bool {
+    let ext = Path::new(filename)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("");
+    !SKIPPED_EXTENSIONS.contains(&ext)
+}
+
+// ── models list ──────────────────────────────────────────────────────────────
+
+/// One row of `models list`.
+#[derive(Debug, Serialize)]
+struct ListEntry {
+    preset: CachedModel,
+    model_id: &'static str,
+    path: Option<String>,
+    #[serde(flatten)]
+    scan: RepoScan,
+}
+
+/// Print every preset's cache state, size and location.
+pub fn list(out: OutputConfig) -> Result<()> {
+    let cache = loader::hf_hub_cache()
+        .context("Can't locate the HuggingFace cache; set HF_HOME or --cache-dir")?;
+    let entries: Vec<ListEntry> = CachedModel::ALL
+        .iter()
+        .copied()
+        .map(|preset| {
+            let dir = loader::repo_cache_dir(preset.model_id());
+            ListEntry {
+                preset,
+                model_id: preset.model_id(),
+                path: dir.as_ref().map(|dir| dir.display().to_string()),
+                scan: scan_preset(preset),
+            }
+        })
+        .collect();
+    if out.json {
+        return ui::print_json(&entries);
+    }
+
+    status!(out, "HuggingFace cache: {}\n", cache.display());
+    println!("{:<14} {:<34} {:>10}  STATE", "PRESET", "REPO", "SIZE");
+    for entry in &entries {
+        let size = match entry.scan.state {
+            CacheState::Missing => "-".to_string(),
+            _ => HumanBytes(entry.scan.size_bytes).to_string(),
+        };
+        println!(
+            "{:<14} {:<34} {:>10}  {}",
+            entry.preset.name(),
+            entry.model_id,
+            size,
+            entry.scan.state
+        );
+    }
+    let total: u64 = entries.iter().map(|entry| entry.scan.size_bytes).sum();
+    status!(out, "\nTotal: {}", HumanBytes(total));
+    Ok(())
+}
+
+// ── models prefetch ──────────────────────────────────────────────────────────
+
+/// Download `preset`'s repo into the hub cache without loading it, so the
+/// next run starts straight away.  Files already cached are skipped.
+pub async fn prefetch(preset: CachedModel, out: OutputConfig) -> Result<()> {
+    let scan = download(preset, out).await?;
+    if out.json {
+        return ui::print_json(&scan);
+    }
+    println!("{preset}: {} ({})", scan.state, HumanBytes(scan.size_bytes));
+    Ok(())
+}
+
+/// Fetch `preset`'s files with progress on stderr and return what ended
+/// up in the cache.  Used by `models prefetch` and `setup`.
+pub async fn download(preset: CachedModel, out: OutputConfig) -> Result<RepoScan> {
+    let cache = loader::hf_hub_cache()
+        .context("Can't locate the HuggingFace cache; set HF_HOME or --cache-dir")?;
+    let model_id = preset.model_id();
+    disk_space::preflight(model_id, preset.download_size_bytes())?;
+    status!(out, "Prefetching {preset} into {}", cache.display());
+
+    let mut builder = hf_hub::api::tokio::ApiBuilder::new()
+        .with_cache_dir(cache)
+        .with_progress(loader::progress_enabled());
+    if let Ok(token) = std::env::var("HF_TOKEN") {
+        builder = builder.with_token(Some(token));
+    }
+    let api = builder
+        .build()
+        .context("Failed to set up the HuggingFace client")?;
+    let repo = &api.model(model_id.to_string());
+
+    loader::with_retries(model_id, || async move {
+        let info = repo
+            .info()
+            .await
+            .with_context(|| format!("Failed to list the files of {model_id}"))?;
+        let files: Vec<&str> = info
+            .siblings
+            .iter()
+            .map(|sibling| sibling.rfilename.as_str())
+            .filter(|name| should_prefetch(name))
+            .collect();
+        for (i, file) in files.iter().enumerate() {
+            status!(out, "  [{}/{}] {file}", i + 1, files.len());
+            repo.get(file)
+                .await
+                .with_context(|| format!("Failed to download {file} from {model_id}"))?;
+        }
+        Ok(())
+    })
+    .await?;
+
+    loader::repo_cache_dir(model_id)
+        .map(|dir| scan_repo(&dir))
+        .context("The downloaded repo is missing from the cache")
+}
+
+// ── models rm ────────────────────────────────────────────────────────────────
+
+/// Delete `preset`'s cached repo, asking first unless `yes`.
+pub fn remove(preset: CachedModel, yes: bool, out: OutputConfig) -> Result<()> {
+    let dir = loader::repo_cache_dir(preset.model_id())
+        .context("Can't locate the HuggingFace cache; set HF_HOME or --cache-dir")?;
+    if !dir.is_dir() {
+        anyhow::bail!("{preset} is not in the cache ({})", dir.display());
+    }
+    let size = HumanBytes(loader::dir_size(&dir));
+
+    if !yes {
+        if !std::io::stdin().is_terminal() {
+            anyhow::bail!(
+                "Refusing to delete {} without confirmation; pass --yes",
+                dir.display()
+            );
+        }
+        eprint!("Delete {preset} ({size}) at {}? [y/N] ", dir.display());
+        std::io::stderr().flush()?;
+        let mut answer = String::new();
+        std::io::stdin().read_line(&mut answer)?;
+        if !matches!(answer.trim(), "y" | "Y" | "yes") {
+            status!(out, "Kept {preset}.");
+            return Ok(());
+        }
+    }
+
+    std::fs::remove_dir_all(&dir).with_context(|| format!("Failed to delete {}", dir.display()))?;
+    status!(out, "Deleted {preset}, freeing {size}.");
+    Ok(())
+}
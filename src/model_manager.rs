@@ -0,0 +1,79 @@
+use anyhow::Result;
+use mistralrs::{IsqType, Model, ModelDType};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::sync::OnceCell;
+
+// ── ModelKey ─────────────────────────────────────────────────────────────────
+
+/// Identifies a built model: the same weights loaded with the same dtype /
+/// ISQ settings can be shared between `PromptEnhancer`, `CliChat` and
+/// `AudioTranscriber`.
+///
+/// mistral.rs' dtype and ISQ enums aren't guaranteed to implement `Hash`, so
+/// the key stores their `Debug` names.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ModelKey {
+    pub model_id: String,
+    pub dtype: Option<String>,
+    pub isq: Option<String>,
+}
+
+impl ModelKey {
+    pub fn new(
+        model_id: impl Into<String>,
+        dtype: Option<ModelDType>,
+        isq: Option<IsqType>,
+    ) -> Self {
+        Self {
+            model_id: model_id.into(),
+            dtype: dtype.map(|d| format!("{d:?}")),
+            isq: isq.map(|q| format!("{q:?}")),
+        }
+    }
+}
+
+// ── ModelManager ─────────────────────────────────────────────────────────────
+
+/// A cache of built models, keyed by [`ModelKey`].
+///
+/// Loading Gemma 3n takes minutes, so code that uses several capabilities
+/// back to back (enhance, then chat, then transcribe) should build each set of
+/// weights once.  The manager is safe to share between tasks: concurrent
+/// requests for the same key wait on a single build, while different keys
+/// build independently.
+#[derive(Default)]
+pub struct ModelManager {
+    slots: Mutex<HashMap<ModelKey, Arc<OnceCell<Arc<Model>>>>>,
+}
+
+impl ModelManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the cached model for `key`, or run `build` to create it.
+    ///
+    /// If `build` fails nothing is cached, so a later call retries.
+    pub async fn get_or_build<F, Fut>(&self, key: ModelKey, build: F) -> Result<Arc<Model>>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Model>>,
+    {
+        let slot = {
+            let mut slots = self.slots.lock().expect("model cache lock poisoned");
+            slots.entry(key).or_default().clone()
+        };
+        let model = slot
+            .get_or_try_init(|| async { build().await.map(Arc::new) })
+            .await?;
+        Ok(model.clone())
+    }
+
+    /// Whether a model for `key` has finished building.
+    pub fn contains(&self, key: &ModelKey) -> bool {
+        let slots = self.slots.lock().expect("model cache lock poisoned");
+        slots.get(key).is_some_and(|slot| slot.initialized())
+    }
+}
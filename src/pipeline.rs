@@ -0,0 +1,394 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::audio_probe;
+use crate::audio_transcription::{AudioTranscriber, TranscriptionModel};
+use crate::image_generation::{ImageGenerator, ImageOptions};
+use crate::image_metadata::MetadataOutput;
+use crate::load_options::LoadOptions;
+use crate::memory;
+use crate::model_manager::ModelManager;
+use crate::promp_enhancer::{
+    self, EnhancerBackend, EnhancerModel, MAX_LYRICS_WORDS, PromptEnhance, PromptEnhancer,
+};
+use crate::prompt_limits::PromptBudget;
+use crate::remote_enhancer::{self, RemoteArgs, RemoteConfig};
+use crate::summarizer::{self, Summarizer, SummaryStyle};
+use crate::template_enhancer::TemplateEnhancer;
+use crate::ui::{self, OutputConfig, detail, fmt_duration, status};
+
+// ── Stages ───────────────────────────────────────────────────────────────────
+
+/// One step of the lyrics-to-art pipeline.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Stage {
+    Transcribe,
+    Prompt,
+    Image,
+}
+
+impl Stage {
+    /// 1-based position, for "stage 2/3" banners.
+    fn number(self) -> usize {
+        match self {
+            Self::Transcribe => 1,
+            Self::Prompt => 2,
+            Self::Image => 3,
+        }
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Transcribe => "transcribe",
+            Self::Prompt => "prompt",
+            Self::Image => "image",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Wall-clock time of one finished stage, model loading included.
+#[derive(Clone, Debug, Serialize)]
+pub struct StageTiming {
+    pub stage: Stage,
+    pub secs: f64,
+}
+
+// ── Options and result ───────────────────────────────────────────────────────
+
+/// Settings for [`run`].
+#[derive(Clone, Debug)]
+pub struct PipelineOptions {
+    /// Visual style passed to the prompt stage (e.g. "ukiyo-e woodblock").
+    pub style: Option<String>,
+    pub transcription_model: TranscriptionModel,
+    pub enhancer_model: EnhancerModel,
+    /// `--enhancer template` skips the enhancer model entirely;
+    /// `--enhancer remote` asks the API in [`remote`](Self::remote).
+    pub enhancer: EnhancerBackend,
+    /// `--remote-url` / `--remote-model` for `--enhancer remote`.
+    pub remote: RemoteArgs,
+    /// `--isq` / `--dtype` for the transcription and enhancer models.
+    pub load: LoadOptions,
+    /// Directory for `lyrics.txt`, `prompt.txt` and `artwork.png` (and
+    /// `summary.txt` when the lyrics were summarized).
+    /// Defaults to `<audio stem>-art` in the current directory.
+    pub out_dir: Option<PathBuf>,
+    pub width: Option<usize>,
+    pub height: Option<usize>,
+    /// Overwrite an existing `artwork.png`.
+    pub force: bool,
+}
+
+/// Everything a pipeline run produced, printed in `--json` mode.
+#[derive(Debug, Serialize)]
+pub struct PipelineResult {
+    pub audio: String,
+    pub lyrics: String,
+    pub lyrics_path: String,
+    /// Summary the prompt was written from, when the lyrics were longer
+    /// than [`MAX_LYRICS_WORDS`].
+    pub summary: Option<String>,
+    pub prompt: String,
+    pub prompt_path: String,
+    pub image_path: String,
+    pub stages: Vec<StageTiming>,
+}
+
+/// Tracks finished stages and the files already on disk, so a failure can
+/// say where it happened and what was kept.
+struct Progress {
+    out: OutputConfig,
+    timings: Vec<StageTiming>,
+    written: Vec<PathBuf>,
+}
+
+impl Progress {
+    fn start(&self, stage: Stage, what: &str) -> Instant {
+        status!(self.out, "\n[{}/3] {what}", stage.number());
+        Instant::now()
+    }
+
+    fn finish(&mut self, stage: Stage, started: Instant) {
+        let elapsed = started.elapsed();
+        status!(self.out, "  done in {}", fmt_duration(elapsed));
+        self.timings.push(StageTiming {
+            stage,
+            secs: elapsed.as_secs_f64(),
+        });
+    }
+
+    fn write(&mut self, path: &Path, contents: &str) -> Result<()> {
+        std::fs::write(path, contents)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        self.written.push(path.to_path_buf());
+        Ok(())
+    }
+
+    /// Context for an error in `stage`, listing the outputs that were kept.
+    fn failure(&self, stage: Stage) -> String {
+        let mut message = format!("Pipeline failed at stage {}/3 ({stage})", stage.number());
+        if !self.written.is_empty() {
+            let kept: Vec<String> = self
+                .written
+                .iter()
+                .map(|p| p.display().to_string())
+                .collect();
+            message.push_str(&format!("; intermediate outputs kept: {}", kept.join(", ")));
+        }
+        message
+    }
+}
+
+// ── Helpers ──────────────────────────────────────────────────────────────────
+
+/// `<audio stem>-art` in the current directory.
+pub fn default_out_dir(audio_path: &Path) -> PathBuf {
+    let stem = audio_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "pipeline".to_string());
+    PathBuf::from(format!("{stem}-art"))
+}
+
+// ── CLI entry-point ──────────────────────────────────────────────────────────
+
+/// Turn a vocal stem into cover art: transcribe the lyrics, distill them into
+/// an image prompt, then render it with FLUX.
+///
+/// Each stage loads its model, does its work and drops the model before the
+/// next one loads, so peak memory is that of the largest model rather than
+/// all three.  The one exception is a prompt enhancer with the same weights
+/// and settings as the transcriber (Gemma 3n by default), which reuses the
+/// transcription model instead of loading it again.  Lyrics and prompt are
+/// written to the output directory as soon as they exist, so a later
+/// failure doesn't lose them.
+pub async fn run(audio_path: PathBuf, options: PipelineOptions, out: OutputConfig) -> Result<()> {
+    // Probe the audio first: a file that can't be decoded should fail now,
+    // not after the transcription model has loaded.
+    let probe = audio_probe::probe_audio(&audio_path)?;
+    if options.load.uqff.has_explicit_path() {
+        anyhow::bail!(
+            "--save-uqff / --from-uqff name a single model's weights; use --cache-quantized with lyrics-art"
+        );
+    }
+
+    let out_dir = options
+        .out_dir
+        .clone()
+        .unwrap_or_else(|| default_out_dir(&audio_path));
+    let image_options = ImageOptions {
+        width: options.width,
+        height: options.height,
+        num_images: 1,
+        out: Some(out_dir.join("artwork.png")),
+        force: options.force,
+        negative_prompt: None,
+        guidance: None,
+        metadata: MetadataOutput::default(),
+        grid: None,
+        sizes: Vec::new(),
+    };
+    // Catch bad dimensions / an existing image before spending minutes on
+    // the first two stages.
+    image_options.generation_params()?;
+    image_options.check_overwrite()?;
+    // A missing --remote-model should fail now too.
+    let remote = match options.enhancer {
+        EnhancerBackend::Remote => Some(options.remote.resolve()?),
+        _ if options.remote.is_set() => {
+            anyhow::bail!("--remote-url / --remote-model / --remote-timeout need --enhancer remote")
+        }
+        _ => None,
+    };
+    std::fs::create_dir_all(&out_dir)
+        .with_context(|| format!("Failed to create output directory: {}", out_dir.display()))?;
+
+    let mut models = ModelManager::new();
+    let mut progress = Progress {
+        out,
+        timings: Vec::new(),
+        written: Vec::new(),
+    };
+
+    // ── Stage 1: transcribe ──
+    let stage = Stage::Transcribe;
+    let started = progress.start(
+        stage,
+        &format!(
+            "Transcribing {} ({probe}) with {}",
+            audio_path.display(),
+            options.transcription_model
+        ),
+    );
+    let lyrics_path = out_dir.join("lyrics.txt");
+    let lyrics = async {
+        let (model, load) = memory::choose(options.transcription_model, &options.load)?;
+        let load_start = Instant::now();
+        let transcriber = AudioTranscriber::from_preset_with_manager(model, &load, &models).await?;
+        detail!(
+            out,
+            "  model loaded in {}",
+            fmt_duration(load_start.elapsed())
+        );
+        let result = transcriber.transcribe_file(&audio_path, None).await?;
+        if result.text.trim().is_empty() {
+            anyhow::bail!("No lyrics were transcribed from {}", audio_path.display());
+        }
+        progress.write(&lyrics_path, &result.text)?;
+        Ok(result.text)
+    }
+    .await
+    .with_context(|| progress.failure(stage))?;
+    progress.finish(stage, started);
+    status!(out, "\nLyrics ({}):\n{lyrics}", lyrics_path.display());
+
+    // ── Stage 2: prompt ──
+    // Keep the transcription model only if the enhancer is the same one.
+    let shared = options.enhancer == EnhancerBackend::Model
+        && models.contains(&options.enhancer_model.model_key(&options.load));
+    if !shared {
+        models = ModelManager::new();
+    }
+    let stage = Stage::Prompt;
+    let enhancer_name = match options.enhancer {
+        EnhancerBackend::Model => options.enhancer_model.to_string(),
+        EnhancerBackend::Template => "the template enhancer".to_string(),
+        EnhancerBackend::Remote => remote.as_ref().map(RemoteConfig::label).unwrap_or_default(),
+    };
+    let started = progress.start(
+        stage,
+        &format!("Writing an image prompt with {enhancer_name}"),
+    );
+    let prompt_path = out_dir.join("prompt.txt");
+    let summary_path = out_dir.join("summary.txt");
+    let (prompt, summary) = async {
+        let style = options.style.as_deref();
+        let (prompt, summary) = match (options.enhancer, remote) {
+            (EnhancerBackend::Model, _) => {
+                let model = options.enhancer_model;
+                let (model, load) = if shared {
+                    (model, options.load.clone())
+                } else {
+                    memory::choose(model, &options.load)?
+                };
+                let load_start = Instant::now();
+                let enhancer =
+                    PromptEnhancer::from_preset_with_manager(model, &load, &models).await?;
+                if shared {
+                    detail!(out, "  reusing the transcription model");
+                } else {
+                    detail!(
+                        out,
+                        "  model loaded in {}",
+                        fmt_duration(load_start.elapsed())
+                    );
+                }
+                // Long lyrics are summarized with the same model rather than
+                // cut, so the prompt draws on the whole song.
+                let words = summarizer::word_count(&lyrics);
+                if words > MAX_LYRICS_WORDS {
+                    status!(out, "  summarizing {words} words of lyrics");
+                    let summary = Summarizer::from_model(enhancer.shared_model())
+                        .summarize(&lyrics, SummaryStyle::Theme, summarizer::DEFAULT_MAX_WORDS)
+                        .await?;
+                    progress.write(&summary_path, &summary.text)?;
+                    let prompt = enhancer.prompt_from_summary(&summary.text, style).await?;
+                    (prompt, Some(summary.text))
+                } else {
+                    (enhancer.prompt_from_lyrics(&lyrics, style).await?, None)
+                }
+            }
+            (EnhancerBackend::Template, _) => {
+                let prompt = TemplateEnhancer::new()
+                    .prompt_from_lyrics(&lyrics, style)
+                    .await?;
+                (prompt, None)
+            }
+            (EnhancerBackend::Remote, config) => {
+                let config = config.context("--enhancer remote has no --remote-model")?;
+                let enhancer = remote_enhancer::connect(
+                    config,
+                    promp_enhancer::DEFAULT_SAMPLER,
+                    PromptBudget::CLIP,
+                )?;
+                (enhancer.prompt_from_lyrics(&lyrics, style).await?, None)
+            }
+        };
+        progress.write(&prompt_path, &prompt)?;
+        Ok::<_, anyhow::Error>((prompt, summary))
+    }
+    .await
+    .with_context(|| progress.failure(stage))?;
+    progress.finish(stage, started);
+    if let Some(summary) = &summary {
+        status!(out, "\nSummary ({}):\n{summary}", summary_path.display());
+    }
+    status!(out, "\nPrompt:\n  \"{prompt}\"");
+    // Release a model kept for the prompt before FLUX loads.
+    drop(models);
+
+    // ── Stage 3: image ──
+    let stage = Stage::Image;
+    let started = progress.start(stage, "Rendering artwork with FLUX.1-schnell");
+    let image = async {
+        let load_start = Instant::now();
+        let generator = ImageGenerator::new().await?;
+        detail!(
+            out,
+            "  model loaded in {}",
+            fmt_duration(load_start.elapsed())
+        );
+        let request = image_options.request_with_budget(&prompt, generator.prompt_budget())?;
+        let result = generator.generate(request).await?;
+        result
+            .images
+            .into_iter()
+            .next()
+            .map(|image| image.path)
+            .context("The diffusion model returned no image")
+    }
+    .await
+    .with_context(|| progress.failure(stage))?;
+    progress.finish(stage, started);
+
+    if out.json {
+        return ui::print_json(&PipelineResult {
+            audio: audio_path.display().to_string(),
+            lyrics,
+            lyrics_path: lyrics_path.display().to_string(),
+            summary,
+            prompt,
+            prompt_path: prompt_path.display().to_string(),
+            image_path: image,
+            stages: progress.timings,
+        });
+    }
+
+    if out.quiet() {
+        println!("{image}");
+        return Ok(());
+    }
+    println!("\nArtwork saved to {image}");
+    let total: f64 = progress.timings.iter().map(|t| t.secs).sum();
+    println!("Stage timings:");
+    for timing in &progress.timings {
+        println!(
+            "  {:<10} {}",
+            timing.stage,
+            fmt_duration(Duration::from_secs_f64(timing.secs))
+        );
+    }
+    println!(
+        "  {:<10} {}",
+        "total",
+        fmt_duration(Duration::from_secs_f64(total))
+    );
+    Ok(())
+}
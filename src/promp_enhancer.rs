@@ -1,13 +1,39 @@
 #![allow(dead_code)]
 
-use anyhow::Result;
-use mistralrs::{
-    IsqType, Model, ModelDType, RequestBuilder, TextMessageRole, TextModelBuilder,
-    VisionModelBuilder,
-};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use mistralrs::{IsqType, Model, ModelDType, RequestBuilder, Response, TextMessageRole};
+use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::io::{self, IsTerminal, Write};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+use crate::cli_chat::estimate_tokens;
+use crate::clipboard;
+use crate::disk_space;
+use crate::enhancer_chain::{self, CliBuilder, FailedLoad, OptionsBuilder};
+use crate::error::Error;
+use crate::load_options::{GB, LoadOptions, LoadSettings};
+use crate::loader::{self, ModelKind};
+use crate::local_model::LocalModel;
+use crate::memory::{self, MemoryFootprint};
+use crate::memstats::{LoadMemory, MemorySnapshot};
+use crate::model_manager::{ModelKey, ModelManager};
+use crate::prompt_limits::{PromptBudget, WordCutoff, truncate_to_words};
+use crate::prompt_session;
+use crate::request_queue::RequestQueue;
+use crate::sampler::{self, SamplerArgs, SamplerConfig};
+use crate::sessions::Session;
+use crate::song_tags;
+use crate::styles::{self, StylePreset};
+use crate::timeout;
+use crate::ui::{self, OutputConfig, detail, fmt_duration, status};
+use crate::uqff::UqffArgs;
+use crate::usage::Usage;
+
 // ── Model presets ────────────────────────────────────────────────────────────
 
 /// Available prompt-enhancer model presets.
@@ -28,6 +54,19 @@ pub enum EnhancerModel {
     /// Phi-3.5-mini — strongest quality, larger memory footprint (~2.8 GB with Q4K).
     #[value(name = "phi-3.5-mini")]
     Phi35Mini,
+
+    /// Qwen2.5-1.5B-Instruct — very small and fast, follows instructions well (~1.8 GB with Q8_0).
+    #[value(name = "qwen2.5-1.5b")]
+    Qwen25_1_5b,
+
+    /// Llama 3.2 3B Instruct — strong small model; gated, needs Meta's license accepted (~2.3 GB with Q4K).
+    #[value(name = "llama-3.2-3b")]
+    Llama32_3b,
+
+    /// SmolLM2-135M-Instruct — tiny and low quality, for CI smoke runs (~0.1 GB with Q4K).
+    #[cfg(feature = "ci-models")]
+    #[value(name = "micro")]
+    Micro,
 }
 
 impl EnhancerModel {
@@ -37,6 +76,10 @@ impl EnhancerModel {
             Self::GemmaE2b => "google/gemma-3n-E2B-it",
             Self::GemmaE4b => "google/gemma-3n-E4B-it",
             Self::Phi35Mini => "microsoft/Phi-3.5-mini-instruct",
+            Self::Qwen25_1_5b => "Qwen/Qwen2.5-1.5B-Instruct",
+            Self::Llama32_3b => "meta-llama/Llama-3.2-3B-Instruct",
+            #[cfg(feature = "ci-models")]
+            Self::Micro => MICRO_MODEL_ID,
         }
     }
 
@@ -46,6 +89,10 @@ impl EnhancerModel {
             Self::GemmaE2b => "Gemma 3n E2B",
             Self::GemmaE4b => "Gemma 3n E4B",
             Self::Phi35Mini => "Phi-3.5-mini",
+            Self::Qwen25_1_5b => "Qwen2.5-1.5B",
+            Self::Llama32_3b => "Llama 3.2 3B",
+            #[cfg(feature = "ci-models")]
+            Self::Micro => "SmolLM2-135M",
         }
     }
 
@@ -55,47 +102,139 @@ impl EnhancerModel {
             Self::GemmaE2b => "~1.5 GB (Q4K)",
             Self::GemmaE4b => "~8 GB (F16)",
             Self::Phi35Mini => "~2.8 GB (Q4K)",
+            Self::Qwen25_1_5b => "~1.8 GB (Q8_0)",
+            Self::Llama32_3b => "~2.3 GB (Q4K)",
+            #[cfg(feature = "ci-models")]
+            Self::Micro => "~0.1 GB (Q4K)",
         }
     }
 
-    /// Build the [`Model`] with the optimal dtype / ISQ settings for this
-    /// preset.
-    ///
-    /// Gemma 3n uses `Gemma3nForConditionalGeneration` (a multimodal
-    /// architecture), so mistral.rs classifies it as a **vision** model even
-    /// when used for text-only chat.  We therefore load it via
-    /// [`VisionModelBuilder`].  Phi-3.5-mini is a pure text model and uses
-    /// [`TextModelBuilder`] as usual.
-    async fn build_model(self) -> Result<Model> {
+    /// Effective parameter count in billions, for memory estimates when
+    /// `--isq` / `--dtype` override the preset.
+    fn approx_params_billions(self) -> f64 {
+        match self {
+            Self::GemmaE2b => 2.0,
+            Self::GemmaE4b => 4.0,
+            Self::Phi35Mini => 3.8,
+            Self::Qwen25_1_5b => 1.5,
+            Self::Llama32_3b => 3.2,
+            #[cfg(feature = "ci-models")]
+            Self::Micro => 0.135,
+        }
+    }
+
+    /// Memory footprint with `opts` applied: the preset string when the
+    /// settings are unchanged, otherwise an estimate.
+    pub fn memory_estimate(self, opts: &LoadOptions) -> String {
+        let settings = opts.apply(self.default_settings());
+        if settings == self.default_settings() {
+            self.approx_memory().to_string()
+        } else {
+            settings.approx_memory(self.approx_params_billions())
+        }
+    }
+
+    /// [`approx_memory`](Self::approx_memory) in bytes.
+    pub fn approx_bytes(self) -> u64 {
+        let bytes = match self {
+            Self::GemmaE2b => 1.5 * GB,
+            Self::GemmaE4b => 8.0 * GB,
+            Self::Phi35Mini => 2.8 * GB,
+            Self::Qwen25_1_5b => 1.8 * GB,
+            Self::Llama32_3b => 2.3 * GB,
+            #[cfg(feature = "ci-models")]
+            Self::Micro => 0.1 * GB,
+        };
+        bytes as u64
+    }
+
+    /// Size of the weights downloaded on first use, checked against free
+    /// disk space before the download starts.  ISQ quantizes after the
+    /// download, so this is the repo's 16-bit safetensors.
+    pub fn download_size_bytes(self) -> u64 {
+        let bytes = match self {
+            Self::GemmaE2b => 10.9 * GB,
+            Self::GemmaE4b => 15.7 * GB,
+            Self::Phi35Mini => 7.7 * GB,
+            Self::Qwen25_1_5b => 3.1 * GB,
+            Self::Llama32_3b => 6.5 * GB,
+            #[cfg(feature = "ci-models")]
+            Self::Micro => MICRO_DOWNLOAD_BYTES,
+        };
+        bytes as u64
+    }
+
+    /// The preset's default dtype / ISQ.
+    pub fn default_settings(self) -> LoadSettings {
         match self {
             // E2B is the "on-device" pick — quantise aggressively to fit in
             // iPhone memory alongside the diffusion model.
-            Self::GemmaE2b => {
-                VisionModelBuilder::new(self.model_id())
-                    .with_isq(IsqType::Q4K)
-                    .with_logging()
-                    .build()
-                    .await
-            }
+            Self::GemmaE2b => LoadSettings::isq(IsqType::Q4K),
 
             // E4B in full F16 — the sweet spot on a Mac with ≥16 GB RAM.
-            Self::GemmaE4b => {
-                VisionModelBuilder::new(self.model_id())
-                    .with_dtype(ModelDType::F16)
-                    .with_logging()
-                    .build()
-                    .await
-            }
+            Self::GemmaE4b => LoadSettings::dtype(ModelDType::F16),
 
             // Phi-3.5-mini at 3.8 B params is too large for F16 on most
             // laptops, so default to Q4K like the upstream examples.
-            Self::Phi35Mini => {
-                TextModelBuilder::new(self.model_id())
-                    .with_isq(IsqType::Q4K)
-                    .with_logging()
-                    .build()
-                    .await
-            }
+            Self::Phi35Mini => LoadSettings::isq(IsqType::Q4K),
+
+            // At 1.5 B params Q4K costs noticeable quality for little
+            // saving, so keep Qwen at 8 bits.
+            Self::Qwen25_1_5b => LoadSettings::isq(IsqType::Q8_0),
+
+            // Llama 3.2 3B is Phi's size class: Q4K.
+            Self::Llama32_3b => LoadSettings::isq(IsqType::Q4K),
+
+            // Quality doesn't matter for a smoke run; Q4K exercises the
+            // same ISQ path as the on-device presets.
+            #[cfg(feature = "ci-models")]
+            Self::Micro => LoadSettings::isq(IsqType::Q4K),
+        }
+    }
+
+    /// Cache key for [`ModelManager`]; mirrors the settings in
+    /// [`build_model`](Self::build_model).
+    pub fn model_key(self, opts: &LoadOptions) -> ModelKey {
+        let settings = opts.apply(self.default_settings());
+        ModelKey::new(self.model_id(), settings.dtype, settings.isq)
+    }
+
+    /// Build the [`Model`] with this preset's dtype / ISQ settings, as
+    /// overridden by `opts`.
+    ///
+    /// Gemma 3n uses `Gemma3nForConditionalGeneration` (a multimodal
+    /// architecture), so mistral.rs classifies it as a **vision** model even
+    /// when used for text-only chat.  We therefore load it via
+    /// [`VisionModelBuilder`](mistralrs::VisionModelBuilder).  Phi-3.5-mini, Qwen2.5 and Llama 3.2
+    /// are pure text models and use [`TextModelBuilder`](mistralrs::TextModelBuilder) as usual;
+    /// each repo ships its own chat template, which mistral.rs applies.
+    /// So does the `micro` preset, which is a Llama-architecture model.
+    pub(crate) async fn build_model(self, opts: &LoadOptions) -> Result<Model> {
+        let settings = opts.apply(self.default_settings());
+        let kind = match self {
+            Self::GemmaE2b | Self::GemmaE4b => ModelKind::Vision,
+            Self::Phi35Mini | Self::Qwen25_1_5b | Self::Llama32_3b => ModelKind::Text,
+            #[cfg(feature = "ci-models")]
+            Self::Micro => ModelKind::Text,
+        };
+        disk_space::preflight(self.model_id(), self.download_size_bytes())?;
+        loader::build_model(self.model_id(), kind, settings, &opts.uqff)
+            .await
+            .map_err(|source| opts.load_error(self.model_id(), source).into())
+    }
+}
+
+impl MemoryFootprint for EnhancerModel {
+    const FALLBACK: Self = Self::GemmaE2b;
+
+    /// The preset figure when the settings are unchanged, otherwise an
+    /// estimate, like [`memory_estimate`](EnhancerModel::memory_estimate).
+    fn required_bytes(self, opts: &LoadOptions) -> u64 {
+        let settings = opts.apply(self.default_settings());
+        if settings == self.default_settings() {
+            self.approx_bytes()
+        } else {
+            settings.approx_bytes(self.approx_params_billions())
         }
     }
 }
@@ -108,30 +247,443 @@ impl fmt::Display for EnhancerModel {
 
 // ── Constants ────────────────────────────────────────────────────────────────
 
-/// CLIP (used by FLUX.1-schnell) has a hard limit of 77 tokens (including
-/// BOS/EOS), so the enhanced prompt must stay under ~50 words to be safe.
-const SYSTEM_PROMPT: &str = r#"You are a prompt enhancer for image generation models. Given a short description, expand it into a vivid image generation prompt. Keep artistic style references if provided. Add lighting, composition, and atmosphere details. The result MUST be under 50 words. Output ONLY the enhanced prompt, no explanation, no quotes."#;
+/// The `micro` preset's repo: small enough for a CI runner to download on
+/// every run, but a real instruct model with a chat template, so a smoke
+/// run goes through the same loader and request code as the full presets.
+#[cfg(feature = "ci-models")]
+pub const MICRO_MODEL_ID: &str = "HuggingFaceTB/SmolLM2-135M-Instruct";
+
+/// Size of [`MICRO_MODEL_ID`]'s BF16 safetensors.
+#[cfg(feature = "ci-models")]
+pub(crate) const MICRO_DOWNLOAD_BYTES: f64 = 0.27 * GB;
+
+/// The enhanced prompt has to fit the diffusion model's text encoder, so
+/// the word limit comes from its [`PromptBudget`]; `{max_words}` is filled
+/// in by [`system_prompt`] (50 for CLIP's 77 tokens).
+pub const SYSTEM_PROMPT_TEMPLATE: &str = r#"You are a prompt enhancer for image generation models. Given a short description, expand it into a vivid image generation prompt. Keep artistic style references if provided. Add lighting, composition, and atmosphere details. The result MUST be under {max_words} words. Output ONLY the enhanced prompt, no explanation, no quotes."#;
+
+/// Default sampling for [`PromptEnhancer`] with CLIP's window.  Generation
+/// is kept short (80 tokens) so the result fits within CLIP's 77-token
+/// window after tokenisation; [`default_sampler`] scales it to other
+/// budgets.
+pub const DEFAULT_SAMPLER: SamplerConfig = SamplerConfig::new(0.9, 0.95, 80);
+
+/// Temperature added per candidate in [`PromptEnhancer::enhance_n`].
+const CANDIDATE_TEMPERATURE_STEP: f64 = 0.05;
 
-/// Maximum number of CLIP tokens the diffusion model accepts (including BOS/EOS).
-const MAX_CLIP_TOKENS: usize = 77;
+/// Ceiling for candidate temperatures; beyond this outputs stop following
+/// the system prompt's length limit.
+const MAX_CANDIDATE_TEMPERATURE: f64 = 1.2;
 
-/// Conservative word-count ceiling so the prompt fits within [`MAX_CLIP_TOKENS`].
-/// CLIP roughly tokenises at the word level; 50 words ≈ 55-65 CLIP tokens,
-/// leaving headroom for BOS/EOS and occasional sub-word splits.
-const MAX_PROMPT_WORDS: usize = 50;
+/// Stage names for [`Error::Generation`] and [`TimedOut`](timeout::TimedOut).
+const ENHANCE_STAGE: &str = "Prompt enhancement";
+const LYRICS_STAGE: &str = "Prompt from lyrics";
+
+/// System prompt for [`PromptEnhancer::prompt_from_lyrics`]: distill song
+/// lyrics into one visual scene rather than expanding a short seed.
+/// Filled in by [`lyrics_system_prompt`].
+pub const LYRICS_SYSTEM_PROMPT_TEMPLATE: &str = r#"You are an art director creating cover artwork for a song. Given the song's lyrics (and optionally a visual style), pick the strongest imagery and mood and describe ONE concrete scene as an image generation prompt. Include subject, setting, lighting, colour palette and atmosphere. Do not quote the lyrics. The result MUST be under {max_words} words. Output ONLY the prompt, no explanation, no quotes."#;
+
+/// Lyrics beyond this many words are cut before prompting; the opening
+/// verses and chorus carry enough imagery and keep the request small.
+/// `lyrics-art` summarizes longer lyrics instead (see
+/// [`prompt_from_summary`](PromptEnhancer::prompt_from_summary)).
+pub const MAX_LYRICS_WORDS: usize = 200;
 
 // ── Helpers ──────────────────────────────────────────────────────────────────
 
-/// Format a `Duration` as `Xm Ys` (e.g. "2m 30.5s") or just `Ys` when under a minute.
-fn fmt_duration(d: Duration) -> String {
-    let total_secs = d.as_secs_f64();
-    let mins = (total_secs / 60.0).floor() as u64;
-    let secs = total_secs - (mins as f64 * 60.0);
-    if mins > 0 {
-        format!("{}m {:.1}s", mins, secs)
+/// [`SYSTEM_PROMPT_TEMPLATE`] asking for at most `budget`'s words.
+pub fn system_prompt(budget: PromptBudget) -> String {
+    budget.fill(SYSTEM_PROMPT_TEMPLATE)
+}
+
+/// [`LYRICS_SYSTEM_PROMPT_TEMPLATE`] asking for at most `budget`'s words.
+pub fn lyrics_system_prompt(budget: PromptBudget) -> String {
+    budget.fill(LYRICS_SYSTEM_PROMPT_TEMPLATE)
+}
+
+/// [`DEFAULT_SAMPLER`] generating up to `budget`'s
+/// [`max_new_tokens`](PromptBudget::max_new_tokens).
+pub fn default_sampler(budget: PromptBudget) -> SamplerConfig {
+    DEFAULT_SAMPLER.with_max_len(budget.max_new_tokens())
+}
+
+/// The sampler for candidate `i` of [`PromptEnhancer::enhance_n`]: the
+/// temperature raised by [`CANDIDATE_TEMPERATURE_STEP`] per candidate up to
+/// [`MAX_CANDIDATE_TEMPERATURE`], and the seed (if any) offset by `i`.
+pub(crate) fn candidate_sampler(base: &SamplerConfig, i: usize) -> SamplerConfig {
+    let temperature = (base.temperature + CANDIDATE_TEMPERATURE_STEP * i as f64)
+        .min(MAX_CANDIDATE_TEMPERATURE.max(base.temperature));
+    let mut sampler = base.clone().with_temperature(temperature);
+    sampler.seed = base.seed.map(|seed| seed.wrapping_add(i as u64));
+    sampler
+}
+
+/// The prompt to use for a cleaned reply: the reply cut to fit `budget`,
+/// or the seed prompt when the reply came back empty or too short.
+pub(crate) fn enhanced_or_seed(seed_prompt: &str, enhanced: &str, budget: PromptBudget) -> String {
+    if falls_short(seed_prompt, enhanced) {
+        budget.truncate(seed_prompt)
+    } else {
+        budget.truncate(enhanced)
+    }
+}
+
+/// Whether a cleaned reply is too short to improve on the seed prompt.
+fn falls_short(seed_prompt: &str, enhanced: &str) -> bool {
+    enhanced.len() <= seed_prompt.len() + 4
+}
+
+/// The user message for [`LYRICS_SYSTEM_PROMPT_TEMPLATE`]: `text` under `label`,
+/// with the style after it.
+pub(crate) fn song_text_message(label: &str, text: &str, style: Option<&str>) -> String {
+    match style {
+        Some(style) => format!("{label}:\n{text}\n\nStyle: {style}"),
+        None => format!("{label}:\n{text}"),
+    }
+}
+
+/// The `--model` name of `preset`, e.g. `gemma-e2b`.
+fn preset_name(preset: EnhancerModel) -> String {
+    use clap::ValueEnum;
+    preset
+        .to_possible_value()
+        .map(|v| v.get_name().to_string())
+        .unwrap_or_default()
+}
+
+// ── Few-shot examples ────────────────────────────────────────────────────────
+
+/// The built-in `--few-shot` examples: a seed and the kind of prompt
+/// [`SYSTEM_PROMPT_TEMPLATE`] asks for, each under CLIP's 50 words.
+pub const DEFAULT_EXAMPLES: [(&str, &str); 3] = [
+    (
+        "a lighthouse in a storm",
+        "A lone lighthouse on a jagged cliff battered by a violent storm, huge waves \
+         exploding against the rocks, its beam cutting through sheets of rain, dark clouds \
+         lit by distant lightning, dramatic low-angle composition, cold blue and grey \
+         palette, moody cinematic atmosphere, highly detailed",
+    ),
+    (
+        "cat in a library, watercolor",
+        "A ginger cat curled up on a stack of old books in a cozy library, warm afternoon \
+         light streaming through tall arched windows, dust motes drifting in the air, \
+         shelves fading into soft shadow, loose watercolor washes and gentle brushstrokes, \
+         amber and sage palette, calm peaceful mood",
+    ),
+    (
+        "cyberpunk street market at night",
+        "A crowded cyberpunk street market at night, pink and cyan neon signs reflected \
+         on rain-slicked pavement, vendors under glowing awnings, steam rising from food \
+         stalls, holographic adverts overhead, eye-level composition with shallow depth of \
+         field, vibrant saturated colors, gritty futuristic atmosphere",
+    ),
+];
+
+/// Estimated tokens all examples together may take; later examples that
+/// would go past it are dropped.  Every enhancement request carries them,
+/// so they add to its prefill time.
+pub const MAX_EXAMPLE_TOKENS: usize = 600;
+
+/// One seed and the enhanced prompt the model should answer it with.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FewShotExample {
+    pub seed: String,
+    pub enhanced: String,
+}
+
+impl FewShotExample {
+    /// Estimated tokens of the seed and the reply.
+    pub fn tokens(&self) -> usize {
+        estimate_tokens(&self.seed) + estimate_tokens(&self.enhanced)
+    }
+}
+
+/// Keep `examples`, in order, while they fit in `max_tokens` together.
+/// Returns the ones kept and how many were dropped.
+pub fn fit_examples(
+    examples: Vec<FewShotExample>,
+    max_tokens: usize,
+) -> (Vec<FewShotExample>, usize) {
+    let total = examples.len();
+    let mut used = 0;
+    let kept: Vec<FewShotExample> = examples
+        .into_iter()
+        .take_while(|example| {
+            used += example.tokens();
+            used <= max_tokens
+        })
+        .collect();
+    let dropped = total - kept.len();
+    (kept, dropped)
+}
+
+/// The examples of a `prompt --few-shot` / `--examples-file` run and where
+/// they came from, as recorded in the plan and `--json` report.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct FewShot {
+    /// `built-in`, or the `--examples-file` path.
+    pub source: String,
+    pub examples: Vec<FewShotExample>,
+}
+
+impl FewShot {
+    /// [`DEFAULT_EXAMPLES`].
+    pub fn builtin() -> Self {
+        Self {
+            source: "built-in".to_string(),
+            examples: DEFAULT_EXAMPLES
+                .iter()
+                .map(|(seed, enhanced)| FewShotExample {
+                    seed: seed.to_string(),
+                    enhanced: enhanced.to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Read an `--examples-file`: TOML with one `[[examples]]` table per
+    /// example, or JSON `{"examples": [...]}`, each with a `seed` and an
+    /// `enhanced` string.  Examples past [`MAX_EXAMPLE_TOKENS`] are dropped
+    /// with a warning.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        #[derive(Deserialize)]
+        struct ExamplesFile {
+            examples: Vec<FewShotExample>,
+        }
+
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read examples file: {}", path.display()))?;
+        let file: ExamplesFile = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => serde_json::from_str(&text)
+                .with_context(|| format!("Invalid examples file: {}", path.display()))?,
+            Some("toml") => toml::from_str(&text)
+                .with_context(|| format!("Invalid examples file: {}", path.display()))?,
+            _ => anyhow::bail!("Examples file must be .toml or .json: {}", path.display()),
+        };
+        if file.examples.is_empty() {
+            anyhow::bail!("Examples file has no examples: {}", path.display());
+        }
+        if let Some(index) = file
+            .examples
+            .iter()
+            .position(|e| e.seed.trim().is_empty() || e.enhanced.trim().is_empty())
+        {
+            anyhow::bail!(
+                "Example {} in {} has an empty seed or enhanced prompt",
+                index + 1,
+                path.display()
+            );
+        }
+        let (examples, dropped) = fit_examples(file.examples, MAX_EXAMPLE_TOKENS);
+        if dropped > 0 {
+            tracing::warn!(
+                "dropping the last {dropped} example(s) of {}: together they pass ~{MAX_EXAMPLE_TOKENS} tokens",
+                path.display()
+            );
+        }
+        if examples.is_empty() {
+            anyhow::bail!(
+                "The first example in {} alone passes ~{MAX_EXAMPLE_TOKENS} tokens",
+                path.display()
+            );
+        }
+        Ok(Self {
+            source: path.display().to_string(),
+            examples,
+        })
+    }
+
+    /// The examples as [`PromptEnhancer::with_examples`] takes them.
+    pub fn pairs(&self) -> Vec<(String, String)> {
+        self.examples
+            .iter()
+            .map(|e| (e.seed.clone(), e.enhanced.clone()))
+            .collect()
+    }
+
+    /// Estimated tokens of every example together.
+    pub fn tokens(&self) -> usize {
+        self.examples.iter().map(FewShotExample::tokens).sum()
+    }
+}
+
+impl fmt::Display for FewShot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} example(s), ~{} tokens ({})",
+            self.examples.len(),
+            self.tokens(),
+            self.source
+        )
+    }
+}
+
+// ── Output cleanup ───────────────────────────────────────────────────────────
+
+/// Labels a model puts before the prompt despite being told not to, e.g.
+/// `Enhanced prompt: …`.  Matched case-insensitively, longest first.
+const OUTPUT_LABELS: &[&str] = &[
+    "enhanced image prompt",
+    "enhanced prompt",
+    "improved prompt",
+    "refined prompt",
+    "expanded prompt",
+    "revised prompt",
+    "final prompt",
+    "image prompt",
+    "prompt",
+    "output",
+];
+
+/// Words that open a lead-in line such as "Here's an enhanced prompt:" or
+/// "Sure!".
+const LEAD_IN_WORDS: &[&str] = &["here", "sure", "certainly", "okay", "ok", "of course"];
+
+/// Starts of the meta-commentary models append after the prompt.
+const META_MARKERS: &[&str] = &[
+    "note:",
+    "notes:",
+    "(note",
+    "*note",
+    "explanation:",
+    "changes:",
+    "this prompt",
+    "this enhanced",
+    "this version",
+    "the prompt above",
+    "i added",
+    "i've added",
+    "i have added",
+    "i enhanced",
+    "i've enhanced",
+    "feel free",
+    "let me know",
+];
+
+/// Pairs that wrap a whole reply: straight and curly quotes, backticks.
+const WRAPPERS: &[(char, char)] = &[
+    ('"', '"'),
+    ('\'', '\''),
+    ('`', '`'),
+    ('“', '”'),
+    ('‘', '’'),
+    ('«', '»'),
+];
+
+/// Reduce a chat model's reply to the bare prompt.
+///
+/// Strips a code fence and surrounding quotes or backticks, a lead-in line
+/// ("Here is the enhanced prompt:") and a label ("Enhanced prompt: …"),
+/// cuts trailing meta-commentary from the first line or sentence that
+/// starts like "Note:" or "This prompt …", and joins the remaining lines
+/// with commas, since CLIP reads one line.
+pub fn clean_enhanced_output(text: &str) -> String {
+    let mut text = strip_wrappers(text.trim()).to_string();
+    loop {
+        let stripped = strip_wrappers(strip_label(strip_lead_in(&text)));
+        if stripped.len() == text.len() {
+            break;
+        }
+        text = stripped.to_string();
+    }
+    let text = strip_wrappers(cut_meta(&text));
+    text.lines()
+        .map(|line| {
+            line.trim()
+                .trim_start_matches(['-', '*', '•'])
+                .trim()
+                .trim_matches('*')
+                .trim()
+        })
+        .filter(|line| !line.is_empty())
+        .map(|line| line.trim_end_matches([',', ';', '.']))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// `text` without a code fence or one pair of [`WRAPPERS`] around all of
+/// it, repeatedly.
+fn strip_wrappers(mut text: &str) -> &str {
+    loop {
+        let before = text.len();
+        if let Some(inner) = text.strip_prefix("```").and_then(|t| t.strip_suffix("```")) {
+            // Drop a language tag on the opening line.
+            text = match inner.split_once('\n') {
+                Some((tag, rest)) if !tag.trim().contains(' ') => rest,
+                _ => inner,
+            };
+        }
+        for &(open, close) in WRAPPERS {
+            if let Some(inner) = text.strip_prefix(open).and_then(|t| t.strip_suffix(close)) {
+                text = inner;
+                break;
+            }
+        }
+        text = text.trim();
+        if text.len() == before {
+            return text;
+        }
+    }
+}
+
+/// `text` without a first line that only introduces the prompt: one
+/// opening with a [`LEAD_IN_WORDS`] word and ending in `:` or `!`, or one
+/// mentioning a prompt and ending in `:`.
+fn strip_lead_in(text: &str) -> &str {
+    let Some((first, rest)) = text.split_once('\n') else {
+        return text;
+    };
+    let line = first.trim().trim_matches('*').trim().to_lowercase();
+    let lead_in = LEAD_IN_WORDS.iter().any(|word| line.starts_with(word))
+        && (line.ends_with(':') || line.ends_with('!'));
+    let announces = line.ends_with(':') && line.contains("prompt");
+    if lead_in || announces {
+        rest.trim()
     } else {
-        format!("{:.1}s", secs)
+        text
+    }
+}
+
+/// `text` without a leading [`OUTPUT_LABELS`] label and its colon, bold
+/// markers included (`**Prompt:**`).
+fn strip_label(text: &str) -> &str {
+    let unbolded = text.trim_start_matches('*');
+    for label in OUTPUT_LABELS {
+        let Some(head) = unbolded.get(..label.len()) else {
+            continue;
+        };
+        if !head.eq_ignore_ascii_case(label) {
+            continue;
+        }
+        let rest = unbolded[label.len()..].trim_start_matches('*');
+        if let Some(rest) = rest.strip_prefix(':') {
+            return rest.trim_start_matches('*').trim();
+        }
     }
+    text
+}
+
+/// `text` up to the first line or sentence (after the first) that starts
+/// with one of the [`META_MARKERS`].
+fn cut_meta(text: &str) -> &str {
+    // ASCII lowercasing keeps byte offsets the same.
+    let lower = text.to_ascii_lowercase();
+    let end = META_MARKERS
+        .iter()
+        .flat_map(|marker| lower.match_indices(marker).map(|(i, _)| i))
+        .filter(|&i| i > 0 && starts_sentence(&text[..i]))
+        .min()
+        .unwrap_or(text.len());
+    text[..end].trim()
+}
+
+/// Whether a sentence or line starts after `before`.
+fn starts_sentence(before: &str) -> bool {
+    let trimmed = before.trim_end_matches([' ', '\t']);
+    trimmed.len() < before.len() && trimmed.ends_with(['.', '!', '?', '"', '”', ')'])
+        || trimmed.ends_with('\n')
 }
 
 // ── PromptEnhancer ───────────────────────────────────────────────────────────
@@ -141,15 +693,33 @@ fn fmt_duration(d: Duration) -> String {
 /// Replicates the behavior of `Gustavosta/MagicPrompt-Stable-Diffusion` (a GPT-2
 /// fine-tune) by using a small instruction-following model with a system prompt
 /// that instructs it to expand short descriptions into rich image generation prompts.
+///
+/// Safe to share between tasks: requests wait their turn in a
+/// [`RequestQueue`] (one at a time unless
+/// [`set_max_concurrency`](Self::set_max_concurrency) says otherwise), and
+/// clones share the model and the queue.
+#[derive(Clone)]
 pub struct PromptEnhancer {
-    model: Model,
+    model: Arc<Model>,
+    /// The model's HuggingFace ID or local path, recorded on the tracing
+    /// spans; see [`with_model_id`](Self::with_model_id).
+    model_id: Option<String>,
     system_prompt: String,
+    /// Seed / reply pairs sent before the seed; see [`with_examples`](Self::with_examples).
+    examples: Vec<FewShotExample>,
+    sampler: SamplerConfig,
+    /// Window replies are cut to; see [`with_prompt_budget`](Self::with_prompt_budget).
+    budget: PromptBudget,
+    /// Limit per request; see [`with_timeout`](Self::with_timeout).
+    timeout: Option<Duration>,
+    /// Bounds the requests in flight; see [`set_max_concurrency`](Self::set_max_concurrency).
+    queue: RequestQueue,
 }
 
 impl PromptEnhancer {
     /// Build a new `PromptEnhancer` using the **default** preset
     /// ([`EnhancerModel::GemmaE4b`]).
-    pub async fn new() -> Result<Self> {
+    pub async fn new() -> Result<Self, Error> {
         Self::from_preset(EnhancerModel::default()).await
     }
 
@@ -157,16 +727,87 @@ impl PromptEnhancer {
     /// presets.  Each preset applies the optimal dtype / ISQ configuration
     /// automatically.
     ///
-    /// Gemma 3n variants are loaded via [`VisionModelBuilder`] (the model
+    /// Gemma 3n variants are loaded via [`VisionModelBuilder`](mistralrs::VisionModelBuilder) (the model
     /// architecture is multimodal), while Phi-3.5-mini uses
-    /// [`TextModelBuilder`].  Both return the same [`Model`] type.
-    pub async fn from_preset(preset: EnhancerModel) -> Result<Self> {
-        let model = preset.build_model().await?;
+    /// [`TextModelBuilder`](mistralrs::TextModelBuilder).  Both return the same [`Model`] type.
+    pub async fn from_preset(preset: EnhancerModel) -> Result<Self, Error> {
+        Self::from_preset_with_options(preset, &LoadOptions::default()).await
+    }
 
-        Ok(Self {
+    /// Like [`from_preset`](Self::from_preset), with `--isq` / `--dtype`
+    /// overrides applied on top of the preset's defaults.
+    pub async fn from_preset_with_options(
+        preset: EnhancerModel,
+        opts: &LoadOptions,
+    ) -> Result<Self, Error> {
+        let model = preset
+            .build_model(opts)
+            .await
+            .map_err(|source| Error::model_load(preset.model_id(), source))?;
+        Ok(Self::from_model(Arc::new(model)).with_model_id(preset.model_id()))
+    }
+
+    /// Try the presets of `chain` in order and keep the first that loads,
+    /// returning it with the enhancer.  Each failure (a gated repo, too
+    /// little memory, …) is reported on stderr before the next preset is
+    /// tried; the error lists every attempt when none loads.
+    pub async fn from_preset_chain(
+        chain: &[EnhancerModel],
+    ) -> Result<(Self, EnhancerModel), Error> {
+        Self::from_preset_chain_with_options(chain, &LoadOptions::default()).await
+    }
+
+    /// Like [`from_preset_chain`](Self::from_preset_chain), with `--isq` /
+    /// `--dtype` overrides applied to every preset.
+    pub async fn from_preset_chain_with_options(
+        chain: &[EnhancerModel],
+        opts: &LoadOptions,
+    ) -> Result<(Self, EnhancerModel), Error> {
+        let chosen = enhancer_chain::load_chain(chain, &OptionsBuilder(opts))
+            .await
+            .map_err(|source| {
+                let ids: Vec<&str> = chain.iter().map(|preset| preset.model_id()).collect();
+                Error::model_load(ids.join(", "), source)
+            })?;
+        Ok((chosen.loaded, chosen.preset))
+    }
+
+    /// Like [`from_preset_with_options`](Self::from_preset_with_options), but
+    /// reuses a model already built by `manager` (e.g. for `CliChat`) instead
+    /// of loading it again.
+    pub async fn from_preset_with_manager(
+        preset: EnhancerModel,
+        opts: &LoadOptions,
+        manager: &ModelManager,
+    ) -> Result<Self, Error> {
+        let model = manager
+            .get_or_build(preset.model_key(opts), || preset.build_model(opts))
+            .await
+            .map_err(|source| Error::model_load(preset.model_id(), source))?;
+        Ok(Self::from_model(model).with_model_id(preset.model_id()))
+    }
+
+    /// Load a model from a local GGUF file or HuggingFace-layout directory
+    /// instead of a preset, for offline use.
+    pub async fn from_local_path(path: &Path, opts: &LoadOptions) -> Result<Self, Error> {
+        let model = async { LocalModel::from_path(path)?.build(opts).await }
+            .await
+            .map_err(|source| Error::model_load(path.display().to_string(), source))?;
+        Ok(Self::from_model(Arc::new(model)).with_model_id(path.display().to_string()))
+    }
+
+    /// Build a `PromptEnhancer` around an already-loaded model.
+    pub fn from_model(model: Arc<Model>) -> Self {
+        Self {
             model,
-            system_prompt: SYSTEM_PROMPT.to_string(),
-        })
+            model_id: None,
+            system_prompt: system_prompt(PromptBudget::CLIP),
+            examples: Vec::new(),
+            sampler: DEFAULT_SAMPLER,
+            budget: PromptBudget::CLIP,
+            timeout: timeout::default_limit(),
+            queue: RequestQueue::default(),
+        }
     }
 
     /// Build a `PromptEnhancer` with an arbitrary HuggingFace model ID.
@@ -174,17 +815,25 @@ impl PromptEnhancer {
     /// The model must be a text/instruction model supported by mistral.rs
     /// (e.g. Gemma, Qwen2, Llama, Mistral).  Loads with F16 dtype and no ISQ —
     /// use [`from_preset`](Self::from_preset) for optimised defaults.
-    pub async fn with_model(model_id: &str) -> Result<Self> {
-        let model = TextModelBuilder::new(model_id)
-            .with_dtype(ModelDType::F16)
-            .with_logging()
-            .build()
-            .await?;
+    pub async fn with_model(model_id: &str) -> Result<Self, Error> {
+        let model = loader::build_model(
+            model_id,
+            ModelKind::Text,
+            LoadSettings::dtype(ModelDType::F16),
+            &UqffArgs::default(),
+        )
+        .await
+        .map_err(|source| Error::model_load(model_id, source))?;
 
-        Ok(Self {
-            model,
-            system_prompt: SYSTEM_PROMPT.to_string(),
-        })
+        Ok(Self::from_model(Arc::new(model)).with_model_id(model_id))
+    }
+
+    /// Name the model on this enhancer's tracing spans.  The loading
+    /// constructors set it; one built with [`from_model`](Self::from_model)
+    /// has none until this is called.
+    pub fn with_model_id(mut self, model_id: impl Into<String>) -> Self {
+        self.model_id = Some(model_id.into());
+        self
     }
 
     /// Override the default system prompt used for enhancement.
@@ -193,96 +842,1128 @@ impl PromptEnhancer {
         self
     }
 
+    /// Show the model how seeds should be expanded: each `(seed, enhanced)`
+    /// pair goes before the real seed as a user message and the assistant's
+    /// reply, which keeps the structure of the output steadier between
+    /// runs than the system prompt alone.  Examples past
+    /// [`MAX_EXAMPLE_TOKENS`] are dropped with a warning.  An empty list
+    /// (the default) sends none.
+    ///
+    /// Used by [`enhance`](Self::enhance) and its variants, not by the
+    /// lyrics prompts.
+    pub fn with_examples(mut self, examples: Vec<(String, String)>) -> Self {
+        let examples = examples
+            .into_iter()
+            .map(|(seed, enhanced)| FewShotExample { seed, enhanced })
+            .collect();
+        let (examples, dropped) = fit_examples(examples, MAX_EXAMPLE_TOKENS);
+        if dropped > 0 {
+            tracing::warn!(
+                "dropping the last {dropped} few-shot example(s): together they pass ~{MAX_EXAMPLE_TOKENS} tokens"
+            );
+        }
+        self.examples = examples;
+        self
+    }
+
+    /// Override the sampling settings (default [`DEFAULT_SAMPLER`]).
+    pub fn with_sampler(mut self, sampler: SamplerConfig) -> Self {
+        self.sampler = sampler;
+        self
+    }
+
+    /// Write prompts for a text encoder with `budget`'s window (default
+    /// [`PromptBudget::CLIP`]): replies are cut to it, and the system prompt
+    /// and the sampler's length limit follow it unless they were
+    /// overridden.
+    pub fn with_prompt_budget(mut self, budget: PromptBudget) -> Self {
+        if self.system_prompt == system_prompt(self.budget) {
+            self.system_prompt = system_prompt(budget);
+        }
+        if self.sampler.max_len == self.budget.max_new_tokens() {
+            self.sampler.max_len = budget.max_new_tokens();
+        }
+        self.budget = budget;
+        self
+    }
+
+    /// Seed the sampler so the same seed prompt gives the same result on
+    /// every run.  Unseeded (the default), each run differs.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.sampler = self.sampler.with_seed(seed);
+        self
+    }
+
+    /// Abandon a request that runs longer than `limit` with a
+    /// [`TimedOut`](timeout::TimedOut) error (default: `--timeout`, if given).
+    pub fn with_timeout(mut self, limit: Duration) -> Self {
+        self.timeout = Some(limit);
+        self
+    }
+
+    /// Let up to `limit` requests (at least one) run at once; see
+    /// [`set_max_concurrency`](Self::set_max_concurrency).
+    pub fn with_max_concurrency(mut self, limit: usize) -> Self {
+        self.set_max_concurrency(limit);
+        self
+    }
+
+    /// Let up to `limit` requests (at least one, the default) run at once
+    /// across every task using this enhancer.  Callers past the limit wait
+    /// in arrival order.  Clones made before the change keep the old limit.
+    pub fn set_max_concurrency(&mut self, limit: usize) {
+        self.queue = RequestQueue::new(limit);
+    }
+
+    /// Most requests this enhancer sends at once.
+    pub fn max_concurrency(&self) -> usize {
+        self.queue.limit()
+    }
+
+    /// The sampling settings used for every request.
+    pub fn sampler(&self) -> &SamplerConfig {
+        &self.sampler
+    }
+
+    /// The per-request limit, for callers sending their own requests to
+    /// [`model`](Self::model).
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// The system prompt used for enhancement.
+    pub fn system_prompt(&self) -> &str {
+        &self.system_prompt
+    }
+
+    /// The window prompts are written for; see
+    /// [`with_prompt_budget`](Self::with_prompt_budget).
+    pub fn prompt_budget(&self) -> PromptBudget {
+        self.budget
+    }
+
+    /// The few-shot examples sent with every enhancement.
+    pub fn examples(&self) -> &[FewShotExample] {
+        &self.examples
+    }
+
+    /// The messages [`enhance`](Self::enhance) sends for `seed_prompt`, in
+    /// order: the system prompt, each example's seed and reply, then the
+    /// seed.
+    pub fn messages(&self, seed_prompt: &str) -> Vec<(TextMessageRole, String)> {
+        let mut messages = vec![(TextMessageRole::System, self.system_prompt.clone())];
+        for example in &self.examples {
+            messages.push((TextMessageRole::User, example.seed.clone()));
+            messages.push((TextMessageRole::Assistant, example.enhanced.clone()));
+        }
+        messages.push((TextMessageRole::User, seed_prompt.to_string()));
+        messages
+    }
+
     /// Enhance a seed prompt into a detailed image generation prompt.
     ///
-    /// If the model fails to produce a meaningful expansion (result is too short
-    /// or identical to input), the original seed prompt is returned as-is.
-    pub async fn enhance(&self, seed_prompt: &str) -> Result<String> {
+    /// The reply goes through [`clean_enhanced_output`] first.  If the model
+    /// fails to produce a meaningful expansion (result is too short or
+    /// identical to input), the original seed prompt is returned as-is.
+    /// The result derefs to the prompt text; `.into()` turns it into a
+    /// `String`.
+    pub async fn enhance(&self, seed_prompt: &str) -> Result<EnhancementResult, Error> {
+        self.enhance_with_sampler(seed_prompt, &self.sampler)
+            .await
+            .map_err(|source| Error::generation(ENHANCE_STAGE, source))
+    }
+
+    /// Like [`enhance`](Self::enhance), calling `on_token` with each chunk
+    /// of the reply as it arrives, e.g. to print it live.
+    ///
+    /// The generation is stopped as soon as the reply reaches the
+    /// [prompt budget](Self::prompt_budget)'s words instead of running
+    /// until the model stops.  The result is the streamed text, cut at a
+    /// stop string, cleaned by [`clean_enhanced_output`] and fitted to the
+    /// budget's tokens exactly as `enhance` does it (so `on_token` may have
+    /// seen a quote or label the result doesn't have), or the seed prompt
+    /// when the reply is too short to use.
+    ///
+    /// When the reply is cut off, the model reports no usage, so
+    /// `completion_tokens` counts the chunks received.
+    #[tracing::instrument(
+        name = "enhance",
+        skip_all,
+        fields(
+            model_id = self.model_id.as_deref(),
+            prompt_words = seed_prompt.split_whitespace().count()
+        )
+    )]
+    pub async fn enhance_streaming<F>(
+        &self,
+        seed_prompt: &str,
+        mut on_token: F,
+    ) -> Result<EnhancementResult, Error>
+    where
+        F: FnMut(&str),
+    {
+        let request = self.request_with(seed_prompt, &self.sampler);
+
+        let _permit = self.queue.acquire().await;
+        let start = Instant::now();
+        let mut cutoff = WordCutoff::new(self.budget.max_words());
+        let mut text = String::new();
+        let mut chunks = 0;
+        let mut reported = None;
+        let streaming = async {
+            let mut stream = self.model.stream_chat_request(request).await?;
+            while let Some(response) = stream.next().await {
+                match response {
+                    Response::Chunk(chunk) => {
+                        if let Some(usage) = &chunk.usage {
+                            reported = Some(usage.clone());
+                        }
+                        let Some(content) = chunk
+                            .choices
+                            .first()
+                            .and_then(|choice| choice.delta.content.as_deref())
+                        else {
+                            continue;
+                        };
+                        chunks += 1;
+                        let kept = cutoff.push(content);
+                        if !kept.is_empty() {
+                            on_token(kept);
+                            text.push_str(kept);
+                        }
+                        // Dropping the stream stops the generation.
+                        if cutoff.is_done() {
+                            break;
+                        }
+                    }
+                    Response::InternalError(e) | Response::ValidationError(e) => {
+                        anyhow::bail!("Generation failed: {e}");
+                    }
+                    Response::ModelError(msg, _) => anyhow::bail!("Model error: {msg}"),
+                    _ => {}
+                }
+            }
+            Ok::<_, anyhow::Error>(())
+        };
+        timeout::within(self.timeout, ENHANCE_STAGE, streaming)
+            .await
+            .map_err(|source| Error::generation(ENHANCE_STAGE, source))?;
+        let duration = start.elapsed();
+
+        let usage = match &reported {
+            Some(usage) => Usage::from_response(usage, duration),
+            None => Usage {
+                completion_tokens: chunks,
+                duration,
+                ..Usage::default()
+            },
+        };
+        let enhanced = clean_enhanced_output(self.sampler.trim_stop(&text));
+        let text = enhanced_or_seed(seed_prompt, &enhanced, self.budget);
+        Ok(EnhancementResult {
+            text,
+            usage,
+            duration,
+        })
+    }
+
+    /// Generate up to `n` distinct enhanced prompts for the same seed.
+    ///
+    /// Each candidate is sampled at a slightly higher temperature than the
+    /// last, starting from the configured one, to spread them out.  With a
+    /// [seed](Self::with_seed), candidate `i` is sampled with `seed + i`, so
+    /// the whole set replays.  Identical outputs are dropped, so fewer than
+    /// `n` prompts may come back.  A candidate that comes back empty or too
+    /// short falls back to the seed prompt like [`enhance`](Self::enhance)
+    /// does, without affecting the others.
+    ///
+    /// A dropped duplicate's usage and duration are added to the candidate
+    /// it repeats, so the totals over the returned candidates cover every
+    /// request made.
+    pub async fn enhance_n(
+        &self,
+        seed_prompt: &str,
+        n: usize,
+    ) -> Result<Vec<EnhancementResult>, Error> {
+        let mut candidates: Vec<EnhancementResult> = Vec::with_capacity(n);
+        for i in 0..n {
+            let sampler = candidate_sampler(&self.sampler, i);
+            let candidate = self
+                .enhance_with_sampler(seed_prompt, &sampler)
+                .await
+                .map_err(|source| Error::generation(ENHANCE_STAGE, source))?;
+            match candidates.iter_mut().find(|c| c.text == candidate.text) {
+                Some(existing) => {
+                    existing.usage += candidate.usage;
+                    existing.duration += candidate.duration;
+                }
+                None => candidates.push(candidate),
+            }
+        }
+        Ok(candidates)
+    }
+
+    /// Enhance each of `seeds`, keeping up to
+    /// [`max_concurrency`](Self::max_concurrency) requests in flight, and
+    /// return the results in the order of `seeds`.  Fails with the first
+    /// error; requests still queued are then dropped.
+    pub async fn enhance_many(&self, seeds: &[&str]) -> Result<Vec<EnhancementResult>, Error> {
+        let mut tasks = tokio::task::JoinSet::new();
+        for (index, seed) in seeds.iter().enumerate() {
+            let enhancer = self.clone();
+            let seed = seed.to_string();
+            tasks.spawn(async move { (index, enhancer.enhance(&seed).await) });
+        }
+        let mut results: Vec<Option<EnhancementResult>> = vec![None; seeds.len()];
+        while let Some(joined) = tasks.join_next().await {
+            let (index, result) = joined
+                .context("Prompt enhancement task failed")
+                .map_err(|source| Error::generation(ENHANCE_STAGE, source))?;
+            results[index] = Some(result?);
+        }
+        Ok(results.into_iter().flatten().collect())
+    }
+
+    /// Send a throwaway one-token request so kernel compilation and cache
+    /// setup happen now rather than in the first timed enhancement.
+    /// Returns how long it took.
+    pub async fn warm_up(&self) -> Result<Duration, Error> {
         let request = RequestBuilder::new()
-            .set_sampler_temperature(0.9)
-            .set_sampler_topp(0.95)
-            // Keep generation short so the result fits within CLIP's 77-token
-            // window after tokenisation.
-            .set_sampler_max_len(80)
+            .set_sampler_max_len(1)
             .add_message(TextMessageRole::System, &self.system_prompt)
-            .add_message(TextMessageRole::User, seed_prompt);
+            .add_message(TextMessageRole::User, "Hi");
+
+        let _permit = self.queue.acquire().await;
+        let start = Instant::now();
+        timeout::within(
+            self.timeout,
+            "Warm-up",
+            self.model.send_chat_request(request),
+        )
+        .await
+        .map_err(|source| Error::generation("Warm-up", source))?;
+        Ok(start.elapsed())
+    }
+
+    /// The chat request [`enhance`](Self::enhance) sends for `seed_prompt`,
+    /// e.g. to stream or time it.
+    pub fn enhance_request(&self, seed_prompt: &str) -> RequestBuilder {
+        self.request_with(seed_prompt, &self.sampler)
+    }
+
+    fn request_with(&self, seed_prompt: &str, sampler: &SamplerConfig) -> RequestBuilder {
+        self.messages(seed_prompt).into_iter().fold(
+            sampler.apply(RequestBuilder::new()),
+            |request, (role, text)| request.add_message(role, text),
+        )
+    }
+
+    #[tracing::instrument(
+        name = "enhance",
+        skip_all,
+        fields(
+            model_id = self.model_id.as_deref(),
+            prompt_words = seed_prompt.split_whitespace().count()
+        )
+    )]
+    async fn enhance_with_sampler(
+        &self,
+        seed_prompt: &str,
+        sampler: &SamplerConfig,
+    ) -> Result<EnhancementResult> {
+        let request = self.request_with(seed_prompt, sampler);
 
-        let response = self.model.send_chat_request(request).await?;
+        let _permit = self.queue.acquire().await;
+        let start = Instant::now();
+        let response = timeout::within(
+            self.timeout,
+            ENHANCE_STAGE,
+            self.model.send_chat_request(request),
+        )
+        .await?;
+        let duration = start.elapsed();
 
         let enhanced = response.choices[0]
             .message
             .content
             .as_ref()
-            .map(|c| c.trim().to_string())
+            .map(|c| clean_enhanced_output(sampler.trim_stop(c)))
             .unwrap_or_default();
 
         // Fallback to the seed prompt if the model returned something too short
-        if enhanced.len() <= seed_prompt.len() + 4 {
-            Ok(truncate_to_words(seed_prompt, MAX_PROMPT_WORDS))
-        } else {
-            Ok(truncate_to_words(&enhanced, MAX_PROMPT_WORDS))
-        }
+        let text = enhanced_or_seed(seed_prompt, &enhanced, self.budget);
+        Ok(EnhancementResult {
+            text,
+            usage: Usage::from_response(&response.usage, duration),
+            duration,
+        })
     }
 
-    /// Build a seed prompt from a song title and style descriptor,
+    /// Compose `seed` with a style preset (see [`StylePreset::compose`]),
     /// then enhance it.
+    pub async fn enhance_with_style(
+        &self,
+        seed_prompt: &str,
+        style: &StylePreset,
+    ) -> Result<EnhancementResult, Error> {
+        self.enhance(&style.compose(seed_prompt)).await
+    }
+
+    /// Build a seed prompt from a song title and style preset, then
+    /// enhance it.
     ///
     /// This is a convenience wrapper matching the Python
     /// `generate_improved_prompt` workflow.
-    pub async fn enhance_for_song(&self, song_title: &str, style: Option<&str>) -> Result<String> {
-        let seed = match style {
-            Some(s) => format!("{song_title}, {s}"),
-            None => song_title.to_string(),
-        };
-        self.enhance(&seed).await
+    pub async fn enhance_for_song(
+        &self,
+        song_title: &str,
+        style: Option<&StylePreset>,
+    ) -> Result<EnhancementResult, Error> {
+        match style {
+            Some(style) => self.enhance_with_style(song_title, style).await,
+            None => self.enhance(song_title).await,
+        }
+    }
+
+    /// Distill transcribed lyrics into an image prompt, optionally in a given
+    /// visual `style`.
+    ///
+    /// Unlike [`enhance`](Self::enhance) the reply is expected to be shorter
+    /// than the input, so there is no fall back to the seed: an empty reply
+    /// is an error.
+    pub async fn prompt_from_lyrics(
+        &self,
+        lyrics: &str,
+        style: Option<&str>,
+    ) -> Result<String, Error> {
+        let excerpt = truncate_to_words(lyrics, MAX_LYRICS_WORDS);
+        self.prompt_from_song_text("Lyrics", &excerpt, style)
+            .await
+            .map_err(|source| Error::generation(LYRICS_STAGE, source))
+    }
+
+    /// Like [`prompt_from_lyrics`](Self::prompt_from_lyrics), from a
+    /// summary of the lyrics (see [`Summarizer`]) rather than the lyrics
+    /// themselves, so the whole song informs the prompt, not just its
+    /// opening.
+    ///
+    /// [`Summarizer`]: crate::summarizer::Summarizer
+    pub async fn prompt_from_summary(
+        &self,
+        summary: &str,
+        style: Option<&str>,
+    ) -> Result<String, Error> {
+        self.prompt_from_song_text("Summary of the lyrics", summary, style)
+            .await
+            .map_err(|source| Error::generation(LYRICS_STAGE, source))
+    }
+
+    /// Send `text` under `label` to the lyrics system prompt.
+    async fn prompt_from_song_text(
+        &self,
+        label: &str,
+        text: &str,
+        style: Option<&str>,
+    ) -> Result<String> {
+        let message = song_text_message(label, text, style);
+
+        let request = self
+            .sampler
+            .apply(RequestBuilder::new())
+            .add_message(TextMessageRole::System, lyrics_system_prompt(self.budget))
+            .add_message(TextMessageRole::User, message);
+
+        let response = self
+            .queue
+            .run(timeout::within(
+                self.timeout,
+                LYRICS_STAGE,
+                self.model.send_chat_request(request),
+            ))
+            .await?;
+        let prompt = response.choices[0]
+            .message
+            .content
+            .as_ref()
+            .map(|c| clean_enhanced_output(c))
+            .unwrap_or_default();
+        if prompt.is_empty() {
+            anyhow::bail!("The model returned an empty prompt for the lyrics");
+        }
+        Ok(self.budget.truncate(&prompt))
     }
 
     /// Return a reference to the underlying `Model` (e.g. for reuse or inspection).
     pub fn model(&self) -> &Model {
         &self.model
     }
+
+    /// Return a shared handle to the underlying `Model` so another component
+    /// can reuse the loaded weights.
+    pub fn shared_model(&self) -> Arc<Model> {
+        self.model.clone()
+    }
+}
+
+// ── EnhancementResult ────────────────────────────────────────────────────────
+
+/// An enhanced prompt with the cost of producing it.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct EnhancementResult {
+    /// The enhanced prompt (or the seed, when the model fell short).
+    pub text: String,
+    /// Tokens processed and generated by the request.
+    pub usage: Usage,
+    /// Wall-clock time of the request.
+    #[serde(rename = "duration_secs", serialize_with = "serialize_secs")]
+    pub duration: Duration,
+}
+
+impl Deref for EnhancementResult {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.text
+    }
+}
+
+impl From<EnhancementResult> for String {
+    fn from(result: EnhancementResult) -> Self {
+        result.text
+    }
+}
+
+impl fmt::Display for EnhancementResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+fn serialize_secs<S: serde::Serializer>(d: &Duration, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_f64(d.as_secs_f64())
 }
 
-/// Truncate `text` to at most `max_words` whitespace-separated words.
+// ── PromptEnhance ────────────────────────────────────────────────────────────
+
+/// Something that turns a seed (or lyrics) into an image prompt.
+///
+/// [`PromptEnhancer`] runs a model; [`TemplateEnhancer`] fills in fixed
+/// templates, so callers that only need a prompt can work offline.
 ///
-/// This is a safety net so that prompts never exceed CLIP's 77-token limit.
-fn truncate_to_words(text: &str, max_words: usize) -> String {
-    let words: Vec<&str> = text.split_whitespace().collect();
-    if words.len() <= max_words {
-        return text.to_string();
+/// [`TemplateEnhancer`]: crate::template_enhancer::TemplateEnhancer
+#[async_trait]
+pub trait PromptEnhance: Send + Sync {
+    /// Expand `seed` into a detailed prompt that fits the diffusion model's
+    /// text encoder.
+    async fn enhance(&self, seed: &str) -> Result<String>;
+
+    /// Up to `n` distinct prompts for the same seed.
+    async fn enhance_n(&self, seed: &str, n: usize) -> Result<Vec<String>>;
+
+    /// Like [`enhance`](Self::enhance), passing the prompt to `on_token` as
+    /// it is generated.  By default it arrives in one piece once done.
+    async fn enhance_streaming(
+        &self,
+        seed: &str,
+        on_token: &mut (dyn for<'t> FnMut(&'t str) + Send),
+    ) -> Result<String> {
+        let prompt = self.enhance(seed).await?;
+        on_token(&prompt);
+        Ok(prompt)
     }
-    words[..max_words].join(" ")
+
+    /// Distill song lyrics into a prompt, optionally in a visual `style`.
+    async fn prompt_from_lyrics(&self, lyrics: &str, style: Option<&str>) -> Result<String>;
+}
+
+#[async_trait]
+impl PromptEnhance for PromptEnhancer {
+    async fn enhance(&self, seed: &str) -> Result<String> {
+        Ok(PromptEnhancer::enhance(self, seed).await?.into())
+    }
+
+    async fn enhance_n(&self, seed: &str, n: usize) -> Result<Vec<String>> {
+        let candidates = PromptEnhancer::enhance_n(self, seed, n).await?;
+        Ok(candidates.into_iter().map(String::from).collect())
+    }
+
+    async fn enhance_streaming(
+        &self,
+        seed: &str,
+        on_token: &mut (dyn for<'t> FnMut(&'t str) + Send),
+    ) -> Result<String> {
+        Ok(PromptEnhancer::enhance_streaming(self, seed, on_token)
+            .await?
+            .into())
+    }
+
+    async fn prompt_from_lyrics(&self, lyrics: &str, style: Option<&str>) -> Result<String> {
+        Ok(PromptEnhancer::prompt_from_lyrics(self, lyrics, style).await?)
+    }
+}
+
+/// Values accepted by `--enhancer`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum EnhancerBackend {
+    /// A text model (see `--model` / `--model-path`).
+    #[default]
+    Model,
+    /// Fixed templates: no model, no download, same output every run.
+    Template,
+    /// An OpenAI-compatible chat-completions API (see `--remote-url` /
+    /// `--remote-model`); needs the `remote-enhancer` feature.
+    Remote,
+}
+
+// ── Live output ──────────────────────────────────────────────────────────────
+
+/// Whether to stream the enhanced prompt to the terminal as it's generated:
+/// only for one prompt, on a terminal, in normal output.
+pub fn streams_live(candidates: usize, out: OutputConfig) -> bool {
+    candidates == 1 && !out.json && !out.quiet() && io::stdout().is_terminal()
+}
+
+/// Prints a streamed prompt as it arrives, trimmed: whitespace is held
+/// back until more text follows it, so the printed text is exactly the
+/// trimmed reply.
+#[derive(Default)]
+struct LiveEcho {
+    printed: String,
+    pending: String,
+}
+
+impl LiveEcho {
+    fn push(&mut self, token: &str) {
+        let start = token.trim_start();
+        let leading = &token[..token.len() - start.len()];
+        if self.printed.is_empty() && start.is_empty() {
+            return;
+        }
+        if !self.printed.is_empty() {
+            self.pending.push_str(leading);
+        }
+        let text = start.trim_end();
+        if text.is_empty() {
+            return;
+        }
+        let out = format!("{}{text}", std::mem::take(&mut self.pending));
+        print!("{out}");
+        let _ = io::stdout().flush();
+        self.printed.push_str(&out);
+        self.pending.push_str(&start[text.len()..]);
+    }
+
+    /// Close the quoted prompt with the time it took.  When `result`
+    /// differs from what streamed (a label or quotes cleaned off, cut to
+    /// the budget, or the seed used instead of a reply too short), print
+    /// it too, so the last prompt shown is the one returned.
+    fn finish(self, seed: &str, result: &str, elapsed: Duration) {
+        println!("\" ({})", fmt_duration(elapsed));
+        if result == self.printed {
+            return;
+        }
+        if falls_short(seed, &clean_enhanced_output(&self.printed)) {
+            println!("  Reply too short; using the seed prompt:\n  \"{result}\"");
+        } else {
+            println!("  Cleaned up:\n  \"{result}\"");
+        }
+    }
+}
+
+/// Enhance `seed` with the prompt printed live, for
+/// [`streams_live`] output.
+pub async fn enhance_live(enhancer: &dyn PromptEnhance, seed: &str) -> Result<String> {
+    println!("Enhanced prompt:");
+    print!("  \"");
+    let mut echo = LiveEcho::default();
+    let start = Instant::now();
+    let prompt = enhancer
+        .enhance_streaming(seed, &mut |token| echo.push(token))
+        .await?;
+    echo.finish(seed, &prompt, start.elapsed());
+    Ok(prompt)
 }
 
 // ── Standalone CLI entry-point ───────────────────────────────────────────────
 
+/// Machine-readable summary of a `prompt` run, printed in `--json` mode.
+#[derive(Debug, Serialize)]
+pub struct PromptReport {
+    /// HuggingFace ID (or local path) of the model that did the enhancement.
+    pub model: String,
+    /// The `--model` name of the preset that loaded; `None` with
+    /// `--model-path`.
+    pub preset: Option<String>,
+    /// Presets of the `--fallback` chain that failed before `preset`
+    /// loaded, with why.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fallback_failures: Vec<FailedLoad>,
+    /// The seed prompt as given.
+    pub seed_prompt: String,
+    /// Name of the `--style` preset composed with the seed, if any.
+    pub style: Option<String>,
+    /// The enhanced prompt (the first candidate).
+    pub enhanced: String,
+    /// Every distinct candidate, when `--candidates` asked for more than one.
+    pub candidates: Vec<String>,
+    /// Seconds spent loading the model.
+    pub load_secs: f64,
+    /// Seconds spent generating the enhanced prompt(s).
+    pub enhance_secs: f64,
+    /// Sampler seed of the run; pass it to `--sampler-seed` to replay it.
+    pub sampler_seed: u64,
+    /// Tokens processed and generated over every request.
+    pub usage: Usage,
+    /// Memory in use once the model was loaded.
+    pub memory: LoadMemory,
+    /// Seconds the `--warmup` request took, not counted in
+    /// `enhance_secs`; `None` when no warm-up was run.
+    pub warm_up_secs: Option<f64>,
+    /// The `--few-shot` / `--examples-file` examples sent before the seed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub few_shot: Option<FewShot>,
+}
+
+/// `prompt` settings chosen on the command line.
+#[derive(Clone, Debug, Default)]
+pub struct PromptOptions {
+    /// `--temperature` / `--top-p` / `--max-tokens` overrides.
+    pub sampler: SamplerArgs,
+    /// `--sampler-seed`; a random seed is picked (and printed) when unset.
+    pub sampler_seed: Option<u64>,
+    /// Number of candidate prompts to generate (`None` = 1).
+    pub candidates: Option<usize>,
+    /// Refine the enhanced prompt interactively afterwards.
+    pub interactive: bool,
+    /// `--style` preset name, composed with the seed before enhancement.
+    pub style: Option<String>,
+    /// Custom system prompt (`None` = [`system_prompt`] for CLIP).
+    pub system_prompt: Option<String>,
+    /// `--few-shot` / `--examples-file`: examples sent before the seed.
+    pub few_shot: Option<FewShot>,
+    /// `--from-audio`: seed from this file's title/artist/genre tags
+    /// instead of a seed prompt.
+    pub from_audio: Option<PathBuf>,
+    /// `--warmup`: send a throwaway request after loading so the timings
+    /// leave out first-request overhead.
+    pub warmup: bool,
+    /// `--session`: keep the `--interactive` refinement under this name
+    /// and resume it on the next run.
+    pub session: Option<String>,
+    /// `--fallback`: presets to try after `--model` if it fails to load;
+    /// empty for [`enhancer_chain::DEFAULT_CHAIN`].
+    pub fallback: Option<Vec<EnhancerModel>>,
+    /// `--copy`: put the (first) enhanced prompt on the clipboard.
+    pub copy: bool,
+}
+
+/// Seed used when `prompt` is given neither a seed prompt nor `--from-audio`.
+const DEFAULT_SEED: &str = "Detective Conan Main Theme, in the style of Raden Saleh, \
+                            trending on artstation, highly detailed";
+
+/// Where the seed of a `prompt` run came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SeedSource {
+    /// `--seed-prompt`.
+    Given,
+    /// The built-in default seed.
+    Default,
+    /// `--from-audio`, from the file's tags.
+    Tags,
+    /// `--from-audio`, from the file name (no title tag).
+    FileName,
+}
+
+/// What a `prompt` run will send, worked out without loading the model:
+/// printed by `--dry-run`, and the first step of [`run`].
+#[derive(Debug, Serialize)]
+pub struct PromptPlan {
+    /// The preset (or local path) that would be loaded.
+    pub model: String,
+    /// `approx_memory()` of the preset; `None` for a local model.
+    pub memory_estimate: Option<String>,
+    /// The `--fallback` chain, in the order tried; empty without it.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fallback: Vec<String>,
+    /// The seed before any style is applied.
+    pub seed_prompt: String,
+    pub seed_source: SeedSource,
+    /// Name of the `--style` preset, if any.
+    pub style: Option<String>,
+    /// System message of every enhancement request.
+    pub system_prompt: String,
+    /// Examples sent between the system message and the seed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub few_shot: Option<FewShot>,
+    /// User message: the seed composed with the style.
+    pub user_message: String,
+    /// Distinct prompts asked for.
+    pub candidates: usize,
+    /// Sampling settings of the first request; later candidates raise the
+    /// temperature and the seed.  The seed is always set: `--sampler-seed`,
+    /// or one picked at random.
+    pub sampler: SamplerConfig,
+}
+
+impl PromptPlan {
+    /// Resolve the seed, style, system prompt and model for a `prompt` run.
+    ///
+    /// Reads `--from-audio` tags and checks the preset against available
+    /// memory (`model` is the preset that would actually load, after any
+    /// `--auto-downgrade`, or the first that fits of a `--fallback` chain),
+    /// but loads nothing and makes no network requests.
+    pub fn new(
+        prompt: Option<String>,
+        model: Option<EnhancerModel>,
+        model_path: Option<&Path>,
+        load: &LoadOptions,
+        options: &PromptOptions,
+    ) -> Result<Self> {
+        let (model, memory_estimate) = match (model_path, &options.fallback) {
+            (Some(path), _) => (path.display().to_string(), None),
+            (None, Some(fallback)) => {
+                let chain = enhancer_chain::resolve(model, fallback);
+                let preset = enhancer_chain::would_load(&chain, load);
+                let mut plan = Self::for_model(
+                    preset.to_string(),
+                    Some(preset.memory_estimate(load)),
+                    prompt,
+                    options,
+                )?;
+                plan.fallback = chain.iter().map(|&preset| preset_name(preset)).collect();
+                return Ok(plan);
+            }
+            (None, None) => {
+                let preset = model.unwrap_or_default();
+                let (preset, load) = match memory::would_load(preset, load) {
+                    Some(choice) => choice,
+                    // Fails with the same error the load would.
+                    None => memory::choose(preset, load)?,
+                };
+                (preset.to_string(), Some(preset.memory_estimate(&load)))
+            }
+        };
+        Self::for_model(model, memory_estimate, prompt, options)
+    }
+
+    /// Like [`new`](Self::new), for a `model` label resolved by the caller
+    /// (e.g. the presets of `prompt --compare`), with no memory check.
+    pub fn for_model(
+        model: String,
+        memory_estimate: Option<String>,
+        prompt: Option<String>,
+        options: &PromptOptions,
+    ) -> Result<Self> {
+        let candidates = options.candidates.unwrap_or(1);
+        if candidates == 0 {
+            anyhow::bail!("--candidates must be at least 1");
+        }
+        let style = options.style.as_deref().map(styles::find).transpose()?;
+
+        let (seed_prompt, seed_source) = match (&options.from_audio, prompt) {
+            (Some(path), _) => {
+                let tags = song_tags::read(path)?;
+                match song_tags::seed_for(&tags, path) {
+                    (seed, true) => (seed, SeedSource::FileName),
+                    (seed, false) => (seed, SeedSource::Tags),
+                }
+            }
+            (None, Some(prompt)) => (prompt, SeedSource::Given),
+            (None, None) => (DEFAULT_SEED.to_string(), SeedSource::Default),
+        };
+        let user_message = match &style {
+            Some(style) => style.compose(&seed_prompt),
+            None => seed_prompt.clone(),
+        };
+
+        Ok(Self {
+            model,
+            memory_estimate,
+            fallback: Vec::new(),
+            seed_prompt,
+            seed_source,
+            style: style.map(|style| style.name),
+            system_prompt: options
+                .system_prompt
+                .clone()
+                .unwrap_or_else(|| system_prompt(PromptBudget::CLIP)),
+            few_shot: options.few_shot.clone(),
+            user_message,
+            candidates,
+            sampler: options
+                .sampler
+                .apply(DEFAULT_SAMPLER)
+                .with_seed(options.sampler_seed.unwrap_or_else(sampler::random_seed)),
+        })
+    }
+}
+
+impl fmt::Display for PromptPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Model          : {}", self.model)?;
+        if let Some(estimate) = &self.memory_estimate {
+            writeln!(f, "Memory estimate: {estimate}")?;
+        }
+        if !self.fallback.is_empty() {
+            writeln!(f, "Fallback chain : {}", self.fallback.join(" → "))?;
+        }
+        let source = match self.seed_source {
+            SeedSource::Given => "",
+            SeedSource::Default => " (default)",
+            SeedSource::Tags => " (from tags)",
+            SeedSource::FileName => " (from file name)",
+        };
+        writeln!(f, "Seed           : {}{source}", self.seed_prompt)?;
+        if let Some(style) = &self.style {
+            writeln!(f, "Style          : {style}")?;
+        }
+        writeln!(f, "Candidates     : {}", self.candidates)?;
+        writeln!(
+            f,
+            "Sampling       : temperature {}, top-p {}, max {} tokens",
+            self.sampler.temperature, self.sampler.top_p, self.sampler.max_len
+        )?;
+        if let Some(top_k) = self.sampler.top_k {
+            writeln!(f, "Top-k          : {top_k}")?;
+        }
+        match (
+            self.sampler.frequency_penalty,
+            self.sampler.presence_penalty,
+        ) {
+            (None, None) => {}
+            (frequency, presence) => writeln!(
+                f,
+                "Penalties      : repeat {}, presence {}",
+                frequency.unwrap_or(0.0),
+                presence.unwrap_or(0.0)
+            )?,
+        }
+        if !self.sampler.stop.is_empty() {
+            let stop: Vec<String> = self.sampler.stop.iter().map(|s| format!("{s:?}")).collect();
+            writeln!(f, "Stop at        : {}", stop.join(", "))?;
+        }
+        if let Some(seed) = self.sampler.seed {
+            writeln!(f, "Sampler seed   : {seed}")?;
+        }
+        if let Some(few_shot) = &self.few_shot {
+            writeln!(f, "Few-shot       : {few_shot}")?;
+        }
+        writeln!(f, "\nSystem message:\n  {}", self.system_prompt)?;
+        if let Some(few_shot) = &self.few_shot {
+            for (i, example) in few_shot.examples.iter().enumerate() {
+                writeln!(f, "\nExample {} user message:\n  {}", i + 1, example.seed)?;
+                writeln!(
+                    f,
+                    "Example {} assistant message:\n  {}",
+                    i + 1,
+                    example.enhanced
+                )?;
+            }
+        }
+        write!(f, "\nUser message:\n  {}", self.user_message)
+    }
+}
+
+/// `prompt --dry-run`: print the [`PromptPlan`] and stop.
+pub fn dry_run(
+    prompt: Option<String>,
+    model: Option<EnhancerModel>,
+    model_path: Option<PathBuf>,
+    load: LoadOptions,
+    options: PromptOptions,
+    out: OutputConfig,
+) -> Result<()> {
+    let plan = PromptPlan::new(prompt, model, model_path.as_deref(), &load, &options)?;
+    if out.json {
+        return ui::print_json(&plan);
+    }
+    println!("{plan}");
+    Ok(())
+}
+
 /// Run the prompt enhancer as a standalone example.
 ///
-/// Loads a text model, takes a seed prompt, and prints the enhanced version.
-pub async fn run(prompt: Option<String>, model: Option<EnhancerModel>) -> Result<()> {
+/// Loads a text model, takes a seed prompt, and prints the enhanced version
+/// (or a [`PromptReport`] with `--json`, or just the prompt text
+/// with `--quiet`).  With `interactive`
+/// the enhanced prompt is then refined in a loop (see
+/// [`prompt_session::run_interactive`]).
+pub async fn run(
+    prompt: Option<String>,
+    model: Option<EnhancerModel>,
+    model_path: Option<PathBuf>,
+    load: LoadOptions,
+    options: PromptOptions,
+    out: OutputConfig,
+) -> Result<()> {
+    if options.interactive && out.json {
+        anyhow::bail!("--json is not supported with --interactive");
+    }
+    let plan = PromptPlan::new(prompt, model, model_path.as_deref(), &load, &options)?;
+    match (plan.seed_source, &options.from_audio) {
+        (SeedSource::FileName, Some(path)) => tracing::warn!(
+            "{} has no title tag, seeding from its file name: {}",
+            path.display(),
+            plan.seed_prompt
+        ),
+        (SeedSource::Tags, _) => status!(out, "Seed from tags: {}", plan.seed_prompt),
+        _ => {}
+    }
+    let PromptOptions {
+        interactive,
+        system_prompt,
+        few_shot,
+        warmup,
+        session,
+        fallback,
+        copy,
+        ..
+    } = options;
+    // Before the load, so a session open elsewhere fails fast.
+    let session = session.as_deref().map(Session::open).transpose()?;
     let preset = model.unwrap_or_default();
+    let n = plan.candidates;
+    let style = plan.style.as_deref().map(styles::find).transpose()?;
+    let seed = plan.seed_prompt;
+    let styled_seed = plan.user_message;
 
-    let seed = prompt.unwrap_or_else(|| {
-        "Detective Conan Main Theme, in the style of Raden Saleh, \
-         trending on artstation, highly detailed"
-            .to_string()
-    });
-
-    println!("Loading prompt enhancer model: {preset}");
-    println!("  Memory estimate: {}", preset.approx_memory());
+    let before = MemorySnapshot::capture();
     let start = Instant::now();
-    let enhancer = PromptEnhancer::from_preset(preset).await?;
+    let mut fallback_failures = Vec::new();
+    let (enhancer, model_label, preset) = match (&model_path, fallback) {
+        (Some(path), _) => {
+            status!(out, "Loading prompt enhancer model: {}", path.display());
+            let enhancer = PromptEnhancer::from_local_path(path, &load).await?;
+            (enhancer, path.display().to_string(), None)
+        }
+        (None, Some(fallback)) => {
+            let chain = enhancer_chain::resolve(model, &fallback);
+            let builder = CliBuilder { load: &load, out };
+            let chosen = enhancer_chain::load_chain(&chain, &builder).await?;
+            if !chosen.failures.is_empty() {
+                status!(
+                    out,
+                    "Fell back to {} after {} failed preset(s)",
+                    preset_name(chosen.preset),
+                    chosen.failures.len()
+                );
+            }
+            fallback_failures = chosen.failures;
+            let model_id = chosen.preset.model_id().to_string();
+            (chosen.loaded, model_id, Some(chosen.preset))
+        }
+        (None, None) => {
+            let (preset, load) = memory::choose(preset, &load)?;
+            status!(out, "Loading prompt enhancer model: {preset}");
+            status!(out, "  Memory estimate: {}", preset.memory_estimate(&load));
+            let settings = load.apply(preset.default_settings());
+            if let Some(weights) = load.uqff.describe(preset.model_id(), settings) {
+                status!(out, "  {weights}");
+            }
+            let enhancer = PromptEnhancer::from_preset_with_options(preset, &load).await?;
+            (enhancer, preset.model_id().to_string(), Some(preset))
+        }
+    };
+    let sampler_seed = plan.sampler.seed.unwrap_or_default();
+    let mut enhancer = enhancer.with_sampler(plan.sampler);
+    if let Some(system_prompt) = system_prompt {
+        enhancer = enhancer.with_system_prompt(system_prompt);
+    }
+    if let Some(few_shot) = &few_shot {
+        enhancer = enhancer.with_examples(few_shot.pairs());
+    }
     let load_elapsed = start.elapsed();
-    println!("Model loaded in {}", fmt_duration(load_elapsed));
+    let memory = MemorySnapshot::capture().since(before);
+    status!(out, "Model loaded in {}", fmt_duration(load_elapsed));
+    status!(out, "{}", memory.banner());
+    let warm_up = if warmup {
+        let elapsed = enhancer.warm_up().await?;
+        status!(out, "Warm-up: {}", fmt_duration(elapsed));
+        Some(elapsed)
+    } else {
+        None
+    };
+    status!(out, "Sampler seed: {sampler_seed}");
+    detail!(out, "System prompt:\n{}", enhancer.system_prompt());
+    if let Some(few_shot) = &few_shot {
+        detail!(out, "Few-shot: {few_shot}");
+        for example in &few_shot.examples {
+            detail!(out, "  {} → {}", example.seed, example.enhanced);
+        }
+    }
 
-    println!("\nSeed prompt:\n  \"{seed}\"\n");
+    if interactive {
+        return prompt_session::run_interactive(enhancer, styled_seed, session).await;
+    }
 
+    if let Some(style) = &style {
+        status!(out, "\nStyle: {style}");
+    }
+    status!(out, "\nSeed prompt:\n  \"{styled_seed}\"\n");
+
+    let live = streams_live(n, out);
     let enhance_start = Instant::now();
-    let enhanced = enhancer.enhance(&seed).await?;
+    let results = if live {
+        println!("Enhanced prompt:");
+        print!("  \"");
+        let mut echo = LiveEcho::default();
+        let result = enhancer
+            .enhance_streaming(&styled_seed, |token| echo.push(token))
+            .await?;
+        echo.finish(&styled_seed, &result, enhance_start.elapsed());
+        vec![result]
+    } else {
+        enhancer.enhance_n(&styled_seed, n).await?
+    };
     let enhance_elapsed = enhance_start.elapsed();
+    let usage: Usage = results.iter().map(|result| result.usage).sum();
+    let candidates: Vec<String> = results.into_iter().map(String::from).collect();
+    // Before any output, so `--json` and `--quiet` runs copy too.
+    let copied = copy && clipboard::copy(&candidates[0]);
+
+    if out.json {
+        return ui::print_json(&PromptReport {
+            model: model_label,
+            preset: preset.map(preset_name),
+            fallback_failures,
+            seed_prompt: seed,
+            style: style.map(|style| style.name),
+            enhanced: candidates[0].clone(),
+            candidates,
+            load_secs: load_elapsed.as_secs_f64(),
+            enhance_secs: enhance_elapsed.as_secs_f64(),
+            sampler_seed,
+            usage,
+            memory,
+            warm_up_secs: warm_up.map(|d| d.as_secs_f64()),
+            few_shot,
+        });
+    }
 
-    println!("Enhanced prompt ({}):", fmt_duration(enhance_elapsed));
-    println!("  \"{enhanced}\"");
+    if out.quiet() {
+        for candidate in &candidates {
+            println!("{candidate}");
+        }
+    } else if live {
+        // Already printed as it streamed in.
+    } else if let [enhanced] = candidates.as_slice() {
+        println!("Enhanced prompt ({}):", fmt_duration(enhance_elapsed));
+        println!("  \"{enhanced}\"");
+    } else {
+        println!(
+            "Enhanced prompts ({} distinct of {n}, {}):",
+            candidates.len(),
+            fmt_duration(enhance_elapsed)
+        );
+        for (i, candidate) in candidates.iter().enumerate() {
+            println!("  [{}] \"{candidate}\"", i + 1);
+        }
+    }
+    status!(out, "\nTokens: {usage}");
+    if copied {
+        status!(out, "Copied to the clipboard.");
+    }
 
     Ok(())
 }
@@ -0,0 +1,206 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::cli_chat::unix_secs;
+use crate::sampler::SamplerConfig;
+
+/// Bumped when the file layout changes; a file with another version is
+/// treated as empty.
+const CACHE_VERSION: u32 = 1;
+
+/// Entries kept; the oldest are dropped beyond this.
+const MAX_ENTRIES: usize = 1000;
+
+// ── Location ─────────────────────────────────────────────────────────────────
+
+/// `~/.cache/mistralrs-example/prompts.json`, or under `XDG_CACHE_HOME`
+/// when set.
+pub fn cache_path() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CACHE_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => {
+            let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+            PathBuf::from(home).join(".cache")
+        }
+    };
+    Some(base.join("mistralrs-example/prompts.json"))
+}
+
+// ── Keys ─────────────────────────────────────────────────────────────────────
+
+/// How `image --seed-prompt` uses the cache.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CacheMode {
+    /// Reuse a cached prompt, or store the new one.
+    #[default]
+    Use,
+    /// `--refresh`: enhance again and replace the cached prompt.
+    Refresh,
+    /// `--no-cache`: neither read nor write.
+    Off,
+}
+
+/// 64-bit FNV-1a.  Used instead of std's hasher, whose output may change
+/// between Rust releases, so keys stay valid across rebuilds.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// What an enhanced prompt depends on: the seed as sent (style
+/// included), the model, the sampler seed, and a hash of the rest.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheKey {
+    pub seed: String,
+    /// Model ID, or the path of a local model.
+    pub model: String,
+    pub sampler_seed: u64,
+    /// [`settings_hash`] of the system prompt and sampler settings.
+    pub settings: String,
+}
+
+impl CacheKey {
+    pub fn new(
+        seed: &str,
+        model: &str,
+        sampler_seed: u64,
+        system_prompt: &str,
+        sampler: &SamplerConfig,
+    ) -> Self {
+        Self {
+            seed: seed.to_string(),
+            model: model.to_string(),
+            sampler_seed,
+            settings: settings_hash(system_prompt, sampler),
+        }
+    }
+}
+
+/// Hex FNV-1a of the system prompt and every sampler setting but the seed,
+/// which is part of the key on its own.
+pub fn settings_hash(system_prompt: &str, sampler: &SamplerConfig) -> String {
+    let sampler = SamplerConfig {
+        seed: None,
+        ..sampler.clone()
+    };
+    let sampler = serde_json::to_string(&sampler).unwrap_or_default();
+    format!(
+        "{:016x}",
+        fnv1a(format!("{system_prompt}\0{sampler}").as_bytes())
+    )
+}
+
+// ── Cache ────────────────────────────────────────────────────────────────────
+
+/// One cached enhancement.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CachedPrompt {
+    #[serde(flatten)]
+    pub key: CacheKey,
+    /// The enhanced prompt.
+    pub prompt: String,
+    /// Seconds since the Unix epoch when it was stored.
+    pub created: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheFile {
+    version: u32,
+    entries: Vec<CachedPrompt>,
+}
+
+/// Enhanced prompts from earlier runs, so `image` can skip loading the
+/// enhancer for a seed it has already seen with the same settings.
+pub struct PromptCache {
+    path: PathBuf,
+    entries: Vec<CachedPrompt>,
+}
+
+impl PromptCache {
+    /// Read the cache at [`cache_path`].
+    pub fn open() -> Option<Self> {
+        cache_path().map(|path| Self::open_at(&path))
+    }
+
+    /// Read the cache at `path`.  A missing file is an empty cache.  So is
+    /// one that can't be read or parsed (or has another version), with a
+    /// warning; it's replaced on the next [`save`](Self::save).
+    pub fn open_at(path: &Path) -> Self {
+        let entries = match std::fs::read_to_string(path) {
+            Ok(json) => match serde_json::from_str::<CacheFile>(&json) {
+                Ok(file) if file.version == CACHE_VERSION => file.entries,
+                Ok(file) => {
+                    tracing::warn!(
+                        "{} has cache version {}, expected {CACHE_VERSION}; starting empty",
+                        path.display(),
+                        file.version
+                    );
+                    Vec::new()
+                }
+                Err(err) => {
+                    tracing::warn!(
+                        "{} is corrupt ({err}); starting with an empty prompt cache",
+                        path.display()
+                    );
+                    Vec::new()
+                }
+            },
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => {
+                tracing::warn!(
+                    "can't read {} ({err}); starting with an empty prompt cache",
+                    path.display()
+                );
+                Vec::new()
+            }
+        };
+        Self {
+            path: path.to_path_buf(),
+            entries,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn get(&self, key: &CacheKey) -> Option<&CachedPrompt> {
+        self.entries.iter().find(|entry| &entry.key == key)
+    }
+
+    /// Store `prompt` under `key`, replacing any earlier entry for it and
+    /// dropping the oldest past [`MAX_ENTRIES`].
+    pub fn insert(&mut self, key: CacheKey, prompt: String) {
+        self.entries.retain(|entry| entry.key != key);
+        self.entries.push(CachedPrompt {
+            key,
+            prompt,
+            created: unix_secs(SystemTime::now()),
+        });
+        if self.entries.len() > MAX_ENTRIES {
+            let excess = self.entries.len() - MAX_ENTRIES;
+            self.entries.drain(..excess);
+        }
+    }
+
+    /// Write the cache, through a temporary file so an interrupted write
+    /// never leaves it half-written.
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let file = CacheFile {
+            version: CACHE_VERSION,
+            entries: self.entries.clone(),
+        };
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_string_pretty(&file)?)
+            .with_context(|| format!("Failed to write {}", tmp.display()))?;
+        std::fs::rename(&tmp, &self.path)
+            .with_context(|| format!("Failed to replace {}", self.path.display()))
+    }
+}
@@ -0,0 +1,283 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+use crate::load_options::LoadOptions;
+use crate::memory;
+use crate::memstats::MemorySnapshot;
+use crate::promp_enhancer::{EnhancerModel, FewShot, PromptEnhancer, PromptOptions, PromptPlan};
+use crate::ui::{self, OutputConfig, fmt_duration, status};
+use crate::usage::Usage;
+
+/// The `--model` name of `preset`, e.g. `gemma-e2b`.
+fn preset_name(preset: EnhancerModel) -> String {
+    preset
+        .to_possible_value()
+        .map(|v| v.get_name().to_string())
+        .unwrap_or_default()
+}
+
+// ── Results ──────────────────────────────────────────────────────────────────
+
+/// One preset's outcome in a `prompt --compare` run: the enhanced prompt
+/// and what it cost, or why the preset couldn't produce one.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PresetComparison {
+    /// The `--model` name, e.g. `gemma-e2b`.
+    pub preset: String,
+    /// HuggingFace ID of the preset.
+    pub model_id: &'static str,
+    /// Seconds spent loading the model; `None` if it never loaded.
+    pub load_secs: Option<f64>,
+    /// Seconds the `--warmup` request took, not counted in
+    /// `enhance_secs`; `None` when no warm-up ran.
+    pub warm_up_secs: Option<f64>,
+    /// Seconds spent enhancing the seed; `None` if enhancement didn't run
+    /// or failed.
+    pub enhance_secs: Option<f64>,
+    /// Whitespace-separated words in `enhanced`.
+    pub words: Option<usize>,
+    pub enhanced: Option<String>,
+    pub usage: Option<Usage>,
+    /// Why the preset failed (a gated repo, too little memory, …).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl PresetComparison {
+    fn failed(preset: EnhancerModel, load_secs: Option<f64>, error: &anyhow::Error) -> Self {
+        Self {
+            preset: preset_name(preset),
+            model_id: preset.model_id(),
+            load_secs,
+            warm_up_secs: None,
+            enhance_secs: None,
+            words: None,
+            enhanced: None,
+            usage: None,
+            error: Some(format!("{error:#}")),
+        }
+    }
+}
+
+/// Enhance `plan`'s seed with each of `presets` in turn and collect the
+/// results, in the order given.
+///
+/// Each preset is loaded, used once and dropped before the next loads, so
+/// only one model is in memory at a time.  Every preset gets the same
+/// system prompt, sampler settings and sampler seed.  With `warmup`, each
+/// preset gets a throwaway request before the timed one.  A preset that
+/// fails to load or enhance is recorded with its error and the comparison
+/// moves on.
+pub async fn compare(
+    presets: &[EnhancerModel],
+    load: &LoadOptions,
+    plan: &PromptPlan,
+    warmup: bool,
+    out: OutputConfig,
+) -> Vec<PresetComparison> {
+    let mut results = Vec::with_capacity(presets.len());
+    for (i, &preset) in presets.iter().enumerate() {
+        status!(out, "\n== {}/{}: {preset} ==", i + 1, presets.len());
+        let result = compare_one(preset, load, plan, warmup, out).await;
+        match &result.error {
+            Some(error) => tracing::warn!("{}: {error}", result.preset),
+            None => status!(
+                out,
+                "  enhanced in {:.1}s: {} words",
+                result.enhance_secs.unwrap_or_default(),
+                result.words.unwrap_or_default()
+            ),
+        }
+        results.push(result);
+    }
+    results
+}
+
+/// Load `preset`, enhance `plan`'s seed with it and release it.
+async fn compare_one(
+    preset: EnhancerModel,
+    load: &LoadOptions,
+    plan: &PromptPlan,
+    warmup: bool,
+    out: OutputConfig,
+) -> PresetComparison {
+    if let Err(err) = memory::choose(preset, load) {
+        return PresetComparison::failed(preset, None, &err);
+    }
+    let before = MemorySnapshot::capture();
+    let start = Instant::now();
+    let enhancer = match PromptEnhancer::from_preset_with_options(preset, load).await {
+        Ok(enhancer) => enhancer
+            .with_sampler(plan.sampler.clone())
+            .with_system_prompt(plan.system_prompt.clone())
+            .with_examples(
+                plan.few_shot
+                    .as_ref()
+                    .map(FewShot::pairs)
+                    .unwrap_or_default(),
+            ),
+        Err(err) => return PresetComparison::failed(preset, None, &err.into()),
+    };
+    let load_secs = start.elapsed().as_secs_f64();
+    status!(
+        out,
+        "  loaded in {load_secs:.1}s ({})",
+        MemorySnapshot::capture().since(before).after
+    );
+    let warm_up_secs = if warmup {
+        match enhancer.warm_up().await {
+            Ok(elapsed) => {
+                status!(out, "  warm-up: {:.1}s", elapsed.as_secs_f64());
+                Some(elapsed.as_secs_f64())
+            }
+            Err(err) => return PresetComparison::failed(preset, Some(load_secs), &err.into()),
+        }
+    } else {
+        None
+    };
+
+    match enhancer.enhance(&plan.user_message).await {
+        Ok(result) => PresetComparison {
+            preset: preset_name(preset),
+            model_id: preset.model_id(),
+            load_secs: Some(load_secs),
+            warm_up_secs,
+            enhance_secs: Some(result.duration.as_secs_f64()),
+            words: Some(result.text.split_whitespace().count()),
+            usage: Some(result.usage),
+            enhanced: Some(result.text),
+            error: None,
+        },
+        Err(err) => PresetComparison::failed(preset, Some(load_secs), &err.into()),
+    }
+}
+
+/// Print `results` as a table of timings and word counts, then each
+/// preset's prompt (or error) in full.
+pub fn print_comparison(results: &[PresetComparison]) {
+    let dash = || "-".to_string();
+    let secs = |s: Option<f64>| s.map_or_else(dash, |s| fmt_duration(Duration::from_secs_f64(s)));
+    println!(
+        "{:<14} {:>8} {:>8} {:>6}",
+        "Preset", "Load", "Enhance", "Words"
+    );
+    for r in results {
+        println!(
+            "{:<14} {:>8} {:>8} {:>6}",
+            r.preset,
+            secs(r.load_secs),
+            secs(r.enhance_secs),
+            r.words.map_or_else(dash, |w| w.to_string())
+        );
+    }
+    for r in results {
+        println!("\n{}:", r.preset);
+        match (&r.enhanced, &r.error) {
+            (Some(enhanced), _) => println!("  \"{enhanced}\""),
+            (None, Some(error)) => println!("  failed: {error}"),
+            (None, None) => println!("  (no result)"),
+        }
+    }
+}
+
+// ── CLI entry-point ──────────────────────────────────────────────────────────
+
+/// The presets `--compare` asked for: the list given, or every preset when
+/// it was passed bare.  Repeats are dropped.
+pub fn resolve_presets(compare: Vec<EnhancerModel>) -> Vec<EnhancerModel> {
+    let presets = if compare.is_empty() {
+        EnhancerModel::value_variants().to_vec()
+    } else {
+        compare
+    };
+    let mut unique = Vec::with_capacity(presets.len());
+    for preset in presets {
+        if !unique.contains(&preset) {
+            unique.push(preset);
+        }
+    }
+    unique
+}
+
+/// The plan shared by every preset, labelled with the preset names.
+fn plan(
+    prompt: Option<String>,
+    presets: &[EnhancerModel],
+    load: &LoadOptions,
+    options: &PromptOptions,
+) -> Result<PromptPlan> {
+    if load.uqff.has_explicit_path() {
+        anyhow::bail!(
+            "--save-uqff / --from-uqff name a single model's weights; use --cache-quantized with --compare"
+        );
+    }
+    if load.auto_downgrade {
+        anyhow::bail!(
+            "--auto-downgrade is not supported with --compare; it would compare a different preset"
+        );
+    }
+    let names: Vec<String> = presets.iter().map(|&preset| preset_name(preset)).collect();
+    PromptPlan::for_model(names.join(", "), None, prompt, options)
+}
+
+/// `prompt --compare --dry-run`: print the shared plan and stop.
+pub fn dry_run(
+    prompt: Option<String>,
+    presets: Vec<EnhancerModel>,
+    load: LoadOptions,
+    options: PromptOptions,
+    out: OutputConfig,
+) -> Result<()> {
+    let plan = plan(prompt, &resolve_presets(presets), &load, &options)?;
+    if out.json {
+        return ui::print_json(&plan);
+    }
+    println!("{plan}");
+    Ok(())
+}
+
+/// `prompt --compare`: enhance the same seed with each preset (every
+/// preset when `presets` is empty) and print the results side by side, or
+/// as a JSON array with `--json`.
+///
+/// Fails only when no preset produced a prompt.
+pub async fn run(
+    prompt: Option<String>,
+    presets: Vec<EnhancerModel>,
+    load: LoadOptions,
+    options: PromptOptions,
+    out: OutputConfig,
+) -> Result<()> {
+    let presets = resolve_presets(presets);
+    let plan = plan(prompt, &presets, &load, &options)?;
+    if let Some(style) = &plan.style {
+        status!(out, "Style: {style}");
+    }
+    status!(out, "Seed prompt:\n  \"{}\"", plan.user_message);
+    status!(
+        out,
+        "Sampler seed: {}",
+        plan.sampler.seed.unwrap_or_default()
+    );
+
+    let results = compare(&presets, &load, &plan, options.warmup, out).await;
+
+    if out.json {
+        ui::print_json(&results)?;
+    } else if out.quiet() {
+        for r in &results {
+            if let Some(enhanced) = &r.enhanced {
+                println!("{}\t{enhanced}", r.preset);
+            }
+        }
+    } else {
+        println!();
+        print_comparison(&results);
+    }
+    if results.iter().all(|r| r.enhanced.is_none()) {
+        anyhow::bail!("No preset produced a prompt");
+    }
+    Ok(())
+}
@@ -0,0 +1,435 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::Path;
+use std::sync::OnceLock;
+use tokenizers::Tokenizer;
+use tokenizers::models::bpe::BPE;
+use tokenizers::normalizers::{Lowercase, NFC, Sequence as NormalizerSequence};
+use tokenizers::pre_tokenizers::byte_level::ByteLevel;
+use tokenizers::pre_tokenizers::sequence::Sequence as PreTokenizerSequence;
+use tokenizers::pre_tokenizers::split::{Split, SplitPattern};
+use tokenizers::tokenizer::SplitDelimiterBehavior;
+
+use crate::loader;
+
+// ── Limits ───────────────────────────────────────────────────────────────────
+
+/// CLIP (used by FLUX.1-schnell) has a hard limit of 77 tokens, including
+/// BOS/EOS.
+pub const MAX_CLIP_TOKENS: usize = 77;
+
+/// Tokens CLIP adds around the prompt (BOS + EOS).
+const SPECIAL_TOKENS: usize = 2;
+
+/// Word-count ceiling used when the CLIP tokenizer isn't available:
+/// [`PromptBudget::CLIP`]'s [`max_words`](PromptBudget::max_words).
+pub const MAX_PROMPT_WORDS: usize = 50;
+
+/// Smallest `--max-prompt-tokens` accepted: anything less leaves no room
+/// for a useful prompt once BOS/EOS are taken.
+pub const MIN_PROMPT_TOKENS: usize = 8;
+
+/// Truncate `text` so it fits in CLIP's window; [`PromptBudget::CLIP`]'s
+/// [`truncate`](PromptBudget::truncate).
+pub fn truncate_prompt(text: &str) -> String {
+    PromptBudget::CLIP.truncate(text)
+}
+
+// ── PromptBudget ─────────────────────────────────────────────────────────────
+
+/// Text encoder windows of the diffusion models we know, by repo name
+/// (matched case-insensitively, on the last path component so local copies
+/// and mirrors match too).  Both FLUX models feed the prompt to CLIP as
+/// well as T5, and CLIP's window is the one that overflows.
+const MODEL_BUDGETS: &[(&str, usize)] = &[
+    ("FLUX.1-schnell", MAX_CLIP_TOKENS),
+    ("FLUX.1-dev", MAX_CLIP_TOKENS),
+];
+
+/// How long a prompt the diffusion model's text encoder takes, and the
+/// limits derived from it: the words the enhancer is asked for and the
+/// tokens it may generate.
+///
+/// Resolved from the diffusion model with [`for_model`](Self::for_model);
+/// models missing from the table get CLIP's 77 tokens, which every
+/// mistral.rs diffusion loader uses.  `--max-prompt-tokens` overrides it
+/// for models with a longer window.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct PromptBudget {
+    max_tokens: usize,
+}
+
+impl Default for PromptBudget {
+    fn default() -> Self {
+        Self::CLIP
+    }
+}
+
+impl PromptBudget {
+    /// CLIP's 77 tokens, BOS/EOS included.
+    pub const CLIP: Self = Self::new(MAX_CLIP_TOKENS);
+
+    /// A window of `max_tokens` tokens, BOS/EOS included; at least
+    /// [`MIN_PROMPT_TOKENS`].
+    pub const fn new(max_tokens: usize) -> Self {
+        Self {
+            max_tokens: if max_tokens < MIN_PROMPT_TOKENS {
+                MIN_PROMPT_TOKENS
+            } else {
+                max_tokens
+            },
+        }
+    }
+
+    /// The window of `model_id` (a repo ID or local path) from the table,
+    /// else [`CLIP`](Self::CLIP).
+    pub fn for_model(model_id: &str) -> Self {
+        let name = model_id
+            .trim_end_matches(['/', '\\'])
+            .rsplit(['/', '\\'])
+            .next()
+            .unwrap_or(model_id);
+        MODEL_BUDGETS
+            .iter()
+            .find(|(known, _)| known.eq_ignore_ascii_case(name))
+            .map_or(Self::CLIP, |&(_, tokens)| Self::new(tokens))
+    }
+
+    /// `max_tokens` when given (`--max-prompt-tokens`), else the window of
+    /// `model_id`.
+    pub fn resolve(model_id: &str, max_tokens: Option<usize>) -> Self {
+        max_tokens.map_or_else(|| Self::for_model(model_id), Self::new)
+    }
+
+    /// Words the prompt may have when no tokenizer is at hand, and the
+    /// limit the enhancer's system prompt asks for: two thirds of the
+    /// window (50 for CLIP), leaving headroom for sub-word splits.  Prompts
+    /// heavy in punctuation or compound words can still exceed the window.
+    pub fn max_words(self) -> usize {
+        (self.max_tokens - SPECIAL_TOKENS) * 2 / 3
+    }
+
+    /// Tokens the enhancer may generate: [`max_words`](Self::max_words)
+    /// with room for sub-word splits (80 for CLIP), so the reply ends
+    /// near the limit instead of running on.
+    pub fn max_new_tokens(self) -> usize {
+        self.max_words() * 8 / 5
+    }
+
+    /// Truncate `text` so it fits in the window.
+    ///
+    /// Counts with the real CLIP tokenizer when it can be found in the
+    /// HuggingFace cache (close enough for T5, which splits words
+    /// similarly), otherwise falls back to [`max_words`](Self::max_words).
+    pub fn truncate(self, text: &str) -> String {
+        clip_tokenizer()
+            .and_then(|tokenizer| {
+                truncate_to_tokens(tokenizer, text, self.max_tokens - SPECIAL_TOKENS).ok()
+            })
+            .unwrap_or_else(|| truncate_to_words(text, self.max_words()))
+    }
+
+    /// `template` with each `{max_words}` replaced by
+    /// [`max_words`](Self::max_words), for system prompts that state the
+    /// limit.
+    pub fn fill(self, template: &str) -> String {
+        template.replace("{max_words}", &self.max_words().to_string())
+    }
+}
+
+/// `--max-prompt-tokens`: a token count of at least [`MIN_PROMPT_TOKENS`].
+pub fn parse_max_tokens_arg(arg: &str) -> Result<usize, String> {
+    let tokens: usize = arg
+        .trim()
+        .parse()
+        .map_err(|_| format!("'{arg}' isn't a number of tokens"))?;
+    if tokens < MIN_PROMPT_TOKENS {
+        return Err(format!("must be at least {MIN_PROMPT_TOKENS}"));
+    }
+    Ok(tokens)
+}
+
+/// `77 tokens (~50 words)`.
+impl std::fmt::Display for PromptBudget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} tokens (~{} words)",
+            self.max_tokens,
+            self.max_words()
+        )
+    }
+}
+
+/// Truncate `text` to at most `max_tokens` tokens (excluding special tokens).
+///
+/// The cut lands on a token boundary; if that boundary falls inside a word,
+/// it moves back to the preceding whitespace so no half-word is left behind.
+/// Trailing separators (`,` `;` `-` …) are dropped.
+pub fn truncate_to_tokens(tokenizer: &Tokenizer, text: &str, max_tokens: usize) -> Result<String> {
+    let encoding = tokenizer
+        .encode(text, false)
+        .map_err(anyhow::Error::msg)
+        .context("Failed to tokenize prompt")?;
+    let offsets = encoding.get_offsets();
+    if offsets.len() <= max_tokens {
+        return Ok(text.to_string());
+    }
+    if max_tokens == 0 {
+        return Ok(String::new());
+    }
+
+    let mut end = offsets[max_tokens - 1].1;
+    let next_start = offsets[max_tokens].0;
+    let mid_word = next_start == end
+        && text[..end]
+            .chars()
+            .next_back()
+            .is_some_and(char::is_alphanumeric)
+        && text[end..]
+            .chars()
+            .next()
+            .is_some_and(char::is_alphanumeric);
+    if mid_word && let Some(space) = text[..end].rfind(char::is_whitespace) {
+        end = space;
+    }
+
+    Ok(text[..end]
+        .trim_end_matches(|c: char| c.is_whitespace() || ",;:-–—".contains(c))
+        .to_string())
+}
+
+/// Truncate `text` to at most `max_words` whitespace-separated words.
+pub fn truncate_to_words(text: &str, max_words: usize) -> String {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    if words.len() <= max_words {
+        return text.to_string();
+    }
+    words[..max_words].join(" ")
+}
+
+/// Stops a streamed reply at `max_words` whitespace-separated words.
+///
+/// Feed it each chunk as it arrives; it returns the part of the chunk to
+/// keep, so a word split across chunks still counts once.  Once the next
+/// word would start past the limit, the rest is dropped and
+/// [`is_done`](Self::is_done) tells the caller to stop the generation.
+/// The kept chunks joined and trimmed give the same words as
+/// [`truncate_to_words`] on the full reply.
+#[derive(Clone, Debug)]
+pub struct WordCutoff {
+    max_words: usize,
+    words: usize,
+    in_word: bool,
+    done: bool,
+}
+
+impl WordCutoff {
+    pub fn new(max_words: usize) -> Self {
+        Self {
+            max_words,
+            words: 0,
+            in_word: false,
+            done: false,
+        }
+    }
+
+    /// The prefix of `chunk` that fits under the limit: all of it until the
+    /// limit is reached, nothing after.
+    pub fn push<'a>(&mut self, chunk: &'a str) -> &'a str {
+        if self.done {
+            return "";
+        }
+        for (i, c) in chunk.char_indices() {
+            if c.is_whitespace() {
+                self.in_word = false;
+            } else if !self.in_word {
+                if self.words == self.max_words {
+                    self.done = true;
+                    return &chunk[..i];
+                }
+                self.words += 1;
+                self.in_word = true;
+            }
+        }
+        chunk
+    }
+
+    /// Whether the limit was reached and the rest of the reply can be
+    /// dropped.
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+}
+
+// ── Overflow retries ─────────────────────────────────────────────────────────
+
+/// Share of a prompt kept by each [`shorten_prompt`] retry.
+pub const RETRY_KEEP: f64 = 0.8;
+
+/// Retries with a shorter prompt after the diffusion model rejects one as
+/// too long.
+pub const MAX_OVERFLOW_RETRIES: usize = 3;
+
+/// Lowercase fragments of the errors candle and mistral.rs raise when the
+/// prompt runs past the text encoder's position embeddings.
+const OVERFLOW_MARKERS: &[&str] = &[
+    "sequence length",
+    "max_position_embeddings",
+    "position_ids",
+    "position embedding",
+    "narrow invalid args",
+    "index-select invalid index",
+    "too many tokens",
+    "prompt is too long",
+];
+
+/// Whether `err` is the text encoder rejecting a prompt that tokenizes
+/// past its window, despite [`truncate_prompt`]: emoji and non-Latin
+/// scripts split into far more tokens than words.
+pub fn is_prompt_overflow(err: &anyhow::Error) -> bool {
+    let message = format!("{err:#}").to_lowercase();
+    OVERFLOW_MARKERS
+        .iter()
+        .any(|marker| message.contains(marker))
+}
+
+/// `prompt` cut to about [`RETRY_KEEP`] of its length, for a retry after
+/// [`is_prompt_overflow`].
+///
+/// Counts CLIP tokens when the tokenizer is cached, words otherwise (see
+/// [`shorten_words`]).  Always shorter than `prompt` unless it's empty.
+pub fn shorten_prompt(prompt: &str) -> String {
+    let by_tokens = clip_tokenizer().and_then(|tokenizer| {
+        let tokens = tokenizer.encode(prompt, false).ok()?.len();
+        let keep = ((tokens as f64 * RETRY_KEEP) as usize).min(tokens.saturating_sub(1));
+        truncate_to_tokens(tokenizer, prompt, keep)
+            .ok()
+            .filter(|shorter| shorter.len() < prompt.trim_end().len())
+    });
+    by_tokens.unwrap_or_else(|| shorten_words(prompt, RETRY_KEEP))
+}
+
+/// Keep about `keep` (0–1) of `text`, dropping at least one unit.
+///
+/// The unit is the whitespace-separated word; text with a single word
+/// (Chinese and Japanese are written without spaces) is cut by characters
+/// instead.  A cut never leaves a dangling zero-width joiner, variation
+/// selector or combining mark, so an emoji sequence loses whole parts, and
+/// trailing separators are dropped as in [`truncate_to_tokens`].
+pub fn shorten_words(text: &str, keep: f64) -> String {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let shorter = if words.len() > 1 {
+        let count = ((words.len() as f64 * keep) as usize).clamp(1, words.len() - 1);
+        words[..count].join(" ")
+    } else {
+        let text = text.trim();
+        let chars = text.chars().count();
+        let count = ((chars as f64 * keep) as usize).min(chars.saturating_sub(1));
+        let end = text
+            .char_indices()
+            .nth(count)
+            .map_or(text.len(), |(i, _)| i);
+        text[..end].trim_end_matches(is_joiner).to_string()
+    };
+    shorter
+        .trim_end_matches(|c: char| c.is_whitespace() || ",;:-–—、，。".contains(c))
+        .to_string()
+}
+
+/// Characters that only modify the one before them: zero-width joiner,
+/// variation selectors, skin-tone modifiers and common combining marks.
+fn is_joiner(c: char) -> bool {
+    matches!(c,
+        '\u{200D}'
+        | '\u{FE00}'..='\u{FE0F}'
+        | '\u{1F3FB}'..='\u{1F3FF}'
+        | '\u{0300}'..='\u{036F}'
+        | '\u{20D0}'..='\u{20FF}'
+    )
+}
+
+/// `12 words, 80 characters`, for messages about a prompt's length.
+pub fn describe_length(text: &str) -> String {
+    format!(
+        "{} words, {} characters",
+        text.split_whitespace().count(),
+        text.chars().count()
+    )
+}
+
+// ── CLIP tokenizer ───────────────────────────────────────────────────────────
+
+/// Repos whose cached snapshot may contain a ready-made CLIP `tokenizer.json`.
+const CLIP_TOKENIZER_REPOS: &[&str] = &["openai/clip-vit-large-patch14"];
+
+/// FLUX repos that ship the CLIP vocabulary under `tokenizer/`.
+const FLUX_REPOS: &[&str] = &[
+    "black-forest-labs/FLUX.1-schnell",
+    "black-forest-labs/FLUX.1-dev",
+];
+
+/// CLIP's pre-tokenization regex, as used by the HuggingFace CLIP tokenizer.
+const CLIP_SPLIT_PATTERN: &str = r"'s|'t|'re|'ve|'m|'ll|'d|[\p{L}]+|[\p{N}]|[^\s\p{L}\p{N}]+";
+
+static CLIP_TOKENIZER: OnceLock<Option<Tokenizer>> = OnceLock::new();
+
+/// The CLIP tokenizer, loaded once per process from the HuggingFace cache.
+///
+/// `None` if it isn't cached yet (FLUX hasn't been downloaded) or fails to
+/// load; callers fall back to the word heuristic.
+pub fn clip_tokenizer() -> Option<&'static Tokenizer> {
+    CLIP_TOKENIZER
+        .get_or_init(|| load_clip_tokenizer().ok())
+        .as_ref()
+}
+
+fn load_clip_tokenizer() -> Result<Tokenizer> {
+    for repo in CLIP_TOKENIZER_REPOS {
+        for snapshot in loader::snapshots(repo) {
+            let path = snapshot.join("tokenizer.json");
+            if path.is_file() {
+                return Tokenizer::from_file(&path).map_err(anyhow::Error::msg);
+            }
+        }
+    }
+
+    for repo in FLUX_REPOS {
+        for snapshot in loader::snapshots(repo) {
+            let dir = snapshot.join("tokenizer");
+            let (vocab, merges) = (dir.join("vocab.json"), dir.join("merges.txt"));
+            if vocab.is_file() && merges.is_file() {
+                return clip_from_vocab(&vocab, &merges);
+            }
+        }
+    }
+
+    anyhow::bail!("No cached CLIP tokenizer in the HuggingFace cache")
+}
+
+/// Build a CLIP tokenizer from `vocab.json` + `merges.txt`, matching the
+/// normalizer / pre-tokenizer / BPE settings of HuggingFace's fast CLIP
+/// tokenizer.
+fn clip_from_vocab(vocab: &Path, merges: &Path) -> Result<Tokenizer> {
+    let bpe = BPE::from_file(&vocab.to_string_lossy(), &merges.to_string_lossy())
+        .end_of_word_suffix("</w>".to_string())
+        .build()
+        .map_err(anyhow::Error::msg)?;
+    let split = Split::new(
+        SplitPattern::Regex(CLIP_SPLIT_PATTERN.to_string()),
+        SplitDelimiterBehavior::Removed,
+        true,
+    )
+    .map_err(anyhow::Error::msg)?;
+
+    let mut tokenizer = Tokenizer::new(bpe);
+    tokenizer.with_normalizer(Some(NormalizerSequence::new(vec![
+        NFC.into(),
+        Lowercase.into(),
+    ])));
+    tokenizer.with_pre_tokenizer(Some(PreTokenizerSequence::new(vec![
+        split.into(),
+        ByteLevel::new(false, true, false).into(),
+    ])));
+    Ok(tokenizer)
+}
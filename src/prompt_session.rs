@@ -0,0 +1,359 @@
+use anyhow::{Context, Result};
+use mistralrs::{RequestBuilder, TextMessageRole};
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use crate::cli_chat::{ChatTurn, SavedChat, SavedKind, command_arg};
+use crate::image_generation::{self, DiffusionConfig, EnhancerOptions, ImageOptions};
+use crate::image_metadata::MetadataOutput;
+use crate::interrupt::Interrupt;
+use crate::promp_enhancer::{PromptEnhancer, clean_enhanced_output};
+use crate::prompt_limits::truncate_prompt;
+use crate::sessions::Session;
+use crate::timeout;
+use crate::ui::OutputConfig;
+
+// ── Constants ────────────────────────────────────────────────────────────────
+
+/// Appended to the enhancer's system prompt for refinement turns.
+const REFINE_SYSTEM_NOTE: &str = "After the first prompt, the user sends revision instructions. Apply each instruction to the latest prompt, keep everything it doesn't change, and output the full revised prompt.";
+
+/// Wraps each revision instruction so the length limit and output format
+/// are restated on every turn, not just in the system prompt.
+fn revision_message(instruction: &str) -> String {
+    format!(
+        "Revise the prompt: {instruction}\n\
+         The result MUST be under 50 words. Output ONLY the revised prompt, no explanation, no quotes."
+    )
+}
+
+// ── PromptSession ────────────────────────────────────────────────────────────
+
+/// One version of the prompt in a [`PromptSession`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Revision {
+    /// The instruction that produced this version; `None` for the first
+    /// enhancement of the seed (or the seed itself after a reset).
+    pub instruction: Option<String>,
+    /// The prompt, already truncated to CLIP's window.
+    pub prompt: String,
+}
+
+/// Iterative refinement of an enhanced prompt.
+///
+/// Keeps the enhancer loaded and replays the seed and every revision as a
+/// conversation, so instructions like "more dramatic lighting" apply to the
+/// latest version rather than starting over.
+pub struct PromptSession {
+    enhancer: PromptEnhancer,
+    seed: String,
+    revisions: Vec<Revision>,
+}
+
+impl PromptSession {
+    /// Enhance `seed` and start a session from the result.
+    pub async fn start(enhancer: PromptEnhancer, seed: impl Into<String>) -> Result<Self> {
+        let seed = seed.into();
+        let prompt = enhancer.enhance(&seed).await?.into();
+        Ok(Self {
+            enhancer,
+            seed,
+            revisions: vec![Revision {
+                instruction: None,
+                prompt,
+            }],
+        })
+    }
+
+    /// Pick up a session saved by [`to_saved`](Self::to_saved), with its
+    /// system prompt and sampler settings, without asking the model again.
+    pub fn resume(enhancer: PromptEnhancer, saved: SavedChat) -> Result<Self> {
+        if saved.kind != SavedKind::Prompt {
+            anyhow::bail!(
+                "This is a saved {} session, not a prompt refinement",
+                saved.kind
+            );
+        }
+        let mut turns = saved.history.into_iter();
+        let (Some(seed), Some(first)) = (turns.next(), turns.next()) else {
+            anyhow::bail!("The saved prompt session is empty");
+        };
+        let mut revisions = vec![Revision {
+            instruction: None,
+            prompt: first.content,
+        }];
+        while let Some(instruction) = turns.next() {
+            let prompt = turns
+                .next()
+                .context("The saved prompt session ends with an unanswered instruction")?;
+            revisions.push(Revision {
+                instruction: Some(instruction.content),
+                prompt: prompt.content,
+            });
+        }
+        let sampler = enhancer
+            .sampler()
+            .clone()
+            .with_temperature(saved.temperature)
+            .with_top_p(saved.top_p)
+            .with_max_len(saved.max_len);
+        Ok(Self {
+            enhancer: enhancer
+                .with_sampler(sampler)
+                .with_system_prompt(saved.system_prompt),
+            seed: seed.content,
+            revisions,
+        })
+    }
+
+    /// The session in the `/save` format: the seed, then each version of
+    /// the prompt after the instruction that produced it.
+    pub fn to_saved(&self) -> SavedChat {
+        let mut history = vec![ChatTurn::now(TextMessageRole::User, &self.seed)];
+        for revision in &self.revisions {
+            if let Some(instruction) = &revision.instruction {
+                history.push(ChatTurn::now(TextMessageRole::User, instruction));
+            }
+            history.push(ChatTurn::now(TextMessageRole::Assistant, &revision.prompt));
+        }
+        let sampler = self.enhancer.sampler();
+        SavedChat {
+            version: SavedChat::VERSION,
+            kind: SavedKind::Prompt,
+            system_prompt: self.enhancer.system_prompt().to_string(),
+            temperature: sampler.temperature,
+            top_p: sampler.top_p,
+            max_len: sampler.max_len,
+            history,
+        }
+    }
+
+    /// The latest version of the prompt.
+    pub fn current(&self) -> &str {
+        &self.revisions[self.revisions.len() - 1].prompt
+    }
+
+    /// The seed the session started from.
+    pub fn seed(&self) -> &str {
+        &self.seed
+    }
+
+    /// Every version so far, oldest first.
+    pub fn revisions(&self) -> &[Revision] {
+        &self.revisions
+    }
+
+    /// Apply `instruction` to the current prompt and return the new version.
+    ///
+    /// An empty reply is an error and leaves the session unchanged.
+    pub async fn refine(&mut self, instruction: &str) -> Result<&str> {
+        let system = format!("{}\n{REFINE_SYSTEM_NOTE}", self.enhancer.system_prompt());
+        let mut request = self
+            .enhancer
+            .sampler()
+            .apply(RequestBuilder::new())
+            .add_message(TextMessageRole::System, system)
+            .add_message(TextMessageRole::User, &self.seed);
+        for revision in &self.revisions {
+            if let Some(instruction) = &revision.instruction {
+                request = request.add_message(TextMessageRole::User, revision_message(instruction));
+            }
+            request = request.add_message(TextMessageRole::Assistant, &revision.prompt);
+        }
+        request = request.add_message(TextMessageRole::User, revision_message(instruction));
+
+        let response = timeout::within(
+            self.enhancer.timeout(),
+            "Prompt revision",
+            self.enhancer.model().send_chat_request(request),
+        )
+        .await?;
+        let prompt = response.choices[0]
+            .message
+            .content
+            .as_ref()
+            .map(|c| clean_enhanced_output(c))
+            .unwrap_or_default();
+        if prompt.is_empty() {
+            anyhow::bail!("The model returned an empty revision");
+        }
+
+        self.revisions.push(Revision {
+            instruction: Some(instruction.to_string()),
+            prompt: truncate_prompt(&prompt),
+        });
+        Ok(self.current())
+    }
+
+    /// Drop every revision and go back to the original seed.
+    pub fn reset(&mut self) -> &str {
+        self.revisions = vec![Revision {
+            instruction: None,
+            prompt: truncate_prompt(&self.seed),
+        }];
+        self.current()
+    }
+
+    /// End the session, returning the final prompt and the enhancer.
+    pub fn finish(self) -> (String, PromptEnhancer) {
+        let prompt = self.current().to_string();
+        (prompt, self.enhancer)
+    }
+}
+
+// ── Interactive loop ─────────────────────────────────────────────────────────
+
+/// Refine `seed` interactively with `enhancer`, for `prompt --interactive`.
+///
+/// Commands:
+/// - `/use`            : accept the current prompt, print it and exit
+/// - `/image [path]`   : accept it and generate an image from it
+/// - `/reset`          : go back to the original seed
+/// - `/history`        : list every revision
+/// - `/help`           : show command help
+///
+/// Anything else is a revision instruction.  EOF acts like `/use`.
+///
+/// With `saved`, the session picks up where that one left off (`seed` is
+/// then ignored) and is written back after every revision.
+pub async fn run_interactive(
+    enhancer: PromptEnhancer,
+    seed: String,
+    saved: Option<Session>,
+) -> Result<()> {
+    let resumed = match &saved {
+        Some(file) => file.load(SavedKind::Prompt)?,
+        None => None,
+    };
+    let mut session = match resumed {
+        Some(state) => {
+            let session = PromptSession::resume(enhancer, state)?;
+            let name = saved.as_ref().map_or("", |file| file.name());
+            println!(
+                "\nResumed session `{name}` ({} revisions).",
+                session.revisions().len() - 1
+            );
+            println!("\nSeed prompt:\n  \"{}\"", session.seed());
+            println!("\nCurrent prompt:\n  \"{}\"", session.current());
+            session
+        }
+        None => {
+            println!("\nSeed prompt:\n  \"{seed}\"");
+            let session = PromptSession::start(enhancer, seed).await?;
+            println!("\nEnhanced prompt:\n  \"{}\"", session.current());
+            session
+        }
+    };
+    let persist = |session: &PromptSession| {
+        if let Some(file) = &saved
+            && let Err(err) = file.save(&session.to_saved())
+        {
+            tracing::warn!("{err:#}");
+        }
+    };
+    persist(&session);
+    println!("\nType a revision (e.g. \"more dramatic lighting, remove the castle\").");
+    println!("Commands: /use, /image [path], /reset, /history, /help");
+
+    let stdin = io::stdin();
+    let image_out = loop {
+        print!("\nrevise> ");
+        io::stdout().flush()?;
+        let mut line = String::new();
+        if stdin.read_line(&mut line)? == 0 {
+            println!();
+            break None;
+        }
+        let input = line.trim();
+
+        match input {
+            "" => continue,
+            "/use" => break None,
+            "/help" => {
+                println!("Commands:");
+                println!("  /use           Accept the current prompt and print it");
+                println!("  /image [path]  Accept it and generate an image");
+                println!("  /reset         Go back to the original seed");
+                println!("  /history       List every revision");
+                println!("  /help          Show this help");
+                continue;
+            }
+            "/reset" => {
+                println!("Back to the seed:\n  \"{}\"", session.reset());
+                persist(&session);
+                continue;
+            }
+            "/history" => {
+                for (i, revision) in session.revisions().iter().enumerate() {
+                    match &revision.instruction {
+                        Some(instruction) => println!("[{i}] {instruction}"),
+                        None => println!("[{i}] (start)"),
+                    }
+                    println!("    \"{}\"", revision.prompt);
+                }
+                continue;
+            }
+            _ => {}
+        }
+
+        if let Some(path) = command_arg(input, "/image") {
+            let out = (!path.is_empty()).then(|| PathBuf::from(path));
+            let options = image_options(out.clone());
+            // Check before the enhancer is dropped, so a bad path doesn't
+            // end the session.
+            if let Err(e) = options.check_overwrite() {
+                println!("error: {e:#}");
+                continue;
+            }
+            break Some(options);
+        }
+        if input.starts_with('/') {
+            println!("Unknown command {input}; type /help for the list.");
+            continue;
+        }
+
+        match session.refine(input).await {
+            Ok(prompt) => {
+                println!("\nRevised prompt:\n  \"{prompt}\"");
+                persist(&session);
+            }
+            Err(e) => println!("error: {e:#}"),
+        }
+    };
+
+    let (prompt, enhancer) = session.finish();
+    println!("\nFinal prompt:\n  \"{prompt}\"");
+
+    let Some(options) = image_out else {
+        return Ok(());
+    };
+    // Free the enhancer before FLUX loads.
+    drop(enhancer);
+    println!();
+    image_generation::run(
+        Some(prompt),
+        None,
+        EnhancerOptions::default(),
+        options,
+        DiffusionConfig::resolve(None, Default::default(), None),
+        Interrupt::install(true).token(),
+        OutputConfig::default(),
+    )
+    .await
+}
+
+/// Default single-image settings for `/image`.
+fn image_options(out: Option<PathBuf>) -> ImageOptions {
+    ImageOptions {
+        width: None,
+        height: None,
+        num_images: 1,
+        out,
+        force: false,
+        negative_prompt: None,
+        guidance: None,
+        metadata: MetadataOutput::default(),
+        grid: None,
+        sizes: Vec::new(),
+    }
+}
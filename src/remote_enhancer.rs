@@ -0,0 +1,463 @@
+use anyhow::Result;
+use std::fmt;
+use std::time::Duration;
+
+use crate::promp_enhancer::PromptEnhance;
+use crate::prompt_limits::PromptBudget;
+use crate::sampler::SamplerConfig;
+
+#[cfg(feature = "remote-enhancer")]
+use anyhow::Context;
+#[cfg(feature = "remote-enhancer")]
+use async_trait::async_trait;
+#[cfg(feature = "remote-enhancer")]
+use serde::Deserialize;
+
+#[cfg(feature = "remote-enhancer")]
+use crate::promp_enhancer::{self, MAX_LYRICS_WORDS, clean_enhanced_output};
+#[cfg(feature = "remote-enhancer")]
+use crate::prompt_limits::truncate_to_words;
+#[cfg(feature = "remote-enhancer")]
+use crate::timeout::TimedOut;
+
+// ── Settings ─────────────────────────────────────────────────────────────────
+
+/// Where `--enhancer remote` sends requests when neither `--remote-url` nor
+/// [`URL_ENV`] is set.
+pub const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
+
+/// Environment fallback for `--remote-url`.
+pub const URL_ENV: &str = "MISTRALRS_EXAMPLE_REMOTE_URL";
+
+/// Environment fallback for `--remote-model`.
+pub const MODEL_ENV: &str = "MISTRALRS_EXAMPLE_REMOTE_MODEL";
+
+/// Variables read for the API key, first one set wins.  There is no flag
+/// for it, so the key never shows up in the process list or shell history.
+pub const API_KEY_ENVS: &[&str] = &["MISTRALRS_EXAMPLE_REMOTE_API_KEY", "OPENAI_API_KEY"];
+
+/// Time limit per request unless `--remote-timeout` says otherwise.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Retries after a 429 or 5xx reply.
+#[cfg(feature = "remote-enhancer")]
+const MAX_RETRIES: u32 = 3;
+
+/// Wait before the first retry when the server sends no `Retry-After`;
+/// doubled for each further attempt.
+#[cfg(feature = "remote-enhancer")]
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+
+/// Longest wait between retries, whatever `Retry-After` asks for.
+#[cfg(feature = "remote-enhancer")]
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// Characters of an error body kept in the message when it isn't the usual
+/// `{"error": {"message": …}}` JSON.
+#[cfg(feature = "remote-enhancer")]
+const MAX_ERROR_BODY_CHARS: usize = 300;
+
+/// `--remote-*` flags, shared by the commands that take `--enhancer`.
+#[derive(Clone, Debug, Default, clap::Args)]
+pub struct RemoteArgs {
+    /// Base URL of the OpenAI-compatible API used by `--enhancer remote`,
+    /// up to and including the version (e.g. http://localhost:11434/v1);
+    /// `/chat/completions` is appended.  Falls back to
+    /// MISTRALRS_EXAMPLE_REMOTE_URL, then the OpenAI API.  The key is read
+    /// from MISTRALRS_EXAMPLE_REMOTE_API_KEY or OPENAI_API_KEY.
+    #[arg(long, value_name = "URL")]
+    pub remote_url: Option<String>,
+
+    /// Model name sent to the API with `--enhancer remote`, e.g.
+    /// gpt-4o-mini.  Falls back to MISTRALRS_EXAMPLE_REMOTE_MODEL.
+    #[arg(long, value_name = "NAME")]
+    pub remote_model: Option<String>,
+
+    /// Seconds to wait for each request to the API (default 60).
+    #[arg(long, value_name = "SECS")]
+    pub remote_timeout: Option<u64>,
+}
+
+impl RemoteArgs {
+    /// Whether any `--remote-*` flag was passed.
+    pub fn is_set(&self) -> bool {
+        self.remote_url.is_some() || self.remote_model.is_some() || self.remote_timeout.is_some()
+    }
+
+    /// The settings to connect with: the flags, then the environment.
+    pub fn resolve(&self) -> Result<RemoteConfig> {
+        self.resolve_with(|name| std::env::var(name).ok())
+    }
+
+    /// [`resolve`](Self::resolve) with another source for the environment.
+    pub fn resolve_with(&self, env: impl Fn(&str) -> Option<String>) -> Result<RemoteConfig> {
+        let env = |name: &str| env(name).filter(|value| !value.trim().is_empty());
+        let base_url = self
+            .remote_url
+            .clone()
+            .or_else(|| env(URL_ENV))
+            .unwrap_or_else(|| DEFAULT_BASE_URL.to_string());
+        let base_url = base_url.trim().trim_end_matches('/').to_string();
+        if !(base_url.starts_with("http://") || base_url.starts_with("https://")) {
+            anyhow::bail!("--remote-url must start with http:// or https://, got {base_url}");
+        }
+        let Some(model) = self.remote_model.clone().or_else(|| env(MODEL_ENV)) else {
+            anyhow::bail!(
+                "--enhancer remote needs a model name: pass --remote-model or set {MODEL_ENV}"
+            );
+        };
+        let timeout = match self.remote_timeout {
+            Some(0) => anyhow::bail!("--remote-timeout must be at least 1 second"),
+            Some(secs) => Duration::from_secs(secs),
+            None => DEFAULT_TIMEOUT,
+        };
+        Ok(RemoteConfig {
+            base_url,
+            model,
+            api_key: API_KEY_ENVS.iter().find_map(|name| env(name)),
+            timeout,
+        })
+    }
+}
+
+/// Where and how [`RemoteEnhancer`] connects.
+#[derive(Clone, PartialEq, Eq)]
+pub struct RemoteConfig {
+    /// API base without a trailing slash, e.g. `https://api.openai.com/v1`.
+    pub base_url: String,
+    pub model: String,
+    /// Sent as a bearer token; local servers usually need none.
+    pub api_key: Option<String>,
+    /// Time limit per request.
+    pub timeout: Duration,
+}
+
+impl RemoteConfig {
+    /// The chat-completions URL requests are posted to.
+    #[cfg(feature = "remote-enhancer")]
+    pub fn endpoint(&self) -> String {
+        format!("{}/chat/completions", self.base_url)
+    }
+
+    /// `gpt-4o-mini at https://api.openai.com/v1`: how the backend is named
+    /// in status output and the image metadata.
+    pub fn label(&self) -> String {
+        format!("{} at {}", self.model, self.base_url)
+    }
+}
+
+/// Keeps the API key out of `{:?}` output.
+impl fmt::Debug for RemoteConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RemoteConfig")
+            .field("base_url", &self.base_url)
+            .field("model", &self.model)
+            .field("api_key", &self.api_key.as_ref().map(|_| "<redacted>"))
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+// ── Requests and replies ─────────────────────────────────────────────────────
+
+/// The chat-completions body for one `system` + `user` exchange.
+/// `top_k` has no counterpart in the API and is left out.
+#[cfg(feature = "remote-enhancer")]
+pub fn request_body(
+    model: &str,
+    system: &str,
+    user: &str,
+    sampler: &SamplerConfig,
+) -> serde_json::Value {
+    let mut body = serde_json::json!({
+        "model": model,
+        "messages": [
+            { "role": "system", "content": system },
+            { "role": "user", "content": user },
+        ],
+        "temperature": sampler.temperature,
+        "top_p": sampler.top_p,
+    });
+    if sampler.max_len > 0 {
+        body["max_tokens"] = sampler.max_len.into();
+    }
+    if let Some(seed) = sampler.seed {
+        body["seed"] = seed.into();
+    }
+    if let Some(penalty) = sampler.frequency_penalty {
+        body["frequency_penalty"] = penalty.into();
+    }
+    if let Some(penalty) = sampler.presence_penalty {
+        body["presence_penalty"] = penalty.into();
+    }
+    if !sampler.stop.is_empty() {
+        body["stop"] = sampler.stop.clone().into();
+    }
+    body
+}
+
+/// Whether a reply with `status` is worth sending the request again:
+/// rate limits and server-side failures.
+#[cfg(feature = "remote-enhancer")]
+pub fn is_retryable(status: u16) -> bool {
+    status == 429 || (500..=599).contains(&status)
+}
+
+/// The wait a `Retry-After` header asks for.  Only the delay-seconds form
+/// is understood; an HTTP date falls back to the usual backoff.
+#[cfg(feature = "remote-enhancer")]
+pub fn retry_after(header: Option<&str>) -> Option<Duration> {
+    header?.trim().parse::<u64>().ok().map(Duration::from_secs)
+}
+
+/// The error for a non-2xx reply: the API's own message when the body is
+/// the usual `{"error": {"message": …}}` JSON (OpenAI and Anthropic both
+/// send it), otherwise the start of the body, plus a hint for the common
+/// causes.
+#[cfg(feature = "remote-enhancer")]
+pub fn status_error(endpoint: &str, status: u16, body: &str) -> String {
+    let detail = serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|json| {
+            let error = &json["error"];
+            error["message"]
+                .as_str()
+                .or_else(|| error.as_str())
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| {
+            let body = body.trim();
+            match body.char_indices().nth(MAX_ERROR_BODY_CHARS) {
+                Some((end, _)) => format!("{}…", &body[..end]),
+                None => body.to_string(),
+            }
+        });
+    let hint = match status {
+        401 | 403 => format!("\nCheck the API key in {}.", API_KEY_ENVS.join(" or ")),
+        404 => "\nCheck --remote-url (it usually ends in /v1) and --remote-model.".to_string(),
+        429 => format!("\nStill rate limited after {MAX_RETRIES} retries; try again later."),
+        _ => String::new(),
+    };
+    let detail = if detail.is_empty() {
+        String::new()
+    } else {
+        format!(": {detail}")
+    };
+    format!("{endpoint} answered HTTP {status}{detail}{hint}")
+}
+
+#[cfg(feature = "remote-enhancer")]
+#[derive(Deserialize)]
+struct ChatResponse {
+    choices: Vec<ChatChoice>,
+}
+
+#[cfg(feature = "remote-enhancer")]
+#[derive(Deserialize)]
+struct ChatChoice {
+    message: ChatMessage,
+}
+
+#[cfg(feature = "remote-enhancer")]
+#[derive(Deserialize)]
+struct ChatMessage {
+    content: Option<String>,
+}
+
+// ── RemoteEnhancer ───────────────────────────────────────────────────────────
+
+/// [`PromptEnhance`] through an OpenAI-compatible chat-completions API, for
+/// machines that can't run a local model; `--enhancer remote` selects it.
+///
+/// Sends the same system prompts as [`PromptEnhancer`] and cleans and cuts
+/// the reply the same way, so the prompt reaching the diffusion model looks
+/// alike whichever side wrote it.
+///
+/// [`PromptEnhancer`]: crate::promp_enhancer::PromptEnhancer
+#[cfg(feature = "remote-enhancer")]
+#[derive(Clone)]
+pub struct RemoteEnhancer {
+    client: reqwest::Client,
+    config: RemoteConfig,
+    system_prompt: String,
+    sampler: SamplerConfig,
+    budget: PromptBudget,
+}
+
+#[cfg(feature = "remote-enhancer")]
+impl RemoteEnhancer {
+    pub fn new(config: RemoteConfig) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .build()
+            .context("Failed to set up the HTTP client")?;
+        Ok(Self {
+            client,
+            config,
+            system_prompt: promp_enhancer::system_prompt(PromptBudget::CLIP),
+            sampler: promp_enhancer::DEFAULT_SAMPLER,
+            budget: PromptBudget::CLIP,
+        })
+    }
+
+    /// Override the default sampling settings.
+    pub fn with_sampler(mut self, sampler: SamplerConfig) -> Self {
+        self.sampler = sampler;
+        self
+    }
+
+    /// Write prompts for `budget`'s window; like
+    /// [`PromptEnhancer::with_prompt_budget`].
+    ///
+    /// [`PromptEnhancer::with_prompt_budget`]: crate::promp_enhancer::PromptEnhancer::with_prompt_budget
+    pub fn with_prompt_budget(mut self, budget: PromptBudget) -> Self {
+        if self.system_prompt == promp_enhancer::system_prompt(self.budget) {
+            self.system_prompt = promp_enhancer::system_prompt(budget);
+        }
+        if self.sampler.max_len == self.budget.max_new_tokens() {
+            self.sampler.max_len = budget.max_new_tokens();
+        }
+        self.budget = budget;
+        self
+    }
+
+    /// Send one exchange and return the reply text.
+    ///
+    /// A 429 or 5xx reply is retried up to [`MAX_RETRIES`] times, waiting
+    /// as long as `Retry-After` asks (capped) or with exponential backoff.
+    /// A request that runs past the time limit fails with [`TimedOut`]
+    /// straight away, since it already waited that long.
+    async fn complete(&self, system: &str, user: &str, sampler: &SamplerConfig) -> Result<String> {
+        let endpoint = self.config.endpoint();
+        let body = request_body(&self.config.model, system, user, sampler);
+        let mut delay = RETRY_BASE_DELAY;
+        let mut retry = 0;
+        loop {
+            let mut request = self.client.post(&endpoint).json(&body);
+            if let Some(key) = &self.config.api_key {
+                request = request.bearer_auth(key);
+            }
+            let response = match request.send().await {
+                Ok(response) => response,
+                Err(err) if err.is_timeout() => {
+                    return Err(TimedOut {
+                        stage: "Remote prompt enhancement".to_string(),
+                        waited: self.config.timeout,
+                    }
+                    .into());
+                }
+                Err(err) => {
+                    return Err(anyhow::Error::new(err))
+                        .with_context(|| format!("Failed to reach {endpoint}"));
+                }
+            };
+
+            let status = response.status().as_u16();
+            if response.status().is_success() {
+                let reply: ChatResponse = response.json().await.with_context(|| {
+                    format!("{endpoint} sent a reply that isn't chat completions JSON")
+                })?;
+                return reply
+                    .choices
+                    .into_iter()
+                    .next()
+                    .map(|choice| choice.message.content.unwrap_or_default())
+                    .with_context(|| format!("{endpoint} replied with no choices"));
+            }
+            if is_retryable(status) && retry < MAX_RETRIES {
+                let header = response
+                    .headers()
+                    .get(reqwest::header::RETRY_AFTER)
+                    .and_then(|value| value.to_str().ok());
+                let wait = retry_after(header).unwrap_or(delay).min(RETRY_MAX_DELAY);
+                retry += 1;
+                tracing::warn!(
+                    "{endpoint} answered HTTP {status}; retrying in {}s (retry {retry}/{MAX_RETRIES})",
+                    wait.as_secs()
+                );
+                tokio::time::sleep(wait).await;
+                delay = (delay * 2).min(RETRY_MAX_DELAY);
+                continue;
+            }
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!(status_error(&endpoint, status, &body));
+        }
+    }
+
+    async fn enhance_with_sampler(&self, seed: &str, sampler: &SamplerConfig) -> Result<String> {
+        let reply = self.complete(&self.system_prompt, seed, sampler).await?;
+        let enhanced = clean_enhanced_output(sampler.trim_stop(&reply));
+        Ok(promp_enhancer::enhanced_or_seed(
+            seed,
+            &enhanced,
+            self.budget,
+        ))
+    }
+}
+
+#[cfg(feature = "remote-enhancer")]
+#[async_trait]
+impl PromptEnhance for RemoteEnhancer {
+    async fn enhance(&self, seed: &str) -> Result<String> {
+        self.enhance_with_sampler(seed, &self.sampler).await
+    }
+
+    /// Candidates are sampled like [`PromptEnhancer::enhance_n`]'s, with the
+    /// temperature stepped up for each; duplicates are dropped.
+    ///
+    /// [`PromptEnhancer::enhance_n`]: crate::promp_enhancer::PromptEnhancer::enhance_n
+    async fn enhance_n(&self, seed: &str, n: usize) -> Result<Vec<String>> {
+        let mut candidates: Vec<String> = Vec::with_capacity(n);
+        for i in 0..n {
+            let sampler = promp_enhancer::candidate_sampler(&self.sampler, i);
+            let candidate = self.enhance_with_sampler(seed, &sampler).await?;
+            if !candidates.contains(&candidate) {
+                candidates.push(candidate);
+            }
+        }
+        Ok(candidates)
+    }
+
+    async fn prompt_from_lyrics(&self, lyrics: &str, style: Option<&str>) -> Result<String> {
+        let excerpt = truncate_to_words(lyrics, MAX_LYRICS_WORDS);
+        let message = promp_enhancer::song_text_message("Lyrics", &excerpt, style);
+        let system = promp_enhancer::lyrics_system_prompt(self.budget);
+        let reply = self.complete(&system, &message, &self.sampler).await?;
+        let prompt = clean_enhanced_output(&reply);
+        if prompt.is_empty() {
+            anyhow::bail!("The remote model returned an empty prompt for the lyrics");
+        }
+        Ok(self.budget.truncate(&prompt))
+    }
+}
+
+// ── Connecting ───────────────────────────────────────────────────────────────
+
+/// The `--enhancer remote` backend for `config`, sampling with `sampler`
+/// and writing prompts for `budget`.
+#[cfg(feature = "remote-enhancer")]
+pub fn connect(
+    config: RemoteConfig,
+    sampler: SamplerConfig,
+    budget: PromptBudget,
+) -> Result<Box<dyn PromptEnhance>> {
+    Ok(Box::new(
+        RemoteEnhancer::new(config)?
+            .with_prompt_budget(budget)
+            .with_sampler(sampler),
+    ))
+}
+
+/// Without the `remote-enhancer` feature there is no HTTP client to connect
+/// with.
+#[cfg(not(feature = "remote-enhancer"))]
+pub fn connect(
+    _config: RemoteConfig,
+    _sampler: SamplerConfig,
+    _budget: PromptBudget,
+) -> Result<Box<dyn PromptEnhance>> {
+    anyhow::bail!(
+        "--enhancer remote needs a build with the remote-enhancer feature: \
+         cargo build --release --features remote-enhancer"
+    )
+}
@@ -0,0 +1,58 @@
+use std::future::Future;
+use std::sync::Arc;
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+// ── RequestQueue ─────────────────────────────────────────────────────────────
+
+/// Requests a model wrapper sends at once unless told otherwise.
+pub const DEFAULT_MAX_CONCURRENCY: usize = 1;
+
+/// Bounds how many requests a model wrapper has in flight.
+///
+/// Callers past the limit wait in arrival order (tokio's semaphore is
+/// fair), so concurrent `enhance` or `transcribe` calls on one instance
+/// queue up instead of interleaving inside the model's scheduler.  Clones
+/// share the limit.
+#[derive(Clone, Debug)]
+pub struct RequestQueue {
+    permits: Arc<Semaphore>,
+    limit: usize,
+}
+
+impl Default for RequestQueue {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_CONCURRENCY)
+    }
+}
+
+impl RequestQueue {
+    /// A queue letting `limit` requests through at once (at least one).
+    pub fn new(limit: usize) -> Self {
+        let limit = limit.max(1);
+        Self {
+            permits: Arc::new(Semaphore::new(limit)),
+            limit,
+        }
+    }
+
+    /// Most requests in flight at once.
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Wait for a free slot, held until the permit is dropped.
+    pub async fn acquire(&self) -> SemaphorePermit<'_> {
+        self.permits
+            .acquire()
+            .await
+            .expect("the request semaphore is never closed")
+    }
+
+    /// Run `request` once a slot is free.  Time spent waiting isn't part
+    /// of `request`, so timeouts and durations measured inside it only
+    /// cover the request itself.
+    pub async fn run<F: Future>(&self, request: F) -> F::Output {
+        let _permit = self.acquire().await;
+        request.await
+    }
+}
@@ -0,0 +1,635 @@
+use anyhow::{Context, Result};
+use mistralrs::AudioInput;
+use serde::Serialize;
+use std::fmt;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+use crate::audio_prep::PrepOptions;
+use crate::audio_transcription::{
+    self, AudioTranscriber, DEFAULT_SAMPLER, Segment, TranscriptionModel,
+};
+use crate::interrupt;
+use crate::load_options::LoadOptions;
+use crate::memory;
+use crate::sampler::DecodingArgs;
+use crate::transcript_output;
+use crate::ui::{self, OutputConfig, detail, fmt_duration, status};
+
+/// `m:ss.s`, the way segment times are shown in status output.
+fn fmt_clock(secs: f64) -> String {
+    let tenths = (secs.max(0.0) * 10.0).round() as u64;
+    format!("{}:{:02}.{}", tenths / 600, (tenths / 10) % 60, tenths % 10)
+}
+
+// ── Selections ───────────────────────────────────────────────────────────────
+
+/// A stretch of the audio, in seconds from its start.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct TimeRange {
+    pub start_secs: f64,
+    pub end_secs: f64,
+}
+
+impl TimeRange {
+    fn of(seg: &Segment) -> Self {
+        Self {
+            start_secs: seg.start_secs,
+            end_secs: seg.end_secs,
+        }
+    }
+
+    /// Whether the two share any time.  Ranges that only touch, one ending
+    /// where the other starts, don't.
+    pub fn overlaps(&self, other: &TimeRange) -> bool {
+        self.start_secs < other.end_secs && other.start_secs < self.end_secs
+    }
+
+    /// The smallest range covering both.
+    pub fn union(&self, other: &TimeRange) -> TimeRange {
+        TimeRange {
+            start_secs: self.start_secs.min(other.start_secs),
+            end_secs: self.end_secs.max(other.end_secs),
+        }
+    }
+}
+
+impl fmt::Display for TimeRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}–{}",
+            fmt_clock(self.start_secs),
+            fmt_clock(self.end_secs)
+        )
+    }
+}
+
+/// Parse a time: seconds (`75`, `75.5`), `m:ss` (`1:15`, `1:15.5`),
+/// `h:mm:ss`, or an SRT / VTT timestamp (`00:01:15,500`).
+pub fn parse_time(text: &str) -> Result<f64> {
+    let text = text.trim();
+    let parts: Vec<&str> = text.split(':').collect();
+    if text.is_empty() || parts.len() > 3 {
+        anyhow::bail!("`{text}` is not a time; expected e.g. 75, 1:15 or 0:01:15.5");
+    }
+    let (units, last) = parts.split_at(parts.len() - 1);
+    let secs: f64 = last[0]
+        .replace(',', ".")
+        .parse()
+        .ok()
+        .filter(|secs: &f64| secs.is_finite() && *secs >= 0.0)
+        .with_context(|| format!("`{text}` is not a time; expected e.g. 75, 1:15 or 0:01:15.5"))?;
+    if !units.is_empty() && secs >= 60.0 {
+        anyhow::bail!("`{text}`: seconds must be below 60 after a minutes field");
+    }
+    let mut total = 0u64;
+    for (i, unit) in units.iter().enumerate() {
+        let value: u64 = unit
+            .parse()
+            .with_context(|| format!("`{text}` is not a time; `{unit}` isn't a whole number"))?;
+        if i > 0 && value >= 60 {
+            anyhow::bail!("`{text}`: minutes must be below 60 after an hours field");
+        }
+        total = total * 60 + value;
+    }
+    Ok(total as f64 * 60.0 + secs)
+}
+
+/// Parse `START-END` (e.g. `1:02-1:15`), each side as for [`parse_time`].
+pub fn parse_range(text: &str) -> Result<TimeRange> {
+    let (start, end) = text.split_once('-').with_context(|| {
+        format!("`{text}` is not a time range; expected START-END, e.g. 1:02-1:15")
+    })?;
+    let range = TimeRange {
+        start_secs: parse_time(start)?,
+        end_secs: parse_time(end)?,
+    };
+    if range.end_secs <= range.start_secs {
+        anyhow::bail!("`{text}`: the end of the range must come after its start");
+    }
+    Ok(range)
+}
+
+/// clap value parser for `--range`.
+pub fn parse_range_arg(text: &str) -> Result<TimeRange, String> {
+    parse_range(text).map_err(|err| format!("{err:#}"))
+}
+
+/// Parse a list of 1-based segment numbers, e.g. `3,5,7-9`.  Returns them
+/// sorted without repeats.
+pub fn parse_indices(text: &str) -> Result<Vec<usize>> {
+    let mut indices = Vec::new();
+    for part in text.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let number = |n: &str| -> Result<usize> {
+            match n.trim().parse::<usize>() {
+                Ok(0) => anyhow::bail!("segment numbers start at 1, got 0"),
+                Ok(n) => Ok(n),
+                Err(_) => anyhow::bail!("`{part}` is not a segment number or N-M span"),
+            }
+        };
+        match part.split_once('-') {
+            Some((first, last)) => {
+                let (first, last) = (number(first)?, number(last)?);
+                if last < first {
+                    anyhow::bail!("`{part}`: the span runs backwards");
+                }
+                indices.extend(first..=last);
+            }
+            None => indices.push(number(part)?),
+        }
+    }
+    if indices.is_empty() {
+        anyhow::bail!("No segment numbers in `{text}`");
+    }
+    indices.sort_unstable();
+    indices.dedup();
+    Ok(indices)
+}
+
+/// The value of `--segments`: what [`parse_indices`] returns, wrapped so
+/// clap takes it as one value rather than a list.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SegmentList(pub Vec<usize>);
+
+/// clap value parser for `--segments`.
+pub fn parse_indices_arg(text: &str) -> Result<SegmentList, String> {
+    parse_indices(text)
+        .map(SegmentList)
+        .map_err(|err| format!("{err:#}"))
+}
+
+// ── Planning ─────────────────────────────────────────────────────────────────
+
+/// One stretch of audio to transcribe again, and the segments its text
+/// replaces.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fix {
+    /// The audio sent to the model.
+    pub span: TimeRange,
+    /// Positions (0-based) of the segments replaced.  Empty for a range in
+    /// a gap, whose text is inserted at `replaces.start`.
+    pub replaces: Range<usize>,
+}
+
+impl Fix {
+    fn merge(&mut self, other: &Fix) {
+        self.span = self.span.union(&other.span);
+        if other.replaces.is_empty() {
+            return;
+        }
+        self.replaces = if self.replaces.is_empty() {
+            other.replaces.clone()
+        } else {
+            self.replaces.start.min(other.replaces.start)..self.replaces.end.max(other.replaces.end)
+        };
+    }
+
+    /// Whether the two would replace any of the same segments or hear any
+    /// of the same audio.
+    fn collides(&self, other: &Fix) -> bool {
+        let shared = self.replaces.start.max(other.replaces.start)
+            < self.replaces.end.min(other.replaces.end);
+        shared || self.span.overlaps(&other.span)
+    }
+}
+
+/// Turn the selection into fixes over `segments`, which must be in time
+/// order.
+///
+/// A segment number re-transcribes exactly that segment.  A time range
+/// re-transcribes the range widened to every segment it overlaps, and
+/// replaces them; a range in a gap between segments adds a new one.
+/// Fixes that overlap are merged, so no audio is heard twice and no
+/// segment is replaced twice.  Segments that only touch stay apart.
+pub fn plan_fixes(
+    segments: &[Segment],
+    indices: &[usize],
+    ranges: &[TimeRange],
+) -> Result<Vec<Fix>> {
+    if segments
+        .windows(2)
+        .any(|pair| pair[1].start_secs < pair[0].start_secs)
+    {
+        anyhow::bail!("The transcript's segments are not in time order");
+    }
+    let mut fixes = Vec::with_capacity(indices.len() + ranges.len());
+    for &index in indices {
+        let Some(seg) = index.checked_sub(1).and_then(|i| segments.get(i)) else {
+            anyhow::bail!(
+                "There is no segment {index}; the transcript has {}",
+                segments.len()
+            );
+        };
+        fixes.push(Fix {
+            span: TimeRange::of(seg),
+            replaces: index - 1..index,
+        });
+    }
+    for range in ranges {
+        let hit: Vec<usize> = (0..segments.len())
+            .filter(|&i| TimeRange::of(&segments[i]).overlaps(range))
+            .collect();
+        fixes.push(match (hit.first(), hit.last()) {
+            (Some(&first), Some(&last)) => Fix {
+                span: segments[first..=last]
+                    .iter()
+                    .fold(*range, |span, seg| span.union(&TimeRange::of(seg))),
+                replaces: first..last + 1,
+            },
+            _ => {
+                let at = segments
+                    .iter()
+                    .take_while(|seg| seg.start_secs < range.start_secs)
+                    .count();
+                Fix {
+                    span: *range,
+                    replaces: at..at,
+                }
+            }
+        });
+    }
+
+    fixes.sort_by(|a, b| {
+        a.span
+            .start_secs
+            .total_cmp(&b.span.start_secs)
+            .then(a.replaces.start.cmp(&b.replaces.start))
+    });
+    let mut merged: Vec<Fix> = Vec::with_capacity(fixes.len());
+    for fix in fixes {
+        match merged.last_mut() {
+            Some(last) if last.collides(&fix) => last.merge(&fix),
+            _ => merged.push(fix),
+        }
+    }
+    Ok(merged)
+}
+
+/// `segments` with each fix's replaced segments swapped for its new one.
+/// `fixes` come from [`plan_fixes`], paired with their new segment.
+pub fn splice(segments: &[Segment], fixes: &[(Fix, Segment)]) -> Vec<Segment> {
+    let mut out = Vec::with_capacity(segments.len() + fixes.len());
+    let mut next = 0;
+    for (fix, segment) in fixes {
+        out.extend_from_slice(&segments[next..fix.replaces.start]);
+        out.push(segment.clone());
+        next = fix.replaces.end;
+    }
+    out.extend_from_slice(&segments[next..]);
+    out
+}
+
+/// The language to keep on a fix's new segment: the one every replaced
+/// segment had, if they agree.
+fn shared_language(replaced: &[Segment]) -> Option<String> {
+    let first = replaced.first()?.language.clone()?;
+    replaced
+        .iter()
+        .all(|seg| seg.language.as_deref() == Some(first.as_str()))
+        .then_some(first)
+}
+
+// ── Audio slices ─────────────────────────────────────────────────────────────
+
+/// The frames (per-channel samples) of `span` in audio of `frames` frames
+/// at `sample_rate`.
+///
+/// Both ends are rounded to the nearest frame, so two segments that touch
+/// share the boundary frame index: the first slice ends exactly where the
+/// second starts, with no frame lost or heard twice.
+pub fn frame_range(span: TimeRange, sample_rate: u32, frames: usize) -> Range<usize> {
+    let frame = |secs: f64| ((secs.max(0.0) * f64::from(sample_rate)).round() as usize).min(frames);
+    let start = frame(span.start_secs);
+    start..frame(span.end_secs).max(start)
+}
+
+/// The samples of `span` in `audio`.
+pub fn slice(audio: &AudioInput, span: TimeRange) -> AudioInput {
+    let channels = usize::from(audio.channels.max(1));
+    let frames = frame_range(span, audio.sample_rate, audio.samples.len() / channels);
+    AudioInput {
+        samples: audio.samples[frames.start * channels..frames.end * channels].to_vec(),
+        sample_rate: audio.sample_rate,
+        channels: audio.channels,
+    }
+}
+
+// ── Transcript files ─────────────────────────────────────────────────────────
+
+/// The layouts `retranscribe` reads and writes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TranscriptFormat {
+    /// SubRip, as written by `transcribe --output-format srt`.
+    Srt,
+    /// The result object of `transcribe --output-format json`.
+    Json,
+}
+
+/// A transcript read back from disk.
+#[derive(Clone, Debug)]
+pub struct TranscriptFile {
+    pub format: TranscriptFormat,
+    pub segments: Vec<Segment>,
+    /// The whole JSON object, so the fields this command doesn't touch are
+    /// written back as they were.
+    json: Option<serde_json::Value>,
+}
+
+impl TranscriptFile {
+    /// Read `path`, as JSON when it ends in `.json` or starts with `{`,
+    /// otherwise as SRT.
+    pub fn read(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read transcript: {}", path.display()))?;
+        let is_json = match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => true,
+            Some(ext) if ext.eq_ignore_ascii_case("srt") => false,
+            _ => text
+                .trim_start_matches('\u{feff}')
+                .trim_start()
+                .starts_with('{'),
+        };
+        let file = if is_json {
+            Self::from_json(&text)
+        } else {
+            parse_srt(&text).map(|segments| Self {
+                format: TranscriptFormat::Srt,
+                segments,
+                json: None,
+            })
+        };
+        let file = file.with_context(|| format!("Failed to parse {}", path.display()))?;
+        if file.segments.is_empty() {
+            anyhow::bail!(
+                "{} has no segments to fix; transcribe with --vad or --timestamps inline \
+                 to get timed segments",
+                path.display()
+            );
+        }
+        Ok(file)
+    }
+
+    fn from_json(text: &str) -> Result<Self> {
+        let json: serde_json::Value = serde_json::from_str(text).context("Invalid JSON")?;
+        let segments = match json.get("segments") {
+            Some(serde_json::Value::Array(_)) => {
+                serde_json::from_value(json["segments"].clone()).context("Invalid `segments`")?
+            }
+            _ => Vec::new(),
+        };
+        Ok(Self {
+            format: TranscriptFormat::Json,
+            segments,
+            json: Some(json),
+        })
+    }
+
+    /// The transcript with `segments` in place of the ones read: SRT, or
+    /// the JSON object with `segments` and `text` replaced.
+    pub fn render(&self, segments: &[Segment]) -> Result<String> {
+        match (&self.json, self.format) {
+            (Some(json), TranscriptFormat::Json) => {
+                let mut json = json.clone();
+                json["segments"] = serde_json::to_value(segments)?;
+                json["text"] = audio_transcription::joined_text(segments).into();
+                Ok(serde_json::to_string_pretty(&json)?)
+            }
+            _ => Ok(transcript_output::format_srt(segments)),
+        }
+    }
+}
+
+/// Parse an SRT document into segments.  A `[xx] ` prefix naming a known
+/// language, as `--detect-language` writes it, becomes the segment's
+/// language again.
+pub fn parse_srt(text: &str) -> Result<Vec<Segment>> {
+    let text = text.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+    let mut segments = Vec::new();
+    for block in text.split("\n\n").map(str::trim).filter(|b| !b.is_empty()) {
+        let mut lines = block.lines();
+        let mut timing = lines.next().unwrap_or_default();
+        if !timing.contains("-->") {
+            timing = lines.next().unwrap_or_default();
+        }
+        let (start, end) = timing
+            .split_once("-->")
+            .with_context(|| format!("Cue {} has no `start --> end` line", segments.len() + 1))?;
+        let cue = || format!("Cue {}", segments.len() + 1);
+        let start_secs = parse_time(start).with_context(cue)?;
+        // WebVTT-style cue settings may follow the end time.
+        let end = end.split_whitespace().next().unwrap_or_default();
+        let end_secs = parse_time(end).with_context(cue)?;
+        let text = lines.collect::<Vec<_>>().join("\n");
+        let (language, text) = split_language(&text);
+        segments.push(Segment {
+            start_secs,
+            end_secs,
+            text: text.to_string(),
+            language,
+        });
+    }
+    Ok(segments)
+}
+
+/// Split a `[xx] ` language prefix off cue text.  Tags that aren't a known
+/// language code, like `[inaudible]`, stay in the text.
+fn split_language(text: &str) -> (Option<String>, &str) {
+    if let Some(rest) = text.strip_prefix('[')
+        && let Some((code, text)) = rest.split_once("] ")
+        && code.len() == 2
+        && audio_transcription::normalize_language(code).as_deref() == Some(code)
+    {
+        return (Some(code.to_string()), text);
+    }
+    (None, text)
+}
+
+// ── Standalone CLI entry-point ───────────────────────────────────────────────
+
+/// Settings for [`run`], from the `retranscribe` command line.
+#[derive(Clone, Debug, Default)]
+pub struct RetranscribeOptions {
+    /// The audio the transcript was made from.
+    pub audio: PathBuf,
+    /// SRT or JSON written by `transcribe`.
+    pub transcript: PathBuf,
+    /// `--segments`: 1-based segment numbers.
+    pub indices: Vec<usize>,
+    /// `--range`: stretches of time.
+    pub ranges: Vec<TimeRange>,
+    pub model: Option<TranscriptionModel>,
+    pub model_path: Option<PathBuf>,
+    pub load: LoadOptions,
+    /// Instruction sent with each slice instead of the default.
+    pub user_prompt: Option<String>,
+    pub system_prompt: Option<String>,
+    pub language: Option<String>,
+    /// `None` for `--no-preprocess`.
+    pub preprocess: Option<PrepOptions>,
+    pub decoding: DecodingArgs,
+    /// Write here instead of stdout.
+    pub output: Option<PathBuf>,
+}
+
+/// One fix as reported in `--json` output.
+#[derive(Debug, Serialize)]
+struct FixReport {
+    /// 1-based numbers of the segments replaced (empty for an insertion).
+    segments: Vec<usize>,
+    start_secs: f64,
+    end_secs: f64,
+    old_text: String,
+    new_text: String,
+    inference_secs: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct RetranscribeReport {
+    audio: String,
+    transcript: String,
+    format: TranscriptFormat,
+    output: Option<String>,
+    fixes: Vec<FixReport>,
+    segments: Vec<Segment>,
+}
+
+/// Re-transcribe the selected segments of `transcript` from `audio` and
+/// write the transcript back with only those segments replaced.
+pub async fn run(
+    options: RetranscribeOptions,
+    cancel: CancellationToken,
+    out: OutputConfig,
+) -> Result<()> {
+    let file = TranscriptFile::read(&options.transcript)?;
+    let fixes = plan_fixes(&file.segments, &options.indices, &options.ranges)?;
+    if fixes.is_empty() {
+        anyhow::bail!("Nothing to re-transcribe: pass --segments and/or --range");
+    }
+    let audio = audio_transcription::decode_input(&options.audio, None)
+        .with_context(|| format!("Failed to decode {}", options.audio.display()))?;
+    let channels = usize::from(audio.channels.max(1));
+    let audio_secs = (audio.samples.len() / channels) as f64 / f64::from(audio.sample_rate);
+    if let Some(fix) = fixes.iter().find(|fix| fix.span.start_secs >= audio_secs) {
+        anyhow::bail!(
+            "{} starts after the end of {} ({}); is it the audio this transcript was made from?",
+            fix.span,
+            options.audio.display(),
+            fmt_clock(audio_secs)
+        );
+    }
+
+    let preset = options.model.unwrap_or_default();
+    let load = &options.load;
+    let load_start = Instant::now();
+    let loading = async {
+        match &options.model_path {
+            Some(path) => {
+                status!(out, "Loading transcription model: {}", path.display());
+                Ok::<_, anyhow::Error>(AudioTranscriber::from_local_path(path, load).await?)
+            }
+            None => {
+                let (preset, load) = memory::choose(preset, load)?;
+                status!(out, "Loading transcription model: {preset}");
+                status!(out, "  Memory estimate: {}", preset.memory_estimate(&load));
+                Ok(AudioTranscriber::from_preset_with_options(preset, &load).await?)
+            }
+        }
+    };
+    let mut transcriber = interrupt::unless_cancelled(Some(&cancel), loading).await?;
+    status!(
+        out,
+        "Model loaded in {}",
+        fmt_duration(load_start.elapsed())
+    );
+    if let Some(system_prompt) = &options.system_prompt {
+        transcriber = transcriber.with_system_prompt(system_prompt.clone());
+    }
+    if let Some(language) = &options.language {
+        transcriber = transcriber.with_language(language);
+    }
+    let transcriber = transcriber
+        .with_preprocessing(options.preprocess)
+        .with_sampler(options.decoding.apply(DEFAULT_SAMPLER))
+        .with_cancellation(cancel.clone());
+
+    let mut replaced = Vec::with_capacity(fixes.len());
+    let mut reports = Vec::with_capacity(fixes.len());
+    for (i, fix) in fixes.iter().enumerate() {
+        let old = &file.segments[fix.replaces.clone()];
+        let numbers: Vec<usize> = fix.replaces.clone().map(|i| i + 1).collect();
+        let label = match numbers.as_slice() {
+            [] => "new segment".to_string(),
+            [n] => format!("segment {n}"),
+            [first, .., last] => format!("segments {first}–{last}"),
+        };
+        status!(
+            out,
+            "[{}/{}] Re-transcribing {label} ({})",
+            i + 1,
+            fixes.len(),
+            fix.span
+        );
+        let start = Instant::now();
+        let result = transcriber
+            .transcribe_audio(slice(&audio, fix.span), options.user_prompt.as_deref())
+            .await
+            .with_context(|| format!("Failed to re-transcribe {label} ({})", fix.span))?;
+        let elapsed = start.elapsed();
+        let text = result.text.trim().to_string();
+        let old_text = audio_transcription::joined_text(old);
+        if text.is_empty() {
+            tracing::warn!("nothing was heard in {}; {label} is left empty", fix.span);
+        }
+        detail!(out, "  was: {old_text:?}");
+        status!(out, "  now: {text:?} ({})", fmt_duration(elapsed));
+        replaced.push((
+            fix.clone(),
+            Segment {
+                start_secs: fix.span.start_secs,
+                end_secs: fix.span.end_secs,
+                text: text.clone(),
+                language: shared_language(old),
+            },
+        ));
+        reports.push(FixReport {
+            segments: numbers,
+            start_secs: fix.span.start_secs,
+            end_secs: fix.span.end_secs,
+            old_text,
+            new_text: text,
+            inference_secs: elapsed.as_secs_f64(),
+        });
+    }
+
+    let segments = splice(&file.segments, &replaced);
+    let rendered = file.render(&segments)?;
+    if let Some(path) = &options.output {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).with_context(|| {
+                format!("Failed to create output directory: {}", parent.display())
+            })?;
+        }
+        std::fs::write(path, &rendered)
+            .with_context(|| format!("Failed to write output file: {}", path.display()))?;
+        status!(out, "Updated transcript written to {}", path.display());
+    }
+
+    if out.json {
+        return ui::print_json(&RetranscribeReport {
+            audio: options.audio.display().to_string(),
+            transcript: options.transcript.display().to_string(),
+            format: file.format,
+            output: options.output.as_ref().map(|p| p.display().to_string()),
+            fixes: reports,
+            segments,
+        });
+    }
+    if options.output.is_none() {
+        print!("{rendered}");
+    }
+    Ok(())
+}
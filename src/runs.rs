@@ -0,0 +1,378 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime};
+
+use crate::artifacts::fmt_bytes;
+use crate::cli_chat::{fmt_utc, unix_secs};
+use crate::config::{self, Config};
+use crate::interrupt;
+use crate::ui::{self, OutputConfig, fmt_duration, status};
+
+// ── Constants ────────────────────────────────────────────────────────────────
+
+/// Base directory for `--run-dir` given without a value, and for
+/// `runs list`.
+pub const DEFAULT_BASE: &str = "runs";
+
+/// Summary written into each run directory when the command ends.
+pub const SUMMARY_FILE: &str = "run.json";
+
+/// The merged config-file settings, written when the run starts.
+pub const CONFIG_FILE: &str = "config.toml";
+
+/// Fresh IDs tried before giving up on a name that's never free.
+const CREATE_ATTEMPTS: usize = 16;
+
+// ── Run IDs ──────────────────────────────────────────────────────────────────
+
+/// The directory name of a run started at `unix_secs`:
+/// `2024-06-05_14-32-10_3fa9c1`, the UTC start time, then six hex digits of
+/// `entropy` so runs started in the same second get different names.
+pub fn run_id(unix_secs: u64, entropy: u64) -> String {
+    let stamp = fmt_utc(unix_secs);
+    // "2024-06-05 14:32:10 UTC" → "2024-06-05_14-32-10"
+    let stamp = stamp[..19].replace(' ', "_").replace(':', "-");
+    format!("{stamp}_{:06x}", entropy & 0xff_ffff)
+}
+
+/// Where the run with `id` lives under `base`.
+pub fn run_path(base: &Path, id: &str) -> PathBuf {
+    base.join(id)
+}
+
+/// Where an output path given as `path` goes in the run directory `dir`:
+/// relative paths are moved under it, absolute ones are left alone.
+pub fn place(dir: &Path, path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        dir.join(path)
+    }
+}
+
+// ── Artifacts ────────────────────────────────────────────────────────────────
+
+/// What a file in a run directory is, by extension.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArtifactKind {
+    /// PNG / JPEG images.
+    Image,
+    /// Plain-text, SRT, VTT and LRC transcripts.
+    Transcript,
+    /// JSON results and image sidecars.
+    Json,
+    /// The effective config.
+    Config,
+    Other,
+}
+
+impl ArtifactKind {
+    pub fn of(path: &Path) -> Self {
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("png" | "jpg" | "jpeg" | "webp") => Self::Image,
+            Some("txt" | "srt" | "vtt" | "lrc") => Self::Transcript,
+            Some("json") => Self::Json,
+            Some("toml") => Self::Config,
+            _ => Self::Other,
+        }
+    }
+
+    /// Name for `count` of these, e.g. `3 images`.
+    fn counted(self, count: usize) -> String {
+        let (one, many) = match self {
+            Self::Image => ("image", "images"),
+            Self::Transcript => ("transcript", "transcripts"),
+            Self::Json => ("json", "json"),
+            Self::Config => ("config", "configs"),
+            Self::Other => ("other", "other"),
+        };
+        format!("{count} {}", if count == 1 { one } else { many })
+    }
+}
+
+/// One file the run left in its directory.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Artifact {
+    /// Relative to the run directory.
+    pub path: String,
+    pub kind: ArtifactKind,
+    pub bytes: u64,
+}
+
+/// Every file under `dir` except [`SUMMARY_FILE`], sorted by path.
+fn collect_artifacts(dir: &Path) -> io::Result<Vec<Artifact>> {
+    fn walk(root: &Path, dir: &Path, found: &mut Vec<Artifact>) -> io::Result<()> {
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            let metadata = entry.metadata()?;
+            if metadata.is_dir() {
+                walk(root, &path, found)?;
+                continue;
+            }
+            let relative = path.strip_prefix(root).unwrap_or(&path);
+            if relative == Path::new(SUMMARY_FILE) {
+                continue;
+            }
+            found.push(Artifact {
+                path: relative.display().to_string(),
+                kind: ArtifactKind::of(&path),
+                bytes: metadata.len(),
+            });
+        }
+        Ok(())
+    }
+
+    let mut found = Vec::new();
+    walk(dir, dir, &mut found)?;
+    found.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(found)
+}
+
+// ── Summary ──────────────────────────────────────────────────────────────────
+
+/// How the command ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunOutcome {
+    Ok,
+    Failed,
+    Interrupted,
+}
+
+/// `run.json`: what ran, for how long, how it ended and what it wrote.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RunSummary {
+    pub id: String,
+    /// The command line after the program name.
+    pub args: Vec<String>,
+    /// Working directory the command ran in, for its relative inputs.
+    pub cwd: Option<String>,
+    /// Unix seconds.
+    pub started: u64,
+    pub duration_secs: f64,
+    pub outcome: RunOutcome,
+    /// The error, for a failed run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub artifacts: Vec<Artifact>,
+}
+
+impl RunSummary {
+    /// The command line as it would be typed, arguments with spaces or
+    /// quotes in single quotes.
+    pub fn command(&self) -> String {
+        self.args
+            .iter()
+            .map(|arg| {
+                if arg.is_empty() || arg.contains(|c: char| c.is_whitespace() || "'\"".contains(c))
+                {
+                    format!("'{}'", arg.replace('\'', r"'\''"))
+                } else {
+                    arg.clone()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Artifacts by kind, e.g. `4 images, 4 json`, leaving out the
+    /// effective config every run has; `-` when there are none.
+    pub fn artifact_counts(&self) -> String {
+        let mut counts: Vec<(ArtifactKind, usize)> = Vec::new();
+        for artifact in &self.artifacts {
+            match counts.iter_mut().find(|(kind, _)| *kind == artifact.kind) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((artifact.kind, 1)),
+            }
+        }
+        counts.retain(|(kind, _)| *kind != ArtifactKind::Config);
+        counts.sort();
+        if counts.is_empty() {
+            return "-".to_string();
+        }
+        counts
+            .into_iter()
+            .map(|(kind, count)| kind.counted(count))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Total size of the artifacts.
+    pub fn total_bytes(&self) -> u64 {
+        self.artifacts.iter().map(|a| a.bytes).sum()
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).context("Failed to serialize the run summary")
+    }
+
+    pub fn from_json(text: &str) -> Result<Self> {
+        serde_json::from_str(text).context("Invalid run summary")
+    }
+}
+
+// ── RunDir ───────────────────────────────────────────────────────────────────
+
+/// The directory `--run-dir` creates for one invocation.  Commands write
+/// their artifacts into it through [`place`](Self::place);
+/// [`finish`](Self::finish) writes [`SUMMARY_FILE`] at the end.
+pub struct RunDir {
+    id: String,
+    path: PathBuf,
+    args: Vec<String>,
+    started: u64,
+    start: Instant,
+}
+
+impl RunDir {
+    /// Create a new run directory under `base` for the command line `args`.
+    ///
+    /// Each name is claimed with a single `create_dir`, so two invocations
+    /// started in the same second can't both get it; the loser tries again
+    /// with a new ID.
+    pub fn create(base: &Path, args: Vec<String>) -> Result<Self> {
+        std::fs::create_dir_all(base)
+            .with_context(|| format!("Failed to create {}", base.display()))?;
+        let started = unix_secs(SystemTime::now());
+        for _ in 0..CREATE_ATTEMPTS {
+            let id = run_id(started, RandomState::new().hash_one(std::process::id()));
+            let path = run_path(base, &id);
+            match std::fs::create_dir(&path) {
+                Ok(()) => {
+                    return Ok(Self {
+                        id,
+                        path,
+                        args,
+                        started,
+                        start: Instant::now(),
+                    });
+                }
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(err) => {
+                    return Err(err)
+                        .with_context(|| format!("Failed to create {}", path.display()));
+                }
+            }
+        }
+        anyhow::bail!(
+            "Failed to find a free run directory name in {}",
+            base.display()
+        )
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// [`place`] an output path in this run.
+    pub fn place(&self, path: &Path) -> PathBuf {
+        place(&self.path, path)
+    }
+
+    /// Write the merged config-file settings as [`CONFIG_FILE`].
+    pub fn write_config(&self, config: &Config) -> Result<()> {
+        let path = self.path.join(CONFIG_FILE);
+        std::fs::write(&path, config::effective_toml(config)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Write [`SUMMARY_FILE`] for a command that ended with `result`.
+    pub fn finish(self, result: &Result<()>) -> Result<RunSummary> {
+        let (outcome, error) = match result {
+            Ok(()) => (RunOutcome::Ok, None),
+            Err(err) if interrupt::is_interrupted(err) => (RunOutcome::Interrupted, None),
+            Err(err) => (RunOutcome::Failed, Some(format!("{err:#}"))),
+        };
+        let summary = RunSummary {
+            id: self.id,
+            args: self.args,
+            cwd: std::env::current_dir()
+                .ok()
+                .map(|dir| dir.display().to_string()),
+            started: self.started,
+            duration_secs: self.start.elapsed().as_secs_f64(),
+            outcome,
+            error,
+            artifacts: collect_artifacts(&self.path)
+                .with_context(|| format!("Failed to list {}", self.path.display()))?,
+        };
+        let path = self.path.join(SUMMARY_FILE);
+        std::fs::write(&path, summary.to_json()?)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(summary)
+    }
+}
+
+// ── runs list ────────────────────────────────────────────────────────────────
+
+/// The finished runs under `base`, oldest first.  Directories without a
+/// [`SUMMARY_FILE`] (still running, or killed) are left out; one that
+/// can't be read is skipped with a warning.
+pub fn read_runs(base: &Path) -> Result<Vec<RunSummary>> {
+    let entries = match std::fs::read_dir(base) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err).with_context(|| format!("Failed to read {}", base.display())),
+    };
+    let mut runs = Vec::new();
+    for entry in entries {
+        let path = entry?.path().join(SUMMARY_FILE);
+        if !path.is_file() {
+            continue;
+        }
+        let summary = std::fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|text| RunSummary::from_json(&text));
+        match summary {
+            Ok(summary) => runs.push(summary),
+            Err(err) => tracing::warn!("skipping {}: {err:#}", path.display()),
+        }
+    }
+    runs.sort_by(|a, b| (a.started, &a.id).cmp(&(b.started, &b.id)));
+    Ok(runs)
+}
+
+/// `runs list`: one line per finished run under `base`.
+pub fn list(base: &Path, out: OutputConfig) -> Result<()> {
+    let runs = read_runs(base)?;
+    if out.json {
+        return ui::print_json(&runs);
+    }
+
+    status!(out, "Run directory: {}\n", base.display());
+    if runs.is_empty() {
+        status!(out, "No runs.");
+        return Ok(());
+    }
+    println!(
+        "{:<27} {:<11} {:>9} {:>9}  {:<24}  COMMAND",
+        "RUN", "OUTCOME", "DURATION", "SIZE", "ARTIFACTS"
+    );
+    for run in &runs {
+        let outcome = match run.outcome {
+            RunOutcome::Ok => "ok",
+            RunOutcome::Failed => "failed",
+            RunOutcome::Interrupted => "interrupted",
+        };
+        println!(
+            "{:<27} {:<11} {:>9} {:>9}  {:<24}  {}",
+            run.id,
+            outcome,
+            fmt_duration(std::time::Duration::from_secs_f64(run.duration_secs)),
+            fmt_bytes(run.total_bytes()),
+            run.artifact_counts(),
+            run.command()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,260 @@
+use mistralrs::{RequestBuilder, StopTokens};
+use serde::Serialize;
+use std::hash::{BuildHasher, RandomState};
+use std::time::SystemTime;
+
+// ── SamplerConfig ────────────────────────────────────────────────────────────
+
+/// Sampling settings applied to every request a component sends.
+///
+/// Shared by `PromptEnhancer`, `CliChat` and `AudioTranscriber`; each
+/// starts from its own defaults and `--temperature` / `--top-p` /
+/// `--max-tokens` and the [`DecodingArgs`] override them via
+/// [`SamplerArgs::apply`].
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SamplerConfig {
+    pub temperature: f64,
+    pub top_p: f64,
+    /// Only sample from the `k` most likely tokens (`None` = no limit).
+    pub top_k: Option<usize>,
+    /// Maximum number of generated tokens per reply (0 = the engine's own
+    /// limit).
+    pub max_len: usize,
+    /// RNG seed for sampling, so a run can be replayed (`None` = the
+    /// engine's own, different every run).
+    pub seed: Option<u64>,
+    /// Penalty on tokens in proportion to how often they already appear
+    /// in the reply (`--repeat-penalty`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f32>,
+    /// Flat penalty on any token that already appears in the reply.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f32>,
+    /// Generation stops at the first of these strings, which is cut from
+    /// the reply (see [`trim_at_stop`]).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
+}
+
+impl SamplerConfig {
+    pub const fn new(temperature: f64, top_p: f64, max_len: usize) -> Self {
+        Self {
+            temperature,
+            top_p,
+            top_k: None,
+            max_len,
+            seed: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            stop: Vec::new(),
+        }
+    }
+
+    pub fn with_temperature(mut self, temperature: f64) -> Self {
+        self.temperature = temperature;
+        self
+    }
+
+    pub fn with_top_p(mut self, top_p: f64) -> Self {
+        self.top_p = top_p;
+        self
+    }
+
+    pub fn with_max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Set these sampler parameters on `request`.
+    pub fn apply(&self, request: RequestBuilder) -> RequestBuilder {
+        let mut request = request
+            .set_sampler_temperature(self.temperature)
+            .set_sampler_topp(self.top_p);
+        if self.max_len > 0 {
+            request = request.set_sampler_max_len(self.max_len);
+        }
+        if let Some(top_k) = self.top_k {
+            request = request.set_sampler_topk(top_k);
+        }
+        if let Some(seed) = self.seed {
+            request = request.set_sampler_seed(seed);
+        }
+        if let Some(penalty) = self.frequency_penalty {
+            request = request.set_sampler_frequency_penalty(penalty);
+        }
+        if let Some(penalty) = self.presence_penalty {
+            request = request.set_sampler_presence_penalty(penalty);
+        }
+        if !self.stop.is_empty() {
+            request = request.set_sampler_stop_toks(StopTokens::Seqs(self.stop.clone()));
+        }
+        request
+    }
+
+    /// `text` cut at the first of the stop strings; see [`trim_at_stop`].
+    pub fn trim_stop<'a>(&self, text: &'a str) -> &'a str {
+        trim_at_stop(text, &self.stop)
+    }
+}
+
+/// `text` up to the earliest occurrence of any of `stop`, with trailing
+/// whitespace removed.
+///
+/// The engine stops generating at a stop string but may leave it at the
+/// end of the reply, and a streamed reply can run past it before the stop
+/// takes effect; every caller trims through here so both cases come out
+/// the same.
+pub fn trim_at_stop<'a>(text: &'a str, stop: &[String]) -> &'a str {
+    let end = stop
+        .iter()
+        .filter(|seq| !seq.is_empty())
+        .filter_map(|seq| text.find(seq.as_str()))
+        .min()
+        .unwrap_or(text.len());
+    text[..end].trim_end()
+}
+
+/// A fresh seed for a run not given `--sampler-seed`; printed so the run
+/// can be replayed with it.
+pub fn random_seed() -> u64 {
+    RandomState::new().hash_one(SystemTime::now())
+}
+
+// ── SamplerArgs ──────────────────────────────────────────────────────────────
+
+/// `--temperature` / `--top-p` / `--max-tokens` overrides, plus the
+/// [`DecodingArgs`].  Unset fields keep the component's defaults.
+#[derive(Clone, Debug, Default, PartialEq, clap::Args)]
+pub struct SamplerArgs {
+    /// Sampling temperature (>= 0; 0 is greedy).  Defaults to 0.9 for
+    /// prompt enhancement and 0.7 for chat.
+    #[arg(long, allow_negative_numbers = true, value_parser = parse_temperature)]
+    pub temperature: Option<f64>,
+
+    /// Nucleus sampling: only sample from the smallest set of tokens whose
+    /// probabilities add up to this value, in (0, 1] (default 0.95).
+    #[arg(long, value_parser = parse_top_p)]
+    pub top_p: Option<f64>,
+
+    /// Maximum number of tokens generated per reply.  Defaults to 80 for
+    /// prompt enhancement (the result is cut to CLIP's window anyway) and
+    /// 512 for chat.
+    #[arg(long, alias = "max-len", value_parser = clap::value_parser!(u64).range(1..))]
+    pub max_tokens: Option<u64>,
+
+    #[command(flatten)]
+    pub decoding: DecodingArgs,
+}
+
+impl SamplerArgs {
+    /// Apply the overrides on top of a component's defaults.
+    pub fn apply(&self, defaults: SamplerConfig) -> SamplerConfig {
+        let config = SamplerConfig {
+            temperature: self.temperature.unwrap_or(defaults.temperature),
+            top_p: self.top_p.unwrap_or(defaults.top_p),
+            max_len: self.max_tokens.map_or(defaults.max_len, |n| n as usize),
+            ..defaults
+        };
+        self.decoding.apply(config)
+    }
+}
+
+/// `--top-k` / `--repeat-penalty` / `--presence-penalty` / `--stop`
+/// overrides.  Separate from [`SamplerArgs`] so `transcribe`, which always
+/// decodes greedily, can take these alone.
+#[derive(Clone, Debug, Default, PartialEq, clap::Args)]
+pub struct DecodingArgs {
+    /// Only sample from the K most likely tokens (>= 1).  No limit by
+    /// default.
+    #[arg(long, value_name = "K", value_parser = clap::value_parser!(u64).range(1..))]
+    pub top_k: Option<u64>,
+
+    /// Penalize tokens in proportion to how often they already appear in
+    /// the reply, in [-2, 2] (a frequency penalty; 0 is none).  Try 0.5 if
+    /// the enhancer repeats "highly detailed".
+    #[arg(long, value_name = "P", allow_negative_numbers = true, value_parser = parse_penalty)]
+    pub repeat_penalty: Option<f32>,
+
+    /// Penalize any token that already appears in the reply, however
+    /// often, in [-2, 2] (0 is none).
+    #[arg(long, value_name = "P", allow_negative_numbers = true, value_parser = parse_penalty)]
+    pub presence_penalty: Option<f32>,
+
+    /// Stop the reply at this string, which is left out of the output
+    /// (repeatable, e.g. --stop "Enhanced prompt:").
+    #[arg(long = "stop", value_name = "SEQ")]
+    pub stop: Vec<String>,
+}
+
+impl DecodingArgs {
+    /// Apply the overrides on top of a component's defaults.  Stop strings
+    /// are added to the defaults' own.
+    pub fn apply(&self, defaults: SamplerConfig) -> SamplerConfig {
+        let mut stop = defaults.stop;
+        for seq in &self.stop {
+            if !stop.contains(seq) {
+                stop.push(seq.clone());
+            }
+        }
+        SamplerConfig {
+            top_k: self.top_k.map(|k| k as usize).or(defaults.top_k),
+            frequency_penalty: self.repeat_penalty.or(defaults.frequency_penalty),
+            presence_penalty: self.presence_penalty.or(defaults.presence_penalty),
+            stop,
+            ..defaults
+        }
+    }
+}
+
+/// A temperature is valid when finite and >= 0.
+pub fn check_temperature(t: f64) -> Result<(), String> {
+    if !(t.is_finite() && t >= 0.0) {
+        return Err(format!("must be >= 0, got {t}"));
+    }
+    Ok(())
+}
+
+/// A top-p is valid in (0, 1].
+pub fn check_top_p(p: f64) -> Result<(), String> {
+    if !(p > 0.0 && p <= 1.0) {
+        return Err(format!("must be in (0, 1], got {p}"));
+    }
+    Ok(())
+}
+
+/// A frequency or presence penalty is valid in [-2, 2].
+pub fn check_penalty(p: f32) -> Result<(), String> {
+    if !(-2.0..=2.0).contains(&p) {
+        return Err(format!("must be in [-2, 2], got {p}"));
+    }
+    Ok(())
+}
+
+fn parse_penalty(value: &str) -> Result<f32, String> {
+    let p: f32 = value
+        .parse()
+        .map_err(|_| format!("`{value}` is not a number"))?;
+    check_penalty(p)?;
+    Ok(p)
+}
+
+fn parse_temperature(value: &str) -> Result<f64, String> {
+    let t: f64 = value
+        .parse()
+        .map_err(|_| format!("`{value}` is not a number"))?;
+    check_temperature(t)?;
+    Ok(t)
+}
+
+fn parse_top_p(value: &str) -> Result<f64, String> {
+    let p: f64 = value
+        .parse()
+        .map_err(|_| format!("`{value}` is not a number"))?;
+    check_top_p(p)?;
+    Ok(p)
+}
@@ -0,0 +1,538 @@
+use anyhow::{Context, Result};
+use axum::extract::{DefaultBodyLimit, FromRequest, Multipart, Request, State};
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use base64::Engine;
+use mistralrs::AudioInput;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::Mutex;
+
+use crate::audio_transcription::{AudioTranscriber, TranscriptionModel};
+use crate::error::Error;
+use crate::image_generation::{ImageGenerator, ImageOptions};
+use crate::image_metadata::MetadataOutput;
+use crate::load_options::LoadOptions;
+use crate::memory;
+use crate::model_manager::ModelManager;
+use crate::promp_enhancer::{self, EnhancerModel, PromptEnhancer};
+use crate::sampler::SamplerArgs;
+use crate::timeout;
+use crate::usage::Usage;
+
+// ── Capabilities ─────────────────────────────────────────────────────────────
+
+/// Largest request body accepted, so audio uploads fit.
+const MAX_BODY_BYTES: usize = 100 * 1024 * 1024;
+
+/// Most images one `/v1/images` request may ask for.
+const MAX_IMAGES_PER_REQUEST: usize = 4;
+
+/// Most prompts one `/v1/enhance` request may ask for.
+const MAX_CANDIDATES_PER_REQUEST: usize = 8;
+
+/// A model `serve` can load, chosen with `--enable`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Capability {
+    /// `POST /v1/enhance` with the prompt enhancer.
+    Enhance,
+    /// `POST /v1/transcribe` with the audio transcriber.
+    Transcribe,
+    /// `POST /v1/images` with FLUX.1-schnell.
+    Images,
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Enhance => "enhance",
+            Self::Transcribe => "transcribe",
+            Self::Images => "images",
+        };
+        write!(f, "{name}")
+    }
+}
+
+// ── Errors ───────────────────────────────────────────────────────────────────
+
+/// An error response: `{"error": {"status": 400, "message": "…"}}`.
+#[derive(Debug)]
+struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn bad_request(message: impl fmt::Display) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            message: message.to_string(),
+        }
+    }
+
+    fn disabled(capability: Capability) -> Self {
+        Self {
+            status: StatusCode::NOT_FOUND,
+            message: format!(
+                "{capability} is not enabled; restart the server with --enable {capability}"
+            ),
+        }
+    }
+}
+
+/// A request that ran past `--timeout` is a 504; anything else not caught
+/// as a bad request is the server's fault.
+impl From<anyhow::Error> for ApiError {
+    fn from(err: anyhow::Error) -> Self {
+        let status = if timeout::is_timed_out(&err) {
+            StatusCode::GATEWAY_TIMEOUT
+        } else {
+            StatusCode::INTERNAL_SERVER_ERROR
+        };
+        Self {
+            status,
+            message: format!("{err:#}"),
+        }
+    }
+}
+
+impl From<Error> for ApiError {
+    fn from(err: Error) -> Self {
+        anyhow::Error::from(err).into()
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        #[derive(Serialize)]
+        struct Body {
+            error: Detail,
+        }
+        #[derive(Serialize)]
+        struct Detail {
+            status: u16,
+            message: String,
+        }
+
+        let body = Body {
+            error: Detail {
+                status: self.status.as_u16(),
+                message: self.message,
+            },
+        };
+        (self.status, Json(body)).into_response()
+    }
+}
+
+// ── State ────────────────────────────────────────────────────────────────────
+
+/// The loaded models.  The enhancer and transcriber queue their own
+/// requests (up to `--max-concurrency` in flight); the image generator
+/// sits behind a lock because a [`Model`] handles one generation at a
+/// time.  Both are fair, so waiting requests are served in arrival order.
+///
+/// [`Model`]: mistralrs::Model
+struct AppState {
+    enhancer: Option<PromptEnhancer>,
+    transcriber: Option<AudioTranscriber>,
+    images: Option<Mutex<ImageGenerator>>,
+}
+
+impl AppState {
+    fn enabled(&self) -> Vec<Capability> {
+        let mut enabled = Vec::new();
+        if self.enhancer.is_some() {
+            enabled.push(Capability::Enhance);
+        }
+        if self.transcriber.is_some() {
+            enabled.push(Capability::Transcribe);
+        }
+        if self.images.is_some() {
+            enabled.push(Capability::Images);
+        }
+        enabled
+    }
+}
+
+type SharedState = Arc<AppState>;
+
+fn router(state: SharedState) -> Router {
+    Router::new()
+        .route("/health", get(health))
+        .route("/v1/enhance", post(enhance))
+        .route("/v1/transcribe", post(transcribe))
+        .route("/v1/images", post(images))
+        .layer(DefaultBodyLimit::max(MAX_BODY_BYTES))
+        .with_state(state)
+}
+
+/// Parse a JSON body, reporting malformed input as a 400 with our error
+/// shape instead of axum's plain-text rejection.
+async fn json_body<T: serde::de::DeserializeOwned>(request: Request) -> Result<T, ApiError> {
+    Json::<T>::from_request(request, &())
+        .await
+        .map(|Json(body)| body)
+        .map_err(|rejection| ApiError::bad_request(rejection.body_text()))
+}
+
+// ── Handlers ─────────────────────────────────────────────────────────────────
+
+#[derive(Serialize)]
+struct HealthResponse {
+    enabled: Vec<Capability>,
+}
+
+async fn health(State(state): State<SharedState>) -> Json<HealthResponse> {
+    Json(HealthResponse {
+        enabled: state.enabled(),
+    })
+}
+
+#[derive(Deserialize)]
+struct EnhanceRequest {
+    seed: String,
+    /// Number of distinct prompts to return (default 1).
+    #[serde(default)]
+    candidates: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct EnhanceResponse {
+    /// The first (or only) enhanced prompt.
+    prompt: String,
+    /// Every distinct prompt, when more than one was asked for.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    candidates: Option<Vec<String>>,
+    /// Tokens processed and generated over every request.
+    usage: Usage,
+}
+
+/// `POST /v1/enhance` — `{"seed": "…", "candidates": 3}` → enhanced prompt(s).
+async fn enhance(
+    State(state): State<SharedState>,
+    request: Request,
+) -> Result<Json<EnhanceResponse>, ApiError> {
+    let body: EnhanceRequest = json_body(request).await?;
+    let enhancer = state
+        .enhancer
+        .as_ref()
+        .ok_or(ApiError::disabled(Capability::Enhance))?;
+    if body.seed.trim().is_empty() {
+        return Err(ApiError::bad_request("`seed` must not be empty"));
+    }
+
+    let response = match body.candidates.unwrap_or(1) {
+        0 => return Err(ApiError::bad_request("`candidates` must be at least 1")),
+        n if n > MAX_CANDIDATES_PER_REQUEST => {
+            return Err(ApiError::bad_request(format!(
+                "`candidates` must be at most {MAX_CANDIDATES_PER_REQUEST}"
+            )));
+        }
+        1 => {
+            let result = enhancer.enhance(&body.seed).await?;
+            EnhanceResponse {
+                usage: result.usage,
+                prompt: result.into(),
+                candidates: None,
+            }
+        }
+        n => {
+            let results = enhancer.enhance_n(&body.seed, n).await?;
+            let usage = results.iter().map(|result| result.usage).sum();
+            let candidates: Vec<String> = results.into_iter().map(String::from).collect();
+            EnhanceResponse {
+                prompt: candidates.first().cloned().unwrap_or_default(),
+                candidates: Some(candidates),
+                usage,
+            }
+        }
+    };
+    Ok(Json(response))
+}
+
+#[derive(Deserialize)]
+struct TranscribeRequest {
+    /// Audio file contents (WAV, MP3, OGG, FLAC …), base64-encoded.
+    audio_base64: String,
+}
+
+/// `POST /v1/transcribe` — a multipart upload with an `audio` file field,
+/// or `{"audio_base64": "…"}` → the transcription result as JSON.
+async fn transcribe(
+    State(state): State<SharedState>,
+    request: Request,
+) -> Result<Response, ApiError> {
+    let transcriber = state
+        .transcriber
+        .as_ref()
+        .ok_or(ApiError::disabled(Capability::Transcribe))?;
+
+    let is_multipart = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("multipart/form-data"));
+    let bytes = if is_multipart {
+        audio_from_multipart(request).await?
+    } else {
+        let body: TranscribeRequest = json_body(request).await?;
+        base64::engine::general_purpose::STANDARD
+            .decode(body.audio_base64.trim())
+            .map_err(|e| {
+                ApiError::bad_request(format!("`audio_base64` is not valid base64: {e}"))
+            })?
+    };
+    let audio = AudioInput::from_bytes(&bytes)
+        .map_err(|e| ApiError::bad_request(format!("Failed to decode audio: {e:#}")))?;
+
+    let result = transcriber.transcribe_audio(audio, None).await?;
+    Ok((
+        [(header::CONTENT_TYPE, "application/json")],
+        result.to_json()?,
+    )
+        .into_response())
+}
+
+/// The bytes of the `audio` (or `file`) field of a multipart upload.
+async fn audio_from_multipart(request: Request) -> Result<Vec<u8>, ApiError> {
+    let mut multipart = Multipart::from_request(request, &())
+        .await
+        .map_err(|rejection| ApiError::bad_request(rejection.body_text()))?;
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| ApiError::bad_request(e.body_text()))?
+    {
+        if matches!(field.name(), Some("audio" | "file")) {
+            let bytes = field
+                .bytes()
+                .await
+                .map_err(|e| ApiError::bad_request(e.body_text()))?;
+            return Ok(bytes.to_vec());
+        }
+    }
+    Err(ApiError::bad_request(
+        "multipart upload has no `audio` file field",
+    ))
+}
+
+/// How `/v1/images` returns its images.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ImageFormat {
+    /// JSON with the PNGs base64-encoded.
+    #[default]
+    B64Json,
+    /// The raw PNG (`image/png`); only for a single image.
+    Png,
+}
+
+#[derive(Deserialize)]
+struct ImagesRequest {
+    prompt: String,
+    width: Option<usize>,
+    height: Option<usize>,
+    /// Number of images (default 1).
+    n: Option<usize>,
+    negative_prompt: Option<String>,
+    guidance: Option<f64>,
+    #[serde(default)]
+    response_format: ImageFormat,
+}
+
+#[derive(Serialize)]
+struct ImagesResponse {
+    /// The prompt the diffusion model saw, after truncation.
+    prompt: String,
+    width: usize,
+    height: usize,
+    data: Vec<ImageData>,
+}
+
+#[derive(Serialize)]
+struct ImageData {
+    b64_json: String,
+    generation_secs: f64,
+}
+
+/// Scratch files for generated PNGs, removed once read back.
+static SCRATCH_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// `POST /v1/images` — `{"prompt": "…", "width": 512, …}` → PNG bytes or
+/// base64 JSON.
+async fn images(State(state): State<SharedState>, request: Request) -> Result<Response, ApiError> {
+    let body: ImagesRequest = json_body(request).await?;
+    let generator = state
+        .images
+        .as_ref()
+        .ok_or(ApiError::disabled(Capability::Images))?;
+    if body.prompt.trim().is_empty() {
+        return Err(ApiError::bad_request("`prompt` must not be empty"));
+    }
+    let num_images = body.n.unwrap_or(1);
+    if num_images > MAX_IMAGES_PER_REQUEST {
+        return Err(ApiError::bad_request(format!(
+            "`n` must be at most {MAX_IMAGES_PER_REQUEST}"
+        )));
+    }
+    if body.response_format == ImageFormat::Png && num_images != 1 {
+        return Err(ApiError::bad_request(
+            "`response_format: png` returns a single image; use b64_json for n > 1",
+        ));
+    }
+
+    let scratch = std::env::temp_dir().join(format!(
+        "mistralrs-example-serve-{}-{}.png",
+        std::process::id(),
+        SCRATCH_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let options = ImageOptions {
+        width: body.width,
+        height: body.height,
+        num_images,
+        out: Some(scratch),
+        force: true,
+        negative_prompt: body.negative_prompt,
+        guidance: body.guidance,
+        metadata: MetadataOutput::NONE,
+        grid: None,
+        sizes: Vec::new(),
+    };
+    let request = options
+        .request(&body.prompt)
+        .map_err(|e| ApiError::bad_request(format!("{e:#}")))?;
+
+    let result = generator.lock().await.generate(request).await?;
+    let mut pngs = Vec::with_capacity(result.images.len());
+    for image in &result.images {
+        let bytes = std::fs::read(&image.path)
+            .with_context(|| format!("Failed to read generated image: {}", image.path));
+        let _ = std::fs::remove_file(&image.path);
+        pngs.push((bytes?, image.generation_secs));
+    }
+
+    if body.response_format == ImageFormat::Png {
+        let (png, _) = pngs
+            .into_iter()
+            .next()
+            .context("The diffusion model returned no image")?;
+        return Ok(([(header::CONTENT_TYPE, "image/png")], png).into_response());
+    }
+    let data = pngs
+        .into_iter()
+        .map(|(png, generation_secs)| ImageData {
+            b64_json: base64::engine::general_purpose::STANDARD.encode(png),
+            generation_secs,
+        })
+        .collect();
+    Ok(Json(ImagesResponse {
+        prompt: result.prompt,
+        width: result.width,
+        height: result.height,
+        data,
+    })
+    .into_response())
+}
+
+// ── CLI entry-point ──────────────────────────────────────────────────────────
+
+/// Settings for [`run`].
+#[derive(Clone, Debug)]
+pub struct ServeOptions {
+    pub host: String,
+    pub port: u16,
+    /// Models to load; the other endpoints answer 404.
+    pub enable: Vec<Capability>,
+    pub enhancer_model: EnhancerModel,
+    pub transcription_model: TranscriptionModel,
+    /// `--isq` / `--dtype` for the enhancer and transcription models.
+    pub load: LoadOptions,
+    /// Sampling overrides for `/v1/enhance`.
+    pub sampler: SamplerArgs,
+    /// Requests the enhancer and the transcriber each send at once.
+    pub max_concurrency: usize,
+}
+
+/// Load the enabled models once, then serve them over HTTP until Ctrl-C.
+pub async fn run(options: ServeOptions) -> Result<()> {
+    if options.enable.is_empty() {
+        anyhow::bail!("--enable must name at least one of enhance, transcribe, images");
+    }
+    if options.load.uqff.has_explicit_path()
+        && options.enable.contains(&Capability::Enhance)
+        && options.enable.contains(&Capability::Transcribe)
+    {
+        anyhow::bail!(
+            "--save-uqff / --from-uqff name a single model's weights; use --cache-quantized when serving several models"
+        );
+    }
+    if options.max_concurrency == 0 {
+        anyhow::bail!("--max-concurrency must be at least 1");
+    }
+    let enabled = |capability| options.enable.contains(&capability);
+
+    // Gemma 3n can serve both endpoints: with the same preset and settings
+    // the transcriber reuses the enhancer's weights.
+    let models = ModelManager::new();
+    let enhancer = if enabled(Capability::Enhance) {
+        let (preset, load) = memory::choose(options.enhancer_model, &options.load)?;
+        println!("Loading prompt enhancer model: {preset}");
+        let enhancer = PromptEnhancer::from_preset_with_manager(preset, &load, &models)
+            .await?
+            .with_sampler(options.sampler.apply(promp_enhancer::DEFAULT_SAMPLER))
+            .with_max_concurrency(options.max_concurrency);
+        Some(enhancer)
+    } else {
+        None
+    };
+    let transcriber = if enabled(Capability::Transcribe) {
+        let preset = options.transcription_model;
+        let (preset, load) = if models.contains(&preset.model_key(&options.load)) {
+            println!("Sharing the enhancer's model for transcription: {preset}");
+            (preset, options.load.clone())
+        } else {
+            let (preset, load) = memory::choose(preset, &options.load)?;
+            println!("Loading transcription model: {preset}");
+            (preset, load)
+        };
+        let transcriber = AudioTranscriber::from_preset_with_manager(preset, &load, &models)
+            .await?
+            .with_max_concurrency(options.max_concurrency);
+        Some(transcriber)
+    } else {
+        None
+    };
+    let images = if enabled(Capability::Images) {
+        println!("Loading diffusion model: FLUX.1-schnell");
+        Some(Mutex::new(ImageGenerator::new().await?))
+    } else {
+        None
+    };
+
+    let state = Arc::new(AppState {
+        enhancer,
+        transcriber,
+        images,
+    });
+    let enabled: Vec<String> = state.enabled().iter().map(|c| c.to_string()).collect();
+
+    let address = format!("{}:{}", options.host, options.port);
+    let listener = tokio::net::TcpListener::bind(&address)
+        .await
+        .with_context(|| format!("Failed to listen on {address}"))?;
+    println!("\nServing {} on http://{address}", enabled.join(", "));
+    println!("Press Ctrl-C to stop.");
+
+    axum::serve(listener, router(state))
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+            println!("\nShutting down.");
+        })
+        .await
+        .context("Server error")
+}
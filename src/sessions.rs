@@ -0,0 +1,268 @@
+use anyhow::{Context, Result};
+use fs2::FileExt;
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+use crate::cli_chat::{SavedChat, SavedKind, fmt_utc, unix_secs};
+use crate::ui::{self, OutputConfig, status};
+
+// ── Locations ────────────────────────────────────────────────────────────────
+
+/// `~/.local/state/mistralrs-example/sessions`, or under `XDG_STATE_HOME`
+/// when set.
+pub fn sessions_dir() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_STATE_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => {
+            let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+            PathBuf::from(home).join(".local").join("state")
+        }
+    };
+    Some(base.join("mistralrs-example").join("sessions"))
+}
+
+fn require_dir() -> Result<PathBuf> {
+    sessions_dir().context("Can't locate the session directory; set HOME or XDG_STATE_HOME")
+}
+
+/// Session names become file names, so only letters, digits, `-`, `_`
+/// and `.` are allowed, and not a leading `.`.
+pub fn check_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid {
+        anyhow::bail!(
+            "Invalid session name `{name}`: use letters, digits, `-`, `_` and `.`, \
+             not starting with `.`"
+        );
+    }
+    Ok(())
+}
+
+fn json_path(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{name}.json"))
+}
+
+fn lock_path(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{name}.lock"))
+}
+
+/// Take the exclusive lock on session `name` in `dir`, or fail if another
+/// process holds it.  The OS drops the lock when the file is closed or the
+/// process dies, so a crash never leaves a session stuck.
+fn lock(dir: &Path, name: &str) -> Result<File> {
+    let path = lock_path(dir, name);
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    if file.try_lock_exclusive().is_err() {
+        anyhow::bail!("Session `{name}` is in use by another process");
+    }
+    Ok(file)
+}
+
+// ── Session ──────────────────────────────────────────────────────────────────
+
+/// A named conversation kept across restarts, for `chat --session` and
+/// `prompt --interactive --session`.
+///
+/// Holds the session's lock for as long as it is open, so two processes
+/// can't write the same session.  The file uses the `/save` format and is
+/// rewritten after every turn.
+pub struct Session {
+    name: String,
+    path: PathBuf,
+    _lock: File,
+}
+
+impl Session {
+    /// Open (or start) session `name` in [`sessions_dir`].
+    pub fn open(name: &str) -> Result<Self> {
+        Self::open_in(&require_dir()?, name)
+    }
+
+    /// Open (or start) session `name` in `dir`, creating `dir` if needed.
+    pub fn open_in(dir: &Path, name: &str) -> Result<Self> {
+        check_name(name)?;
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+        let lock = lock(dir, name)?;
+        Ok(Self {
+            name: name.to_string(),
+            path: json_path(dir, name),
+            _lock: lock,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The saved state, or `None` for a new session.  Fails if it was
+    /// saved by the other kind of command.
+    pub fn load(&self, kind: SavedKind) -> Result<Option<SavedChat>> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+        let saved = SavedChat::read(&self.path)?;
+        if saved.kind != kind {
+            anyhow::bail!(
+                "Session `{}` belongs to {}, not {kind}; pick another name",
+                self.name,
+                saved.kind
+            );
+        }
+        Ok(Some(saved))
+    }
+
+    /// Replace the saved state with `saved`.
+    pub fn save(&self, saved: &SavedChat) -> Result<()> {
+        saved
+            .write(&self.path)
+            .with_context(|| format!("Failed to save session `{}`", self.name))
+    }
+}
+
+// ── sessions list ────────────────────────────────────────────────────────────
+
+/// One saved session, as listed by `sessions list`.
+#[derive(Debug, Serialize)]
+pub struct SessionEntry {
+    pub name: String,
+    pub kind: SavedKind,
+    /// Messages in the history.
+    pub messages: usize,
+    /// Seconds since the Unix epoch of the last write.
+    pub modified: Option<u64>,
+    /// Open in another process right now.
+    pub in_use: bool,
+    pub path: String,
+}
+
+/// The sessions saved in `dir`, by name.  Files that don't parse are
+/// skipped with a warning.
+pub fn entries(dir: &Path) -> Result<Vec<SessionEntry>> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut entries = Vec::new();
+    for item in
+        std::fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))?
+    {
+        let path = item?.path();
+        if path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        let saved = match SavedChat::read(&path) {
+            Ok(saved) => saved,
+            Err(err) => {
+                tracing::warn!("skipping {}: {err:#}", path.display());
+                continue;
+            }
+        };
+        let modified = std::fs::metadata(&path)
+            .and_then(|meta| meta.modified())
+            .ok()
+            .map(unix_secs);
+        entries.push(SessionEntry {
+            name: name.to_string(),
+            kind: saved.kind,
+            messages: saved.history.len(),
+            modified,
+            in_use: lock(dir, name).is_err(),
+            path: path.display().to_string(),
+        });
+    }
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(entries)
+}
+
+/// `sessions list`: print every saved session.
+pub fn list(out: OutputConfig) -> Result<()> {
+    let dir = require_dir()?;
+    let entries = entries(&dir)?;
+    if out.json {
+        return ui::print_json(&entries);
+    }
+
+    status!(out, "Session directory: {}\n", dir.display());
+    if entries.is_empty() {
+        status!(out, "No saved sessions.");
+        return Ok(());
+    }
+    println!(
+        "{:<20} {:<6} {:>8}  {:<23}  STATE",
+        "NAME", "KIND", "MESSAGES", "MODIFIED"
+    );
+    for entry in &entries {
+        println!(
+            "{:<20} {:<6} {:>8}  {:<23}  {}",
+            entry.name,
+            entry.kind,
+            entry.messages,
+            entry.modified.map_or_else(|| "-".to_string(), fmt_utc),
+            if entry.in_use { "in use" } else { "idle" }
+        );
+    }
+    Ok(())
+}
+
+// ── sessions rm ──────────────────────────────────────────────────────────────
+
+/// Delete session `name` from `dir`.  Fails if it doesn't exist or is open
+/// in another process.
+pub fn remove_in(dir: &Path, name: &str) -> Result<()> {
+    check_name(name)?;
+    let path = json_path(dir, name);
+    if !path.exists() {
+        anyhow::bail!("No session named `{name}` in {}", dir.display());
+    }
+    let lock = lock(dir, name)?;
+    std::fs::remove_file(&path).with_context(|| format!("Failed to delete {}", path.display()))?;
+    drop(lock);
+    // Best effort: a process that opens the session right now recreates it.
+    let _ = std::fs::remove_file(lock_path(dir, name));
+    Ok(())
+}
+
+/// `sessions rm`: delete a saved session, asking first unless `yes`.
+pub fn remove(name: &str, yes: bool, out: OutputConfig) -> Result<()> {
+    let dir = require_dir()?;
+    check_name(name)?;
+    if !json_path(&dir, name).exists() {
+        anyhow::bail!("No session named `{name}` in {}", dir.display());
+    }
+
+    if !yes {
+        if !std::io::stdin().is_terminal() {
+            anyhow::bail!("Refusing to delete session `{name}` without confirmation; pass --yes");
+        }
+        eprint!("Delete session `{name}`? [y/N] ");
+        std::io::stderr().flush()?;
+        let mut answer = String::new();
+        std::io::stdin().read_line(&mut answer)?;
+        if !matches!(answer.trim(), "y" | "Y" | "yes") {
+            status!(out, "Kept session `{name}`.");
+            return Ok(());
+        }
+    }
+
+    remove_in(&dir, name)?;
+    status!(out, "Deleted session `{name}`.");
+    Ok(())
+}
@@ -0,0 +1,506 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::fmt;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+use crate::audio_transcription::TranscriptionModel;
+use crate::config;
+use crate::device;
+use crate::load_options::LoadOptions;
+use crate::memory::{self, MemoryFootprint};
+use crate::model_cache::{self, CacheState, CachedModel};
+use crate::promp_enhancer::EnhancerModel;
+use crate::ui::{self, OutputConfig, status};
+
+// ── Checks ───────────────────────────────────────────────────────────────────
+
+/// Outcome of one [`CheckResult`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    /// Nothing to do.
+    Ok,
+    /// Works, but not as well as it could; the fix is optional.
+    Warn,
+    /// Something the user has to fix before the default presets will run.
+    Fail,
+}
+
+impl fmt::Display for CheckStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Ok => "ok",
+            Self::Warn => "warn",
+            Self::Fail => "FAIL",
+        })
+    }
+}
+
+/// What one `setup` check found, and what to do about it.
+#[derive(Clone, Debug, Serialize)]
+pub struct CheckResult {
+    /// Stable name for scripts: `hf-token`, `memory`, `device` or `cache`.
+    pub check: &'static str,
+    pub status: CheckStatus,
+    /// What was found, in one line.
+    pub summary: String,
+    /// The next steps, for a warning or failure.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fix: Option<String>,
+}
+
+impl CheckResult {
+    fn ok(check: &'static str, summary: impl Into<String>) -> Self {
+        Self {
+            check,
+            status: CheckStatus::Ok,
+            summary: summary.into(),
+            fix: None,
+        }
+    }
+
+    fn warn(check: &'static str, summary: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            check,
+            status: CheckStatus::Warn,
+            summary: summary.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn fail(check: &'static str, summary: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            check,
+            status: CheckStatus::Fail,
+            summary: summary.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self.check {
+            "hf-token" => "HF token",
+            "memory" => "Memory",
+            "device" => "Device",
+            "cache" => "Model cache",
+            other => other,
+        }
+    }
+}
+
+impl fmt::Display for CheckResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:<7}{:<15}: {}",
+            format!("[{}]", self.status),
+            self.label(),
+            self.summary
+        )?;
+        for line in self.fix.iter().flat_map(|fix| fix.lines()) {
+            write!(f, "\n{:<24}{line}", "")?;
+        }
+        Ok(())
+    }
+}
+
+/// Where `huggingface-cli login` keeps its token: `HF_TOKEN_PATH`, else
+/// `$HF_HOME/token`, else `~/.cache/huggingface/token`.
+pub fn token_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("HF_TOKEN_PATH") {
+        return Some(PathBuf::from(path));
+    }
+    if let Some(home) = std::env::var_os("HF_HOME") {
+        return Some(PathBuf::from(home).join("token"));
+    }
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+    Some(PathBuf::from(home).join(".cache/huggingface/token"))
+}
+
+/// Whether an executable called `name` is on `PATH`.
+fn on_path(name: &str) -> bool {
+    std::env::var_os("PATH").is_some_and(|paths| {
+        std::env::split_paths(&paths)
+            .any(|dir| dir.join(name).is_file() || dir.join(format!("{name}.exe")).is_file())
+    })
+}
+
+/// A HuggingFace token in `HF_TOKEN` or from `huggingface-cli login`.
+///
+/// Whether the token has accepted Gemma's license can only be seen when
+/// the first download is attempted.
+pub fn check_hf_token() -> CheckResult {
+    if std::env::var("HF_TOKEN").is_ok_and(|token| !token.trim().is_empty()) {
+        return CheckResult::ok("hf-token", "HF_TOKEN is set");
+    }
+    if let Some(path) = token_path()
+        && std::fs::read_to_string(&path).is_ok_and(|token| !token.trim().is_empty())
+    {
+        return CheckResult::ok(
+            "hf-token",
+            format!("logged in with huggingface-cli ({})", path.display()),
+        );
+    }
+    let login = if on_path("huggingface-cli") {
+        "run `huggingface-cli login`"
+    } else {
+        "run `pip install -U huggingface_hub` and then `huggingface-cli login`"
+    };
+    CheckResult::fail(
+        "hf-token",
+        "no HuggingFace token found",
+        format!(
+            "Gemma 3n (the transcription model and default enhancer) is gated:\n\
+             1. Accept its license at https://huggingface.co/{} while logged in.\n\
+             2. Create a token with read access at https://huggingface.co/settings/tokens.\n\
+             3. export HF_TOKEN=<token>, or {login}.\n\
+             Until then, prompt and image work with --model phi-3.5-mini or qwen2.5-1.5b.",
+            EnhancerModel::GemmaE4b.model_id()
+        ),
+    )
+}
+
+/// Memory free for a model, against the smallest preset.
+pub fn check_memory() -> CheckResult {
+    let smallest = EnhancerModel::FALLBACK.required_bytes(&LoadOptions::default());
+    match memory::available_bytes() {
+        None => CheckResult::warn(
+            "memory",
+            "this platform doesn't report available memory",
+            "Presets are loaded as asked; choose gemma-e2b if loading runs out of memory.",
+        ),
+        Some(available) if available < smallest => CheckResult::fail(
+            "memory",
+            format!("{} available", memory::fmt_gb(available)),
+            format!(
+                "Even {} needs ~{}. Close other applications and run setup again.",
+                EnhancerModel::FALLBACK,
+                memory::fmt_gb(smallest)
+            ),
+        ),
+        Some(available) => {
+            CheckResult::ok("memory", format!("{} available", memory::fmt_gb(available)))
+        }
+    }
+}
+
+/// Whether an NVIDIA driver is loaded, judged from the files it creates.
+fn nvidia_driver_present() -> bool {
+    Path::new("/proc/driver/nvidia/version").exists() || Path::new("/dev/nvidia0").exists()
+}
+
+/// The compute backend this binary will use, and a GPU it can't.
+pub fn check_device() -> CheckResult {
+    let device = device::selected();
+    let apple = cfg!(any(target_os = "macos", target_os = "ios"));
+    if let Err(err) = device.check_available() {
+        return CheckResult::fail("device", device.describe(), format!("{err:#}"));
+    }
+    match device {
+        device::Device::Auto if apple => CheckResult::ok("device", "Metal (Apple GPU)"),
+        device::Device::Auto if cfg!(feature = "cuda") && !nvidia_driver_present() => {
+            CheckResult::warn(
+                "device",
+                "CUDA build, but no NVIDIA driver was found",
+                "Models will run on the CPU. Install the NVIDIA driver, or pass --device cpu \
+                 to say so explicitly.",
+            )
+        }
+        device::Device::Auto if !cfg!(feature = "cuda") && nvidia_driver_present() => {
+            CheckResult::warn(
+                "device",
+                "CPU only: an NVIDIA GPU is present but this build has no CUDA support",
+                "Rebuild with `cargo build --release --features cuda` to use it.",
+            )
+        }
+        device::Device::Auto if !cfg!(feature = "cuda") => CheckResult::ok(
+            "device",
+            "CPU (no GPU in this build; image generation will be slow)",
+        ),
+        device => CheckResult::ok("device", device.describe()),
+    }
+}
+
+/// Whether `presets` are downloaded already.
+pub fn check_cache(presets: &[CachedModel]) -> CheckResult {
+    let missing: Vec<String> = presets
+        .iter()
+        .filter_map(|&preset| {
+            let state = model_cache::scan_preset(preset).state;
+            (state != CacheState::Complete).then(|| format!("{} ({state})", preset.name()))
+        })
+        .collect();
+    if missing.is_empty() {
+        let names: Vec<&str> = presets.iter().map(|preset| preset.name()).collect();
+        return CheckResult::ok("cache", format!("{} downloaded", names.join(", ")));
+    }
+    CheckResult::warn(
+        "cache",
+        format!("not downloaded yet: {}", missing.join(", ")),
+        "The first run downloads them; `models prefetch <preset>` or `setup --prefetch` \
+         does it now.",
+    )
+}
+
+// ── Recommendation ───────────────────────────────────────────────────────────
+
+/// Presets `setup` suggests for this machine.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Recommendation {
+    pub enhancer: EnhancerModel,
+    pub transcription: TranscriptionModel,
+}
+
+/// Config keys the recommended presets are written to, per subcommand.
+const ENHANCER_KEYS: &[(&str, &str)] = &[
+    ("prompt", "model"),
+    ("image", "model"),
+    ("chat", "model"),
+    ("lyrics-art", "enhancer-model"),
+    ("serve", "enhancer-model"),
+];
+const TRANSCRIPTION_KEYS: &[(&str, &str)] = &[
+    ("transcribe", "model"),
+    ("describe", "model"),
+    ("lyrics-art", "transcription-model"),
+    ("serve", "transcription-model"),
+];
+
+/// The name a preset has on the command line, e.g. "gemma-e2b".
+fn preset_name<P: ValueEnum>(preset: P) -> String {
+    preset
+        .to_possible_value()
+        .expect("presets are never skipped")
+        .get_name()
+        .to_string()
+}
+
+/// The largest presets that fit in `available` bytes (`None`: unknown, so
+/// the defaults).  Without a token the enhancer is an ungated one, since
+/// Gemma can't be downloaded; transcription has no ungated preset.
+pub fn recommend(available: Option<u64>, has_token: bool) -> Recommendation {
+    let opts = LoadOptions::default();
+    let fits = |bytes: u64| available.is_none_or(|available| bytes <= available);
+    let enhancers: &[EnhancerModel] = if has_token {
+        &[EnhancerModel::GemmaE4b, EnhancerModel::GemmaE2b]
+    } else {
+        &[EnhancerModel::Phi35Mini, EnhancerModel::Qwen25_1_5b]
+    };
+    let enhancer = enhancers
+        .iter()
+        .copied()
+        .find(|preset| fits(preset.required_bytes(&opts)))
+        .unwrap_or(enhancers[enhancers.len() - 1]);
+    let transcription = [TranscriptionModel::GemmaE4b, TranscriptionModel::GemmaE2b]
+        .into_iter()
+        .find(|preset| fits(preset.required_bytes(&opts)))
+        .unwrap_or(TranscriptionModel::FALLBACK);
+    Recommendation {
+        enhancer,
+        transcription,
+    }
+}
+
+impl Recommendation {
+    /// The repos these presets download, each once.
+    pub fn cached_models(&self) -> Vec<CachedModel> {
+        let mut models = vec![CachedModel::from(self.enhancer)];
+        let transcription = CachedModel::from(self.transcription);
+        if !models.contains(&transcription) {
+            models.push(transcription);
+        }
+        models
+    }
+
+    /// The `(section, key, value)` settings that make these presets the
+    /// defaults.
+    pub fn settings(&self) -> Vec<(&'static str, &'static str, toml::Value)> {
+        let enhancer = toml::Value::String(preset_name(self.enhancer));
+        let transcription = toml::Value::String(preset_name(self.transcription));
+        ENHANCER_KEYS
+            .iter()
+            .map(|&(section, key)| (section, key, enhancer.clone()))
+            .chain(
+                TRANSCRIPTION_KEYS
+                    .iter()
+                    .map(|&(section, key)| (section, key, transcription.clone())),
+            )
+            .collect()
+    }
+}
+
+impl fmt::Display for Recommendation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Enhancer       : {} — {}, {}",
+            preset_name(self.enhancer),
+            self.enhancer,
+            self.enhancer.approx_memory()
+        )?;
+        write!(
+            f,
+            "Transcription  : {} — {}, {}",
+            preset_name(self.transcription),
+            self.transcription,
+            self.transcription.approx_memory()
+        )
+    }
+}
+
+// ── CLI entry-point ──────────────────────────────────────────────────────────
+
+/// `setup` flags.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SetupOptions {
+    /// Don't ask: write the config, and download only with `prefetch`.
+    pub yes: bool,
+    /// Download the recommended presets without asking.
+    pub prefetch: bool,
+}
+
+/// Machine-readable result of `setup`, printed in `--json` mode.
+#[derive(Serialize)]
+struct SetupReport<'a> {
+    checks: &'a [CheckResult],
+    enhancer: String,
+    transcription: String,
+    /// Presets downloaded during this run.
+    prefetched: &'a [CachedModel],
+    /// The config file written; `None` with `--dry-run` or when declined.
+    config_written: Option<&'a Path>,
+}
+
+/// Ask a yes/no question on stderr; an empty answer means `default`.
+fn confirm(question: &str, default: bool) -> Result<bool> {
+    let hint = if default { "[Y/n]" } else { "[y/N]" };
+    eprint!("{question} {hint} ");
+    std::io::stderr().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(match answer.trim() {
+        "" => default,
+        answer => matches!(answer, "y" | "Y" | "yes"),
+    })
+}
+
+/// Run every check, recommend presets, offer to download them and write
+/// them to the user config file.
+///
+/// Interactive unless `--yes`; `--dry-run` only checks and recommends.
+/// Fails after reporting when any check failed, so scripts can use
+/// `setup --yes` (or `--dry-run`) as a validation step.
+pub async fn run(options: SetupOptions, dry_run: bool, out: OutputConfig) -> Result<()> {
+    let interactive = !options.yes && !dry_run;
+    if interactive && !std::io::stdin().is_terminal() {
+        anyhow::bail!(
+            "setup asks before downloading or writing the config; pass --yes to accept its \
+             recommendations, or --dry-run to only run the checks"
+        );
+    }
+    let config_path = config::user_config_path()
+        .context("Can't locate the user config directory; set HOME or XDG_CONFIG_HOME")?;
+
+    let token = check_hf_token();
+    let recommendation = recommend(memory::available_bytes(), token.status == CheckStatus::Ok);
+    let presets = recommendation.cached_models();
+    let mut checks = vec![token, check_memory(), check_device(), check_cache(&presets)];
+
+    if !out.json {
+        println!("Checks");
+        for check in &checks {
+            println!("  {}", check.to_string().replace('\n', "\n  "));
+        }
+        println!("\nRecommended presets");
+        println!("  {}", recommendation.to_string().replace('\n', "\n  "));
+    }
+
+    // ── Download ────────────────────────────────────────────────────────
+    let missing: Vec<CachedModel> = presets
+        .iter()
+        .copied()
+        .filter(|&preset| model_cache::scan_preset(preset).state != CacheState::Complete)
+        .collect();
+    let download = !dry_run
+        && !missing.is_empty()
+        && (options.prefetch
+            || (interactive && {
+                let names: Vec<&str> = missing.iter().map(|preset| preset.name()).collect();
+                println!();
+                confirm(&format!("Download {} now?", names.join(" and ")), false)?
+            }));
+    let mut prefetched = Vec::new();
+    if download {
+        let mut result = None;
+        for preset in missing {
+            status!(out);
+            if let Err(err) = model_cache::download(preset, out).await {
+                result = Some(CheckResult::fail(
+                    "cache",
+                    format!("downloading {} failed", preset.name()),
+                    format!("{err:#}"),
+                ));
+                break;
+            }
+            prefetched.push(preset);
+        }
+        let result = result.unwrap_or_else(|| check_cache(&presets));
+        if !out.json {
+            println!("\n  {}", result.to_string().replace('\n', "\n  "));
+        }
+        // The cache check is always last.
+        *checks.last_mut().expect("checks always run") = result;
+    }
+
+    // ── Config ──────────────────────────────────────────────────────────
+    let write = !dry_run
+        && (!interactive || {
+            println!();
+            confirm(
+                &format!(
+                    "Make these presets the defaults in {}?",
+                    config_path.display()
+                ),
+                true,
+            )?
+        });
+    if write {
+        config::write_settings(&config_path, &recommendation.settings())?;
+    }
+    let config_written = write.then_some(config_path.as_path());
+
+    let failed = checks
+        .iter()
+        .filter(|check| check.status == CheckStatus::Fail)
+        .count();
+    if out.json {
+        ui::print_json(&SetupReport {
+            checks: &checks,
+            enhancer: preset_name(recommendation.enhancer),
+            transcription: preset_name(recommendation.transcription),
+            prefetched: &prefetched,
+            config_written,
+        })?;
+    } else {
+        if let Some(path) = config_written {
+            println!(
+                "\nDefaults written to {} (see `config show`).",
+                path.display()
+            );
+        }
+        if failed == 0 {
+            println!(
+                "\nReady. Try: cargo run --release -- image --seed-prompt \"lonely astronaut\""
+            );
+        }
+    }
+    if failed > 0 {
+        anyhow::bail!(
+            "{failed} setup check{} failed; follow the steps above and run setup again",
+            if failed == 1 { "" } else { "s" }
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,167 @@
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::path::Path;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::{MetadataOptions, StandardTagKey, Tag, Value};
+use symphonia::core::probe::Hint;
+
+// ── Limits ───────────────────────────────────────────────────────────────────
+
+/// Longest a single tag value may be, in characters, before it is cut.
+///
+/// Real titles and artist names are far shorter; anything longer is usually
+/// a lyrics or comment blob stored in the wrong frame.
+pub const MAX_TAG_CHARS: usize = 120;
+
+// ── Song tags ────────────────────────────────────────────────────────────────
+
+/// The tags that seed a prompt, already sanitized.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SongTags {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub genre: Option<String>,
+}
+
+impl SongTags {
+    /// Pick title, artist and genre out of a list of tags.
+    ///
+    /// The first usable value for each key wins; values that sanitize to
+    /// nothing are skipped so a later tag can fill the field.
+    pub fn from_tags<'a>(tags: impl IntoIterator<Item = &'a Tag>) -> Self {
+        let mut song = Self::default();
+        for tag in tags {
+            let field = match tag.std_key {
+                Some(StandardTagKey::TrackTitle) => &mut song.title,
+                Some(StandardTagKey::Artist) => &mut song.artist,
+                Some(StandardTagKey::Genre) => &mut song.genre,
+                _ => continue,
+            };
+            if field.is_none() {
+                *field = tag_text(&tag.value).and_then(|text| sanitize(&text));
+            }
+        }
+        song
+    }
+
+    /// Fill any field still missing from `other`.
+    pub fn or(self, other: Self) -> Self {
+        Self {
+            title: self.title.or(other.title),
+            artist: self.artist.or(other.artist),
+            genre: self.genre.or(other.genre),
+        }
+    }
+
+    /// `"<title> by <artist>, <genre>"`, leaving out whatever is missing.
+    ///
+    /// `None` without a title: an artist and genre alone say too little
+    /// about the song to seed a prompt.
+    pub fn seed(&self) -> Option<String> {
+        let mut seed = self.title.clone()?;
+        if let Some(artist) = &self.artist {
+            seed.push_str(" by ");
+            seed.push_str(artist);
+        }
+        if let Some(genre) = &self.genre {
+            seed.push_str(", ");
+            seed.push_str(genre);
+        }
+        Some(seed)
+    }
+}
+
+/// The text of a tag value; binary values (cover art, blobs) have none.
+fn tag_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(text.clone()),
+        Value::Binary(_) | Value::Flag => None,
+        other => Some(other.to_string()),
+    }
+}
+
+/// Make a tag value safe to put in a prompt.
+///
+/// Drops control characters and U+FFFD (what lossy decoding of a
+/// non-UTF-8 tag leaves behind), collapses runs of whitespace, and cuts
+/// the result to [`MAX_TAG_CHARS`] on a word boundary where possible.
+/// `None` when nothing is left.
+pub fn sanitize(value: &str) -> Option<String> {
+    let cleaned: String = value
+        .chars()
+        .map(|c| if c.is_whitespace() { ' ' } else { c })
+        .filter(|&c| !c.is_control() && c != char::REPLACEMENT_CHARACTER)
+        .collect();
+    let collapsed = cleaned.split_whitespace().collect::<Vec<_>>().join(" ");
+    if collapsed.is_empty() {
+        return None;
+    }
+    if collapsed.chars().count() <= MAX_TAG_CHARS {
+        return Some(collapsed);
+    }
+
+    let cut: String = collapsed.chars().take(MAX_TAG_CHARS).collect();
+    let cut = match cut.rfind(' ') {
+        Some(space) if space > 0 => &cut[..space],
+        _ => &cut,
+    };
+    Some(cut.trim_end_matches([',', ';', ':', '-', ' ']).to_string())
+}
+
+// ── Reading files ────────────────────────────────────────────────────────────
+
+/// Read title, artist and genre from an audio file's ID3, Vorbis comment
+/// or RIFF INFO tags.
+///
+/// Tags found while probing (ID3v2 ahead of an MP3 stream) and tags in
+/// the container itself are merged, container first. A file with no tags
+/// gives an empty [`SongTags`], not an error; only a file that can't be
+/// opened or isn't a recognised audio format is.
+pub fn read(path: &Path) -> Result<SongTags> {
+    let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+    let mut probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .with_context(|| format!("{} is not a readable audio file", path.display()))?;
+
+    let container = probed
+        .format
+        .metadata()
+        .current()
+        .map(|rev| SongTags::from_tags(rev.tags()))
+        .unwrap_or_default();
+    let outside = probed
+        .metadata
+        .get()
+        .and_then(|meta| meta.current().map(|rev| SongTags::from_tags(rev.tags())))
+        .unwrap_or_default();
+    Ok(container.or(outside))
+}
+
+/// The seed prompt for `path`, and whether it fell back to the file name.
+///
+/// Uses [`SongTags::seed`] when the file has a title tag, otherwise the
+/// file stem with `_` and `-` read as spaces (`my_song-final.mp3` →
+/// `my song final`).
+pub fn seed_for(tags: &SongTags, path: &Path) -> (String, bool) {
+    if let Some(seed) = tags.seed() {
+        return (seed, false);
+    }
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().replace(['_', '-'], " "))
+        .and_then(|s| sanitize(&s))
+        .unwrap_or_else(|| "untitled song".to_string());
+    (stem, true)
+}
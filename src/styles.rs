@@ -0,0 +1,177 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::prompt_limits::{MAX_PROMPT_WORDS, truncate_to_words};
+
+// ── Built-in presets ─────────────────────────────────────────────────────────
+
+/// Styles available without a `styles.toml`: `(name, suffix, description)`.
+const BUILTIN_STYLES: &[(&str, &str, &str)] = &[
+    (
+        "watercolor",
+        "watercolor painting, soft light, loose washes, paper texture",
+        "Soft watercolor washes",
+    ),
+    (
+        "cyberpunk",
+        "gritty cyberpunk, neon lights, rain-slicked streets, high contrast",
+        "Neon-lit cyberpunk city",
+    ),
+    (
+        "noir",
+        "black and white film noir, hard shadows, venetian blinds, 1940s",
+        "Monochrome film noir",
+    ),
+    (
+        "ukiyo-e",
+        "ukiyo-e woodblock print, flat colors, bold outlines, Edo period",
+        "Japanese woodblock print",
+    ),
+    (
+        "raden-saleh",
+        "in the style of Raden Saleh, romantic oil painting, dramatic sky",
+        "Javanese romanticism",
+    ),
+    (
+        "album-cover",
+        "moody cinematic album cover, centered subject, strong silhouette",
+        "Square-friendly cover art",
+    ),
+];
+
+/// Longest suffix a style may have, so the seed always keeps at least
+/// `MAX_PROMPT_WORDS - MAX_STYLE_WORDS` words.
+pub const MAX_STYLE_WORDS: usize = 20;
+
+// ── StylePreset ──────────────────────────────────────────────────────────────
+
+/// A named suffix composed with the seed before enhancement (`--style`).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StylePreset {
+    pub name: String,
+    /// Words appended to the seed, e.g. "watercolor, soft light".
+    pub suffix: String,
+    pub description: Option<String>,
+}
+
+impl StylePreset {
+    fn new(name: &str, suffix: &str, description: Option<String>) -> Result<Self> {
+        let suffix = suffix.trim();
+        if suffix.is_empty() {
+            anyhow::bail!("Style `{name}` has an empty suffix");
+        }
+        let words = suffix.split_whitespace().count();
+        if words > MAX_STYLE_WORDS {
+            anyhow::bail!(
+                "Style `{name}` has {words} words; at most {MAX_STYLE_WORDS} are allowed"
+            );
+        }
+        Ok(Self {
+            name: name.to_string(),
+            suffix: suffix.to_string(),
+            description,
+        })
+    }
+
+    /// `"<seed>, <suffix>"`.  The seed is cut so the result stays within
+    /// [`MAX_PROMPT_WORDS`]; the style's own words are always kept.
+    pub fn compose(&self, seed: &str) -> String {
+        let budget = MAX_PROMPT_WORDS.saturating_sub(self.suffix.split_whitespace().count());
+        let seed = truncate_to_words(seed.trim(), budget);
+        let seed = seed.trim_end_matches(|c: char| c.is_whitespace() || ",;".contains(c));
+        if seed.is_empty() {
+            self.suffix.clone()
+        } else {
+            format!("{seed}, {}", self.suffix)
+        }
+    }
+}
+
+impl fmt::Display for StylePreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.name, self.suffix)
+    }
+}
+
+// ── User file ────────────────────────────────────────────────────────────────
+
+/// One table in `styles.toml`:
+///
+/// ```toml
+/// [pastel]
+/// suffix = "pastel colors, soft focus, dreamy"
+/// description = "Soft pastel dream"   # optional
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StyleEntry {
+    suffix: String,
+    description: Option<String>,
+}
+
+/// `~/.config/mistralrs-example/styles.toml`, or under `XDG_CONFIG_HOME`
+/// when set.
+pub fn user_styles_path() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => {
+            let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+            PathBuf::from(home).join(".config")
+        }
+    };
+    Some(base.join("mistralrs-example/styles.toml"))
+}
+
+/// Parse the contents of a `styles.toml`.
+pub fn parse_styles(text: &str) -> Result<Vec<StylePreset>> {
+    let entries: BTreeMap<String, StyleEntry> = toml::from_str(text)?;
+    entries
+        .into_iter()
+        .map(|(name, entry)| StylePreset::new(&name, &entry.suffix, entry.description))
+        .collect()
+}
+
+fn load_user_styles(path: &Path) -> Result<Vec<StylePreset>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    parse_styles(&text).with_context(|| format!("Invalid style file: {}", path.display()))
+}
+
+// ── Lookup ───────────────────────────────────────────────────────────────────
+
+/// Every available style, sorted by name.  A user style with a built-in's
+/// name replaces it.
+pub fn all_styles() -> Result<Vec<StylePreset>> {
+    let mut styles: BTreeMap<String, StylePreset> = BTreeMap::new();
+    for (name, suffix, description) in BUILTIN_STYLES {
+        let style = StylePreset::new(name, suffix, Some(description.to_string()))?;
+        styles.insert(style.name.clone(), style);
+    }
+    if let Some(path) = user_styles_path().filter(|path| path.exists()) {
+        for style in load_user_styles(&path)? {
+            styles.insert(style.name.clone(), style);
+        }
+    }
+    Ok(styles.into_values().collect())
+}
+
+/// Look up `--style <name>`; an unknown name lists the available ones.
+pub fn find(name: &str) -> Result<StylePreset> {
+    let styles = all_styles()?;
+    if let Some(style) = styles.iter().find(|style| style.name == name) {
+        return Ok(style.clone());
+    }
+    let names: Vec<&str> = styles.iter().map(|style| style.name.as_str()).collect();
+    anyhow::bail!(
+        "Unknown style `{name}`. Available styles: {}.\n\
+         Add your own in {}.",
+        names.join(", "),
+        user_styles_path().map_or_else(
+            || "styles.toml".to_string(),
+            |path| path.display().to_string()
+        ),
+    )
+}
@@ -0,0 +1,566 @@
+use anyhow::{Context, Result};
+use mistralrs::{Model, RequestBuilder, TextMessageRole};
+use serde::Serialize;
+use std::fmt;
+use std::io::{IsTerminal, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::load_options::LoadOptions;
+use crate::local_model::LocalModel;
+use crate::memory;
+use crate::memstats::{LoadMemory, MemorySnapshot};
+use crate::promp_enhancer::EnhancerModel;
+use crate::request_queue::RequestQueue;
+use crate::sampler::{SamplerArgs, SamplerConfig};
+use crate::timeout;
+use crate::ui::{self, OutputConfig, detail, fmt_duration, status};
+use crate::usage::Usage;
+
+// ── Constants ────────────────────────────────────────────────────────────────
+
+/// Longest summary `--max-words` allows by default.
+pub const DEFAULT_MAX_WORDS: usize = 80;
+
+/// Texts longer than this many words are summarized in chunks first
+/// (`--chunk-words`).  Small enough for the 1.5B presets to keep track of.
+pub const DEFAULT_CHUNK_WORDS: usize = 800;
+
+/// Smallest `--chunk-words`: below this a chunk says too little to
+/// summarize.
+pub const MIN_CHUNK_WORDS: usize = 50;
+
+/// Default sampling: low temperature so the summary sticks to the text.
+/// `max_len` is raised per request to fit the word budget.
+pub const DEFAULT_SAMPLER: SamplerConfig = SamplerConfig::new(0.3, 0.9, 256);
+
+const SYSTEM_PROMPT: &str = "You summarize text faithfully, without adding anything that is \
+not in it. Output ONLY the summary, with no preamble, title or commentary.";
+
+// ── Summary styles ───────────────────────────────────────────────────────────
+
+/// Shape of the summary (`summarize --style`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SummaryStyle {
+    /// Short bullet points, one per line.
+    Bullet,
+    /// One paragraph of prose.
+    #[default]
+    Paragraph,
+    /// The central theme, mood and imagery, e.g. of song lyrics: the
+    /// input for an image prompt.
+    Theme,
+}
+
+impl SummaryStyle {
+    /// The instruction sent with the text.
+    fn instruction(self, max_words: usize) -> String {
+        match self {
+            Self::Bullet => format!(
+                "Summarize the text below as short bullet points, one per line, each starting \
+                 with \"- \". Use at most {max_words} words in total."
+            ),
+            Self::Paragraph => {
+                format!("Summarize the text below in one paragraph of at most {max_words} words.")
+            }
+            Self::Theme => format!(
+                "Describe the central theme, mood and imagery of the text below (for example \
+                 song lyrics) in one paragraph of at most {max_words} words. Do not quote it."
+            ),
+        }
+    }
+}
+
+impl fmt::Display for SummaryStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Bullet => "bullet",
+            Self::Paragraph => "paragraph",
+            Self::Theme => "theme",
+        })
+    }
+}
+
+// ── Chunking ─────────────────────────────────────────────────────────────────
+
+/// Whitespace-separated words in `text`.
+pub fn word_count(text: &str) -> usize {
+    text.split_whitespace().count()
+}
+
+/// Split `text` into chunks of at most `max_words` words, for summarizing
+/// piece by piece.
+///
+/// Chunks break between lines, never inside one, unless a single line is
+/// longer than `max_words`; that line is cut into runs of `max_words`
+/// words.  Once a chunk is at least half full, a blank line (a paragraph
+/// or stanza break) ends it, so chunks tend to hold whole stanzas.  Line
+/// breaks inside a chunk are kept; blank lines are not.  `max_words` is
+/// taken as at least 1.
+pub fn chunk_text(text: &str, max_words: usize) -> Vec<String> {
+    let max_words = max_words.max(1);
+    let mut chunks = Vec::new();
+    let mut current: Vec<String> = Vec::new();
+    let mut words = 0;
+    let mut flush = |current: &mut Vec<String>, words: &mut usize| {
+        if !current.is_empty() {
+            chunks.push(current.join("\n"));
+            current.clear();
+        }
+        *words = 0;
+    };
+    for line in text.lines().map(str::trim) {
+        let count = word_count(line);
+        if count == 0 {
+            if words * 2 >= max_words {
+                flush(&mut current, &mut words);
+            }
+            continue;
+        }
+        if count > max_words {
+            flush(&mut current, &mut words);
+            let line_words: Vec<&str> = line.split_whitespace().collect();
+            for run in line_words.chunks(max_words) {
+                current.push(run.join(" "));
+                words = run.len();
+                if words == max_words {
+                    flush(&mut current, &mut words);
+                }
+            }
+            continue;
+        }
+        if words + count > max_words {
+            flush(&mut current, &mut words);
+        }
+        current.push(line.to_string());
+        words += count;
+    }
+    flush(&mut current, &mut words);
+    chunks
+}
+
+/// Cut `text` to at most `max_words` words, keeping its line breaks: the
+/// model's bullet lists stay lists.
+pub fn cap_words(text: &str, max_words: usize) -> String {
+    let mut kept = Vec::new();
+    let mut words = 0;
+    for line in text.lines() {
+        let count = word_count(line);
+        if words + count <= max_words {
+            kept.push(line.trim_end().to_string());
+            words += count;
+            continue;
+        }
+        let rest: Vec<&str> = line.split_whitespace().take(max_words - words).collect();
+        // A lone bullet marker is not worth keeping.
+        if rest
+            .iter()
+            .flat_map(|word| word.chars())
+            .any(char::is_alphanumeric)
+        {
+            kept.push(rest.join(" "));
+        }
+        break;
+    }
+    kept.join("\n").trim().to_string()
+}
+
+/// Tokens to allow for a reply of `words` words, with room for the
+/// tokenizer splitting words and for bullet markers.
+fn tokens_for(words: usize) -> usize {
+    words * 2 + 32
+}
+
+// ── Summarizer ───────────────────────────────────────────────────────────────
+
+/// Summarizes text with one of the [`EnhancerModel`] text presets.
+///
+/// Texts longer than [`chunk_words`](Self::with_chunk_words) are
+/// map-reduced: split with [`chunk_text`], each chunk summarized on its
+/// own, and the joined chunk summaries summarized again (repeating while
+/// they are still too long).  The final pass uses the requested
+/// [`SummaryStyle`] and word limit.
+///
+/// Like [`PromptEnhancer`](crate::promp_enhancer::PromptEnhancer), clones
+/// share the model and the [`RequestQueue`], and a model loaded for
+/// enhancement can be reused via [`from_model`](Self::from_model).
+#[derive(Clone)]
+pub struct Summarizer {
+    model: Arc<Model>,
+    sampler: SamplerConfig,
+    chunk_words: usize,
+    /// Limit per request: `--timeout`, if given.
+    timeout: Option<Duration>,
+    /// Bounds the requests in flight; chunks are summarized concurrently
+    /// up to its limit.
+    queue: RequestQueue,
+}
+
+impl Summarizer {
+    /// Load an [`EnhancerModel`] preset with `--isq` / `--dtype` overrides
+    /// applied.
+    pub async fn from_preset_with_options(
+        preset: EnhancerModel,
+        opts: &LoadOptions,
+    ) -> Result<Self> {
+        Ok(Self::from_model(Arc::new(preset.build_model(opts).await?)))
+    }
+
+    /// Load a model from a local GGUF file or HuggingFace-layout directory
+    /// instead of a preset, for offline use.
+    pub async fn from_local_path(path: &Path, opts: &LoadOptions) -> Result<Self> {
+        let model = LocalModel::from_path(path)?.build(opts).await?;
+        Ok(Self::from_model(Arc::new(model)))
+    }
+
+    /// Build a `Summarizer` around an already-loaded text model.
+    pub fn from_model(model: Arc<Model>) -> Self {
+        Self {
+            model,
+            sampler: DEFAULT_SAMPLER,
+            chunk_words: DEFAULT_CHUNK_WORDS,
+            timeout: timeout::default_limit(),
+            queue: RequestQueue::default(),
+        }
+    }
+
+    /// Override the sampling settings (default [`DEFAULT_SAMPLER`]).  Each
+    /// request still gets at least enough tokens for its word budget.
+    pub fn with_sampler(mut self, sampler: SamplerConfig) -> Self {
+        self.sampler = sampler;
+        self
+    }
+
+    /// Summarize texts longer than `words` words in chunks of that size
+    /// (default [`DEFAULT_CHUNK_WORDS`], at least [`MIN_CHUNK_WORDS`]).
+    pub fn with_chunk_words(mut self, words: usize) -> Self {
+        self.chunk_words = words.max(MIN_CHUNK_WORDS);
+        self
+    }
+
+    /// Words each chunk summary may use: enough for the final pass to
+    /// work with, while two of them still fit in a chunk so every round
+    /// shrinks the text.
+    fn partial_words(&self, max_words: usize) -> usize {
+        (self.chunk_words / 4)
+            .max(max_words)
+            .min(self.chunk_words / 2)
+    }
+
+    /// Summarize `text` in `style` in at most `max_words` words.
+    ///
+    /// The model is asked to respect the limit, and a longer reply is cut
+    /// to it with [`cap_words`].
+    pub async fn summarize(
+        &self,
+        text: &str,
+        style: SummaryStyle,
+        max_words: usize,
+    ) -> Result<SummaryResult> {
+        if max_words == 0 {
+            anyhow::bail!("--max-words must be at least 1");
+        }
+        let input_words = word_count(text);
+        if input_words == 0 {
+            anyhow::bail!("Nothing to summarize: the text is empty");
+        }
+
+        let start = Instant::now();
+        let mut usage = Usage::default();
+        let mut requests = 0;
+        let mut chunk_counts = Vec::new();
+        let mut current = text.trim().to_string();
+        while word_count(&current) > self.chunk_words {
+            let chunks = chunk_text(&current, self.chunk_words);
+            let partial_words = self.partial_words(max_words);
+            let (parts, part_usage) = self.summarize_chunks(&chunks, partial_words).await?;
+            usage += part_usage;
+            requests += chunks.len();
+            chunk_counts.push(chunks.len());
+            current = parts.join("\n\n");
+        }
+
+        let (reply, final_usage) = self
+            .request(&style.instruction(max_words), &current, max_words)
+            .await?;
+        usage += final_usage;
+        requests += 1;
+        let text = cap_words(&reply, max_words);
+        if text.is_empty() {
+            anyhow::bail!("The model returned an empty summary");
+        }
+        Ok(SummaryResult {
+            text,
+            style,
+            input_words,
+            chunk_counts,
+            requests,
+            usage,
+            duration: start.elapsed(),
+        })
+    }
+
+    /// Summarize each of `chunks` in at most `words` words, keeping up to
+    /// the queue's limit in flight, and return the summaries in order with
+    /// their combined usage.
+    async fn summarize_chunks(
+        &self,
+        chunks: &[String],
+        words: usize,
+    ) -> Result<(Vec<String>, Usage)> {
+        let mut tasks = tokio::task::JoinSet::new();
+        for (index, chunk) in chunks.iter().enumerate() {
+            let summarizer = self.clone();
+            let chunk = chunk.clone();
+            let instruction = format!(
+                "This is part {} of {} of a longer text. Summarize it in at most {words} words, \
+                 keeping its key points, names, imagery and mood, so the parts can be combined \
+                 into one summary.",
+                index + 1,
+                chunks.len()
+            );
+            tasks.spawn(async move {
+                let result = summarizer.request(&instruction, &chunk, words).await;
+                (index, result)
+            });
+        }
+        let mut parts: Vec<Option<String>> = vec![None; chunks.len()];
+        let mut usage = Usage::default();
+        while let Some(joined) = tasks.join_next().await {
+            let (index, result) = joined.context("Summarization task failed")?;
+            let (reply, part_usage) = result?;
+            usage += part_usage;
+            parts[index] = Some(cap_words(&reply, words));
+        }
+        Ok((parts.into_iter().flatten().collect(), usage))
+    }
+
+    /// Send `instruction` and `text` in one request with room for `words`
+    /// words, and return the trimmed reply.
+    async fn request(
+        &self,
+        instruction: &str,
+        text: &str,
+        words: usize,
+    ) -> Result<(String, Usage)> {
+        let sampler = self
+            .sampler
+            .clone()
+            .with_max_len(self.sampler.max_len.max(tokens_for(words)));
+        let request = sampler
+            .apply(RequestBuilder::new())
+            .add_message(TextMessageRole::System, SYSTEM_PROMPT)
+            .add_message(
+                TextMessageRole::User,
+                format!("{instruction}\n\nText:\n{text}"),
+            );
+
+        let _permit = self.queue.acquire().await;
+        let start = Instant::now();
+        let response = timeout::within(
+            self.timeout,
+            "Summarization",
+            self.model.send_chat_request(request),
+        )
+        .await?;
+        let usage = Usage::from_response(&response.usage, start.elapsed());
+        let reply = response.choices[0]
+            .message
+            .content
+            .as_ref()
+            .map(|c| sampler.trim_stop(c).trim().to_string())
+            .unwrap_or_default();
+        Ok((reply, usage))
+    }
+}
+
+// ── SummaryResult ────────────────────────────────────────────────────────────
+
+/// A summary with what it took to produce it.
+#[derive(Clone, Debug, Serialize)]
+pub struct SummaryResult {
+    /// The summary, at most the requested number of words.
+    pub text: String,
+    pub style: SummaryStyle,
+    /// Words in the text summarized.
+    pub input_words: usize,
+    /// Chunks summarized in each map round before the final pass; empty
+    /// when the text fit in one request.
+    pub chunk_counts: Vec<usize>,
+    /// Requests sent, the final pass included.
+    pub requests: usize,
+    /// Tokens processed and generated over every request.
+    pub usage: Usage,
+    /// Wall-clock time of all requests.
+    #[serde(rename = "duration_secs", serialize_with = "serialize_secs")]
+    pub duration: Duration,
+}
+
+fn serialize_secs<S: serde::Serializer>(d: &Duration, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_f64(d.as_secs_f64())
+}
+
+impl fmt::Display for SummaryResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "── Summary ({}) ──", self.style)?;
+        writeln!(f, "{}", self.text)?;
+        writeln!(f, "───────────────────")?;
+        writeln!(
+            f,
+            "Words          : {} → {}",
+            self.input_words,
+            word_count(&self.text)
+        )?;
+        if !self.chunk_counts.is_empty() {
+            let rounds: Vec<String> = self.chunk_counts.iter().map(usize::to_string).collect();
+            writeln!(
+                f,
+                "Chunks         : {} (per round), then one final pass",
+                rounds.join(" → ")
+            )?;
+        }
+        writeln!(f, "Time           : {}", fmt_duration(self.duration))?;
+        write!(f, "Tokens         : {}", self.usage)
+    }
+}
+
+// ── CLI entry-point ──────────────────────────────────────────────────────────
+
+/// `summarize` settings chosen on the command line.
+#[derive(Clone, Debug, Default)]
+pub struct SummarizeOptions {
+    /// File to summarize, `-` for stdin.
+    pub input: Option<PathBuf>,
+    /// `--text`: the text itself.
+    pub text: Option<String>,
+    pub style: SummaryStyle,
+    pub max_words: usize,
+    pub chunk_words: usize,
+    /// `--temperature` / `--top-p` / `--max-tokens` overrides.
+    pub sampler: SamplerArgs,
+}
+
+/// Machine-readable result of `summarize`, printed in `--json` mode.
+#[derive(Serialize)]
+struct SummarizeReport {
+    /// HuggingFace ID (or local path) of the model used.
+    model: String,
+    /// The file, "stdin" or "--text".
+    source: String,
+    #[serde(flatten)]
+    summary: SummaryResult,
+    load_secs: f64,
+    memory: LoadMemory,
+}
+
+/// The text to summarize and where it came from: `--text`, the file, or
+/// stdin (for `-`, or when nothing is given and stdin is piped).
+fn read_input(input: Option<&Path>, text: Option<String>) -> Result<(String, String)> {
+    if let Some(text) = text {
+        return Ok((text, "--text".to_string()));
+    }
+    match input {
+        Some(path) if path.as_os_str() != "-" => {
+            let text = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            Ok((text, path.display().to_string()))
+        }
+        Some(_) => read_stdin(),
+        None if !std::io::stdin().is_terminal() => read_stdin(),
+        None => anyhow::bail!("Give a FILE to summarize, `-` for stdin, or --text"),
+    }
+}
+
+fn read_stdin() -> Result<(String, String)> {
+    let mut text = String::new();
+    std::io::stdin()
+        .lock()
+        .read_to_string(&mut text)
+        .context("Failed to read the text from stdin")?;
+    Ok((text, "stdin".to_string()))
+}
+
+/// Summarize a file, stdin or `--text` and print the summary (or a JSON
+/// report with `--json`, or just the summary with `--quiet`).
+pub async fn run(
+    model: Option<EnhancerModel>,
+    model_path: Option<PathBuf>,
+    load: LoadOptions,
+    options: SummarizeOptions,
+    out: OutputConfig,
+) -> Result<()> {
+    let SummarizeOptions {
+        input,
+        text,
+        style,
+        max_words,
+        chunk_words,
+        sampler,
+    } = options;
+    if max_words == 0 {
+        anyhow::bail!("--max-words must be at least 1");
+    }
+    if chunk_words < MIN_CHUNK_WORDS {
+        anyhow::bail!("--chunk-words must be at least {MIN_CHUNK_WORDS}");
+    }
+    // Read the text before the model loads, so a missing file fails fast.
+    let (text, source) = read_input(input.as_deref(), text)?;
+    if word_count(&text) == 0 {
+        anyhow::bail!("Nothing to summarize: {source} is empty");
+    }
+
+    let before = MemorySnapshot::capture();
+    let load_start = Instant::now();
+    let (summarizer, model_label) = match &model_path {
+        Some(path) => {
+            status!(out, "Loading summarization model: {}", path.display());
+            let summarizer = Summarizer::from_local_path(path, &load).await?;
+            (summarizer, path.display().to_string())
+        }
+        None => {
+            let (preset, load) = memory::choose(model.unwrap_or_default(), &load)?;
+            status!(out, "Loading summarization model: {preset}");
+            status!(out, "  Memory estimate: {}", preset.memory_estimate(&load));
+            let summarizer = Summarizer::from_preset_with_options(preset, &load).await?;
+            (summarizer, preset.model_id().to_string())
+        }
+    };
+    let summarizer = summarizer
+        .with_sampler(sampler.apply(DEFAULT_SAMPLER))
+        .with_chunk_words(chunk_words);
+    let load_elapsed = load_start.elapsed();
+    let memory = MemorySnapshot::capture().since(before);
+    status!(out, "Model loaded in {}", fmt_duration(load_elapsed));
+    status!(out, "{}\n", memory.banner());
+
+    let chunks = chunk_text(&text, chunk_words).len();
+    if chunks > 1 {
+        status!(
+            out,
+            "Summarizing {source}: {} words in {chunks} chunks",
+            word_count(&text)
+        );
+    } else {
+        status!(out, "Summarizing {source}: {} words", word_count(&text));
+    }
+    let summary = summarizer.summarize(&text, style, max_words).await?;
+    detail!(out, "{} request(s)", summary.requests);
+
+    if out.json {
+        return ui::print_json(&SummarizeReport {
+            model: model_label,
+            source,
+            summary,
+            load_secs: load_elapsed.as_secs_f64(),
+            memory,
+        });
+    }
+    if out.quiet() {
+        println!("{}", summary.text);
+    } else {
+        println!("\n{summary}");
+    }
+    Ok(())
+}
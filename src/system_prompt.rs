@@ -0,0 +1,52 @@
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+
+// ── SystemPromptArgs ─────────────────────────────────────────────────────────
+
+/// `--system-prompt` / `--system-prompt-file`.  Neither keeps the
+/// component's built-in system prompt.
+#[derive(Clone, Debug, Default, PartialEq, Eq, clap::Args)]
+pub struct SystemPromptArgs {
+    /// System prompt to send instead of the built-in one.
+    #[arg(long, value_name = "TEXT", conflicts_with = "system_prompt_file")]
+    pub system_prompt: Option<String>,
+
+    /// Read the system prompt from a UTF-8 text file (one trailing newline
+    /// is dropped).
+    #[arg(long, value_name = "PATH")]
+    pub system_prompt_file: Option<PathBuf>,
+}
+
+impl SystemPromptArgs {
+    /// The custom system prompt, or `None` to keep the default.
+    ///
+    /// Called before any model loads, so a missing file, invalid UTF-8 or
+    /// an empty prompt fails fast instead of sending an empty system turn.
+    pub fn resolve(&self) -> Result<Option<String>> {
+        let (prompt, source) = match (&self.system_prompt, &self.system_prompt_file) {
+            (Some(text), _) => (text.clone(), "--system-prompt".to_string()),
+            (None, Some(path)) => {
+                if !path.exists() {
+                    anyhow::bail!("System prompt file not found: {}", path.display());
+                }
+                let bytes = std::fs::read(path).with_context(|| {
+                    format!("Failed to read system prompt file: {}", path.display())
+                })?;
+                let text = String::from_utf8(bytes).with_context(|| {
+                    format!("System prompt file is not valid UTF-8: {}", path.display())
+                })?;
+                let text = text
+                    .strip_suffix("\r\n")
+                    .or_else(|| text.strip_suffix('\n'))
+                    .unwrap_or(&text)
+                    .to_string();
+                (text, path.display().to_string())
+            }
+            (None, None) => return Ok(None),
+        };
+        if prompt.trim().is_empty() {
+            anyhow::bail!("The system prompt from {source} is empty");
+        }
+        Ok(Some(prompt))
+    }
+}
@@ -0,0 +1,85 @@
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::promp_enhancer::PromptEnhance;
+use crate::prompt_limits::PromptBudget;
+
+// ── Templates ────────────────────────────────────────────────────────────────
+
+/// Detail phrases appended to a seed, one per candidate.  The first is what
+/// [`TemplateEnhancer::enhance`] uses.
+const TEMPLATES: &[&str] = &[
+    "{seed}, highly detailed, dramatic lighting, rich colors, sharp focus, masterpiece",
+    "{seed}, cinematic composition, volumetric light, atmospheric haze, intricate details",
+    "{seed}, soft golden hour light, shallow depth of field, muted palette, fine textures",
+    "{seed}, moody night scene, neon reflections, deep shadows, high contrast",
+    "{seed}, wide establishing shot, epic scale, dynamic sky, painterly brushwork",
+];
+
+/// Style used by [`TemplateEnhancer::prompt_from_lyrics`] when none is given.
+const DEFAULT_LYRICS_STYLE: &str = "moody cinematic album cover";
+
+/// Model-free [`PromptEnhance`]: fills the seed into fixed templates.
+///
+/// Deterministic and instant, so it works offline and gives reproducible
+/// prompts; `--enhancer template` selects it.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TemplateEnhancer {
+    budget: PromptBudget,
+}
+
+impl TemplateEnhancer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cut prompts to `budget`'s window (default [`PromptBudget::CLIP`]).
+    pub fn with_prompt_budget(mut self, budget: PromptBudget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    fn fill(&self, template: &str, seed: &str) -> String {
+        self.budget
+            .truncate(&template.replace("{seed}", seed.trim()))
+    }
+
+    /// The first `n` distinct candidates for `seed`; what
+    /// [`enhance_n`](PromptEnhance::enhance_n) returns, without the async.
+    pub fn candidates(&self, seed: &str, n: usize) -> Vec<String> {
+        let mut candidates: Vec<String> = Vec::with_capacity(n);
+        for template in TEMPLATES.iter().take(n) {
+            let candidate = self.fill(template, seed);
+            if !candidates.contains(&candidate) {
+                candidates.push(candidate);
+            }
+        }
+        candidates
+    }
+}
+
+#[async_trait]
+impl PromptEnhance for TemplateEnhancer {
+    async fn enhance(&self, seed: &str) -> Result<String> {
+        Ok(self.fill(TEMPLATES[0], seed))
+    }
+
+    /// The first `n` templates (at most one per template).
+    async fn enhance_n(&self, seed: &str, n: usize) -> Result<Vec<String>> {
+        Ok(self.candidates(seed, n))
+    }
+
+    /// Builds the scene from the first line of the lyrics that isn't a tag
+    /// like `[inaudible]` or `(chorus)`.
+    async fn prompt_from_lyrics(&self, lyrics: &str, style: Option<&str>) -> Result<String> {
+        let line = lyrics
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with(['[', '(']))
+            .ok_or_else(|| anyhow::anyhow!("The lyrics have no line to build a prompt from"))?;
+        let style = style.unwrap_or(DEFAULT_LYRICS_STYLE);
+        Ok(self.budget.truncate(&format!(
+            "{style} inspired by \"{line}\", evocative scene, dramatic lighting, rich colors"
+        )))
+    }
+}
@@ -0,0 +1,155 @@
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::path::Path;
+
+// ── Word list ────────────────────────────────────────────────────────────────
+
+/// Words `--censor` masks without a `--censor-list`.  Matched as whole
+/// words, case-insensitively; inflections that matter are listed
+/// separately since there is no stemming.
+const DEFAULT_WORDS: &[&str] = &[
+    "arsehole",
+    "asshole",
+    "assholes",
+    "bastard",
+    "bastards",
+    "bitch",
+    "bitches",
+    "bullshit",
+    "cock",
+    "cunt",
+    "cunts",
+    "dick",
+    "dickhead",
+    "fuck",
+    "fucked",
+    "fucker",
+    "fuckers",
+    "fuckin",
+    "fucking",
+    "fucks",
+    "motherfucker",
+    "motherfuckers",
+    "motherfucking",
+    "piss",
+    "pissed",
+    "pussy",
+    "shit",
+    "shits",
+    "shitty",
+    "slut",
+    "whore",
+];
+
+/// Read a `--censor-list` file: one word per line; blank lines and lines
+/// starting with `#` are skipped.
+pub fn read_word_list(path: &Path) -> Result<Vec<String>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read censor list: {}", path.display()))?;
+    Ok(text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+// ── Censor ───────────────────────────────────────────────────────────────────
+
+/// Masks listed words in transcripts: `Shit,` becomes `S***,`.
+///
+/// Only whole words match, so "class" and "Dickens" are left alone.  A
+/// word is a run of letters and digits, with apostrophes allowed inside
+/// (`fuckin'` is matched as `fuckin`).  The first letter keeps its case
+/// and every other character becomes `*`, so the text keeps its length
+/// in characters and its line structure.
+#[derive(Clone, Debug)]
+pub struct Censor {
+    /// Lowercased words to mask.
+    words: HashSet<String>,
+}
+
+impl Default for Censor {
+    /// The built-in word list.
+    fn default() -> Self {
+        Self::empty().with_words(DEFAULT_WORDS.iter().copied())
+    }
+}
+
+impl Censor {
+    /// A censor that masks nothing until words are added.
+    pub fn empty() -> Self {
+        Self {
+            words: HashSet::new(),
+        }
+    }
+
+    /// Also mask `words` (matched case-insensitively).
+    pub fn with_words<S: AsRef<str>>(mut self, words: impl IntoIterator<Item = S>) -> Self {
+        self.words.extend(
+            words
+                .into_iter()
+                .map(|word| word.as_ref().trim().to_lowercase())
+                .filter(|word| !word.is_empty()),
+        );
+        self
+    }
+
+    /// Also mask the words in a `--censor-list` file.
+    pub fn with_list(self, path: &Path) -> Result<Self> {
+        Ok(self.with_words(read_word_list(path)?))
+    }
+
+    /// Mask every listed word in `text`, returning the new text and the
+    /// number of words masked.
+    pub fn apply(&self, text: &str) -> (String, usize) {
+        let mut out = String::with_capacity(text.len());
+        let mut count = 0;
+        let mut rest = text;
+        while let Some(start) = rest.find(is_word_char) {
+            out.push_str(&rest[..start]);
+            let word = &rest[start..];
+            let len = word_len(word);
+            let word = &word[..len];
+            if self.words.contains(&word.to_lowercase()) {
+                out.push_str(&mask(word));
+                count += 1;
+            } else {
+                out.push_str(word);
+            }
+            rest = &rest[start + len..];
+        }
+        out.push_str(rest);
+        (out, count)
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric()
+}
+
+/// Byte length of the word at the start of `text`: word characters, and
+/// apostrophes with a word character on both sides (`don't`, not the
+/// closing quote of `'fuck'`).
+fn word_len(text: &str) -> usize {
+    let mut end = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let joins = matches!(c, '\'' | '’')
+            && end == i
+            && chars.peek().is_some_and(|&(_, next)| is_word_char(next));
+        if is_word_char(c) || joins {
+            end = i + c.len_utf8();
+        } else {
+            break;
+        }
+    }
+    end
+}
+
+/// `Fuck` → `F***`: keep the first character, star the rest.
+fn mask(word: &str) -> String {
+    let mut chars = word.chars();
+    let first = chars.next().map(String::from).unwrap_or_default();
+    first + &"*".repeat(chars.count())
+}
@@ -0,0 +1,80 @@
+use anyhow::Result;
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::ui::fmt_duration;
+
+// ── TimedOut ─────────────────────────────────────────────────────────────────
+
+/// Error returned when an inference request runs past its time limit.
+///
+/// The request future has been dropped by then, so nothing is left waiting
+/// on the reply.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TimedOut {
+    /// What was running, e.g. "Prompt enhancement".
+    pub stage: String,
+    /// How long it was given.
+    pub waited: Duration,
+}
+
+impl fmt::Display for TimedOut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} timed out after {}; the request was abandoned",
+            self.stage,
+            fmt_duration(self.waited)
+        )
+    }
+}
+
+impl std::error::Error for TimedOut {}
+
+/// Whether `err` (or anything in its chain) is [`TimedOut`].
+pub fn is_timed_out(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| cause.is::<TimedOut>())
+}
+
+/// Await `fut`, failing with [`TimedOut`] for `stage` if it takes longer
+/// than `limit`.  Without a limit this is just `fut.await`.
+pub async fn within<T>(
+    limit: Option<Duration>,
+    stage: &str,
+    fut: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    let Some(limit) = limit else {
+        return fut.await;
+    };
+    let started = Instant::now();
+    match tokio::time::timeout(limit, fut).await {
+        Ok(result) => result,
+        Err(_) => Err(TimedOut {
+            stage: stage.to_string(),
+            waited: started.elapsed(),
+        }
+        .into()),
+    }
+}
+
+// ── Default ──────────────────────────────────────────────────────────────────
+
+/// `--timeout` in milliseconds; 0 means no limit.
+static DEFAULT_TIMEOUT_MS: AtomicU64 = AtomicU64::new(0);
+
+/// Limit every inference request made by wrappers built afterwards
+/// (`--timeout`).  `None` removes the limit.
+pub fn set_default(limit: Option<Duration>) {
+    let ms = limit.map_or(0, |d| d.as_millis().clamp(1, u64::MAX as u128) as u64);
+    DEFAULT_TIMEOUT_MS.store(ms, Ordering::Relaxed);
+}
+
+/// The limit set with [`set_default`], which wrappers start with.
+pub fn default_limit() -> Option<Duration> {
+    match DEFAULT_TIMEOUT_MS.load(Ordering::Relaxed) {
+        0 => None,
+        ms => Some(Duration::from_millis(ms)),
+    }
+}
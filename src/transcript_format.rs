@@ -0,0 +1,208 @@
+// ── Text formats ─────────────────────────────────────────────────────────────
+
+/// How `transcribe --text-format` reshapes the model's reply.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum TextFormat {
+    /// Tidy sung lyrics: see [`LyricsFormatter`].
+    Lyrics,
+}
+
+/// Lines shorter than this many characters are merged with a neighbour
+/// unless `--min-line-chars` says otherwise.
+pub const DEFAULT_MIN_LINE_CHARS: usize = 12;
+
+/// Lines up to this many characters lose a trailing period.  Longer ones
+/// read as prose, where the period belongs.
+const SHORT_LINE_CHARS: usize = 60;
+
+// ── LyricsFormatter ──────────────────────────────────────────────────────────
+
+/// Turns the model's one-phrase-per-line transcript of a song into
+/// something that reads like a lyric sheet.
+///
+/// Each line is tidied on its own first, then lines are combined:
+///
+/// 1. Curly quotes become straight ones, en / em dashes `-`, `…` three
+///    dots, and runs of whitespace a single space.
+/// 2. Lines with no letters or digits (a lone `.` or `-`) are dropped, as
+///    is punctuation left at the start of a line (`, and I`).
+/// 3. A single trailing period is removed from short lines; an ellipsis
+///    is kept.
+/// 4. A line repeated back to back is written once with a `(x2)`, `(x3)`,
+///    … count.
+/// 5. Lines shorter than [`min_line_chars`](Self::with_min_line_chars) are
+///    joined to the next line, or to the previous one at the end of a
+///    stanza.  Counted lines are left alone, and lines that repeat once
+///    merged are counted as well.
+///
+/// Blank lines separate stanzas: they are kept (several become one) and
+/// lines are never merged across them.
+#[derive(Clone, Copy, Debug)]
+pub struct LyricsFormatter {
+    min_line_chars: usize,
+}
+
+impl Default for LyricsFormatter {
+    fn default() -> Self {
+        Self {
+            min_line_chars: DEFAULT_MIN_LINE_CHARS,
+        }
+    }
+}
+
+impl LyricsFormatter {
+    /// Merge lines shorter than `chars` characters; 0 turns merging off.
+    pub fn with_min_line_chars(mut self, chars: usize) -> Self {
+        self.min_line_chars = chars;
+        self
+    }
+
+    /// Reformat `text`.
+    pub fn apply(&self, text: &str) -> String {
+        stanzas(text)
+            .iter()
+            .map(|stanza| {
+                let lines: Vec<String> = stanza
+                    .iter()
+                    .map(|line| normalize_punctuation(line))
+                    .filter_map(|line| strip_stray_punctuation(&line).map(str::to_string))
+                    .map(|line| strip_trailing_period(&line).to_string())
+                    .collect();
+                let lines = merge_short_lines(collapse_repeats(&lines), self.min_line_chars);
+                collapse_repeats(&lines).join("\n")
+            })
+            .filter(|stanza| !stanza.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+// ── Line transformations ─────────────────────────────────────────────────────
+
+/// Split `text` into stanzas at blank lines, dropping the blank lines.
+pub fn stanzas(text: &str) -> Vec<Vec<&str>> {
+    let mut stanzas = Vec::new();
+    let mut current = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if !line.is_empty() {
+            current.push(line);
+        } else if !current.is_empty() {
+            stanzas.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.is_empty() {
+        stanzas.push(current);
+    }
+    stanzas
+}
+
+/// Straight quotes, ASCII dashes and dots, single spaces: `“Don’t go…”`
+/// becomes `"Don't go..."`.
+pub fn normalize_punctuation(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    for c in line.chars() {
+        match c {
+            '‘' | '’' | '‚' | '‛' | '′' => out.push('\''),
+            '“' | '”' | '„' | '‟' | '″' => out.push('"'),
+            '–' | '—' | '―' | '‐' | '‑' => out.push('-'),
+            '…' => out.push_str("..."),
+            c if c.is_whitespace() => out.push(' '),
+            c => out.push(c),
+        }
+    }
+    out.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// `line` without punctuation stranded at its start (`, and I` → `and I`);
+/// `None` when nothing but punctuation is left.
+pub fn strip_stray_punctuation(line: &str) -> Option<&str> {
+    if !line.chars().any(char::is_alphanumeric) {
+        return None;
+    }
+    Some(line.trim_start_matches([',', ';', ':', '.', '!', '?', ' ']))
+        .filter(|line| !line.is_empty())
+}
+
+/// `line` without one trailing period when it is short.  `...` is kept.
+pub fn strip_trailing_period(line: &str) -> &str {
+    match line.strip_suffix('.') {
+        Some(rest) if !rest.ends_with('.') && line.chars().count() <= SHORT_LINE_CHARS => {
+            rest.trim_end()
+        }
+        _ => line,
+    }
+}
+
+/// Join each line shorter than `min_chars` characters to the line after
+/// it, repeatedly, so a run of fragments becomes one line.  A short line
+/// with nothing after it is joined to the line before instead.  Lines
+/// with a `(x2)` count are never merged, so the count keeps applying to
+/// the whole line.
+pub fn merge_short_lines(lines: Vec<String>, min_chars: usize) -> Vec<String> {
+    let mut merged: Vec<String> = Vec::with_capacity(lines.len());
+    let mut pending: Option<String> = None;
+    for line in lines {
+        if split_repeat(&line).1 > 1 {
+            if let Some(short) = pending.take() {
+                append_to_last(&mut merged, short);
+            }
+            merged.push(line);
+            continue;
+        }
+        let line = match pending.take() {
+            Some(short) => format!("{short} {line}"),
+            None => line,
+        };
+        if line.chars().count() < min_chars {
+            pending = Some(line);
+        } else {
+            merged.push(line);
+        }
+    }
+    if let Some(short) = pending {
+        append_to_last(&mut merged, short);
+    }
+    merged
+}
+
+/// Join `short` to the last of `lines`, or add it on its own when that
+/// line is counted or there is none.
+fn append_to_last(lines: &mut Vec<String>, short: String) {
+    match lines.last_mut() {
+        Some(last) if split_repeat(last).1 == 1 => {
+            last.push(' ');
+            last.push_str(&short);
+        }
+        _ => lines.push(short),
+    }
+}
+
+/// A line without its `(xN)` count, and the count (1 when there is none).
+pub fn split_repeat(line: &str) -> (&str, usize) {
+    line.strip_suffix(')')
+        .and_then(|rest| rest.rsplit_once(" (x"))
+        .and_then(|(text, count)| Some((text, count.parse().ok().filter(|&n| n > 1)?)))
+        .unwrap_or((line, 1))
+}
+
+/// Write a line repeated back to back once, followed by its count:
+/// `["Hey", "Hey", "Go"]` → `["Hey (x2)", "Go"]`.  Lines only match when
+/// they are identical; counts already written are added up, so
+/// `["Hey (x2)", "Hey"]` gives `["Hey (x3)"]`.
+pub fn collapse_repeats<S: AsRef<str>>(lines: &[S]) -> Vec<String> {
+    let mut collapsed: Vec<(&str, usize)> = Vec::new();
+    for line in lines {
+        let (line, times) = split_repeat(line.as_ref());
+        match collapsed.last_mut() {
+            Some((last, count)) if *last == line => *count += times,
+            _ => collapsed.push((line, times)),
+        }
+    }
+    collapsed
+        .into_iter()
+        .map(|(line, count)| match count {
+            1 => line.to_string(),
+            count => format!("{line} (x{count})"),
+        })
+        .collect()
+}
@@ -0,0 +1,181 @@
+use crate::audio_transcription::Segment;
+
+// ── Output formats ───────────────────────────────────────────────────────────
+
+/// How a transcription is rendered when printed or written to `--output`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Plain transcription text.
+    #[default]
+    Text,
+    /// SubRip subtitles (`HH:MM:SS,mmm`).
+    Srt,
+    /// WebVTT subtitles (`HH:MM:SS.mmm`).
+    Vtt,
+    /// LRC lyrics (`[mm:ss.xx]line`), for music players.
+    Lrc,
+    /// The full result as a JSON object.
+    Json,
+}
+
+impl OutputFormat {
+    /// File extension for this format, used when a batch writes one file
+    /// per input.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Text => "txt",
+            Self::Srt => "srt",
+            Self::Vtt => "vtt",
+            Self::Lrc => "lrc",
+            Self::Json => "json",
+        }
+    }
+}
+
+// ── Subtitle formatting ──────────────────────────────────────────────────────
+
+/// Format seconds as `HH:MM:SS<sep>mmm`, rounding to the nearest millisecond.
+///
+/// SRT uses `,` as the millisecond separator, WebVTT uses `.`.
+pub fn fmt_timestamp(secs: f64, ms_separator: char) -> String {
+    let total_ms = (secs.max(0.0) * 1000.0).round() as u64;
+    let ms = total_ms % 1000;
+    let total_secs = total_ms / 1000;
+    let s = total_secs % 60;
+    let m = (total_secs / 60) % 60;
+    let h = total_secs / 3600;
+    format!("{h:02}:{m:02}:{s:02}{ms_separator}{ms:03}")
+}
+
+/// Render segments as an SRT document.  Segments with empty text are skipped
+/// and cue numbers stay contiguous.
+pub fn format_srt(segments: &[Segment]) -> String {
+    let mut out = String::new();
+    for (i, seg) in cues(segments).enumerate() {
+        out.push_str(&format!(
+            "{}\n{} --> {}\n{}\n\n",
+            i + 1,
+            fmt_timestamp(seg.start_secs, ','),
+            fmt_timestamp(seg.end_secs, ','),
+            srt_text(seg),
+        ));
+    }
+    out
+}
+
+/// Render segments as a WebVTT document.
+pub fn format_vtt(segments: &[Segment]) -> String {
+    let mut out = String::from("WEBVTT\n\n");
+    for seg in cues(segments) {
+        out.push_str(&format!(
+            "{} --> {}\n{}\n\n",
+            fmt_timestamp(seg.start_secs, '.'),
+            fmt_timestamp(seg.end_secs, '.'),
+            vtt_text(seg),
+        ));
+    }
+    out
+}
+
+/// Cue text for SRT, which has no markup for languages: a detected
+/// language is shown as a `[en]` prefix.
+fn srt_text(seg: &Segment) -> String {
+    match &seg.language {
+        Some(language) => format!("[{language}] {}", seg.text.trim()),
+        None => seg.text.trim().to_string(),
+    }
+}
+
+/// Cue text for WebVTT, with a detected language as a `<lang>` span so
+/// players can pick a font or voice for it.
+fn vtt_text(seg: &Segment) -> String {
+    match &seg.language {
+        Some(language) => format!("<lang {language}>{}</lang>", seg.text.trim()),
+        None => seg.text.trim().to_string(),
+    }
+}
+
+/// Segments worth emitting as subtitle cues.
+fn cues(segments: &[Segment]) -> impl Iterator<Item = &Segment> {
+    segments.iter().filter(|seg| !seg.text.trim().is_empty())
+}
+
+// ── LRC ──────────────────────────────────────────────────────────────────────
+
+/// The ID tags at the top of an LRC file; each is left out when unknown.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LrcHeader {
+    /// `[ti:]`, the song title.
+    pub title: Option<String>,
+    /// `[ar:]`, the artist.
+    pub artist: Option<String>,
+    /// `[length:]`, the song length in seconds.
+    pub length_secs: Option<f64>,
+}
+
+/// Format seconds as LRC's `mm:ss.xx`, rounding to the nearest hundredth.
+/// Minutes go past 59 rather than growing an hours field.
+pub fn fmt_lrc_timestamp(secs: f64) -> String {
+    let total_cs = (secs.max(0.0) * 100.0).round() as u64;
+    let cs = total_cs % 100;
+    let s = (total_cs / 100) % 60;
+    let m = total_cs / 6000;
+    format!("{m:02}:{s:02}.{cs:02}")
+}
+
+/// Render segments as an LRC document: the header tags, then one
+/// `[mm:ss.xx]text` entry per line.  A segment of several lines becomes
+/// consecutive entries at its start time; empty lines are skipped.
+pub fn format_lrc(segments: &[Segment], header: &LrcHeader) -> String {
+    let mut out = String::new();
+    if let Some(title) = &header.title {
+        out.push_str(&format!("[ti:{}]\n", lrc_tag_value(title)));
+    }
+    if let Some(artist) = &header.artist {
+        out.push_str(&format!("[ar:{}]\n", lrc_tag_value(artist)));
+    }
+    if let Some(length) = header.length_secs {
+        let secs = length.max(0.0).round() as u64;
+        out.push_str(&format!("[length:{:02}:{:02}]\n", secs / 60, secs % 60));
+    }
+    for seg in cues(segments) {
+        let stamp = fmt_lrc_timestamp(seg.start_secs);
+        for line in seg.text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            out.push_str(&format!("[{stamp}]{line}\n"));
+        }
+    }
+    out
+}
+
+/// A tag value on one line, with brackets (a `]` would end the tag
+/// early) turned into parentheses.
+fn lrc_tag_value(value: &str) -> String {
+    value
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace('[', "(")
+        .replace(']', ")")
+}
+
+/// One segment per non-empty line of `text`, spaced evenly over
+/// `duration_secs`: an approximate timing for LRC when the transcript has
+/// no timestamps of its own.
+pub fn spread_lines(text: &str, duration_secs: f64) -> Vec<Segment> {
+    let lines: Vec<&str> = text
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .collect();
+    let step = duration_secs.max(0.0) / lines.len().max(1) as f64;
+    lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| Segment {
+            start_secs: i as f64 * step,
+            end_secs: (i + 1) as f64 * step,
+            text: line.to_string(),
+            language: None,
+        })
+        .collect()
+}
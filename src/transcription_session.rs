@@ -0,0 +1,276 @@
+use anyhow::Result;
+use mistralrs::{AudioInput, Model, RequestBuilder, TextMessageRole};
+use std::io::{self, Write};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+use crate::cli_chat::{command_arg, spawn_stdin_reader};
+use crate::sampler::SamplerConfig;
+use crate::timeout;
+use crate::usage::Usage;
+
+// ── Constants ────────────────────────────────────────────────────────────────
+
+/// Appended to the transcription system prompt for the whole session, so
+/// follow-up questions get answers rather than another transcript.
+pub const FOLLOW_UP_SYSTEM_NOTE: &str = "After the transcription, the user may ask follow-up \
+questions about the audio or the transcript. Answer them briefly and directly, based on what \
+you heard.";
+
+/// Sampling for follow-up answers: low temperature, room for a paragraph.
+pub const FOLLOW_UP_SAMPLER: SamplerConfig = SamplerConfig::new(0.3, 0.9, 512);
+
+// ── TranscriptionSession ─────────────────────────────────────────────────────
+
+/// One exchange in a [`TranscriptionSession`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Exchange {
+    pub question: String,
+    pub answer: String,
+}
+
+/// A conversation about transcribed audio, for `transcribe --interactive`.
+///
+/// Keeps the request that carried the audio (system prompt, the audio
+/// message and the transcript as the model's reply) and appends each
+/// question and answer to it, so follow-ups are asked with the audio
+/// still in the conversation instead of starting a new request from the
+/// file.  Built by [`AudioTranscriber::session`].
+///
+/// [`AudioTranscriber::session`]: crate::audio_transcription::AudioTranscriber::session
+pub struct TranscriptionSession {
+    model: Arc<Model>,
+    system_prompt: String,
+    /// The decoded audio, kept for [`retranscribe`](Self::retranscribe).
+    audio: Vec<AudioInput>,
+    /// System prompt, audio message, transcript and every exchange so far.
+    request: RequestBuilder,
+    transcript: String,
+    exchanges: Vec<Exchange>,
+    sampler: SamplerConfig,
+    /// Limit per request; see [`with_timeout`](Self::with_timeout).
+    timeout: Option<Duration>,
+}
+
+impl TranscriptionSession {
+    /// Start a session from a finished transcription: `instruction` is the
+    /// user text that went with `audio`, and `transcript` the reply.
+    ///
+    /// `system_prompt` is the transcription system prompt;
+    /// [`FOLLOW_UP_SYSTEM_NOTE`] is added to it.
+    pub fn new(
+        model: Arc<Model>,
+        system_prompt: &str,
+        audio: Vec<AudioInput>,
+        instruction: &str,
+        transcript: &str,
+    ) -> Result<Self> {
+        let system_prompt = format!("{system_prompt}\n{FOLLOW_UP_SYSTEM_NOTE}");
+        let request = Self::audio_request(&model, &system_prompt, &audio, instruction)?
+            .add_message(TextMessageRole::Assistant, transcript);
+        Ok(Self {
+            model,
+            system_prompt,
+            audio,
+            request,
+            transcript: transcript.to_string(),
+            exchanges: Vec::new(),
+            sampler: FOLLOW_UP_SAMPLER,
+            timeout: timeout::default_limit(),
+        })
+    }
+
+    /// Abandon a request that runs longer than `limit` with a
+    /// [`TimedOut`](timeout::TimedOut) error (default: `--timeout`, if given).
+    pub fn with_timeout(mut self, limit: Duration) -> Self {
+        self.timeout = Some(limit);
+        self
+    }
+
+    /// The current transcript: the first one, or the latest
+    /// [`retranscribe`](Self::retranscribe).
+    pub fn transcript(&self) -> &str {
+        &self.transcript
+    }
+
+    /// Questions asked since the last transcript, oldest first.
+    pub fn exchanges(&self) -> &[Exchange] {
+        &self.exchanges
+    }
+
+    /// Ask `question` about the audio and return the answer.
+    ///
+    /// The exchange is added to the conversation only once the answer
+    /// arrives, so a failed or timed-out question leaves it unchanged.
+    pub async fn ask(&mut self, question: &str) -> Result<(String, Usage)> {
+        let request = self
+            .sampler
+            .apply(self.request.clone())
+            .add_message(TextMessageRole::User, question);
+        let (answer, usage) = self.send(request, "Follow-up answer").await?;
+        let answer = if answer.is_empty() {
+            "(empty response)".to_string()
+        } else {
+            answer
+        };
+        self.request = self
+            .request
+            .clone()
+            .add_message(TextMessageRole::User, question)
+            .add_message(TextMessageRole::Assistant, &answer);
+        self.exchanges.push(Exchange {
+            question: question.to_string(),
+            answer: answer.clone(),
+        });
+        Ok((answer, usage))
+    }
+
+    /// Transcribe the audio again with a new `instruction` and start the
+    /// conversation over from that transcript.
+    ///
+    /// An empty reply is an error and leaves the session unchanged.
+    pub async fn retranscribe(&mut self, instruction: &str) -> Result<(&str, Usage)> {
+        let request =
+            Self::audio_request(&self.model, &self.system_prompt, &self.audio, instruction)?;
+        let (transcript, usage) = self
+            .send(
+                request.clone().set_sampler_temperature(0.0),
+                "Transcription",
+            )
+            .await?;
+        if transcript.is_empty() {
+            anyhow::bail!("The model returned an empty transcript");
+        }
+        self.request = request.add_message(TextMessageRole::Assistant, &transcript);
+        self.transcript = transcript;
+        self.exchanges.clear();
+        Ok((&self.transcript, usage))
+    }
+
+    /// The opening of the conversation: system prompt and the audio with
+    /// `instruction`.
+    fn audio_request(
+        model: &Model,
+        system_prompt: &str,
+        audio: &[AudioInput],
+        instruction: &str,
+    ) -> Result<RequestBuilder> {
+        RequestBuilder::new()
+            .add_message(TextMessageRole::System, system_prompt)
+            .add_audio_message(TextMessageRole::User, instruction, audio.to_vec(), model)
+    }
+
+    /// Send `request` and return the trimmed reply with its usage.
+    async fn send(&self, request: RequestBuilder, what: &str) -> Result<(String, Usage)> {
+        let start = Instant::now();
+        let response =
+            timeout::within(self.timeout, what, self.model.send_chat_request(request)).await?;
+        let usage = Usage::from_response(&response.usage, start.elapsed());
+        let text = response.choices[0]
+            .message
+            .content
+            .as_ref()
+            .map(|c| self.sampler.trim_stop(c).trim().to_string())
+            .unwrap_or_default();
+        Ok((text, usage))
+    }
+}
+
+// ── Interactive loop ─────────────────────────────────────────────────────────
+
+/// Printed by `/help`.
+const HELP: &str = "Commands:
+  /retranscribe <instruction>  Transcribe again with a new instruction
+  /transcript   Show the current transcript
+  /history      List the questions asked since the last transcript
+  /help         Show this help
+  /done         End the session";
+
+/// Ask questions about the transcribed audio, for `transcribe
+/// --interactive`.
+///
+/// Anything typed that isn't a command is a question.  EOF and `/done` end
+/// the session; so does `cancel` (Ctrl-C), at the prompt or while an answer
+/// is pending.
+pub async fn run_interactive(
+    mut session: TranscriptionSession,
+    cancel: CancellationToken,
+) -> Result<()> {
+    println!("\nAsk about the audio (e.g. \"what language is the second verse in?\").");
+    println!("Commands: /retranscribe <instruction>, /transcript, /history, /help, /done");
+
+    let mut lines = spawn_stdin_reader();
+    loop {
+        print!("\nask> ");
+        io::stdout().flush()?;
+        let line = tokio::select! {
+            line = lines.recv() => line,
+            _ = cancel.cancelled() => None,
+        };
+        let Some(line) = line else {
+            println!();
+            break;
+        };
+        let line = line?;
+        let input = line.trim();
+
+        match input {
+            "" => continue,
+            "/done" => break,
+            "/help" => {
+                println!("{HELP}");
+                continue;
+            }
+            "/transcript" => {
+                println!("{}", session.transcript());
+                continue;
+            }
+            "/history" => {
+                if session.exchanges().is_empty() {
+                    println!("No questions yet.");
+                }
+                for (i, exchange) in session.exchanges().iter().enumerate() {
+                    println!("[{}] {}", i + 1, exchange.question);
+                    println!("    {}", exchange.answer);
+                }
+                continue;
+            }
+            _ => {}
+        }
+
+        let reply = if let Some(instruction) = command_arg(input, "/retranscribe") {
+            if instruction.is_empty() {
+                println!("Usage: /retranscribe <instruction>");
+                continue;
+            }
+            tokio::select! {
+                result = session.retranscribe(instruction) => Some(result.map(|(transcript, usage)| {
+                    format!("Transcript:\n{transcript}\n({usage})")
+                })),
+                _ = cancel.cancelled() => None,
+            }
+        } else if input.starts_with('/') && !input.contains(char::is_whitespace) {
+            println!("Unknown command {input}; type /help for the list.");
+            continue;
+        } else {
+            tokio::select! {
+                result = session.ask(input) => Some(result.map(|(answer, usage)| {
+                    format!("{answer}\n({usage})")
+                })),
+                _ = cancel.cancelled() => None,
+            }
+        };
+
+        match reply {
+            Some(Ok(reply)) => println!("{reply}"),
+            Some(Err(err)) if timeout::is_timed_out(&err) => println!("({err})"),
+            Some(Err(err)) => println!("error: {err:#}"),
+            None => {
+                println!("\n(interrupted)");
+                break;
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,101 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use serde::Serialize;
+
+// ── Output config ────────────────────────────────────────────────────────────
+
+/// How much a command prints besides its result.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Verbosity {
+    /// `--quiet`: only the result (prompt text, transcript, image path),
+    /// warnings and errors.
+    Quiet,
+    /// Loading banners, memory estimates and timings.
+    #[default]
+    Normal,
+    /// Everything in `Normal`, plus mistral.rs logging and per-stage timings.
+    Verbose,
+}
+
+/// The global `--json` / `--quiet` / `--verbose` flags, passed to every
+/// command's `run`.
+///
+/// `--quiet --json` is the scripting mode: a single JSON object on stdout
+/// and nothing on stderr but warnings and errors.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OutputConfig {
+    pub json: bool,
+    pub verbosity: Verbosity,
+}
+
+impl OutputConfig {
+    pub fn new(json: bool, quiet: bool, verbose: bool) -> Self {
+        let verbosity = match (quiet, verbose) {
+            (true, _) => Verbosity::Quiet,
+            (false, true) => Verbosity::Verbose,
+            (false, false) => Verbosity::Normal,
+        };
+        Self { json, verbosity }
+    }
+
+    pub fn quiet(self) -> bool {
+        self.verbosity == Verbosity::Quiet
+    }
+
+    pub fn verbose(self) -> bool {
+        self.verbosity == Verbosity::Verbose
+    }
+}
+
+// ── Printing ─────────────────────────────────────────────────────────────────
+
+/// Log a human-oriented progress line as a `tracing` event at `info`.
+///
+/// The subscriber [`logging::init`](crate::logging::init) installs writes
+/// it to stderr, so that stdout carries nothing but the result.  Nothing is
+/// logged with `--quiet`.
+macro_rules! status {
+    ($out:expr) => {
+        $crate::ui::status!($out, "")
+    };
+    ($out:expr, $($arg:tt)*) => {{
+        let out: $crate::ui::OutputConfig = $out;
+        if !out.quiet() {
+            ::tracing::info!("{}", format_args!($($arg)*))
+        }
+    }};
+}
+
+/// Like [`status!`], but at `debug` and only with `--verbose`.
+macro_rules! detail {
+    ($out:expr, $($arg:tt)*) => {{
+        let out: $crate::ui::OutputConfig = $out;
+        if out.verbose() {
+            ::tracing::debug!("{}", format_args!($($arg)*))
+        }
+    }};
+}
+
+pub(crate) use {detail, status};
+
+/// Write `value` to stdout as a single pretty-printed JSON object.
+pub fn print_json<T: Serialize>(value: &T) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+// ── Formatting ───────────────────────────────────────────────────────────────
+
+/// Format a `Duration` as `Xm Ys` (e.g. "2m 30.5s") or just `Ys` when under
+/// a minute.
+pub fn fmt_duration(d: Duration) -> String {
+    let total_secs = d.as_secs_f64();
+    let mins = (total_secs / 60.0).floor() as u64;
+    let secs = total_secs - (mins as f64 * 60.0);
+    if mins > 0 {
+        format!("{mins}m {secs:.1}s")
+    } else {
+        format!("{secs:.1}s")
+    }
+}
@@ -0,0 +1,218 @@
+use anyhow::{Context, Result};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::load_options::LoadSettings;
+use crate::loader;
+
+// ── CLI flags ────────────────────────────────────────────────────────────────
+
+/// `--save-uqff` / `--from-uqff` / `--cache-quantized`: reuse ISQ output
+/// across runs instead of quantizing on every launch.
+///
+/// UQFF is mistral.rs's format for already-quantized weights.  Loading one
+/// skips ISQ entirely, which takes minutes for the Q4K presets.
+#[derive(Clone, Debug, Default, PartialEq, Eq, clap::Args)]
+pub struct UqffArgs {
+    /// Quantize as usual and write the quantized weights to this UQFF file.
+    #[arg(long, value_name = "PATH", conflicts_with = "from_uqff")]
+    pub save_uqff: Option<PathBuf>,
+
+    /// Load quantized weights from this UQFF file instead of running ISQ.
+    #[arg(long, value_name = "PATH")]
+    pub from_uqff: Option<PathBuf>,
+
+    /// Cache quantized weights under ~/.cache/mistralrs-example/uqff and
+    /// reuse them on later runs.  The cache is rebuilt when the model's
+    /// HuggingFace revision changes.
+    #[arg(long, conflicts_with_all = ["save_uqff", "from_uqff"])]
+    pub cache_quantized: bool,
+}
+
+impl UqffArgs {
+    /// Whether `--save-uqff` or `--from-uqff` was given.  Those name one
+    /// model's weights, so commands that load several models reject them.
+    pub fn has_explicit_path(&self) -> bool {
+        self.save_uqff.is_some() || self.from_uqff.is_some()
+    }
+
+    /// What to do for `model_id` loaded with `settings`.  `None` means a
+    /// plain load: no flag was given, or `--cache-quantized` has nothing to
+    /// cache (no ISQ, or a local model without a hub revision).
+    pub fn resolve(&self, model_id: &str, settings: LoadSettings) -> Option<Uqff> {
+        if let Some(path) = &self.from_uqff {
+            return Some(Uqff::Load {
+                path: path.clone(),
+                cached: false,
+            });
+        }
+        if let Some(path) = &self.save_uqff {
+            return Some(Uqff::Save {
+                path: path.clone(),
+                cache: None,
+            });
+        }
+        if !self.cache_quantized {
+            return None;
+        }
+        let entry = CacheEntry::new(model_id, settings)?;
+        if entry.is_fresh() {
+            Some(Uqff::Load {
+                path: entry.path,
+                cached: true,
+            })
+        } else {
+            Some(Uqff::Save {
+                path: entry.path.clone(),
+                cache: Some(entry),
+            })
+        }
+    }
+
+    /// One line for the load banner saying where the weights come from,
+    /// e.g. "Weights: UQFF cache (…/q4k.uqff)".
+    pub fn describe(&self, model_id: &str, settings: LoadSettings) -> Option<String> {
+        self.resolve(model_id, settings)
+            .map(|uqff| format!("Weights: {uqff}"))
+    }
+}
+
+// ── Resolved plan ────────────────────────────────────────────────────────────
+
+/// How a build uses UQFF.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Uqff {
+    /// Load quantized weights from `path`, skipping ISQ.
+    Load { path: PathBuf, cached: bool },
+    /// Quantize with ISQ and write the result to `path`.  For the implicit
+    /// cache, `cache` records the revision once the write succeeds.
+    Save {
+        path: PathBuf,
+        cache: Option<CacheEntry>,
+    },
+}
+
+impl Uqff {
+    /// Called before the build.
+    pub fn prepare(&self) -> Result<()> {
+        match self {
+            Self::Load { path, .. } if !path.exists() => {
+                anyhow::bail!("UQFF file not found: {}", path.display())
+            }
+            Self::Save {
+                cache: Some(entry), ..
+            } => entry.prepare(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Called after a successful build.
+    pub fn finish(&self) -> Result<()> {
+        match self {
+            Self::Save {
+                cache: Some(entry), ..
+            } => entry.mark_fresh(),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for Uqff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Load { path, cached: true } => {
+                write!(f, "UQFF cache ({}), skipping ISQ", path.display())
+            }
+            Self::Load {
+                path,
+                cached: false,
+            } => write!(f, "UQFF file ({}), skipping ISQ", path.display()),
+            Self::Save {
+                path,
+                cache: Some(_),
+            } => write!(
+                f,
+                "quantizing and saving to UQFF cache ({})",
+                path.display()
+            ),
+            Self::Save { path, cache: None } => {
+                write!(f, "quantizing and saving to {}", path.display())
+            }
+        }
+    }
+}
+
+// ── Implicit cache ───────────────────────────────────────────────────────────
+
+/// `~/.cache/mistralrs-example/uqff`, or under `XDG_CACHE_HOME` when set.
+pub fn cache_dir() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CACHE_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => {
+            let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+            PathBuf::from(home).join(".cache")
+        }
+    };
+    Some(base.join("mistralrs-example/uqff"))
+}
+
+/// One cached artifact: `<cache>/<model_id>/<isq>.uqff` (`<isq>-<dtype>`
+/// when `--dtype` is set), with the hub revision it was made from in a
+/// `.revision` file next to it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CacheEntry {
+    pub path: PathBuf,
+    model_id: String,
+}
+
+impl CacheEntry {
+    /// `None` without ISQ (nothing to cache) or for local models, which
+    /// have no hub revision to key on.
+    fn new(model_id: &str, settings: LoadSettings) -> Option<Self> {
+        let isq = settings.isq?;
+        loader::repo_cache_dir(model_id)?;
+        let name = match settings.dtype {
+            Some(dtype) => format!("{isq:?}-{dtype:?}"),
+            None => format!("{isq:?}"),
+        }
+        .to_lowercase();
+        Some(Self {
+            path: cache_dir()?.join(model_id).join(format!("{name}.uqff")),
+            model_id: model_id.to_string(),
+        })
+    }
+
+    fn revision_path(&self) -> PathBuf {
+        self.path.with_extension("revision")
+    }
+
+    /// The artifact exists and was made from the revision the hub cache
+    /// currently points at.
+    fn is_fresh(&self) -> bool {
+        let Some(current) = loader::repo_revision(&self.model_id) else {
+            return false;
+        };
+        let recorded = std::fs::read_to_string(self.revision_path()).unwrap_or_default();
+        self.path.exists() && recorded.trim() == current
+    }
+
+    /// Record the revision the artifact was just written from.
+    fn mark_fresh(&self) -> Result<()> {
+        let Some(revision) = loader::repo_revision(&self.model_id) else {
+            return Ok(());
+        };
+        let path = self.revision_path();
+        std::fs::write(&path, revision)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Create the directory the artifact goes in and forget the recorded
+    /// revision, so an interrupted write is never mistaken for a fresh one.
+    fn prepare(&self) -> Result<()> {
+        let dir = self.path.parent().unwrap_or(Path::new("."));
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create UQFF cache directory: {}", dir.display()))?;
+        let _ = std::fs::remove_file(self.revision_path());
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+use serde::Serialize;
+use std::fmt;
+use std::ops::{Add, AddAssign};
+use std::time::Duration;
+
+// ── Usage ────────────────────────────────────────────────────────────────────
+
+/// Token counts reported by the model for one or more requests, with the
+/// wall-clock time they took.
+///
+/// Summing (`+` / `+=`) adds counts and durations, so the rate of a
+/// multi-request job (VAD segments, several candidates) is its overall rate.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Usage {
+    /// Tokens in the prompt (system prompt, history, input).
+    pub prompt_tokens: usize,
+    /// Tokens the model generated.
+    pub completion_tokens: usize,
+    /// Time from sending the request(s) until the reply was complete.
+    pub duration: Duration,
+}
+
+impl Usage {
+    /// Counts from a mistral.rs response, timed by the caller.
+    pub fn from_response(usage: &mistralrs::Usage, duration: Duration) -> Self {
+        Self {
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+            duration,
+        }
+    }
+
+    /// Generated tokens per second of `duration`.
+    ///
+    /// `None` when no time was measured; zero completion tokens is a rate
+    /// of 0.0, not `None`.
+    pub fn tokens_per_sec(&self) -> Option<f64> {
+        let secs = self.duration.as_secs_f64();
+        (secs > 0.0).then(|| self.completion_tokens as f64 / secs)
+    }
+}
+
+impl Add for Usage {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            prompt_tokens: self.prompt_tokens + other.prompt_tokens,
+            completion_tokens: self.completion_tokens + other.completion_tokens,
+            duration: self.duration + other.duration,
+        }
+    }
+}
+
+impl AddAssign for Usage {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl std::iter::Sum for Usage {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Add::add)
+    }
+}
+
+impl Serialize for Usage {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct JsonUsage {
+            prompt_tokens: usize,
+            completion_tokens: usize,
+            duration_secs: f64,
+            tokens_per_sec: Option<f64>,
+        }
+
+        JsonUsage {
+            prompt_tokens: self.prompt_tokens,
+            completion_tokens: self.completion_tokens,
+            duration_secs: self.duration.as_secs_f64(),
+            tokens_per_sec: self.tokens_per_sec(),
+        }
+        .serialize(s)
+    }
+}
+
+/// `12 prompt + 48 completion tokens, 21.3 tok/s`.
+impl fmt::Display for Usage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} prompt + {} completion tokens",
+            self.prompt_tokens, self.completion_tokens
+        )?;
+        if let Some(rate) = self.tokens_per_sec() {
+            write!(f, ", {rate:.1} tok/s")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,447 @@
+use anyhow::{Context, Result};
+use mistralrs::AudioInput;
+use notify::{EventKind, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use crate::audio_transcription::{self, AudioTranscriber, TranscribeOptions, TranscriptionModel};
+use crate::interrupt;
+use crate::load_options::LoadOptions;
+use crate::memory;
+use crate::memstats::MemorySnapshot;
+use crate::transcript_output::OutputFormat;
+use crate::ui::{OutputConfig, detail, fmt_duration, status};
+
+// ── Constants ────────────────────────────────────────────────────────────────
+
+/// Extensions picked up from the watched directory.
+pub const AUDIO_EXTENSIONS: &[&str] = &["wav", "mp3", "ogg", "flac"];
+
+/// How long a file's size and modification time must stay the same before
+/// it counts as fully written.
+pub const SETTLE_TIME: Duration = Duration::from_secs(2);
+
+/// How often pending files are checked.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Name of the state file kept in the watched directory.
+pub const STATE_FILE: &str = ".transcribed.json";
+
+// ── FileSnapshot ─────────────────────────────────────────────────────────────
+
+/// Size and modification time of a file at one moment.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileSnapshot {
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+}
+
+impl FileSnapshot {
+    /// Stat `path`; `None` if it is gone or not a regular file.
+    pub fn of(path: &Path) -> Option<Self> {
+        let meta = std::fs::metadata(path).ok().filter(|meta| meta.is_file())?;
+        Some(Self {
+            size: meta.len(),
+            modified: meta.modified().ok(),
+        })
+    }
+}
+
+/// Whether `path` looks like audio to transcribe: a supported extension,
+/// and not a hidden or temporary file (`.name.wav`).
+pub fn is_audio_file(path: &Path) -> bool {
+    let hidden = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .is_none_or(|name| name.starts_with('.'));
+    let ext = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase());
+    !hidden && ext.is_some_and(|ext| AUDIO_EXTENSIONS.contains(&ext.as_str()))
+}
+
+// ── SettleTracker ────────────────────────────────────────────────────────────
+
+/// Holds back files that are still being written.
+///
+/// A file is ready once its [`FileSnapshot`] has stayed the same for the
+/// settle time and it isn't empty.  The clock and the filesystem are passed
+/// in, so the timing can be driven without a real directory.
+pub struct SettleTracker {
+    settle: Duration,
+    pending: HashMap<PathBuf, Pending>,
+}
+
+struct Pending {
+    last: Option<FileSnapshot>,
+    since: Instant,
+}
+
+impl SettleTracker {
+    pub fn new(settle: Duration) -> Self {
+        Self {
+            settle,
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Something happened to `path` at `now`: (re)start its settle timer.
+    pub fn touch(&mut self, path: PathBuf, now: Instant) {
+        self.pending.insert(
+            path,
+            Pending {
+                last: None,
+                since: now,
+            },
+        );
+    }
+
+    /// Check every pending file with `stat` and return (and forget) the
+    /// ones that have settled, sorted by path.
+    ///
+    /// A file whose snapshot changed restarts its timer; one that
+    /// disappeared is dropped.
+    pub fn take_ready(
+        &mut self,
+        now: Instant,
+        stat: impl Fn(&Path) -> Option<FileSnapshot>,
+    ) -> Vec<PathBuf> {
+        let mut ready = Vec::new();
+        self.pending.retain(|path, pending| {
+            let Some(current) = stat(path) else {
+                return false;
+            };
+            if pending.last != Some(current) {
+                pending.last = Some(current);
+                pending.since = now;
+                return true;
+            }
+            if current.size > 0 && now.duration_since(pending.since) >= self.settle {
+                ready.push(path.clone());
+                return false;
+            }
+            true
+        });
+        ready.sort();
+        ready
+    }
+}
+
+// ── WatchState ───────────────────────────────────────────────────────────────
+
+/// A transcribed file as recorded in the state file.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Processed {
+    #[serde(flatten)]
+    pub snapshot: FileSnapshot,
+    /// Name of the transcription written next to it.
+    pub output: String,
+}
+
+/// Files already transcribed, so a restart skips them.
+///
+/// Keyed by file name.  A file counts as done only while it is unchanged
+/// and was written in the same output format, so editing a stem or
+/// switching `--output-format` transcribes it again.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct WatchState {
+    version: u32,
+    files: BTreeMap<String, Processed>,
+}
+
+impl WatchState {
+    const VERSION: u32 = 1;
+
+    /// Read the state file at `path`; a missing file is an empty state.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self {
+                version: Self::VERSION,
+                files: BTreeMap::new(),
+            });
+        }
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let state: Self = serde_json::from_str(&json).with_context(|| {
+            format!(
+                "{} is not a watch state file (delete it to start over)",
+                path.display()
+            )
+        })?;
+        if state.version != Self::VERSION {
+            anyhow::bail!(
+                "{} has unsupported version {} (expected {})",
+                path.display(),
+                state.version,
+                Self::VERSION
+            );
+        }
+        Ok(state)
+    }
+
+    /// Write the state to `path`, replacing it in one step so a crash
+    /// can't leave it half-written.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", tmp.display()))?;
+        std::fs::rename(&tmp, path).with_context(|| format!("Failed to replace {}", path.display()))
+    }
+
+    /// Whether `name` was already transcribed exactly as `entry` describes.
+    pub fn is_done(&self, name: &str, entry: &Processed) -> bool {
+        self.files.get(name) == Some(entry)
+    }
+
+    pub fn record(&mut self, name: String, entry: Processed) {
+        self.files.insert(name, entry);
+    }
+
+    /// Number of files recorded.
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+}
+
+// ── Helpers ──────────────────────────────────────────────────────────────────
+
+/// The state entry `path` would get if transcribed now, or `None` if it
+/// has vanished.
+fn entry_for(path: &Path, extension: &str) -> Option<(String, Processed)> {
+    let name = path.file_name()?.to_str()?.to_string();
+    let output = Path::new(&name)
+        .with_extension(extension)
+        .to_string_lossy()
+        .into_owned();
+    let snapshot = FileSnapshot::of(path)?;
+    Some((name, Processed { snapshot, output }))
+}
+
+// ── CLI entry-point ──────────────────────────────────────────────────────────
+
+/// Watch `dir` and transcribe each audio file that lands in it.
+///
+/// The model loads once.  Files already in the directory are picked up
+/// first, then new ones as they finish being written (see
+/// [`SettleTracker`]).  Each transcription is written next to its audio
+/// as `<stem>.<txt|srt|vtt|json>` and recorded in [`STATE_FILE`].  A file
+/// that fails is reported and left out of the state, so it is retried
+/// when it changes or on the next run.
+///
+/// Runs until `cancel` fires; a transcription cut short then is discarded.
+pub async fn run(
+    dir: PathBuf,
+    model: Option<TranscriptionModel>,
+    model_path: Option<PathBuf>,
+    load: LoadOptions,
+    options: TranscribeOptions,
+    cancel: CancellationToken,
+    out: OutputConfig,
+) -> Result<()> {
+    let TranscribeOptions {
+        user_prompt,
+        system_prompt,
+        language,
+        translate,
+        preprocess,
+        vad_threshold,
+        detect_language,
+        vocal_check,
+        timestamps,
+        lyrics,
+        censor,
+        output_format,
+        decoding,
+        ..
+    } = options;
+
+    if !dir.is_dir() {
+        anyhow::bail!("Not a directory: {}", dir.display());
+    }
+    let dir = dir
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve {}", dir.display()))?;
+    let state_path = dir.join(STATE_FILE);
+    let mut state = WatchState::load(&state_path)?;
+    if translate && user_prompt.is_some() {
+        tracing::warn!("--translate is ignored because --user-prompt replaces its instruction");
+    }
+
+    // Subscribe before loading the model, so files that land meanwhile
+    // are not missed.
+    let (tx, mut events) = mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event| {
+        let _ = tx.send(event);
+    })
+    .context("Failed to start the directory watcher")?;
+    watcher
+        .watch(&dir, RecursiveMode::NonRecursive)
+        .with_context(|| format!("Failed to watch {}", dir.display()))?;
+
+    let before = MemorySnapshot::capture();
+    let load_start = Instant::now();
+    let loading = async {
+        match &model_path {
+            Some(path) => {
+                status!(out, "Loading transcription model: {}", path.display());
+                Ok::<_, anyhow::Error>(AudioTranscriber::from_local_path(path, &load).await?)
+            }
+            None => {
+                let (preset, load) = memory::choose(model.unwrap_or_default(), &load)?;
+                status!(out, "Loading transcription model: {preset}");
+                status!(out, "  Memory estimate: {}", preset.memory_estimate(&load));
+                let settings = load.apply(preset.default_settings());
+                if let Some(weights) = load.uqff.describe(preset.model_id(), settings) {
+                    status!(out, "  {weights}");
+                }
+                Ok(AudioTranscriber::from_preset_with_options(preset, &load).await?)
+            }
+        }
+    };
+    let mut transcriber = interrupt::unless_cancelled(Some(&cancel), loading).await?;
+    status!(
+        out,
+        "Model loaded in {}",
+        fmt_duration(load_start.elapsed())
+    );
+    status!(
+        out,
+        "{}\n",
+        MemorySnapshot::capture().since(before).banner()
+    );
+    if let Some(system_prompt) = system_prompt {
+        transcriber = transcriber.with_system_prompt(system_prompt);
+    }
+    if let Some(language) = &language {
+        transcriber = transcriber.with_language(language);
+    }
+    let transcriber = transcriber
+        .with_translation(translate)
+        .with_preprocessing(preprocess)
+        .with_vad(vad_threshold)
+        .with_language_detection(detect_language)
+        .with_vocal_check(vocal_check)
+        .with_timestamps(timestamps)
+        .with_lyrics_format(lyrics)
+        .with_censor(censor)
+        .with_sampler(decoding.apply(audio_transcription::DEFAULT_SAMPLER))
+        .with_cancellation(cancel.clone());
+    detail!(
+        out,
+        "System prompt:\n{}",
+        transcriber.effective_system_prompt(user_prompt.as_deref())
+    );
+
+    let extension = output_format.extension();
+    let mut tracker = SettleTracker::new(SETTLE_TIME);
+    let existing =
+        std::fs::read_dir(&dir).with_context(|| format!("Failed to list {}", dir.display()))?;
+    for entry in existing.flatten() {
+        let path = entry.path();
+        if is_audio_file(&path) {
+            tracker.touch(path, Instant::now());
+        }
+    }
+    status!(
+        out,
+        "Watching {} ({} already transcribed; Ctrl-C to stop)",
+        dir.display(),
+        state.len()
+    );
+
+    let mut transcribed = 0;
+    let mut failed = 0;
+    let mut poll = tokio::time::interval(POLL_INTERVAL);
+    'watch: loop {
+        tokio::select! {
+            _ = cancel.cancelled() => break,
+            event = events.recv() => match event {
+                Some(Ok(event)) => {
+                    if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                        for path in event.paths.into_iter().filter(|path| is_audio_file(path)) {
+                            tracker.touch(path, Instant::now());
+                        }
+                    }
+                }
+                Some(Err(err)) => tracing::warn!("watching {}: {err}", dir.display()),
+                None => anyhow::bail!("The directory watcher stopped unexpectedly"),
+            },
+            _ = poll.tick() => {
+                for path in tracker.take_ready(Instant::now(), FileSnapshot::of) {
+                    let Some((name, entry)) = entry_for(&path, extension) else {
+                        continue;
+                    };
+                    if state.is_done(&name, &entry) {
+                        continue;
+                    }
+                    status!(out, "Transcribing: {name}");
+                    let started = Instant::now();
+                    let decoding = {
+                        let path = path.clone();
+                        tokio::task::spawn_blocking(move || {
+                            let bytes = std::fs::read(&path).with_context(|| {
+                                format!("Failed to read audio file: {}", path.display())
+                            })?;
+                            AudioInput::from_bytes(&bytes).context("Failed to decode audio bytes")
+                        })
+                    };
+                    let result = match decoding.await.context("Decoding panicked")? {
+                        Ok(audio) => transcriber
+                            .transcribe_audio(audio, user_prompt.as_deref())
+                            .await
+                            .map_err(anyhow::Error::from)
+                            .and_then(|mut result| {
+                                if result.interrupted {
+                                    return Err(interrupt::Interrupted.into());
+                                }
+                                if output_format == OutputFormat::Lrc {
+                                    result.read_tags(&path);
+                                }
+                                let output = dir.join(&entry.output);
+                                std::fs::write(&output, result.render(output_format)?)
+                                    .with_context(|| {
+                                        format!("Failed to write output file: {}", output.display())
+                                    })?;
+                                Ok(output)
+                            }),
+                        Err(err) => Err(err),
+                    };
+                    match result {
+                        Ok(output) => {
+                            transcribed += 1;
+                            state.record(name, entry);
+                            state.save(&state_path)?;
+                            status!(
+                                out,
+                                "  wrote {} ({})",
+                                output.display(),
+                                fmt_duration(started.elapsed())
+                            );
+                            if out.quiet() {
+                                println!("{}", output.display());
+                            }
+                        }
+                        Err(err) if interrupt::is_interrupted(&err) => break 'watch,
+                        Err(err) => {
+                            failed += 1;
+                            tracing::error!("{name}: {err:#}");
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    drop(watcher);
+    status!(
+        out,
+        "\nStopped watching {}: {transcribed} transcribed, {failed} failed",
+        dir.display()
+    );
+    Ok(())
+}